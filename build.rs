@@ -0,0 +1,27 @@
+use std::{env, process::Command};
+
+/// Embeds the commit and compiler the crate was built from, recorded in run manifests.
+fn main() {
+    let output = |program: &str, args: &[&str]| {
+        Command::new(program)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    };
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+
+    println!(
+        "cargo:rustc-env=LGP_GIT_COMMIT={}",
+        output("git", &["rev-parse", "HEAD"])
+    );
+    println!(
+        "cargo:rustc-env=LGP_RUSTC_VERSION={}",
+        output(&rustc, &["--version"])
+    );
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
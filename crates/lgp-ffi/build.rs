@@ -0,0 +1,13 @@
+use std::{env, path::PathBuf};
+
+/// Regenerates `include/lgp.h` from the exported functions.
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    cbindgen::generate(&crate_dir)
+        .expect("Failed to generate the C header.")
+        .write_to_file(crate_dir.join("include").join("lgp.h"));
+}
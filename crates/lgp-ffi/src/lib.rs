@@ -0,0 +1,288 @@
+//! C-compatible bindings for loading evolved controllers and predicting their actions.
+//!
+//! No panic crosses the boundary: failures surface as a null model or a negative error code.
+
+use std::{
+    cell::Cell,
+    ffi::CStr,
+    fs::File,
+    os::raw::c_char,
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use lgp::{
+    core::{
+        characteristics::Load,
+        engines::freeze_engine::{Freeze, FreezeEngine},
+        environment::State,
+        program::Program,
+    },
+    extensions::q_learning::QProgram,
+    utils::predict::Predictor,
+};
+use serde::Deserialize;
+
+/// The model or observation pointer was null.
+pub const LGP_ERROR_NULL: i32 = -1;
+/// The observation holds fewer values than the model reads.
+pub const LGP_ERROR_INVALID_INPUT: i32 = -2;
+/// Prediction panicked; the model may be left in an unspecified state.
+pub const LGP_ERROR_PANIC: i32 = -3;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Model {
+    // Tried first, since a `QProgram` wraps a `Program`.
+    QProgram(QProgram),
+    Program(Program),
+}
+
+/// A loaded controller, owned by the caller until passed to `lgp_model_free`.
+pub struct LgpModel {
+    model: Model,
+}
+
+impl Load for LgpModel {}
+
+impl<'de> Deserialize<'de> for LgpModel {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut model = Model::deserialize(deserializer)?;
+
+        if let Model::QProgram(q_program) = &mut model {
+            FreezeEngine::freeze(q_program);
+            q_program.q_table.set_greedy(true);
+        }
+
+        Ok(LgpModel { model })
+    }
+}
+
+impl LgpModel {
+    fn predict(&mut self, observation: &mut Observation) -> usize {
+        match &mut self.model {
+            Model::Program(program) => program.predict(observation),
+            Model::QProgram(q_program) => q_program.predict(observation),
+        }
+    }
+}
+
+/// Observation passed across the boundary; reads past its end yield `0` and are recorded
+/// instead of panicking.
+struct Observation<'a> {
+    values: &'a [f64],
+    out_of_range: Cell<bool>,
+}
+
+impl State for Observation<'_> {
+    fn get_value(&self, at_idx: usize) -> f64 {
+        self.values.get(at_idx).copied().unwrap_or_else(|| {
+            self.out_of_range.set(true);
+            0.
+        })
+    }
+
+    fn execute_action(&mut self, _action: usize) -> f64 {
+        0.
+    }
+
+    fn get(&mut self) -> Option<&mut Self> {
+        Some(self)
+    }
+}
+
+/// Loads a saved `Program` or `QProgram` (e.g. `best.json`). Q-tables are frozen and act
+/// greedily.
+///
+/// Returns null if `path` is null, unreadable or not a saved model.
+///
+/// # Safety
+///
+/// `path` must be null or a valid, NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lgp_model_load(path: *const c_char) -> *mut LgpModel {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+
+    let load = || {
+        let path = CStr::from_ptr(path).to_str().ok()?;
+        let file = File::open(path).ok()?;
+
+        LgpModel::read_from(file).ok()
+    };
+
+    match catch_unwind(AssertUnwindSafe(load)) {
+        Ok(Some(model)) => Box::into_raw(Box::new(model)),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Predicts the action for an observation of `len` values.
+///
+/// Returns the action (zero or greater) or one of the negative `LGP_ERROR_*` codes.
+///
+/// # Safety
+///
+/// `model` must be null or returned by `lgp_model_load` and not yet freed; `obs` must be null or
+/// point to `len` readable values.
+#[no_mangle]
+pub unsafe extern "C" fn lgp_model_predict(
+    model: *mut LgpModel,
+    obs: *const f64,
+    len: usize,
+) -> i32 {
+    if model.is_null() || obs.is_null() {
+        return LGP_ERROR_NULL;
+    }
+
+    let model = &mut *model;
+    let mut observation = Observation {
+        values: slice::from_raw_parts(obs, len),
+        out_of_range: Cell::new(false),
+    };
+
+    match catch_unwind(AssertUnwindSafe(|| model.predict(&mut observation))) {
+        Ok(_) if observation.out_of_range.get() => LGP_ERROR_INVALID_INPUT,
+        Ok(action) => i32::try_from(action).unwrap_or(LGP_ERROR_PANIC),
+        Err(_) => LGP_ERROR_PANIC,
+    }
+}
+
+/// Frees a model returned by `lgp_model_load`. Null is ignored.
+///
+/// # Safety
+///
+/// `model` must be null or returned by `lgp_model_load` and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn lgp_model_free(model: *mut LgpModel) {
+    if !model.is_null() {
+        drop(Box::from_raw(model));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, ffi::CString};
+
+    use lgp::core::{
+        characteristics::Save,
+        engines::generate_engine::{Generate, GenerateEngine},
+        instruction::{Instruction, InstructionGeneratorParametersBuilder, Mode, Op},
+        program::{ProgramGeneratorParameters, ProgramGeneratorParametersBuilder},
+    };
+    use lgp::extensions::q_learning::{QConsts, QProgramGeneratorParameters};
+    use lgp::utils::{predict::Features, random::update_seed};
+
+    use super::*;
+
+    fn program_parameters() -> ProgramGeneratorParameters {
+        ProgramGeneratorParametersBuilder::default()
+            .max_instructions(20)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_actions(2)
+                    .n_inputs(4)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    fn observations() -> Vec<[f64; 4]> {
+        (0..20)
+            .map(|i| {
+                let x = i as f64 / 10. - 1.;
+                [x, -x, x * x, 0.5 - x]
+            })
+            .collect()
+    }
+
+    fn save(model: &impl Save, name: &str) -> CString {
+        let path = env::temp_dir().join("lgp_ffi").join(name);
+        model.save(path.to_str().unwrap()).unwrap();
+
+        CString::new(path.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn given_saved_program_when_loaded_through_ffi_then_predictions_match_rust() {
+        update_seed(Some(5));
+        let mut program: Program = GenerateEngine::generate(program_parameters());
+        let path = save(&program, "program.json");
+
+        unsafe {
+            let model = lgp_model_load(path.as_ptr());
+            assert!(!model.is_null());
+
+            for observation in observations() {
+                let expected = program.predict(&mut Features(&observation));
+                let action = lgp_model_predict(model, observation.as_ptr(), observation.len());
+
+                assert_eq!(action, expected as i32);
+            }
+
+            lgp_model_free(model);
+        }
+    }
+
+    #[test]
+    fn given_saved_q_program_when_loaded_through_ffi_then_predictions_match_rust() {
+        update_seed(Some(5));
+        let mut q_program: QProgram = GenerateEngine::generate(QProgramGeneratorParameters {
+            program_parameters: program_parameters(),
+            consts: QConsts::default(),
+            initial_q_table: None,
+        });
+        let path = save(&q_program, "q_program.json");
+
+        unsafe {
+            let model = lgp_model_load(path.as_ptr());
+            assert!(!model.is_null());
+            assert!(matches!((*model).model, Model::QProgram(_)));
+
+            for observation in observations() {
+                let expected = q_program.predict(&mut Features(&observation));
+                let action = lgp_model_predict(model, observation.as_ptr(), observation.len());
+
+                assert_eq!(action, expected as i32);
+            }
+
+            lgp_model_free(model);
+        }
+    }
+
+    #[test]
+    fn given_invalid_arguments_when_called_then_errors_are_returned_without_panicking() {
+        let missing = CString::new("/nonexistent/best.json").unwrap();
+        let observation = [0.; 4];
+        update_seed(Some(5));
+        let mut program: Program = GenerateEngine::generate(program_parameters());
+        // Always reads the last input.
+        program
+            .instructions
+            .insert(0, Instruction::new(0, 3, Mode::External, Op::Add, 1.));
+        let path = save(&program, "short_observation.json");
+
+        unsafe {
+            assert!(lgp_model_load(ptr::null()).is_null());
+            assert!(lgp_model_load(missing.as_ptr()).is_null());
+            assert_eq!(
+                lgp_model_predict(ptr::null_mut(), observation.as_ptr(), 4),
+                LGP_ERROR_NULL
+            );
+            lgp_model_free(ptr::null_mut());
+
+            let model = lgp_model_load(path.as_ptr());
+            assert_eq!(
+                lgp_model_predict(model, observation.as_ptr(), 3),
+                LGP_ERROR_INVALID_INPUT
+            );
+            lgp_model_free(model);
+        }
+    }
+}
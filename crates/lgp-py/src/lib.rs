@@ -0,0 +1,412 @@
+//! Python bindings for training and running linear genetic programs.
+//!
+//! Exposes the `lgp` module; see the crate README for building it with maturin.
+
+use std::fs::File;
+
+use gym_rs::envs::classical_control::{cartpole::CartPoleEnv, mountain_car::MountainCarEnv};
+use lgp::{
+    core::{
+        characteristics::{Load, Save},
+        config::Problem,
+        config_override::apply_overrides,
+        engines::{
+            core_engine::{Core, HyperParameters, HyperParametersBuilder},
+            freeze_engine::Freeze,
+            status_engine::Status,
+        },
+        instruction::InstructionGeneratorParametersBuilder,
+        program::{Program, ProgramGeneratorParametersBuilder},
+    },
+    extensions::q_learning::QProgram,
+    problems::{
+        gym::{GymRsEngine, GymRsQEngine},
+        iris::IrisEngine,
+    },
+    utils::{
+        benchmark_tools::SummaryStatistics,
+        error::LgpError,
+        predict::{Features, Predictor},
+    },
+};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+    types::{PyBool, PyDict},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+const CART_POLE_LGP: &str = include_str!("../../../assets/parameters/cart-pole-lgp.json");
+const CART_POLE_Q: &str = include_str!("../../../assets/parameters/cart-pole-q.json");
+const MOUNTAIN_CAR_LGP: &str = include_str!("../../../assets/parameters/mountain-car-lgp.json");
+const MOUNTAIN_CAR_Q: &str = include_str!("../../../assets/parameters/mountain-car-q.json");
+
+fn to_py_err(error: LgpError) -> PyErr {
+    match error {
+        LgpError::Config(_) | LgpError::UnknownEnvironment { .. } => {
+            PyValueError::new_err(error.to_string())
+        }
+        _ => PyRuntimeError::new_err(error.to_string()),
+    }
+}
+
+/// Hyperparameters given as keyword arguments mirroring the fields of `HyperParameters`,
+/// e.g. `Hyperparameters(population_size=50, program_parameters={"max_instructions": 10})`.
+///
+/// Arguments are applied as overrides on the defaults of the problem being trained, so unknown
+/// or mistyped fields are only reported when training starts. `None` keeps the default.
+#[pyclass]
+#[derive(Clone, Debug, Default)]
+pub struct Hyperparameters {
+    overrides: Vec<String>,
+}
+
+impl Hyperparameters {
+    fn flatten(&mut self, prefix: &str, kwargs: &PyDict) -> PyResult<()> {
+        for (key, value) in kwargs {
+            let key = format!("{}{}", prefix, key.str()?.to_str()?);
+
+            if value.is_none() {
+                continue;
+            } else if let Ok(table) = value.downcast::<PyDict>() {
+                self.flatten(&format!("{}.", key), table)?;
+            } else if let Ok(flag) = value.downcast::<PyBool>() {
+                self.overrides.push(format!("{}={}", key, flag.is_true()));
+            } else {
+                self.overrides
+                    .push(format!("{}={}", key, value.str()?.to_str()?));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves these overrides against the defaults of a problem.
+    fn resolve<C>(&self, defaults: &HyperParameters<C>) -> PyResult<HyperParameters<C>>
+    where
+        C: Core,
+    {
+        let parameters = apply_overrides(defaults, &self.overrides).map_err(to_py_err)?;
+        parameters
+            .validate()
+            .map_err(|violations| to_py_err(violations.into()))?;
+
+        Ok(parameters)
+    }
+}
+
+#[pymethods]
+impl Hyperparameters {
+    #[new]
+    #[pyo3(signature = (**kwargs))]
+    fn new(kwargs: Option<&PyDict>) -> PyResult<Self> {
+        let mut parameters = Hyperparameters::default();
+
+        if let Some(kwargs) = kwargs {
+            parameters.flatten("", kwargs)?;
+        }
+
+        Ok(parameters)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Hyperparameters({})", self.overrides.join(", "))
+    }
+}
+
+/// Fitness of the population after each generation, best first.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GenerationMetrics {
+    pub generation: usize,
+    pub best: f64,
+    pub median: f64,
+    pub worst: f64,
+    pub mean: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Model {
+    Program(Program),
+    QProgram(QProgram),
+}
+
+/// The frozen champion of a run, with the metrics recorded while evolving it.
+#[pyclass]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrainedModel {
+    environment: String,
+    n_inputs: usize,
+    model: Model,
+    metrics: Vec<GenerationMetrics>,
+}
+
+impl Save for TrainedModel {}
+impl Load for TrainedModel {}
+
+#[pymethods]
+impl TrainedModel {
+    /// Predicts the class or action for one row of `n_inputs` feature values.
+    fn predict(&self, features: Vec<f64>) -> PyResult<usize> {
+        if features.len() != self.n_inputs {
+            return Err(PyValueError::new_err(format!(
+                "Expected {} features, got {}",
+                self.n_inputs,
+                features.len()
+            )));
+        }
+
+        let prediction = match self.model.clone() {
+            Model::Program(mut program) => program.predict(&mut Features(&features)),
+            Model::QProgram(mut program) => program.predict(&mut Features(&features)),
+        };
+
+        Ok(prediction)
+    }
+
+    /// One dict per generation with `generation`, `best`, `median`, `worst` and `mean`.
+    fn metrics<'py>(&self, py: Python<'py>) -> PyResult<Vec<&'py PyDict>> {
+        self.metrics
+            .iter()
+            .map(|metrics| {
+                let row = PyDict::new(py);
+                row.set_item("generation", metrics.generation)?;
+                row.set_item("best", metrics.best)?;
+                row.set_item("median", metrics.median)?;
+                row.set_item("worst", metrics.worst)?;
+                row.set_item("mean", metrics.mean)?;
+
+                Ok(row)
+            })
+            .collect()
+    }
+
+    #[getter]
+    fn environment(&self) -> &str {
+        &self.environment
+    }
+
+    fn save(&self, path: &str) -> PyResult<()> {
+        let file = File::create(path)?;
+        self.write_to(file).map_err(to_py_err)
+    }
+
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        let file = File::open(path)?;
+        Self::read_from(file).map_err(to_py_err)
+    }
+
+    fn __repr__(&self) -> String {
+        let best = self.metrics.last().map_or(f64::NAN, |metrics| metrics.best);
+        format!(
+            "TrainedModel(environment={:?}, generations={}, best={})",
+            self.environment,
+            self.metrics.len(),
+            best
+        )
+    }
+}
+
+/// Evolves for `n_generations`, returning the frozen champion and the metrics of every
+/// generation.
+fn train<C>(parameters: &HyperParameters<C>) -> PyResult<(C::Individual, Vec<GenerationMetrics>)>
+where
+    C: Core,
+{
+    let mut champion = None;
+    let mut metrics = vec![];
+
+    let engine = parameters.try_build_engine().map_err(to_py_err)?;
+
+    for (generation, population) in engine.take(parameters.n_generations).enumerate() {
+        if population.is_empty() {
+            break;
+        }
+
+        let fitnesses: Vec<f64> = population.iter().map(C::Status::get_fitness).collect();
+        let statistics = SummaryStatistics::from_values(&fitnesses);
+
+        metrics.push(GenerationMetrics {
+            generation,
+            best: fitnesses[0],
+            median: fitnesses[fitnesses.len() / 2],
+            worst: fitnesses[fitnesses.len() - 1],
+            mean: statistics.mean,
+        });
+        champion = Some(population[0].clone());
+    }
+
+    let mut champion =
+        champion.ok_or_else(|| PyRuntimeError::new_err("The run produced no generations"))?;
+    C::Freeze::freeze(&mut champion);
+
+    Ok((champion, metrics))
+}
+
+fn defaults<C>(json: &str) -> HyperParameters<C>
+where
+    C: Core,
+    HyperParameters<C>: DeserializeOwned,
+{
+    serde_json::from_str(json).expect("Bundled parameters are valid.")
+}
+
+fn iris_defaults() -> HyperParameters<IrisEngine> {
+    let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+        .n_actions(3)
+        .n_inputs(4)
+        .build()
+        .unwrap();
+    let program_parameters = ProgramGeneratorParametersBuilder::default()
+        .max_instructions(100)
+        .instruction_generator_parameters(instruction_parameters)
+        .build()
+        .unwrap();
+
+    HyperParametersBuilder::<IrisEngine>::default()
+        .program_parameters(program_parameters)
+        .build()
+        .unwrap()
+}
+
+/// Trains a classifier on the Iris dataset, downloaded unless `dataset_path` is given.
+#[pyfunction]
+fn train_iris(hp: &Hyperparameters) -> PyResult<TrainedModel> {
+    let parameters = hp.resolve(&iris_defaults())?;
+    let (champion, metrics) = train(&parameters)?;
+
+    Ok(TrainedModel {
+        environment: "iris-lgp".to_string(),
+        n_inputs: 4,
+        model: Model::Program(champion),
+        metrics,
+    })
+}
+
+/// Trains on a gym problem by its CLI name, e.g. `cart-pole-lgp` or `mountain-car-q`, starting
+/// from the tuned parameters of that problem.
+#[pyfunction]
+fn train_gym(env_name: &str, hp: &Hyperparameters) -> PyResult<TrainedModel> {
+    let (model, n_inputs, metrics) = match Problem::from_name(env_name).map_err(to_py_err)? {
+        Problem::CartPoleLgp => {
+            let parameters = hp.resolve(&defaults::<GymRsEngine<CartPoleEnv>>(CART_POLE_LGP))?;
+            let (champion, metrics) = train(&parameters)?;
+            (Model::Program(champion), 4, metrics)
+        }
+        Problem::CartPoleQ => {
+            let parameters = hp.resolve(&defaults::<GymRsQEngine<CartPoleEnv>>(CART_POLE_Q))?;
+            let (champion, metrics) = train(&parameters)?;
+            (Model::QProgram(champion), 4, metrics)
+        }
+        Problem::MountainCarLgp => {
+            let parameters =
+                hp.resolve(&defaults::<GymRsEngine<MountainCarEnv>>(MOUNTAIN_CAR_LGP))?;
+            let (champion, metrics) = train(&parameters)?;
+            (Model::Program(champion), 2, metrics)
+        }
+        Problem::MountainCarQ => {
+            let parameters =
+                hp.resolve(&defaults::<GymRsQEngine<MountainCarEnv>>(MOUNTAIN_CAR_Q))?;
+            let (champion, metrics) = train(&parameters)?;
+            (Model::QProgram(champion), 2, metrics)
+        }
+        Problem::IrisLgp => {
+            return Err(PyValueError::new_err(
+                "`iris-lgp` is not a gym problem; use `train_iris` instead",
+            ))
+        }
+    };
+
+    Ok(TrainedModel {
+        environment: env_name.to_string(),
+        n_inputs,
+        model,
+        metrics,
+    })
+}
+
+#[pymodule]
+#[pyo3(name = "lgp")]
+fn lgp_py(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add_class::<Hyperparameters>()?;
+    module.add_class::<TrainedModel>()?;
+    module.add_function(wrap_pyfunction!(train_iris, module)?)?;
+    module.add_function(wrap_pyfunction!(train_gym, module)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use pyo3::types::IntoPyDict;
+
+    use super::*;
+
+    fn run(code: &str) -> PyResult<()> {
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "lgp")?;
+            lgp_py(py, module)?;
+
+            let path = env::temp_dir().join("lgp_py").join("model.json");
+            std::fs::create_dir_all(path.parent().unwrap())?;
+
+            let globals = [
+                ("lgp", module.to_object(py)),
+                ("path", path.to_str().unwrap().to_object(py)),
+            ]
+            .into_py_dict(py);
+
+            py.run(code, Some(globals), None)
+        })
+    }
+
+    #[test]
+    fn given_kwargs_when_iris_is_trained_then_model_predicts_and_round_trips() {
+        run(r#"
+hp = lgp.Hyperparameters(
+    population_size=10,
+    n_generations=3,
+    seed=7,
+    track_diversity=False,
+    dataset_path="../../assets/fixtures/iris.csv",
+    program_parameters={"max_instructions": 10},
+)
+model = lgp.train_iris(hp)
+
+metrics = model.metrics()
+assert [row["generation"] for row in metrics] == [0, 1, 2]
+assert set(metrics[0]) == {"generation", "best", "median", "worst", "mean"}
+assert metrics[-1]["best"] >= metrics[-1]["worst"]
+
+prediction = model.predict([5.1, 3.5, 1.4, 0.2])
+assert prediction in (0, 1, 2)
+
+model.save(path)
+restored = lgp.TrainedModel.load(path)
+assert restored.environment == "iris-lgp"
+assert restored.predict([5.1, 3.5, 1.4, 0.2]) == prediction
+"#)
+        .unwrap();
+    }
+
+    #[test]
+    fn given_invalid_input_when_called_then_value_errors_are_raised() {
+        run(r#"
+def raises_value_error(f):
+    try:
+        f()
+    except ValueError:
+        return True
+    return False
+
+assert raises_value_error(lambda: lgp.train_gym("pong", lgp.Hyperparameters()))
+assert raises_value_error(lambda: lgp.train_gym("iris-lgp", lgp.Hyperparameters()))
+assert raises_value_error(lambda: lgp.train_iris(lgp.Hyperparameters(population_size="many")))
+assert raises_value_error(lambda: lgp.train_iris(lgp.Hyperparameters(no_such_field=1)))
+"#)
+        .unwrap();
+    }
+}
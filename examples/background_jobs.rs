@@ -0,0 +1,111 @@
+//! Runs evolution jobs behind `JobServer`'s HTTP API: starts a job, polls its progress, fetches
+//! its champion, and cancels a second one.
+//!
+//! Run with `cargo run --example background_jobs --features serve`.
+
+use std::{thread, time::Duration};
+
+use lgp::{
+    core::{
+        engines::{
+            core_engine::{HyperParameters, HyperParametersBuilder},
+            status_engine::{Status, StatusEngine},
+        },
+        instruction::InstructionGeneratorParametersBuilder,
+        program::{Program, ProgramGeneratorParametersBuilder},
+    },
+    data::dataset::Dataset,
+    problems::iris::{IrisEngine, IrisStateParameters},
+    utils::jobs::{JobCreated, JobServer, JobState, JobStatus},
+};
+use reqwest::blocking::Client;
+use uuid::Uuid;
+
+fn parameters(n_generations: usize) -> HyperParameters<IrisEngine> {
+    let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+        .n_actions(3)
+        .n_inputs(4)
+        .build()
+        .unwrap();
+    let program_parameters = ProgramGeneratorParametersBuilder::default()
+        .max_instructions(20)
+        .instruction_generator_parameters(instruction_parameters)
+        .build()
+        .unwrap();
+
+    HyperParametersBuilder::<IrisEngine>::default()
+        .program_parameters(program_parameters)
+        .population_size(20)
+        .n_trials(1)
+        .n_generations(n_generations)
+        .build()
+        .unwrap()
+}
+
+fn dataset() -> Dataset {
+    Dataset::new(
+        vec![
+            vec![5.1, 3.5, 1.4, 0.2],
+            vec![4.9, 3.0, 1.4, 0.2],
+            vec![7.0, 3.2, 4.7, 1.4],
+            vec![6.4, 3.2, 4.5, 1.5],
+            vec![6.3, 3.3, 6.0, 2.5],
+            vec![5.8, 2.7, 5.1, 1.9],
+        ],
+        vec![0, 0, 1, 1, 2, 2],
+        vec![],
+        vec![],
+    )
+}
+
+fn start(client: &Client, base: &str, n_generations: usize) -> Uuid {
+    let body = serde_json::to_string(&parameters(n_generations)).unwrap();
+    let response = client
+        .post(format!("{}/jobs", base))
+        .body(body)
+        .send()
+        .unwrap();
+
+    serde_json::from_str::<JobCreated>(&response.text().unwrap())
+        .unwrap()
+        .id
+}
+
+fn wait(client: &Client, base: &str, id: Uuid) -> JobStatus {
+    loop {
+        let response = client.get(format!("{}/jobs/{}", base, id)).send().unwrap();
+        let status: JobStatus = serde_json::from_str(&response.text().unwrap()).unwrap();
+
+        if status.state != JobState::Running {
+            return status;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn main() {
+    let server = JobServer::<IrisEngine>::bind("127.0.0.1:0", IrisStateParameters::new(dataset()))
+        .expect("a free port")
+        .with_artifacts(std::env::temp_dir());
+    let base = format!("http://{}", server.spawn());
+    let client = Client::new();
+
+    let finished = start(&client, &base, 20);
+    let cancelled = start(&client, &base, 1_000_000);
+
+    thread::sleep(Duration::from_millis(200));
+    client
+        .delete(format!("{}/jobs/{}", base, cancelled))
+        .send()
+        .unwrap();
+
+    println!("finished: {:?}", wait(&client, &base, finished));
+    let response = client
+        .get(format!("{}/jobs/{}/champion", base, finished))
+        .send()
+        .unwrap();
+    let champion: Program = serde_json::from_str(&response.text().unwrap()).unwrap();
+    println!("champion fitness {}", StatusEngine::get_fitness(&champion));
+
+    println!("cancelled: {:?}", wait(&client, &base, cancelled));
+}
@@ -1,5 +1,6 @@
 #!/usr/bin/env python
 
+import gzip
 import json
 from pathlib import Path
 import numpy as np
@@ -26,6 +27,17 @@ DEFAULTS = {
 }
 
 
+def fitness_value(fitness: Any) -> float:
+    # Fitness is saved as {"valid": x}, "invalid" or "not_evaluated"; older runs saved floats.
+    if isinstance(fitness, dict):
+        return fitness["valid"]
+    if fitness == "invalid":
+        return float("-inf")
+    if fitness is None or fitness == "not_evaluated":
+        return float("nan")
+    return fitness
+
+
 def generate_tables(
     path: str,
     output_dir: str = "assets/tables",
@@ -33,8 +45,31 @@ def generate_tables(
     # Load programs from JSON file.
     basename: str = Path(path).name
 
-    with open(Path(path) / "population.json", "r") as f:
-        programs: List[List[Dict[str, Any]]] = json.load(f)
+    # Runs only record their populations when asked to; otherwise use the per-generation
+    # metrics.
+    stream_path = Path(path) / "population.jsonl.gz"
+    if not stream_path.exists() and not (Path(path) / "population.json").exists():
+        metrics = pd.read_csv(Path(path) / "metrics.csv", index_col="generation")
+        data = {
+            "Max": metrics["best"],
+            "Median": metrics["median"],
+            "Min": metrics["worst"],
+        }
+        df = pd.DataFrame(data)
+        df.index.name = "Generation"
+
+        tables_path = Path(output_dir)
+        tables_path.mkdir(parents=True, exist_ok=True)
+        df.to_csv(tables_path / f"{basename}.csv")
+        return
+
+    programs: List[List[Dict[str, Any]]]
+    if stream_path.exists():
+        with gzip.open(stream_path, "rt") as f:
+            programs = [json.loads(line) for line in f if line.strip()]
+    else:
+        with open(Path(path) / "population.json", "r") as f:
+            programs = json.load(f)
 
     # Extract fitness scores and generation information from programs.
     fitness_scores: List[List[float]] = []
@@ -45,7 +80,7 @@ def generate_tables(
             if "program" in program:
                 program = program["program"]
 
-            generation_fitness.append(program["fitness"])
+            generation_fitness.append(fitness_value(program["fitness"]))
 
         fitness_scores.append(generation_fitness)
         generations.append(i)
@@ -116,7 +151,7 @@ def main():
     parser.add_argument(
         "--input",
         required=True,
-        help="Directory containing either a table for figure generation or a population.json file for table generation.",
+        help="Directory containing either a table for figure generation or run outputs (population.jsonl.gz, population.json or metrics.csv) for table generation.",
     )
     parser.add_argument(
         "--output",
@@ -0,0 +1,72 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::random::standard_normal;
+
+/// Learning rate of the log-normal self-adaptation.
+const TAU: f64 = 0.2;
+
+/// Variation operator applied to fill an offspring slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variation {
+    Crossover,
+    Mutation,
+    Clone,
+}
+
+/// Variation parameters carried by an individual and evolved alongside its genome, as in
+/// self-adaptive evolution strategies.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveRates {
+    /// Scales the `constant_sigma` used when mutating this individual's constants.
+    pub mutation_sigma: f64,
+    /// Relative weights of crossover, mutation and cloning.
+    pub variation_weights: [f64; 3],
+}
+
+impl AdaptiveRates {
+    /// Starts from the global percentages, with cloning taking the remainder.
+    pub fn new(mutation_percent: f64, crossover_percent: f64) -> Self {
+        AdaptiveRates {
+            mutation_sigma: 1.,
+            variation_weights: [
+                crossover_percent,
+                mutation_percent,
+                (1. - crossover_percent - mutation_percent).max(0.),
+            ],
+        }
+    }
+
+    /// Multiplies every rate by an independent log-normal factor.
+    pub fn perturb<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.mutation_sigma *= (TAU * standard_normal(rng)).exp();
+
+        for weight in self.variation_weights.iter_mut() {
+            *weight *= (TAU * standard_normal(rng)).exp();
+        }
+    }
+
+    /// Draws an operator in proportion to the variation weights; clones when all are zero.
+    pub fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> Variation {
+        let total = self.variation_weights.iter().sum::<f64>();
+
+        if !(total > 0.) {
+            return Variation::Clone;
+        }
+
+        let mut draw = rng.gen_range(0. ..total);
+        for (weight, variation) in self.variation_weights.iter().zip([
+            Variation::Crossover,
+            Variation::Mutation,
+            Variation::Clone,
+        ]) {
+            if draw < *weight {
+                return variation;
+            }
+            draw -= weight;
+        }
+
+        Variation::Clone
+    }
+}
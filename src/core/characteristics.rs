@@ -1,23 +1,92 @@
 use std::{
-    error::Error,
-    fs::{read_to_string, OpenOptions},
-    io::Write,
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, Read, Write},
     path::{Path, PathBuf},
 };
 
-use serde::{de::DeserializeOwned, Serialize};
+use clap::ValueEnum;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::utils::benchmark_tools::create_path;
+use crate::utils::{benchmark_tools::create_path, error::LgpResult};
+
+use super::{
+    adaptation::AdaptiveRates, diversity::edit_distance, instruction::Instruction,
+    provenance::Provenance, registers::Registers,
+};
+
+/// Encoding of saved artifacts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// Pretty-printed JSON, readable by other tools.
+    #[default]
+    Json,
+    /// Compact bincode, for large artifacts such as full populations. Not self-describing, so
+    /// the reader must know the type it holds.
+    Binary,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Binary => "bin",
+        }
+    }
+
+    /// Binary for `.bin` files, JSON otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("bin") => Format::Binary,
+            _ => Format::Json,
+        }
+    }
+
+    /// `<stem>.<extension>` inside `directory`.
+    pub fn file_name(&self, directory: &Path, stem: &str) -> PathBuf {
+        directory.join(format!("{}.{}", stem, self.extension()))
+    }
+
+    /// The artifact `stem` saved in `directory` in either format, preferring JSON when both
+    /// exist.
+    pub fn find(directory: &Path, stem: &str) -> PathBuf {
+        let json = Format::Json.file_name(directory, stem);
+        let binary = Format::Binary.file_name(directory, stem);
+
+        if !json.exists() && binary.exists() {
+            binary
+        } else {
+            json
+        }
+    }
+}
 
 pub trait Load
 where
     Self: Sized + DeserializeOwned,
 {
-    fn load(path: impl Into<PathBuf>) -> Self {
-        let contents = read_to_string(&path.into()).unwrap();
-        let deserialized: Self = serde_json::from_str(&contents).unwrap();
+    /// Reads JSON, or bincode from `.bin` files.
+    fn load(path: impl Into<PathBuf>) -> LgpResult<Self> {
+        let path = path.into();
+
+        Self::load_as(&path, Format::from_path(&path))
+    }
+
+    fn load_as(path: &Path, format: Format) -> LgpResult<Self> {
+        Self::read_as(BufReader::new(File::open(path)?), format)
+    }
 
-        deserialized
+    /// Reads from any source, e.g. an in-memory buffer where there is no file system.
+    fn read_from(reader: impl Read) -> LgpResult<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    fn read_as(reader: impl Read, format: Format) -> LgpResult<Self> {
+        match format {
+            Format::Json => Self::read_from(reader),
+            Format::Binary => Ok(bincode::deserialize_from(reader)?),
+        }
     }
 }
 
@@ -25,7 +94,7 @@ pub trait Save
 where
     Self: Serialize,
 {
-    fn save(&self, path: &str) -> Result<String, Box<dyn Error>> {
+    fn save(&self, path: &str) -> LgpResult<String> {
         create_path(path, true)?;
 
         let serialized = serde_json::to_string_pretty(&self)?;
@@ -39,10 +108,83 @@ where
 
         Ok(serialized)
     }
+
+    /// Writes `path` in `format`, replacing any previous file.
+    fn save_as(&self, path: &Path, format: Format) -> LgpResult<()> {
+        let path = create_path(path.to_str().unwrap(), true)?;
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        self.write_as(&mut writer, format)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Writes to any sink, e.g. an in-memory buffer where there is no file system.
+    fn write_to(&self, writer: impl Write) -> LgpResult<()> {
+        serde_json::to_writer_pretty(writer, &self)?;
+
+        Ok(())
+    }
+
+    fn write_as(&self, writer: impl Write, format: Format) -> LgpResult<()> {
+        match format {
+            Format::Json => self.write_to(writer),
+            Format::Binary => Ok(bincode::serialize_into(writer, &self)?),
+        }
+    }
 }
 
 pub trait Reproduce: Load + Save {}
 
+/// Exposes the instruction sequence of an individual for structural analysis.
+pub trait Genotype {
+    fn instructions(&self) -> &[Instruction];
+
+    fn registers(&self) -> &Registers;
+
+    /// Actions the individual chooses between when it does not read them off its output
+    /// registers, e.g. the columns of a Q-table.
+    fn n_actions(&self) -> Option<usize> {
+        None
+    }
+
+    /// Undoes `Freeze`, so a saved individual keeps learning when it is evolved again.
+    fn unfreeze(&mut self) {}
+
+    /// Called by the engine on every individual entering the next generation, e.g. to decay
+    /// learning constants by generation rather than by how much the individual acted.
+    fn end_generation(&mut self) {}
+
+    /// Variation parameters carried by the individual under adaptive variation.
+    fn adaptive_rates(&self) -> Option<&AdaptiveRates> {
+        None
+    }
+
+    fn set_adaptive_rates(&mut self, _rates: AdaptiveRates) {}
+
+    /// How the individual was bred; `None` for individuals of the first generation.
+    fn provenance(&self) -> Option<&Provenance> {
+        None
+    }
+
+    fn set_provenance(&mut self, _provenance: Provenance) {}
+
+    /// Identifies the individual in logs, e.g. when its evaluation fails.
+    fn id(&self) -> Option<Uuid> {
+        None
+    }
+
+    /// Distance used by genotypic niching. Defaults to the edit distance between instruction
+    /// sequences; individuals carrying learned state add the distance between it.
+    fn distance(&self, other: &Self) -> f64
+    where
+        Self: Sized,
+    {
+        edit_distance(self.instructions(), other.instructions()) as f64
+    }
+}
+
 impl<T> Load for T where T: Sized + DeserializeOwned {}
 impl<T> Save for T where T: Serialize {}
 impl<T> Reproduce for T where T: Load + Save {}
@@ -1,5 +1,31 @@
+use std::{
+    fs,
+    iter::repeat_with,
+    path::{Path, PathBuf},
+};
+
+use crate::core::characteristics::{Load, Save};
 use crate::core::engines::reset_engine::{Reset, ResetEngine};
-use crate::core::engines::status_engine::{Status, StatusEngine};
+use crate::core::program::Program;
+use crate::core::registry::{
+    find_problem, run_paired_problems, CART_POLE_LGP, CART_POLE_Q, IRIS_LGP, MOUNTAIN_CAR_LGP,
+    MOUNTAIN_CAR_Q, PROBLEMS,
+};
+use crate::extensions::q_learning::QProgram;
+use crate::utils::batch::{run_batch, BatchConfig};
+use crate::utils::benchmark_tools::default_output_root;
+use crate::utils::comparison::compare_runs;
+use crate::utils::config_discovery::{config_table, discover_configs, ConfigFilter};
+use crate::utils::convert::{convert, ArtifactKind};
+use crate::utils::error::{LgpError, LgpResult};
+#[cfg(feature = "metrics-export")]
+use crate::utils::metrics::MetricsExporter;
+use crate::utils::paired::save_paired_report;
+#[cfg(feature = "plots")]
+use crate::utils::plotting::{plot_q_table, plot_run, PlotParameters};
+use crate::utils::predict::{predict_csv, Features};
+use crate::utils::random::generator;
+use crate::utils::run_store::RunStore;
 use crate::{
     core::engines::core_engine::HyperParameters,
     problems::{
@@ -7,25 +33,347 @@ use crate::{
         iris::IrisEngine,
     },
 };
-use clap::Parser;
-use config::{Config, Environment, File};
+use clap::{Args, Parser, ValueEnum};
+use config::{Config, Environment, File, FileFormat};
 use gym_rs::envs::classical_control::{cartpole::CartPoleEnv, mountain_car::MountainCarEnv};
+use itertools::Itertools;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 use super::engines::core_engine::Core;
 
-// Generate a macro which takes hyperparameters, builds the necessary engine and run its
-// outputting the best score for each generation
-macro_rules! run_actuator {
-    ($engine:ident, $hyperparameters:ident) => {
-        for population in $hyperparameters
-            .build_engine()
-            .take($hyperparameters.population_size)
-        {
-            println!("{}", StatusEngine::get_fitness(population.first().unwrap()));
-        }
-        println!("{}", serde_json::to_string(&$hyperparameters).unwrap());
-    };
+/// Random input rows a model simplified by `lgp inspect --simplify` is checked on.
+const N_SIMPLIFICATION_PROBES: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+pub enum Problem {
+    MountainCarQ,
+    MountainCarLgp,
+    CartPoleQ,
+    CartPoleLgp,
+    IrisLgp,
+}
+
+impl Problem {
+    /// Looks a problem up by its CLI name, e.g. `cart-pole-lgp`, or one of its aliases.
+    pub fn from_name(name: &str) -> LgpResult<Self> {
+        find_problem(name).map(|descriptor| descriptor.problem())
+    }
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct LandscapeArgs {
+    /// Problem the champion was evolved on, by name; see `lgp list`.
+    #[arg(long)]
+    pub environment: String,
+    /// Path to the saved champion (e.g. best.json).
+    pub model: PathBuf,
+    /// Hyperparameters used to generate and evaluate the neighbourhood.
+    #[arg(long)]
+    pub config: String,
+    #[arg(long, default_value = "landscape.csv")]
+    pub output: PathBuf,
+    #[arg(long, default_value = "1000")]
+    pub max_neighbours: usize,
+    /// `key=value` overrides applied to the loaded hyperparameters.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct RefineArgs {
+    /// Problem the champion was evolved on, by name; see `lgp list`.
+    #[arg(long)]
+    pub environment: String,
+    /// Path to the saved champion (e.g. best.json).
+    pub model: PathBuf,
+    /// Hyperparameters used to generate the training and holdout trials.
+    #[arg(long)]
+    pub config: String,
+    /// Maximum number of fitness evaluations.
+    #[arg(long, default_value = "100")]
+    pub budget: usize,
+    #[arg(long, default_value = "1.")]
+    pub initial_step: f64,
+    /// `key=value` overrides applied to the loaded hyperparameters.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct BatchArgs {
+    /// File holding the `[[experiments]]` to run.
+    pub batch: String,
+    /// Number of experiments run concurrently.
+    #[arg(long, default_value = "1")]
+    pub parallel: usize,
+    /// Directory outputs are saved under; `outputs` by default.
+    #[arg(long)]
+    pub output_root: Option<PathBuf>,
+    /// Serve the metrics of the runs for Prometheus on this port, at `/metrics`.
+    #[cfg(feature = "metrics-export")]
+    #[arg(long)]
+    pub metrics_port: Option<u16>,
+}
+
+/// Problems with both an LGP and a Q-learning variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+pub enum PairedProblem {
+    CartPole,
+    MountainCar,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct PairedArgs {
+    #[arg(value_enum)]
+    pub environment: PairedProblem,
+    #[arg(long, default_value = "100")]
+    pub n_generations: usize,
+    #[arg(long, default_value = "10")]
+    pub repeats: usize,
+    /// Seed of the first repeat; repeat `i` of both variants is seeded with `seed + i`.
+    #[arg(long, default_value = "0")]
+    pub seed: u64,
+    /// Directory the report is saved under, relative to the output root; `<environment>_paired`
+    /// by default.
+    #[arg(long)]
+    pub output: Option<String>,
+    /// Directory outputs are saved under; `outputs` by default.
+    #[arg(long)]
+    pub output_root: Option<PathBuf>,
+    /// `key=value` overrides applied to the hyperparameters of both variants.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct SweepArgs {
+    /// Problem to sweep, by name; see `lgp list`.
+    #[arg(long)]
+    pub environment: String,
+    /// Base hyperparameters shared by every configuration.
+    #[arg(long)]
+    pub config: String,
+    /// File holding the `[sweep]` grid; defaults to the base config.
+    #[arg(long)]
+    pub sweep: Option<String>,
+    /// Number of configurations run concurrently.
+    #[arg(long, default_value = "1")]
+    pub jobs: usize,
+    /// Output directory of the sweep, relative to the output root.
+    #[arg(long, default_value = "sweep")]
+    pub name: String,
+    /// Directory outputs are saved under; `outputs` by default.
+    #[arg(long)]
+    pub output_root: Option<PathBuf>,
+    /// `key=value` overrides applied to the base hyperparameters.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct TuneArgs {
+    /// Problem to tune, by name; see `lgp list`.
+    #[arg(long)]
+    pub environment: String,
+    /// Base hyperparameters the sampled values override.
+    #[arg(long)]
+    pub config: String,
+    /// File holding the `[space]` distributions.
+    #[arg(long)]
+    pub space: String,
+    /// Number of sampled configurations.
+    #[arg(long, default_value = "50")]
+    pub trials: usize,
+    /// Generations of a full evaluation; defaults to the base `n_generations`.
+    #[arg(long)]
+    pub generations: Option<usize>,
+    /// Prune with successive halving, keeping the best `1 / eta` at each rung.
+    #[arg(long)]
+    pub eta: Option<usize>,
+    /// Output directory of the tuning results, relative to the output root.
+    #[arg(long, default_value = "tune")]
+    pub name: String,
+    /// Directory outputs are saved under; `outputs` by default.
+    #[arg(long)]
+    pub output_root: Option<PathBuf>,
+    /// `key=value` overrides applied to the base hyperparameters.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct CompareArgs {
+    /// Directory of the first run, or of its repeats.
+    pub run_a: PathBuf,
+    /// Directory of the second run, or of its repeats.
+    pub run_b: PathBuf,
+    /// Report output path.
+    #[arg(long, default_value = "report.json")]
+    pub out: PathBuf,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct ConvertArgs {
+    /// Saved artifact; `.bin` files are read as binary and anything else as JSON.
+    pub input: PathBuf,
+    /// Destination, whose extension picks the format in the same way.
+    pub output: PathBuf,
+    /// What the artifact holds; inferred from JSON inputs when omitted.
+    #[arg(long, value_enum)]
+    pub kind: Option<ArtifactKind>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct AnimateArgs {
+    /// Problem the run was evolved on, by name; see `lgp list`.
+    #[arg(long)]
+    pub environment: String,
+    /// Output directory of the run (containing champions.json).
+    pub run_dir: PathBuf,
+    /// Replay the champion of every n-th generation.
+    #[arg(long, default_value = "10")]
+    pub every: usize,
+    #[arg(long, default_value = "1")]
+    pub episodes: usize,
+    #[arg(long, default_value = "frames")]
+    pub out: PathBuf,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct PredictArgs {
+    /// Problem the model was evolved on, by name; see `lgp list`.
+    #[arg(long)]
+    pub environment: String,
+    /// Path to the saved model (e.g. best.json).
+    #[arg(long)]
+    pub model: PathBuf,
+    /// Headerless CSV of feature rows; columns beyond the problem's inputs are ignored.
+    #[arg(long)]
+    pub input: PathBuf,
+    #[arg(long, default_value = "predictions.csv")]
+    pub output: PathBuf,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct InspectArgs {
+    /// Problem the model was evolved on, by name; see `lgp list`.
+    #[arg(long)]
+    pub environment: String,
+    /// Path to the saved model (e.g. best.json).
+    pub model: PathBuf,
+    /// Print the execution trace of the model on `--input` instead of its instructions.
+    #[arg(long, requires = "input")]
+    pub trace: bool,
+    /// Comma-separated input row, e.g. `5.1,3.5,1.4,0.2`.
+    #[arg(long, value_delimiter = ',')]
+    pub input: Vec<f64>,
+    /// Print the trace as JSON.
+    #[arg(long)]
+    pub json: bool,
+    /// Simplify the model first, checking it still behaves the same on random inputs in
+    /// `[-10, 10]` (and on `--input`).
+    #[arg(long)]
+    pub simplify: bool,
+    /// Write the Q-table of a Q-learning model to `q_table.csv` in this directory (and, with
+    /// plots enabled, a `q_table.png` heatmap), and print its best action per register.
+    #[arg(long, value_name = "DIR")]
+    pub q_table: Option<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct RunArgs {
+    /// Name of a registered problem, e.g. `cart-pole-lgp` or `iris`; see `lgp list`.
+    pub environment: String,
+    /// Hyperparameters to start from; the command line defaults when unset.
+    #[arg(long)]
+    pub config: Option<String>,
+    /// Replaces the configured seed.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Directory the outputs are saved under, relative to the output root; the problem's name
+    /// in snake case by default.
+    #[arg(long)]
+    pub output: Option<String>,
+    /// Directory outputs are saved under; `outputs` by default.
+    #[arg(long)]
+    pub output_root: Option<PathBuf>,
+    /// `key=value` overrides applied to the hyperparameters.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+    /// Print the resolved hyperparameters and output directory as JSON, then exit without
+    /// running.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Print only the resolved hyperparameters as JSON, loadable with `--config`, then exit
+    /// without running.
+    #[arg(long, conflicts_with = "dry_run")]
+    pub print_config: bool,
+    /// Serve the metrics of the runs for Prometheus on this port, at `/metrics`.
+    #[cfg(feature = "metrics-export")]
+    #[arg(long)]
+    pub metrics_port: Option<u16>,
+}
+
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct ListArgs {
+    /// List saved runs, oldest first, instead of the available problems.
+    #[arg(long)]
+    pub runs: bool,
+    /// Directory the runs are saved under; `outputs` when unset.
+    #[arg(long, requires = "runs")]
+    pub base_dir: Option<PathBuf>,
+    /// List the hyperparameter files under this directory instead of the available problems,
+    /// each parsed with the problem its file name starts with.
+    #[arg(long, value_name = "DIR", conflicts_with = "runs")]
+    pub configs: Option<PathBuf>,
+    /// Keep the configs matching `env=<name>`, `q_learning=<bool>` or `seed_pinned=<bool>`.
+    #[arg(long = "filter", value_name = "KEY=VALUE", requires = "configs")]
+    pub filters: Vec<ConfigFilter>,
+    #[arg(long, value_enum, default_value = "table")]
+    pub format: ListFormat,
+    /// Same as `--format json`.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+pub enum ListFormat {
+    Table,
+    Json,
+}
+
+#[cfg(feature = "plots")]
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct PlotArgs {
+    /// Output directory of the run (containing metrics.csv).
+    pub run_dir: PathBuf,
+    /// Output image; `.svg` renders a vector image, anything else a bitmap.
+    #[arg(long, default_value = "fitness.png")]
+    pub out: PathBuf,
+    #[arg(long, default_value = "Fitness")]
+    pub title: String,
+    #[arg(long, requires = "y_max")]
+    pub y_min: Option<f64>,
+    #[arg(long, requires = "y_min")]
+    pub y_max: Option<f64>,
+}
+
+#[cfg(feature = "serve")]
+#[derive(Args, Clone, Debug, Deserialize, Serialize)]
+pub struct ServeEvalArgs {
+    /// Problem whose individuals are evaluated, by name; see `lgp list`.
+    #[arg(long)]
+    pub environment: String,
+    #[arg(long, default_value = "8080")]
+    pub port: u16,
+    /// Hyperparameters the trials are generated with (e.g. `dataset_path`); the problem's
+    /// defaults when unset. Must match the configuration of the runs sending work.
+    #[arg(long)]
+    pub config: Option<String>,
+    /// `key=value` overrides applied to the loaded hyperparameters.
+    #[arg(long = "set", value_name = "KEY=VALUE", requires = "config")]
+    pub overrides: Vec<String>,
 }
 
 #[derive(Parser, Deserialize, Serialize)]
@@ -35,98 +383,620 @@ pub enum Actuator {
     CartPoleQ(HyperParameters<GymRsQEngine<CartPoleEnv>>),
     CartPoleLGP(HyperParameters<GymRsEngine<CartPoleEnv>>),
     IrisLgp(HyperParameters<IrisEngine>),
+    /// Run a registered problem by name, from a configuration file or the defaults.
+    Run(RunArgs),
+    /// Export the single-mutation fitness landscape around a saved champion.
+    Landscape(LandscapeArgs),
+    /// Refine the constants of a saved champion with coordinate descent.
+    Refine(RefineArgs),
+    /// Replay per-generation champions on fixed initial states and export their trajectories.
+    Animate(AnimateArgs),
+    /// Predict the class of every row of a CSV file with a saved model.
+    Predict(PredictArgs),
+    /// Print the instructions of a saved model, or trace its execution on an input row.
+    Inspect(InspectArgs),
+    /// Run every configuration of a parameter grid and rank them by final best fitness.
+    Sweep(SweepArgs),
+    /// Run the experiments listed in a file, several at a time.
+    Batch(BatchArgs),
+    /// Run the LGP and Q-learning variants of a problem on the same seeds and trials and
+    /// compare them repeat by repeat.
+    Paired(PairedArgs),
+    /// Search hyperparameters by random sampling, optionally with successive halving.
+    Tune(TuneArgs),
+    /// Compare the final best fitness and fitness curves of two saved runs.
+    Compare(CompareArgs),
+    /// Convert saved individuals between JSON and binary.
+    Convert(ConvertArgs),
+    /// List the available problems, or saved runs with their status and final best fitness.
+    List(ListArgs),
+    /// Plot the best, median and worst fitness per generation of a saved run.
+    #[cfg(feature = "plots")]
+    Plot(PlotArgs),
+    /// Evaluate individuals sent by runs configured with `--eval-worker`.
+    #[cfg(feature = "serve")]
+    ServeEval(ServeEvalArgs),
 }
 
 impl Actuator {
-    pub fn run(&mut self) {
+    pub fn run(&mut self) -> LgpResult<()> {
         // Use the run engine macro for each branch of the enum
         match self {
             Actuator::MountainCarQ(hyperparameters) => {
-                ResetEngine::reset(&mut hyperparameters.program_parameters.consts);
-
-                hyperparameters
-                    .program_parameters
-                    .program_parameters
-                    .instruction_generator_parameters
-                    .n_actions = 3;
-                hyperparameters
-                    .program_parameters
-                    .program_parameters
-                    .instruction_generator_parameters
-                    .n_inputs = 2;
-                hyperparameters.default_fitness = -200.0;
-
-                run_actuator!(GymRsQEngine, hyperparameters);
+                MOUNTAIN_CAR_Q.execute(hyperparameters, &default_output_root(), None)?
             }
             Actuator::MountainCarLGP(hyperparameters) => {
-                hyperparameters
-                    .program_parameters
-                    .instruction_generator_parameters
-                    .n_actions = 3;
-                hyperparameters
-                    .program_parameters
-                    .instruction_generator_parameters
-                    .n_inputs = 2;
-                hyperparameters.default_fitness = -200.0;
-
-                run_actuator!(GymRsEngine, hyperparameters);
+                MOUNTAIN_CAR_LGP.execute(hyperparameters, &default_output_root(), None)?
+            }
+            Actuator::CartPoleQ(hyperparameters) => {
+                CART_POLE_Q.execute(hyperparameters, &default_output_root(), None)?
+            }
+            Actuator::CartPoleLGP(hyperparameters) => {
+                CART_POLE_LGP.execute(hyperparameters, &default_output_root(), None)?
             }
             Actuator::IrisLgp(hyperparameters) => {
-                hyperparameters
-                    .program_parameters
-                    .instruction_generator_parameters
-                    .n_actions = 3;
-                hyperparameters
-                    .program_parameters
-                    .instruction_generator_parameters
-                    .n_inputs = 4;
+                IRIS_LGP.execute(hyperparameters, &default_output_root(), None)?
+            }
+            Actuator::Run(args) if args.dry_run || args.print_config => {
+                let resolved = find_problem(&args.environment)?.resolve(
+                    args.config.as_deref(),
+                    &args.overrides,
+                    args.seed,
+                    &output_root(&args.output_root),
+                    args.output.as_deref(),
+                )?;
 
-                run_actuator!(IrisEngine, hyperparameters);
+                let printed = match args.print_config {
+                    true => serde_json::to_string_pretty(&resolved.hyperparameters)?,
+                    false => serde_json::to_string_pretty(&resolved)?,
+                };
+                println!("{}", printed);
             }
-            Actuator::CartPoleQ(hyperparameters) => {
-                ResetEngine::reset(&mut hyperparameters.program_parameters.consts);
-                hyperparameters
-                    .program_parameters
-                    .program_parameters
-                    .instruction_generator_parameters
-                    .n_actions = 2;
-                hyperparameters
-                    .program_parameters
-                    .program_parameters
-                    .instruction_generator_parameters
-                    .n_inputs = 4;
-                hyperparameters.default_fitness = 500.0;
-
-                run_actuator!(GymRsQEngine, hyperparameters);
+            Actuator::Run(args) => {
+                #[cfg(feature = "metrics-export")]
+                start_metrics_exporter(args.metrics_port)?;
+
+                find_problem(&args.environment)?.run(
+                    args.config.as_deref(),
+                    &args.overrides,
+                    args.seed,
+                    &output_root(&args.output_root),
+                    args.output.as_deref(),
+                )?
             }
-            Actuator::CartPoleLGP(hyperparameters) => {
-                hyperparameters
-                    .program_parameters
-                    .instruction_generator_parameters
-                    .n_actions = 2;
-                hyperparameters
-                    .program_parameters
-                    .instruction_generator_parameters
-                    .n_inputs = 4;
-                hyperparameters.default_fitness = 500.0;
-
-                run_actuator!(GymRsEngine, hyperparameters);
+            Actuator::Landscape(args) => find_problem(&args.environment)?.landscape(args)?,
+            Actuator::Refine(args) => find_problem(&args.environment)?.refine(args)?,
+            Actuator::Animate(args) => find_problem(&args.environment)?.animate(args)?,
+            Actuator::Predict(args) => {
+                let descriptor = find_problem(&args.environment)?;
+                if descriptor.problem() != Problem::IrisLgp {
+                    return Err(LgpError::Config(
+                        "Prediction is only supported for classification environments.".to_string(),
+                    ));
+                }
+
+                let mut model = Program::load(&args.model)?;
+                let n_predictions =
+                    predict_csv(&mut model, &args.input, &args.output, descriptor.n_inputs())?;
+                println!("{}", n_predictions);
+            }
+            Actuator::Inspect(args) => {
+                let descriptor = find_problem(&args.environment)?;
+                let n_inputs = descriptor.n_inputs();
+                let (mut program, q_table) = match descriptor.problem() {
+                    Problem::MountainCarQ | Problem::CartPoleQ => {
+                        let QProgram { program, q_table } = QProgram::load(&args.model)?;
+                        (program, Some(q_table))
+                    }
+                    Problem::MountainCarLgp | Problem::CartPoleLgp | Problem::IrisLgp => {
+                        (Program::load(&args.model)?, None)
+                    }
+                };
+
+                if let Some(dir) = &args.q_table {
+                    let q_table = q_table.as_ref().ok_or_else(|| {
+                        LgpError::Config(
+                            "Q-tables are only saved by Q-learning environments.".to_string(),
+                        )
+                    })?;
+
+                    std::fs::create_dir_all(dir)?;
+                    std::fs::write(dir.join("q_table.csv"), q_table.to_csv())?;
+                    #[cfg(feature = "plots")]
+                    plot_q_table(
+                        q_table,
+                        &dir.join("q_table.png"),
+                        &PlotParameters {
+                            title: "Q-values".to_string(),
+                            ..Default::default()
+                        },
+                    )?;
+
+                    for summary in q_table.summary() {
+                        eprintln!(
+                            "r{}: best action {}, values in [{}, {}]",
+                            summary.register,
+                            summary.best_action,
+                            summary.min_value,
+                            summary.max_value
+                        );
+                    }
+                }
+
+                if args.simplify {
+                    let mut rows = repeat_with(|| {
+                        repeat_with(|| generator().gen_range(-10. ..=10.))
+                            .take(n_inputs)
+                            .collect_vec()
+                    })
+                    .take(N_SIMPLIFICATION_PROBES)
+                    .collect_vec();
+                    rows.extend((args.input.len() >= n_inputs).then(|| args.input.clone()));
+
+                    let probes = rows.iter().map(|row| Features(row)).collect_vec();
+                    let n_instructions = program.instructions.len();
+                    program = program.simplify(&probes);
+                    eprintln!(
+                        "Simplified {} instructions to {}.",
+                        n_instructions,
+                        program.instructions.len()
+                    );
+                }
+
+                if !args.trace {
+                    for (idx, instruction) in program.instructions.iter().enumerate() {
+                        println!("{:>4}  {}", idx, instruction);
+                    }
+                } else if args.input.len() < n_inputs {
+                    return Err(LgpError::Config(format!(
+                        "Expected {} input values, got {}",
+                        n_inputs,
+                        args.input.len()
+                    )));
+                } else {
+                    ResetEngine::reset(&mut program.registers);
+                    let trace = program.run_traced(&Features(&args.input));
+
+                    if args.json {
+                        println!("{}", serde_json::to_string_pretty(&trace).unwrap());
+                    } else {
+                        print!("{}", trace);
+                    }
+                }
+            }
+            Actuator::Paired(args) => {
+                let report = match args.environment {
+                    PairedProblem::CartPole => run_paired_problems(
+                        &CART_POLE_LGP,
+                        &CART_POLE_Q,
+                        args.n_generations,
+                        args.repeats,
+                        args.seed,
+                        &args.overrides,
+                    )?,
+                    PairedProblem::MountainCar => run_paired_problems(
+                        &MOUNTAIN_CAR_LGP,
+                        &MOUNTAIN_CAR_Q,
+                        args.n_generations,
+                        args.repeats,
+                        args.seed,
+                        &args.overrides,
+                    )?,
+                };
+                let name = args.output.clone().unwrap_or_else(|| {
+                    match args.environment {
+                        PairedProblem::CartPole => "cart_pole_paired",
+                        PairedProblem::MountainCar => "mountain_car_paired",
+                    }
+                    .to_string()
+                });
+
+                save_paired_report(&report, &output_root(&args.output_root), &name)?;
+                println!(
+                    "{} vs {}: {} wins, {} losses, {} ties, Wilcoxon p = {:.4}",
+                    report.arm_a,
+                    report.arm_b,
+                    report.wins,
+                    report.losses,
+                    report.ties,
+                    report.p_value
+                );
             }
+            Actuator::Batch(args) => {
+                let batch = BatchConfig::load(&args.batch)?;
+                #[cfg(feature = "metrics-export")]
+                start_metrics_exporter(args.metrics_port)?;
+                let outcomes = run_batch(&batch, args.parallel, &output_root(&args.output_root))?;
+
+                println!(
+                    "{:<40} {:<18} {:<10} {:>12}",
+                    "experiment", "environment", "status", "duration (s)"
+                );
+                for outcome in &outcomes {
+                    println!(
+                        "{:<40} {:<18} {:<10} {:>12.1}",
+                        outcome.output,
+                        outcome.environment,
+                        if outcome.error.is_none() {
+                            "ok"
+                        } else {
+                            "failed"
+                        },
+                        outcome.duration_ms as f64 / 1000.
+                    );
+                    if let Some(error) = &outcome.error {
+                        println!("  {}", error);
+                    }
+                }
+
+                let n_failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+                println!(
+                    "{} succeeded, {} failed",
+                    outcomes.len() - n_failed,
+                    n_failed
+                );
+                if n_failed > 0 {
+                    return Err(LgpError::Other(format!(
+                        "{} of {} experiments failed",
+                        n_failed,
+                        outcomes.len()
+                    )));
+                }
+            }
+            Actuator::Sweep(args) => find_problem(&args.environment)?.sweep(args)?,
+            Actuator::Tune(args) => find_problem(&args.environment)?.tune(args)?,
+            Actuator::List(args) if args.configs.is_some() => {
+                let directory = args.configs.as_ref().unwrap();
+                let configs = discover_configs(directory)?
+                    .into_iter()
+                    .filter(|config| args.filters.iter().all(|filter| filter.matches(config)))
+                    .collect_vec();
+
+                if args.json || args.format == ListFormat::Json {
+                    println!("{}", serde_json::to_string_pretty(&configs)?);
+                } else {
+                    print!("{}", config_table(&configs));
+                }
+            }
+            Actuator::List(args) if !args.runs => {
+                println!(
+                    "{:<18} {:>8} {:>9} {:>15}  aliases",
+                    "problem", "inputs", "actions", "default fitness"
+                );
+
+                for descriptor in PROBLEMS {
+                    let default_fitness = descriptor
+                        .default_fitness()
+                        .map_or_else(|| "-".to_string(), |fitness| fitness.to_string());
+
+                    println!(
+                        "{:<18} {:>8} {:>9} {:>15}  {}",
+                        descriptor.name(),
+                        descriptor.n_inputs(),
+                        descriptor.n_actions(),
+                        default_fitness,
+                        descriptor.aliases().join(", ")
+                    );
+                }
+            }
+            Actuator::List(args) => {
+                let store = match &args.base_dir {
+                    Some(base_dir) => RunStore::new(base_dir),
+                    None => RunStore::from_env(),
+                };
+                let runs = store.list_runs();
+
+                if args.json || args.format == ListFormat::Json {
+                    println!("{}", serde_json::to_string_pretty(&runs).unwrap());
+                } else {
+                    println!(
+                        "{:<40} {:<10} {:>20} {:>11} {:>12}",
+                        "run", "status", "seed", "generations", "final best"
+                    );
+
+                    for run in runs {
+                        let status = match (run.status, &run.error) {
+                            (_, Some(_)) => "error".to_string(),
+                            (Some(status), None) => serde_json::to_value(status)
+                                .unwrap()
+                                .as_str()
+                                .unwrap()
+                                .to_string(),
+                            (None, None) => "-".to_string(),
+                        };
+                        let optional =
+                            |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+
+                        println!(
+                            "{:<40} {:<10} {:>20} {:>11} {:>12}",
+                            run.run_id,
+                            status,
+                            optional(run.seed.map(|seed| seed.to_string())),
+                            run.n_generations,
+                            optional(run.final_best_fitness.map(|best| format!("{:.4}", best)))
+                        );
+
+                        if let Some(error) = &run.error {
+                            println!("  {}", error);
+                        }
+                    }
+                }
+            }
+            Actuator::Convert(args) => {
+                let kind = convert(&args.input, &args.output, args.kind)?;
+                println!(
+                    "Converted {} ({}) to {}",
+                    args.input.display(),
+                    kind.to_possible_value().unwrap().get_name(),
+                    args.output.display()
+                );
+            }
+            Actuator::Compare(args) => {
+                let report = compare_runs(&args.run_a, &args.run_b)?;
+                report.save(args.out.to_str().unwrap())?;
+                println!("{}", serde_json::to_string(&report).unwrap());
+            }
+            #[cfg(feature = "plots")]
+            Actuator::Plot(args) => {
+                let parameters = PlotParameters {
+                    title: args.title.clone(),
+                    y_range: args.y_min.zip(args.y_max),
+                    ..Default::default()
+                };
+                let curves = plot_run(&args.run_dir, &args.out, &parameters)?;
+                println!("{}", serde_json::to_string(&curves).unwrap());
+            }
+            #[cfg(feature = "serve")]
+            Actuator::ServeEval(args) => find_problem(&args.environment)?.serve_eval(args)?,
         }
+
+        Ok(())
+    }
+}
+
+/// The output root given on the command line, or the default one.
+pub(crate) fn output_root(output_root: &Option<PathBuf>) -> PathBuf {
+    output_root.clone().unwrap_or_else(default_output_root)
+}
+
+/// Serves the metrics of the runs of this process on `port`, when given.
+#[cfg(feature = "metrics-export")]
+fn start_metrics_exporter(port: Option<u16>) -> LgpResult<()> {
+    if let Some(port) = port {
+        let address = MetricsExporter::bind(("0.0.0.0", port))?.spawn();
+        eprintln!("Serving metrics on http://{}/metrics", address);
     }
+
+    Ok(())
 }
 
-pub fn load_hyper_parameters<C>(
-    filename: &str,
-) -> Result<HyperParameters<C>, Box<dyn std::error::Error>>
+/// Files a config may extend through, counting itself.
+const MAX_EXTENDS_DEPTH: usize = 8;
+
+/// Loads the hyperparameters in `filename`. A config may name another with `extends = "base.toml"`,
+/// resolved relative to it, which is loaded first and merged under it: tables merge key by key,
+/// scalars and arrays (e.g. `ops`) are replaced.
+pub fn load_hyper_parameters<C>(filename: &str) -> LgpResult<HyperParameters<C>>
 where
     C: Core,
 {
     let settings = Config::builder()
-        .add_source(File::with_name(filename))
+        .add_source(File::from_str(
+            &read_extended(filename)?.to_string(),
+            FileFormat::Json,
+        ))
         .add_source(Environment::default())
         .build()?;
 
     let parameters: HyperParameters<C> = settings.try_deserialize()?;
+    parameters.validate()?;
+
     Ok(parameters)
 }
+
+/// The settings in `filename` merged over those of the files it extends, without `extends`.
+fn read_extended(filename: &str) -> LgpResult<serde_json::Value> {
+    let mut path = PathBuf::from(filename);
+    let mut visited: Vec<PathBuf> = vec![];
+    let mut layers = vec![];
+
+    loop {
+        let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if visited.contains(&canonical) {
+            return Err(LgpError::Config(format!(
+                "`{}` extends itself through {}",
+                path.display(),
+                visited
+                    .iter()
+                    .map(|file| format!("`{}`", file.display()))
+                    .join(" -> ")
+            )));
+        }
+        if visited.len() == MAX_EXTENDS_DEPTH {
+            return Err(LgpError::Config(format!(
+                "`{}` extends more than {} files deep",
+                filename, MAX_EXTENDS_DEPTH
+            )));
+        }
+        visited.push(canonical);
+
+        let mut layer: serde_json::Value = Config::builder()
+            .add_source(File::with_name(&path.to_string_lossy()))
+            .build()?
+            .try_deserialize()?;
+        let extends = layer
+            .as_object_mut()
+            .and_then(|settings| settings.remove("extends"));
+        layers.push(layer);
+
+        match extends {
+            None => break,
+            Some(serde_json::Value::String(parent)) => {
+                let parent = path.parent().unwrap_or(Path::new("")).join(parent);
+                if !parent.is_file() {
+                    return Err(LgpError::Config(format!(
+                        "`{}` extends `{}`, which does not exist",
+                        path.display(),
+                        parent.display()
+                    )));
+                }
+                path = parent;
+            }
+            Some(other) => {
+                return Err(LgpError::Config(format!(
+                    "`extends` of `{}` must be a file name, not {}",
+                    path.display(),
+                    other
+                )))
+            }
+        }
+    }
+
+    let mut settings = layers.pop().unwrap();
+    for layer in layers.into_iter().rev() {
+        merge_settings(&mut settings, layer);
+    }
+
+    Ok(settings)
+}
+
+/// Merges `child` over `base`: tables merge key by key, anything else replaces.
+fn merge_settings(base: &mut serde_json::Value, child: serde_json::Value) {
+    match (base, child) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(child)) => {
+            for (key, value) in child {
+                match base.get_mut(&key) {
+                    Some(base) => merge_settings(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, child) => *base = child,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::core::{instruction::Op, instructions::OpSet};
+
+    use super::*;
+
+    #[test]
+    fn given_malformed_toml_when_loaded_then_config_error_is_returned() {
+        let path = env::temp_dir().join("lgp_config").join("malformed.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "population_size = [1,\nn_generations = ").unwrap();
+
+        let result = load_hyper_parameters::<IrisEngine>(path.to_str().unwrap());
+
+        assert!(matches!(result, Err(LgpError::Config(_))));
+        assert_eq!(result.err().unwrap().exit_code(), 2);
+    }
+
+    /// A directory holding a complete `base.json` and the given files.
+    fn extends_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = env::temp_dir().join("lgp_config_extends").join(name);
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("experiments")).unwrap();
+
+        let mut base = HyperParameters::<IrisEngine>::try_parse_from(["lgp"]).unwrap();
+        let instructions = &mut base.program_parameters.instruction_generator_parameters;
+        instructions.n_inputs = 4;
+        instructions.n_actions = 3;
+        fs::write(
+            directory.join("base.json"),
+            serde_json::to_string(&base).unwrap(),
+        )
+        .unwrap();
+        for (file, contents) in files {
+            fs::write(directory.join(file), contents).unwrap();
+        }
+
+        directory
+    }
+
+    #[test]
+    fn given_child_config_when_loaded_then_it_is_merged_over_its_parent() -> LgpResult<()> {
+        let directory = extends_dir(
+            "merged",
+            &[
+                (
+                    "small.toml",
+                    "extends = \"base.json\"\npopulation_size = 20\n\n\
+                     [program_parameters.instruction_generator_parameters]\n\
+                     n_calculation_registers = 3\nops = [\"sin\", \"cos\"]\n",
+                ),
+                (
+                    "experiments/short.toml",
+                    "extends = \"../small.toml\"\nn_generations = 4\n",
+                ),
+            ],
+        );
+        let base =
+            load_hyper_parameters::<IrisEngine>(directory.join("base.json").to_str().unwrap())?;
+        let loaded = load_hyper_parameters::<IrisEngine>(
+            directory.join("experiments/short.toml").to_str().unwrap(),
+        )?;
+
+        // Scalars override, from every level of the chain.
+        assert_eq!(loaded.population_size, 20);
+        assert_eq!(loaded.n_generations, 4);
+        assert_eq!(loaded.gap, base.gap);
+
+        // Nested tables merge key by key, arrays are replaced.
+        let instructions = &loaded.program_parameters.instruction_generator_parameters;
+        let base_instructions = &base.program_parameters.instruction_generator_parameters;
+        assert_eq!(instructions.n_calculation_registers, 3);
+        assert_eq!(
+            instructions.external_factor,
+            base_instructions.external_factor
+        );
+        assert_eq!(instructions.ops, OpSet::new(&[Op::Sin, Op::Cos]));
+
+        // The saved config is flattened.
+        let saved = serde_json::to_value(&loaded)?;
+        assert!(saved.get("extends").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_missing_or_cyclic_parent_when_loaded_then_config_error_is_returned() {
+        let directory = extends_dir(
+            "broken",
+            &[
+                ("orphan.toml", "extends = \"missing.toml\"\n"),
+                ("a.toml", "extends = \"b.toml\"\n"),
+                ("b.toml", "extends = \"a.toml\"\n"),
+            ],
+        );
+        let load = |file: &str| {
+            load_hyper_parameters::<IrisEngine>(directory.join(file).to_str().unwrap())
+        };
+
+        assert!(matches!(
+            load("orphan.toml"),
+            Err(LgpError::Config(message)) if message.contains("missing.toml")
+        ));
+        assert!(matches!(
+            load("a.toml"),
+            Err(LgpError::Config(message)) if message.contains("extends itself")
+        ));
+    }
+
+    #[test]
+    fn given_unknown_environment_when_looked_up_then_unknown_environment_error_is_returned() {
+        assert!(matches!(
+            Problem::from_name("pong"),
+            Err(LgpError::UnknownEnvironment { name, .. }) if name == "pong"
+        ));
+        assert!(matches!(
+            Problem::from_name("cart-pole-lgp"),
+            Ok(Problem::CartPoleLgp)
+        ));
+    }
+}
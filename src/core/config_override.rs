@@ -0,0 +1,203 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Number, Value};
+
+use crate::utils::error::{LgpError, LgpResult};
+
+/// Splits a `key=value` override.
+pub fn parse_override(assignment: &str) -> LgpResult<(&str, &str)> {
+    assignment
+        .split_once('=')
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| {
+            LgpError::Config(format!(
+                "Expected an override of the form `key=value`, got `{}`",
+                assignment
+            ))
+        })
+}
+
+/// Parses `raw` with the type of the value it replaces. Unset values (`null`) take the type the
+/// literal reads as: bool, integer, float, then string.
+fn parse_like(current: &Value, key: &str, raw: &str) -> LgpResult<Value> {
+    let wrong_type = |expected: &str| {
+        LgpError::Config(format!(
+            "Expected {} for `{}`, got `{}`",
+            expected, key, raw
+        ))
+    };
+
+    match current {
+        Value::Bool(_) => raw
+            .parse()
+            .map(Value::Bool)
+            .map_err(|_| wrong_type("a bool")),
+        Value::Number(number) if number.is_f64() => raw
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| wrong_type("a float")),
+        Value::Number(number) if number.is_u64() => raw
+            .parse::<u64>()
+            .map(Value::from)
+            .map_err(|_| wrong_type("an unsigned integer")),
+        Value::Number(_) => raw
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| wrong_type("an integer")),
+        Value::String(_) => Ok(Value::String(raw.to_string())),
+        Value::Null => Ok(raw
+            .parse::<bool>()
+            .map(Value::Bool)
+            .or_else(|_| raw.parse::<i64>().map(Value::from))
+            .ok()
+            .or_else(|| {
+                raw.parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .map(Value::Number)
+            })
+            .unwrap_or_else(|| Value::String(raw.to_string()))),
+        Value::Array(_) | Value::Object(_) => Err(wrong_type("a key inside this table or array")),
+    }
+}
+
+/// Sets the value at a dotted `key` (e.g. `program_parameters.max_instructions`, or `objectives.0`
+/// to index an array), keeping the type of the value it replaces.
+pub fn set_path(root: &mut Value, key: &str, raw: &str) -> LgpResult<()> {
+    let missing = || LgpError::Config(format!("Unknown config key `{}`", key));
+
+    let target = key.split('.').try_fold(root, |node, segment| match node {
+        Value::Object(table) => table.get_mut(segment),
+        Value::Array(items) => segment
+            .parse::<usize>()
+            .ok()
+            .and_then(move |idx| items.get_mut(idx)),
+        _ => None,
+    });
+    let target = target.ok_or_else(missing)?;
+
+    *target = parse_like(target, key, raw)?;
+
+    Ok(())
+}
+
+/// Applies `key=value` overrides to anything serializable, returning the resolved value.
+pub fn apply_overrides<T>(value: &T, overrides: &[String]) -> LgpResult<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut root = serde_json::to_value(value)?;
+
+    for assignment in overrides {
+        let (key, raw) = parse_override(assignment)?;
+        set_path(&mut root, key, raw)?;
+    }
+
+    serde_json::from_value(root)
+        .map_err(|error| LgpError::Config(format!("Invalid overrides: {}", error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        core::{
+            engines::core_engine::{HyperParameters, HyperParametersBuilder},
+            instruction::InstructionGeneratorParametersBuilder,
+            program::ProgramGeneratorParametersBuilder,
+            schedule::Schedule,
+        },
+        problems::iris::IrisEngine,
+    };
+
+    use super::*;
+
+    fn hyper_parameters() -> HyperParameters<IrisEngine> {
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()
+            .unwrap();
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()
+            .unwrap();
+
+        HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn given_nested_keys_when_overridden_then_values_keep_their_types() {
+        let overrides = [
+            "population_size=500",
+            "gap=0.25",
+            "track_diversity=true",
+            "seed=7",
+            "program_parameters.instruction_generator_parameters.n_calculation_registers=2",
+        ]
+        .map(String::from);
+
+        let resolved = apply_overrides(&hyper_parameters(), &overrides).unwrap();
+
+        assert_eq!(resolved.population_size, 500);
+        assert_eq!(resolved.gap, Schedule::Constant(0.25));
+        assert!(resolved.track_diversity);
+        assert_eq!(resolved.seed, Some(7));
+        assert_eq!(
+            resolved
+                .program_parameters
+                .instruction_generator_parameters
+                .n_calculation_registers,
+            2
+        );
+    }
+
+    #[test]
+    fn given_array_index_when_overridden_then_element_is_replaced() {
+        let mut root = json!({ "operations": [{ "percent": 0.5 }, { "percent": 0.5 }] });
+
+        set_path(&mut root, "operations.1.percent", "0.8").unwrap();
+
+        assert_eq!(root["operations"][1]["percent"], json!(0.8));
+        assert_eq!(root["operations"][0]["percent"], json!(0.5));
+    }
+
+    #[test]
+    fn given_wrong_type_when_overridden_then_error_names_the_key() {
+        for assignment in ["population_size=lots", "population_size=1.5", "gap=half"] {
+            let error = apply_overrides(&hyper_parameters(), &[assignment.to_string()])
+                .err()
+                .expect("Mistyped overrides are rejected.");
+            let key = assignment.split('=').next().unwrap();
+
+            assert!(matches!(&error, LgpError::Config(message) if message.contains(key)));
+        }
+    }
+
+    #[test]
+    fn given_missing_key_when_overridden_then_error_names_the_key() {
+        for key in [
+            "populaton_size",
+            "program_parameters.missing",
+            "gap.nested",
+            "operations.3",
+        ] {
+            let error = apply_overrides(&hyper_parameters(), &[format!("{}=1", key)])
+                .err()
+                .expect("Unknown keys are rejected.");
+
+            assert!(
+                matches!(&error, LgpError::Config(message) if message.contains(&format!("`{}`", key)))
+            );
+        }
+
+        assert!(apply_overrides(&hyper_parameters(), &["population_size".to_string()]).is_err());
+    }
+}
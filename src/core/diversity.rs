@@ -0,0 +1,173 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::random::generator;
+
+use super::{
+    characteristics::Genotype,
+    engines::{core_engine::Core, status_engine::Status},
+    instruction::Instruction,
+};
+
+/// Number of random pairs used to estimate the mean pairwise edit distance.
+const N_SAMPLED_PAIRS: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiversityMetrics {
+    /// Number of structurally distinct instruction sequences.
+    pub n_unique: usize,
+    /// Mean edit distance between randomly sampled pairs of individuals.
+    pub mean_edit_distance: f64,
+    /// Shannon entropy of the distribution of fitness values.
+    pub fitness_entropy: f64,
+}
+
+impl DiversityMetrics {
+    pub fn compute<C>(population: &[C::Individual]) -> Self
+    where
+        C: Core,
+    {
+        let n_unique = population
+            .iter()
+            .map(|individual| fingerprint(individual.instructions()))
+            .collect::<HashSet<_>>()
+            .len();
+
+        let mean_edit_distance = if population.len() < 2 {
+            0.
+        } else {
+            let total: usize = (0..N_SAMPLED_PAIRS)
+                .map(|_| {
+                    let a = generator().gen_range(0..population.len());
+                    let b = generator().gen_range(0..population.len());
+                    edit_distance(population[a].instructions(), population[b].instructions())
+                })
+                .sum();
+
+            total as f64 / N_SAMPLED_PAIRS as f64
+        };
+
+        let mut fitness_counts: HashMap<u64, usize> = HashMap::new();
+        for individual in population {
+            *fitness_counts
+                .entry(C::Status::get_fitness(individual).to_bits())
+                .or_default() += 1;
+        }
+
+        let fitness_entropy = fitness_counts
+            .values()
+            .map(|count| {
+                let p = *count as f64 / population.len() as f64;
+                -p * p.ln()
+            })
+            .sum();
+
+        DiversityMetrics {
+            n_unique,
+            mean_edit_distance,
+            fitness_entropy,
+        }
+    }
+}
+
+pub fn fingerprint(instructions: &[Instruction]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    instructions.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Levenshtein distance between two instruction sequences.
+pub fn edit_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, item_a) in a.iter().enumerate() {
+        current[0] = i + 1;
+
+        for (j, item_b) in b.iter().enumerate() {
+            let substitution = previous[j] + (item_a != item_b) as usize;
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::instructions::OpSet;
+    use crate::{
+        core::{
+            engines::{
+                core_engine::Core,
+                generate_engine::{Generate, GenerateEngine},
+            },
+            instruction::InstructionGeneratorParameters,
+            program::{Program, ProgramGeneratorParameters},
+        },
+        problems::iris::IrisEngine,
+    };
+
+    use super::*;
+    use crate::core::registers::{RegisterInit, RegisterSaturation};
+
+    fn parameters() -> ProgramGeneratorParameters {
+        ProgramGeneratorParameters {
+            max_instructions: 10,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 1,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 10.,
+                input_scales: None,
+                n_actions: 3,
+                n_inputs: 4,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        }
+    }
+
+    #[test]
+    fn given_population_of_clones_then_unique_count_is_one() {
+        let program: Program = GenerateEngine::generate(parameters());
+        let population = vec![program; 10];
+
+        let metrics = DiversityMetrics::compute::<IrisEngine>(&population);
+
+        assert_eq!(metrics.n_unique, 1);
+        assert_eq!(metrics.mean_edit_distance, 0.);
+        assert_eq!(metrics.fitness_entropy, 0.);
+    }
+
+    #[test]
+    fn given_sequences_when_edit_distance_then_levenshtein_distance_is_returned() {
+        assert_eq!(edit_distance(&[1, 2, 3], &[1, 2, 3]), 0);
+        assert_eq!(edit_distance(&[1, 2, 3], &[1, 3]), 1);
+        assert_eq!(edit_distance(&[1, 2, 3], &[4, 5]), 3);
+        assert_eq!(edit_distance::<usize>(&[], &[1, 2]), 2);
+    }
+
+    #[test]
+    fn given_duplicates_when_dedup_structural_then_duplicates_are_replaced() {
+        let program: Program = GenerateEngine::generate(parameters());
+        let mut population = vec![program; 10];
+
+        IrisEngine::dedup_structural(&mut population, parameters());
+
+        assert_eq!(population.len(), 10);
+        assert!(DiversityMetrics::compute::<IrisEngine>(&population).n_unique > 1);
+    }
+}
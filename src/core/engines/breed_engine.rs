@@ -1,8 +1,85 @@
+use std::{fmt, str::FromStr};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
 pub trait Breed<T>
 where
     T: Clone,
 {
     fn two_point_crossover(mate_1: &T, mate_2: &T) -> (T, T);
+
+    fn one_point_crossover(mate_1: &T, mate_2: &T) -> (T, T) {
+        Self::two_point_crossover(mate_1, mate_2)
+    }
+
+    /// Swaps each aligned pair of instructions with probability `swap_probability`.
+    fn uniform_crossover(mate_1: &T, mate_2: &T, _swap_probability: f64) -> (T, T) {
+        Self::two_point_crossover(mate_1, mate_2)
+    }
+
+    fn crossover(mate_1: &T, mate_2: &T, kind: CrossoverKind) -> (T, T) {
+        match kind {
+            CrossoverKind::OnePoint => Self::one_point_crossover(mate_1, mate_2),
+            CrossoverKind::TwoPoint => Self::two_point_crossover(mate_1, mate_2),
+            CrossoverKind::Uniform(p) => Self::uniform_crossover(mate_1, mate_2, p),
+        }
+    }
 }
 
 pub struct BreedEngine;
+
+/// Decides which of the two children produced by crossover enter the population.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum CrossoverChildPolicy {
+    /// Keep one child chosen uniformly at random.
+    #[default]
+    RandomOne,
+    /// Keep both children while crossover slots remain.
+    Both,
+    /// Keep the child scoring higher on a small probe set of trials.
+    BestOfPair,
+}
+
+/// How the instructions of two parents are recombined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossoverKind {
+    /// Exchanges the tails after one cut point in each parent.
+    OnePoint,
+    /// Exchanges one segment of each parent.
+    #[default]
+    TwoPoint,
+    /// Exchanges aligned instructions independently with the given probability.
+    Uniform(f64),
+}
+
+impl FromStr for CrossoverKind {
+    type Err = String;
+
+    /// Parses `one_point`, `two_point` or `uniform:<p>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "one_point" => Ok(CrossoverKind::OnePoint),
+            "two_point" => Ok(CrossoverKind::TwoPoint),
+            other => other
+                .strip_prefix("uniform:")
+                .and_then(|p| p.parse::<f64>().ok())
+                .filter(|p| (0. ..=1.).contains(p))
+                .map(CrossoverKind::Uniform)
+                .ok_or_else(|| format!("Unknown crossover `{}`", other)),
+        }
+    }
+}
+
+impl fmt::Display for CrossoverKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrossoverKind::OnePoint => write!(f, "one_point"),
+            CrossoverKind::TwoPoint => write!(f, "two_point"),
+            CrossoverKind::Uniform(p) => write!(f, "uniform:{}", p),
+        }
+    }
+}
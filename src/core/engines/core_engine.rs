@@ -1,16 +1,63 @@
-use std::{iter::repeat_with, sync::Arc};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt,
+    iter::repeat_with,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use clap::{Args, Parser};
 use derivative::Derivative;
 use itertools::Itertools;
 use rand::{seq::IteratorRandom, Rng};
 
+#[cfg(feature = "metrics-export")]
+use crate::utils::metrics::MetricsRecorder;
+#[cfg(feature = "serve")]
+use crate::utils::remote::RemoteFitness;
 use crate::{
     core::{
-        engines::{breed_engine::Breed, reset_engine::Reset},
-        environment::State,
+        adaptation::{AdaptiveRates, Variation},
+        characteristics::{Format, Genotype},
+        config_override::apply_overrides,
+        diversity::{fingerprint, DiversityMetrics},
+        engines::{
+            breed_engine::{Breed, CrossoverChildPolicy, CrossoverKind},
+            fitness_engine::{FitnessAggregation, Objective},
+            reset_engine::Reset,
+        },
+        environment::{BehaviorDescriptor, State},
+        hall_of_fame::{HallOfFame, HallOfFameConfig},
+        initialization::InitStrategy,
+        instruction_stats::InstructionStats,
+        lineage::LineageTracker,
+        niching::NichingConfig,
+        novelty::{mean_descriptor, NoveltyArchive, NoveltyConfig},
+        pareto::{non_dominated_fronts, pareto_order, Objectives},
+        program::ProgramShape,
+        provenance::Provenance,
+        schedule::Schedule,
+    },
+    problems::{
+        classification::{ClassWeights, FitnessMetric},
+        multi_task::MultiTaskConfig,
+        normalization::ObservationNormalization,
+        reward_shaping::RewardShaper,
+    },
+    utils::{
+        benchmark_tools::{CrossValidation, SummaryStatistics},
+        error::{require, ConfigViolation, LgpResult},
+        events::{Event, EventWriter},
+        random::{derive_seed, entropy_seed, generator, reseed, run_seed, with_seed, RngManager},
     },
-    utils::random::{generator, update_seed},
 };
 
 use super::{
@@ -19,9 +66,9 @@ use super::{
 };
 use derive_builder::Builder;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use tracing::info;
+use tracing::{error, info, warn};
 
-#[derive(Debug, Deserialize, Serialize, Builder, Copy, Derivative, Parser)]
+#[derive(Debug, Deserialize, Serialize, Builder, Derivative, Parser)]
 #[command(author, version, about, long_about=None)]
 #[command(propagate_version = true)]
 #[derivative(Clone)]
@@ -29,21 +76,29 @@ pub struct HyperParameters<C>
 where
     C: Core,
 {
+    /// Fitness of non-finite trial scores; see `fallback_fitness` for minimization.
     #[builder(default = "0.")]
     #[arg(long, default_value = "0.")]
     pub default_fitness: f64,
+    /// Whether higher (`maximize`) or lower (`minimize`) fitness is better.
+    #[builder(default = "Objective::default()")]
+    #[arg(long, value_enum, default_value = "maximize")]
+    #[serde(default)]
+    pub objective: Objective,
     #[builder(default = "100")]
     #[arg(long, default_value = "100")]
     pub population_size: usize,
-    #[builder(default = "0.5")]
+    /// Share of the population replaced every generation; like the percentages below, a
+    /// constant or a `Schedule` across generations, e.g. `linear:0.8:0.2`.
+    #[builder(default = "Schedule::Constant(0.5)", setter(into))]
     #[arg(long, default_value = "0.5")]
-    pub gap: f64,
-    #[builder(default = "0.5")]
+    pub gap: Schedule,
+    #[builder(default = "Schedule::Constant(0.5)", setter(into))]
     #[arg(long, default_value = "0.5")]
-    pub mutation_percent: f64,
-    #[builder(default = "0.5")]
+    pub mutation_percent: Schedule,
+    #[builder(default = "Schedule::Constant(0.5)", setter(into))]
     #[arg(long, default_value = "0.5")]
-    pub crossover_percent: f64,
+    pub crossover_percent: Schedule,
     #[builder(default = "100")]
     #[arg(long, default_value = "100")]
     pub n_generations: usize,
@@ -53,254 +108,3311 @@ where
     #[builder(default = "None")]
     #[arg(long)]
     pub seed: Option<u64>,
+    /// How the first generation is created: `random`, `ramped_half_and_half:<min>:<max>` or
+    /// `seeded:<path>[,<path>...]`, e.g. to continue from the `best.json` of an earlier run.
+    #[builder(default)]
+    #[arg(long, default_value = "random")]
+    #[serde(default)]
+    pub init_strategy: InitStrategy,
+    /// Log population diversity metrics every generation.
+    #[builder(default = "false")]
+    #[arg(long)]
+    #[serde(default)]
+    pub track_diversity: bool,
+    /// Count the operations, operand kinds and register writes of the population every
+    /// generation, in the event stream and `instruction_stats.json`.
+    #[builder(default = "false")]
+    #[arg(long)]
+    #[serde(default)]
+    pub track_instruction_stats: bool,
+    /// Keep each individual's per-trial scores alongside its fitness, in saved individuals and
+    /// as the mean within-individual trial variance reported every generation.
+    #[builder(default = "false")]
+    #[arg(long)]
+    #[serde(default)]
+    pub record_trial_scores: bool,
+    /// Track the parents every individual was bred from and save the ancestry of the final best
+    /// individual as `lineage.json`.
+    #[builder(default = "false")]
+    #[arg(long)]
+    #[serde(default)]
+    pub track_lineage: bool,
+    /// Generations a branch of the lineage is kept after its last individual died out.
+    #[builder(default = "0")]
+    #[arg(long, default_value = "0")]
+    #[serde(default)]
+    pub lineage_retention: usize,
+    /// Also save the ancestry as a Graphviz graph, `lineage.dot`.
+    #[builder(default = "false")]
+    #[arg(long)]
+    #[serde(default)]
+    pub lineage_dot: bool,
+    /// Fitness sharing applied to selection only; configured through config files.
+    #[builder(default = "None")]
+    #[arg(skip)]
+    #[serde(default)]
+    pub niching: Option<NichingConfig>,
+    /// Novelty search, blending behavioural novelty into selection (RL problems only);
+    /// configured through config files. The archive is saved as `novelty_archive.json`.
+    #[builder(default = "None")]
+    #[arg(skip)]
+    #[serde(default)]
+    pub novelty: Option<NoveltyConfig>,
+    /// Archive of the best individuals seen, re-evaluated on fixed validation trials;
+    /// configured through config files. Frozen entries are saved as `hall_of_fame.json`.
+    #[builder(default = "None")]
+    #[arg(skip)]
+    #[serde(default)]
+    pub hall_of_fame: Option<HallOfFameConfig>,
+    /// Optimizing parsimony alongside fitness switches selection to non-dominated sorting;
+    /// fitness sharing is not applied in that mode. Configured through config files.
+    #[builder(default = "Objectives::default()")]
+    #[arg(skip)]
+    #[serde(default)]
+    pub objectives: Objectives,
+    #[builder(default = "CrossoverChildPolicy::default()")]
+    #[arg(long, value_enum, default_value = "random_one")]
+    #[serde(default)]
+    pub crossover_child_policy: CrossoverChildPolicy,
+    /// How parents are recombined: `one_point`, `two_point` or `uniform:<p>`.
+    #[builder(default = "CrossoverKind::default()")]
+    #[arg(long, default_value = "two_point")]
+    #[serde(default)]
+    pub crossover: CrossoverKind,
+    /// Each individual carries its own mutation step size and operator weights, seeded from
+    /// `mutation_percent` and `crossover_percent` and perturbed whenever it is mutated.
+    #[builder(default = "false")]
+    #[arg(long)]
+    #[serde(default)]
+    pub adaptive_variation: bool,
+    /// Number of trials used to compare children under the `best_of_pair` policy.
+    #[builder(default = "1")]
+    #[arg(long, default_value = "1")]
+    #[serde(default = "default_probe_trials")]
+    pub probe_trials: usize,
+    /// How trial scores are combined into fitness: `mean`, `median`, `min` or `quantile:<q>`.
+    #[builder(default = "FitnessAggregation::default()")]
+    #[arg(long, default_value = "mean")]
+    #[serde(default)]
+    pub fitness_aggregation: FitnessAggregation,
+    /// Which trials are regenerated between generations: `fixed`, `fresh_each_generation` or
+    /// `rolling_replace:<k>`. The command line regenerates every generation by default.
+    #[builder(default = "TrialPolicy::default()")]
+    #[arg(long, default_value = "fresh_each_generation")]
+    #[serde(default)]
+    pub trial_policy: TrialPolicy,
+    /// `generational` or `steady_state:<replacements_per_generation>`.
+    #[builder(default = "ReplacementStrategy::default()")]
+    #[arg(long, default_value = "generational")]
+    #[serde(default)]
+    pub replacement: ReplacementStrategy,
+    /// Ends every episode after this many steps (RL problems only).
+    #[builder(default = "None")]
+    #[arg(long)]
+    #[serde(default)]
+    pub max_episode_steps: Option<usize>,
+    /// Reward shaping applied to every step: `none` or `potential:<scale>` (MountainCar only).
+    /// Evolution runs on the shaped rewards; `best_report.json` reports the unshaped return.
+    #[builder(default = "RewardShaper::default()")]
+    #[arg(long, default_value = "none")]
+    #[serde(default)]
+    pub reward_shaping: RewardShaper,
+    /// Normalization of the observations programs read (RL problems only); configured through
+    /// config files. Learned running statistics are saved as `inference_state.json`, whose
+    /// `normalization` can be configured here to replay a champion as it was evolved.
+    #[builder(default)]
+    #[arg(skip)]
+    #[serde(default)]
+    pub normalization: ObservationNormalization,
+    /// Replay the final champion and save its trajectory (RL problems only).
+    #[builder(default = "false")]
+    #[arg(long)]
+    #[serde(default)]
+    pub record_trajectory: bool,
+    /// CSV dataset (classification problems only); a cached download is used when unset.
+    #[builder(default = "None")]
+    #[arg(long)]
+    #[serde(default)]
+    pub dataset_path: Option<PathBuf>,
+    /// Score evolved against: `accuracy` or `macro_f1` (classification problems only).
+    #[builder(default = "FitnessMetric::default()")]
+    #[arg(long, value_enum, default_value = "accuracy")]
+    #[serde(default)]
+    pub fitness_metric: FitnessMetric,
+    /// Weight of a correct prediction of each class in the accuracy: `uniform`, `balanced`
+    /// (inverse class frequency) or one weight per class such as `1,9` (classification problems
+    /// only).
+    #[builder(default = "ClassWeights::default()")]
+    #[arg(long, default_value = "uniform")]
+    #[serde(default)]
+    pub class_weights: ClassWeights,
+    /// Evaluate every trial on a stratified sample of about this many rows, redrawn whenever
+    /// trials are (classification problems only).
+    #[builder(default = "None")]
+    #[arg(long)]
+    #[serde(default)]
+    pub max_samples_per_trial: Option<usize>,
+    /// Evaluate every trial on a bootstrap resample of this fraction of the rows, drawn with
+    /// replacement whenever trials are. Defaults to 1 with more than one trial, which would
+    /// otherwise all hold the whole dataset (classification problems only).
+    #[builder(default = "None")]
+    #[arg(long)]
+    #[serde(default)]
+    pub trial_fraction: Option<f64>,
+    /// Replay the final champion in a rendered window (RL problems only).
+    #[builder(default = "false")]
+    #[arg(long)]
+    #[serde(default)]
+    pub render: bool,
+    #[builder(default = "1")]
+    #[arg(long, default_value = "1")]
+    #[serde(default = "default_render_episodes")]
+    pub render_episodes: usize,
+    /// Cross-validate instead of running a single evolution (classification problems only);
+    /// configured through config files.
+    #[builder(default = "None")]
+    #[arg(skip)]
+    #[serde(default)]
+    pub cross_validation: Option<CrossValidation>,
+    /// Number of independent runs, each seeded with `seed + index`.
+    #[builder(default = "1")]
+    #[arg(long, default_value = "1")]
+    #[serde(default = "default_repeats")]
+    pub repeats: usize,
+    /// Individuals kept by survival even when invalid, so an all-invalid population still
+    /// yields parents. With `0`, such a run ends instead.
+    #[builder(default = "1")]
+    #[arg(long, default_value = "1")]
+    #[serde(default = "default_min_survivors")]
+    pub min_survivors: usize,
+    /// Save the frozen champion and a fitness summary under `gen_<n>` every `n` generations.
+    #[builder(default = "None")]
+    #[arg(long)]
+    #[serde(default)]
+    pub snapshot_interval: Option<usize>,
+    /// Stream every individual of every generation to `population.jsonl.gz` while running.
+    #[builder(default = "false")]
+    #[arg(long)]
+    #[serde(default)]
+    pub save_full_population: bool,
+    /// Also save a simplified copy of the final champion as `best_simplified`, checked on the
+    /// trial data (classification problems only).
+    #[builder(default = "false")]
+    #[arg(long)]
+    #[serde(default)]
+    pub export_simplified: bool,
+    /// Format of the saved individuals (`best`, `median`, `worst`, `champions`, `pareto` and
+    /// snapshots); parameters and metrics are always saved as text.
+    #[builder(default)]
+    #[arg(long, value_enum, default_value = "json")]
+    #[serde(default)]
+    pub output_format: Format,
+    /// Base URLs of `lgp serve-eval` workers (e.g. `http://10.0.0.2:8080`) to evaluate the
+    /// population on. Requires the `serve` feature and a `seed`, from which workers regenerate
+    /// the trials; trials replaced by observers such as `RegenerateTrials` are not seen by
+    /// workers, so refresh trials through `trial_policy` instead.
+    #[builder(default)]
+    #[arg(long = "eval-worker", value_name = "URL")]
+    #[serde(default)]
+    pub eval_workers: Vec<String>,
+    /// Abandon the evaluation of an individual after this many milliseconds, assigning it
+    /// `default_fitness`. Requires a `seed`: evaluations run on worker threads which regenerate
+    /// the trials from their seeds, with the same caveat as `eval_workers`. Threads cannot be
+    /// stopped, so an abandoned evaluation keeps its thread until it returns on its own; an
+    /// individual that never halts leaks one thread for the rest of the process.
+    #[builder(default = "None")]
+    #[arg(long)]
+    #[serde(default)]
+    pub eval_timeout_ms: Option<u64>,
+    /// Stop the run once it has taken this long (e.g. `2h30m`), after the generation in
+    /// progress; outputs are saved as usual and the manifest records the stop.
+    #[builder(default = "None")]
+    #[arg(long, value_parser = humantime::parse_duration)]
+    #[serde(default, with = "humantime_duration")]
+    pub max_wall_time: Option<Duration>,
+    /// Warn, with the time spent evaluating and varying, when a generation takes longer than
+    /// this many milliseconds.
+    #[builder(default = "None")]
+    #[arg(long)]
+    #[serde(default)]
+    pub soft_deadline_ms: Option<u64>,
+    /// Append a JSONL stream of run and generation events to this file.
+    #[builder(default = "None")]
+    #[arg(long)]
+    #[serde(default)]
+    pub events_file: Option<PathBuf>,
+    /// Tasks of a `MultiTaskEngine` run, each a name, a weight and overrides of the
+    /// hyperparameters its trials are generated from, and how their fitness is combined;
+    /// configured through config files.
+    #[builder(default = "None")]
+    #[arg(skip)]
+    #[serde(default)]
+    pub multi_task: Option<MultiTaskConfig>,
+    /// `key=value` overrides of any field, including nested ones (e.g.
+    /// `program_parameters.max_instructions=50`), resolved by `apply_overrides`.
+    #[builder(default)]
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    #[serde(skip)]
+    pub overrides: Vec<String>,
     #[command(flatten)]
     pub program_parameters: C::ProgramParameters,
 }
 
-pub struct CoreIter<C>
+/// (De)serializes optional durations as humantime strings such as `2h30m`.
+mod humantime_duration {
+    use std::time::Duration;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match duration {
+            Some(duration) => {
+                serializer.serialize_str(&humantime::format_duration(*duration).to_string())
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|duration| humantime::parse_duration(&duration).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+fn default_probe_trials() -> usize {
+    1
+}
+
+fn default_repeats() -> usize {
+    1
+}
+
+fn default_render_episodes() -> usize {
+    1
+}
+
+fn default_min_survivors() -> usize {
+    1
+}
+
+/// Why a run ended before reaching `n_generations`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunError {
+    /// No individual survived selection, leaving no parents for variation.
+    EmptyPopulation { generation: usize },
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::EmptyPopulation { generation } => write!(
+                f,
+                "No individual survived generation {}; set `default_fitness` or `min_survivors` \
+                 to keep invalid individuals.",
+                generation
+            ),
+        }
+    }
+}
+
+impl Error for RunError {}
+
+/// Why a run stopped before `n_generations`, without failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// `max_wall_time` elapsed.
+    WallTime,
+    /// The stop flag was set, e.g. by SIGINT.
+    Interrupted,
+}
+
+/// Why the evaluation of an individual was abandoned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalFailure {
+    Panicked,
+    TimedOut,
+}
+
+impl fmt::Display for EvalFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalFailure::Panicked => write!(f, "Evaluation panicked"),
+            EvalFailure::TimedOut => write!(f, "Evaluation timed out"),
+        }
+    }
+}
+
+/// Seed of a single trial, derived from the run seed so trials can be regenerated exactly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TrialSeed(pub u64);
+
+/// Decides which trials are regenerated between generations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrialPolicy {
+    /// Evaluate every generation on the trials generated at construction.
+    #[default]
+    Fixed,
+    /// Replace every trial after each generation.
+    FreshEachGeneration,
+    /// Replace `k` trials after each generation, oldest first.
+    RollingReplace { k: usize },
+}
+
+impl TrialPolicy {
+    fn n_replacements(&self, n_trials: usize) -> usize {
+        match *self {
+            TrialPolicy::Fixed => 0,
+            TrialPolicy::FreshEachGeneration => n_trials,
+            TrialPolicy::RollingReplace { k } => k.min(n_trials),
+        }
+    }
+}
+
+impl FromStr for TrialPolicy {
+    type Err = String;
+
+    /// Parses `fixed`, `fresh_each_generation` or `rolling_replace:<k>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "fixed" => Ok(TrialPolicy::Fixed),
+            "fresh_each_generation" => Ok(TrialPolicy::FreshEachGeneration),
+            other => other
+                .strip_prefix("rolling_replace:")
+                .and_then(|k| k.parse::<usize>().ok())
+                .map(|k| TrialPolicy::RollingReplace { k })
+                .ok_or_else(|| format!("Unknown trial policy `{}`", other)),
+        }
+    }
+}
+
+impl fmt::Display for TrialPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrialPolicy::Fixed => write!(f, "fixed"),
+            TrialPolicy::FreshEachGeneration => write!(f, "fresh_each_generation"),
+            TrialPolicy::RollingReplace { k } => write!(f, "rolling_replace:{}", k),
+        }
+    }
+}
+
+/// How offspring enter the population.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplacementStrategy {
+    /// Rank, cut by `gap` and refill the whole population every generation.
+    #[default]
+    Generational,
+    /// Produce, evaluate and insert one offspring at a time, each replacing the worst
+    /// individual. A generation spans `replacements_per_generation` offspring; only they are
+    /// evaluated, and `gap`, niching, Pareto ranking and the crossover child policy are unused.
+    SteadyState { replacements_per_generation: usize },
+}
+
+impl FromStr for ReplacementStrategy {
+    type Err = String;
+
+    /// Parses `generational` or `steady_state:<replacements_per_generation>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "generational" => Ok(ReplacementStrategy::Generational),
+            other => other
+                .strip_prefix("steady_state:")
+                .and_then(|n| n.parse::<usize>().ok())
+                .map(
+                    |replacements_per_generation| ReplacementStrategy::SteadyState {
+                        replacements_per_generation,
+                    },
+                )
+                .ok_or_else(|| format!("Unknown replacement strategy `{}`", other)),
+        }
+    }
+}
+
+impl fmt::Display for ReplacementStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplacementStrategy::Generational => write!(f, "generational"),
+            ReplacementStrategy::SteadyState {
+                replacements_per_generation,
+            } => write!(f, "steady_state:{}", replacements_per_generation),
+        }
+    }
+}
+
+/// Number of offspring produced by each operator during one round of variation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VariationCounts {
+    pub n_crossover: usize,
+    pub n_mutation: usize,
+    pub n_clone: usize,
+    /// Evaluations spent comparing crossover children under the `best_of_pair` policy.
+    pub n_probe_evaluations: usize,
+    /// Offspring the operator percentages (or adaptive rates) asked of each operator.
+    #[serde(default)]
+    pub requested: OperatorCounts,
+}
+
+/// Values of the scheduled hyperparameters in effect during one generation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EffectiveRates {
+    pub gap: f64,
+    pub mutation_percent: f64,
+    pub crossover_percent: f64,
+}
+
+/// Fitness on one task of a multi-task run over the individuals evaluated in a generation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TaskMetrics {
+    pub task: usize,
+    pub mean: f64,
+    pub best: f64,
+}
+
+/// Number of offspring per variation operator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperatorCounts {
+    pub n_crossover: usize,
+    pub n_mutation: usize,
+    pub n_clone: usize,
+}
+
+/// Hooks into every generation of a `CoreIter`, e.g. to drive progress bars or to change the
+/// trials between generations. All callbacks default to doing nothing.
+pub trait GenerationObserver<C>
 where
     C: Core,
 {
-    generation: usize,
-    next_population: Vec<C::Individual>,
-    params: HyperParameters<C>,
-    trials: Vec<C::State>,
+    /// Called once per generation with the evaluated population, best first.
+    fn on_generation(&mut self, _generation: usize, _ranked: &[C::Individual]) {}
+
+    /// Called once per generation after offspring have been produced.
+    fn on_variation(&mut self, _offspring_counts: VariationCounts) {}
+
+    /// Called at the end of every generation; the trials are used to evaluate the next one.
+    fn update_trials(
+        &mut self,
+        _generation: usize,
+        _trials: &mut Vec<C::State>,
+        _state_parameters: &C::StateParameters,
+    ) {
+    }
 }
 
-impl<C> CoreIter<C>
+/// Draws fresh trials every generation instead of evaluating on a fixed set.
+pub struct RegenerateTrials;
+
+impl<C> GenerationObserver<C> for RegenerateTrials
 where
     C: Core,
 {
-    pub fn new(hp: HyperParameters<C>) -> Self {
-        let current_population = C::init_population(hp.program_parameters, hp.population_size);
-        let trials: Vec<C::State> = repeat_with(|| C::Generate::generate(()))
-            .take(hp.n_trials)
-            .collect_vec();
-
-        Self {
-            generation: 0,
-            next_population: current_population,
-            params: hp,
-            trials,
+    fn update_trials(
+        &mut self,
+        _generation: usize,
+        trials: &mut Vec<C::State>,
+        state_parameters: &C::StateParameters,
+    ) {
+        for trial in trials.iter_mut() {
+            *trial = C::Generate::generate(state_parameters.clone());
         }
     }
 }
 
-impl<C> Iterator for CoreIter<C>
+pub struct CoreIter<C>
 where
     C: Core,
 {
-    type Item = Vec<C::Individual>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.generation > self.params.n_generations {
-            return None;
-        }
+    generation: usize,
+    n_evaluations: usize,
+    next_population: Vec<C::Individual>,
+    params: HyperParameters<C>,
+    state_parameters: C::StateParameters,
+    trials: Vec<C::State>,
+    /// Seed of each trial, when the run is seeded.
+    trial_seeds: Vec<Option<TrialSeed>>,
+    /// Task each trial is drawn from.
+    trial_tasks: Vec<usize>,
+    #[cfg(feature = "serve")]
+    remote: Option<RemoteFitness>,
+    cancellation: Option<Arc<AtomicBool>>,
+    stop_flag: Option<Arc<AtomicBool>>,
+    events: Option<EventWriter>,
+    #[cfg(feature = "metrics-export")]
+    metrics: Option<MetricsRecorder>,
+    /// Only set when events are written or time limits are configured, since clocks are
+    /// unavailable on `wasm32-unknown-unknown`.
+    started: Option<Instant>,
+    observers: Vec<Box<dyn GenerationObserver<C>>>,
+    /// Index of the oldest trial, replaced first under `TrialPolicy::RollingReplace`.
+    next_replacement: usize,
+    failure: Option<RunError>,
+    /// Individuals of the last evaluated generation whose registers were saturated.
+    n_saturated: usize,
+    /// Evaluations that panicked or timed out so far.
+    n_eval_failures: usize,
+    /// Behaviour descriptors of the last evaluated individuals, collected under novelty search.
+    descriptors: Vec<Vec<f64>>,
+    /// Fitness on every task of the individuals evaluated this generation, in multi-task runs.
+    task_fitness: Vec<Vec<f64>>,
+    /// Metrics of every task over the last generation, in multi-task runs.
+    task_metrics: Vec<TaskMetrics>,
+    /// Scheduled hyperparameters in effect during the current generation.
+    rates: EffectiveRates,
+    novelty_archive: NoveltyArchive,
+    hall_of_fame: Option<HallOfFame<C::Individual>>,
+    /// Fixed trials hall of fame candidates are evaluated on.
+    validation_trials: Vec<C::State>,
+    lineage: Option<LineageTracker>,
+    stop_reason: Option<StopReason>,
+}
 
-        let mut population = self.next_population.clone();
+/// Time spent in the phases of a generation, measured when the clock runs.
+#[derive(Debug, Clone, Copy, Default)]
+struct PhaseTimes {
+    eval_ms: u64,
+    variation_ms: u64,
+}
 
-        C::eval_fitness(
-            &mut population,
-            &mut self.trials,
-            self.params.default_fitness,
-        );
-        C::rank(&mut population);
+/// Mutation and crossover percentages clamped to [0, 1] and scaled down to sum to at most 1; the
+/// remaining share of offspring are clones.
+pub fn operator_percents(mutation_percent: f64, crossover_percent: f64) -> (f64, f64) {
+    let mutation_percent = mutation_percent.max(0.).min(1.);
+    let crossover_percent = crossover_percent.max(0.).min(1.);
+    let total = mutation_percent + crossover_percent;
 
-        assert!(population.iter().all(C::Status::evaluated));
+    if total > 1. {
+        (mutation_percent / total, crossover_percent / total)
+    } else {
+        (mutation_percent, crossover_percent)
+    }
+}
 
-        info!(
-            best = serde_json::to_string(&population.first()).unwrap(),
-            median = serde_json::to_string(&population.get(population.len() / 2)).unwrap(),
-            worst = serde_json::to_string(&population.last()).unwrap(),
-            generation = serde_json::to_string(&self.generation).unwrap()
-        );
+/// Mean over `population` of the variance of each individual's recorded trial scores, or `None`
+/// when no individual recorded any.
+pub fn mean_trial_variance<C>(population: &[C::Individual]) -> Option<f64>
+where
+    C: Core,
+{
+    let variances = population
+        .iter()
+        .filter_map(C::Status::trial_scores)
+        .filter(|scores| !scores.is_empty())
+        .map(|scores| {
+            let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+            scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / scores.len() as f64
+        })
+        .collect_vec();
 
-        let mut new_population = population.clone();
+    (!variances.is_empty()).then(|| variances.iter().sum::<f64>() / variances.len() as f64)
+}
 
-        C::survive(&mut new_population, self.params.gap);
-        C::variation(
-            &mut new_population,
-            self.params.crossover_percent,
-            self.params.mutation_percent,
-            self.params.program_parameters,
-        );
+fn elapsed_ms(since: Option<Instant>) -> u64 {
+    since.map_or(0, |since| since.elapsed().as_millis() as u64)
+}
 
-        self.next_population = new_population;
-        self.generation += 1;
+/// Generates the trial of `slot` after `round` refreshes, seeded explicitly when the run is.
+fn generate_trial<C>(
+    hp: &HyperParameters<C>,
+    state_parameters: &C::StateParameters,
+    round: usize,
+    slot: usize,
+) -> C::State
+where
+    C: Core,
+{
+    let state_parameters =
+        C::task_parameters(state_parameters, task_of::<C>(state_parameters, slot));
 
-        return Some(population);
+    match hp.trial_seed(round, slot) {
+        Some(seed) => seeded_trial::<C>(&state_parameters, seed),
+        None => C::Generate::generate(state_parameters),
     }
 }
 
-impl<T> HyperParameters<T>
+/// Task the trial in `slot` is drawn from.
+pub fn task_of<C>(state_parameters: &C::StateParameters, slot: usize) -> usize
 where
-    T: Core,
+    C: Core,
 {
-    pub fn build_engine(&self) -> CoreIter<T> {
-        update_seed(self.seed);
-        CoreIter::new(self.clone())
-    }
+    slot % C::n_tasks(state_parameters)
 }
 
-pub trait Core {
-    type Individual: Ord + Clone + Send + Sync + Serialize + DeserializeOwned;
-    type ProgramParameters: Copy + Send + Sync + Clone + Serialize + DeserializeOwned + Args;
-    type State: State;
-    type FitnessMarker;
-    type Generate: Generate<Self::ProgramParameters, Self::Individual> + Generate<(), Self::State>;
-    type Fitness: Fitness<Self::Individual, Self::State, Self::FitnessMarker>;
-    type Reset: Reset<Self::Individual> + Reset<Self::State>;
-    type Breed: Breed<Self::Individual>;
-    type Mutate: Mutate<Self::ProgramParameters, Self::Individual>;
-    type Status: Status<Self::Individual>;
-    type Freeze: Freeze<Self::Individual>;
+/// Fitness on every task, in task order, from the scores of the trials of each in `tasks`.
+fn task_fitness(
+    scores: &[f64],
+    tasks: &[usize],
+    n_tasks: usize,
+    aggregation: FitnessAggregation,
+    default_fitness: f64,
+) -> Vec<f64> {
+    (0..n_tasks)
+        .map(|task| {
+            let task_scores = scores
+                .iter()
+                .zip(tasks)
+                .filter(|(_, trial_task)| **trial_task == task)
+                .map(|(score, _)| *score)
+                .collect_vec();
 
-    fn init_population(
-        program_parameters: Self::ProgramParameters,
-        population_size: usize,
-    ) -> Vec<Self::Individual> {
-        let population = repeat_with(|| Self::Generate::generate(program_parameters))
-            .take(population_size)
-            .collect();
+            aggregation.aggregate(&task_scores, default_fitness)
+        })
+        .collect()
+}
 
-        population
-    }
+/// Generates the trial identified by `seed`. Whatever the generation draws from the shared
+/// generator is seeded too, so the trial can be regenerated anywhere, e.g. on a remote worker.
+pub fn seeded_trial<C>(state_parameters: &C::StateParameters, seed: TrialSeed) -> C::State
+where
+    C: Core,
+{
+    with_seed(seed.0, || {
+        C::Generate::generate(C::seed_trial(state_parameters, seed))
+    })
+}
 
-    fn eval_fitness(
-        population: &mut Vec<Self::Individual>,
-        trials: &mut Vec<Self::State>,
-        default_fitness: f64,
-    ) {
-        for individual in population.iter_mut() {
-            let mut scores = trials
+/// Raw scores of `individual` on every trial, or why its evaluation was abandoned.
+///
+/// Without a timeout, `trials` are evaluated on the calling thread and panics are caught. With
+/// one, a worker thread evaluates a copy of the individual on the trials regenerated from
+/// `seeds` and `tasks`, each with the generator seeded by its trial; a worker that runs past the
+/// timeout is left to finish in the background.
+fn guarded_trial_scores<C>(
+    individual: &mut C::Individual,
+    trials: &mut [C::State],
+    seeds: &[Option<TrialSeed>],
+    tasks: &[usize],
+    state_parameters: &C::StateParameters,
+    timeout: Option<Duration>,
+) -> Result<Vec<f64>, EvalFailure>
+where
+    C: Core,
+{
+    let Some(timeout) = timeout else {
+        let evaluate = || {
+            trials
                 .iter_mut()
                 .map(|trial| {
-                    Self::Reset::reset(individual);
-                    Self::Reset::reset(trial);
-                    Self::Fitness::eval_fitness(individual, trial)
+                    C::Reset::reset(individual);
+                    C::Reset::reset(trial);
+                    C::Fitness::eval_fitness(individual, trial)
                 })
-                .collect_vec();
+                .collect_vec()
+        };
 
-            let n_trials = scores.len();
-            scores = scores
+        return catch_unwind(AssertUnwindSafe(evaluate)).map_err(|_| EvalFailure::Panicked);
+    };
+
+    let trial_parameters = seeds
+        .iter()
+        .zip(tasks)
+        .map(|(seed, task)| {
+            let seed = seed.expect("Timeouts require a seeded run.");
+            (seed, C::task_parameters(state_parameters, *task))
+        })
+        .collect_vec();
+    let mut evaluated = individual.clone();
+    let rng = RngManager::current();
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        // Keeps the run seed of the caller, e.g. for exploration seeds derived from it.
+        let scores = rng.enter(0, || {
+            trial_parameters
                 .into_iter()
-                .map(|s| if !s.is_finite() { default_fitness } else { s })
-                .collect_vec();
-            let average = scores.into_iter().sum::<f64>() / n_trials as f64;
-            Self::Status::set_fitness(individual, average);
+                .map(|(seed, state_parameters)| {
+                    with_seed(seed.0, || {
+                        let mut trial =
+                            C::Generate::generate(C::seed_trial(&state_parameters, seed));
+                        C::Reset::reset(&mut evaluated);
+                        C::Reset::reset(&mut trial);
+                        C::Fitness::eval_fitness(&mut evaluated, &mut trial)
+                    })
+                })
+                .collect_vec()
+        });
+
+        // The receiver is gone once the evaluation timed out.
+        let _ = sender.send((evaluated, scores));
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok((evaluated, scores)) => {
+            *individual = evaluated;
+            Ok(scores)
         }
+        // A panicking worker drops the sender without sending.
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(EvalFailure::Panicked),
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(EvalFailure::TimedOut),
     }
+}
 
-    fn rank(population: &mut Vec<Self::Individual>) {
-        population.sort_by(|a, b| b.cmp(a));
-        debug_assert!(population.windows(2).all(|w| {
-            let a = &w[0];
-            let b = &w[1];
+impl<C> CoreIter<C>
+where
+    C: Core,
+{
+    pub fn new(hp: HyperParameters<C>, state_parameters: C::StateParameters) -> Self {
+        let population = hp
+            .init_strategy
+            .population(&hp)
+            .expect("Failed to initialize the population.");
 
-            debug_assert!(a >= b);
-            a >= b
-        }));
+        Self::with_population(hp, state_parameters, population)
     }
 
-    fn survive(population: &mut Vec<Self::Individual>, gap: f64) {
-        let n_individuals = population.len();
+    /// Starts from `population` instead of the one `init_strategy` creates.
+    pub fn with_population(
+        hp: HyperParameters<C>,
+        state_parameters: C::StateParameters,
+        mut current_population: Vec<C::Individual>,
+    ) -> Self {
+        let rates = hp.rates(0);
+        let (mutation_percent, crossover_percent) =
+            operator_percents(rates.mutation_percent, rates.crossover_percent);
+        if (mutation_percent, crossover_percent)
+            != (rates.mutation_percent, rates.crossover_percent)
+        {
+            warn!(
+                "Mutation and crossover percentages of {} and {} are out of range; \
+                 using {} and {}.",
+                rates.mutation_percent,
+                rates.crossover_percent,
+                mutation_percent,
+                crossover_percent
+            );
+        }
+        if hp.replacement == ReplacementStrategy::Generational
+            && (rates.gap * hp.population_size as f64).floor() < 1.
+        {
+            warn!(
+                "A gap of {} replaces none of {} individuals, so no offspring are bred.",
+                rates.gap, hp.population_size
+            );
+        }
 
-        let mut n_of_individuals_to_drop =
-            (n_individuals as isize) - ((1.0 - gap) * (n_individuals as f64)).floor() as isize;
+        if hp.adaptive_variation {
+            let rates = AdaptiveRates::new(rates.mutation_percent, rates.crossover_percent);
+            for individual in current_population.iter_mut() {
+                individual.set_adaptive_rates(rates);
+            }
+        }
+        let trials = (0..hp.n_trials)
+            .map(|slot| generate_trial(&hp, &state_parameters, 0, slot))
+            .collect_vec();
+        let trial_seeds = (0..hp.n_trials)
+            .map(|slot| hp.trial_seed(0, slot))
+            .collect_vec();
+        let trial_tasks = (0..hp.n_trials)
+            .map(|slot| task_of::<C>(&state_parameters, slot))
+            .collect_vec();
+        #[cfg(feature = "serve")]
+        let remote = (!hp.eval_workers.is_empty()).then(|| {
+            RemoteFitness::new(hp.eval_workers.clone()).expect("Failed to create the HTTP client.")
+        });
 
-        population.retain(Self::Status::valid);
-        let n_individuals_dropped = n_individuals - population.len();
-        n_of_individuals_to_drop -= n_individuals_dropped as isize;
+        let events = hp.events_file.as_ref().map(|path| {
+            let mut events = EventWriter::open(path).expect("Failed to open events file.");
+            events
+                .write(&Event::RunStarted {
+                    seed: run_seed(),
+                    config: serde_json::to_value(&hp).unwrap(),
+                })
+                .expect("Failed to write event.");
+            events
+        });
+        let started =
+            (events.is_some() || hp.max_wall_time.is_some() || hp.soft_deadline_ms.is_some())
+                .then(Instant::now);
+        let validation_trials = hp
+            .hall_of_fame
+            .map(|config| {
+                (0..config.n_validation_trials)
+                    .map(|slot| {
+                        let seed = TrialSeed(derive_seed(config.validation_seed, slot as u128));
+                        let task = task_of::<C>(&state_parameters, slot);
+                        seeded_trial::<C>(&C::task_parameters(&state_parameters, task), seed)
+                    })
+                    .collect_vec()
+            })
+            .unwrap_or_default();
+        let lineage = hp
+            .track_lineage
+            .then(|| LineageTracker::new(hp.lineage_retention));
+        let hall_of_fame = hp
+            .hall_of_fame
+            .map(|config| HallOfFame::new(config.capacity).with_objective(hp.objective));
 
-        while n_of_individuals_to_drop > 0 {
-            n_of_individuals_to_drop -= 1;
-            population.pop();
+        Self {
+            generation: 0,
+            n_evaluations: 0,
+            next_population: current_population,
+            params: hp,
+            state_parameters,
+            trials,
+            trial_seeds,
+            trial_tasks,
+            #[cfg(feature = "serve")]
+            remote,
+            cancellation: None,
+            stop_flag: None,
+            events,
+            #[cfg(feature = "metrics-export")]
+            metrics: None,
+            started,
+            observers: vec![],
+            next_replacement: 0,
+            failure: None,
+            n_saturated: 0,
+            n_eval_failures: 0,
+            descriptors: vec![],
+            task_fitness: vec![],
+            task_metrics: vec![],
+            rates,
+            novelty_archive: NoveltyArchive::default(),
+            hall_of_fame,
+            validation_trials,
+            lineage,
+            stop_reason: None,
         }
     }
 
-    fn variation(
-        population: &mut Vec<Self::Individual>,
-        crossover_percent: f64,
-        mutation_percent: f64,
-        program_parameters: Self::ProgramParameters,
-    ) {
-        debug_assert!(population.len() > 0);
+    /// Trials the next generation is evaluated on.
+    pub fn trials(&self) -> &[C::State] {
+        &self.trials
+    }
+
+    /// Seeds of the trials the next generation is evaluated on, when the run is seeded.
+    pub fn trial_seeds(&self) -> &[Option<TrialSeed>] {
+        &self.trial_seeds
+    }
 
-        let pop_cap = population.capacity();
-        let pop_len = population.len();
+    /// Task each trial is drawn from.
+    pub fn trial_tasks(&self) -> &[usize] {
+        &self.trial_tasks
+    }
 
-        let remaining_pool_spots = pop_cap - pop_len;
+    /// Fitness on every task over the individuals evaluated in the last generation; empty
+    /// unless the run has several tasks.
+    pub fn task_metrics(&self) -> &[TaskMetrics] {
+        &self.task_metrics
+    }
 
-        if remaining_pool_spots == 0 {
+    /// Values of `gap`, `mutation_percent` and `crossover_percent` in the last generation.
+    pub fn rates(&self) -> EffectiveRates {
+        self.rates
+    }
+
+    /// Regenerates trials according to the trial policy. With a seed, the trials drawn after
+    /// each generation depend only on the seed and the generation.
+    fn refresh_trials(&mut self) {
+        let n_trials = self.trials.len();
+        let n_replacements = self.params.trial_policy.n_replacements(n_trials);
+
+        if n_replacements == 0 {
             return;
         }
 
-        let n_mutations = (remaining_pool_spots as f64 * mutation_percent).floor() as usize;
-        let n_crossovers = (remaining_pool_spots as f64 * crossover_percent).floor() as usize;
-        let n_clones = remaining_pool_spots - n_mutations - n_crossovers;
+        let round = self.generation + 1;
+        let generate = || {
+            (0..n_replacements)
+                .map(|offset| {
+                    let slot = (self.next_replacement + offset) % n_trials;
+                    generate_trial(&self.params, &self.state_parameters, round, slot)
+                })
+                .collect_vec()
+        };
 
-        let mut clone_offspring: Vec<Self::Individual> = Vec::with_capacity(n_clones);
-        let mut mutation_offspring: Vec<Self::Individual> = Vec::with_capacity(n_mutations);
-        let mut crossover_offspring: Vec<Self::Individual> = Vec::with_capacity(n_crossovers);
+        let fresh = match self.params.seed {
+            Some(seed) => with_seed(derive_seed(seed, self.generation as u128), generate),
+            None => generate(),
+        };
 
-        debug_assert!(n_mutations + n_crossovers <= remaining_pool_spots);
+        for (offset, trial) in fresh.into_iter().enumerate() {
+            let slot = (self.next_replacement + offset) % n_trials;
+            self.trials[slot] = trial;
+            self.trial_seeds[slot] = self.params.trial_seed(round, slot);
+        }
 
-        let rc_population = Arc::new(population.clone());
+        self.next_replacement = (self.next_replacement + n_replacements) % n_trials;
+    }
+
+    /// Evaluates `population` on the current trials, keeping the scores on the individuals under
+    /// `record_trial_scores`.
+    fn eval_trial_scores(&mut self, population: &mut [C::Individual]) -> Vec<Vec<f64>> {
+        let behaviours = self.eval_scores(population);
+
+        if self.params.record_trial_scores {
+            for (individual, scores) in population.iter_mut().zip(&behaviours) {
+                C::Status::set_trial_scores(individual, scores.clone());
+            }
+        }
+
+        behaviours
+    }
+
+    /// Evaluates `population` on the current trials, on the remote workers when configured.
+    /// Workers regenerate trials without their tasks, so multi-task runs evaluate locally.
+    fn eval_scores(&mut self, population: &mut [C::Individual]) -> Vec<Vec<f64>> {
+        let n_tasks = C::n_tasks(&self.state_parameters);
+
+        #[cfg(feature = "serve")]
+        if let Some(remote) = self.remote.as_ref().filter(|_| n_tasks == 1) {
+            let seeds = self.trial_seeds.iter().copied().collect::<Option<Vec<_>>>();
+
+            if let Some(seeds) = seeds {
+                return remote.eval_trial_scores::<C>(
+                    population,
+                    &mut self.trials,
+                    &seeds,
+                    self.params.fallback_fitness(),
+                    self.params.fitness_aggregation,
+                );
+            }
+        }
+
+        let timeout = self.params.eval_timeout_ms.map(Duration::from_millis);
+        let default_fitness = self.params.fallback_fitness();
+        let mut behaviours = Vec::with_capacity(population.len());
+        self.descriptors.clear();
+
+        for individual in population.iter_mut() {
+            let scores = match guarded_trial_scores::<C>(
+                individual,
+                &mut self.trials,
+                &self.trial_seeds,
+                &self.trial_tasks,
+                &self.state_parameters,
+                timeout,
+            ) {
+                Ok(scores) => scores
+                    .into_iter()
+                    .map(|s| if !s.is_finite() { default_fitness } else { s })
+                    .collect_vec(),
+                Err(failure) => {
+                    self.n_eval_failures += 1;
+                    warn!(
+                        id = serde_json::to_string(&individual.id()).unwrap(),
+                        generation = serde_json::to_string(&self.generation).unwrap(),
+                        "{}; assigning the default fitness.",
+                        failure
+                    );
+
+                    vec![default_fitness; self.trials.len()]
+                }
+            };
+
+            let task_fitness = task_fitness(
+                &scores,
+                &self.trial_tasks,
+                n_tasks,
+                self.params.fitness_aggregation,
+                default_fitness,
+            );
+            let fitness =
+                C::aggregate_tasks(&self.state_parameters, &task_fitness, self.params.objective);
+            C::Status::set_fitness(individual, fitness);
+            behaviours.push(scores);
+            if n_tasks > 1 {
+                self.task_fitness.push(task_fitness);
+            }
+
+            // Trials are left as the individual played them.
+            if self.params.novelty.is_some() {
+                let descriptors = self
+                    .trials
+                    .iter()
+                    .filter_map(C::State::behavior_descriptor)
+                    .collect_vec();
+                self.descriptors.push(mean_descriptor(&descriptors));
+            }
+        }
+
+        behaviours
+    }
+
+    /// Registers an observer; observers are notified in registration order.
+    pub fn with_observer(mut self, observer: Box<dyn GenerationObserver<C>>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Stops the iterator before the next generation once `flag` is set, allowing runs on
+    /// background threads to be cancelled cooperatively.
+    pub fn with_cancellation(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancellation = Some(flag);
+        self
+    }
+
+    /// Records the metrics of every generation for `MetricsExporter` under the recorder's labels.
+    #[cfg(feature = "metrics-export")]
+    pub fn with_metrics(mut self, recorder: MetricsRecorder) -> Self {
+        self.metrics = Some(recorder);
+        self
+    }
+
+    /// Like `with_cancellation`, but the stop is recorded as `StopReason::Interrupted` so
+    /// runners can save the generations completed so far as an interrupted run.
+    pub fn with_stop_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.stop_flag = Some(flag);
+        self
+    }
+
+    /// Why the run ended early, if it did.
+    pub fn failure(&self) -> Option<RunError> {
+        self.failure
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .map(|flag| flag.load(Ordering::Relaxed))
+            .unwrap_or(false)
+    }
+
+    /// Index of the next generation to be evaluated.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn state_parameters(&self) -> &C::StateParameters {
+        &self.state_parameters
+    }
+
+    /// Evaluations that panicked or timed out so far; their individuals got `default_fitness`.
+    pub fn n_eval_failures(&self) -> usize {
+        self.n_eval_failures
+    }
+
+    /// Number of (individual, trial) evaluations performed so far, including crossover probes.
+    pub fn n_evaluations(&self) -> usize {
+        self.n_evaluations
+    }
+
+    /// Individuals of the last generation that hit register saturation during evaluation.
+    pub fn n_saturated(&self) -> usize {
+        self.n_saturated
+    }
+
+    /// Behaviour descriptors archived so far under novelty search.
+    pub fn novelty_archive(&self) -> &NoveltyArchive {
+        &self.novelty_archive
+    }
+
+    /// Why the run stopped early without failing, if it did.
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    /// The current time, when the clock runs.
+    fn clock(&self) -> Option<Instant> {
+        self.started.map(|_| Instant::now())
+    }
+
+    /// Best individuals seen so far, when a hall of fame is configured.
+    pub fn hall_of_fame(&self) -> Option<&HallOfFame<C::Individual>> {
+        self.hall_of_fame.as_ref()
+    }
+
+    /// Ancestry of the individuals evaluated so far, under `track_lineage`.
+    pub fn lineage(&self) -> Option<&LineageTracker> {
+        self.lineage.as_ref()
+    }
+}
+
+impl<C> Iterator for CoreIter<C>
+where
+    C: Core,
+{
+    type Item = Vec<C::Individual>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.generation > self.params.n_generations
+            || self.is_cancelled()
+            || self.failure.is_some()
+            || self.stop_reason.is_some()
+        {
+            return None;
+        }
+
+        if let Some(flag) = &self.stop_flag {
+            if flag.load(Ordering::Relaxed) {
+                warn!(
+                    "Stopping after {} generations: interrupted.",
+                    self.generation
+                );
+                self.stop_reason = Some(StopReason::Interrupted);
+                return None;
+            }
+        }
+
+        // The first generation always runs, so there is something to save.
+        if let (Some(max_wall_time), Some(started)) = (self.params.max_wall_time, self.started) {
+            if self.generation > 0 && started.elapsed() >= max_wall_time {
+                warn!(
+                    "Stopping after {} generations: the wall time of {} elapsed.",
+                    self.generation,
+                    humantime::format_duration(max_wall_time)
+                );
+                self.stop_reason = Some(StopReason::WallTime);
+                return None;
+            }
+        }
+
+        self.rates = self.params.rates(self.generation);
+
+        if let ReplacementStrategy::SteadyState {
+            replacements_per_generation,
+        } = self.params.replacement
+        {
+            return Some(self.next_steady_state(replacements_per_generation));
+        }
+
+        let generation_started = self.clock();
+        let mut population = self.next_population.clone();
+
+        let eval_started = self.clock();
+        let behaviours = self.eval_trial_scores(&mut population);
+        let mut times = PhaseTimes {
+            eval_ms: elapsed_ms(eval_started),
+            ..Default::default()
+        };
+        self.n_saturated = population
+            .iter_mut()
+            .map(C::Status::take_saturations)
+            .filter(|n_saturations| *n_saturations > 0)
+            .count();
+
+        // Sharing and novelty need the population in the same order as the behaviours.
+        let unranked = (self.params.niching.is_some() || self.params.novelty.is_some())
+            .then(|| population.clone());
+        C::rank(&mut population, self.params.objective);
+
+        self.report_generation(&population);
+        self.update_hall_of_fame(&population);
+        if let Some(lineage) = self.lineage.as_mut() {
+            lineage.record(self.generation, &population);
+        }
+
+        let mut new_population = match (self.params.niching, self.params.novelty, unranked) {
+            _ if self.params.objectives.is_multi_objective() => {
+                let mut new_population = population.clone();
+                C::rank_pareto(&mut new_population, self.params.objective);
+                new_population
+            }
+            (_, Some(novelty), Some(mut unranked)) => {
+                let scores = self.novelty_archive.update(&self.descriptors, &novelty);
+                novelty.rank::<C>(&mut unranked, &scores);
+                unranked
+            }
+            (Some(niching), _, Some(mut unranked)) => {
+                niching.rank::<C>(&mut unranked, &behaviours);
+                unranked
+            }
+            _ => population.clone(),
+        };
+
+        self.n_evaluations += behaviours.iter().map(Vec::len).sum::<usize>();
+
+        let variation_started = self.clock();
+        C::survive(
+            &mut new_population,
+            self.params.population_size,
+            self.rates.gap,
+            self.params.min_survivors,
+        );
+
+        // The evaluated population is still returned; the run ends on the next call.
+        let offspring_counts = if new_population.is_empty() {
+            let failure = RunError::EmptyPopulation {
+                generation: self.generation,
+            };
+            error!("{}", failure);
+            self.failure = Some(failure);
+
+            VariationCounts::default()
+        } else {
+            let n_probe_trials = self.params.probe_trials.min(self.trials.len());
+            C::variation(
+                &mut new_population,
+                self.params.population_size,
+                self.rates.crossover_percent,
+                self.rates.mutation_percent,
+                self.params.program_parameters,
+                self.params.crossover_child_policy,
+                self.params.crossover,
+                &mut self.trials[..n_probe_trials],
+                self.params.fallback_fitness(),
+                self.params.fitness_aggregation,
+                self.params.objective,
+                self.generation,
+            )
+        };
+        self.n_evaluations += offspring_counts.n_probe_evaluations;
+        times.variation_ms = elapsed_ms(variation_started);
+
+        self.finish_generation(&population, offspring_counts, generation_started, times);
+
+        debug_assert!(
+            self.failure.is_some() || new_population.len() == self.params.population_size,
+            "Population size drifted from {} to {}.",
+            self.params.population_size,
+            new_population.len()
+        );
+
+        new_population.iter_mut().for_each(Genotype::end_generation);
+        self.next_population = new_population;
+        self.generation += 1;
+
+        return Some(population);
+    }
+}
+
+impl<C> CoreIter<C>
+where
+    C: Core,
+{
+    /// Logs the ranked population and notifies observers of it.
+    fn report_generation(&mut self, population: &[C::Individual]) {
+        assert!(population.iter().all(C::Status::evaluated));
+
+        info!(
+            best = serde_json::to_string(&population.first()).unwrap(),
+            median = serde_json::to_string(&population.get(population.len() / 2)).unwrap(),
+            worst = serde_json::to_string(&population.last()).unwrap(),
+            generation = serde_json::to_string(&self.generation).unwrap()
+        );
+
+        for observer in self.observers.iter_mut() {
+            observer.on_generation(self.generation, population);
+        }
+
+        if self.params.track_diversity {
+            let diversity = DiversityMetrics::compute::<C>(population);
+
+            info!(
+                diversity = serde_json::to_string(&diversity).unwrap(),
+                generation = serde_json::to_string(&self.generation).unwrap()
+            );
+        }
+    }
+
+    /// Evaluates frozen copies of the best individuals of the ranked `population` on the
+    /// validation trials and archives those good enough.
+    fn update_hall_of_fame(&mut self, population: &[C::Individual]) {
+        let (Some(config), Some(hall_of_fame)) =
+            (self.params.hall_of_fame, self.hall_of_fame.as_mut())
+        else {
+            return;
+        };
+        let default_fitness = self.params.fallback_fitness();
+
+        for candidate in population.iter().take(config.n_candidates) {
+            if hall_of_fame.contains(candidate) {
+                continue;
+            }
+
+            let mut frozen = candidate.clone();
+            C::Freeze::freeze(&mut frozen);
+
+            let scores = self
+                .validation_trials
+                .iter_mut()
+                .map(|trial| {
+                    C::Reset::reset(&mut frozen);
+                    C::Reset::reset(trial);
+                    C::Fitness::eval_fitness(&mut frozen, trial)
+                })
+                .map(|s| if !s.is_finite() { default_fitness } else { s })
+                .collect_vec();
+            self.n_evaluations += scores.len();
+            C::Reset::reset(&mut frozen);
+
+            let fitness = self
+                .params
+                .fitness_aggregation
+                .aggregate(&scores, default_fitness);
+            hall_of_fame.insert(self.generation, fitness, frozen);
+        }
+    }
+
+    /// Metrics of every task over the individuals evaluated since the last call.
+    fn summarize_tasks(&mut self) -> Vec<TaskMetrics> {
+        let task_fitness = std::mem::take(&mut self.task_fitness);
+        let n_tasks = task_fitness.first().map_or(0, Vec::len);
+        let objective = self.params.objective;
+
+        (0..n_tasks)
+            .map(|task| {
+                let values = task_fitness
+                    .iter()
+                    .map(|fitness| fitness[task])
+                    .collect_vec();
+
+                TaskMetrics {
+                    task,
+                    mean: values.iter().sum::<f64>() / values.len() as f64,
+                    best: values
+                        .iter()
+                        .copied()
+                        .min_by(|a, b| objective.compare(*a, *b))
+                        .unwrap_or(f64::NAN),
+                }
+            })
+            .collect()
+    }
+
+    /// Records the generation's metrics and prepares the trials of the next one.
+    fn finish_generation(
+        &mut self,
+        population: &[C::Individual],
+        offspring_counts: VariationCounts,
+        generation_started: Option<Instant>,
+        times: PhaseTimes,
+    ) {
+        for observer in self.observers.iter_mut() {
+            observer.on_variation(offspring_counts);
+        }
+
+        if let Some(soft_deadline_ms) = self.params.soft_deadline_ms {
+            let duration_ms = elapsed_ms(generation_started);
+
+            if duration_ms > soft_deadline_ms {
+                warn!(
+                    duration_ms = serde_json::to_string(&duration_ms).unwrap(),
+                    eval_ms = serde_json::to_string(&times.eval_ms).unwrap(),
+                    variation_ms = serde_json::to_string(&times.variation_ms).unwrap(),
+                    generation = serde_json::to_string(&self.generation).unwrap(),
+                    "Generation exceeded its soft deadline of {} ms.",
+                    soft_deadline_ms
+                );
+            }
+        }
+
+        info!(
+            n_evaluations = serde_json::to_string(&self.n_evaluations).unwrap(),
+            n_saturated = serde_json::to_string(&self.n_saturated).unwrap(),
+            n_eval_failures = serde_json::to_string(&self.n_eval_failures).unwrap(),
+            offspring = serde_json::to_string(&offspring_counts).unwrap(),
+            rates = serde_json::to_string(&self.rates).unwrap(),
+            generation = serde_json::to_string(&self.generation).unwrap()
+        );
+
+        let instruction_stats = self
+            .params
+            .track_instruction_stats
+            .then(|| InstructionStats::compute::<C>(population));
+
+        if let Some(stats) = &instruction_stats {
+            info!(
+                instruction_stats = serde_json::to_string(stats).unwrap(),
+                generation = serde_json::to_string(&self.generation).unwrap()
+            );
+        }
+
+        let trial_variance = self
+            .params
+            .record_trial_scores
+            .then(|| mean_trial_variance::<C>(population))
+            .flatten();
+
+        if let Some(trial_variance) = trial_variance {
+            info!(
+                trial_variance = serde_json::to_string(&trial_variance).unwrap(),
+                generation = serde_json::to_string(&self.generation).unwrap()
+            );
+        }
+
+        self.task_metrics = self.summarize_tasks();
+        if !self.task_metrics.is_empty() {
+            info!(
+                task_metrics = serde_json::to_string(&self.task_metrics).unwrap(),
+                generation = serde_json::to_string(&self.generation).unwrap()
+            );
+        }
+
+        if let Some(events) = self.events.as_mut() {
+            let fitnesses = population.iter().map(C::Status::get_fitness).collect_vec();
+            let statistics = SummaryStatistics::from_values(&fitnesses);
+
+            events
+                .write(&Event::Generation {
+                    generation: self.generation,
+                    best: fitnesses.first().copied().unwrap_or(f64::NAN),
+                    median: fitnesses
+                        .get(fitnesses.len() / 2)
+                        .copied()
+                        .unwrap_or(f64::NAN),
+                    worst: fitnesses.last().copied().unwrap_or(f64::NAN),
+                    mean: statistics.mean,
+                    std: statistics.std,
+                    duration_ms: elapsed_ms(generation_started),
+                    n_evaluations: self.n_evaluations,
+                    n_saturated: self.n_saturated,
+                    n_eval_failures: self.n_eval_failures,
+                    instruction_stats,
+                    trial_variance,
+                    task_metrics: (!self.task_metrics.is_empty())
+                        .then(|| self.task_metrics.clone()),
+                    offspring: offspring_counts,
+                    rates: self.rates,
+                })
+                .expect("Failed to write event.");
+        }
+
+        #[cfg(feature = "metrics-export")]
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.record::<C>(self.generation, population, self.n_evaluations);
+        }
+
+        self.refresh_trials();
+
+        for observer in self.observers.iter_mut() {
+            observer.update_trials(self.generation, &mut self.trials, &self.state_parameters);
+        }
+    }
+
+    /// Evaluates the initial population in full, then inserts one offspring at a time in later
+    /// generations. Survivors keep the fitness measured on the trials they were evaluated on.
+    fn next_steady_state(&mut self, replacements_per_generation: usize) -> Vec<C::Individual> {
+        let generation_started = self.clock();
+        let mut population = self.next_population.clone();
+        let mut offspring_counts = VariationCounts::default();
+        let mut times = PhaseTimes::default();
+
+        if self.generation == 0 {
+            let eval_started = self.clock();
+            let behaviours = self.eval_trial_scores(&mut population);
+            times.eval_ms = elapsed_ms(eval_started);
+            self.n_evaluations += behaviours.iter().map(Vec::len).sum::<usize>();
+            self.n_saturated = population
+                .iter_mut()
+                .map(C::Status::take_saturations)
+                .filter(|n_saturations| *n_saturations > 0)
+                .count();
+        } else {
+            self.n_saturated = 0;
+
+            for _ in 0..replacements_per_generation {
+                let variation_started = self.clock();
+                let (child, variation) = C::offspring(
+                    &population,
+                    self.params.program_parameters,
+                    self.rates.crossover_percent,
+                    self.rates.mutation_percent,
+                    self.params.crossover,
+                    self.generation,
+                );
+                match variation {
+                    Variation::Crossover => offspring_counts.n_crossover += 1,
+                    Variation::Mutation => offspring_counts.n_mutation += 1,
+                    Variation::Clone => offspring_counts.n_clone += 1,
+                }
+                offspring_counts.requested = OperatorCounts {
+                    n_crossover: offspring_counts.n_crossover,
+                    n_mutation: offspring_counts.n_mutation,
+                    n_clone: offspring_counts.n_clone,
+                };
+                times.variation_ms += elapsed_ms(variation_started);
+
+                let mut offspring = vec![child];
+                let eval_started = self.clock();
+                let scores = self.eval_trial_scores(&mut offspring);
+                times.eval_ms += elapsed_ms(eval_started);
+                self.n_evaluations += scores.iter().map(Vec::len).sum::<usize>();
+                self.n_saturated += (C::Status::take_saturations(&mut offspring[0]) > 0) as usize;
+
+                // The offspring replaces the worst individual, unless it is the worst itself.
+                population.extend(offspring);
+                C::rank(&mut population, self.params.objective);
+                population.pop();
+            }
+        }
+
+        C::rank(&mut population, self.params.objective);
+        self.report_generation(&population);
+        self.update_hall_of_fame(&population);
+        if let Some(lineage) = self.lineage.as_mut() {
+            lineage.record(self.generation, &population);
+        }
+        self.finish_generation(&population, offspring_counts, generation_started, times);
+
+        debug_assert_eq!(
+            population.len(),
+            self.params.population_size,
+            "Population size drifted."
+        );
+
+        self.next_population = population.clone();
+        self.next_population
+            .iter_mut()
+            .for_each(Genotype::end_generation);
+        self.generation += 1;
+
+        population
+    }
+}
+
+impl<C> Drop for CoreIter<C>
+where
+    C: Core,
+{
+    fn drop(&mut self) {
+        if let Some(events) = self.events.as_mut() {
+            // Errors cannot be surfaced from `drop`; the stream simply lacks its trailer.
+            let _ = events.write(&Event::RunFinished {
+                n_generations: self.generation,
+                n_evaluations: self.n_evaluations,
+                duration_ms: elapsed_ms(self.started),
+            });
+        }
+    }
+}
+
+impl<T> HyperParameters<T>
+where
+    T: Core,
+{
+    pub fn build_engine(&self) -> CoreIter<T> {
+        self.try_build_engine()
+            .expect("Failed to load state parameters.")
+    }
+
+    /// Builds the engine, surfacing failures to load the state parameters (e.g. a dataset).
+    pub fn try_build_engine(&self) -> LgpResult<CoreIter<T>> {
+        self.try_build_engine_with(self.state_parameters()?)
+    }
+
+    pub fn state_parameters(&self) -> LgpResult<T::StateParameters> {
+        T::state_parameters(self)
+    }
+
+    /// Fitness substituted for non-finite trial scores. Under `Objective::Minimize` the default
+    /// of 0 would rank failed evaluations among the best, so it becomes the worst finite fitness.
+    pub fn fallback_fitness(&self) -> f64 {
+        match self.objective {
+            Objective::Minimize if self.default_fitness == 0. => f64::MAX,
+            _ => self.default_fitness,
+        }
+    }
+
+    /// Checks ranges and cross-field invariants, reporting every violation at once.
+    pub fn validate(&self) -> Result<(), Vec<ConfigViolation>> {
+        let mut violations = vec![];
+        require(
+            &mut violations,
+            self.population_size >= 1,
+            "population_size",
+            self.population_size,
+            "at least 1",
+        );
+        self.gap.require_unit(&mut violations, "gap");
+        self.mutation_percent
+            .require_unit(&mut violations, "mutation_percent");
+        self.crossover_percent
+            .require_unit(&mut violations, "crossover_percent");
+        let max_percent = (0..=self.n_generations)
+            .map(|generation| {
+                let rates = self.rates(generation);
+                rates.mutation_percent + rates.crossover_percent
+            })
+            .fold(f64::NEG_INFINITY, f64::max);
+        require(
+            &mut violations,
+            max_percent <= 1.,
+            "mutation_percent + crossover_percent",
+            max_percent,
+            "at most 1 in every generation",
+        );
+        require(
+            &mut violations,
+            self.n_trials >= 1,
+            "n_trials",
+            self.n_trials,
+            "at least 1",
+        );
+        require(
+            &mut violations,
+            self.probe_trials >= 1,
+            "probe_trials",
+            self.probe_trials,
+            "at least 1",
+        );
+        require(
+            &mut violations,
+            self.repeats >= 1,
+            "repeats",
+            self.repeats,
+            "at least 1",
+        );
+        require(
+            &mut violations,
+            self.min_survivors <= self.population_size,
+            "min_survivors",
+            self.min_survivors,
+            &format!("at most population_size ({})", self.population_size),
+        );
+        require(
+            &mut violations,
+            !self.render || self.render_episodes >= 1,
+            "render_episodes",
+            self.render_episodes,
+            "at least 1 when rendering",
+        );
+        require(
+            &mut violations,
+            self.snapshot_interval != Some(0),
+            "snapshot_interval",
+            0,
+            "at least 1 when set",
+        );
+        require(
+            &mut violations,
+            self.eval_workers.is_empty() || cfg!(feature = "serve"),
+            "eval_workers",
+            self.eval_workers.join(","),
+            "empty without the `serve` feature",
+        );
+        require(
+            &mut violations,
+            self.eval_workers.is_empty() || self.seed.is_some(),
+            "seed",
+            "unset",
+            "set when eval_workers are given",
+        );
+        require(
+            &mut violations,
+            self.eval_timeout_ms != Some(0),
+            "eval_timeout_ms",
+            0,
+            "at least 1 when set",
+        );
+        require(
+            &mut violations,
+            self.eval_timeout_ms.is_none() || self.seed.is_some(),
+            "seed",
+            "unset",
+            "set when eval_timeout_ms is given",
+        );
+        if let Some(novelty) = self.novelty {
+            require(
+                &mut violations,
+                novelty.is_valid(),
+                "novelty",
+                format!("{:?}", novelty),
+                "k >= 1, a finite sparseness_threshold and novelty_weight in [0, 1]",
+            );
+            require(
+                &mut violations,
+                self.niching.is_none() && !self.objectives.is_multi_objective(),
+                "novelty",
+                "set",
+                "unset alongside niching or multiple objectives",
+            );
+            require(
+                &mut violations,
+                self.replacement == ReplacementStrategy::Generational,
+                "novelty",
+                "set",
+                "unset with steady-state replacement",
+            );
+            require(
+                &mut violations,
+                self.eval_workers.is_empty() && self.eval_timeout_ms.is_none(),
+                "novelty",
+                "set",
+                "unset with eval_workers or eval_timeout_ms, whose trials are played elsewhere",
+            );
+        }
+        require(
+            &mut violations,
+            self.objective == Objective::Maximize
+                || (self.niching.is_none() && self.novelty.is_none()),
+            "objective",
+            "minimize",
+            "maximize alongside niching or novelty, which score higher fitness as better",
+        );
+        require(
+            &mut violations,
+            self.max_wall_time != Some(Duration::ZERO),
+            "max_wall_time",
+            "0s",
+            "longer than 0s when set",
+        );
+        require(
+            &mut violations,
+            self.soft_deadline_ms != Some(0),
+            "soft_deadline_ms",
+            0,
+            "at least 1 when set",
+        );
+        if let Some(hall_of_fame) = self.hall_of_fame {
+            require(
+                &mut violations,
+                hall_of_fame.is_valid(),
+                "hall_of_fame",
+                format!("{:?}", hall_of_fame),
+                "a positive capacity, n_candidates and n_validation_trials",
+            );
+        }
+        require(
+            &mut violations,
+            self.init_strategy.is_valid(),
+            "init_strategy",
+            &self.init_strategy,
+            "a ramp with 1 <= min <= max, or at least one seeded path",
+        );
+        require(
+            &mut violations,
+            !self.lineage_dot || self.track_lineage,
+            "lineage_dot",
+            self.lineage_dot,
+            "only with track_lineage",
+        );
+        if let ReplacementStrategy::SteadyState {
+            replacements_per_generation,
+        } = self.replacement
+        {
+            require(
+                &mut violations,
+                replacements_per_generation >= 1,
+                "replacement",
+                self.replacement,
+                "at least 1 replacement per generation",
+            );
+        }
+
+        violations.extend(T::validate(self));
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Applies the `--set` overrides in place so the resolved configuration is the one saved.
+    pub fn apply_overrides(&mut self) -> LgpResult<()> {
+        let overrides = std::mem::take(&mut self.overrides);
+        *self = apply_overrides(&*self, &overrides)?;
+
+        Ok(())
+    }
+
+    pub fn build_engine_with(&self, state_parameters: T::StateParameters) -> CoreIter<T> {
+        reseed(self.master_seed());
+        CoreIter::new(self.clone(), state_parameters)
+    }
+
+    /// Like `build_engine_with`, surfacing failures to create the first generation (e.g. a
+    /// seeded individual that does not fit the program parameters).
+    pub fn try_build_engine_with(
+        &self,
+        state_parameters: T::StateParameters,
+    ) -> LgpResult<CoreIter<T>> {
+        reseed(self.master_seed());
+        let population = self.init_strategy.population(self)?;
+
+        Ok(CoreIter::with_population(
+            self.clone(),
+            state_parameters,
+            population,
+        ))
+    }
+
+    /// The same hyperparameters for engine `D`, which evolves the same kind of individuals, e.g.
+    /// the problem a `MultiTaskEngine` wraps.
+    pub fn retarget<D>(&self) -> LgpResult<HyperParameters<D>>
+    where
+        D: Core<ProgramParameters = T::ProgramParameters>,
+    {
+        let mut retargeted: HyperParameters<D> =
+            serde_json::from_value(serde_json::to_value(self)?)?;
+        retargeted.overrides = self.overrides.clone();
+
+        Ok(retargeted)
+    }
+
+    /// Values of the scheduled hyperparameters at `generation`.
+    pub fn rates(&self, generation: usize) -> EffectiveRates {
+        EffectiveRates {
+            gap: self.gap.value(generation, self.n_generations),
+            mutation_percent: self.mutation_percent.value(generation, self.n_generations),
+            crossover_percent: self.crossover_percent.value(generation, self.n_generations),
+        }
+    }
+
+    /// `seed`, or a seed drawn from entropy for unseeded runs.
+    pub fn master_seed(&self) -> u64 {
+        self.seed.unwrap_or_else(entropy_seed)
+    }
+
+    /// Seed of the trial generated in `slot` after `round` refreshes (`0` for the initial
+    /// trials); only seeded runs seed their trials.
+    pub fn trial_seed(&self, round: usize, slot: usize) -> Option<TrialSeed> {
+        self.seed
+            .map(|seed| TrialSeed(derive_seed(derive_seed(seed, round as u128), slot as u128)))
+    }
+}
+
+/// Engines are marker types, so they and everything they name can be moved to worker threads.
+pub trait Core: 'static {
+    type Individual: Ord + Clone + Send + Sync + Serialize + DeserializeOwned + Genotype;
+    type ProgramParameters: Copy
+        + Send
+        + Sync
+        + Clone
+        + Serialize
+        + DeserializeOwned
+        + Args
+        + ProgramShape;
+    type State: State + BehaviorDescriptor;
+    /// Everything needed to generate a trial state (datasets, environment settings). `Default`
+    /// must be cheap and infallible; see `default_state_parameters` for the standard
+    /// configuration of the problem.
+    type StateParameters: Clone + Send + Sync + Default;
+    type FitnessMarker;
+    type Generate: Generate<Self::ProgramParameters, Self::Individual>
+        + Generate<Self::StateParameters, Self::State>;
+    type Fitness: Fitness<Self::Individual, Self::State, Self::FitnessMarker>;
+    type Reset: Reset<Self::Individual> + Reset<Self::State>;
+    type Breed: Breed<Self::Individual>;
+    type Mutate: Mutate<Self::ProgramParameters, Self::Individual>;
+    type Status: Status<Self::Individual>;
+    type Freeze: Freeze<Self::Individual>;
+
+    /// Standard configuration of the problem, for problems that load it (e.g. a dataset).
+    fn default_state_parameters() -> LgpResult<Self::StateParameters> {
+        Ok(Self::StateParameters::default())
+    }
+
+    /// State parameters used by `HyperParameters::build_engine`, for problems configured
+    /// through hyperparameters.
+    fn state_parameters(_hp: &HyperParameters<Self>) -> LgpResult<Self::StateParameters>
+    where
+        Self: Sized,
+    {
+        Self::default_state_parameters()
+    }
+
+    /// Disjoint training and holdout state parameters, for problems whose trials are drawn from
+    /// a fixed pool such as a dataset. Trials of other problems are independent draws, so both
+    /// parts are the parameters themselves.
+    fn holdout_split(
+        state_parameters: &Self::StateParameters,
+        _seed: u64,
+    ) -> (Self::StateParameters, Self::StateParameters) {
+        (state_parameters.clone(), state_parameters.clone())
+    }
+
+    /// Problem-specific checks run by `HyperParameters::validate`, such as program parameters
+    /// and options the problem does not support.
+    fn validate(_hp: &HyperParameters<Self>) -> Vec<ConfigViolation>
+    where
+        Self: Sized,
+    {
+        vec![]
+    }
+
+    /// Problem-specific breakdown of the best individual of a run on the trials it was evolved
+    /// on, such as per-class metrics for classification; saved as `best_report.json`.
+    fn report(
+        _best: &Self::Individual,
+        _state_parameters: &Self::StateParameters,
+    ) -> LgpResult<Option<serde_json::Value>> {
+        Ok(None)
+    }
+
+    /// State saved individuals depend on to act as they did while evolving, such as observation
+    /// statistics learned during the run; saved as `inference_state.json`.
+    fn inference_state(
+        _state_parameters: &Self::StateParameters,
+    ) -> LgpResult<Option<serde_json::Value>> {
+        Ok(None)
+    }
+
+    /// A copy of the best individual of a run without redundant instructions, checked to behave
+    /// the same on the trials it was evolved on; saved as `best_simplified`.
+    fn simplify(
+        _best: &Self::Individual,
+        _state_parameters: &Self::StateParameters,
+    ) -> Option<Self::Individual> {
+        None
+    }
+
+    /// Parameters generating the trial seeded by `seed`. Problems whose states draw from their
+    /// own random source (e.g. gym environments) forward the seed to it.
+    fn seed_trial(parameters: &Self::StateParameters, _seed: TrialSeed) -> Self::StateParameters {
+        parameters.clone()
+    }
+
+    /// Number of tasks trials are drawn from, e.g. the environments of a `MultiTaskEngine`;
+    /// trial `slot` belongs to task `slot % n_tasks`.
+    fn n_tasks(_parameters: &Self::StateParameters) -> usize {
+        1
+    }
+
+    /// Parameters generating the trials of `task`.
+    fn task_parameters(parameters: &Self::StateParameters, _task: usize) -> Self::StateParameters {
+        parameters.clone()
+    }
+
+    /// Combines the fitness on every task, in task order, into the fitness of an individual.
+    fn aggregate_tasks(
+        _parameters: &Self::StateParameters,
+        task_fitness: &[f64],
+        _objective: Objective,
+    ) -> f64 {
+        task_fitness.iter().sum::<f64>() / task_fitness.len() as f64
+    }
+
+    fn init_population(
+        program_parameters: Self::ProgramParameters,
+        population_size: usize,
+    ) -> Vec<Self::Individual> {
+        let population = repeat_with(|| Self::Generate::generate(program_parameters))
+            .take(population_size)
+            .collect();
+
+        population
+    }
+
+    /// Replaces structural duplicates (identical instruction sequences) with freshly generated
+    /// individuals.
+    fn dedup_structural(
+        population: &mut Vec<Self::Individual>,
+        program_parameters: Self::ProgramParameters,
+    ) {
+        let mut seen = HashSet::new();
+
+        for individual in population.iter_mut() {
+            if !seen.insert(fingerprint(individual.instructions())) {
+                *individual = Self::Generate::generate(program_parameters);
+            }
+        }
+    }
+
+    fn eval_fitness(
+        population: &mut Vec<Self::Individual>,
+        trials: &mut Vec<Self::State>,
+        default_fitness: f64,
+        aggregation: FitnessAggregation,
+    ) {
+        Self::eval_trial_scores(population, trials, default_fitness, aggregation);
+    }
+
+    /// Evaluates the population like `eval_fitness` and returns the per-trial scores of each
+    /// individual (non-finite scores replaced by `default_fitness`).
+    fn eval_trial_scores(
+        population: &mut [Self::Individual],
+        trials: &mut [Self::State],
+        default_fitness: f64,
+        aggregation: FitnessAggregation,
+    ) -> Vec<Vec<f64>> {
+        let mut behaviours = Vec::with_capacity(population.len());
+
+        for individual in population.iter_mut() {
+            let mut scores = trials
+                .iter_mut()
+                .map(|trial| {
+                    Self::Reset::reset(individual);
+                    Self::Reset::reset(trial);
+                    Self::Fitness::eval_fitness(individual, trial)
+                })
+                .collect_vec();
+
+            scores = scores
+                .into_iter()
+                .map(|s| if !s.is_finite() { default_fitness } else { s })
+                .collect_vec();
+            let fitness = aggregation.aggregate(&scores, default_fitness);
+            Self::Status::set_fitness(individual, fitness);
+            behaviours.push(scores);
+        }
+
+        behaviours
+    }
+
+    /// Orders the population from best to worst under `objective`. Invalid and unevaluated
+    /// individuals come last in either direction, and ties fall back on the individuals' `Ord`,
+    /// e.g. their ids for programs.
+    fn rank(population: &mut Vec<Self::Individual>, objective: Objective) {
+        let order = |a: &Self::Individual, b: &Self::Individual| {
+            if Self::Status::valid(a) && Self::Status::valid(b) {
+                objective
+                    .compare(Self::Status::get_fitness(a), Self::Status::get_fitness(b))
+                    .then_with(|| b.cmp(a))
+            } else {
+                b.cmp(a)
+            }
+        };
+
+        population.sort_by(order);
+        debug_assert!(population
+            .windows(2)
+            .all(|w| order(&w[0], &w[1]) != std::cmp::Ordering::Greater));
+    }
+
+    /// Objectives used in multi-objective mode: fitness oriented by `objective` and negated
+    /// effective length.
+    fn objectives(individual: &Self::Individual, objective: Objective) -> Vec<f64> {
+        vec![
+            objective.maximized(Self::Status::get_fitness(individual)),
+            -(Self::Status::effective_length(individual) as f64),
+        ]
+    }
+
+    /// Orders the population by Pareto front, using crowding distance within a front. Combined
+    /// with `survive`, whole fronts are kept until the gap is met.
+    fn rank_pareto(population: &mut Vec<Self::Individual>, objective: Objective) {
+        let objectives = population
+            .iter()
+            .map(|individual| Self::objectives(individual, objective))
+            .collect_vec();
+        let order = pareto_order(&objectives);
+
+        let mut individuals = population.drain(..).map(Some).collect_vec();
+        population.extend(order.into_iter().filter_map(|idx| individuals[idx].take()));
+    }
+
+    fn pareto_front(
+        population: &[Self::Individual],
+        objective: Objective,
+    ) -> Vec<Self::Individual> {
+        let objectives = population
+            .iter()
+            .map(|individual| Self::objectives(individual, objective))
+            .collect_vec();
+
+        non_dominated_fronts(&objectives)
+            .first()
+            .map(|front| front.iter().map(|idx| population[*idx].clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Drops invalid individuals, then the worst ones, so that `floor(gap * population_size)`
+    /// of a full population are replaced. At least `min_survivors` individuals are kept, the
+    /// best invalid ones included if need be, and the best valid one always is.
+    fn survive(
+        population: &mut Vec<Self::Individual>,
+        population_size: usize,
+        gap: f64,
+        min_survivors: usize,
+    ) {
+        let n_to_drop = (gap * population_size as f64).floor() as usize;
+        let n_to_keep = population_size.saturating_sub(n_to_drop);
+
+        let n_valid = population.iter().filter(|i| Self::Status::valid(i)).count();
+        let mut n_invalid_to_keep = min_survivors.saturating_sub(n_valid);
+
+        population.retain(|individual| {
+            if Self::Status::valid(individual) {
+                true
+            } else if n_invalid_to_keep > 0 {
+                n_invalid_to_keep -= 1;
+                true
+            } else {
+                false
+            }
+        });
+        population.truncate(n_to_keep.max(min_survivors).max(1));
+    }
+
+    /// Produces one offspring of parents drawn uniformly from `population`, bred in
+    /// `generation`. The operator follows the first parent's adaptive rates when it carries them
+    /// and the global percentages otherwise; crossover keeps one of its two children at random.
+    fn offspring(
+        population: &[Self::Individual],
+        program_parameters: Self::ProgramParameters,
+        crossover_percent: f64,
+        mutation_percent: f64,
+        crossover: CrossoverKind,
+        generation: usize,
+    ) -> (Self::Individual, Variation) {
+        let parent = population
+            .iter()
+            .choose(&mut generator())
+            .expect("Population to not be empty.");
+        let (mutation_percent, crossover_percent) =
+            operator_percents(mutation_percent, crossover_percent);
+        let variation = parent
+            .adaptive_rates()
+            .copied()
+            .unwrap_or_else(|| AdaptiveRates::new(mutation_percent, crossover_percent))
+            .choose(&mut generator());
+
+        let mut child = match variation {
+            Variation::Crossover => {
+                let mate = population.iter().choose(&mut generator()).unwrap();
+                let (child_a, child_b) = Self::Breed::crossover(parent, mate, crossover);
+                let mut child = if generator().gen_bool(0.5) {
+                    child_a
+                } else {
+                    child_b
+                };
+
+                child.set_provenance(Provenance::new(variation, &[parent, mate], generation));
+                child
+            }
+            Variation::Mutation => {
+                let mut child = parent.clone();
+                Self::Mutate::mutate(&mut child, program_parameters);
+                child
+            }
+            Variation::Clone => {
+                let mut child = parent.clone();
+                Self::Reset::reset(&mut child);
+                child
+            }
+        };
+
+        if variation != Variation::Crossover {
+            child.set_provenance(Provenance::new(variation, &[parent], generation));
+        }
+
+        (child, variation)
+    }
+
+    /// Fills the population back up to `population_size` with crossover, mutation and clone
+    /// offspring.
+    ///
+    /// Individuals carrying adaptive rates replace the global percentages with their own; the
+    /// global ones are normalized by `operator_percents` first.
+    ///
+    /// Returns the number of offspring per operator, along with the (individual, trial)
+    /// evaluations spent probing crossover children.
+    fn variation(
+        population: &mut Vec<Self::Individual>,
+        population_size: usize,
+        crossover_percent: f64,
+        mutation_percent: f64,
+        program_parameters: Self::ProgramParameters,
+        crossover_child_policy: CrossoverChildPolicy,
+        crossover: CrossoverKind,
+        probe_trials: &mut [Self::State],
+        default_fitness: f64,
+        aggregation: FitnessAggregation,
+        objective: Objective,
+        generation: usize,
+    ) -> VariationCounts {
+        debug_assert!(population.len() > 0);
+
+        let remaining_pool_spots = population_size.saturating_sub(population.len());
+
+        if remaining_pool_spots == 0 {
+            return VariationCounts::default();
+        }
+
+        let adaptive = population
+            .iter()
+            .any(|individual| individual.adaptive_rates().is_some());
+
+        // Under adaptive variation each slot draws a parent and follows its operator weights.
+        let (n_mutations, n_crossovers) = if adaptive {
+            (0..remaining_pool_spots).fold((0, 0), |(n_mutations, n_crossovers), _| {
+                let variation = population
+                    .iter()
+                    .choose(&mut generator())
+                    .and_then(|parent| parent.adaptive_rates())
+                    .map(|rates| rates.choose(&mut generator()));
+
+                match variation {
+                    Some(Variation::Mutation) => (n_mutations + 1, n_crossovers),
+                    Some(Variation::Crossover) => (n_mutations, n_crossovers + 1),
+                    _ => (n_mutations, n_crossovers),
+                }
+            })
+        } else {
+            let (mutation_percent, crossover_percent) =
+                operator_percents(mutation_percent, crossover_percent);
+
+            (
+                (remaining_pool_spots as f64 * mutation_percent).floor() as usize,
+                (remaining_pool_spots as f64 * crossover_percent).floor() as usize,
+            )
+        };
+        let n_clones = remaining_pool_spots
+            .saturating_sub(n_mutations)
+            .saturating_sub(n_crossovers);
+
+        let n_crossover_pairs = match crossover_child_policy {
+            CrossoverChildPolicy::Both => (n_crossovers + 1) / 2,
+            _ => n_crossovers,
+        };
+
+        let mut clone_offspring: Vec<Self::Individual> = Vec::with_capacity(n_clones);
+        let mut mutation_offspring: Vec<Self::Individual> = Vec::with_capacity(n_mutations);
+        let mut crossover_pairs: Vec<(Self::Individual, Self::Individual)> =
+            Vec::with_capacity(n_crossover_pairs);
+
+        debug_assert!(n_mutations + n_crossovers <= remaining_pool_spots);
+
+        let rc_population = Arc::new(population.clone());
+        // Which worker runs each operator varies, so each draws from its own forked stream.
+        let rng = RngManager::current();
+        let [crossover_key, mutation_key, clone_key]: [u128; 3] = generator().gen();
 
         rayon::scope(|s| {
             s.spawn(|_| {
-                crossover_offspring.extend((0..n_crossovers).filter_map(|_| {
-                    let population_to_read = rc_population.clone();
-                    let parent_a = population_to_read.iter().choose(&mut generator());
-                    let parent_b = population_to_read.iter().choose(&mut generator());
-
-                    if let (Some(parent_a), Some(parent_b)) = (parent_a, parent_b) {
-                        let children = Self::Breed::two_point_crossover(&parent_a, &parent_b);
-                        match generator().gen_range(0..2) {
-                            0 => Some(children.0),
-                            1 => Some(children.1),
-                            _ => unreachable!(),
+                rng.enter(crossover_key, || {
+                    crossover_pairs.extend((0..n_crossover_pairs).filter_map(|_| {
+                        let population_to_read = rc_population.clone();
+                        let parent_a = population_to_read.iter().choose(&mut generator());
+                        let parent_b = population_to_read.iter().choose(&mut generator());
+
+                        if let (Some(parent_a), Some(parent_b)) = (parent_a, parent_b) {
+                            let provenance = Provenance::new(
+                                Variation::Crossover,
+                                &[parent_a, parent_b],
+                                generation,
+                            );
+                            let (mut child_a, mut child_b) =
+                                Self::Breed::crossover(parent_a, parent_b, crossover);
+                            child_a.set_provenance(provenance.clone());
+                            child_b.set_provenance(provenance);
+
+                            Some((child_a, child_b))
+                        } else {
+                            None
                         }
-                    } else {
-                        None
-                    }
-                }));
+                    }));
+                })
             });
-
             s.spawn(|_| {
-                mutation_offspring.extend((0..n_mutations).filter_map(|_| {
-                    let population_to_read = rc_population.clone();
-                    let parent = population_to_read.iter().choose(&mut generator());
-
-                    if let Some(internal_parent) = parent {
-                        let mut clone = internal_parent.clone();
-                        Self::Mutate::mutate(&mut clone, program_parameters);
-                        Some(clone)
-                    } else {
-                        None
-                    }
-                }))
+                rng.enter(mutation_key, || {
+                    mutation_offspring.extend((0..n_mutations).filter_map(|_| {
+                        let population_to_read = rc_population.clone();
+                        let parent = population_to_read.iter().choose(&mut generator());
+
+                        if let Some(internal_parent) = parent {
+                            let mut clone = internal_parent.clone();
+                            Self::Mutate::mutate(&mut clone, program_parameters);
+                            clone.set_provenance(Provenance::new(
+                                Variation::Mutation,
+                                &[internal_parent],
+                                generation,
+                            ));
+                            Some(clone)
+                        } else {
+                            None
+                        }
+                    }))
+                })
             });
 
             s.spawn(|_| {
-                clone_offspring.extend((0..n_clones).filter_map(|_| {
-                    let population_to_read = rc_population.clone();
-                    let parent = population_to_read.iter().choose(&mut generator());
-
-                    if let Some(internal_parent) = parent {
-                        let mut clone = internal_parent.clone();
-                        Self::Reset::reset(&mut clone);
-                        Some(clone)
-                    } else {
-                        None
-                    }
-                }))
+                rng.enter(clone_key, || {
+                    clone_offspring.extend((0..n_clones).filter_map(|_| {
+                        let population_to_read = rc_population.clone();
+                        let parent = population_to_read.iter().choose(&mut generator());
+
+                        if let Some(internal_parent) = parent {
+                            let mut clone = internal_parent.clone();
+                            Self::Reset::reset(&mut clone);
+                            clone.set_provenance(Provenance::new(
+                                Variation::Clone,
+                                &[internal_parent],
+                                generation,
+                            ));
+                            Some(clone)
+                        } else {
+                            None
+                        }
+                    }))
+                })
             });
         });
 
+        let mut n_probe_evaluations = 0;
+
+        let mut crossover_offspring: Vec<Self::Individual> = match crossover_child_policy {
+            CrossoverChildPolicy::RandomOne => crossover_pairs
+                .into_iter()
+                .map(|children| match generator().gen_range(0..2) {
+                    0 => children.0,
+                    1 => children.1,
+                    _ => unreachable!(),
+                })
+                .collect(),
+            CrossoverChildPolicy::Both => crossover_pairs
+                .into_iter()
+                .flat_map(|(child_a, child_b)| [child_a, child_b])
+                .take(n_crossovers)
+                .collect(),
+            CrossoverChildPolicy::BestOfPair => crossover_pairs
+                .into_iter()
+                .map(|(child_a, child_b)| {
+                    // Probe copies so that evaluation side effects (e.g. learning) are discarded.
+                    let mut probes = vec![child_a.clone(), child_b.clone()];
+                    let scores = Self::eval_trial_scores(
+                        &mut probes,
+                        probe_trials,
+                        default_fitness,
+                        aggregation,
+                    );
+                    n_probe_evaluations += scores.iter().map(Vec::len).sum::<usize>();
+
+                    let fitness_a = Self::Status::get_fitness(&probes[0]);
+                    let fitness_b = Self::Status::get_fitness(&probes[1]);
+
+                    if objective.is_better(fitness_b, fitness_a) {
+                        child_b
+                    } else {
+                        child_a
+                    }
+                })
+                .collect(),
+        };
+
+        let counts = VariationCounts {
+            n_crossover: crossover_offspring.len(),
+            n_mutation: mutation_offspring.len(),
+            n_clone: clone_offspring.len(),
+            n_probe_evaluations,
+            requested: OperatorCounts {
+                n_crossover: n_crossovers,
+                n_mutation: n_mutations,
+                n_clone: n_clones,
+            },
+        };
+
         // Step 3: Add Children to Population
         population.append(&mut crossover_offspring);
         population.append(&mut mutation_offspring);
         population.append(&mut clone_offspring);
+
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::instructions::OpSet;
+    use crate::{
+        core::{
+            engines::{generate_engine::GenerateEngine, status_engine::StatusEngine},
+            instruction::InstructionGeneratorParameters,
+            program::{Program, ProgramGeneratorParameters},
+        },
+        data::dataset::Dataset,
+        problems::iris::{IrisEngine, IrisState, IrisStateParameters},
+        utils::error::LgpError,
+    };
+
+    use std::{marker::PhantomData, sync::Mutex};
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::core::engines::{
+        breed_engine::BreedEngine, fitness_engine::FitnessEngine, freeze_engine::FreezeEngine,
+        mutate_engine::MutateEngine, reset_engine::ResetEngine,
+    };
+    use crate::core::registers::{RegisterInit, RegisterSaturation};
+
+    fn parameters() -> ProgramGeneratorParameters {
+        ProgramGeneratorParameters {
+            max_instructions: 10,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 1,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 10.,
+                input_scales: None,
+                n_actions: 3,
+                n_inputs: 4,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        }
+    }
+
+    fn dataset() -> Dataset {
+        Dataset::new(
+            vec![vec![5.1, 3.5, 1.4, 0.2], vec![7.0, 3.2, 4.7, 1.4]],
+            vec![0, 1],
+            vec![],
+            vec![],
+        )
+    }
+
+    fn trials(n_trials: usize) -> Vec<IrisState> {
+        let state_parameters = IrisStateParameters::new(dataset());
+
+        repeat_with(|| GenerateEngine::generate(state_parameters.clone()))
+            .take(n_trials)
+            .collect()
+    }
+
+    fn vary(
+        policy: CrossoverChildPolicy,
+        n_probe_trials: usize,
+    ) -> (Vec<Program>, VariationCounts) {
+        let mut population = IrisEngine::init_population(parameters(), 5);
+
+        let mut trials = trials(n_probe_trials);
+        let counts = IrisEngine::variation(
+            &mut population,
+            10,
+            1.,
+            0.,
+            parameters(),
+            policy,
+            CrossoverKind::default(),
+            &mut trials,
+            0.,
+            FitnessAggregation::default(),
+            Objective::Maximize,
+            0,
+        );
+
+        (population, counts)
+    }
+
+    #[test]
+    fn given_each_objective_when_ranked_then_best_comes_first_and_invalid_last() {
+        let population = [0.5, f64::NAN, 0.9, f64::NEG_INFINITY, 0.1].map(|fitness| {
+            let mut program: Program = GenerateEngine::generate(parameters());
+            StatusEngine::set_fitness(&mut program, fitness);
+            program
+        });
+        let ranked = |objective| {
+            let mut population = population.to_vec();
+            IrisEngine::rank(&mut population, objective);
+            population
+                .iter()
+                .map(StatusEngine::get_fitness)
+                .collect_vec()
+        };
+
+        // NaN fitness is invalid, like infinite fitness.
+        let invalid = f64::NEG_INFINITY;
+        assert_eq!(
+            ranked(Objective::Maximize),
+            [0.9, 0.5, 0.1, invalid, invalid]
+        );
+        assert_eq!(
+            ranked(Objective::Minimize),
+            [0.1, 0.5, 0.9, invalid, invalid]
+        );
+    }
+
+    #[test]
+    fn given_recorded_trial_scores_when_summarized_then_variances_are_averaged() {
+        let population = [Some(vec![1., 3.]), Some(vec![2., 2., 2.]), None].map(|scores| {
+            let mut program: Program = GenerateEngine::generate(parameters());
+            if let Some(scores) = scores {
+                StatusEngine::set_trial_scores(&mut program, scores);
+            }
+            program
+        });
+
+        assert_eq!(mean_trial_variance::<IrisEngine>(&population), Some(0.5));
+        assert_eq!(mean_trial_variance::<IrisEngine>(&population[2..]), None);
+
+        let mut reset = population[0].clone();
+        ResetEngine::reset(&mut reset);
+        assert_eq!(StatusEngine::trial_scores(&reset), None);
+    }
+
+    #[test]
+    fn given_minimization_when_default_fitness_is_unset_then_failures_fall_back_to_the_worst() {
+        let mut parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(parameters())
+            .build()
+            .unwrap();
+        assert_eq!(parameters.fallback_fitness(), 0.);
+
+        parameters.objective = Objective::Minimize;
+        assert_eq!(parameters.fallback_fitness(), f64::MAX);
+
+        parameters.default_fitness = 100.;
+        assert_eq!(parameters.fallback_fitness(), 100.);
+    }
+
+    #[test]
+    fn given_each_crossover_child_policy_when_varied_then_offspring_count_is_exact() {
+        for policy in [
+            CrossoverChildPolicy::RandomOne,
+            CrossoverChildPolicy::Both,
+            CrossoverChildPolicy::BestOfPair,
+        ] {
+            let (population, _) = vary(policy, 2);
+            assert_eq!(population.len(), 10, "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn given_even_operators_when_one_generation_passes_then_offspring_carry_their_provenance() {
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(parameters())
+            .population_size(20)
+            .n_trials(1)
+            .n_generations(1)
+            .mutation_percent(0.5)
+            .crossover_percent(0.5)
+            .seed(Some(6))
+            .build()
+            .unwrap();
+        let state_parameters = IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+
+        let populations = parameters.build_engine_with(state_parameters).collect_vec();
+        let previous_ids = populations[0]
+            .iter()
+            .map(|program| program.id)
+            .collect::<HashSet<_>>();
+
+        let offspring = populations[1]
+            .iter()
+            .filter_map(|program| program.provenance().map(|provenance| (program, provenance)))
+            .collect_vec();
+        assert_eq!(offspring.len(), 10);
+
+        for (program, provenance) in offspring {
+            assert_eq!(provenance.generation, 0);
+            assert!(provenance
+                .parents
+                .iter()
+                .all(|parent| previous_ids.contains(parent)));
+
+            match provenance.operator {
+                Variation::Crossover => assert_eq!(provenance.parents.len(), 2),
+                Variation::Mutation => {
+                    assert_eq!(provenance.parents.len(), 1);
+                    assert!(!previous_ids.contains(&program.id));
+                }
+                Variation::Clone => assert_eq!(provenance.parents, vec![program.id]),
+            }
+
+            let mut reset = program.clone();
+            ResetEngine::reset(&mut reset);
+            assert_eq!(reset.provenance(), Some(provenance));
+        }
+
+        let mut survivors = populations[1]
+            .iter()
+            .filter(|program| program.provenance().is_none());
+        assert!(survivors.all(|program| previous_ids.contains(&program.id)));
+    }
+
+    #[test]
+    fn given_population_size_when_iterating_many_generations_then_size_is_constant() {
+        for population_size in [10, 101] {
+            let parameters = HyperParametersBuilder::<IrisEngine>::default()
+                .program_parameters(parameters())
+                .population_size(population_size)
+                .n_trials(1)
+                .n_generations(30)
+                .build()
+                .unwrap();
+            let state_parameters =
+                IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+
+            let sizes = parameters
+                .build_engine_with(state_parameters)
+                .map(|population| population.len())
+                .collect_vec();
+
+            assert_eq!(sizes.len(), 31);
+            assert!(sizes.iter().all(|size| *size == population_size));
+        }
+    }
+
+    #[test]
+    fn given_same_seed_when_run_twice_then_every_generation_is_identical() {
+        let run = || {
+            let parameters = HyperParametersBuilder::<IrisEngine>::default()
+                .program_parameters(parameters())
+                .population_size(20)
+                .n_trials(1)
+                .n_generations(5)
+                .seed(Some(17))
+                .build()
+                .unwrap();
+            // Trials are shuffled before the run is seeded, so take the dataset as is.
+            let state_parameters = IrisStateParameters::new(dataset());
+
+            parameters
+                .build_engine_with(state_parameters)
+                .map(|population| serde_json::to_string(&population).unwrap())
+                .collect_vec()
+        };
+
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn given_adaptive_variation_when_iterating_then_every_individual_carries_rates() {
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(parameters())
+            .population_size(20)
+            .n_trials(1)
+            .n_generations(10)
+            .adaptive_variation(true)
+            .build()
+            .unwrap();
+        let state_parameters = IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+
+        let populations = parameters.build_engine_with(state_parameters).collect_vec();
+
+        assert_eq!(populations.len(), 11);
+        assert!(populations
+            .iter()
+            .flatten()
+            .all(|individual| individual.adaptive_rates.is_some()));
+    }
+
+    #[test]
+    fn given_equal_generations_when_steady_state_then_fewer_evaluations_and_constant_size() {
+        let run = |replacement: ReplacementStrategy| {
+            let parameters = HyperParametersBuilder::<IrisEngine>::default()
+                .program_parameters(parameters())
+                .population_size(20)
+                .n_trials(2)
+                .n_generations(5)
+                .replacement(replacement)
+                .build()
+                .unwrap();
+            let state_parameters =
+                IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+
+            let mut engine = parameters.build_engine_with(state_parameters);
+            let sizes = engine
+                .by_ref()
+                .map(|population| population.len())
+                .collect_vec();
+
+            (sizes, engine.n_evaluations())
+        };
+
+        let (generational_sizes, generational) = run(ReplacementStrategy::Generational);
+        let (steady_sizes, steady) = run(ReplacementStrategy::SteadyState {
+            replacements_per_generation: 5,
+        });
+
+        // 6 generations of 20 individuals on 2 trials, against the initial 20 and then 5
+        // offspring per generation.
+        assert_eq!(generational, 6 * 20 * 2);
+        assert_eq!(steady, (20 + 5 * 5) * 2);
+        assert_eq!(generational_sizes.len(), steady_sizes.len());
+        assert!(steady_sizes.iter().all(|size| *size == 20));
+
+        assert_eq!(
+            "steady_state:5".parse(),
+            Ok(ReplacementStrategy::SteadyState {
+                replacements_per_generation: 5
+            })
+        );
+    }
+
+    #[test]
+    fn given_cancelled_flag_when_iterating_then_no_further_generations_are_produced() {
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(parameters())
+            .population_size(10)
+            .n_trials(1)
+            .n_generations(10)
+            .build()
+            .unwrap();
+        let state_parameters = IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+        let flag = Arc::new(AtomicBool::new(false));
+
+        let mut engine = parameters
+            .build_engine_with(state_parameters)
+            .with_cancellation(flag.clone());
+
+        assert!(engine.next().is_some());
+        assert!(engine.next().is_some());
+        assert_eq!(engine.generation(), 2);
+
+        flag.store(true, Ordering::Relaxed);
+
+        assert!(engine.next().is_none());
+        assert_eq!(engine.generation(), 2);
+    }
+
+    #[test]
+    fn given_all_invalid_population_when_survived_then_min_survivors_are_kept() {
+        let invalid = || {
+            let mut population = IrisEngine::init_population(parameters(), 10);
+            population
+                .iter_mut()
+                .for_each(|program| StatusEngine::set_fitness(program, f64::NAN));
+            population
+        };
+
+        let mut population = invalid();
+        IrisEngine::survive(&mut population, 10, 0.5, 1);
+        assert_eq!(population.len(), 1);
+
+        let mut population = invalid();
+        IrisEngine::survive(&mut population, 10, 0.5, 0);
+        assert!(population.is_empty());
+    }
+
+    /// Scores every program as non-finite.
+    struct InvalidFitness;
+
+    impl Fitness<Program, IrisState, ()> for InvalidFitness {
+        fn eval_fitness(_program: &mut Program, _states: &mut IrisState) -> f64 {
+            f64::NAN
+        }
+    }
+
+    #[derive(Clone)]
+    struct InvalidEngine;
+
+    impl Core for InvalidEngine {
+        type State = IrisState;
+        type StateParameters = IrisStateParameters;
+        type Individual = Program;
+        type ProgramParameters = ProgramGeneratorParameters;
+        type FitnessMarker = ();
+        type Generate = GenerateEngine;
+        type Fitness = InvalidFitness;
+        type Reset = ResetEngine;
+        type Breed = BreedEngine;
+        type Mutate = MutateEngine;
+        type Status = StatusEngine;
+        type Freeze = FreezeEngine;
+    }
+
+    #[test]
+    fn given_no_survivors_when_iterating_then_run_ends_with_an_error() {
+        // A negative infinite default fitness leaves every individual invalid.
+        let parameters = HyperParametersBuilder::<InvalidEngine>::default()
+            .program_parameters(parameters())
+            .population_size(10)
+            .n_trials(1)
+            .n_generations(10)
+            .default_fitness(f64::NEG_INFINITY)
+            .min_survivors(0)
+            .build()
+            .unwrap();
+        let state_parameters = IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+
+        let mut engine = parameters.build_engine_with(state_parameters);
+
+        assert_eq!(engine.next().map(|population| population.len()), Some(10));
+        assert!(engine.next().is_none());
+        assert_eq!(
+            engine.failure(),
+            Some(RunError::EmptyPopulation { generation: 0 })
+        );
+    }
+
+    #[test]
+    fn given_full_gap_and_no_min_survivors_when_survived_then_the_best_is_kept() {
+        let mut population = IrisEngine::init_population(parameters(), 10);
+        population
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, program)| StatusEngine::set_fitness(program, idx as f64));
+        IrisEngine::rank(&mut population, Objective::Maximize);
+
+        IrisEngine::survive(&mut population, 10, 1., 0);
+
+        assert_eq!(population.len(), 1);
+        assert_eq!(StatusEngine::get_fitness(&population[0]), 9.);
+    }
+
+    #[test]
+    fn given_percentages_out_of_range_when_normalized_then_they_sum_to_at_most_one() {
+        assert_eq!(operator_percents(0.5, 0.5), (0.5, 0.5));
+        assert_eq!(operator_percents(0.2, 0.3), (0.2, 0.3));
+        assert_eq!(operator_percents(1., 1.), (0.5, 0.5));
+        assert_eq!(operator_percents(0.75, 0.75), (0.5, 0.5));
+        assert_eq!(operator_percents(-1., 2.), (0., 1.));
+        assert_eq!(operator_percents(f64::NAN, 0.5), (0., 0.5));
+    }
+
+    #[test]
+    fn given_percentages_summing_over_one_when_varied_then_population_is_refilled() {
+        let mut population = IrisEngine::init_population(parameters(), 3);
+
+        let counts = IrisEngine::variation(
+            &mut population,
+            10,
+            0.9,
+            0.8,
+            parameters(),
+            CrossoverChildPolicy::RandomOne,
+            CrossoverKind::default(),
+            &mut trials(1),
+            0.,
+            FitnessAggregation::default(),
+            Objective::Maximize,
+            0,
+        );
+
+        assert_eq!(population.len(), 10);
+        assert_eq!(counts.n_crossover + counts.n_mutation + counts.n_clone, 7);
+    }
+
+    #[test]
+    fn given_boundary_gaps_and_percentages_when_iterating_then_runs_complete() {
+        let percentages = [
+            (0., 0.),
+            (1., 0.),
+            (0., 1.),
+            (0.5, 0.5),
+            (0.8, 0.7),
+            (1., 1.),
+        ];
+
+        for gap in [0., 1.] {
+            for (mutation_percent, crossover_percent) in percentages {
+                for min_survivors in [0, 1] {
+                    let parameters = HyperParametersBuilder::<IrisEngine>::default()
+                        .program_parameters(parameters())
+                        .population_size(10)
+                        .n_trials(1)
+                        .n_generations(2)
+                        .gap(gap)
+                        .mutation_percent(mutation_percent)
+                        .crossover_percent(crossover_percent)
+                        .min_survivors(min_survivors)
+                        .build()
+                        .unwrap();
+                    let state_parameters =
+                        IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+
+                    let mut engine = parameters.build_engine_with(state_parameters);
+                    let sizes = engine
+                        .by_ref()
+                        .map(|population| population.len())
+                        .collect_vec();
+
+                    let case = (gap, mutation_percent, crossover_percent, min_survivors);
+                    assert_eq!(sizes, vec![10; 3], "{:?}", case);
+                    assert_eq!(engine.failure(), None, "{:?}", case);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn given_best_of_pair_when_varied_then_probe_evaluations_are_counted() {
+        // 5 pairs, 2 children each, 2 probe trials.
+        assert_eq!(
+            vary(CrossoverChildPolicy::BestOfPair, 2)
+                .1
+                .n_probe_evaluations,
+            20
+        );
+        assert_eq!(
+            vary(CrossoverChildPolicy::RandomOne, 2)
+                .1
+                .n_probe_evaluations,
+            0
+        );
+        assert_eq!(vary(CrossoverChildPolicy::Both, 2).1.n_probe_evaluations, 0);
+    }
+
+    #[derive(Default)]
+    struct Callbacks {
+        generations: Vec<usize>,
+        n_variations: usize,
+        n_offspring: usize,
+        n_updates: usize,
+    }
+
+    struct Counter(Arc<std::sync::Mutex<Callbacks>>);
+
+    impl GenerationObserver<IrisEngine> for Counter {
+        fn on_generation(&mut self, generation: usize, ranked: &[Program]) {
+            assert_eq!(ranked.len(), 10);
+            self.0.lock().unwrap().generations.push(generation);
+        }
+
+        fn on_variation(&mut self, offspring_counts: VariationCounts) {
+            let mut callbacks = self.0.lock().unwrap();
+            callbacks.n_variations += 1;
+            callbacks.n_offspring += offspring_counts.n_crossover
+                + offspring_counts.n_mutation
+                + offspring_counts.n_clone;
+        }
+
+        fn update_trials(
+            &mut self,
+            _generation: usize,
+            trials: &mut Vec<IrisState>,
+            _state_parameters: &IrisStateParameters,
+        ) {
+            assert_eq!(trials.len(), 1);
+            self.0.lock().unwrap().n_updates += 1;
+        }
+    }
+
+    #[test]
+    fn given_observer_when_iterating_then_callbacks_fire_once_per_generation() {
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(parameters())
+            .population_size(10)
+            .n_trials(1)
+            .build()
+            .unwrap();
+        let state_parameters = IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+        let callbacks = Arc::new(std::sync::Mutex::new(Callbacks::default()));
+
+        parameters
+            .build_engine_with(state_parameters)
+            .with_observer(Box::new(Counter(callbacks.clone())))
+            .with_observer(Box::new(RegenerateTrials))
+            .take(4)
+            .for_each(drop);
+
+        let callbacks = callbacks.lock().unwrap();
+        assert_eq!(callbacks.generations, vec![0, 1, 2, 3]);
+        assert_eq!(callbacks.n_variations, 4);
+        assert_eq!(callbacks.n_updates, 4);
+        // Half of every generation is replaced (gap = 0.5).
+        assert_eq!(callbacks.n_offspring, 4 * 5);
+    }
+
+    struct RequestedMutations(Arc<std::sync::Mutex<Vec<usize>>>);
+
+    impl GenerationObserver<IrisEngine> for RequestedMutations {
+        fn on_variation(&mut self, offspring_counts: VariationCounts) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(offspring_counts.requested.n_mutation);
+        }
+    }
+
+    #[test]
+    fn given_linear_mutation_schedule_when_iterating_then_each_generation_uses_its_scheduled_rate()
+    {
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(parameters())
+            .population_size(20)
+            .n_trials(1)
+            .n_generations(8)
+            .mutation_percent(Schedule::Linear { from: 0.8, to: 0. })
+            .crossover_percent(0.1)
+            .build()
+            .unwrap();
+        assert!(parameters.validate().is_ok());
+        let state_parameters = IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+        let requested = Arc::new(std::sync::Mutex::new(vec![]));
+
+        let mut engine = parameters
+            .build_engine_with(state_parameters)
+            .with_observer(Box::new(RequestedMutations(requested.clone())));
+        let mut rates = vec![];
+        while engine.next().is_some() {
+            rates.push(engine.rates());
+        }
+
+        assert_eq!(rates.len(), 9);
+        for (generation, (rates, requested)) in rates
+            .iter()
+            .zip(requested.lock().unwrap().iter())
+            .enumerate()
+        {
+            let expected = 0.8 + (0. - 0.8) * generation as f64 / 8.;
+            assert!((rates.mutation_percent - expected).abs() < 1e-12);
+            assert_eq!((rates.gap, rates.crossover_percent), (0.5, 0.1));
+            // Half of the population is bred every generation.
+            assert_eq!(*requested, (10. * rates.mutation_percent).floor() as usize);
+        }
+        assert_eq!(rates[8].mutation_percent, 0.);
+    }
+
+    #[test]
+    fn given_hall_of_fame_when_iterating_then_best_validated_individuals_are_archived() {
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(parameters())
+            .population_size(10)
+            .n_trials(1)
+            .hall_of_fame(Some(HallOfFameConfig {
+                capacity: 3,
+                n_candidates: 2,
+                n_validation_trials: 2,
+                validation_seed: 7,
+            }))
+            .build()
+            .unwrap();
+        let state_parameters = IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+        let mut engine = parameters.build_engine_with(state_parameters);
+
+        engine.by_ref().take(5).for_each(drop);
+
+        let hall_of_fame = engine.hall_of_fame().unwrap();
+        assert!(!hall_of_fame.is_empty() && hall_of_fame.len() <= 3);
+        assert!(hall_of_fame
+            .entries()
+            .windows(2)
+            .all(|pair| pair[0].fitness >= pair[1].fitness));
+        assert!(hall_of_fame
+            .entries()
+            .iter()
+            .all(|entry| entry.generation < 5));
+    }
+
+    fn valid_parameters() -> HyperParameters<IrisEngine> {
+        HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(parameters())
+            .build()
+            .unwrap()
+    }
+
+    fn violated_fields(parameters: &HyperParameters<IrisEngine>) -> Vec<String> {
+        parameters
+            .validate()
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|violation| violation.field)
+            .collect()
+    }
+
+    #[test]
+    fn given_each_invalid_field_when_validated_then_its_path_is_reported() {
+        let cases: &[(&str, fn(&mut HyperParameters<IrisEngine>))] = &[
+            ("population_size", |p| p.population_size = 0),
+            ("gap", |p| p.gap = 1.5.into()),
+            ("mutation_percent", |p| {
+                p.mutation_percent = (-0.1).into();
+                p.crossover_percent = 0.0.into();
+            }),
+            ("crossover_percent", |p| {
+                p.crossover_percent = 2.0.into();
+                p.mutation_percent = (-1.0).into();
+            }),
+            ("mutation_percent + crossover_percent", |p| {
+                p.mutation_percent = 0.8.into()
+            }),
+            ("n_trials", |p| p.n_trials = 0),
+            ("probe_trials", |p| p.probe_trials = 0),
+            ("repeats", |p| p.repeats = 0),
+            ("min_survivors", |p| p.min_survivors = 101),
+            ("max_wall_time", |p| p.max_wall_time = Some(Duration::ZERO)),
+            ("soft_deadline_ms", |p| p.soft_deadline_ms = Some(0)),
+            ("hall_of_fame", |p| {
+                p.hall_of_fame = Some(HallOfFameConfig {
+                    capacity: 0,
+                    n_candidates: 1,
+                    n_validation_trials: 1,
+                    validation_seed: 0,
+                })
+            }),
+            ("lineage_dot", |p| p.lineage_dot = true),
+            ("render_episodes", |p| {
+                p.render = true;
+                p.render_episodes = 0;
+            }),
+            ("program_parameters.max_instructions", |p| {
+                p.program_parameters.max_instructions = 0
+            }),
+            (
+                "program_parameters.instruction_generator_parameters.n_actions",
+                |p| {
+                    p.program_parameters
+                        .instruction_generator_parameters
+                        .n_actions = 0
+                },
+            ),
+            (
+                "program_parameters.instruction_generator_parameters.constant_probability",
+                |p| {
+                    p.program_parameters
+                        .instruction_generator_parameters
+                        .constant_probability = 1.5
+                },
+            ),
+            ("max_episode_steps", |p| p.max_episode_steps = Some(10)),
+            ("record_trajectory", |p| p.record_trajectory = true),
+            ("reward_shaping", |p| {
+                p.reward_shaping = RewardShaper::PotentialBased { scale: 1000. }
+            }),
+            ("normalization", |p| {
+                p.normalization = ObservationNormalization::Running(Default::default())
+            }),
+            ("eval_timeout_ms", |p| p.eval_timeout_ms = Some(0)),
+        ];
+
+        assert!(valid_parameters().validate().is_ok());
+
+        for (field, invalidate) in cases {
+            let mut parameters = valid_parameters();
+            invalidate(&mut parameters);
+
+            let fields = violated_fields(&parameters);
+            assert!(
+                fields.contains(&field.to_string()),
+                "{}: {:?}",
+                field,
+                fields
+            );
+        }
+    }
+
+    #[test]
+    fn given_several_invalid_fields_when_validated_then_all_are_reported() {
+        let mut parameters = valid_parameters();
+        parameters.population_size = 0;
+        parameters.gap = 1.5.into();
+        parameters.mutation_percent = 0.9.into();
+        parameters
+            .program_parameters
+            .instruction_generator_parameters
+            .n_actions = 0;
+
+        let fields = violated_fields(&parameters);
+        assert_eq!(
+            fields,
+            vec![
+                "population_size",
+                "gap",
+                "mutation_percent + crossover_percent",
+                "min_survivors",
+                "program_parameters.instruction_generator_parameters.n_actions",
+            ]
+        );
+
+        let error = LgpError::from(parameters.validate().unwrap_err());
+        assert!(error
+            .to_string()
+            .contains("`gap` is 1.5, expected in [0, 1]"));
+    }
+
+    /// Fails the first individual it sees, and that individual only.
+    trait Fault: Clone + 'static {
+        fn faulty() -> &'static Mutex<Option<Uuid>>;
+        fn fail();
+    }
+
+    #[derive(Clone)]
+    struct Panic;
+    #[derive(Clone)]
+    struct Hang;
+
+    impl Fault for Panic {
+        fn faulty() -> &'static Mutex<Option<Uuid>> {
+            static FAULTY: Mutex<Option<Uuid>> = Mutex::new(None);
+            &FAULTY
+        }
+
+        fn fail() {
+            panic!("Faulty individual.");
+        }
+    }
+
+    impl Fault for Hang {
+        fn faulty() -> &'static Mutex<Option<Uuid>> {
+            static FAULTY: Mutex<Option<Uuid>> = Mutex::new(None);
+            &FAULTY
+        }
+
+        fn fail() {
+            thread::sleep(Duration::from_secs(2));
+        }
+    }
+
+    struct FaultyFitness;
+
+    impl<F> Fitness<Program, IrisState, F> for FaultyFitness
+    where
+        F: Fault,
+    {
+        fn eval_fitness(program: &mut Program, states: &mut IrisState) -> f64 {
+            let faulty = *F::faulty().lock().unwrap().get_or_insert(program.id);
+            if program.id == faulty {
+                F::fail();
+            }
+
+            <FitnessEngine as Fitness<Program, IrisState, ()>>::eval_fitness(program, states)
+        }
+    }
+
+    #[derive(Clone)]
+    struct FaultyEngine<F>(PhantomData<F>);
+
+    impl<F> Core for FaultyEngine<F>
+    where
+        F: Fault,
+    {
+        type State = IrisState;
+        type StateParameters = IrisStateParameters;
+        type Individual = Program;
+        type ProgramParameters = ProgramGeneratorParameters;
+        type FitnessMarker = F;
+        type Generate = GenerateEngine;
+        type Fitness = FaultyFitness;
+        type Reset = ResetEngine;
+        type Breed = BreedEngine;
+        type Mutate = MutateEngine;
+        type Status = StatusEngine;
+        type Freeze = FreezeEngine;
+    }
+
+    fn faulty_run<F>(eval_timeout_ms: Option<u64>) -> (Vec<Program>, usize, usize)
+    where
+        F: Fault,
+    {
+        let parameters = HyperParametersBuilder::<FaultyEngine<F>>::default()
+            .program_parameters(parameters())
+            .population_size(5)
+            .n_trials(1)
+            .default_fitness(-1.)
+            .seed(Some(3))
+            .eval_timeout_ms(eval_timeout_ms)
+            .build()
+            .unwrap();
+        let state_parameters = IrisStateParameters::new(trials(1).pop().unwrap().dataset().clone());
+
+        let mut engine = parameters.build_engine_with(state_parameters);
+        let populations = engine.by_ref().take(3).collect_vec();
+
+        (
+            populations[0].clone(),
+            populations.len(),
+            engine.n_eval_failures(),
+        )
+    }
+
+    fn assert_faulty_gets_default_fitness<F>(population: &[Program])
+    where
+        F: Fault,
+    {
+        let faulty = F::faulty().lock().unwrap().unwrap();
+        let individual = population
+            .iter()
+            .find(|individual| individual.id == faulty)
+            .unwrap();
+
+        assert_eq!(population.len(), 5);
+        assert_eq!(StatusEngine::get_fitness(individual), -1.);
+    }
+
+    #[test]
+    fn given_panicking_evaluation_when_iterating_then_run_continues_with_default_fitness() {
+        let (population, n_generations, n_eval_failures) = faulty_run::<Panic>(None);
+
+        assert_eq!(n_generations, 3);
+        assert!(n_eval_failures >= 1);
+        assert_faulty_gets_default_fitness::<Panic>(&population);
+    }
+
+    #[test]
+    fn given_hanging_evaluation_when_timeout_elapses_then_run_continues_with_default_fitness() {
+        let (population, n_generations, n_eval_failures) = faulty_run::<Hang>(Some(100));
+
+        assert_eq!(n_generations, 3);
+        assert!(n_eval_failures >= 1);
+        assert_faulty_gets_default_fitness::<Hang>(&population);
     }
 }
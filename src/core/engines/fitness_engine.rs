@@ -1,3 +1,9 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use clap::ValueEnum;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
 use super::reset_engine::{Reset, ResetEngine};
 
 pub trait Fitness<I, S, P> {
@@ -11,3 +17,158 @@ impl Reset<f64> for ResetEngine {
 }
 
 pub struct FitnessEngine;
+
+/// Combines the scores of an individual across trials into its fitness.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FitnessAggregation {
+    #[default]
+    Mean,
+    Median,
+    /// Worst case, for evolving robust controllers on noisy tasks.
+    Min,
+    /// Linearly interpolated quantile, with `0.` the minimum and `1.` the maximum.
+    Quantile(f64),
+}
+
+impl FitnessAggregation {
+    /// Aggregates trial scores after replacing non-finite ones by `default_fitness`. Yields NaN
+    /// when there are no scores.
+    pub fn aggregate(&self, scores: &[f64], default_fitness: f64) -> f64 {
+        if scores.is_empty() {
+            return f64::NAN;
+        }
+
+        let scores = scores
+            .iter()
+            .map(|s| if !s.is_finite() { default_fitness } else { *s })
+            .collect_vec();
+
+        match *self {
+            FitnessAggregation::Mean => scores.iter().sum::<f64>() / scores.len() as f64,
+            FitnessAggregation::Median => quantile(&scores, 0.5),
+            FitnessAggregation::Min => quantile(&scores, 0.),
+            FitnessAggregation::Quantile(q) => quantile(&scores, q),
+        }
+    }
+}
+
+/// Whether higher or lower fitness is better. Ranked populations always start with the best
+/// individual, so best, median and worst follow the direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum Objective {
+    #[default]
+    Maximize,
+    /// E.g. for error or cost objectives.
+    Minimize,
+}
+
+impl Objective {
+    /// Orders fitness values from best to worst.
+    pub fn compare(&self, a: f64, b: f64) -> Ordering {
+        match self {
+            Objective::Maximize => b.total_cmp(&a),
+            Objective::Minimize => a.total_cmp(&b),
+        }
+    }
+
+    /// Whether fitness `a` is strictly better than `b`.
+    pub fn is_better(&self, a: f64, b: f64) -> bool {
+        match self {
+            Objective::Maximize => a > b,
+            Objective::Minimize => a < b,
+        }
+    }
+
+    /// `fitness` oriented so that higher is better, e.g. for Pareto dominance.
+    pub fn maximized(&self, fitness: f64) -> f64 {
+        match self {
+            Objective::Maximize => fitness,
+            Objective::Minimize => -fitness,
+        }
+    }
+}
+
+fn quantile(scores: &[f64], q: f64) -> f64 {
+    let sorted = scores
+        .iter()
+        .copied()
+        .sorted_by(f64::total_cmp)
+        .collect_vec();
+
+    let position = q.clamp(0., 1.) * (sorted.len() - 1) as f64;
+    let lower = sorted[position.floor() as usize];
+    let upper = sorted[position.ceil() as usize];
+
+    lower + (upper - lower) * position.fract()
+}
+
+impl FromStr for FitnessAggregation {
+    type Err = String;
+
+    /// Parses `mean`, `median`, `min` or `quantile:<q>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "mean" => Ok(FitnessAggregation::Mean),
+            "median" => Ok(FitnessAggregation::Median),
+            "min" => Ok(FitnessAggregation::Min),
+            other => other
+                .strip_prefix("quantile:")
+                .and_then(|q| q.parse::<f64>().ok())
+                .filter(|q| (0. ..=1.).contains(q))
+                .map(FitnessAggregation::Quantile)
+                .ok_or_else(|| format!("Unknown fitness aggregation `{}`", other)),
+        }
+    }
+}
+
+impl fmt::Display for FitnessAggregation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FitnessAggregation::Mean => write!(f, "mean"),
+            FitnessAggregation::Median => write!(f, "median"),
+            FitnessAggregation::Min => write!(f, "min"),
+            FitnessAggregation::Quantile(q) => write!(f, "quantile:{}", q),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCORES: [f64; 5] = [4., f64::NAN, 1., 3., 2.];
+
+    #[test]
+    fn given_scores_with_nan_when_aggregated_then_each_mode_uses_the_default_fitness() {
+        // The NaN counts as the default fitness of 0: [0, 1, 2, 3, 4].
+        assert_eq!(FitnessAggregation::Mean.aggregate(&SCORES, 0.), 2.);
+        assert_eq!(FitnessAggregation::Median.aggregate(&SCORES, 0.), 2.);
+        assert_eq!(FitnessAggregation::Min.aggregate(&SCORES, 0.), 0.);
+        assert_eq!(FitnessAggregation::Quantile(1.).aggregate(&SCORES, 0.), 4.);
+        assert_eq!(
+            FitnessAggregation::Quantile(0.125).aggregate(&SCORES, 0.),
+            0.5
+        );
+
+        assert_eq!(FitnessAggregation::Min.aggregate(&SCORES, 1.5), 1.);
+        assert!(FitnessAggregation::Mean.aggregate(&[], 0.).is_nan());
+    }
+
+    #[test]
+    fn given_names_when_parsed_then_round_trip_through_display() {
+        for aggregation in [
+            FitnessAggregation::Mean,
+            FitnessAggregation::Median,
+            FitnessAggregation::Min,
+            FitnessAggregation::Quantile(0.25),
+        ] {
+            assert_eq!(aggregation.to_string().parse(), Ok(aggregation));
+        }
+
+        assert!("quantile:2".parse::<FitnessAggregation>().is_err());
+        assert!("max".parse::<FitnessAggregation>().is_err());
+    }
+}
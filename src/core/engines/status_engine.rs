@@ -1,3 +1,9 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::reset_engine::{Reset, ResetEngine};
+
 pub struct StatusEngine;
 
 pub trait Status<T> {
@@ -5,4 +11,199 @@ pub trait Status<T> {
     fn evaluated(item: &T) -> bool;
     fn set_fitness(program: &mut T, fitness: f64);
     fn get_fitness(program: &T) -> f64;
+    /// Number of instructions that can influence the action registers.
+    fn effective_length(item: &T) -> usize;
+    /// Returns and clears the number of register values saturated while evaluating the item.
+    fn take_saturations(_item: &mut T) -> usize {
+        0
+    }
+    /// Keeps the per-trial scores the fitness was aggregated from, for items that store them.
+    fn set_trial_scores(_item: &mut T, _scores: Vec<f64>) {}
+    /// Per-trial scores kept by `set_trial_scores`, until the item is reset.
+    fn trial_scores(_item: &T) -> Option<&[f64]> {
+        None
+    }
+}
+
+/// Fitness of an individual. Ordered from best to worst as: valid fitness by value, invalid,
+/// then not evaluated, so ranking puts unevaluated individuals last.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FitnessState {
+    #[default]
+    NotEvaluated,
+    Valid(f64),
+    /// The evaluation yielded a non-finite fitness.
+    Invalid,
+}
+
+impl FitnessState {
+    /// Classifies the fitness an evaluation yielded: NaN and infinities are invalid. Only
+    /// resetting makes an individual not evaluated.
+    pub fn from_fitness(fitness: f64) -> Self {
+        if fitness.is_finite() {
+            FitnessState::Valid(fitness)
+        } else {
+            FitnessState::Invalid
+        }
+    }
+
+    /// Raw fitness; NaN when not evaluated and negative infinity when invalid.
+    pub fn fitness(&self) -> f64 {
+        match self {
+            FitnessState::NotEvaluated => f64::NAN,
+            FitnessState::Valid(fitness) => *fitness,
+            FitnessState::Invalid => f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        matches!(self, FitnessState::Valid(_))
+    }
+
+    pub fn is_evaluated(&self) -> bool {
+        !matches!(self, FitnessState::NotEvaluated)
+    }
+}
+
+impl Ord for FitnessState {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let rank = |state: &FitnessState| match state {
+            FitnessState::NotEvaluated => 0,
+            FitnessState::Invalid => 1,
+            FitnessState::Valid(_) => 2,
+        };
+
+        match (self, other) {
+            (FitnessState::Valid(a), FitnessState::Valid(b)) => a.total_cmp(b),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+impl PartialOrd for FitnessState {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FitnessState {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FitnessState {}
+
+impl Reset<FitnessState> for ResetEngine {
+    fn reset(item: &mut FitnessState) {
+        *item = FitnessState::NotEvaluated;
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TaggedFitnessState {
+    NotEvaluated,
+    Valid(f64),
+    Invalid,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FitnessRepr {
+    State(TaggedFitnessState),
+    /// Plain floats saved before fitness states existed, with NaN saved as `null`.
+    Legacy(Option<f64>),
+}
+
+impl<'de> Deserialize<'de> for FitnessState {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Binary formats are not self-describing, so legacy floats cannot be told apart there;
+        // they only ever held the tagged form.
+        let repr = if deserializer.is_human_readable() {
+            FitnessRepr::deserialize(deserializer)?
+        } else {
+            FitnessRepr::State(TaggedFitnessState::deserialize(deserializer)?)
+        };
+
+        Ok(repr.into())
+    }
+}
+
+impl From<FitnessRepr> for FitnessState {
+    fn from(repr: FitnessRepr) -> Self {
+        match repr {
+            FitnessRepr::State(TaggedFitnessState::NotEvaluated) => FitnessState::NotEvaluated,
+            FitnessRepr::State(TaggedFitnessState::Valid(fitness)) => {
+                FitnessState::from_fitness(fitness)
+            }
+            FitnessRepr::State(TaggedFitnessState::Invalid) => FitnessState::Invalid,
+            FitnessRepr::Legacy(Some(fitness)) => FitnessState::from_fitness(fitness),
+            FitnessRepr::Legacy(None) => FitnessState::NotEvaluated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_mixed_states_when_sorted_descending_then_unevaluated_come_last() {
+        let mut states = vec![
+            FitnessState::NotEvaluated,
+            FitnessState::Valid(0.),
+            FitnessState::Invalid,
+            FitnessState::Valid(-1.),
+            FitnessState::NotEvaluated,
+            FitnessState::Valid(0.5),
+        ];
+        states.sort_by(|a, b| b.cmp(a));
+
+        assert_eq!(
+            states,
+            vec![
+                FitnessState::Valid(0.5),
+                FitnessState::Valid(0.),
+                FitnessState::Valid(-1.),
+                FitnessState::Invalid,
+                FitnessState::NotEvaluated,
+                FitnessState::NotEvaluated,
+            ]
+        );
+    }
+
+    #[test]
+    fn given_non_finite_fitness_when_classified_then_it_is_invalid_not_unevaluated() {
+        for fitness in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(FitnessState::from_fitness(fitness), FitnessState::Invalid);
+        }
+        assert_eq!(FitnessState::from_fitness(0.5), FitnessState::Valid(0.5));
+
+        let mut state = FitnessState::from_fitness(f64::NAN);
+        assert!(state.is_evaluated());
+        ResetEngine::reset(&mut state);
+        assert!(!state.is_evaluated());
+    }
+
+    #[test]
+    fn given_old_and_new_formats_when_deserialized_then_states_match() {
+        let parse = |json: &str| serde_json::from_str::<FitnessState>(json).unwrap();
+
+        assert_eq!(parse("0.0"), FitnessState::Valid(0.));
+        assert_eq!(parse("0.75"), FitnessState::Valid(0.75));
+        assert_eq!(parse("null"), FitnessState::NotEvaluated);
+
+        for state in [
+            FitnessState::NotEvaluated,
+            FitnessState::Valid(0.),
+            FitnessState::Invalid,
+        ] {
+            assert_eq!(parse(&serde_json::to_string(&state).unwrap()), state);
+        }
+    }
 }
@@ -10,10 +10,45 @@ pub trait State: Sized {
     fn get(&mut self) -> Option<&mut Self>;
 }
 
+/// How the current episode stands, telling a true end of the episode apart from a cut-off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpisodeStatus {
+    Running,
+    /// The environment reached a terminal state (e.g. the pole fell); no reward follows it.
+    Terminated,
+    /// The episode was cut off (e.g. by a time limit) in a state that would have gone on.
+    Truncated,
+}
+
 pub trait RlState: State {
     /// Returns true if episode count > MAX or terminal_signal sent from environment.
     fn is_terminal(&mut self) -> bool;
 
     // Returns the initial state.
     fn get_initial_state(&self) -> Vec<f64>;
+
+    /// Actions taken in the current episode; 0 for states that do not count them.
+    fn steps(&self) -> usize {
+        0
+    }
+
+    /// States that do not tell truncation apart report every end of an episode as a
+    /// termination.
+    fn status(&mut self) -> EpisodeStatus {
+        if self.is_terminal() {
+            EpisodeStatus::Terminated
+        } else {
+            EpisodeStatus::Running
+        }
+    }
+}
+
+/// Summary of how the episode played on a state went, compared between individuals by novelty
+/// search.
+pub trait BehaviorDescriptor {
+    /// Descriptor of the episode played so far, or `None` when the state does not describe
+    /// behaviour.
+    fn behavior_descriptor(&self) -> Option<Vec<f64>> {
+        None
+    }
 }
@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+
+use super::{characteristics::Genotype, engines::fitness_engine::Objective};
+
+/// Archive of the best individuals seen during a run. Candidates are re-evaluated on a fixed set
+/// of validation trials, so entries from different generations are comparable even when the
+/// training trials change.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HallOfFameConfig {
+    /// Entries kept; the worst entry is evicted once it is full.
+    pub capacity: usize,
+    /// Best individuals of every generation considered for the archive.
+    #[serde(default = "default_n_candidates")]
+    pub n_candidates: usize,
+    /// Validation trials candidates are evaluated on.
+    #[serde(default = "default_n_validation_trials")]
+    pub n_validation_trials: usize,
+    /// Seed of the validation trials, independent of the run seed so that runs share them.
+    #[serde(default)]
+    pub validation_seed: u64,
+}
+
+fn default_n_candidates() -> usize {
+    1
+}
+
+fn default_n_validation_trials() -> usize {
+    10
+}
+
+impl HallOfFameConfig {
+    /// Capacity, candidates and validation trials must all be positive.
+    pub fn is_valid(&self) -> bool {
+        self.capacity >= 1 && self.n_candidates >= 1 && self.n_validation_trials >= 1
+    }
+}
+
+/// An archived individual, frozen when it entered the archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HallOfFameEntry<I> {
+    /// Generation the individual was first archived in.
+    pub generation: usize,
+    /// Fitness on the validation trials.
+    pub fitness: f64,
+    pub individual: I,
+}
+
+/// Entries ordered from best to worst validation fitness; saved with the run outputs as
+/// `hall_of_fame.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HallOfFame<I> {
+    capacity: usize,
+    #[serde(default)]
+    objective: Objective,
+    entries: Vec<HallOfFameEntry<I>>,
+}
+
+impl<I> HallOfFame<I>
+where
+    I: Genotype,
+{
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            objective: Objective::default(),
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Ranks entries under `objective` instead of maximizing their fitness.
+    pub fn with_objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[HallOfFameEntry<I>] {
+        &self.entries
+    }
+
+    pub fn best(&self) -> Option<&HallOfFameEntry<I>> {
+        self.entries.first()
+    }
+
+    /// Whether `individual`, or one with the same id or instructions, is archived.
+    pub fn contains(&self, individual: &I) -> bool {
+        self.entries.iter().any(|entry| {
+            let other = &entry.individual;
+            (individual.id().is_some() && individual.id() == other.id())
+                || individual.instructions() == other.instructions()
+        })
+    }
+
+    /// Archives `individual` if it beats the worst entry or the archive is not full, evicting
+    /// the worst entry when needed. Entries with equal fitness keep their insertion order, so
+    /// the newest of them is evicted first. Returns whether the individual was archived.
+    pub fn insert(&mut self, generation: usize, fitness: f64, individual: I) -> bool {
+        if !fitness.is_finite() || self.capacity == 0 || self.contains(&individual) {
+            return false;
+        }
+
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| self.objective.is_better(fitness, entry.fitness))
+            .unwrap_or(self.entries.len());
+
+        if position >= self.capacity {
+            return false;
+        }
+
+        self.entries.insert(
+            position,
+            HallOfFameEntry {
+                generation,
+                fitness,
+                individual,
+            },
+        );
+        self.entries.truncate(self.capacity);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::{
+        core::{
+            engines::core_engine::Core,
+            instruction::InstructionGeneratorParametersBuilder,
+            program::{Program, ProgramGeneratorParametersBuilder},
+        },
+        problems::iris::IrisEngine,
+    };
+
+    use super::*;
+
+    fn programs(n: usize) -> Vec<Program> {
+        let parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(20)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_actions(3)
+                    .n_inputs(4)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        IrisEngine::init_population(parameters, n)
+    }
+
+    #[test]
+    fn given_fluctuating_fitness_then_the_true_best_are_kept() {
+        let individuals = programs(6);
+        let mut hall_of_fame = HallOfFame::new(3);
+
+        // The best of each generation, whose fitness rises and falls.
+        let fitness = [0.4, 0.9, 0.2, 0.7, 0.95, 0.1];
+        for (generation, (individual, fitness)) in individuals.iter().zip(fitness).enumerate() {
+            hall_of_fame.insert(generation, fitness, individual.clone());
+        }
+
+        assert_eq!(hall_of_fame.len(), 3);
+        assert_eq!(
+            hall_of_fame
+                .entries()
+                .iter()
+                .map(|entry| (entry.generation, entry.fitness))
+                .collect_vec(),
+            vec![(4, 0.95), (1, 0.9), (3, 0.7)]
+        );
+        assert_eq!(
+            hall_of_fame.best().unwrap().individual.id(),
+            individuals[4].id()
+        );
+    }
+
+    #[test]
+    fn given_minimized_fitness_then_the_lowest_are_kept() {
+        let individuals = programs(6);
+        let mut hall_of_fame = HallOfFame::new(3).with_objective(Objective::Minimize);
+
+        let fitness = [0.4, 0.9, 0.2, 0.7, 0.95, 0.1];
+        for (generation, (individual, fitness)) in individuals.iter().zip(fitness).enumerate() {
+            hall_of_fame.insert(generation, fitness, individual.clone());
+        }
+
+        assert_eq!(
+            hall_of_fame
+                .entries()
+                .iter()
+                .map(|entry| (entry.generation, entry.fitness))
+                .collect_vec(),
+            vec![(5, 0.1), (2, 0.2), (0, 0.4)]
+        );
+    }
+
+    #[test]
+    fn given_full_archive_then_the_worst_and_newest_entry_is_evicted() {
+        let individuals = programs(5);
+        let mut hall_of_fame = HallOfFame::new(2);
+
+        assert!(hall_of_fame.insert(0, 0.5, individuals[0].clone()));
+        assert!(hall_of_fame.insert(1, 0.5, individuals[1].clone()));
+        // Ties with the worst entry do not displace it.
+        assert!(!hall_of_fame.insert(2, 0.5, individuals[2].clone()));
+        // Evicts the newest of the tied entries.
+        assert!(hall_of_fame.insert(3, 0.6, individuals[3].clone()));
+        assert_eq!(
+            hall_of_fame
+                .entries()
+                .iter()
+                .map(|entry| entry.generation)
+                .collect_vec(),
+            vec![3, 0]
+        );
+
+        // Archived individuals and non-finite fitness are rejected.
+        assert!(!hall_of_fame.insert(4, 0.9, individuals[3].clone()));
+        assert!(!hall_of_fame.insert(4, f64::NAN, individuals[4].clone()));
+        assert_eq!(hall_of_fame.len(), 2);
+    }
+}
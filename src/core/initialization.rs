@@ -0,0 +1,321 @@
+use std::{fmt, iter::repeat_with, path::PathBuf, str::FromStr};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::utils::error::{LgpError, LgpResult};
+
+use super::{
+    characteristics::{Format, Genotype, Load},
+    engines::{
+        core_engine::{Core, HyperParameters},
+        generate_engine::Generate,
+        reset_engine::Reset,
+    },
+    instruction::InstructionGeneratorParameters,
+    program::ProgramShape,
+};
+
+/// How the first generation is created.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InitStrategy {
+    /// Programs of uniformly random lengths up to `max_instructions`.
+    #[default]
+    Random,
+    /// Spreads programs evenly over the lengths `min..=max`: half of the programs at each length
+    /// have exactly that many instructions, the other half a random length from `min` up to it.
+    RampedHalfAndHalf { min: usize, max: usize },
+    /// Starts from the individuals saved at `paths` (e.g. the `best.json` of an earlier run),
+    /// filling the rest of the population randomly. Saved individuals must have the shape of
+    /// the current program parameters.
+    Seeded { paths: Vec<PathBuf> },
+}
+
+impl InitStrategy {
+    /// The first generation of `hp.population_size` individuals.
+    pub fn population<C>(&self, hp: &HyperParameters<C>) -> LgpResult<Vec<C::Individual>>
+    where
+        C: Core,
+    {
+        let program_parameters = hp.program_parameters;
+
+        match self {
+            InitStrategy::Random => Ok(C::init_population(program_parameters, hp.population_size)),
+            &InitStrategy::RampedHalfAndHalf { min, max } => {
+                let n_lengths = max.saturating_sub(min) + 1;
+                let population = (0..hp.population_size)
+                    .map(|index| {
+                        let length = min + index % n_lengths;
+                        let full = (index / n_lengths) % 2 == 0;
+
+                        let mut using = program_parameters;
+                        let shape = using.program_parameters();
+                        shape.max_instructions = length;
+                        shape.min_instructions = if full { length } else { min };
+
+                        C::Generate::generate(using)
+                    })
+                    .collect();
+
+                Ok(population)
+            }
+            InitStrategy::Seeded { paths } => {
+                let mut shape = program_parameters;
+                let instruction_parameters = *shape.instruction_parameters();
+
+                let mut population = paths
+                    .iter()
+                    .map(|path| {
+                        let mut individual = C::Individual::load_as(path, Format::from_path(path))?;
+                        check_shape(&individual, &instruction_parameters).map_err(|reason| {
+                            LgpError::Config(format!(
+                                "Seeded individual `{}` does not fit the program parameters: {}",
+                                path.display(),
+                                reason
+                            ))
+                        })?;
+
+                        individual.unfreeze();
+                        C::Reset::reset(&mut individual);
+                        Ok(individual)
+                    })
+                    .collect::<LgpResult<Vec<_>>>()?;
+
+                if population.len() > hp.population_size {
+                    warn!(
+                        "{} seeded individuals exceed the population size of {}, keeping the \
+                         first ones.",
+                        population.len(),
+                        hp.population_size
+                    );
+                    population.truncate(hp.population_size);
+                }
+
+                let n_random = hp.population_size - population.len();
+                population.extend(
+                    repeat_with(|| C::Generate::generate(program_parameters)).take(n_random),
+                );
+
+                Ok(population)
+            }
+        }
+    }
+
+    /// Ramps must satisfy `1 <= min <= max`, and seeding needs at least one path.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            InitStrategy::Random => true,
+            InitStrategy::RampedHalfAndHalf { min, max } => 1 <= *min && min <= max,
+            InitStrategy::Seeded { paths } => !paths.is_empty(),
+        }
+    }
+}
+
+/// Why `individual` cannot be evolved with `using`, if it cannot: its registers, actions and
+/// the registers and inputs its instructions read must all exist under `using`.
+pub fn check_shape(
+    individual: &impl Genotype,
+    using: &InstructionGeneratorParameters,
+) -> Result<(), String> {
+    let registers = individual.registers();
+
+    if registers.n_outputs() != using.n_output_registers() {
+        return Err(format!(
+            "{} output registers, expected {}",
+            registers.n_outputs(),
+            using.n_output_registers()
+        ));
+    }
+    if registers.len() != using.n_registers() {
+        return Err(format!(
+            "{} registers, expected {}",
+            registers.len(),
+            using.n_registers()
+        ));
+    }
+    if let Some(n_actions) = individual.n_actions().filter(|&n| n != using.n_actions) {
+        return Err(format!(
+            "{} actions, expected {}",
+            n_actions, using.n_actions
+        ));
+    }
+
+    let out_of_range = individual.instructions().iter().find(|instruction| {
+        instruction.src_idx() >= using.n_registers()
+            || instruction
+                .register_operand()
+                .map_or(false, |idx| idx >= using.n_registers())
+            || instruction
+                .input_operand()
+                .map_or(false, |idx| idx >= using.n_inputs)
+    });
+
+    match out_of_range {
+        Some(instruction) => Err(format!(
+            "`{}` reads beyond {} registers and {} inputs",
+            instruction,
+            using.n_registers(),
+            using.n_inputs
+        )),
+        None => Ok(()),
+    }
+}
+
+impl FromStr for InitStrategy {
+    type Err = String;
+
+    /// Parses `random`, `ramped_half_and_half:<min>:<max>` or `seeded:<path>[,<path>...]`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if s == "random" {
+            return Ok(InitStrategy::Random);
+        }
+        if let Some(bounds) = s.strip_prefix("ramped_half_and_half:") {
+            return bounds
+                .split(':')
+                .map(|bound| bound.trim().parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+                .and_then(|bounds| match bounds[..] {
+                    [min, max] => Some(InitStrategy::RampedHalfAndHalf { min, max }),
+                    _ => None,
+                })
+                .ok_or_else(|| format!("Invalid ramp `{}`", bounds));
+        }
+        if let Some(paths) = s.strip_prefix("seeded:") {
+            return Ok(InitStrategy::Seeded {
+                paths: paths.split(',').map(|path| path.trim().into()).collect(),
+            });
+        }
+
+        Err(format!("Unknown initialization strategy `{}`", s))
+    }
+}
+
+impl fmt::Display for InitStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitStrategy::Random => write!(f, "random"),
+            InitStrategy::RampedHalfAndHalf { min, max } => {
+                write!(f, "ramped_half_and_half:{}:{}", min, max)
+            }
+            InitStrategy::Seeded { paths } => write!(
+                f,
+                "seeded:{}",
+                paths.iter().map(|path| path.display()).join(",")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, env};
+
+    use uuid::Uuid;
+
+    use crate::{
+        core::{
+            characteristics::Save,
+            engines::core_engine::HyperParametersBuilder,
+            instruction::InstructionGeneratorParametersBuilder,
+            program::{ProgramGeneratorParameters, ProgramGeneratorParametersBuilder},
+        },
+        problems::iris::IrisEngine,
+    };
+
+    use super::*;
+
+    fn program_parameters(n_calculation_registers: usize) -> ProgramGeneratorParameters {
+        ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_calculation_registers(n_calculation_registers)
+                    .n_actions(3)
+                    .n_inputs(4)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    fn hyper_parameters(
+        init_strategy: InitStrategy,
+        n_calculation_registers: usize,
+    ) -> HyperParameters<IrisEngine> {
+        HyperParametersBuilder::<IrisEngine>::default()
+            .population_size(40)
+            .init_strategy(init_strategy)
+            .program_parameters(program_parameters(n_calculation_registers))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn given_ramped_half_and_half_then_lengths_spread_over_the_ramp() {
+        let hp = hyper_parameters(InitStrategy::RampedHalfAndHalf { min: 3, max: 6 }, 1);
+        let population = hp.init_strategy.population(&hp).unwrap();
+
+        assert_eq!(population.len(), 40);
+
+        let lengths = population
+            .iter()
+            .map(|individual| individual.instructions().len())
+            .collect_vec();
+        assert!(lengths.iter().all(|length| (3..=6).contains(length)));
+
+        // Every length is reached at least by its full programs, a quarter of the population.
+        let counts: HashMap<usize, usize> = lengths.iter().copied().counts();
+        assert!((3..=6).all(|length| counts.get(&length).copied().unwrap_or(0) >= 5));
+    }
+
+    #[test]
+    fn given_saved_individual_when_seeded_then_it_joins_a_random_remainder() {
+        let path = env::temp_dir()
+            .join(Uuid::new_v4().to_string())
+            .join("best.json");
+
+        let donor = hyper_parameters(InitStrategy::Random, 1);
+        let saved = IrisEngine::init_population(donor.program_parameters, 1).remove(0);
+        saved.save(path.to_str().unwrap()).unwrap();
+
+        let hp = hyper_parameters(
+            InitStrategy::Seeded {
+                paths: vec![path.clone()],
+            },
+            1,
+        );
+        let population = hp.init_strategy.population(&hp).unwrap();
+
+        assert_eq!(population.len(), 40);
+        assert_eq!(population[0].instructions(), saved.instructions());
+        assert_eq!(population[0].id(), saved.id());
+
+        let mismatched = hyper_parameters(InitStrategy::Seeded { paths: vec![path] }, 3);
+        let error = mismatched
+            .init_strategy
+            .population(&mismatched)
+            .unwrap_err();
+        assert!(matches!(error, LgpError::Config(_)));
+        assert!(error.to_string().contains("registers"));
+    }
+
+    #[test]
+    fn given_specs_when_parsed_then_round_trip_through_display() {
+        for spec in [
+            "random",
+            "ramped_half_and_half:2:8",
+            "seeded:runs/a/best.json,runs/b/best.json",
+        ] {
+            assert_eq!(spec.parse::<InitStrategy>().unwrap().to_string(), spec);
+        }
+
+        assert!("ramped_half_and_half:2".parse::<InitStrategy>().is_err());
+        assert!("grow".parse::<InitStrategy>().is_err());
+    }
+}
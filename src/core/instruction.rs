@@ -1,56 +1,146 @@
 use clap::Args;
 use derivative::Derivative;
 use derive_builder::Builder;
+use itertools::Itertools;
 use rand::distributions::Standard;
 use rand::prelude::Distribution;
+use rand::seq::SliceRandom;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
-use crate::utils::random::generator;
+use crate::utils::error::{require, ConfigViolation};
+use crate::utils::random::{generator, standard_normal};
 
 use super::engines::generate_engine::{Generate, GenerateEngine};
 use super::engines::mutate_engine::{Mutate, MutateEngine};
 use super::environment::State;
-use super::registers::Registers;
+use super::instructions::OpSet;
+use super::registers::{RegisterInit, RegisterSaturation, Registers};
 use derive_more::Display;
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Copy, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Copy, Deserialize)]
 pub enum Mode {
     External,
     Internal,
+    /// The operand is the instruction's own immediate constant.
+    Constant,
 }
 
-#[derive(Clone, Copy, Debug, Display, Serialize, PartialEq, Eq, Deserialize)]
+/// Largest argument passed to `exp`, keeping results finite.
+pub const MAX_EXP_ARGUMENT: f64 = 50.;
+/// Smallest magnitude passed to `ln`, keeping results finite.
+pub const MIN_LN_ARGUMENT: f64 = 1e-10;
+
+/// Operations are serialized by name so saved programs survive changes to the configured op set.
+#[derive(Clone, Copy, Debug, Display, Serialize, PartialEq, Eq, Hash, Deserialize)]
 pub enum Op {
     #[display(fmt = "+")]
+    #[serde(rename = "add", alias = "Add")]
     Add,
     #[display(fmt = "*")]
+    #[serde(rename = "mul", alias = "Mult")]
     Mult,
     #[display(fmt = "/")]
+    #[serde(rename = "div", alias = "Divide")]
     Divide,
     #[display(fmt = "-")]
+    #[serde(rename = "sub", alias = "Sub")]
     Sub,
+    #[display(fmt = "sin")]
+    #[serde(rename = "sin")]
+    Sin,
+    #[display(fmt = "cos")]
+    #[serde(rename = "cos")]
+    Cos,
+    #[display(fmt = "exp")]
+    #[serde(rename = "exp")]
+    Exp,
+    #[display(fmt = "ln")]
+    #[serde(rename = "ln")]
+    Ln,
+    #[display(fmt = "max")]
+    #[serde(rename = "max")]
+    Max,
+    #[display(fmt = "min")]
+    #[serde(rename = "min")]
+    Min,
+    /// Executes the next instruction only if the source register is greater than the operand.
+    #[display(fmt = ">")]
+    #[serde(rename = "if_gt")]
+    IfGreater,
+    /// Executes the next instruction only if the source register is less than the operand.
+    #[display(fmt = "<")]
+    #[serde(rename = "if_lt")]
+    IfLess,
 }
 
 impl Op {
+    pub const ALL: [Op; 10] = [
+        Op::Add,
+        Op::Mult,
+        Op::Divide,
+        Op::Sub,
+        Op::Sin,
+        Op::Cos,
+        Op::Exp,
+        Op::Ln,
+        Op::Max,
+        Op::Min,
+    ];
+
+    pub const CONDITIONALS: [Op; 2] = [Op::IfGreater, Op::IfLess];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Add => "add",
+            Op::Mult => "mul",
+            Op::Divide => "div",
+            Op::Sub => "sub",
+            Op::Sin => "sin",
+            Op::Cos => "cos",
+            Op::Exp => "exp",
+            Op::Ln => "ln",
+            Op::Max => "max",
+            Op::Min => "min",
+            Op::IfGreater => "if_gt",
+            Op::IfLess => "if_lt",
+        }
+    }
+
+    /// Looks up an arithmetic op by name; conditionals are enabled separately.
+    pub fn from_name(name: &str) -> Option<Op> {
+        Op::ALL.into_iter().find(|op| op.name() == name)
+    }
+
+    pub fn is_conditional(&self) -> bool {
+        matches!(self, Op::IfGreater | Op::IfLess)
+    }
+
+    /// Unary operations only transform the source register and ignore the second operand.
+    pub fn is_unary(&self) -> bool {
+        matches!(self, Op::Sin | Op::Cos | Op::Exp | Op::Ln)
+    }
+
+    /// Applies the operation with protected semantics: division by zero yields 1, `exp` clamps
+    /// its argument and `ln` takes the logarithm of the clamped magnitude.
     pub fn apply(&self, a: f64, b: f64) -> f64 {
         match *self {
             Op::Add => a + b,
             Op::Mult => a * b,
-            Op::Divide => a / 2.,
+            Op::Divide if b == 0. => 1.,
+            Op::Divide => a / b,
             Op::Sub => a - b,
-        }
-    }
-}
-
-impl Distribution<Op> for Standard {
-    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Op {
-        match rng.gen_range(0..=3) {
-            0 => Op::Add,
-            1 => Op::Mult,
-            2 => Op::Divide,
-            _ => Op::Sub,
+            Op::Sin => a.sin(),
+            Op::Cos => a.cos(),
+            Op::Exp => a.min(MAX_EXP_ARGUMENT).exp(),
+            Op::Ln => a.abs().max(MIN_LN_ARGUMENT).ln(),
+            Op::Max => a.max(b),
+            Op::Min => a.min(b),
+            // Conditionals never write to their source register.
+            Op::IfGreater | Op::IfLess => a,
         }
     }
 }
@@ -64,25 +154,292 @@ impl Distribution<Mode> for Standard {
     }
 }
 
+/// Per-input scales of external operands. Interned for the lifetime of the process so generator
+/// parameters stay `Copy`; configurations hold a handful of them at most.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<f64>", into = "Vec<f64>")]
+pub struct InputScales(&'static [f64]);
+
+impl InputScales {
+    pub fn new(scales: Vec<f64>) -> Self {
+        InputScales(Box::leak(scales.into_boxed_slice()))
+    }
+
+    pub fn as_slice(&self) -> &'static [f64] {
+        self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<f64>> for InputScales {
+    fn from(scales: Vec<f64>) -> Self {
+        InputScales::new(scales)
+    }
+}
+
+impl From<InputScales> for Vec<f64> {
+    fn from(scales: InputScales) -> Self {
+        scales.0.to_vec()
+    }
+}
+
+impl FromStr for InputScales {
+    type Err = String;
+
+    /// Parses comma-separated scales such as `0.2,1,2.4,1`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|scale| scale.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map(InputScales::new)
+            .map_err(|_| format!("Invalid input scales `{}`", s))
+    }
+}
+
+impl fmt::Display for InputScales {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.iter().join(","))
+    }
+}
+
 #[derive(Clone, Derivative, Debug, Serialize, Args, PartialEq, Deserialize, Builder)]
 #[derivative(Copy)]
+#[builder(build_fn(validate = "Self::validate"))]
 pub struct InstructionGeneratorParameters {
-    #[arg(long, default_value = "1")]
+    /// Working registers following the output registers. Formerly `n_extras`.
+    #[arg(long, alias = "n-extras", default_value = "1")]
     #[builder(default = "1")]
-    pub n_extras: usize,
+    #[serde(alias = "n_extras")]
+    pub n_calculation_registers: usize,
+    /// Registers read as the program's outputs; defaults to one per action.
+    #[arg(long)]
+    #[builder(default = "None")]
+    #[serde(default)]
+    pub n_output_registers: Option<usize>,
+    #[arg(long, value_enum, default_value = "zeros")]
+    #[builder(default = "RegisterInit::default()")]
+    #[serde(default)]
+    pub register_init: RegisterInit,
+    /// Applied after every instruction: `none`, `clamp:<min>:<max>` or `wrap:<min>:<max>`.
+    #[arg(long, default_value = "none")]
+    #[builder(default = "RegisterSaturation::default()")]
+    #[serde(default)]
+    pub register_saturation: RegisterSaturation,
+    /// Scale of external operands, baked into instructions when they are generated.
     #[arg(long, default_value = "10.")]
     #[builder(default = "10.")]
+    #[serde(default = "default_external_factor")]
     pub external_factor: f64,
+    /// Scale of each input, e.g. `0.2,1,2.4,1`, replacing `external_factor` when set.
+    #[arg(long)]
+    #[builder(default = "None")]
+    #[serde(default)]
+    pub input_scales: Option<InputScales>,
     #[arg(skip)]
     pub n_actions: usize,
     #[arg(skip)]
     pub n_inputs: usize,
+    /// Operations instructions may use, e.g. `add,sub,mul,div`.
+    #[arg(long, default_value = "add,sub,mul,div")]
+    #[builder(default = "OpSet::default()")]
+    #[serde(default)]
+    pub ops: OpSet,
+    /// Probability of generating a conditional (skip-next) instruction instead of an op.
+    #[arg(long, default_value = "0.")]
+    #[builder(default = "0.")]
+    #[serde(default)]
+    pub conditional_probability: f64,
+    /// Probability of generating an instruction whose operand is an immediate constant.
+    #[arg(long, default_value = "0.")]
+    #[builder(default = "0.")]
+    #[serde(default)]
+    pub constant_probability: f64,
+    /// Range immediate constants are sampled from when generated.
+    #[arg(skip = DEFAULT_CONSTANT_RANGE)]
+    #[builder(default = "DEFAULT_CONSTANT_RANGE")]
+    #[serde(default = "default_constant_range")]
+    pub constant_range: (f64, f64),
+    /// Standard deviation of the Gaussian noise added to constants during mutation.
+    #[arg(long, default_value = "1.")]
+    #[builder(default = "1.")]
+    #[serde(default = "default_constant_sigma")]
+    pub constant_sigma: f64,
+}
+
+pub const DEFAULT_CONSTANT_RANGE: (f64, f64) = (-5., 5.);
+
+impl InstructionGeneratorParameters {
+    /// Violations of the generator's ranges, with fields nested under `prefix`.
+    pub fn violations(&self, prefix: &str) -> Vec<ConfigViolation> {
+        let mut violations = vec![];
+        let field = |name: &str| format!("{}.{}", prefix, name);
+        let unit = |value: f64| (0. ..=1.).contains(&value);
+
+        require(
+            &mut violations,
+            self.n_actions >= 1,
+            &field("n_actions"),
+            self.n_actions,
+            "at least 1",
+        );
+        require(
+            &mut violations,
+            self.n_output_registers != Some(0),
+            &field("n_output_registers"),
+            self.n_output_registers(),
+            "at least 1",
+        );
+        require(
+            &mut violations,
+            self.n_inputs >= 1,
+            &field("n_inputs"),
+            self.n_inputs,
+            "at least 1",
+        );
+        require(
+            &mut violations,
+            unit(self.conditional_probability),
+            &field("conditional_probability"),
+            self.conditional_probability,
+            "in [0, 1]",
+        );
+        require(
+            &mut violations,
+            unit(self.constant_probability),
+            &field("constant_probability"),
+            self.constant_probability,
+            "in [0, 1]",
+        );
+        if let Some(input_scales) = self.input_scales {
+            require(
+                &mut violations,
+                input_scales.len() == self.n_inputs,
+                &field("input_scales"),
+                input_scales.len(),
+                &format!("one scale per input ({})", self.n_inputs),
+            );
+        }
+        require(
+            &mut violations,
+            self.constant_range.0 <= self.constant_range.1,
+            &field("constant_range"),
+            format!("{:?}", self.constant_range),
+            "a (low, high) pair with low <= high",
+        );
+
+        violations
+    }
+}
+
+fn default_external_factor() -> f64 {
+    10.
+}
+
+fn default_constant_range() -> (f64, f64) {
+    DEFAULT_CONSTANT_RANGE
+}
+
+fn default_constant_sigma() -> f64 {
+    1.
 }
 
 impl InstructionGeneratorParameters {
+    pub fn n_output_registers(&self) -> usize {
+        self.n_output_registers.unwrap_or(self.n_actions)
+    }
+
     pub fn n_registers(&self) -> usize {
-        // Mountain Car Example: | -1 | 0 | 1 | Extra |
-        self.n_actions + self.n_extras
+        // Mountain Car Example: | -1 | 0 | 1 | Calculation |
+        self.n_output_registers() + self.n_calculation_registers
+    }
+
+    /// Scale of input `idx`: its entry of `input_scales`, or `external_factor` when unset.
+    pub fn input_scale(&self, idx: usize) -> f64 {
+        self.input_scales
+            .and_then(|scales| scales.as_slice().get(idx).copied())
+            .unwrap_or(self.external_factor)
+    }
+
+    /// Scale of every input; `external_factor` for each when `input_scales` is unset.
+    pub fn input_scales(&self) -> Vec<f64> {
+        (0..self.n_inputs)
+            .map(|idx| self.input_scale(idx))
+            .collect()
+    }
+
+    #[deprecated(note = "Use `n_calculation_registers`.")]
+    pub fn n_extras(&self) -> usize {
+        self.n_calculation_registers
+    }
+}
+
+impl InstructionGeneratorParametersBuilder {
+    /// Input scales, when given alongside `n_inputs`, must have one entry per input.
+    fn validate(&self) -> Result<(), String> {
+        match (self.input_scales, self.n_inputs) {
+            (Some(Some(scales)), Some(n_inputs)) if scales.len() != n_inputs => Err(format!(
+                "input_scales has {} scales but there are {} inputs",
+                scales.len(),
+                n_inputs
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    #[deprecated(note = "Use `n_calculation_registers`.")]
+    pub fn n_extras(&mut self, value: usize) -> &mut Self {
+        self.n_calculation_registers(value)
+    }
+}
+
+/// A single deterministic micro-mutation applied to a program.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Display)]
+pub enum Perturbation {
+    #[display(fmt = "op[{}]:{}->{}", index, from, to)]
+    Op { index: usize, from: Op, to: Op },
+    #[display(fmt = "source[{}]:{}->{}", index, from, to)]
+    Source {
+        index: usize,
+        from: usize,
+        to: usize,
+    },
+    #[display(
+        fmt = "target[{}]:{:?}({})->{:?}({})",
+        index,
+        from_mode,
+        from,
+        to_mode,
+        to
+    )]
+    Target {
+        index: usize,
+        from_mode: Mode,
+        from: usize,
+        to_mode: Mode,
+        to: usize,
+    },
+    #[display(fmt = "delete[{}]", index)]
+    Delete { index: usize },
+    #[display(fmt = "insert[{}]", index)]
+    Insert { index: usize },
+}
+
+impl Perturbation {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Perturbation::Op { .. } => "op",
+            Perturbation::Source { .. } => "source",
+            Perturbation::Target { .. } => "target",
+            Perturbation::Delete { .. } => "delete",
+            Perturbation::Insert { .. } => "insert",
+        }
     }
 }
 
@@ -93,47 +450,128 @@ pub struct Instruction {
     tgt_idx: usize,
     mode: Mode,
     op: Op,
+    /// Scale applied to external operands: the scale of the input read, baked in at generation.
+    /// Afterwards, it only follows the target when mutation moves it to another input under
+    /// per-input scales; crossover copies instructions verbatim, and only constant refinement
+    /// adjusts it explicitly. An instruction therefore contributes the same value regardless of
+    /// how it entered a program, even if the generation parameters change later.
     external_factor: f64,
+    /// Value of the operand in `Mode::Constant`, evolved by Gaussian mutation.
+    #[serde(default)]
+    immediate: f64,
+}
+
+impl Hash for Instruction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.src_idx.hash(state);
+        self.tgt_idx.hash(state);
+        self.mode.hash(state);
+        self.op.hash(state);
+        self.external_factor.to_bits().hash(state);
+        self.immediate.to_bits().hash(state);
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operand = match self.mode {
+            Mode::External => format!("{} * x{}", self.external_factor, self.tgt_idx),
+            Mode::Internal => format!("r{}", self.tgt_idx),
+            Mode::Constant => format!("{}", self.immediate),
+        };
+
+        match self.op {
+            op if op.is_conditional() => write!(f, "if r{} {} {}", self.src_idx, op, operand),
+            op if op.is_unary() => write!(f, "r{} = {}(r{})", self.src_idx, op, self.src_idx),
+            Op::Max | Op::Min => write!(
+                f,
+                "r{} = {}(r{}, {})",
+                self.src_idx, self.op, self.src_idx, operand
+            ),
+            op => write!(
+                f,
+                "r{} = r{} {} {}",
+                self.src_idx, self.src_idx, op, operand
+            ),
+        }
+    }
 }
 
 impl Generate<InstructionGeneratorParameters, Instruction> for GenerateEngine {
     fn generate(using: InstructionGeneratorParameters) -> Instruction {
         let src_idx = generator().gen_range(0..using.n_registers());
 
-        let mode = generator().gen();
+        let constant_probability = using.constant_probability.clamp(0., 1.);
 
-        let upper_bound_target_index = if mode == Mode::External {
-            using.n_inputs
+        let mode = if constant_probability > 0. && generator().gen_bool(constant_probability) {
+            Mode::Constant
         } else {
-            using.n_registers()
+            generator().gen()
         };
 
-        let target_index = generator().gen_range(0..upper_bound_target_index);
+        let target_index = match mode {
+            Mode::External => generator().gen_range(0..using.n_inputs),
+            Mode::Internal => generator().gen_range(0..using.n_registers()),
+            Mode::Constant => 0,
+        };
 
-        let executable = generator().gen();
+        let immediate = if mode == Mode::Constant {
+            let (low, high) = using.constant_range;
+            generator().gen_range(low.min(high)..=low.max(high))
+        } else {
+            0.
+        };
+
+        let conditional_probability = using.conditional_probability.clamp(0., 1.);
+
+        let executable =
+            if conditional_probability > 0. && generator().gen_bool(conditional_probability) {
+                *Op::CONDITIONALS.choose(&mut generator()).unwrap()
+            } else {
+                using.ops.choose(&mut generator())
+            };
 
         Instruction {
             src_idx,
             tgt_idx: target_index,
             mode,
             op: executable,
-            external_factor: using.external_factor,
+            external_factor: if mode == Mode::External {
+                using.input_scale(target_index)
+            } else {
+                using.external_factor
+            },
+            immediate,
         }
     }
 }
 
 impl Mutate<InstructionGeneratorParameters, Instruction> for MutateEngine {
     fn mutate(instruction: &mut Instruction, using: InstructionGeneratorParameters) {
+        // Constants are tuned in place rather than replaced half of the time.
+        if instruction.mode == Mode::Constant && generator().gen_bool(0.5) {
+            instruction.immediate += using.constant_sigma * standard_normal(&mut generator());
+            return;
+        }
+
         let mutated = GenerateEngine::generate(using);
 
         let swap_target = generator().gen();
         let swap_source = generator().gen();
         let swap_exec = generator().gen();
 
-        // Flip a Coin: Target
+        // Flip a Coin: Target (the instruction keeps its own external factor unless inputs are
+        // scaled individually).
         if swap_target {
             instruction.mode = mutated.mode;
             instruction.tgt_idx = mutated.tgt_idx;
+
+            if mutated.mode == Mode::Constant {
+                instruction.immediate = mutated.immediate;
+            }
+            if mutated.mode == Mode::External && using.input_scales.is_some() {
+                instruction.external_factor = mutated.external_factor;
+            }
         }
 
         // Flip a Coin: Source
@@ -149,15 +587,394 @@ impl Mutate<InstructionGeneratorParameters, Instruction> for MutateEngine {
 }
 
 impl Instruction {
-    pub fn apply<'b>(&self, registers: &'b mut Registers, input: &impl State) {
+    pub fn new(src_idx: usize, tgt_idx: usize, mode: Mode, op: Op, external_factor: f64) -> Self {
+        Instruction {
+            src_idx,
+            tgt_idx,
+            mode,
+            op,
+            external_factor,
+            immediate: 0.,
+        }
+    }
+
+    /// Creates an instruction whose operand is the immediate constant `value`.
+    pub fn with_constant(src_idx: usize, op: Op, value: f64) -> Self {
+        Instruction {
+            src_idx,
+            tgt_idx: 0,
+            mode: Mode::Constant,
+            op,
+            external_factor: 0.,
+            immediate: value,
+        }
+    }
+
+    /// The tunable constant of the instruction: the scale of an input or its immediate value.
+    pub fn constant(&self) -> Option<f64> {
+        match self.mode {
+            Mode::External => Some(self.external_factor),
+            Mode::Constant => Some(self.immediate),
+            Mode::Internal => None,
+        }
+    }
+
+    pub fn set_constant(&mut self, value: f64) {
+        match self.mode {
+            Mode::External => self.external_factor = value,
+            Mode::Constant => self.immediate = value,
+            Mode::Internal => {}
+        }
+    }
+
+    /// Executes the instruction and returns whether the next instruction should run.
+    /// Values of the source register and of the operand the instruction reads.
+    pub fn operands(&self, registers: &Registers, input: &impl State) -> (f64, f64) {
         let target_value = match self.mode {
             Mode::External => self.external_factor * input.get_value(self.tgt_idx),
-            _ => *registers.get(self.tgt_idx),
+            Mode::Constant => self.immediate,
+            Mode::Internal => *registers.get(self.tgt_idx),
+        };
+
+        (*registers.get(self.src_idx), target_value)
+    }
+
+    pub fn apply<'b>(&self, registers: &'b mut Registers, input: &impl State) -> bool {
+        let (source_value, target_value) = self.operands(registers, input);
+
+        match self.op {
+            Op::IfGreater => source_value > target_value,
+            Op::IfLess => source_value < target_value,
+            op => {
+                registers.update(self.src_idx, op.apply(source_value, target_value));
+                true
+            }
+        }
+    }
+
+    pub fn is_conditional(&self) -> bool {
+        self.op.is_conditional()
+    }
+}
+
+impl Instruction {
+    pub fn src_idx(&self) -> usize {
+        self.src_idx
+    }
+
+    pub fn op(&self) -> Op {
+        self.op
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The input read as the second operand, if any.
+    pub fn input_operand(&self) -> Option<usize> {
+        (self.mode == Mode::External).then_some(self.tgt_idx)
+    }
+
+    /// The register read as the second operand, if any. External targets read an input and
+    /// unary operations ignore their second operand.
+    pub fn register_operand(&self) -> Option<usize> {
+        match (self.mode, self.op) {
+            (Mode::Internal, op) if !op.is_unary() => Some(self.tgt_idx),
+            _ => None,
+        }
+    }
+
+    /// Whether the instruction leaves its source register unchanged, e.g. `r1 = r1 * 1`,
+    /// `r2 = r2 + 0` or `r3 = max(r3, r3)`.
+    pub fn is_identity(&self) -> bool {
+        match (self.mode, self.op) {
+            (Mode::Constant, Op::Add | Op::Sub) => self.immediate == 0.,
+            (Mode::Constant, Op::Mult | Op::Divide) => self.immediate == 1.,
+            (Mode::Internal, Op::Max | Op::Min) => self.tgt_idx == self.src_idx,
+            _ => false,
+        }
+    }
+
+    /// Whether the result does not depend on the previous value of the source register:
+    /// protected division by a constant zero yields 1, and multiplying a finite value by a
+    /// constant zero yields 0.
+    pub fn overwrites_source(&self) -> bool {
+        self.mode == Mode::Constant
+            && self.immediate == 0.
+            && matches!(self.op, Op::Mult | Op::Divide)
+    }
+
+    /// A single instruction equivalent to this one followed by `next`, when both apply
+    /// compatible operations with constant operands to the same register.
+    pub fn fold(&self, next: &Instruction) -> Option<Instruction> {
+        if self.mode != Mode::Constant
+            || next.mode != Mode::Constant
+            || self.src_idx != next.src_idx
+        {
+            return None;
+        }
+
+        let signed = |instruction: &Instruction| match instruction.op {
+            Op::Sub => -instruction.immediate,
+            _ => instruction.immediate,
+        };
+        let (a, b) = (self.immediate, next.immediate);
+
+        let (op, value) = match (self.op, next.op) {
+            (Op::Add | Op::Sub, Op::Add | Op::Sub) => (Op::Add, signed(self) + signed(next)),
+            (Op::Mult, Op::Mult) => (Op::Mult, a * b),
+            (Op::Max, Op::Max) => (Op::Max, a.max(b)),
+            (Op::Min, Op::Min) => (Op::Min, a.min(b)),
+            _ => return None,
+        };
+
+        Some(Instruction::with_constant(self.src_idx, op, value))
+    }
+
+    /// Enumerates every instruction that differs from this one by a single operator,
+    /// source or target change, in a fixed order.
+    pub fn neighbours(
+        &self,
+        index: usize,
+        using: InstructionGeneratorParameters,
+    ) -> Vec<(Perturbation, Instruction)> {
+        let mut neighbours = vec![];
+
+        for op in using.ops.iter().filter(|op| *op != self.op) {
+            neighbours.push((
+                Perturbation::Op {
+                    index,
+                    from: self.op,
+                    to: op,
+                },
+                Instruction { op, ..*self },
+            ));
+        }
+
+        for src_idx in (0..using.n_registers()).filter(|idx| *idx != self.src_idx) {
+            neighbours.push((
+                Perturbation::Source {
+                    index,
+                    from: self.src_idx,
+                    to: src_idx,
+                },
+                Instruction { src_idx, ..*self },
+            ));
+        }
+
+        let targets = (0..using.n_registers())
+            .map(|idx| (Mode::Internal, idx))
+            .chain((0..using.n_inputs).map(|idx| (Mode::External, idx)))
+            .filter(|target| *target != (self.mode, self.tgt_idx));
+
+        for (mode, tgt_idx) in targets {
+            neighbours.push((
+                Perturbation::Target {
+                    index,
+                    from_mode: self.mode,
+                    from: self.tgt_idx,
+                    to_mode: mode,
+                    to: tgt_idx,
+                },
+                Instruction {
+                    mode,
+                    tgt_idx,
+                    ..*self
+                },
+            ));
+        }
+
+        neighbours
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::repeat_with;
+
+    use crate::core::{
+        engines::breed_engine::{Breed, BreedEngine},
+        instructions::{Instructions, OpSet},
+    };
+
+    use super::*;
+
+    struct Input;
+
+    impl State for Input {
+        fn get_value(&self, _at_idx: usize) -> f64 {
+            2.
+        }
+
+        fn execute_action(&mut self, _action: usize) -> f64 {
+            0.
+        }
+
+        fn get(&mut self) -> Option<&mut Self> {
+            Some(self)
+        }
+    }
+
+    fn parameters(external_factor: f64) -> InstructionGeneratorParameters {
+        InstructionGeneratorParameters {
+            n_calculation_registers: 1,
+            n_output_registers: None,
+            register_init: RegisterInit::Zeros,
+            register_saturation: RegisterSaturation::None,
+            external_factor,
+            input_scales: None,
+            n_actions: 1,
+            n_inputs: 1,
+            ops: OpSet::new(&[Op::Add]),
+            conditional_probability: 0.,
+            constant_probability: 0.,
+            constant_range: (-5., 5.),
+            constant_sigma: 1.,
+        }
+    }
+
+    /// Value written by `r_src = r_src + operand` starting from zeroed registers.
+    fn contribution(instruction: &Instruction) -> f64 {
+        let mut registers = Registers::new(1, 1);
+        instruction.apply(&mut registers, &Input);
+        *registers.get(instruction.src_idx)
+    }
+
+    fn external(using: InstructionGeneratorParameters) -> Instruction {
+        repeat_with(|| GenerateEngine::generate(using))
+            .find(|instruction: &Instruction| instruction.mode == Mode::External)
+            .unwrap()
+    }
+
+    #[test]
+    fn given_same_parameters_then_contribution_is_independent_of_provenance() {
+        let using = parameters(10.);
+
+        let generated = external(using);
+        assert_eq!(contribution(&generated), 20.);
+
+        let mutated = repeat_with(|| {
+            let mut instruction = Instruction::new(0, 0, Mode::Internal, Op::Add, 10.);
+            MutateEngine::mutate(&mut instruction, using);
+            instruction
+        })
+        .find(|instruction| instruction.mode == Mode::External)
+        .unwrap();
+        assert_eq!(contribution(&mutated), 20.);
+
+        let parents: [Instructions; 2] = [vec![external(using); 4], vec![external(using); 4]];
+        let (child, _) = BreedEngine::two_point_crossover(&parents[0], &parents[1]);
+        assert!(child
+            .iter()
+            .all(|instruction| contribution(instruction) == 20.));
+    }
+
+    #[test]
+    fn given_changed_parameters_when_mutated_then_existing_scale_is_kept() {
+        let mut instruction = external(parameters(10.));
+
+        for _ in 0..100 {
+            MutateEngine::mutate(&mut instruction, parameters(3.));
+
+            if instruction.mode == Mode::External {
+                assert_eq!(contribution(&instruction), 20.);
+            }
+        }
+
+        let round_trip: Instruction =
+            serde_json::from_str(&serde_json::to_string(&instruction).unwrap()).unwrap();
+        assert_eq!(round_trip, instruction);
+    }
+
+    #[test]
+    fn given_constant_operand_then_it_is_applied_disassembled_and_serialized() {
+        let instruction = Instruction::with_constant(0, Op::Add, 3.5);
+
+        assert_eq!(contribution(&instruction), 3.5);
+        assert_eq!(instruction.to_string(), "r0 = r0 + 3.5");
+        assert_eq!(instruction.constant(), Some(3.5));
+
+        let round_trip: Instruction =
+            serde_json::from_str(&serde_json::to_string(&instruction).unwrap()).unwrap();
+        assert_eq!(round_trip, instruction);
+    }
+
+    #[test]
+    fn given_constants_enabled_then_generated_constants_are_within_range() {
+        let using = InstructionGeneratorParameters {
+            constant_probability: 1.,
+            constant_range: (2., 3.),
+            ..parameters(1.)
         };
 
-        let source_value = *registers.get(self.src_idx);
-        let new_source_value = self.op.apply(source_value, target_value);
+        for _ in 0..100 {
+            let instruction: Instruction = GenerateEngine::generate(using);
+            assert_eq!(instruction.mode, Mode::Constant);
+            assert!((2. ..=3.).contains(&instruction.immediate));
+        }
+    }
+
+    #[test]
+    fn given_config_with_only_external_factor_when_deserialized_then_every_input_uses_it() {
+        let using: InstructionGeneratorParameters = serde_json::from_str(
+            r#"{"n_calculation_registers": 1, "external_factor": 4.0, "n_actions": 2, "n_inputs": 3}"#,
+        )
+        .unwrap();
+
+        assert_eq!(using.input_scales, None);
+        assert_eq!(using.input_scales(), vec![4., 4., 4.]);
+        assert!(serde_json::to_string(&using)
+            .unwrap()
+            .contains(r#""input_scales":null"#));
+    }
 
-        registers.update(self.src_idx, new_source_value);
+    #[test]
+    fn given_config_with_input_scales_when_deserialized_then_they_round_trip() {
+        let using: InstructionGeneratorParameters = serde_json::from_str(
+            r#"{"n_calculation_registers": 1, "input_scales": [0.2, 1.0, 2.4], "n_actions": 2, "n_inputs": 3}"#,
+        )
+        .unwrap();
+
+        assert_eq!(using.input_scales(), vec![0.2, 1., 2.4]);
+        assert!(using.violations("program").is_empty());
+
+        let round_trip: InstructionGeneratorParameters =
+            serde_json::from_str(&serde_json::to_string(&using).unwrap()).unwrap();
+        assert_eq!(round_trip, using);
+        assert_eq!(
+            "0.2,1,2.4".parse::<InputScales>().unwrap(),
+            using.input_scales.unwrap()
+        );
+    }
+
+    #[test]
+    fn given_input_scales_of_wrong_length_when_built_then_error() {
+        let built = InstructionGeneratorParametersBuilder::default()
+            .n_actions(2)
+            .n_inputs(3)
+            .input_scales(Some(InputScales::new(vec![1., 2.])))
+            .build();
+
+        assert!(built.is_err());
+
+        let using = InstructionGeneratorParameters {
+            input_scales: Some(InputScales::new(vec![1., 2.])),
+            ..parameters(1.)
+        };
+        assert_eq!(using.violations("program").len(), 1);
+    }
+
+    #[test]
+    fn given_input_scales_when_generated_then_each_input_uses_its_scale() {
+        let using = InstructionGeneratorParameters {
+            n_inputs: 2,
+            input_scales: Some(InputScales::new(vec![0.5, 3.])),
+            ..parameters(10.)
+        };
+
+        for _ in 0..20 {
+            let instruction = external(using);
+            assert_eq!(instruction.external_factor, [0.5, 3.][instruction.tgt_idx]);
+        }
     }
 }
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+
+use super::{
+    characteristics::Genotype,
+    engines::core_engine::Core,
+    instruction::{Instruction, Mode},
+};
+
+/// Instructions by the kind of their second operand.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperandCounts {
+    pub register: usize,
+    pub input: usize,
+    pub constant: usize,
+    /// Unary operations, which ignore their second operand.
+    pub unused: usize,
+}
+
+/// Which operations, operands and registers the instructions of a population use.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionStats {
+    pub n_programs: usize,
+    pub n_instructions: usize,
+    /// Instructions per operation, by name, e.g. `div`.
+    pub ops: BTreeMap<String, usize>,
+    pub operands: OperandCounts,
+    /// Instructions writing each register, by index; conditionals write none.
+    #[serde(deserialize_with = "register_indices")]
+    pub register_writes: BTreeMap<usize, usize>,
+}
+
+impl InstructionStats {
+    pub fn compute<C>(population: &[C::Individual]) -> Self
+    where
+        C: Core,
+    {
+        Self::from_programs(population)
+    }
+
+    pub fn from_programs<'a, G>(programs: impl IntoIterator<Item = &'a G>) -> Self
+    where
+        G: Genotype + 'a,
+    {
+        let mut stats = InstructionStats::default();
+
+        for program in programs {
+            stats.n_programs += 1;
+            program
+                .instructions()
+                .iter()
+                .for_each(|instruction| stats.count(instruction));
+        }
+
+        stats
+    }
+
+    fn count(&mut self, instruction: &Instruction) {
+        self.n_instructions += 1;
+        *self
+            .ops
+            .entry(instruction.op().name().to_string())
+            .or_default() += 1;
+
+        let operand = match instruction.mode() {
+            _ if instruction.op().is_unary() => &mut self.operands.unused,
+            Mode::Internal => &mut self.operands.register,
+            Mode::External => &mut self.operands.input,
+            Mode::Constant => &mut self.operands.constant,
+        };
+        *operand += 1;
+
+        if !instruction.is_conditional() {
+            *self
+                .register_writes
+                .entry(instruction.src_idx())
+                .or_default() += 1;
+        }
+    }
+
+    /// Adds the counts of `other`, e.g. to aggregate generations over a run.
+    pub fn merge(&mut self, other: &InstructionStats) {
+        self.n_programs += other.n_programs;
+        self.n_instructions += other.n_instructions;
+        self.operands.register += other.operands.register;
+        self.operands.input += other.operands.input;
+        self.operands.constant += other.operands.constant;
+        self.operands.unused += other.operands.unused;
+
+        for (op, count) in &other.ops {
+            *self.ops.entry(op.clone()).or_default() += count;
+        }
+
+        for (register, count) in &other.register_writes {
+            *self.register_writes.entry(*register).or_default() += count;
+        }
+    }
+
+    /// Share of the instructions using the operation named `op`.
+    pub fn op_frequency(&self, op: &str) -> f64 {
+        match self.n_instructions {
+            0 => 0.,
+            n => self.ops.get(op).copied().unwrap_or(0) as f64 / n as f64,
+        }
+    }
+}
+
+/// Reads register indices saved as JSON keys, which are strings. Serde only parses those back
+/// into numbers when the map is read directly, not once buffered, e.g. inside a tagged `Event`.
+fn register_indices<'de, D>(deserializer: D) -> Result<BTreeMap<usize, usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    if !deserializer.is_human_readable() {
+        return BTreeMap::deserialize(deserializer);
+    }
+
+    BTreeMap::<String, usize>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(register, count)| {
+            register
+                .parse()
+                .map(|register| (register, count))
+                .map_err(D::Error::custom)
+        })
+        .collect()
+}
+
+/// Instruction statistics of every generation of a run, saved as `instruction_stats.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunInstructionStats {
+    pub generations: Vec<InstructionStats>,
+    /// Sum over the generations.
+    pub total: InstructionStats,
+}
+
+impl RunInstructionStats {
+    pub fn from_populations<C>(populations: &[Vec<C::Individual>]) -> Self
+    where
+        C: Core,
+    {
+        let generations = populations
+            .iter()
+            .map(|population| InstructionStats::compute::<C>(population))
+            .collect::<Vec<_>>();
+
+        let mut total = InstructionStats::default();
+        generations
+            .iter()
+            .for_each(|generation| total.merge(generation));
+
+        RunInstructionStats { generations, total }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{
+            engines::{
+                generate_engine::{Generate, GenerateEngine},
+                reset_engine::{Reset, ResetEngine},
+            },
+            instruction::{InstructionGeneratorParametersBuilder, Op},
+            program::{Program, ProgramGeneratorParametersBuilder},
+        },
+        problems::iris::IrisEngine,
+    };
+
+    use super::*;
+
+    fn program(instructions: Vec<Instruction>) -> Program {
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(1)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_actions(3)
+                    .n_inputs(4)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        let mut program: Program = GenerateEngine::generate(program_parameters);
+        program.instructions = instructions;
+        ResetEngine::reset(&mut program);
+
+        program
+    }
+
+    /// Two programs: four additions, a division, a sine and a conditional.
+    fn population() -> Vec<Program> {
+        vec![
+            program(vec![
+                Instruction::new(0, 1, Mode::External, Op::Add, 1.),
+                Instruction::new(0, 2, Mode::Internal, Op::Add, 1.),
+                Instruction::new(1, 0, Mode::Internal, Op::Divide, 1.),
+                Instruction::new(2, 0, Mode::Internal, Op::Sin, 1.),
+            ]),
+            program(vec![
+                Instruction::new(1, 3, Mode::External, Op::IfGreater, 1.),
+                Instruction::with_constant(1, Op::Add, 2.),
+                Instruction::new(0, 0, Mode::External, Op::Add, 1.),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn given_hand_made_population_when_stats_computed_then_counts_match_its_instructions() {
+        let stats = InstructionStats::compute::<IrisEngine>(&population());
+
+        assert_eq!(stats.n_programs, 2);
+        assert_eq!(stats.n_instructions, 7);
+        assert_eq!(
+            stats.ops,
+            BTreeMap::from([
+                ("add".to_string(), 4),
+                ("div".to_string(), 1),
+                ("if_gt".to_string(), 1),
+                ("sin".to_string(), 1),
+            ])
+        );
+        assert_eq!(
+            stats.operands,
+            OperandCounts {
+                register: 2,
+                input: 3,
+                constant: 1,
+                unused: 1,
+            }
+        );
+        assert_eq!(
+            stats.register_writes,
+            BTreeMap::from([(0, 3), (1, 2), (2, 1)])
+        );
+        assert_eq!(stats.op_frequency("add"), 4. / 7.);
+        assert_eq!(stats.op_frequency("mul"), 0.);
+    }
+
+    #[test]
+    fn given_generations_when_aggregated_then_total_sums_every_generation() {
+        let populations = vec![population(), population()[..1].to_vec()];
+        let stats = RunInstructionStats::from_populations::<IrisEngine>(&populations);
+
+        assert_eq!(stats.generations.len(), 2);
+        assert_eq!(stats.generations[1].n_instructions, 4);
+        assert_eq!(stats.total.n_programs, 3);
+        assert_eq!(stats.total.n_instructions, 11);
+        assert_eq!(stats.total.ops["add"], 6);
+        assert_eq!(stats.total.ops["div"], 2);
+        assert_eq!(stats.total.operands.register, 4);
+        assert_eq!(stats.total.register_writes[&0], 5);
+
+        let json = serde_json::to_string(&stats).unwrap();
+        let parsed: RunInstructionStats = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, stats);
+    }
+}
@@ -1,72 +1,180 @@
+use std::{fmt, str::FromStr};
+
 use crate::utils::random::generator;
 use itertools::Itertools;
-use rand::Rng;
+use rand::{seq::IteratorRandom, Rng};
+use serde::{Deserialize, Serialize};
 
 use super::{
     engines::breed_engine::{Breed, BreedEngine},
-    instruction::Instruction,
+    instruction::{Instruction, Op},
 };
 
+/// The registry of operations available to generated instructions, stored as a bit set over
+/// `Op::ALL` and written as a list of op names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Op>", into = "Vec<Op>")]
+pub struct OpSet(u16);
+
+impl OpSet {
+    pub fn new(ops: &[Op]) -> Self {
+        OpSet(ops.iter().fold(0, |bits, op| bits | Self::bit(*op)))
+    }
+
+    fn bit(op: Op) -> u16 {
+        1 << Op::ALL.iter().position(|other| *other == op).unwrap()
+    }
+
+    pub fn contains(&self, op: Op) -> bool {
+        self.0 & Self::bit(op) != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Op> + '_ {
+        Op::ALL.into_iter().filter(|op| self.contains(*op))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> Op {
+        self.iter().choose(rng).expect("Op set to not be empty.")
+    }
+}
+
+impl Default for OpSet {
+    fn default() -> Self {
+        OpSet::new(&[Op::Add, Op::Sub, Op::Mult, Op::Divide])
+    }
+}
+
+impl TryFrom<Vec<Op>> for OpSet {
+    type Error = String;
+
+    fn try_from(ops: Vec<Op>) -> Result<Self, Self::Error> {
+        if ops.is_empty() {
+            return Err("At least one op must be enabled".to_string());
+        }
+
+        Ok(OpSet::new(&ops))
+    }
+}
+
+impl From<OpSet> for Vec<Op> {
+    fn from(ops: OpSet) -> Self {
+        ops.iter().collect()
+    }
+}
+
+impl FromStr for OpSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .map(|name| Op::from_name(name).ok_or_else(|| format!("Unknown op `{}`", name)))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(OpSet::try_from)
+    }
+}
+
+impl fmt::Display for OpSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.iter().map(|op| op.name()).join(","))
+    }
+}
+
+/// Picks the length of the segment exchanged by the second parent so that neither child is
+/// empty or longer than the longer parent; children of valid parents therefore respect
+/// `max_instructions`.
+fn mate_segment_len(len_a: usize, len_b: usize, segment_a: usize, min_segment: usize) -> usize {
+    let longest = len_a.max(len_b) as isize;
+    let (len_a, len_b, segment_a) = (len_a as isize, len_b as isize, segment_a as isize);
+
+    // Child lengths are `len_a - segment_a + segment_b` and `len_b - segment_b + segment_a`.
+    let low = (segment_a + (1 - len_a).max(len_b - longest)).max(min_segment as isize);
+    let high = (segment_a + (longest - len_a).min(len_b - 1)).min(len_b);
+    debug_assert!(low <= high, "No segment keeps both children in bounds.");
+
+    generator().gen_range(low..=high) as usize
+}
+
+fn exchange(
+    mate_1: &Instructions,
+    mate_2: &Instructions,
+    segment_a: std::ops::Range<usize>,
+    segment_b: std::ops::Range<usize>,
+) -> (Instructions, Instructions) {
+    let mut instructions_a = mate_1.clone();
+    let mut instructions_b = mate_2.clone();
+
+    instructions_a.splice(segment_a.clone(), mate_2[segment_b.clone()].iter().cloned());
+    instructions_b.splice(segment_b, mate_1[segment_a].iter().cloned());
+
+    debug_assert!(instructions_a.len() > 0, "instructions A after crossover");
+    debug_assert!(instructions_b.len() > 0, "instructions B after crossover");
+
+    (instructions_a, instructions_b)
+}
+
 impl Breed<Instructions> for BreedEngine {
     fn two_point_crossover(
         mate_1: &Instructions,
         mate_2: &Instructions,
     ) -> (Instructions, Instructions) {
-        let mut instructions_a = mate_1.clone();
-        let mut instructions_b = mate_2.clone();
+        debug_assert!(mate_1.len() > 0);
+        debug_assert!(mate_2.len() > 0);
 
-        debug_assert!(instructions_a.len() > 0);
-        debug_assert!(instructions_b.len() > 0);
+        let a_start = generator().gen_range(0..mate_1.len());
+        let a_len = generator().gen_range(1..=mate_1.len() - a_start);
 
-        let a_start = generator().gen_range(0..instructions_a.len());
-        let b_start = generator().gen_range(0..instructions_b.len());
+        let b_len = mate_segment_len(mate_1.len(), mate_2.len(), a_len, 1);
+        let b_start = generator().gen_range(0..=mate_2.len() - b_len);
 
-        let a_end = if a_start == instructions_a.len() - 1 {
-            None
-        } else {
-            debug_assert!(instructions_a.len() > a_start);
-            Some(generator().gen_range(a_start + 1..instructions_a.len()))
-        };
+        exchange(
+            mate_1,
+            mate_2,
+            a_start..a_start + a_len,
+            b_start..b_start + b_len,
+        )
+    }
 
-        let b_end = if b_start == instructions_b.len() - 1 {
-            None
-        } else {
-            debug_assert!(instructions_b.len() > b_start);
-            Some(generator().gen_range(b_start + 1..instructions_b.len()))
-        };
+    fn one_point_crossover(
+        mate_1: &Instructions,
+        mate_2: &Instructions,
+    ) -> (Instructions, Instructions) {
+        debug_assert!(mate_1.len() > 0);
+        debug_assert!(mate_2.len() > 0);
 
-        let a_chunk = match a_end {
-            None => &instructions_a[a_start..],
-            Some(a_end_idx) => &instructions_a[a_start..a_end_idx],
-        }
-        .iter()
-        .cloned()
-        .collect_vec();
+        let a_cut = generator().gen_range(0..=mate_1.len());
+        let b_tail = mate_segment_len(mate_1.len(), mate_2.len(), mate_1.len() - a_cut, 0);
 
-        let b_chunk = match b_end {
-            None => &instructions_b[b_start..],
-            Some(b_end_idx) => &instructions_b[b_start..b_end_idx],
-        }
-        .iter()
-        .cloned()
-        .collect_vec();
-
-        if let Some(a_end_idx) = a_end {
-            instructions_a.splice(a_start..a_end_idx, b_chunk)
-        } else {
-            instructions_a.splice(a_start.., b_chunk)
-        }
-        .collect_vec();
+        exchange(
+            mate_1,
+            mate_2,
+            a_cut..mate_1.len(),
+            mate_2.len() - b_tail..mate_2.len(),
+        )
+    }
 
-        if let Some(b_end_idx) = b_end {
-            instructions_b.splice(b_start..b_end_idx, a_chunk)
-        } else {
-            instructions_b.splice(b_start.., a_chunk)
-        }
-        .collect_vec();
+    fn uniform_crossover(
+        mate_1: &Instructions,
+        mate_2: &Instructions,
+        swap_probability: f64,
+    ) -> (Instructions, Instructions) {
+        let mut instructions_a = mate_1.clone();
+        let mut instructions_b = mate_2.clone();
 
-        debug_assert!(instructions_a.len() > 0, "instructions A after crossover");
-        debug_assert!(instructions_b.len() > 0, "instructions B after crossover");
+        // Only aligned positions are exchanged, so both children keep their parent's length.
+        for (a, b) in instructions_a.iter_mut().zip(instructions_b.iter_mut()) {
+            if generator().gen_bool(swap_probability) {
+                std::mem::swap(a, b);
+            }
+        }
 
         (instructions_a, instructions_b)
     }
@@ -76,14 +184,18 @@ pub type Instructions = Vec<Instruction>;
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
+    use crate::core::instructions::{exchange, Instructions, OpSet};
 
     use crate::core::{
         engines::{
-            breed_engine::{Breed, BreedEngine},
+            breed_engine::{Breed, BreedEngine, CrossoverKind},
             generate_engine::{Generate, GenerateEngine},
         },
-        instruction::InstructionGeneratorParameters,
-        program::ProgramGeneratorParameters,
+        instruction::{Instruction, InstructionGeneratorParameters, Op},
+        program::{Program, ProgramGeneratorParameters},
+        registers::{RegisterInit, RegisterSaturation},
     };
 
     #[test]
@@ -92,11 +204,21 @@ mod tests {
         let max_instructions = 100;
         let parameters = ProgramGeneratorParameters {
             max_instructions,
+            min_instructions: 1,
             instruction_generator_parameters: InstructionGeneratorParameters {
-                n_extras: 1,
+                n_calculation_registers: 1,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
                 external_factor: 10.,
+                input_scales: None,
                 n_inputs: 4,
                 n_actions: 2,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
             },
         };
 
@@ -126,4 +248,187 @@ mod tests {
             program_b = new_parent_b;
         }
     }
+
+    fn random_parents(max_instructions: usize) -> (Instructions, Instructions) {
+        let parameters = ProgramGeneratorParameters {
+            max_instructions,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 1,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 10.,
+                input_scales: None,
+                n_inputs: 4,
+                n_actions: 2,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.5,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        };
+        let program_a: Program = GenerateEngine::generate(parameters);
+        let program_b: Program = GenerateEngine::generate(parameters);
+
+        (program_a.instructions, program_b.instructions)
+    }
+
+    fn multiset(instructions: &[Instruction]) -> Vec<String> {
+        instructions
+            .iter()
+            .map(|instruction| serde_json::to_string(instruction).unwrap())
+            .sorted()
+            .collect()
+    }
+
+    #[test]
+    fn given_random_parents_when_crossed_then_children_stay_within_length_bounds() {
+        let max_instructions = 20;
+
+        for kind in [
+            CrossoverKind::OnePoint,
+            CrossoverKind::TwoPoint,
+            CrossoverKind::Uniform(0.5),
+        ] {
+            for _ in 0..500 {
+                let (parent_a, parent_b) = random_parents(max_instructions);
+                let (child_a, child_b) = BreedEngine::crossover(&parent_a, &parent_b, kind);
+
+                for child in [&child_a, &child_b] {
+                    assert!((1..=max_instructions).contains(&child.len()), "{}", kind);
+                }
+
+                assert_eq!(
+                    multiset(&[parent_a, parent_b].concat()),
+                    multiset(&[child_a, child_b].concat()),
+                    "{}",
+                    kind
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn given_uniform_crossover_then_lengths_are_kept_and_extremes_swap_all_or_nothing() {
+        let (parent_a, parent_b) = random_parents(20);
+        let aligned = parent_a.len().min(parent_b.len());
+
+        let (kept_a, kept_b) = BreedEngine::uniform_crossover(&parent_a, &parent_b, 0.);
+        assert_eq!(
+            (kept_a.clone(), kept_b.clone()),
+            (parent_a.clone(), parent_b.clone())
+        );
+
+        let (swapped_a, swapped_b) = BreedEngine::uniform_crossover(&parent_a, &parent_b, 1.);
+        assert_eq!(swapped_a.len(), parent_a.len());
+        assert_eq!(swapped_b.len(), parent_b.len());
+        assert_eq!(swapped_a[..aligned], parent_b[..aligned]);
+        assert_eq!(swapped_b[..aligned], parent_a[..aligned]);
+    }
+
+    #[test]
+    fn given_edge_segments_when_exchanged_then_children_match_vec_splicing() {
+        let (parent_a, parent_b) = random_parents(20);
+        let (len_a, len_b) = (parent_a.len(), parent_b.len());
+
+        let expected = |segment_a: std::ops::Range<usize>, segment_b: std::ops::Range<usize>| {
+            let child_a = [
+                &parent_a[..segment_a.start],
+                &parent_b[segment_b.clone()],
+                &parent_a[segment_a.end..],
+            ]
+            .concat();
+            let child_b = [
+                &parent_b[..segment_b.start],
+                &parent_a[segment_a],
+                &parent_b[segment_b.end..],
+            ]
+            .concat();
+
+            (child_a, child_b)
+        };
+
+        // Prefixes including the head, suffixes including the tail, whole lists and an empty
+        // segment against a non-empty one.
+        for (segment_a, segment_b) in [
+            (0..1, 0..len_b),
+            (len_a - 1..len_a, len_b - 1..len_b),
+            (0..len_a, 0..len_b),
+            (len_a..len_a, 0..1),
+        ] {
+            let (child_a, child_b) =
+                exchange(&parent_a, &parent_b, segment_a.clone(), segment_b.clone());
+
+            assert_eq!((child_a, child_b), expected(segment_a, segment_b));
+        }
+    }
+
+    #[test]
+    fn given_crossover_names_when_parsed_then_round_trip_through_display() {
+        for kind in [
+            CrossoverKind::OnePoint,
+            CrossoverKind::TwoPoint,
+            CrossoverKind::Uniform(0.25),
+        ] {
+            assert_eq!(kind.to_string().parse(), Ok(kind));
+        }
+
+        assert!("uniform:1.5".parse::<CrossoverKind>().is_err());
+        assert!("three_point".parse::<CrossoverKind>().is_err());
+    }
+
+    #[test]
+    fn given_protected_ops_when_applied_then_results_stay_finite() {
+        assert_eq!(Op::Divide.apply(3., 0.), 1.);
+        assert_eq!(Op::Divide.apply(3., 2.), 1.5);
+        assert!(Op::Exp.apply(1e6, 0.).is_finite());
+        assert!(Op::Ln.apply(0., 0.).is_finite());
+        assert_eq!(Op::Ln.apply(-1., 0.), 0.);
+        assert_eq!(Op::Max.apply(1., 2.), 2.);
+        assert_eq!(Op::Min.apply(1., 2.), 1.);
+    }
+
+    #[test]
+    fn given_op_set_when_generating_then_disabled_ops_are_never_emitted() {
+        let ops: OpSet = "sin,max".parse().unwrap();
+        let parameters = InstructionGeneratorParameters {
+            n_calculation_registers: 1,
+            n_output_registers: None,
+            register_init: RegisterInit::Zeros,
+            register_saturation: RegisterSaturation::None,
+            external_factor: 10.,
+            input_scales: None,
+            n_inputs: 4,
+            n_actions: 2,
+            ops,
+            conditional_probability: 0.,
+            constant_probability: 0.,
+            constant_range: (-5., 5.),
+            constant_sigma: 1.,
+        };
+
+        for _ in 0..1000 {
+            let instruction: Instruction = GenerateEngine::generate(parameters);
+            let json = serde_json::to_string(&instruction).unwrap();
+
+            assert!(json.contains(r#""op":"sin""#) || json.contains(r#""op":"max""#));
+        }
+    }
+
+    #[test]
+    fn given_op_names_then_op_set_parses_and_serializes_by_name() {
+        let ops: OpSet = "add, div".parse().unwrap();
+
+        assert_eq!(ops.len(), 2);
+        assert!(ops.contains(Op::Divide));
+        assert_eq!(serde_json::to_string(&ops).unwrap(), r#"["add","div"]"#);
+        assert_eq!(ops.to_string(), "add,div");
+        assert!("add,pow".parse::<OpSet>().is_err());
+        assert!(serde_json::from_str::<OpSet>("[]").is_err());
+
+        // Programs saved before ops were named by their lowercase names still load.
+        assert_eq!(serde_json::from_str::<Op>(r#""Mult""#).unwrap(), Op::Mult);
+    }
 }
@@ -0,0 +1,299 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{adaptation::Variation, characteristics::Genotype};
+
+/// An individual of a lineage and the parents it was bred from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineageNode {
+    pub id: Uuid,
+    /// Empty for individuals of the first generation.
+    pub parents: Vec<Uuid>,
+    /// `None` for individuals of the first generation.
+    pub operator: Option<Variation>,
+    /// Generation the individual was first evaluated in.
+    pub generation: usize,
+}
+
+/// Ancestry DAG of the individuals of a run, from child to parents, maintained by `CoreIter`
+/// under `track_lineage`. Branches that die out are pruned `retention` generations after their
+/// last individual did, so memory follows the living population rather than the run length.
+#[derive(Debug, Clone, Default)]
+pub struct LineageTracker {
+    nodes: HashMap<Uuid, LineageNode>,
+    /// Last generation each individual was part of the population in.
+    last_alive: HashMap<Uuid, usize>,
+    retention: usize,
+}
+
+impl LineageTracker {
+    pub fn new(retention: usize) -> Self {
+        LineageTracker {
+            retention,
+            ..Default::default()
+        }
+    }
+
+    /// Adds the individuals of `population`, evaluated in `generation`, then prunes dead
+    /// branches. Individuals without an id are ignored.
+    pub fn record<G>(&mut self, generation: usize, population: &[G])
+    where
+        G: Genotype,
+    {
+        for individual in population {
+            let Some(id) = individual.id() else {
+                continue;
+            };
+
+            self.last_alive.insert(id, generation);
+            // Clones share their parent's id, so they are the same node.
+            self.nodes.entry(id).or_insert_with(|| {
+                let provenance = individual.provenance();
+
+                LineageNode {
+                    id,
+                    parents: provenance
+                        .map(|provenance| {
+                            provenance
+                                .parents
+                                .iter()
+                                .copied()
+                                .filter(|parent| *parent != id)
+                                .unique()
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    operator: provenance.map(|provenance| provenance.operator),
+                    generation,
+                }
+            });
+        }
+
+        self.prune(generation);
+    }
+
+    /// Drops the individuals that neither were alive in the last `retention` generations nor are
+    /// ancestors of one that was.
+    fn prune(&mut self, generation: usize) {
+        let roots = self
+            .last_alive
+            .iter()
+            .filter(|(_, last_alive)| generation - **last_alive <= self.retention)
+            .map(|(id, _)| *id)
+            .collect_vec();
+        let kept = self.ancestors(roots);
+
+        self.nodes.retain(|id, _| kept.contains(id));
+        self.last_alive.retain(|id, _| kept.contains(id));
+    }
+
+    /// `roots` and every known ancestor of them.
+    fn ancestors(&self, roots: impl IntoIterator<Item = Uuid>) -> HashSet<Uuid> {
+        let mut seen = HashSet::new();
+        let mut pending = roots.into_iter().collect_vec();
+
+        while let Some(id) = pending.pop() {
+            if let Some(node) = self.nodes.get(&id) {
+                if seen.insert(id) {
+                    pending.extend(node.parents.iter().copied());
+                }
+            }
+        }
+
+        seen
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&LineageNode> {
+        self.nodes.get(id)
+    }
+
+    /// The ancestry of the individual `id`: itself and every ancestor still tracked.
+    pub fn ancestry(&self, id: Uuid) -> Lineage {
+        let nodes = self
+            .ancestors([id])
+            .into_iter()
+            .filter_map(|ancestor| self.nodes.get(&ancestor).cloned())
+            .sorted_by_key(|node| (node.generation, node.id))
+            .collect();
+
+        Lineage { root: id, nodes }
+    }
+}
+
+/// Ancestry of one individual, typically the best of a run, saved as `lineage.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lineage {
+    pub root: Uuid,
+    /// Ordered by generation, oldest first.
+    pub nodes: Vec<LineageNode>,
+}
+
+impl Lineage {
+    /// Graphviz DOT graph with an edge from every parent to its child, labelled with the
+    /// operator; saved as `lineage.dot` under `lineage_dot`.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph lineage {\n");
+
+        for node in &self.nodes {
+            let shape = if node.id == self.root {
+                ", shape=doublecircle"
+            } else {
+                ""
+            };
+            dot += &format!(
+                "  \"{}\" [label=\"gen {}\"{}];\n",
+                node.id, node.generation, shape
+            );
+        }
+
+        for node in &self.nodes {
+            let operator = node
+                .operator
+                .map(|operator| format!("{:?}", operator).to_lowercase())
+                .unwrap_or_default();
+
+            for parent in &node.parents {
+                dot += &format!(
+                    "  \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    parent, node.id, operator
+                );
+            }
+        }
+
+        dot + "}\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{
+            engines::{
+                core_engine::HyperParametersBuilder,
+                generate_engine::{Generate, GenerateEngine},
+            },
+            instruction::InstructionGeneratorParametersBuilder,
+            program::{Program, ProgramGeneratorParameters, ProgramGeneratorParametersBuilder},
+            provenance::Provenance,
+        },
+        data::dataset::Dataset,
+        problems::iris::{IrisEngine, IrisStateParameters},
+    };
+
+    use super::*;
+
+    fn program_parameters() -> ProgramGeneratorParameters {
+        ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_actions(2)
+                    .n_inputs(4)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    fn child(operator: Variation, parents: &[&Program], generation: usize) -> Program {
+        let mut child: Program = GenerateEngine::generate(program_parameters());
+        child.set_provenance(Provenance::new(operator, parents, generation));
+        child
+    }
+
+    #[test]
+    fn given_dead_branch_when_retention_elapses_then_it_is_pruned() {
+        let founders: [Program; 2] =
+            [(); 2].map(|_| GenerateEngine::generate(program_parameters()));
+        let [a, b] = &founders;
+        let c = child(Variation::Mutation, &[a], 0);
+        let d = child(Variation::Mutation, &[&c], 1);
+
+        for (retention, n_after_second, n_after_third) in [(0, 2, 3), (1, 3, 3)] {
+            let mut tracker = LineageTracker::new(retention);
+            tracker.record(0, &founders);
+            assert_eq!(tracker.len(), 2);
+
+            // `b` dies out without offspring, `a` lives on through `c`.
+            tracker.record(1, &[c.clone()]);
+            assert_eq!(tracker.len(), n_after_second, "{}", retention);
+            tracker.record(2, &[d.clone()]);
+            assert_eq!(tracker.len(), n_after_third, "{}", retention);
+
+            assert!(tracker.get(&b.id).is_none());
+            assert_eq!(tracker.ancestry(d.id).nodes.len(), 3);
+        }
+    }
+
+    #[test]
+    fn given_five_generations_when_tracked_then_best_ancestry_is_connected_down_to_founders() {
+        let rows = vec![
+            vec![5.1, 3.5, 1.4, 0.2],
+            vec![7.0, 3.2, 4.7, 1.4],
+            vec![4.9, 3.0, 1.4, 0.2],
+            vec![6.4, 3.2, 4.5, 1.5],
+        ];
+        let dataset = Dataset::new(rows, vec![0, 1, 0, 1], vec![], vec![]);
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters())
+            .population_size(10)
+            .n_trials(1)
+            .n_generations(4)
+            .track_lineage(true)
+            .seed(Some(7))
+            .build()
+            .unwrap();
+
+        let mut engine = parameters.build_engine_with(IrisStateParameters::new(dataset));
+        let populations = engine.by_ref().collect_vec();
+        assert_eq!(populations.len(), 5);
+
+        let best = &populations[4][0];
+        let lineage = engine.lineage().unwrap().ancestry(best.id);
+        let ids = lineage
+            .nodes
+            .iter()
+            .map(|node| node.id)
+            .collect::<HashSet<_>>();
+        let founders = populations[0]
+            .iter()
+            .map(|program| program.id)
+            .collect::<HashSet<_>>();
+
+        assert!(ids.contains(&best.id));
+        for node in &lineage.nodes {
+            assert!(node.parents.iter().all(|parent| ids.contains(parent)));
+
+            if node.parents.is_empty() {
+                assert_eq!(node.generation, 0);
+                assert!(founders.contains(&node.id));
+            } else {
+                assert!(node.generation > 0);
+            }
+        }
+
+        let dot = lineage.to_dot();
+        assert!(dot.starts_with("digraph lineage {\n"));
+        assert!(dot.ends_with("}\n"));
+        let root = lineage
+            .nodes
+            .iter()
+            .find(|node| node.id == best.id)
+            .unwrap();
+        for parent in &root.parents {
+            assert!(dot.contains(&format!("\"{}\" -> \"{}\"", parent, best.id)));
+        }
+    }
+}
@@ -1,9 +1,26 @@
+pub mod adaptation;
 pub mod characteristics;
+#[cfg(feature = "cli")]
 pub mod config;
+pub mod config_override;
+pub mod diversity;
 pub mod environment;
+pub mod hall_of_fame;
+pub mod initialization;
 pub mod instruction;
+pub mod instruction_stats;
 pub mod instructions;
+pub mod lineage;
+pub mod niching;
+pub mod novelty;
+pub mod pareto;
 pub mod program;
+pub mod provenance;
 pub mod registers;
+#[cfg(feature = "cli")]
+pub mod registry;
+pub mod schedule;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
 
 pub mod engines;
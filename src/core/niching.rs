@@ -0,0 +1,203 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    characteristics::Genotype,
+    engines::{core_engine::Core, status_engine::Status},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+    /// Edit distance between instruction sequences, plus the distance between learned state for
+    /// individuals that carry it (see `Genotype::distance`).
+    #[default]
+    Genotypic,
+    /// Euclidean distance between per-trial score vectors.
+    Phenotypic,
+}
+
+/// Fitness sharing: individuals closer than `radius` share their fitness, penalizing crowded
+/// niches during selection.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NichingConfig {
+    pub radius: f64,
+    #[serde(default)]
+    pub metric: DistanceMetric,
+}
+
+pub fn genotypic_distance<T: Genotype>(a: &T, b: &T) -> f64 {
+    a.distance(b)
+}
+
+pub fn phenotypic_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+impl NichingConfig {
+    fn sharing(&self, distance: f64) -> f64 {
+        if distance < self.radius {
+            1. - distance / self.radius
+        } else {
+            0.
+        }
+    }
+
+    /// Computes the shared fitness of every individual, where `behaviours[i]` holds the per-trial
+    /// scores of `population[i]`.
+    ///
+    /// Fitness values are shifted to be non-negative before sharing so that dividing by the niche
+    /// count always acts as a penalty.
+    pub fn shared_fitness<C>(
+        &self,
+        population: &[C::Individual],
+        behaviours: &[Vec<f64>],
+    ) -> Vec<f64>
+    where
+        C: Core,
+    {
+        let fitness = population.iter().map(C::Status::get_fitness).collect_vec();
+        let offset = fitness.iter().copied().fold(0., f64::min);
+
+        (0..population.len())
+            .map(|i| {
+                let niche_count: f64 = (0..population.len())
+                    .map(|j| {
+                        let distance = match self.metric {
+                            DistanceMetric::Genotypic => {
+                                genotypic_distance(&population[i], &population[j])
+                            }
+                            DistanceMetric::Phenotypic => {
+                                phenotypic_distance(&behaviours[i], &behaviours[j])
+                            }
+                        };
+                        self.sharing(distance)
+                    })
+                    .sum();
+
+                (fitness[i] - offset) / niche_count
+            })
+            .collect()
+    }
+
+    /// Orders the population by descending shared fitness. The raw fitness of each individual is
+    /// left untouched.
+    pub fn rank<C>(&self, population: &mut Vec<C::Individual>, behaviours: &[Vec<f64>])
+    where
+        C: Core,
+    {
+        let shared = self.shared_fitness::<C>(population, behaviours);
+
+        *population = population
+            .drain(..)
+            .zip(shared)
+            .sorted_by(|(_, a), (_, b)| b.total_cmp(a))
+            .map(|(individual, _)| individual)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::instructions::OpSet;
+    use crate::{
+        core::{
+            engines::{
+                fitness_engine::Objective,
+                generate_engine::{Generate, GenerateEngine},
+                status_engine::StatusEngine,
+            },
+            instruction::InstructionGeneratorParameters,
+            program::{Program, ProgramGeneratorParameters},
+        },
+        problems::iris::IrisEngine,
+    };
+
+    use super::*;
+    use crate::core::registers::{RegisterInit, RegisterSaturation};
+
+    fn clusters() -> Vec<Program> {
+        let parameters = ProgramGeneratorParameters {
+            max_instructions: 10,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 1,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 10.,
+                input_scales: None,
+                n_actions: 3,
+                n_inputs: 4,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        };
+
+        let mut a: Program = GenerateEngine::generate(parameters);
+        a.instructions.truncate(2);
+        StatusEngine::set_fitness(&mut a, 1.);
+
+        let mut b = a.clone();
+        b.instructions.truncate(1);
+        StatusEngine::set_fitness(&mut b, 0.9);
+
+        let mut population = vec![a; 8];
+        population.extend(vec![b; 2]);
+        population
+    }
+
+    fn n_small_cluster_in_top_half(population: &[Program]) -> usize {
+        population[..population.len() / 2]
+            .iter()
+            .filter(|individual| individual.instructions.len() == 1)
+            .count()
+    }
+
+    #[test]
+    fn given_two_clusters_when_sharing_then_small_cluster_is_selected_more() {
+        let mut raw = clusters();
+        IrisEngine::rank(&mut raw, Objective::Maximize);
+
+        let mut shared = clusters();
+        let behaviours = vec![vec![]; shared.len()];
+        let niching = NichingConfig {
+            radius: 1.,
+            metric: DistanceMetric::Genotypic,
+        };
+        niching.rank::<IrisEngine>(&mut shared, &behaviours);
+
+        assert_eq!(n_small_cluster_in_top_half(&raw), 0);
+        assert_eq!(n_small_cluster_in_top_half(&shared), 2);
+
+        // Raw fitness is not modified by sharing.
+        assert!(shared
+            .iter()
+            .all(|individual| [1., 0.9].contains(&StatusEngine::get_fitness(individual))));
+    }
+
+    #[test]
+    fn given_phenotypic_metric_then_distance_uses_behaviours() {
+        let population = clusters();
+        let behaviours = population
+            .iter()
+            .map(|individual| vec![StatusEngine::get_fitness(individual)])
+            .collect_vec();
+        let niching = NichingConfig {
+            radius: 0.05,
+            metric: DistanceMetric::Phenotypic,
+        };
+
+        let shared = niching.shared_fitness::<IrisEngine>(&population, &behaviours);
+
+        assert_eq!(shared[0], 1. / 8.);
+        assert_eq!(shared[9], 0.9 / 2.);
+    }
+}
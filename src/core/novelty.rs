@@ -0,0 +1,219 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    engines::{core_engine::Core, status_engine::Status},
+    niching::phenotypic_distance,
+};
+
+/// Novelty search: individuals are rewarded for behaving unlike the current population and the
+/// archive of past behaviours, described by the `BehaviorDescriptor` of the trials they played.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NoveltyConfig {
+    /// Nearest neighbours whose mean distance is an individual's novelty.
+    #[serde(default = "default_k")]
+    pub k: usize,
+    /// Novelty a descriptor needs to be added to the archive.
+    pub sparseness_threshold: f64,
+    /// Weight of novelty in selection: `1` selects on novelty alone, `0` on fitness alone.
+    /// Both are scaled to `[0, 1]` over the generation before blending.
+    #[serde(default = "default_novelty_weight")]
+    pub novelty_weight: f64,
+}
+
+fn default_k() -> usize {
+    15
+}
+
+fn default_novelty_weight() -> f64 {
+    1.
+}
+
+/// Behaviour descriptors novel enough when they were seen, saved with the run outputs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoveltyArchive {
+    pub descriptors: Vec<Vec<f64>>,
+}
+
+impl NoveltyArchive {
+    pub fn len(&self) -> usize {
+        self.descriptors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.descriptors.is_empty()
+    }
+
+    /// Novelty of every descriptor: the mean distance to its `k` nearest neighbours among the
+    /// other descriptors and the archive. Descriptors more novel than the sparseness threshold
+    /// are archived afterwards.
+    pub fn update(&mut self, descriptors: &[Vec<f64>], config: &NoveltyConfig) -> Vec<f64> {
+        let novelty = descriptors
+            .iter()
+            .enumerate()
+            .map(|(i, descriptor)| {
+                let distances = descriptors
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, other)| other)
+                    .chain(self.descriptors.iter())
+                    .map(|other| phenotypic_distance(descriptor, other))
+                    .sorted_by(f64::total_cmp)
+                    .take(config.k)
+                    .collect_vec();
+
+                if distances.is_empty() {
+                    0.
+                } else {
+                    distances.iter().sum::<f64>() / distances.len() as f64
+                }
+            })
+            .collect_vec();
+
+        for (descriptor, novelty) in descriptors.iter().zip(novelty.iter()) {
+            if *novelty > config.sparseness_threshold {
+                self.descriptors.push(descriptor.clone());
+            }
+        }
+
+        novelty
+    }
+}
+
+/// Scales `values` to `[0, 1]`; all zeros when they are equal.
+fn min_max_scale(values: &[f64]) -> Vec<f64> {
+    let (low, high) = values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &value| {
+            (low.min(value), high.max(value))
+        });
+
+    values
+        .iter()
+        .map(|value| {
+            if high > low {
+                (value - low) / (high - low)
+            } else {
+                0.
+            }
+        })
+        .collect()
+}
+
+impl NoveltyConfig {
+    /// Selection score of every individual, blending its fitness with `novelty[i]`.
+    pub fn scores<C>(&self, population: &[C::Individual], novelty: &[f64]) -> Vec<f64>
+    where
+        C: Core,
+    {
+        let fitness = population.iter().map(C::Status::get_fitness).collect_vec();
+        let weight = self.novelty_weight.clamp(0., 1.);
+
+        min_max_scale(&fitness)
+            .into_iter()
+            .zip(min_max_scale(novelty))
+            .map(|(fitness, novelty)| (1. - weight) * fitness + weight * novelty)
+            .collect()
+    }
+
+    /// Orders the population by descending selection score. The raw fitness of each individual
+    /// is left untouched.
+    pub fn rank<C>(&self, population: &mut Vec<C::Individual>, novelty: &[f64])
+    where
+        C: Core,
+    {
+        let scores = self.scores::<C>(population, novelty);
+
+        *population = population
+            .drain(..)
+            .zip(scores)
+            .sorted_by(|(_, a), (_, b)| b.total_cmp(a))
+            .map(|(individual, _)| individual)
+            .collect();
+    }
+
+    /// `k` must be positive, the threshold finite and the weight in `[0, 1]`.
+    pub fn is_valid(&self) -> bool {
+        self.k >= 1
+            && self.sparseness_threshold.is_finite()
+            && (0. ..=1.).contains(&self.novelty_weight)
+    }
+}
+
+/// Element-wise mean of the descriptors of the trials an individual played; empty when there
+/// are none.
+pub fn mean_descriptor(descriptors: &[Vec<f64>]) -> Vec<f64> {
+    let Some(first) = descriptors.first() else {
+        return vec![];
+    };
+
+    (0..first.len())
+        .map(|idx| {
+            descriptors
+                .iter()
+                .map(|descriptor| descriptor[idx])
+                .sum::<f64>()
+                / descriptors.len() as f64
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(k: usize, sparseness_threshold: f64) -> NoveltyConfig {
+        NoveltyConfig {
+            k,
+            sparseness_threshold,
+            novelty_weight: 1.,
+        }
+    }
+
+    #[test]
+    fn given_scripted_descriptors_then_novelty_is_mean_distance_to_k_nearest() {
+        let mut archive = NoveltyArchive {
+            descriptors: vec![vec![10., 0.]],
+        };
+        let descriptors = vec![vec![0., 0.], vec![3., 4.], vec![0., 1.]];
+
+        let novelty = archive.update(&descriptors, &config(2, 100.));
+
+        // [0, 0]: distances 5, 1 and 10, nearest two 1 and 5.
+        // [3, 4]: distances 5, sqrt(18) and sqrt(65), nearest two sqrt(18) and 5.
+        // [0, 1]: distances 1, sqrt(18) and sqrt(101), nearest two 1 and sqrt(18).
+        let expected = [3., (18f64.sqrt() + 5.) / 2., (1. + 18f64.sqrt()) / 2.];
+        for (novelty, expected) in novelty.iter().zip(expected) {
+            assert!((novelty - expected).abs() < 1e-12);
+        }
+        assert_eq!(archive.len(), 1);
+    }
+
+    #[test]
+    fn given_sparseness_threshold_then_only_novel_descriptors_are_archived() {
+        let mut archive = NoveltyArchive::default();
+        let descriptors = vec![vec![0.], vec![0.5], vec![10.]];
+
+        // Novelty with k = 1: 0.5, 0.5 and 9.5.
+        archive.update(&descriptors, &config(1, 1.));
+        assert_eq!(archive.descriptors, vec![vec![10.]]);
+
+        // [10.2] is 0.2 from the archived [10]; [-5] is 15 from its nearest neighbour.
+        let novelty = archive.update(&[vec![10.2], vec![-5.]], &config(1, 1.));
+        assert!((novelty[0] - 0.2).abs() < 1e-12);
+        assert_eq!(archive.descriptors, vec![vec![10.], vec![-5.]]);
+    }
+
+    #[test]
+    fn given_trial_descriptors_then_their_mean_describes_the_individual() {
+        assert_eq!(mean_descriptor(&[vec![1., 4.], vec![3., 0.]]), vec![2., 2.]);
+        assert!(mean_descriptor(&[]).is_empty());
+    }
+
+    #[test]
+    fn given_equal_values_when_scaled_then_they_are_zero() {
+        assert_eq!(min_max_scale(&[2., 2.]), vec![0., 0.]);
+        assert_eq!(min_max_scale(&[1., 3., 2.]), vec![0., 1., 0.5]);
+    }
+}
@@ -0,0 +1,286 @@
+use std::cmp::Ordering;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    Fitness,
+    /// Prefers shorter effective programs.
+    Parsimony,
+}
+
+/// The objectives optimized by the engine, written as a list in config files
+/// (e.g. `objectives = ["fitness", "parsimony"]`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Objective>", into = "Vec<Objective>")]
+pub struct Objectives {
+    pub parsimony: bool,
+}
+
+impl Objectives {
+    pub fn is_multi_objective(&self) -> bool {
+        self.parsimony
+    }
+}
+
+impl TryFrom<Vec<Objective>> for Objectives {
+    type Error = String;
+
+    fn try_from(objectives: Vec<Objective>) -> Result<Self, Self::Error> {
+        if !objectives.contains(&Objective::Fitness) {
+            return Err("`fitness` must be one of the objectives".to_string());
+        }
+
+        Ok(Objectives {
+            parsimony: objectives.contains(&Objective::Parsimony),
+        })
+    }
+}
+
+impl From<Objectives> for Vec<Objective> {
+    fn from(objectives: Objectives) -> Self {
+        let mut list = vec![Objective::Fitness];
+
+        if objectives.parsimony {
+            list.push(Objective::Parsimony);
+        }
+
+        list
+    }
+}
+
+/// Whether `a` is at least as good as `b` on every objective and strictly better on one.
+/// All objectives are maximized.
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x >= y) && a.iter().zip(b).any(|(x, y)| x > y)
+}
+
+/// Fast non-dominated sorting: returns the indices of each front, best front first.
+pub fn non_dominated_fronts(objectives: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let n = objectives.len();
+    let mut dominated_by: Vec<Vec<usize>> = vec![vec![]; n];
+    let mut n_dominating = vec![0; n];
+
+    for i in 0..n {
+        for j in 0..n {
+            if dominates(&objectives[i], &objectives[j]) {
+                dominated_by[i].push(j);
+            } else if dominates(&objectives[j], &objectives[i]) {
+                n_dominating[i] += 1;
+            }
+        }
+    }
+
+    let mut fronts = vec![];
+    let mut current = (0..n).filter(|i| n_dominating[*i] == 0).collect_vec();
+
+    while !current.is_empty() {
+        let mut next = vec![];
+
+        for i in current.iter() {
+            for j in dominated_by[*i].iter() {
+                n_dominating[*j] -= 1;
+
+                if n_dominating[*j] == 0 {
+                    next.push(*j);
+                }
+            }
+        }
+
+        fronts.push(current);
+        current = next;
+    }
+
+    fronts
+}
+
+/// Crowding distance of every member of `front`, in the same order. Boundary points receive an
+/// infinite distance.
+pub fn crowding_distance(objectives: &[Vec<f64>], front: &[usize]) -> Vec<f64> {
+    let mut distances = vec![0.; front.len()];
+    let n_objectives = objectives.first().map(Vec::len).unwrap_or(0);
+
+    if front.len() < 3 {
+        return vec![f64::INFINITY; front.len()];
+    }
+
+    for m in 0..n_objectives {
+        let order = (0..front.len())
+            .sorted_by(|a, b| objectives[front[*a]][m].total_cmp(&objectives[front[*b]][m]))
+            .collect_vec();
+
+        let min = objectives[front[order[0]]][m];
+        let max = objectives[front[*order.last().unwrap()]][m];
+
+        distances[order[0]] = f64::INFINITY;
+        distances[*order.last().unwrap()] = f64::INFINITY;
+
+        if max - min <= 0. {
+            continue;
+        }
+
+        for k in 1..order.len() - 1 {
+            let previous = objectives[front[order[k - 1]]][m];
+            let next = objectives[front[order[k + 1]]][m];
+            distances[order[k]] += (next - previous) / (max - min);
+        }
+    }
+
+    distances
+}
+
+/// Orders indices front by front, and by descending crowding distance within a front, so that
+/// truncating the order keeps whole fronts first and the least crowded members of the last one.
+pub fn pareto_order(objectives: &[Vec<f64>]) -> Vec<usize> {
+    non_dominated_fronts(objectives)
+        .into_iter()
+        .flat_map(|front| {
+            let distances = crowding_distance(objectives, &front);
+
+            front
+                .into_iter()
+                .zip(distances)
+                .sorted_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal))
+                .map(|(idx, _)| idx)
+                .collect_vec()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::instructions::OpSet;
+    use crate::{
+        core::{
+            engines::{
+                core_engine::Core,
+                fitness_engine::Objective,
+                generate_engine::{Generate, GenerateEngine},
+                status_engine::{Status, StatusEngine},
+            },
+            instruction::{Instruction, InstructionGeneratorParameters, Mode, Op},
+            program::{Program, ProgramGeneratorParameters},
+        },
+        problems::iris::IrisEngine,
+    };
+
+    use super::*;
+    use crate::core::registers::{RegisterInit, RegisterSaturation};
+
+    /// (fitness, -length) pairs.
+    fn objectives() -> Vec<Vec<f64>> {
+        vec![
+            vec![10., -5.], // 0: front 0
+            vec![8., -2.],  // 1: front 0
+            vec![8., -6.],  // 2: dominated by 0 and 1 -> front 1
+            vec![5., -1.],  // 3: front 0
+            vec![4., -3.],  // 4: dominated by 1 -> front 1
+            vec![3., -7.],  // 5: dominated by 2 and 4 -> front 2
+        ]
+    }
+
+    #[test]
+    fn given_known_objectives_when_sorted_then_fronts_match() {
+        let fronts = non_dominated_fronts(&objectives())
+            .into_iter()
+            .map(|front| front.into_iter().sorted().collect_vec())
+            .collect_vec();
+
+        assert_eq!(fronts, vec![vec![0, 1, 3], vec![2, 4], vec![5]]);
+    }
+
+    #[test]
+    fn given_front_when_crowding_distance_then_boundaries_are_infinite() {
+        let objectives = objectives();
+        let distances = crowding_distance(&objectives, &[0, 1, 3]);
+
+        assert_eq!(distances[0], f64::INFINITY);
+        assert_eq!(distances[2], f64::INFINITY);
+        // (10 - 5) / (10 - 5) + (-1 - -5) / (-1 - -5) = 2.
+        assert_eq!(distances[1], 2.);
+    }
+
+    #[test]
+    fn given_objectives_when_ordered_then_fronts_are_kept_whole() {
+        let order = pareto_order(&objectives());
+
+        assert_eq!(order[..3].iter().sorted().collect_vec(), vec![&0, &1, &3]);
+        assert_eq!(order[3..5].iter().sorted().collect_vec(), vec![&2, &4]);
+        assert_eq!(order[5], 5);
+    }
+
+    #[test]
+    fn given_programs_with_known_fitness_and_length_then_pareto_front_matches() {
+        let parameters = ProgramGeneratorParameters {
+            max_instructions: 1,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 0,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 1.,
+                input_scales: None,
+                n_actions: 1,
+                n_inputs: 1,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        };
+        let program = |fitness: f64, length: usize| {
+            let mut program: Program = GenerateEngine::generate(parameters);
+            program.instructions =
+                vec![Instruction::new(0, 0, Mode::External, Op::Add, 1.); length];
+            StatusEngine::set_fitness(&mut program, fitness);
+            program
+        };
+
+        let population = vec![
+            program(10., 5),
+            program(8., 2),
+            program(8., 6),
+            program(5., 1),
+        ];
+
+        assert_eq!(StatusEngine::effective_length(&population[0]), 5);
+
+        let front = IrisEngine::pareto_front(&population, Objective::Maximize)
+            .iter()
+            .map(|individual| {
+                (
+                    StatusEngine::get_fitness(individual),
+                    individual.instructions.len(),
+                )
+            })
+            .sorted_by(|a, b| a.0.total_cmp(&b.0))
+            .collect_vec();
+
+        assert_eq!(front, vec![(5., 1), (8., 2), (10., 5)]);
+
+        let mut ranked = population.clone();
+        IrisEngine::rank_pareto(&mut ranked, Objective::Maximize);
+        assert_eq!(ranked.last().unwrap().instructions.len(), 6);
+
+        // Lower fitness is better when minimizing, so the shortest program dominates the rest.
+        let front = IrisEngine::pareto_front(&population, Objective::Minimize);
+        assert_eq!(front.len(), 1);
+        assert_eq!(StatusEngine::get_fitness(&front[0]), 5.);
+    }
+
+    #[test]
+    fn given_objective_lists_then_objectives_round_trip() {
+        let objectives: Objectives = serde_json::from_str(r#"["fitness", "parsimony"]"#).unwrap();
+        assert!(objectives.parsimony);
+        assert_eq!(
+            serde_json::to_string(&objectives).unwrap(),
+            r#"["fitness","parsimony"]"#
+        );
+
+        assert!(serde_json::from_str::<Objectives>(r#"["parsimony"]"#).is_err());
+    }
+}
@@ -1,27 +1,32 @@
-use std::iter::repeat_with;
+use std::{collections::HashSet, fmt, iter::repeat_with};
 
-use crate::utils::random::generator;
+use crate::utils::error::{require, ConfigViolation};
+use crate::utils::random::{generator, new_id};
 use clap::Args;
 use derivative::Derivative;
 use derive_builder::Builder;
+use itertools::Itertools;
 use rand::{seq::IteratorRandom, Rng};
 
-use serde::{Deserialize, Serialize};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 use uuid::Uuid;
 
 use super::{
+    adaptation::AdaptiveRates,
+    characteristics::Genotype,
     engines::{
-        breed_engine::{Breed, BreedEngine},
+        breed_engine::{Breed, BreedEngine, CrossoverKind},
         freeze_engine::{Freeze, FreezeEngine},
         generate_engine::{Generate, GenerateEngine},
         mutate_engine::{Mutate, MutateEngine},
         reset_engine::{Reset, ResetEngine},
-        status_engine::{Status, StatusEngine},
+        status_engine::{FitnessState, Status, StatusEngine},
     },
     environment::State,
-    instruction::InstructionGeneratorParameters,
+    instruction::{Instruction, InstructionGeneratorParameters},
     instructions::Instructions,
-    registers::Registers,
+    provenance::Provenance,
+    registers::{RegisterSaturation, Registers},
 };
 
 #[derive(Clone, Debug, Args, Deserialize, Serialize, Derivative, Builder)]
@@ -30,14 +35,68 @@ pub struct ProgramGeneratorParameters {
     #[arg(long, default_value = "12")]
     #[builder(default = "12")]
     pub max_instructions: usize,
+    /// Fewest instructions a generated program starts with.
+    #[arg(long, default_value = "1")]
+    #[builder(default = "1")]
+    #[serde(default = "default_min_instructions")]
+    pub min_instructions: usize,
     #[command(flatten)]
     pub instruction_generator_parameters: InstructionGeneratorParameters,
 }
 
+impl ProgramGeneratorParameters {
+    /// Violations of the generator's ranges, with fields nested under `prefix`.
+    pub fn violations(&self, prefix: &str) -> Vec<ConfigViolation> {
+        let mut violations = vec![];
+
+        require(
+            &mut violations,
+            self.max_instructions >= 1,
+            &format!("{}.max_instructions", prefix),
+            self.max_instructions,
+            "at least 1",
+        );
+        require(
+            &mut violations,
+            (1..=self.max_instructions).contains(&self.min_instructions),
+            &format!("{}.min_instructions", prefix),
+            self.min_instructions,
+            "in [1, max_instructions]",
+        );
+        violations.extend(
+            self.instruction_generator_parameters
+                .violations(&format!("{}.instruction_generator_parameters", prefix)),
+        );
+
+        violations
+    }
+}
+
+/// Program parameters whose inputs and actions are fixed by the problem, and whose program
+/// lengths initialization strategies adjust.
+pub trait ProgramShape {
+    fn program_parameters(&mut self) -> &mut ProgramGeneratorParameters;
+
+    fn instruction_parameters(&mut self) -> &mut InstructionGeneratorParameters {
+        &mut self.program_parameters().instruction_generator_parameters
+    }
+}
+
+impl ProgramShape for ProgramGeneratorParameters {
+    fn program_parameters(&mut self) -> &mut ProgramGeneratorParameters {
+        self
+    }
+}
+
+fn default_min_instructions() -> usize {
+    1
+}
+
 impl Reset<Program> for ResetEngine {
     fn reset(item: &mut Program) {
         ResetEngine::reset(&mut item.registers);
         ResetEngine::reset(&mut item.fitness);
+        item.trial_scores = None;
     }
 }
 
@@ -45,39 +104,153 @@ impl Freeze<Program> for FreezeEngine {}
 
 impl Status<Program> for StatusEngine {
     fn set_fitness(program: &mut Program, fitness: f64) {
-        program.fitness = fitness;
+        program.fitness = FitnessState::from_fitness(fitness);
     }
 
     fn get_fitness(program: &Program) -> f64 {
-        program.fitness
+        program.fitness.fitness()
     }
 
     fn valid(item: &Program) -> bool {
-        item.fitness.is_finite()
+        item.fitness.is_valid()
     }
 
     fn evaluated(item: &Program) -> bool {
-        !item.fitness.is_nan()
+        item.fitness.is_evaluated()
+    }
+
+    fn effective_length(item: &Program) -> usize {
+        item.effective_instructions().len()
+    }
+
+    fn take_saturations(item: &mut Program) -> usize {
+        item.registers.take_saturations()
+    }
+
+    fn set_trial_scores(item: &mut Program, scores: Vec<f64>) {
+        item.trial_scores = Some(scores);
+    }
+
+    fn trial_scores(item: &Program) -> Option<&[f64]> {
+        item.trial_scores.as_deref()
+    }
+}
+
+/// One instruction of a traced run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub index: usize,
+    pub instruction: String,
+    /// Source register and operand values read; `None` when skipped by a failed conditional.
+    pub operands: Option<(f64, f64)>,
+    /// Outcome of a conditional instruction.
+    pub condition: Option<bool>,
+    /// Registers after the instruction.
+    pub registers: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    pub steps: Vec<TraceStep>,
+}
+
+impl fmt::Display for ExecutionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            let read = match (step.operands, step.condition) {
+                (None, _) => "skipped".to_string(),
+                (Some((source, operand)), None) => format!("read {}, {}", source, operand),
+                (Some((source, operand)), Some(passed)) => {
+                    format!("read {}, {} -> {}", source, operand, passed)
+                }
+            };
+
+            writeln!(
+                f,
+                "{:>4}  {:<28} {:<32} [{}]",
+                step.index,
+                step.instruction,
+                read,
+                step.registers.iter().join(", ")
+            )?;
+        }
+
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Derivative, Builder)]
+#[derive(Debug, Clone, Deserialize, Builder)]
 pub struct Program {
     pub id: Uuid,
     pub instructions: Instructions,
     pub registers: Registers,
-    pub fitness: f64,
+    pub fitness: FitnessState,
+    /// Set under adaptive variation; kept by `Reset` and inherited by offspring.
+    #[builder(default)]
+    #[serde(default)]
+    pub adaptive_rates: Option<AdaptiveRates>,
+    /// Scores on each trial of the last evaluation, set under `record_trial_scores`.
+    #[builder(default)]
+    #[serde(default)]
+    pub trial_scores: Option<Vec<f64>>,
+    /// Set by variation; kept by `Reset`.
+    #[builder(default)]
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+}
+
+impl Serialize for Program {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Binary formats read fields by position, so only JSON may omit unset fields.
+        let human_readable = serializer.is_human_readable();
+        let skip_rates = human_readable && self.adaptive_rates.is_none();
+        let skip_scores = human_readable && self.trial_scores.is_none();
+        let skip_provenance = human_readable && self.provenance.is_none();
+        let n_fields = 7 - skip_rates as usize - skip_scores as usize - skip_provenance as usize;
+        let mut program = serializer.serialize_struct("Program", n_fields)?;
+
+        program.serialize_field("id", &self.id)?;
+        program.serialize_field("instructions", &self.instructions)?;
+        program.serialize_field("registers", &self.registers)?;
+        program.serialize_field("fitness", &self.fitness)?;
+        if skip_rates {
+            program.skip_field("adaptive_rates")?;
+        } else {
+            program.serialize_field("adaptive_rates", &self.adaptive_rates)?;
+        }
+        if skip_scores {
+            program.skip_field("trial_scores")?;
+        } else {
+            program.serialize_field("trial_scores", &self.trial_scores)?;
+        }
+        if skip_provenance {
+            program.skip_field("provenance")?;
+        } else {
+            program.serialize_field("provenance", &self.provenance)?;
+        }
+
+        program.end()
+    }
 }
 
+/// Programs are the same individual when they share an id, whatever their fitness.
 impl PartialEq for Program {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
     }
 }
 
+/// Orders by fitness as `FitnessState` does, from not evaluated (NaN) through invalid to the
+/// highest valid fitness. Equal fitness is broken by id, the smaller id ranking higher, so
+/// sorting does not depend on the order programs were produced in.
 impl Ord for Program {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        f64::total_cmp(&self.fitness, &other.fitness)
+        self.fitness
+            .cmp(&other.fitness)
+            .then_with(|| other.id.cmp(&self.id))
     }
 }
 
@@ -89,43 +262,368 @@ impl PartialOrd for Program {
     }
 }
 
+impl Genotype for Program {
+    fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    fn adaptive_rates(&self) -> Option<&AdaptiveRates> {
+        self.adaptive_rates.as_ref()
+    }
+
+    fn set_adaptive_rates(&mut self, rates: AdaptiveRates) {
+        self.adaptive_rates = Some(rates);
+    }
+
+    fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    fn set_provenance(&mut self, provenance: Provenance) {
+        self.provenance = Some(provenance);
+    }
+
+    fn id(&self) -> Option<Uuid> {
+        Some(self.id)
+    }
+}
+
 impl Program {
     pub fn run(&mut self, input: &impl State) {
+        self.registers.load_inputs(input);
+        let mut skip = false;
+
         for instruction in &self.instructions {
-            instruction.apply(&mut self.registers, input)
+            // A failed conditional skips exactly the next instruction.
+            if skip {
+                skip = false;
+                continue;
+            }
+
+            skip = !instruction.apply(&mut self.registers, input);
+            self.registers.saturate(instruction.src_idx());
         }
     }
+
+    /// Runs like `run` while recording every instruction, for debugging. Kept separate from
+    /// `run` so untraced execution does no extra work.
+    pub fn run_traced(&mut self, input: &impl State) -> ExecutionTrace {
+        self.registers.load_inputs(input);
+        let mut skip = false;
+        let mut steps = Vec::with_capacity(self.instructions.len());
+
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            let (operands, condition) = if skip {
+                skip = false;
+                (None, None)
+            } else {
+                let operands = instruction.operands(&self.registers, input);
+                skip = !instruction.apply(&mut self.registers, input);
+                self.registers.saturate(instruction.src_idx());
+
+                (
+                    Some(operands),
+                    instruction.is_conditional().then_some(!skip),
+                )
+            };
+
+            steps.push(TraceStep {
+                index,
+                instruction: instruction.to_string(),
+                operands,
+                condition,
+                registers: self.registers.iter().copied().collect(),
+            });
+        }
+
+        ExecutionTrace { steps }
+    }
+
+    /// Indices of the instructions whose result can reach an action register (i.e. excluding
+    /// introns), found by a backward pass over the register dependencies.
+    pub fn effective_instructions(&self) -> Vec<usize> {
+        let mut effective_registers: HashSet<usize> = (0..self.registers.n_outputs()).collect();
+        let mut effective = vec![];
+        let mut next_is_effective = false;
+
+        for (idx, instruction) in self.instructions.iter().enumerate().rev() {
+            // Conditionals matter only if they guard an effective instruction.
+            let is_effective = if instruction.is_conditional() {
+                next_is_effective
+            } else {
+                effective_registers.contains(&instruction.src_idx())
+            };
+
+            next_is_effective = is_effective;
+
+            if !is_effective {
+                continue;
+            }
+
+            effective.push(idx);
+
+            if instruction.is_conditional() {
+                effective_registers.insert(instruction.src_idx());
+            }
+
+            if let Some(register) = instruction.register_operand() {
+                effective_registers.insert(register);
+            }
+        }
+
+        effective.reverse();
+        effective
+    }
+
+    /// A smaller program producing the same outputs. Removes instructions whose results never
+    /// reach an output (including values overwritten before they are read), identities such as
+    /// `r1 = r1 * 1` or `r2 = r2 + 0`, and folds consecutive constant operations on a register.
+    ///
+    /// Rewrites repeat until none applies. A rewrite is only kept if the program still behaves
+    /// like this one on `probes`, since overwrites and folding assume finite, unsaturated values.
+    pub fn simplify<S: State>(&self, probes: &[S]) -> Program {
+        self.simplify_with(probes, &SIMPLIFICATIONS)
+    }
+
+    fn simplify_with<S: State>(&self, probes: &[S], rewrites: &[Rewrite]) -> Program {
+        let mut simplified = self.clone();
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for rewrite in rewrites {
+                let candidate = rewrite(&simplified);
+
+                // Every rewrite shrinks the program, which guarantees a fixpoint.
+                if candidate.instructions.len() < simplified.instructions.len()
+                    && self.behaves_like(&candidate, probes)
+                {
+                    simplified = candidate;
+                    changed = true;
+                }
+            }
+        }
+
+        simplified
+    }
+
+    /// Whether `other` leaves the same values in the output registers as this program after
+    /// every probe, running the probes in order from reset registers as evaluation does. Values
+    /// may differ by rounding.
+    pub fn behaves_like<S: State>(&self, other: &Program, probes: &[S]) -> bool {
+        let (mut program, mut other) = (self.clone(), other.clone());
+        ResetEngine::reset(&mut program.registers);
+        ResetEngine::reset(&mut other.registers);
+        let n_outputs = program.registers.n_outputs();
+
+        probes.iter().all(|probe| {
+            program.run(probe);
+            other.run(probe);
+
+            program.registers[..n_outputs]
+                .iter()
+                .zip(&other.registers[..n_outputs])
+                .all(|(a, b)| {
+                    a == b
+                        || (a.is_nan() && b.is_nan())
+                        || (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.)
+                })
+        })
+    }
+
+    /// Whether the instruction at `idx` may be skipped by a conditional.
+    fn is_guarded(&self, idx: usize) -> bool {
+        idx > 0 && self.instructions[idx - 1].is_conditional()
+    }
+
+    fn without(&self, removed: &HashSet<usize>) -> Program {
+        let mut program = self.clone();
+        program.instructions = self
+            .instructions
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !removed.contains(idx))
+            .map(|(_, instruction)| *instruction)
+            .collect();
+
+        program
+    }
+
+    /// Removes instructions whose results never reach an output. Unlike `effective_instructions`,
+    /// registers carried over to the next run count as read, and a register is dead before an
+    /// instruction that always overwrites it.
+    fn without_dead_code(&self) -> Program {
+        let outputs: HashSet<usize> = (0..self.registers.n_outputs()).collect();
+        let mut live_at_end = outputs.clone();
+
+        loop {
+            let (effective, live_at_start) = self.liveness(&live_at_end);
+            let carried = outputs.union(&live_at_start).copied().collect();
+
+            if carried == live_at_end {
+                let removed = (0..self.instructions.len())
+                    .filter(|idx| !effective.contains(idx))
+                    .collect();
+                return self.without(&removed);
+            }
+
+            live_at_end = carried;
+        }
+    }
+
+    /// Backward pass returning the effective instructions and the registers read before being
+    /// written, given the registers read after the program ends.
+    fn liveness(&self, live_at_end: &HashSet<usize>) -> (HashSet<usize>, HashSet<usize>) {
+        let mut live = live_at_end.clone();
+        let mut effective = HashSet::new();
+        let mut next_is_effective = false;
+
+        for (idx, instruction) in self.instructions.iter().enumerate().rev() {
+            // Conditionals matter only if they guard an effective instruction.
+            let is_effective = if instruction.is_conditional() {
+                next_is_effective
+            } else {
+                live.contains(&instruction.src_idx())
+            };
+
+            next_is_effective = is_effective;
+
+            if !is_effective {
+                continue;
+            }
+
+            effective.insert(idx);
+
+            if instruction.overwrites_source() && !self.is_guarded(idx) {
+                live.remove(&instruction.src_idx());
+            } else {
+                live.insert(instruction.src_idx());
+            }
+
+            if let Some(register) = instruction.register_operand() {
+                live.insert(register);
+            }
+        }
+
+        (effective, live)
+    }
+
+    /// Removes identities, together with the conditional guarding them.
+    fn without_identities(&self) -> Program {
+        let mut removed = HashSet::new();
+
+        for (idx, instruction) in self.instructions.iter().enumerate() {
+            if !instruction.is_identity() {
+                continue;
+            }
+
+            if !self.is_guarded(idx) {
+                removed.insert(idx);
+            } else if !self.is_guarded(idx - 1) {
+                removed.extend([idx - 1, idx]);
+            }
+        }
+
+        self.without(&removed)
+    }
+
+    /// Folds pairs of consecutive constant operations on the same register. Skipped under
+    /// register saturation, which applies after every instruction.
+    fn with_folded_constants(&self) -> Program {
+        let mut program = self.clone();
+
+        if self.registers.saturation() != RegisterSaturation::None {
+            return program;
+        }
+
+        let mut instructions = vec![];
+        let mut idx = 0;
+
+        while idx < self.instructions.len() {
+            let instruction = self.instructions[idx];
+            let folded = self
+                .instructions
+                .get(idx + 1)
+                .filter(|_| !self.is_guarded(idx))
+                .and_then(|next| instruction.fold(next));
+
+            match folded {
+                Some(folded) => {
+                    instructions.push(folded);
+                    idx += 2;
+                }
+                None => {
+                    instructions.push(instruction);
+                    idx += 1;
+                }
+            }
+        }
+
+        program.instructions = instructions;
+        program
+    }
 }
 
+type Rewrite = fn(&Program) -> Program;
+
+/// Rewrites applied by `Program::simplify`, in order.
+const SIMPLIFICATIONS: [Rewrite; 3] = [
+    Program::without_dead_code,
+    Program::without_identities,
+    Program::with_folded_constants,
+];
+
 impl Generate<ProgramGeneratorParameters, Program> for GenerateEngine {
     fn generate(using: ProgramGeneratorParameters) -> Program {
         let ProgramGeneratorParameters {
             max_instructions,
+            min_instructions,
             instruction_generator_parameters,
-            ..
         } = using;
 
         let registers = Registers::new(
-            instruction_generator_parameters.n_actions,
-            instruction_generator_parameters.n_extras,
-        );
-        let n_instructions = generator().gen_range(1..=max_instructions);
+            instruction_generator_parameters.n_output_registers(),
+            instruction_generator_parameters.n_calculation_registers,
+        )
+        .with_init(
+            instruction_generator_parameters.register_init,
+            instruction_generator_parameters.n_inputs,
+        )
+        .with_saturation(instruction_generator_parameters.register_saturation);
+        let n_instructions =
+            generator().gen_range(min_instructions.clamp(1, max_instructions)..=max_instructions);
         let instructions =
             repeat_with(|| GenerateEngine::generate(instruction_generator_parameters))
                 .take(n_instructions)
                 .collect();
 
         Program {
-            id: Uuid::new_v4(),
+            id: new_id(),
             instructions,
             registers,
-            fitness: f64::NAN,
+            fitness: FitnessState::NotEvaluated,
+            adaptive_rates: None,
+            trial_scores: None,
+            provenance: None,
         }
     }
 }
 
 impl Mutate<ProgramGeneratorParameters, Program> for MutateEngine {
     fn mutate(item: &mut Program, using: ProgramGeneratorParameters) {
+        let mut instruction_parameters = using.instruction_generator_parameters;
+
+        // Rates mutate first so that the new step size is the one applied.
+        if let Some(rates) = item.adaptive_rates.as_mut() {
+            rates.perturb(&mut generator());
+            instruction_parameters.constant_sigma *= rates.mutation_sigma;
+        }
+
         // Pick instruction to mutate.
         let instruction = item
             .instructions
@@ -133,7 +631,7 @@ impl Mutate<ProgramGeneratorParameters, Program> for MutateEngine {
             .choose(&mut generator())
             .unwrap();
 
-        MutateEngine::mutate(instruction, using.instruction_generator_parameters);
+        MutateEngine::mutate(instruction, instruction_parameters);
 
         ResetEngine::reset(&mut item.id);
         ResetEngine::reset(item);
@@ -142,8 +640,24 @@ impl Mutate<ProgramGeneratorParameters, Program> for MutateEngine {
 
 impl Breed<Program> for BreedEngine {
     fn two_point_crossover(mate_1: &Program, mate_2: &Program) -> (Program, Program) {
+        BreedEngine::crossover(mate_1, mate_2, CrossoverKind::TwoPoint)
+    }
+
+    fn one_point_crossover(mate_1: &Program, mate_2: &Program) -> (Program, Program) {
+        BreedEngine::crossover(mate_1, mate_2, CrossoverKind::OnePoint)
+    }
+
+    fn uniform_crossover(
+        mate_1: &Program,
+        mate_2: &Program,
+        swap_probability: f64,
+    ) -> (Program, Program) {
+        BreedEngine::crossover(mate_1, mate_2, CrossoverKind::Uniform(swap_probability))
+    }
+
+    fn crossover(mate_1: &Program, mate_2: &Program, kind: CrossoverKind) -> (Program, Program) {
         let (child_1_instructions, child_2_instructions) =
-            BreedEngine::two_point_crossover(&mate_1.instructions, &mate_2.instructions);
+            BreedEngine::crossover(&mate_1.instructions, &mate_2.instructions, kind);
 
         let mut child_1 = mate_1.clone();
         let mut child_2 = mate_2.clone();
@@ -163,18 +677,324 @@ impl Breed<Program> for BreedEngine {
 
 #[cfg(test)]
 mod tests {
+    use crate::core::instructions::OpSet;
 
-    use crate::core::instruction::InstructionGeneratorParameters;
+    use std::cmp::Ordering;
+
+    use crate::core::instruction::{
+        InstructionGeneratorParameters, InstructionGeneratorParametersBuilder, Mode, Op,
+    };
 
     use super::*;
+    use crate::core::registers::{RegisterInit, RegisterSaturation};
+
+    struct Input(f64);
+
+    impl State for Input {
+        fn get_value(&self, _at_idx: usize) -> f64 {
+            self.0
+        }
+
+        fn execute_action(&mut self, _action: usize) -> f64 {
+            0.
+        }
+
+        fn get(&mut self) -> Option<&mut Self> {
+            Some(self)
+        }
+    }
+
+    fn ranked_program(id: u128, fitness: f64) -> Program {
+        let params = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(1)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_actions(2)
+                    .n_inputs(1)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        let mut program: Program = GenerateEngine::generate(params);
+        program.id = Uuid::from_u128(id);
+        StatusEngine::set_fitness(&mut program, fitness);
+        program
+    }
+
+    fn unevaluated(id: u128) -> Program {
+        let mut program = ranked_program(id, 0.);
+        ResetEngine::reset(&mut program.fitness);
+        program
+    }
+
+    #[test]
+    fn given_unevaluated_and_invalid_fitness_when_compared_then_they_rank_below_any_valid_fitness()
+    {
+        let not_evaluated = unevaluated(1);
+        let invalid = ranked_program(2, f64::NEG_INFINITY);
+        let nan = ranked_program(4, f64::NAN);
+        let valid = ranked_program(3, -1e300);
+
+        assert_eq!(nan.fitness, FitnessState::Invalid);
+        assert!(not_evaluated < invalid);
+        assert!(not_evaluated < nan);
+        assert!(invalid < valid);
+        assert!(not_evaluated < valid);
+        assert_eq!(not_evaluated.cmp(&not_evaluated.clone()), Ordering::Equal);
+    }
+
+    #[test]
+    fn given_equal_fitness_and_different_ids_when_compared_then_the_smaller_id_ranks_higher() {
+        let first = ranked_program(1, 0.5);
+        let second = ranked_program(2, 0.5);
+
+        assert_eq!(first.cmp(&second), Ordering::Greater);
+        assert_eq!(second.cmp(&first), Ordering::Less);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn given_mixed_population_when_sorted_in_any_order_then_ranking_is_the_same() {
+        let population = [
+            ranked_program(3, 0.5),
+            unevaluated(1),
+            ranked_program(4, f64::NEG_INFINITY),
+            ranked_program(2, 0.5),
+            ranked_program(5, 0.9),
+            unevaluated(6),
+        ];
+        let ranked = |mut population: Vec<Program>| {
+            population.sort_by(|a, b| b.cmp(a));
+            population
+                .iter()
+                .map(|program| program.id.as_u128())
+                .collect_vec()
+        };
+
+        let expected = vec![5, 2, 3, 4, 1, 6];
+        assert_eq!(ranked(population.to_vec()), expected);
+        assert_eq!(ranked(population.iter().rev().cloned().collect()), expected);
+    }
+
+    #[test]
+    fn given_conditional_when_run_then_guarded_write_depends_on_condition() {
+        let params = ProgramGeneratorParameters {
+            max_instructions: 1,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 0,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 1.,
+                input_scales: None,
+                n_actions: 2,
+                n_inputs: 1,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        };
+        let mut program: Program = GenerateEngine::generate(params);
+        program.instructions = vec![
+            // if r0 < x
+            Instruction::new(0, 0, Mode::External, Op::IfLess, 1.),
+            // r1 = r1 + x
+            Instruction::new(1, 0, Mode::External, Op::Add, 1.),
+            // r0 = r0 + 2 * x, always executed.
+            Instruction::new(0, 0, Mode::External, Op::Add, 2.),
+        ];
+
+        program.run(&Input(5.));
+        assert_eq!(program.registers[0..2], [10., 5.]);
+
+        ResetEngine::reset(&mut program.registers);
+        program.run(&Input(-5.));
+        assert_eq!(program.registers[0..2], [-10., 0.]);
+
+        assert_eq!(program.effective_instructions(), vec![0, 1, 2]);
+        assert_eq!(program.instructions[0].to_string(), "if r0 < 1 * x0");
+        assert!(serde_json::to_string(&program.instructions[0])
+            .unwrap()
+            .contains(r#""op":"if_lt""#));
+    }
+
+    #[test]
+    fn given_register_counts_when_generated_then_layout_follows_parameters() {
+        for (n_actions, n_output_registers, n_calculation_registers, n_registers) in
+            [(3, None, 1, 4), (3, Some(1), 2, 3), (2, Some(2), 0, 2)]
+        {
+            let params = ProgramGeneratorParameters {
+                max_instructions: 20,
+                min_instructions: 1,
+                instruction_generator_parameters: InstructionGeneratorParameters {
+                    n_calculation_registers,
+                    n_output_registers,
+                    register_init: RegisterInit::Ones,
+                    register_saturation: RegisterSaturation::None,
+                    external_factor: 1.,
+                    input_scales: None,
+                    n_actions,
+                    n_inputs: 2,
+                    ops: OpSet::default(),
+                    conditional_probability: 0.,
+                    constant_probability: 0.,
+                    constant_range: (-5., 5.),
+                    constant_sigma: 1.,
+                },
+            };
+            assert_eq!(
+                params.instruction_generator_parameters.n_registers(),
+                n_registers
+            );
+
+            let program: Program = GenerateEngine::generate(params);
+
+            assert_eq!(program.registers.len(), n_registers);
+            assert_eq!(
+                program.registers.n_outputs(),
+                n_output_registers.unwrap_or(n_actions)
+            );
+            assert!(program.registers.iter().all(|value| *value == 1.));
+            assert!(program
+                .instructions
+                .iter()
+                .all(|instruction| instruction.src_idx() < n_registers));
+        }
+    }
+
+    #[test]
+    fn given_legacy_n_extras_when_deserialized_then_it_sets_calculation_registers() {
+        let params: InstructionGeneratorParameters = serde_json::from_str(
+            r#"{"n_extras":2,"external_factor":1.0,"n_actions":3,"n_inputs":4}"#,
+        )
+        .unwrap();
+
+        assert_eq!(params.n_calculation_registers, 2);
+        assert_eq!(params.n_output_registers(), 3);
+        assert_eq!(params.register_init, RegisterInit::Zeros);
+        assert_eq!(params.n_registers(), 5);
+    }
+
+    #[test]
+    fn given_two_instructions_when_traced_then_register_snapshots_are_recorded() {
+        let params = ProgramGeneratorParameters {
+            max_instructions: 1,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 1,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 1.,
+                input_scales: None,
+                n_actions: 1,
+                n_inputs: 1,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        };
+        let mut program: Program = GenerateEngine::generate(params);
+        program.instructions = vec![
+            // r0 = r0 + 2 * x
+            Instruction::new(0, 0, Mode::External, Op::Add, 2.),
+            // r1 = r1 + r0
+            Instruction::new(1, 0, Mode::Internal, Op::Add, 1.),
+        ];
+
+        let trace = program.run_traced(&Input(3.));
+
+        assert_eq!(
+            trace.steps,
+            vec![
+                TraceStep {
+                    index: 0,
+                    instruction: program.instructions[0].to_string(),
+                    operands: Some((0., 6.)),
+                    condition: None,
+                    registers: vec![6., 0.],
+                },
+                TraceStep {
+                    index: 1,
+                    instruction: program.instructions[1].to_string(),
+                    operands: Some((0., 6.)),
+                    condition: None,
+                    registers: vec![6., 6.],
+                },
+            ]
+        );
+        assert_eq!(trace.to_string().lines().count(), 2);
+
+        // Tracing leaves the registers exactly as an untraced run would.
+        ResetEngine::reset(&mut program.registers);
+        program.run(&Input(3.));
+        assert_eq!(program.registers[..], trace.steps[1].registers[..]);
+
+        let round_trip: ExecutionTrace =
+            serde_json::from_str(&serde_json::to_string(&trace).unwrap()).unwrap();
+        assert_eq!(round_trip, trace);
+    }
+
+    #[test]
+    fn given_program_with_introns_when_effective_instructions_then_introns_are_excluded() {
+        let params = ProgramGeneratorParameters {
+            max_instructions: 1,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 2,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 1.,
+                input_scales: None,
+                n_actions: 1,
+                n_inputs: 1,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        };
+        let mut program: Program = GenerateEngine::generate(params);
+        program.instructions = vec![
+            // r1 = r1 + r2: effective, r1 is read below.
+            Instruction::new(1, 2, Mode::Internal, Op::Add, 1.),
+            // r2 = r2 * x: intron, r2 is never read afterwards.
+            Instruction::new(2, 0, Mode::External, Op::Mult, 1.),
+            // r0 = r0 - r1: effective.
+            Instruction::new(0, 1, Mode::Internal, Op::Sub, 1.),
+            // r1 = r1 / r0: intron, overwrites r1 after its last use.
+            Instruction::new(1, 0, Mode::Internal, Op::Divide, 1.),
+        ];
+
+        assert_eq!(program.effective_instructions(), vec![0, 2]);
+        assert_eq!(StatusEngine::effective_length(&program), 2);
+    }
 
     #[test]
     fn given_instructions_when_breed_then_two_children_are_produced_using_genes_of_parents() {
         let params = InstructionGeneratorParameters {
-            n_extras: 1,
+            n_calculation_registers: 1,
+            n_output_registers: None,
+            register_init: RegisterInit::Zeros,
+            register_saturation: RegisterSaturation::None,
             external_factor: 10.,
+            input_scales: None,
             n_actions: 4,
             n_inputs: 2,
+            ops: OpSet::default(),
+            conditional_probability: 0.,
+            constant_probability: 0.,
+            constant_range: (-5., 5.),
+            constant_sigma: 1.,
         };
         let instructions_a: Instructions =
             (0..10).map(|_| GenerateEngine::generate(params)).collect();
@@ -195,13 +1015,23 @@ mod tests {
     #[test]
     fn given_programs_when_two_point_crossover_then_two_children_are_produced() {
         let instruction_generator_parameters = InstructionGeneratorParameters {
-            n_extras: 1,
+            n_calculation_registers: 1,
+            n_output_registers: None,
+            register_init: RegisterInit::Zeros,
+            register_saturation: RegisterSaturation::None,
             external_factor: 10.,
+            input_scales: None,
             n_actions: 2,
             n_inputs: 4,
+            ops: OpSet::default(),
+            conditional_probability: 0.,
+            constant_probability: 0.,
+            constant_range: (-5., 5.),
+            constant_sigma: 1.,
         };
         let program_params = ProgramGeneratorParameters {
             max_instructions: 100,
+            min_instructions: 1,
             instruction_generator_parameters,
         };
 
@@ -218,4 +1048,242 @@ mod tests {
         assert_ne!(program_b, child_a);
         assert_ne!(program_b, child_b);
     }
+
+    /// Squared error of `r0` against `y = 3.7x` over a handful of samples.
+    fn regression_error(program: &mut Program) -> f64 {
+        [0.5, 1., 2., 3.]
+            .into_iter()
+            .map(|x| {
+                ResetEngine::reset(&mut program.registers);
+                program.run(&Input(x));
+                (program.registers[0] - 3.7 * x).powi(2)
+            })
+            .sum()
+    }
+
+    /// Lowest error found by a seeded hill climber with restarts.
+    fn fit(constant_probability: f64) -> f64 {
+        crate::utils::random::update_seed(Some(7));
+
+        let params = ProgramGeneratorParameters {
+            max_instructions: 2,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 0,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 1.,
+                input_scales: None,
+                n_actions: 1,
+                n_inputs: 1,
+                ops: OpSet::new(&[Op::Add, Op::Mult]),
+                conditional_probability: 0.,
+                constant_probability,
+                constant_range: (-5., 5.),
+                constant_sigma: 0.5,
+            },
+        };
+
+        let mut best_error = f64::INFINITY;
+
+        for _ in 0..20 {
+            let mut program: Program = GenerateEngine::generate(params);
+            let mut error = regression_error(&mut program);
+
+            for _ in 0..200 {
+                let mut candidate = program.clone();
+                MutateEngine::mutate(&mut candidate, params);
+                let candidate_error = regression_error(&mut candidate);
+
+                if candidate_error <= error {
+                    program = candidate;
+                    error = candidate_error;
+                }
+            }
+
+            best_error = best_error.min(error);
+        }
+
+        best_error
+    }
+
+    #[test]
+    fn given_regression_problem_when_constants_enabled_then_error_is_lower() {
+        let without_constants = fit(0.);
+        let with_constants = fit(0.5);
+
+        // Without constants the closest fit is x * x.
+        assert!(without_constants > 25.);
+        assert!(with_constants < without_constants);
+    }
+
+    #[test]
+    fn given_adaptive_parent_when_mutated_then_offspring_sigma_is_drawn_around_the_parent() {
+        let parameters = ProgramGeneratorParameters {
+            max_instructions: 5,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 1,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 1.,
+                input_scales: None,
+                n_actions: 1,
+                n_inputs: 1,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        };
+        let mut parent: Program = GenerateEngine::generate(parameters);
+        parent.set_adaptive_rates(AdaptiveRates {
+            mutation_sigma: 50.,
+            ..AdaptiveRates::new(0.5, 0.5)
+        });
+
+        let log_sigmas = (0..200)
+            .map(|_| {
+                let mut child = parent.clone();
+                MutateEngine::mutate(&mut child, parameters);
+                ResetEngine::reset(&mut child);
+
+                child.adaptive_rates.unwrap().mutation_sigma.ln()
+            })
+            .collect_vec();
+        let mean = log_sigmas.iter().sum::<f64>() / log_sigmas.len() as f64;
+
+        // The global default is a scale of 1, i.e. a log of 0.
+        assert!((mean - 50f64.ln()).abs() < 0.1, "{}", mean);
+        assert!(log_sigmas.iter().any(|sigma| *sigma != 50f64.ln()));
+
+        let json = serde_json::to_string(&parent).unwrap();
+        let loaded: Program = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.adaptive_rates, parent.adaptive_rates);
+    }
+
+    /// A program over one output (r0) and two calculation registers reading a single input.
+    fn program_with(instructions: Vec<Instruction>) -> Program {
+        let params = ProgramGeneratorParameters {
+            max_instructions: 1,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 2,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 1.,
+                input_scales: None,
+                n_actions: 1,
+                n_inputs: 1,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        };
+        let mut program: Program = GenerateEngine::generate(params);
+        program.instructions = instructions;
+
+        program
+    }
+
+    const PROBES: [Input; 4] = [Input(1.), Input(-2.), Input(0.5), Input(7.)];
+
+    #[test]
+    fn given_identities_when_simplified_then_they_are_removed_with_their_guards() {
+        let add_input = Instruction::new(0, 0, Mode::External, Op::Add, 1.);
+        let program = program_with(vec![
+            add_input,
+            Instruction::with_constant(0, Op::Mult, 1.),
+            Instruction::with_constant(0, Op::Add, 0.),
+            Instruction::with_constant(0, Op::Sub, 0.),
+            Instruction::with_constant(0, Op::Divide, 1.),
+            Instruction::new(0, 0, Mode::Internal, Op::Max, 1.),
+            // if r0 < x, then an identity: both go.
+            Instruction::new(0, 0, Mode::External, Op::IfLess, 1.),
+            Instruction::with_constant(0, Op::Add, 0.),
+        ]);
+
+        assert_eq!(program.simplify(&PROBES).instructions, vec![add_input]);
+    }
+
+    #[test]
+    fn given_overwritten_value_when_simplified_then_writes_before_the_overwrite_are_removed() {
+        let overwrite = Instruction::with_constant(0, Op::Mult, 0.);
+        let add_twice_input = Instruction::new(0, 0, Mode::External, Op::Add, 2.);
+        let program = program_with(vec![
+            // r0 = r0 + x: dead, r0 is zeroed before it is read.
+            Instruction::new(0, 0, Mode::External, Op::Add, 1.),
+            overwrite,
+            add_twice_input,
+            // r2 = r2 + x: intron.
+            Instruction::new(2, 0, Mode::External, Op::Add, 1.),
+        ]);
+
+        assert_eq!(
+            program.simplify(&PROBES).instructions,
+            vec![overwrite, add_twice_input]
+        );
+    }
+
+    #[test]
+    fn given_register_read_on_the_next_run_when_simplified_then_its_write_is_kept() {
+        let program = program_with(vec![
+            // r0 = r0 + r1, reading the r1 written on the previous run.
+            Instruction::new(0, 1, Mode::Internal, Op::Add, 1.),
+            // r1 = r1 + x
+            Instruction::new(1, 0, Mode::External, Op::Add, 1.),
+        ]);
+
+        assert_eq!(program.effective_instructions(), vec![0]);
+        assert_eq!(program.simplify(&PROBES).instructions, program.instructions);
+    }
+
+    #[test]
+    fn given_consecutive_constant_operations_when_simplified_then_they_are_folded() {
+        let add_input = Instruction::new(0, 0, Mode::External, Op::Add, 1.);
+        let program = program_with(vec![
+            Instruction::with_constant(0, Op::Add, 2.),
+            Instruction::with_constant(0, Op::Sub, 0.5),
+            Instruction::with_constant(0, Op::Mult, 3.),
+            Instruction::with_constant(0, Op::Mult, 2.),
+            add_input,
+        ]);
+
+        let simplified = program.simplify(&PROBES);
+
+        assert_eq!(
+            simplified.instructions,
+            vec![
+                Instruction::with_constant(0, Op::Add, 1.5),
+                Instruction::with_constant(0, Op::Mult, 6.),
+                add_input,
+            ]
+        );
+        assert!(program.behaves_like(&simplified, &PROBES));
+    }
+
+    #[test]
+    fn given_unsound_rewrite_when_simplified_then_equivalence_check_rejects_it() {
+        let program = program_with(vec![
+            Instruction::new(0, 0, Mode::External, Op::Add, 1.),
+            Instruction::with_constant(0, Op::Mult, 3.),
+        ]);
+        let drop_last: Rewrite = |program| {
+            let mut shorter = program.clone();
+            shorter.instructions.pop();
+            shorter
+        };
+
+        assert!(!program.behaves_like(&drop_last(&program), &PROBES));
+        assert_eq!(
+            program.simplify_with(&PROBES, &[drop_last]).instructions,
+            program.instructions
+        );
+    }
 }
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{adaptation::Variation, characteristics::Genotype};
+
+/// How an individual was bred. Kept by `Reset` and saved with the individual, so lineages can be
+/// rebuilt from saved populations.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Ids of the parents: two for crossover, one otherwise. Clones keep their parent's id.
+    pub parents: Vec<Uuid>,
+    pub operator: Variation,
+    /// Generation whose survivors bred the individual; it is first evaluated in the next one.
+    pub generation: usize,
+}
+
+impl Provenance {
+    pub fn new<G>(operator: Variation, parents: &[&G], generation: usize) -> Self
+    where
+        G: Genotype,
+    {
+        Provenance {
+            parents: parents.iter().filter_map(|parent| parent.id()).collect(),
+            operator,
+            generation,
+        }
+    }
+}
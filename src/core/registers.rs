@@ -1,18 +1,28 @@
 use core::slice::Iter;
-use std::{ops::Index, slice::SliceIndex};
+use std::{fmt, ops::Index, slice::SliceIndex, str::FromStr};
 
+use clap::ValueEnum;
 use itertools::Itertools;
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, Rng};
 use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::utils::random::generator;
 
-use super::engines::reset_engine::{Reset, ResetEngine};
+use super::{
+    engines::reset_engine::{Reset, ResetEngine},
+    environment::State,
+};
 
-fn deserialize_vec_with_null<'de, D>(deserializer: D) -> Result<Vec<f64>, D::Error>
+/// Reads a list of floats in which NaN and infinities were serialized as `null`.
+pub(crate) fn deserialize_vec_with_null<'de, D>(deserializer: D) -> Result<Vec<f64>, D::Error>
 where
     D: Deserializer<'de>,
 {
+    // Only JSON writes non-finite values as `null`.
+    if !deserializer.is_human_readable() {
+        return Vec::deserialize(deserializer);
+    }
+
     let vec_opt: Option<Vec<Option<f64>>> = Deserialize::deserialize(deserializer)?;
     Ok(vec_opt
         .unwrap_or_default()
@@ -21,11 +31,105 @@ where
         .collect())
 }
 
+/// Values the registers hold when a program starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterInit {
+    #[default]
+    Zeros,
+    Ones,
+    /// Calculation registers start with the inputs of the first state (cycling when there are
+    /// more registers than inputs); output registers start at zero.
+    CopyInputs,
+}
+
+/// Applied to a register after every instruction writing to it, keeping evolved programs from
+/// overflowing into non-finite values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterSaturation {
+    #[default]
+    None,
+    /// Clamps values into `[min, max]`; NaN becomes 0.
+    Clamp { min: f64, max: f64 },
+    /// Wraps values around into `[min, max)`; non-finite values become 0.
+    Wrap { min: f64, max: f64 },
+}
+
+impl RegisterSaturation {
+    pub fn apply(&self, value: f64) -> f64 {
+        match *self {
+            RegisterSaturation::None => value,
+            RegisterSaturation::Clamp { .. } if value.is_nan() => 0.,
+            RegisterSaturation::Clamp { min, max } => value.clamp(min, max),
+            RegisterSaturation::Wrap { .. } if !value.is_finite() => 0.,
+            RegisterSaturation::Wrap { min, max } if value < min || value >= max => {
+                min + (value - min).rem_euclid(max - min)
+            }
+            RegisterSaturation::Wrap { .. } => value,
+        }
+    }
+}
+
+impl FromStr for RegisterSaturation {
+    type Err = String;
+
+    /// Parses `none`, `clamp:<min>:<max>` or `wrap:<min>:<max>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "none" {
+            return Ok(RegisterSaturation::None);
+        }
+
+        let bounds = |rest: &str| {
+            rest.split_once(':')
+                .and_then(|(min, max)| Some((min.parse::<f64>().ok()?, max.parse::<f64>().ok()?)))
+                .filter(|(min, max)| min < max)
+        };
+
+        match s.split_once(':') {
+            Some(("clamp", rest)) => {
+                bounds(rest).map(|(min, max)| RegisterSaturation::Clamp { min, max })
+            }
+            Some(("wrap", rest)) => {
+                bounds(rest).map(|(min, max)| RegisterSaturation::Wrap { min, max })
+            }
+            _ => None,
+        }
+        .ok_or_else(|| format!("Unknown register saturation `{}`", s))
+    }
+}
+
+impl fmt::Display for RegisterSaturation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterSaturation::None => write!(f, "none"),
+            RegisterSaturation::Clamp { min, max } => write!(f, "clamp:{}:{}", min, max),
+            RegisterSaturation::Wrap { min, max } => write!(f, "wrap:{}:{}", min, max),
+        }
+    }
+}
+
+/// Output registers come first, followed by the calculation registers.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Registers {
     #[serde(deserialize_with = "deserialize_vec_with_null")]
     data: Vec<f64>,
-    n_actions: usize,
+    #[serde(alias = "n_actions")]
+    n_outputs: usize,
+    #[serde(default)]
+    init: RegisterInit,
+    #[serde(default)]
+    n_inputs: usize,
+    #[serde(default)]
+    saturation: RegisterSaturation,
+    /// Values changed by `saturation` since the last `take_saturations`. Unlike the values
+    /// themselves, the count survives `Reset` so it can span every trial of an evaluation.
+    #[serde(skip)]
+    n_saturations: usize,
+    /// Set by `Reset` under `RegisterInit::CopyInputs` until the next run loads its inputs.
+    #[serde(skip)]
+    awaiting_inputs: bool,
 }
 
 pub enum ArgmaxResult {
@@ -49,9 +153,14 @@ impl ArgmaxResult {
     }
 
     pub fn any(&self) -> ActionRegister {
+        self.any_with(&mut generator())
+    }
+
+    /// Breaks ties using the given generator.
+    pub fn any_with<R: Rng + ?Sized>(&self, rng: &mut R) -> ActionRegister {
         match self {
             ArgmaxResult::MaxValues(indices) if indices.len() >= 1 => {
-                ActionRegister::Value(indices.choose(&mut generator()).copied().unwrap())
+                ActionRegister::Value(indices.choose(rng).copied().unwrap())
             }
             _ => ActionRegister::Overflow,
         }
@@ -60,28 +169,91 @@ impl ArgmaxResult {
 
 pub enum ArgmaxInput {
     All,
-    ActionRegisters,
+    OutputRegisters,
 }
 
 impl Reset<Registers> for ResetEngine {
     fn reset(item: &mut Registers) {
+        let initial_value = match item.init {
+            RegisterInit::Ones => 1.,
+            RegisterInit::Zeros | RegisterInit::CopyInputs => 0.,
+        };
+
         for value in item.data.as_mut_slice() {
-            *value = 0.
+            *value = initial_value
         }
+
+        item.awaiting_inputs = item.init == RegisterInit::CopyInputs;
     }
 }
 
 impl Registers {
-    pub fn new(n_actions: usize, n_working_registers: usize) -> Self {
-        let data = vec![0.; n_actions + n_working_registers];
+    pub fn new(n_outputs: usize, n_calculation_registers: usize) -> Self {
+        let data = vec![0.; n_outputs + n_calculation_registers];
+
+        Registers {
+            data,
+            n_outputs,
+            init: RegisterInit::Zeros,
+            n_inputs: 0,
+            saturation: RegisterSaturation::None,
+            n_saturations: 0,
+            awaiting_inputs: false,
+        }
+    }
+
+    pub fn with_saturation(mut self, saturation: RegisterSaturation) -> Self {
+        self.saturation = saturation;
+        self
+    }
+
+    /// Applies the saturation policy to the register at `index`.
+    pub fn saturate(&mut self, index: usize) {
+        let value = self.data[index];
+        let saturated = self.saturation.apply(value);
+
+        if saturated.to_bits() != value.to_bits() {
+            self.data[index] = saturated;
+            self.n_saturations += 1;
+        }
+    }
+
+    /// Returns and clears the number of saturated values.
+    pub fn take_saturations(&mut self) -> usize {
+        std::mem::take(&mut self.n_saturations)
+    }
+
+    /// Applies `init` from now on; `n_inputs` bounds the inputs copied by
+    /// `RegisterInit::CopyInputs`.
+    pub fn with_init(mut self, init: RegisterInit, n_inputs: usize) -> Self {
+        self.init = init;
+        self.n_inputs = n_inputs;
+        ResetEngine::reset(&mut self);
+        self
+    }
+
+    /// Copies the inputs into the calculation registers if they were reset under
+    /// `RegisterInit::CopyInputs` and no state has been loaded since.
+    pub fn load_inputs(&mut self, input: &impl State) {
+        if !self.awaiting_inputs {
+            return;
+        }
+
+        self.awaiting_inputs = false;
+
+        if self.n_inputs == 0 {
+            return;
+        }
 
-        Registers { data, n_actions }
+        for idx in self.n_outputs..self.data.len() {
+            self.data[idx] = input.get_value((idx - self.n_outputs) % self.n_inputs);
+        }
     }
 
     pub fn argmax(&self, range: ArgmaxInput) -> ArgmaxResult {
         let range_to_use = match range {
             ArgmaxInput::All => 0..(self.data.len()),
-            ArgmaxInput::ActionRegisters => 0..(self.n_actions),
+            ArgmaxInput::OutputRegisters => 0..(self.n_outputs),
         };
 
         let sliced_data = &self.data[range_to_use];
@@ -106,9 +278,16 @@ impl Registers {
         ArgmaxResult::MaxValues(max_indices)
     }
 
+    pub fn n_outputs(&self) -> usize {
+        self.n_outputs
+    }
+
+    pub fn saturation(&self) -> RegisterSaturation {
+        self.saturation
+    }
+
     pub fn len(&self) -> usize {
-        let Registers { data, .. } = self;
-        data.len()
+        self.data.len()
     }
 
     pub fn update(&mut self, index: usize, value: f64) {
@@ -139,7 +318,143 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::core::registers::Registers;
+    use crate::core::{
+        engines::reset_engine::{Reset, ResetEngine},
+        environment::State,
+        registers::{ArgmaxInput, ArgmaxResult, RegisterInit, RegisterSaturation, Registers},
+    };
+
+    struct Row(Vec<f64>);
+
+    impl State for Row {
+        fn get_value(&self, at_idx: usize) -> f64 {
+            self.0[at_idx]
+        }
+
+        fn execute_action(&mut self, _action: usize) -> f64 {
+            0.
+        }
+
+        fn get(&mut self) -> Option<&mut Self> {
+            Some(self)
+        }
+    }
+
+    fn argmax(registers: &Registers, range: ArgmaxInput) -> Vec<usize> {
+        match registers.argmax(range) {
+            ArgmaxResult::MaxValues(indices) => indices,
+            ArgmaxResult::Overflow => vec![],
+        }
+    }
+
+    #[test]
+    fn given_each_init_policy_when_reset_then_layout_matches() {
+        let row = Row(vec![7., 8.]);
+
+        let mut zeros = Registers::new(2, 3).with_init(RegisterInit::Zeros, 2);
+        zeros.load_inputs(&row);
+        assert_eq!(zeros[..], [0.; 5]);
+
+        let mut ones = Registers::new(1, 2).with_init(RegisterInit::Ones, 2);
+        ones.load_inputs(&row);
+        assert_eq!(ones[..], [1.; 3]);
+
+        // Outputs stay at zero; calculation registers cycle through the inputs.
+        let mut copied = Registers::new(2, 3).with_init(RegisterInit::CopyInputs, 2);
+        copied.load_inputs(&row);
+        assert_eq!(copied[..], [0., 0., 7., 8., 7.]);
+
+        // Only the first state after a reset is copied in.
+        copied.update(2, 1.);
+        copied.load_inputs(&Row(vec![9., 9.]));
+        assert_eq!(copied[..], [0., 0., 1., 8., 7.]);
+
+        ResetEngine::reset(&mut copied);
+        copied.load_inputs(&Row(vec![9., 9.]));
+        assert_eq!(copied[..], [0., 0., 9., 9., 9.]);
+    }
+
+    #[test]
+    fn given_output_count_when_argmax_then_only_outputs_are_considered() {
+        for (n_outputs, n_calculation_registers) in [(1, 3), (2, 2), (3, 0)] {
+            let mut registers = Registers::new(n_outputs, n_calculation_registers);
+            registers.update(registers.len() - 1, 10.);
+            registers.update(0, 5.);
+
+            assert_eq!(registers.len(), n_outputs + n_calculation_registers);
+            assert_eq!(registers.n_outputs(), n_outputs);
+            assert_eq!(
+                argmax(&registers, ArgmaxInput::All),
+                vec![registers.len() - 1]
+            );
+
+            let expected = if n_calculation_registers == 0 {
+                vec![n_outputs - 1]
+            } else {
+                vec![0]
+            };
+            assert_eq!(argmax(&registers, ArgmaxInput::OutputRegisters), expected);
+        }
+    }
+
+    #[test]
+    fn given_legacy_registers_when_deserialized_then_action_count_is_the_output_count() {
+        let registers: Registers =
+            serde_json::from_str(r#"{"data":[0.0,0.0,0.0],"n_actions":2}"#).unwrap();
+
+        assert_eq!(registers.n_outputs(), 2);
+        assert_eq!(registers.len(), 3);
+    }
+
+    #[test]
+    fn given_overflowing_sequence_when_saturated_then_values_stay_in_range() {
+        let clamp = RegisterSaturation::Clamp {
+            min: -10.,
+            max: 10.,
+        };
+        let wrap = RegisterSaturation::Wrap {
+            min: -10.,
+            max: 10.,
+        };
+
+        let mut registers = Registers::new(1, 0).with_saturation(clamp);
+        let mut clamped = vec![];
+        for _ in 0..4 {
+            // r0 = (r0 + 3) * 2, overflowing on the second step.
+            registers.update(0, (registers[0] + 3.) * 2.);
+            registers.saturate(0);
+            clamped.push(registers[0]);
+        }
+        assert_eq!(clamped, vec![6., 10., 10., 10.]);
+        assert_eq!(registers.take_saturations(), 3);
+        assert_eq!(registers.take_saturations(), 0);
+
+        assert_eq!(wrap.apply(18.), -2.);
+        assert_eq!(wrap.apply(-12.), 8.);
+        assert_eq!(wrap.apply(10.), -10.);
+        assert_eq!(wrap.apply(4.), 4.);
+
+        for policy in [clamp, wrap] {
+            assert_eq!(policy.apply(f64::NAN), 0.);
+        }
+        assert_eq!(clamp.apply(f64::INFINITY), 10.);
+        assert_eq!(wrap.apply(f64::INFINITY), 0.);
+        assert!(RegisterSaturation::None.apply(f64::NAN).is_nan());
+    }
+
+    #[test]
+    fn given_saturation_names_when_parsed_then_round_trip_through_display() {
+        for saturation in [
+            RegisterSaturation::None,
+            RegisterSaturation::Clamp { min: -1., max: 1. },
+            RegisterSaturation::Wrap { min: 0., max: 6.5 },
+        ] {
+            assert_eq!(saturation.to_string().parse(), Ok(saturation));
+        }
+
+        assert!("clamp:1:-1".parse::<RegisterSaturation>().is_err());
+        assert!("wrap:0".parse::<RegisterSaturation>().is_err());
+    }
 
     #[test]
     fn given_registers_when_indexed_with_range_then_slice_is_returned() {
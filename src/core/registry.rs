@@ -0,0 +1,758 @@
+//! Problems the command line can run, looked up by name instead of matched by hand.
+//!
+//! A problem is registered by adding a `Registered` static below and listing it in `PROBLEMS`.
+
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use gym_rs::{
+    core::Env,
+    envs::classical_control::{cartpole::CartPoleEnv, mountain_car::MountainCarEnv},
+    utils::renderer::RenderMode,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "serve")]
+use crate::{core::config::ServeEvalArgs, utils::remote::EvalServer};
+use crate::{
+    core::{
+        characteristics::Load,
+        config::{
+            load_hyper_parameters, output_root, AnimateArgs, LandscapeArgs, Problem, RefineArgs,
+            SweepArgs, TuneArgs,
+        },
+        config_override::apply_overrides,
+        engines::{
+            core_engine::{Core, HyperParameters},
+            fitness_engine::Fitness,
+            reset_engine::{Reset, ResetEngine},
+            status_engine::Status,
+        },
+        environment::RlState,
+        program::ProgramShape,
+    },
+    problems::{
+        gym::{evaluate_with_render, GymRsEngine, GymRsInput, GymRsParameters, GymRsQEngine},
+        iris::IrisEngine,
+        reward_shaping::RewardShaper,
+    },
+    utils::{
+        benchmark_tools::{cross_validate, run_repeats},
+        error::{LgpError, LgpResult},
+        landscape::{explore_landscape, save_landscape, Neighbourhood},
+        paired::{run_paired, PairedReport},
+        random::{generator, reseed},
+        refine::{refine, save_refinement, Constants, RefineParameters},
+        sweep::{run_sweep, SweepConfig},
+        trajectory::{animate, save_trajectory, Recorder},
+        tune::{evolution_score, save_tuning_results, tune, SearchSpace, TuneParameters},
+    },
+};
+
+/// Replays the saved champions of a run; see `animate`.
+pub type Animate = fn(&Path, usize, usize, &Path, usize) -> Result<Vec<usize>, Box<dyn Error>>;
+
+/// Hyperparameters of a run once its config, overrides, seed and program shape are resolved,
+/// with the directory it saves under.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolvedRun {
+    pub output_dir: PathBuf,
+    pub hyperparameters: serde_json::Value,
+}
+
+/// Name, program shape and entry point of a runnable problem.
+pub trait ProblemDescriptor: Sync {
+    /// Command line name, e.g. `cart-pole-lgp`.
+    fn name(&self) -> &'static str;
+    /// Other names the problem is found by, e.g. the `cart_pole_lgp` of its output directory.
+    fn aliases(&self) -> &'static [&'static str];
+    fn problem(&self) -> Problem;
+    fn n_inputs(&self) -> usize;
+    fn n_actions(&self) -> usize;
+    /// Overrides the configured `default_fitness` when set.
+    fn default_fitness(&self) -> Option<f64>;
+    /// Runs with the hyperparameters in `config`, or the command line defaults, saving under
+    /// `output_root/output` (the problem's name in snake case by default).
+    fn run(
+        &self,
+        config: Option<&str>,
+        overrides: &[String],
+        seed: Option<u64>,
+        output_root: &Path,
+        output: Option<&str>,
+    ) -> LgpResult<()>;
+    /// Resolves what `run` would evolve with the same arguments, without running it or touching
+    /// the filesystem.
+    fn resolve(
+        &self,
+        config: Option<&str>,
+        overrides: &[String],
+        seed: Option<u64>,
+        output_root: &Path,
+        output: Option<&str>,
+    ) -> LgpResult<ResolvedRun>;
+    /// Loads and validates the hyperparameters in `config`.
+    fn load_config(&self, config: &str) -> LgpResult<serde_json::Value>;
+    /// Evaluates and saves the fitness landscape around a saved champion.
+    fn landscape(&self, args: &LandscapeArgs) -> LgpResult<()>;
+    /// Polishes the constants of a saved champion, saving the result next to the original.
+    fn refine(&self, args: &RefineArgs) -> LgpResult<()>;
+    /// Replays the per-generation champions of a run and writes their trajectories.
+    fn animate(&self, args: &AnimateArgs) -> LgpResult<()>;
+    /// Runs every configuration of a parameter grid.
+    fn sweep(&self, args: &SweepArgs) -> LgpResult<()>;
+    /// Samples configurations around base hyperparameters, scoring each with a shortened
+    /// evolution.
+    fn tune(&self, args: &TuneArgs) -> LgpResult<()>;
+    /// Serves fitness evaluations on the trials regenerated from request seeds until killed.
+    #[cfg(feature = "serve")]
+    fn serve_eval(&self, args: &ServeEvalArgs) -> LgpResult<()>;
+}
+
+pub struct Registered<C>
+where
+    C: Core,
+{
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub problem: Problem,
+    pub n_inputs: usize,
+    pub n_actions: usize,
+    pub default_fitness: Option<f64>,
+    /// Applied after the overrides, before the shape is fixed.
+    pub prepare: fn(&mut HyperParameters<C>),
+    /// Evolves validated hyperparameters, saving under the given output root and name.
+    pub evolve: fn(&HyperParameters<C>, &Path, &str) -> LgpResult<()>,
+    /// Replays saved champions; unset for problems without an environment to replay them in.
+    pub animate: Option<Animate>,
+}
+
+impl<C> Registered<C>
+where
+    C: Core,
+{
+    /// Applies the overrides, fixes the program shape, draws a seed when none is set and
+    /// validates the result.
+    pub fn resolve(&self, hyperparameters: &mut HyperParameters<C>) -> LgpResult<()> {
+        hyperparameters.apply_overrides()?;
+        (self.prepare)(hyperparameters);
+
+        let instruction_parameters = hyperparameters.program_parameters.instruction_parameters();
+        instruction_parameters.n_inputs = self.n_inputs;
+        instruction_parameters.n_actions = self.n_actions;
+
+        if let Some(default_fitness) = self.default_fitness {
+            hyperparameters.default_fitness = default_fitness;
+        }
+        // Drawn seeds fit in an `i64`, which `config` reads integers as, so that printed configs
+        // load back unchanged.
+        hyperparameters
+            .seed
+            .get_or_insert_with(|| generator().next_u64() >> 1);
+
+        hyperparameters.validate().map_err(LgpError::from)
+    }
+
+    /// Resolves the hyperparameters and evolves.
+    pub fn execute(
+        &self,
+        hyperparameters: &mut HyperParameters<C>,
+        output_root: &Path,
+        output: Option<&str>,
+    ) -> LgpResult<()> {
+        self.resolve(hyperparameters)?;
+        (self.evolve)(hyperparameters, output_root, &self.output(output))
+    }
+
+    /// Directory name the outputs are saved under; the problem's name in snake case by default.
+    pub fn output(&self, output: Option<&str>) -> String {
+        output.map_or_else(|| self.name.replace('-', "_"), str::to_string)
+    }
+
+    /// The hyperparameters in `config`, or the command line defaults, with the overrides and
+    /// seed of the command line attached.
+    fn load(
+        &self,
+        config: Option<&str>,
+        overrides: &[String],
+        seed: Option<u64>,
+    ) -> LgpResult<HyperParameters<C>> {
+        let mut hyperparameters = match config {
+            Some(config) => load_hyper_parameters::<C>(config)?,
+            None => HyperParameters::<C>::try_parse_from(["lgp"])
+                .map_err(|error| LgpError::Config(error.to_string()))?,
+        };
+        hyperparameters.overrides.extend(overrides.iter().cloned());
+        if seed.is_some() {
+            hyperparameters.seed = seed;
+        }
+
+        Ok(hyperparameters)
+    }
+
+    /// The hyperparameters in `config` with `overrides` applied.
+    fn load_overridden(&self, config: &str, overrides: &[String]) -> LgpResult<HyperParameters<C>> {
+        apply_overrides(&load_hyper_parameters::<C>(config)?, overrides)
+    }
+}
+
+impl<C> ProblemDescriptor for Registered<C>
+where
+    C: Core,
+    C::Individual: Neighbourhood<C::ProgramParameters> + Constants,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        self.aliases
+    }
+
+    fn problem(&self) -> Problem {
+        self.problem
+    }
+
+    fn n_inputs(&self) -> usize {
+        self.n_inputs
+    }
+
+    fn n_actions(&self) -> usize {
+        self.n_actions
+    }
+
+    fn default_fitness(&self) -> Option<f64> {
+        self.default_fitness
+    }
+
+    fn run(
+        &self,
+        config: Option<&str>,
+        overrides: &[String],
+        seed: Option<u64>,
+        output_root: &Path,
+        output: Option<&str>,
+    ) -> LgpResult<()> {
+        let mut hyperparameters = self.load(config, overrides, seed)?;
+
+        self.execute(&mut hyperparameters, output_root, output)
+    }
+
+    fn resolve(
+        &self,
+        config: Option<&str>,
+        overrides: &[String],
+        seed: Option<u64>,
+        output_root: &Path,
+        output: Option<&str>,
+    ) -> LgpResult<ResolvedRun> {
+        let mut hyperparameters = self.load(config, overrides, seed)?;
+        Registered::resolve(self, &mut hyperparameters)?;
+
+        Ok(ResolvedRun {
+            output_dir: output_root.join(self.output(output)),
+            hyperparameters: serde_json::to_value(&hyperparameters)?,
+        })
+    }
+
+    fn load_config(&self, config: &str) -> LgpResult<serde_json::Value> {
+        Ok(serde_json::to_value(load_hyper_parameters::<C>(config)?)?)
+    }
+
+    fn landscape(&self, args: &LandscapeArgs) -> LgpResult<()> {
+        let parameters = self.load_overridden(&args.config, &args.overrides)?;
+        parameters.validate()?;
+        reseed(parameters.master_seed());
+
+        let champion = C::Individual::load(&args.model)?;
+        let landscape = explore_landscape(&champion, &parameters, args.max_neighbours)?;
+
+        save_landscape(&landscape, &args.output)?;
+        println!("{}", serde_json::to_string(&landscape.summary).unwrap());
+
+        Ok(())
+    }
+
+    fn refine(&self, args: &RefineArgs) -> LgpResult<()> {
+        let parameters = self.load_overridden(&args.config, &args.overrides)?;
+        parameters.validate()?;
+        reseed(parameters.master_seed());
+
+        let champion = C::Individual::load(&args.model)?;
+        let refine_parameters = RefineParameters {
+            budget: args.budget,
+            initial_step: args.initial_step,
+            ..Default::default()
+        };
+        let refinement = refine(&champion, &parameters, refine_parameters)?;
+
+        save_refinement::<C>(&refinement, &args.model)?;
+        println!("{}", serde_json::to_string(&refinement.report).unwrap());
+
+        Ok(())
+    }
+
+    fn animate(&self, args: &AnimateArgs) -> LgpResult<()> {
+        let animate = self.animate.ok_or_else(|| {
+            LgpError::Config("Animation is only supported for RL environments.".to_string())
+        })?;
+
+        let generations = animate(
+            &args.run_dir,
+            args.every,
+            args.episodes,
+            &args.out,
+            self.n_inputs,
+        )?;
+        println!("{}", serde_json::to_string(&generations).unwrap());
+
+        Ok(())
+    }
+
+    fn sweep(&self, args: &SweepArgs) -> LgpResult<()> {
+        let parameters = self.load_overridden(&args.config, &args.overrides)?;
+        let sweep = SweepConfig::load(args.sweep.as_deref().unwrap_or(&args.config))?;
+
+        let results = run_sweep(
+            &parameters,
+            parameters.state_parameters()?,
+            &sweep,
+            args.jobs,
+            &output_root(&args.output_root),
+            &args.name,
+        )?;
+        println!("{}", serde_json::to_string(&results).unwrap());
+
+        Ok(())
+    }
+
+    fn tune(&self, args: &TuneArgs) -> LgpResult<()> {
+        let parameters = self.load_overridden(&args.config, &args.overrides)?;
+        let space = SearchSpace::load(&args.space)?;
+        let state_parameters = parameters.state_parameters()?;
+
+        let tune_parameters = TuneParameters {
+            budget: args.trials,
+            max_resource: args.generations.unwrap_or(parameters.n_generations),
+            eta: args.eta,
+            seed: parameters.seed.unwrap_or(0),
+        };
+        let results = tune(&space, tune_parameters, |configuration, n_generations| {
+            evolution_score(&parameters, &state_parameters, configuration, n_generations)
+        });
+
+        save_tuning_results(&results, &output_root(&args.output_root), &args.name)?;
+        println!("{}", serde_json::to_string(&results.best).unwrap());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serve")]
+    fn serve_eval(&self, args: &ServeEvalArgs) -> LgpResult<()> {
+        let state_parameters = match &args.config {
+            Some(config) => self
+                .load_overridden(config, &args.overrides)?
+                .state_parameters()?,
+            None => C::default_state_parameters()?,
+        };
+
+        let server = EvalServer::<C>::bind(("0.0.0.0", args.port), state_parameters)?;
+        println!("{}", server.local_addr());
+        server.run();
+
+        Ok(())
+    }
+}
+
+pub static MOUNTAIN_CAR_Q: Registered<GymRsQEngine<MountainCarEnv>> = Registered {
+    name: "mountain-car-q",
+    aliases: &["mountain_car_q"],
+    problem: Problem::MountainCarQ,
+    n_inputs: 2,
+    n_actions: 3,
+    default_fitness: Some(-200.),
+    prepare: reset_q_consts,
+    evolve: evolve_rl::<GymRsQEngine<MountainCarEnv>, MountainCarEnv>,
+    animate: Some(animate::<GymRsQEngine<MountainCarEnv>>),
+};
+
+pub static MOUNTAIN_CAR_LGP: Registered<GymRsEngine<MountainCarEnv>> = Registered {
+    name: "mountain-car-lgp",
+    aliases: &["mountain_car_lgp"],
+    problem: Problem::MountainCarLgp,
+    n_inputs: 2,
+    n_actions: 3,
+    default_fitness: Some(-200.),
+    prepare: |_| {},
+    evolve: evolve_rl::<GymRsEngine<MountainCarEnv>, MountainCarEnv>,
+    animate: Some(animate::<GymRsEngine<MountainCarEnv>>),
+};
+
+pub static CART_POLE_Q: Registered<GymRsQEngine<CartPoleEnv>> = Registered {
+    name: "cart-pole-q",
+    aliases: &["cart_pole_q"],
+    problem: Problem::CartPoleQ,
+    n_inputs: 4,
+    n_actions: 2,
+    default_fitness: Some(500.),
+    prepare: reset_q_consts,
+    evolve: evolve_rl::<GymRsQEngine<CartPoleEnv>, CartPoleEnv>,
+    animate: Some(animate::<GymRsQEngine<CartPoleEnv>>),
+};
+
+pub static CART_POLE_LGP: Registered<GymRsEngine<CartPoleEnv>> = Registered {
+    name: "cart-pole-lgp",
+    aliases: &["cart_pole_lgp"],
+    problem: Problem::CartPoleLgp,
+    n_inputs: 4,
+    n_actions: 2,
+    default_fitness: Some(500.),
+    prepare: |_| {},
+    evolve: evolve_rl::<GymRsEngine<CartPoleEnv>, CartPoleEnv>,
+    animate: Some(animate::<GymRsEngine<CartPoleEnv>>),
+};
+
+pub static IRIS_LGP: Registered<IrisEngine> = Registered {
+    name: "iris-lgp",
+    aliases: &["iris_lgp", "iris"],
+    problem: Problem::IrisLgp,
+    n_inputs: 4,
+    n_actions: 3,
+    default_fitness: None,
+    prepare: |_| {},
+    evolve: evolve_iris,
+    animate: None,
+};
+
+/// Every registered problem, in the order they are listed.
+pub static PROBLEMS: &[&dyn ProblemDescriptor] = &[
+    &MOUNTAIN_CAR_Q,
+    &MOUNTAIN_CAR_LGP,
+    &CART_POLE_Q,
+    &CART_POLE_LGP,
+    &IRIS_LGP,
+];
+
+/// Looks a problem up by its name or an alias, ignoring case.
+pub fn find_problem(name: &str) -> LgpResult<&'static dyn ProblemDescriptor> {
+    PROBLEMS
+        .iter()
+        .copied()
+        .find(|descriptor| {
+            std::iter::once(descriptor.name())
+                .chain(descriptor.aliases().iter().copied())
+                .any(|candidate| candidate.eq_ignore_ascii_case(name))
+        })
+        .ok_or_else(|| LgpError::UnknownEnvironment {
+            name: name.to_string(),
+            available: PROBLEMS
+                .iter()
+                .map(|descriptor| descriptor.name().to_string())
+                .collect(),
+        })
+}
+
+/// Command line defaults of `problem` with `overrides` applied, resolved as `lgp run` would.
+fn default_parameters<C>(
+    problem: &Registered<C>,
+    overrides: &[String],
+) -> LgpResult<HyperParameters<C>>
+where
+    C: Core,
+{
+    let mut hyperparameters = problem.load(None, overrides, None)?;
+    problem.resolve(&mut hyperparameters)?;
+
+    Ok(hyperparameters)
+}
+
+/// Runs the LGP and Q-learning variants of a problem for `n_generations` on the same seeds, and
+/// so the same trials, comparing them repeat by repeat.
+pub fn run_paired_problems<A, B>(
+    lgp: &Registered<A>,
+    q: &Registered<B>,
+    n_generations: usize,
+    n_repeats: usize,
+    seed: u64,
+    overrides: &[String],
+) -> LgpResult<PairedReport>
+where
+    A: Core,
+    B: Core,
+{
+    let mut overrides = overrides.to_vec();
+    overrides.push(format!("n_generations={}", n_generations));
+
+    run_paired(
+        (lgp.name, &default_parameters(lgp, &overrides)?),
+        (q.name, &default_parameters(q, &overrides)?),
+        n_repeats,
+        seed,
+    )
+}
+
+pub fn run_cart_pole_paired(n_generations: usize, n_repeats: usize) -> LgpResult<PairedReport> {
+    run_paired_problems(
+        &CART_POLE_LGP,
+        &CART_POLE_Q,
+        n_generations,
+        n_repeats,
+        0,
+        &[],
+    )
+}
+
+fn reset_q_consts<T>(hyperparameters: &mut HyperParameters<GymRsQEngine<T>>)
+where
+    T: Env + 'static,
+{
+    ResetEngine::reset(&mut hyperparameters.program_parameters.consts);
+}
+
+/// Prints the best fitness of every generation, or the aggregate of repeated runs, then the
+/// hyperparameters. Returns the final champion of a single run with the state parameters it was
+/// evolved on.
+fn evolve<C>(
+    hyperparameters: &HyperParameters<C>,
+    output_root: &Path,
+    output: &str,
+) -> LgpResult<Option<(C::Individual, C::StateParameters)>>
+where
+    C: Core,
+{
+    let mut champion = None;
+
+    if hyperparameters.repeats > 1 {
+        let aggregate = run_repeats(
+            hyperparameters,
+            hyperparameters.state_parameters()?,
+            hyperparameters.repeats,
+            output_root,
+            output,
+        )?;
+        println!("{}", serde_json::to_string(&aggregate).unwrap());
+    } else {
+        let mut engine = hyperparameters.try_build_engine()?;
+        let mut best = None;
+
+        for (generation, population) in engine
+            .by_ref()
+            .take(hyperparameters.population_size)
+            .enumerate()
+        {
+            let first = population.first().ok_or_else(|| {
+                LgpError::Other(format!(
+                    "Generation {} has an empty population.",
+                    generation
+                ))
+            })?;
+            println!("{}", C::Status::get_fitness(first));
+            best = Some(first.clone());
+        }
+
+        champion = best.map(|best| (best, engine.state_parameters().clone()));
+    }
+    println!("{}", serde_json::to_string(&hyperparameters).unwrap());
+
+    Ok(champion)
+}
+
+/// Evolves an RL problem, replaying its champion when a trajectory or rendering is requested.
+fn evolve_rl<C, T>(
+    hyperparameters: &HyperParameters<C>,
+    output_root: &Path,
+    output: &str,
+) -> LgpResult<()>
+where
+    C: Core<State = GymRsInput<T>, StateParameters = GymRsParameters>,
+    C::Fitness: Fitness<C::Individual, Recorder<C::State>, C::FitnessMarker>,
+    GymRsInput<T>: RlState,
+    T: Env + 'static,
+{
+    let Some((champion, state_parameters)) = evolve(hyperparameters, output_root, output)? else {
+        return Ok(());
+    };
+
+    if hyperparameters.record_trajectory {
+        save_trajectory(&champion, hyperparameters, output_root, output)?;
+    }
+
+    if hyperparameters.render {
+        // Keeps the observation statistics learned during the run; rewards are shown unshaped.
+        let parameters = GymRsParameters {
+            reward_shaping: RewardShaper::None,
+            ..state_parameters
+        };
+
+        if let Some(rewards) = evaluate_with_render::<C, T>(
+            &champion,
+            parameters,
+            hyperparameters.render_episodes,
+            RenderMode::Human,
+        ) {
+            println!("{}", serde_json::to_string(&rewards).unwrap());
+        }
+    }
+
+    Ok(())
+}
+
+/// Evolves on iris, or cross-validates when configured to.
+fn evolve_iris(
+    hyperparameters: &HyperParameters<IrisEngine>,
+    output_root: &Path,
+    output: &str,
+) -> LgpResult<()> {
+    match hyperparameters.cross_validation {
+        Some(cross_validation) => {
+            let state_parameters = hyperparameters.state_parameters()?;
+            let results = cross_validate(hyperparameters, &state_parameters, cross_validation);
+            println!("{}", serde_json::to_string(&results).unwrap());
+        }
+        None => {
+            evolve(hyperparameters, output_root, output)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use clap::ValueEnum;
+
+    use crate::utils::paired::PairedOutcome;
+
+    use super::*;
+
+    #[test]
+    fn given_every_name_and_alias_when_looked_up_then_its_problem_is_found() {
+        let cases = [
+            ("mountain-car-q", Problem::MountainCarQ),
+            ("mountain_car_q", Problem::MountainCarQ),
+            ("mountain-car-lgp", Problem::MountainCarLgp),
+            ("mountain_car_lgp", Problem::MountainCarLgp),
+            ("cart-pole-q", Problem::CartPoleQ),
+            ("cart_pole_q", Problem::CartPoleQ),
+            ("Cart-Pole-LGP", Problem::CartPoleLgp),
+            ("cart_pole_lgp", Problem::CartPoleLgp),
+            ("iris-lgp", Problem::IrisLgp),
+            ("iris_lgp", Problem::IrisLgp),
+            ("iris", Problem::IrisLgp),
+        ];
+
+        for (name, problem) in cases {
+            let descriptor = find_problem(name).unwrap();
+
+            assert_eq!(descriptor.problem(), problem, "{}", name);
+            assert!(matches!(Problem::from_name(name), Ok(found) if found == problem));
+        }
+    }
+
+    #[test]
+    fn given_every_problem_when_registered_then_it_matches_its_cli_name() {
+        for descriptor in PROBLEMS {
+            let name = descriptor
+                .problem()
+                .to_possible_value()
+                .unwrap()
+                .get_name()
+                .to_string();
+
+            assert_eq!(descriptor.name(), name);
+            assert!(descriptor.n_inputs() > 0 && descriptor.n_actions() > 0);
+        }
+    }
+
+    #[test]
+    fn given_unknown_name_when_looked_up_then_error_lists_available_problems() {
+        let error = find_problem("pong").err().unwrap();
+        let message = error.to_string();
+
+        assert!(message.contains("`pong`"));
+        for descriptor in PROBLEMS {
+            assert!(message.contains(descriptor.name()), "{}", message);
+        }
+        assert_eq!(error.exit_code(), 4);
+    }
+
+    #[test]
+    fn given_paired_cart_pole_runs_then_both_arms_share_trials_and_are_compared() -> LgpResult<()> {
+        let overrides = ["population_size=6", "n_trials=2"].map(String::from);
+        let report = run_paired_problems(&CART_POLE_LGP, &CART_POLE_Q, 3, 2, 11, &overrides)?;
+
+        assert_eq!(
+            (report.arm_a.as_str(), report.arm_b.as_str()),
+            ("cart-pole-lgp", "cart-pole-q")
+        );
+        assert_eq!(report.repeats.len(), 2);
+        assert_eq!(report.wins + report.losses + report.ties, 2);
+        assert_eq!(report.generations.len(), 3);
+        assert!(report.p_value > 0. && report.p_value <= 1.);
+        assert_eq!(
+            report
+                .repeats
+                .iter()
+                .map(|repeat| repeat.seed)
+                .collect::<Vec<_>>(),
+            vec![11, 12]
+        );
+        assert!(report.repeats.iter().all(|repeat| repeat.identical_trials));
+
+        let differences = report
+            .repeats
+            .iter()
+            .map(|repeat| repeat.final_best_a - repeat.final_best_b)
+            .collect::<Vec<_>>();
+        assert!(
+            (report.generations[2].mean_difference - (differences[0] + differences[1]) / 2.).abs()
+                < 1e-9
+        );
+
+        for (repeat, difference) in report.repeats.iter().zip(differences) {
+            assert_eq!(
+                repeat.outcome,
+                match difference {
+                    difference if difference > 0. => PairedOutcome::Win,
+                    difference if difference < 0. => PairedOutcome::Loss,
+                    _ => PairedOutcome::Tie,
+                }
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_overrides_when_resolved_then_nothing_is_written_and_the_config_round_trips(
+    ) -> LgpResult<()> {
+        let root = env::temp_dir().join("lgp_dry_run");
+        let _ = fs::remove_dir_all(&root);
+
+        let overrides = ["population_size=30", "n_generations=7"].map(String::from);
+        let resolved = find_problem("iris")?.resolve(None, &overrides, None, &root, None)?;
+
+        assert!(!root.exists());
+        assert_eq!(resolved.output_dir, root.join("iris_lgp"));
+        assert_eq!(resolved.hyperparameters["population_size"], 30);
+        assert_eq!(resolved.hyperparameters["n_generations"], 7);
+        assert!(resolved.hyperparameters["seed"].is_u64());
+
+        let config = env::temp_dir().join("lgp_dry_run_config.json");
+        fs::write(
+            &config,
+            serde_json::to_string_pretty(&resolved.hyperparameters)?,
+        )?;
+        let loaded = load_hyper_parameters::<IrisEngine>(config.to_str().unwrap())?;
+
+        assert_eq!(serde_json::to_value(&loaded)?, resolved.hyperparameters);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+use std::{fmt, str::FromStr};
+
+use itertools::Itertools;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::utils::error::{require, ConfigViolation};
+
+/// A value that changes across the generations of a run, e.g. a mutation percentage that is
+/// high early and low late. Configured as a bare float for a constant, or as a table tagged by
+/// `type`:
+///
+/// ```toml
+/// gap = 0.5
+/// mutation_percent = { type = "linear", from = 0.8, to = 0.2 }
+/// crossover_percent = { type = "exponential", from = 0.1, to = 0.6, rate = 0.05 }
+/// # or { type = "steps", steps = [[0, 0.5], [50, 0.3]] }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schedule {
+    Constant(f64),
+    /// Moves from `from` at the first generation to `to` at the last one in equal steps.
+    Linear {
+        from: f64,
+        to: f64,
+    },
+    /// Decays from `from` towards `to`, as `to + (from - to) * exp(-rate * generation)`.
+    Exponential {
+        from: f64,
+        to: f64,
+        rate: f64,
+    },
+    /// `(generation, value)` pairs in increasing generation order; each value holds from its
+    /// generation until the next step, and the first one before it.
+    Steps(Vec<(usize, f64)>),
+}
+
+impl Schedule {
+    /// Value at `generation` of a run of `n_generations`, counted like `n_generations` itself.
+    pub fn value(&self, generation: usize, n_generations: usize) -> f64 {
+        match self {
+            Schedule::Constant(value) => *value,
+            Schedule::Linear { from, to } => {
+                let progress = match n_generations {
+                    0 => 0.,
+                    n_generations => generation.min(n_generations) as f64 / n_generations as f64,
+                };
+
+                // Weighted so that both ends are hit exactly.
+                from * (1. - progress) + to * progress
+            }
+            Schedule::Exponential { from, to, rate } => {
+                let weight = (-rate * generation as f64).exp();
+                from * weight + to * (1. - weight)
+            }
+            Schedule::Steps(steps) => steps
+                .iter()
+                .take_while(|(start, _)| *start <= generation)
+                .last()
+                .or_else(|| steps.first())
+                .map_or(f64::NAN, |(_, value)| *value),
+        }
+    }
+
+    /// Values bounding every value the schedule takes.
+    fn extremes(&self) -> Vec<f64> {
+        match self {
+            Schedule::Constant(value) => vec![*value],
+            Schedule::Linear { from, to } | Schedule::Exponential { from, to, .. } => {
+                vec![*from, *to]
+            }
+            Schedule::Steps(steps) => steps.iter().map(|(_, value)| *value).collect(),
+        }
+    }
+
+    /// Requires every value to lie in [0, 1], and the schedule itself to be well formed.
+    pub fn require_unit(&self, violations: &mut Vec<ConfigViolation>, field: &str) {
+        require(
+            violations,
+            self.extremes()
+                .into_iter()
+                .all(|value| (0. ..=1.).contains(&value)),
+            field,
+            self,
+            "in [0, 1]",
+        );
+
+        match self {
+            Schedule::Exponential { rate, .. } => require(
+                violations,
+                rate.is_finite() && *rate >= 0.,
+                &format!("{}.rate", field),
+                rate,
+                "finite and at least 0",
+            ),
+            Schedule::Steps(steps) => require(
+                violations,
+                !steps.is_empty()
+                    && steps
+                        .iter()
+                        .tuple_windows()
+                        .all(|((previous, _), (next, _))| previous < next),
+                &format!("{}.steps", field),
+                self,
+                "at least one step, in increasing generation order",
+            ),
+            _ => {}
+        }
+    }
+}
+
+impl From<f64> for Schedule {
+    fn from(value: f64) -> Self {
+        Schedule::Constant(value)
+    }
+}
+
+/// Layout of schedules in binary formats, which are not self-describing and so can hold
+/// neither bare floats nor tables tagged by `type`.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Schedule")]
+enum BinarySchedule {
+    Constant(f64),
+    Linear { from: f64, to: f64 },
+    Exponential { from: f64, to: f64, rate: f64 },
+    Steps(Vec<(usize, f64)>),
+}
+
+impl Serialize for Schedule {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            ScheduleRepr::from(self.clone()).serialize(serializer)
+        } else {
+            BinarySchedule::serialize(self, serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            ScheduleRepr::deserialize(deserializer).map(Schedule::from)
+        } else {
+            BinarySchedule::deserialize(deserializer)
+        }
+    }
+}
+
+/// A bare float, or a schedule table tagged by `type`.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ScheduleRepr {
+    Constant(f64),
+    Table(ScheduleTable),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ScheduleTable {
+    Constant { value: f64 },
+    Linear { from: f64, to: f64 },
+    Exponential { from: f64, to: f64, rate: f64 },
+    Steps { steps: Vec<(usize, f64)> },
+}
+
+impl From<ScheduleRepr> for Schedule {
+    fn from(repr: ScheduleRepr) -> Self {
+        match repr {
+            ScheduleRepr::Constant(value)
+            | ScheduleRepr::Table(ScheduleTable::Constant { value }) => Schedule::Constant(value),
+            ScheduleRepr::Table(ScheduleTable::Linear { from, to }) => {
+                Schedule::Linear { from, to }
+            }
+            ScheduleRepr::Table(ScheduleTable::Exponential { from, to, rate }) => {
+                Schedule::Exponential { from, to, rate }
+            }
+            ScheduleRepr::Table(ScheduleTable::Steps { steps }) => Schedule::Steps(steps),
+        }
+    }
+}
+
+/// Constants stay bare floats, so configs without schedules read as before.
+impl From<Schedule> for ScheduleRepr {
+    fn from(schedule: Schedule) -> Self {
+        match schedule {
+            Schedule::Constant(value) => ScheduleRepr::Constant(value),
+            Schedule::Linear { from, to } => {
+                ScheduleRepr::Table(ScheduleTable::Linear { from, to })
+            }
+            Schedule::Exponential { from, to, rate } => {
+                ScheduleRepr::Table(ScheduleTable::Exponential { from, to, rate })
+            }
+            Schedule::Steps(steps) => ScheduleRepr::Table(ScheduleTable::Steps { steps }),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    /// Parses a float, `linear:<from>:<to>`, `exponential:<from>:<to>:<rate>` or
+    /// `steps:<generation>=<value>[,<generation>=<value>...]`, e.g. `steps:0=0.5,50=0.3`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Unknown schedule `{}`", s);
+        let float = |value: &str| value.trim().parse::<f64>().map_err(|_| invalid());
+
+        if let Ok(value) = s.trim().parse::<f64>() {
+            return Ok(Schedule::Constant(value));
+        }
+
+        match s.trim().split_once(':').ok_or_else(invalid)? {
+            ("linear", values) => match values.split(':').collect_vec()[..] {
+                [from, to] => Ok(Schedule::Linear {
+                    from: float(from)?,
+                    to: float(to)?,
+                }),
+                _ => Err(invalid()),
+            },
+            ("exponential", values) => match values.split(':').collect_vec()[..] {
+                [from, to, rate] => Ok(Schedule::Exponential {
+                    from: float(from)?,
+                    to: float(to)?,
+                    rate: float(rate)?,
+                }),
+                _ => Err(invalid()),
+            },
+            ("steps", steps) => steps
+                .split(',')
+                .map(|step| {
+                    let (generation, value) = step.split_once('=').ok_or_else(invalid)?;
+                    let generation = generation.trim().parse().map_err(|_| invalid())?;
+
+                    Ok((generation, float(value)?))
+                })
+                .collect::<Result<_, _>>()
+                .map(Schedule::Steps),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Constant(value) => write!(f, "{}", value),
+            Schedule::Linear { from, to } => write!(f, "linear:{}:{}", from, to),
+            Schedule::Exponential { from, to, rate } => {
+                write!(f, "exponential:{}:{}:{}", from, to, rate)
+            }
+            Schedule::Steps(steps) => write!(
+                f,
+                "steps:{}",
+                steps
+                    .iter()
+                    .map(|(generation, value)| format!("{}={}", generation, value))
+                    .join(",")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, File, FileFormat};
+
+    use crate::utils::misc::VoidResultAnyError;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Rates {
+        gap: Schedule,
+        mutation_percent: Schedule,
+        crossover_percent: Schedule,
+        steps: Schedule,
+    }
+
+    #[test]
+    fn given_bare_floats_and_tables_when_parsed_from_toml_then_schedules_match(
+    ) -> VoidResultAnyError {
+        let rates: Rates = Config::builder()
+            .add_source(File::from_str(
+                "gap = 0.5\n\
+                 mutation_percent = { type = \"linear\", from = 0.8, to = 0.2 }\n\
+                 crossover_percent = { type = \"exponential\", from = 0.1, to = 0.6, rate = 0.05 }\n\
+                 steps = { type = \"steps\", steps = [[0, 0.5], [50, 0.3]] }\n",
+                FileFormat::Toml,
+            ))
+            .build()?
+            .try_deserialize()?;
+
+        assert_eq!(rates.gap, Schedule::Constant(0.5));
+        assert_eq!(
+            rates.mutation_percent,
+            Schedule::Linear { from: 0.8, to: 0.2 }
+        );
+        assert_eq!(
+            rates.crossover_percent,
+            Schedule::Exponential {
+                from: 0.1,
+                to: 0.6,
+                rate: 0.05
+            }
+        );
+        assert_eq!(rates.steps, Schedule::Steps(vec![(0, 0.5), (50, 0.3)]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_schedules_when_serialized_then_constants_stay_bare_floats_and_round_trip() {
+        let schedules = [
+            Schedule::Constant(0.5),
+            Schedule::Linear { from: 0.8, to: 0.2 },
+            Schedule::Steps(vec![(0, 0.5), (50, 0.3)]),
+        ];
+
+        assert_eq!(serde_json::to_string(&schedules[0]).unwrap(), "0.5");
+        for schedule in schedules {
+            let json = serde_json::to_string(&schedule).unwrap();
+            assert_eq!(serde_json::from_str::<Schedule>(&json).unwrap(), schedule);
+            assert_eq!(schedule.to_string().parse::<Schedule>().unwrap(), schedule);
+        }
+
+        assert!("linear:0.8".parse::<Schedule>().is_err());
+        assert!("cosine:0.8:0.2".parse::<Schedule>().is_err());
+    }
+
+    #[test]
+    fn given_schedules_when_evaluated_then_values_follow_their_formulas() {
+        let linear = Schedule::Linear { from: 0.8, to: 0.2 };
+        assert_eq!(linear.value(0, 10), 0.8);
+        assert!((linear.value(5, 10) - 0.5).abs() < 1e-12);
+        assert_eq!(linear.value(10, 10), 0.2);
+        assert_eq!(linear.value(0, 0), 0.8);
+
+        let exponential = Schedule::Exponential {
+            from: 0.1,
+            to: 0.6,
+            rate: 0.05,
+        };
+        assert_eq!(exponential.value(0, 10), 0.1);
+        assert!((exponential.value(20, 10) - (0.6 - 0.5 * (-1f64).exp())).abs() < 1e-12);
+
+        let steps = Schedule::Steps(vec![(5, 0.5), (50, 0.3)]);
+        assert_eq!(steps.value(0, 100), 0.5);
+        assert_eq!(steps.value(49, 100), 0.5);
+        assert_eq!(steps.value(50, 100), 0.3);
+    }
+
+    #[test]
+    fn given_malformed_schedules_when_validated_then_violations_are_reported() {
+        let mut violations = vec![];
+        Schedule::Linear { from: 1.2, to: 0. }.require_unit(&mut violations, "gap");
+        Schedule::Exponential {
+            from: 0.5,
+            to: 0.1,
+            rate: -1.,
+        }
+        .require_unit(&mut violations, "mutation_percent");
+        Schedule::Steps(vec![(10, 0.1), (5, 0.2)])
+            .require_unit(&mut violations, "crossover_percent");
+
+        let fields = violations
+            .into_iter()
+            .map(|violation| violation.field)
+            .collect_vec();
+        assert_eq!(
+            fields,
+            vec!["gap", "mutation_percent.rate", "crossover_percent.steps"]
+        );
+    }
+}
@@ -0,0 +1,458 @@
+use std::{cmp::Ordering, sync::OnceLock};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::random::generator;
+
+use super::{
+    characteristics::Genotype,
+    engines::{
+        breed_engine::Breed,
+        core_engine::{Core, VariationCounts},
+        fitness_engine::{Fitness, FitnessEngine, Objective},
+        freeze_engine::{Freeze, FreezeEngine},
+        generate_engine::Generate,
+        mutate_engine::{Mutate, MutateEngine},
+        reset_engine::{Reset, ResetEngine},
+        status_engine::{FitnessState, Status, StatusEngine},
+    },
+    environment::{BehaviorDescriptor, State},
+    instruction::{Instruction, InstructionGeneratorParametersBuilder},
+    program::{ProgramGeneratorParameters, ProgramGeneratorParametersBuilder},
+    registers::Registers,
+};
+
+/// Individual of `MockEngine`: a byte string whose fitness is the sum of its bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockIndividual {
+    pub genes: Vec<u8>,
+    pub fitness: FitnessState,
+}
+
+impl MockIndividual {
+    pub fn new(genes: Vec<u8>) -> Self {
+        MockIndividual {
+            genes,
+            fitness: FitnessState::NotEvaluated,
+        }
+    }
+}
+
+/// Ordered by fitness, then by genes with the smallest ranking highest, like programs by id.
+impl Ord for MockIndividual {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.fitness
+            .cmp(&other.fitness)
+            .then_with(|| other.genes.cmp(&self.genes))
+    }
+}
+
+impl PartialOrd for MockIndividual {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MockIndividual {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MockIndividual {}
+
+impl Genotype for MockIndividual {
+    fn instructions(&self) -> &[Instruction] {
+        &[]
+    }
+
+    fn registers(&self) -> &Registers {
+        static REGISTERS: OnceLock<Registers> = OnceLock::new();
+        REGISTERS.get_or_init(|| Registers::new(1, 0))
+    }
+}
+
+/// Trial of `MockEngine`; individuals score the same on every trial.
+#[derive(Debug, Clone, Default)]
+pub struct MockState;
+
+impl State for MockState {
+    fn get_value(&self, _at_idx: usize) -> f64 {
+        0.
+    }
+
+    fn execute_action(&mut self, _action: usize) -> f64 {
+        0.
+    }
+
+    fn get(&mut self) -> Option<&mut Self> {
+        None
+    }
+}
+
+impl BehaviorDescriptor for MockState {}
+
+/// Generates `MockEngine` individuals and trials. Kept apart from `GenerateEngine` so that
+/// generating programs never has to pick between the mock and the real implementations.
+pub struct MockGenerateEngine;
+
+/// Breeds `MockEngine` individuals.
+pub struct MockBreedEngine;
+
+/// Genes have between `min_instructions` and `max_instructions` random bytes.
+impl Generate<ProgramGeneratorParameters, MockIndividual> for MockGenerateEngine {
+    fn generate(using: ProgramGeneratorParameters) -> MockIndividual {
+        let max_length = using.max_instructions;
+        let length =
+            generator().gen_range(using.min_instructions.clamp(1, max_length)..=max_length);
+        let genes = (0..length).map(|_| generator().gen()).collect();
+
+        MockIndividual::new(genes)
+    }
+}
+
+impl Generate<(), MockState> for MockGenerateEngine {
+    fn generate(_using: ()) -> MockState {
+        MockState
+    }
+}
+
+impl Fitness<MockIndividual, MockState, ()> for FitnessEngine {
+    fn eval_fitness(individual: &mut MockIndividual, _state: &mut MockState) -> f64 {
+        individual.genes.iter().map(|gene| *gene as f64).sum()
+    }
+}
+
+impl Reset<MockIndividual> for ResetEngine {
+    fn reset(item: &mut MockIndividual) {
+        ResetEngine::reset(&mut item.fitness);
+    }
+}
+
+impl Reset<MockState> for ResetEngine {
+    fn reset(_item: &mut MockState) {}
+}
+
+/// Exchanges the tails of the parents after a random cut point in each.
+impl Breed<MockIndividual> for MockBreedEngine {
+    fn two_point_crossover(
+        mate_1: &MockIndividual,
+        mate_2: &MockIndividual,
+    ) -> (MockIndividual, MockIndividual) {
+        let cut_1 = generator().gen_range(0..=mate_1.genes.len());
+        let cut_2 = generator().gen_range(0..=mate_2.genes.len());
+        let (head_1, tail_1) = mate_1.genes.split_at(cut_1);
+        let (head_2, tail_2) = mate_2.genes.split_at(cut_2);
+
+        (
+            MockIndividual::new([head_1, tail_2].concat()),
+            MockIndividual::new([head_2, tail_1].concat()),
+        )
+    }
+}
+
+/// Replaces one random gene.
+impl Mutate<ProgramGeneratorParameters, MockIndividual> for MutateEngine {
+    fn mutate(item: &mut MockIndividual, _using: ProgramGeneratorParameters) {
+        if !item.genes.is_empty() {
+            let idx = generator().gen_range(0..item.genes.len());
+            item.genes[idx] = generator().gen();
+        }
+        ResetEngine::reset(item);
+    }
+}
+
+impl Status<MockIndividual> for StatusEngine {
+    fn valid(item: &MockIndividual) -> bool {
+        item.fitness.is_valid()
+    }
+
+    fn evaluated(item: &MockIndividual) -> bool {
+        item.fitness.is_evaluated()
+    }
+
+    fn set_fitness(program: &mut MockIndividual, fitness: f64) {
+        program.fitness = FitnessState::from_fitness(fitness);
+    }
+
+    fn get_fitness(program: &MockIndividual) -> f64 {
+        program.fitness.fitness()
+    }
+
+    fn effective_length(item: &MockIndividual) -> usize {
+        item.genes.len()
+    }
+}
+
+impl Freeze<MockIndividual> for FreezeEngine {}
+
+/// A fast synthetic problem for exercising the engine, e.g. in property-based tests, without
+/// programs or datasets. Individuals are byte strings and fitness is deterministic.
+#[derive(Clone)]
+pub struct MockEngine;
+
+impl MockEngine {
+    /// Program parameters generating genes of up to `max_length` bytes.
+    pub fn program_parameters(max_length: usize) -> ProgramGeneratorParameters {
+        ProgramGeneratorParametersBuilder::default()
+            .max_instructions(max_length)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_actions(1)
+                    .n_inputs(1)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+}
+
+impl Core for MockEngine {
+    type Individual = MockIndividual;
+    type ProgramParameters = ProgramGeneratorParameters;
+    type State = MockState;
+    type StateParameters = ();
+    type FitnessMarker = ();
+    type Generate = MockGenerateEngine;
+    type Fitness = FitnessEngine;
+    type Reset = ResetEngine;
+    type Breed = MockBreedEngine;
+    type Mutate = MutateEngine;
+    type Status = StatusEngine;
+    type Freeze = FreezeEngine;
+}
+
+/// Checks that every individual of `population` has been evaluated.
+pub fn check_evaluated<C>(population: &[C::Individual]) -> Result<(), String>
+where
+    C: Core,
+{
+    match population.iter().position(|i| !C::Status::evaluated(i)) {
+        Some(idx) => Err(format!("Individual {} was not evaluated.", idx)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that `population` is ordered from best to worst under `objective`, with valid
+/// individuals first.
+pub fn check_ranked<C>(population: &[C::Individual], objective: Objective) -> Result<(), String>
+where
+    C: Core,
+{
+    for (idx, pair) in population.windows(2).enumerate() {
+        let (a, b) = (&pair[0], &pair[1]);
+        let in_order = match (C::Status::valid(a), C::Status::valid(b)) {
+            (true, true) => {
+                objective.compare(C::Status::get_fitness(a), C::Status::get_fitness(b))
+                    != Ordering::Greater
+            }
+            (valid_a, valid_b) => valid_a || !valid_b,
+        };
+
+        if !in_order {
+            return Err(format!(
+                "Individuals {} and {} are out of order: {} then {}.",
+                idx,
+                idx + 1,
+                C::Status::get_fitness(a),
+                C::Status::get_fitness(b)
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that `survive` dropped exactly `floor(gap * population_size)` of `n_individuals`
+/// valid individuals, or kept `min_survivors` of them, and at least one.
+pub fn check_survived(
+    n_individuals: usize,
+    n_survivors: usize,
+    population_size: usize,
+    gap: f64,
+    min_survivors: usize,
+) -> Result<(), String> {
+    let n_dropped = (gap * population_size as f64).floor() as usize;
+    let expected = n_individuals
+        .saturating_sub(n_dropped)
+        .max(min_survivors.max(1).min(n_individuals));
+
+    if n_survivors == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} of {} individuals survived a gap of {}, expected {}.",
+            n_survivors, n_individuals, gap, expected
+        ))
+    }
+}
+
+/// Checks that `variation` filled `n_survivors` back up to `population_size`, with every
+/// offspring counted under its operator.
+pub fn check_refilled(
+    n_survivors: usize,
+    n_individuals: usize,
+    population_size: usize,
+    counts: VariationCounts,
+) -> Result<(), String> {
+    let n_offspring = counts.n_crossover + counts.n_mutation + counts.n_clone;
+
+    if n_individuals != population_size {
+        Err(format!(
+            "Variation left {} individuals, expected {}.",
+            n_individuals, population_size
+        ))
+    } else if n_survivors + n_offspring != population_size {
+        Err(format!(
+            "{} offspring were counted for {} open spots.",
+            n_offspring,
+            population_size - n_survivors
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::core::engines::{
+        breed_engine::CrossoverChildPolicy,
+        core_engine::{HyperParameters, HyperParametersBuilder},
+        fitness_engine::FitnessAggregation,
+    };
+
+    use super::*;
+
+    fn unit() -> impl Strategy<Value = f64> {
+        prop_oneof![Just(0.), Just(1.), 0. ..=1.]
+    }
+
+    fn child_policy() -> impl Strategy<Value = CrossoverChildPolicy> {
+        prop_oneof![
+            Just(CrossoverChildPolicy::RandomOne),
+            Just(CrossoverChildPolicy::Both),
+            Just(CrossoverChildPolicy::BestOfPair),
+        ]
+    }
+
+    fn objective() -> impl Strategy<Value = Objective> {
+        prop_oneof![Just(Objective::Maximize), Just(Objective::Minimize)]
+    }
+
+    #[test]
+    fn given_full_gap_when_iterating_then_the_best_survives_each_generation() {
+        let parameters = HyperParametersBuilder::<MockEngine>::default()
+            .program_parameters(MockEngine::program_parameters(8))
+            .population_size(10)
+            .n_trials(1)
+            .n_generations(5)
+            .gap(1.)
+            .build()
+            .unwrap();
+
+        let mut engine = parameters.build_engine_with(());
+        let populations = engine.by_ref().collect::<Vec<_>>();
+
+        assert_eq!(populations.len(), 6);
+        assert!(populations.iter().all(|population| population.len() == 10));
+        assert!(engine.failure().is_none());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn given_random_hyperparameters_when_iterating_then_invariants_hold(
+            population_size in 1usize..500,
+            gap in unit(),
+            mutation_percent in unit(),
+            crossover_percent in unit(),
+            crossover_child_policy in child_policy(),
+            objective in objective(),
+            seed in any::<u64>(),
+        ) {
+            prop_assume!(mutation_percent + crossover_percent <= 1.);
+
+            let parameters: HyperParameters<MockEngine> = HyperParametersBuilder::default()
+                .program_parameters(MockEngine::program_parameters(8))
+                .population_size(population_size)
+                .gap(gap)
+                .mutation_percent(mutation_percent)
+                .crossover_percent(crossover_percent)
+                .crossover_child_policy(crossover_child_policy)
+                .objective(objective)
+                .n_trials(1)
+                .n_generations(3)
+                .seed(Some(seed))
+                .build()
+                .unwrap();
+            prop_assert!(parameters.validate().is_ok());
+
+            let mut engine = parameters.build_engine_with(());
+            let mut n_populations = 0;
+
+            for population in engine.by_ref() {
+                n_populations += 1;
+                prop_assert_eq!(population.len(), population_size);
+                prop_assert_eq!(check_evaluated::<MockEngine>(&population), Ok(()));
+                prop_assert_eq!(check_ranked::<MockEngine>(&population, objective), Ok(()));
+            }
+
+            prop_assert_eq!(n_populations, 4);
+            prop_assert!(engine.failure().is_none());
+        }
+
+        #[test]
+        fn given_random_population_when_each_step_runs_then_its_invariant_holds(
+            population_size in 1usize..500,
+            gap in unit(),
+            mutation_percent in unit(),
+            crossover_percent in unit(),
+            min_survivors in 0usize..4,
+            crossover_child_policy in child_policy(),
+            objective in objective(),
+        ) {
+            prop_assume!(mutation_percent + crossover_percent <= 1.);
+            let min_survivors = min_survivors.min(population_size);
+            let program_parameters = MockEngine::program_parameters(8);
+            let mut trials = vec![MockState];
+
+            let mut population = MockEngine::init_population(program_parameters, population_size);
+            MockEngine::eval_fitness(&mut population, &mut trials, 0., FitnessAggregation::Mean);
+            prop_assert_eq!(check_evaluated::<MockEngine>(&population), Ok(()));
+
+            MockEngine::rank(&mut population, objective);
+            prop_assert_eq!(check_ranked::<MockEngine>(&population, objective), Ok(()));
+
+            MockEngine::survive(&mut population, population_size, gap, min_survivors);
+            let n_survivors = population.len();
+            prop_assert_eq!(
+                check_survived(population_size, n_survivors, population_size, gap, min_survivors),
+                Ok(())
+            );
+
+            let counts = MockEngine::variation(
+                &mut population,
+                population_size,
+                crossover_percent,
+                mutation_percent,
+                program_parameters,
+                crossover_child_policy,
+                Default::default(),
+                &mut trials,
+                0.,
+                FitnessAggregation::Mean,
+                objective,
+                0,
+            );
+            prop_assert_eq!(
+                check_refilled(n_survivors, population.len(), population_size, counts),
+                Ok(())
+            );
+        }
+    }
+}
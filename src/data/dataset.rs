@@ -0,0 +1,409 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+
+/// A labelled, in-memory table of features that problem states are built from.
+///
+/// All sampling utilities take an explicit seed so that splits are reproducible independently
+/// of the global generator.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Dataset {
+    pub features: Vec<Vec<f64>>,
+    pub labels: Vec<usize>,
+    pub feature_names: Vec<String>,
+    pub label_names: Vec<String>,
+}
+
+/// State parameters built around a dataset, so cross-validation can evaluate every fold under
+/// the same settings.
+pub trait DatasetParameters: Sized {
+    fn dataset(&self) -> &Dataset;
+
+    /// These parameters with `dataset` in place of their own.
+    fn with_dataset(&self, dataset: Dataset) -> Self;
+
+    /// These parameters with trials evaluating every row once, e.g. to validate on a fold.
+    fn without_sampling(&self) -> Self;
+}
+
+fn seeded(seed: u64) -> Xoshiro256PlusPlus {
+    Xoshiro256PlusPlus::seed_from_u64(seed)
+}
+
+/// Number of items taken for a ratio, rounding half away from zero.
+fn n_for_ratio(n: usize, ratio: f64) -> usize {
+    ((n as f64) * ratio.clamp(0., 1.)).round() as usize
+}
+
+impl Dataset {
+    pub fn new(
+        features: Vec<Vec<f64>>,
+        labels: Vec<usize>,
+        feature_names: Vec<String>,
+        label_names: Vec<String>,
+    ) -> Self {
+        debug_assert_eq!(features.len(), labels.len());
+
+        Dataset {
+            features,
+            labels,
+            feature_names,
+            label_names,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn n_features(&self) -> usize {
+        self.features.first().map(Vec::len).unwrap_or(0)
+    }
+
+    pub fn n_classes(&self) -> usize {
+        let observed = self.labels.iter().max().map(|max| max + 1).unwrap_or(0);
+        observed.max(self.label_names.len())
+    }
+
+    /// Builds a new dataset from the rows at `indices`, in that order.
+    pub fn select(&self, indices: &[usize]) -> Dataset {
+        Dataset {
+            features: indices.iter().map(|i| self.features[*i].clone()).collect(),
+            labels: indices.iter().map(|i| self.labels[*i]).collect(),
+            feature_names: self.feature_names.clone(),
+            label_names: self.label_names.clone(),
+        }
+    }
+
+    /// Shuffles the rows in place. Consumes the generator exactly like shuffling the rows
+    /// themselves would.
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let mut indices = (0..self.len()).collect_vec();
+        indices.shuffle(rng);
+        *self = self.select(&indices);
+    }
+
+    /// Row indices grouped by class.
+    pub fn class_indices(&self) -> Vec<Vec<usize>> {
+        let mut classes = vec![vec![]; self.n_classes()];
+
+        for (idx, label) in self.labels.iter().enumerate() {
+            classes[*label].push(idx);
+        }
+
+        classes
+    }
+
+    /// Randomly splits the rows into two datasets, the first holding `ratio` of the rows.
+    pub fn split(&self, ratio: f64, seed: u64) -> (Dataset, Dataset) {
+        let mut indices = (0..self.len()).collect_vec();
+        indices.shuffle(&mut seeded(seed));
+
+        let (first, second) = indices.split_at(n_for_ratio(self.len(), ratio));
+        (self.select(first), self.select(second))
+    }
+
+    /// Splits every class separately so both datasets preserve the class proportions.
+    ///
+    /// Each class contributes `round(ratio * class_size)` rows to the first dataset, so a class
+    /// with a single row ends up entirely on one side.
+    pub fn stratified_split(&self, ratio: f64, seed: u64) -> (Dataset, Dataset) {
+        let mut rng = seeded(seed);
+        let mut first = vec![];
+        let mut second = vec![];
+
+        for mut class in self.class_indices() {
+            class.shuffle(&mut rng);
+            let (a, b) = class.split_at(n_for_ratio(class.len(), ratio));
+            first.extend_from_slice(a);
+            second.extend_from_slice(b);
+        }
+
+        (self.select(&first), self.select(&second))
+    }
+
+    /// Partitions the rows into `k` folds and returns `(train, validation)` pairs, one per fold.
+    /// Every row appears in exactly one validation fold; fold sizes differ by at most one.
+    pub fn k_folds(&self, k: usize, seed: u64) -> Vec<(Dataset, Dataset)> {
+        let mut indices = (0..self.len()).collect_vec();
+        indices.shuffle(&mut seeded(seed));
+
+        self.folds(&indices, k)
+    }
+
+    /// Like `k_folds`, but deals every class out separately so each validation fold preserves
+    /// the class proportions (per-class counts differ by at most one between folds).
+    pub fn stratified_k_folds(&self, k: usize, seed: u64) -> Vec<(Dataset, Dataset)> {
+        let mut rng = seeded(seed);
+        let mut indices = vec![];
+
+        for mut class in self.class_indices() {
+            class.shuffle(&mut rng);
+            indices.extend(class);
+        }
+
+        self.folds(&indices, k)
+    }
+
+    /// Deals `indices` out to `k` folds in round-robin order.
+    fn folds(&self, indices: &[usize], k: usize) -> Vec<(Dataset, Dataset)> {
+        let k = k.max(1);
+
+        (0..k)
+            .map(|fold| {
+                let (validation, train): (Vec<_>, Vec<_>) = indices
+                    .iter()
+                    .enumerate()
+                    .partition(|(position, _)| position % k == fold);
+
+                let validation = validation.into_iter().map(|(_, i)| *i).collect_vec();
+                let train = train.into_iter().map(|(_, i)| *i).collect_vec();
+
+                (self.select(&train), self.select(&validation))
+            })
+            .collect()
+    }
+
+    /// Samples `n` rows without replacement (all rows if `n` exceeds the dataset size).
+    pub fn subsample(&self, n: usize, seed: u64) -> Dataset {
+        let mut indices = (0..self.len()).collect_vec();
+        indices.shuffle(&mut seeded(seed));
+        indices.truncate(n);
+
+        self.select(&indices)
+    }
+
+    /// Samples `n` rows with replacement, so rows can repeat; empty datasets stay empty.
+    pub fn bootstrap(&self, n: usize, seed: u64) -> Dataset {
+        if self.is_empty() {
+            return self.clone();
+        }
+
+        let mut rng = seeded(seed);
+        let indices = (0..n).map(|_| rng.gen_range(0..self.len())).collect_vec();
+
+        self.select(&indices)
+    }
+
+    /// Samples about `n` rows without replacement, taking `round(n / len * class_size)` rows of
+    /// every class so the class proportions are preserved. Every class keeps at least one row,
+    /// so rare classes are never dropped.
+    pub fn stratified_subsample(&self, n: usize, seed: u64) -> Dataset {
+        let ratio = n as f64 / self.len().max(1) as f64;
+        let mut rng = seeded(seed);
+        let mut indices = vec![];
+
+        for mut class in self.class_indices() {
+            class.shuffle(&mut rng);
+            let n_class = n_for_ratio(class.len(), ratio).max(1).min(class.len());
+            indices.extend_from_slice(&class[..n_class]);
+        }
+
+        self.select(&indices)
+    }
+
+    /// Replaces the label of `round(fraction * len)` randomly chosen rows with a different class.
+    pub fn inject_label_noise(&self, fraction: f64, seed: u64) -> Dataset {
+        let mut rng = seeded(seed);
+        let mut noisy = self.clone();
+        let n_classes = self.n_classes();
+
+        if n_classes < 2 {
+            return noisy;
+        }
+
+        let mut indices = (0..self.len()).collect_vec();
+        indices.shuffle(&mut rng);
+
+        for idx in indices.into_iter().take(n_for_ratio(self.len(), fraction)) {
+            let offset = rng.gen_range(1..n_classes);
+            noisy.labels[idx] = (self.labels[idx] + offset) % n_classes;
+        }
+
+        noisy
+    }
+
+    /// Stable hash of the contents (features, labels and names).
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        for row in &self.features {
+            row.iter()
+                .for_each(|value| value.to_bits().hash(&mut hasher));
+        }
+
+        self.labels.hash(&mut hasher);
+        self.feature_names.hash(&mut hasher);
+        self.label_names.hash(&mut hasher);
+
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dataset(labels: Vec<usize>) -> Dataset {
+        let features = (0..labels.len()).map(|i| vec![i as f64]).collect();
+        Dataset::new(
+            features,
+            labels,
+            vec!["x".to_string()],
+            vec!["a".to_string(), "b".to_string()],
+        )
+    }
+
+    fn ids(dataset: &Dataset) -> Vec<usize> {
+        dataset.features.iter().map(|row| row[0] as usize).collect()
+    }
+
+    #[test]
+    fn given_uneven_ratio_when_split_then_sizes_are_rounded_and_rows_are_disjoint() {
+        let data = dataset(vec![0; 10]);
+        let (a, b) = data.split(0.35, 7);
+
+        assert_eq!(a.len(), 4);
+        assert_eq!(b.len(), 6);
+
+        let mut all = ids(&a);
+        all.extend(ids(&b));
+        all.sort();
+        assert_eq!(all, (0..10).collect_vec());
+
+        assert_eq!(data.split(0.35, 7), (a, b));
+    }
+
+    #[test]
+    fn given_classes_when_stratified_split_then_proportions_are_preserved() {
+        let data = dataset(vec![0, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+        let (a, b) = data.stratified_split(0.5, 1);
+
+        assert_eq!(a.labels.iter().filter(|l| **l == 0).count(), 4);
+        assert_eq!(a.labels.iter().filter(|l| **l == 1).count(), 1);
+        assert_eq!(b.labels.iter().filter(|l| **l == 0).count(), 4);
+        assert_eq!(b.labels.iter().filter(|l| **l == 1).count(), 1);
+    }
+
+    #[test]
+    fn given_tiny_class_when_stratified_split_then_class_goes_to_one_side() {
+        let data = dataset(vec![0, 0, 0, 1]);
+        let (a, b) = data.stratified_split(0.5, 3);
+
+        // round(0.5 * 1) = 1, round(0.5 * 3) = 2.
+        assert_eq!(a.labels.iter().filter(|l| **l == 1).count(), 1);
+        assert_eq!(a.len(), 3);
+        assert_eq!(b.len(), 1);
+    }
+
+    #[test]
+    fn given_k_folds_then_each_row_is_validated_exactly_once() {
+        let data = dataset(vec![0; 10]);
+        let folds = data.k_folds(3, 11);
+
+        assert_eq!(folds.len(), 3);
+        assert_eq!(
+            folds.iter().map(|(_, v)| v.len()).collect_vec(),
+            vec![4, 3, 3]
+        );
+
+        let mut validated = folds.iter().flat_map(|(_, v)| ids(v)).collect_vec();
+        validated.sort();
+        assert_eq!(validated, (0..10).collect_vec());
+
+        for (train, validation) in &folds {
+            assert_eq!(train.len() + validation.len(), 10);
+            assert!(ids(train).iter().all(|id| !ids(validation).contains(id)));
+        }
+    }
+
+    #[test]
+    fn given_stratified_k_folds_then_every_fold_preserves_class_proportions() {
+        let data = dataset(vec![0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1]);
+        let folds = data.stratified_k_folds(3, 4);
+
+        for (train, validation) in &folds {
+            assert_eq!(validation.labels.iter().filter(|l| **l == 0).count(), 2);
+            assert_eq!(validation.labels.iter().filter(|l| **l == 1).count(), 3);
+            assert_eq!(train.len() + validation.len(), 15);
+        }
+
+        let mut validated = folds.iter().flat_map(|(_, v)| ids(v)).collect_vec();
+        validated.sort();
+        assert_eq!(validated, (0..15).collect_vec());
+    }
+
+    #[test]
+    fn given_subsample_then_rows_are_distinct_and_capped() {
+        let data = dataset(vec![0; 10]);
+
+        let sample = data.subsample(4, 5);
+        assert_eq!(ids(&sample).into_iter().unique().count(), 4);
+
+        assert_eq!(data.subsample(20, 5).len(), 10);
+    }
+
+    #[test]
+    fn given_seed_when_bootstrapped_then_rows_repeat_and_the_sample_is_reproducible() {
+        let data = dataset(vec![0; 20]);
+
+        let sample = data.bootstrap(20, 3);
+        assert_eq!(sample.len(), 20);
+        assert!(ids(&sample).into_iter().unique().count() < 20);
+        assert_eq!(sample, data.bootstrap(20, 3));
+        assert_ne!(sample, data.bootstrap(20, 4));
+
+        assert_eq!(data.bootstrap(5, 3).len(), 5);
+        assert!(dataset(vec![]).bootstrap(5, 3).is_empty());
+    }
+
+    #[test]
+    fn given_rare_class_when_stratified_subsample_then_it_is_kept_in_proportion() {
+        let data = dataset([vec![0; 18], vec![1; 2]].concat());
+
+        let sample = data.stratified_subsample(10, 5);
+        assert_eq!(sample.labels.iter().filter(|l| **l == 0).count(), 9);
+        assert_eq!(sample.labels.iter().filter(|l| **l == 1).count(), 1);
+        assert_eq!(ids(&sample).into_iter().unique().count(), 10);
+
+        let tiny = data.stratified_subsample(2, 5);
+        assert_eq!(tiny.labels.iter().filter(|l| **l == 1).count(), 1);
+        assert_eq!(data.stratified_subsample(40, 5).len(), 20);
+    }
+
+    #[test]
+    fn given_noise_fraction_then_exactly_that_many_labels_change() {
+        let data = dataset(vec![0, 1, 0, 1, 0, 1, 0, 1, 0, 1]);
+        let noisy = data.inject_label_noise(0.3, 9);
+
+        let n_changed = data
+            .labels
+            .iter()
+            .zip(noisy.labels.iter())
+            .filter(|(a, b)| a != b)
+            .count();
+
+        assert_eq!(n_changed, 3);
+        assert_eq!(noisy, data.inject_label_noise(0.3, 9));
+    }
+
+    #[test]
+    fn given_different_contents_then_fingerprints_differ() {
+        let data = dataset(vec![0, 1]);
+        let mut other = data.clone();
+        other.features[0][0] = 42.;
+
+        assert_eq!(data.fingerprint(), data.clone().fingerprint());
+        assert_ne!(data.fingerprint(), other.fingerprint());
+    }
+}
@@ -1,33 +1,27 @@
-use crate::core::{
-    engines::fitness_engine::{Fitness, FitnessEngine},
-    environment::State,
-    program::Program,
-    registers::{ActionRegister, ArgmaxInput},
+use crate::{
+    core::{
+        engines::fitness_engine::{Fitness, FitnessEngine},
+        program::Program,
+    },
+    problems::classification::{classify, ClassificationReport, FitnessMetric, LabelledState},
 };
 
 impl<T> Fitness<Program, T, ()> for FitnessEngine
 where
-    T: State,
+    T: LabelledState,
 {
     fn eval_fitness(program: &mut Program, states: &mut T) -> f64 {
-        let mut n_correct = 0.;
-        let mut n_total = 0.;
+        let (predictions, overflowed) = classify(program, states);
 
-        while let Some(state) = states.get() {
-            program.run(state);
+        if overflowed {
+            return f64::NEG_INFINITY;
+        }
 
-            match program.registers.argmax(ArgmaxInput::ActionRegisters).one() {
-                ActionRegister::Overflow => {
-                    return f64::NEG_INFINITY;
-                }
-                ActionRegister::Value(predicted_class) => {
-                    n_correct += state.execute_action(predicted_class);
-                }
-            };
+        let report = ClassificationReport::new(states.n_classes(), &predictions, overflowed);
 
-            n_total += 1.;
+        match states.fitness_metric() {
+            FitnessMetric::Accuracy => report.weighted_accuracy(|class| states.class_weight(class)),
+            FitnessMetric::MacroF1 => report.macro_f1,
         }
-
-        n_correct / n_total
     }
 }
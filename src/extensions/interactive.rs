@@ -46,7 +46,7 @@ where
             program.run(state);
 
             // Eval
-            let reward = match program.registers.argmax(ArgmaxInput::ActionRegisters).any() {
+            let reward = match program.registers.argmax(ArgmaxInput::OutputRegisters).any() {
                 ActionRegister::Value(action) => state.execute_action(action),
                 ActionRegister::Overflow => {
                     return f64::NEG_INFINITY;
@@ -1,16 +1,26 @@
-use std::fmt::{self, Debug};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock},
+};
 
-use clap::Args;
-use derivative::Derivative;
+use clap::{Args, ValueEnum};
 use derive_builder::Builder;
-use rand::Rng;
-use serde::{Deserialize, Serialize};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tracing::info;
+use uuid::Uuid;
 
 use crate::{
     core::{
+        adaptation::AdaptiveRates,
+        characteristics::{Format, Genotype, Load},
+        diversity::edit_distance,
         engines::{
-            breed_engine::{Breed, BreedEngine},
+            breed_engine::{Breed, BreedEngine, CrossoverKind},
             fitness_engine::{Fitness, FitnessEngine},
             freeze_engine::{Freeze, FreezeEngine},
             generate_engine::{Generate, GenerateEngine},
@@ -18,19 +28,143 @@ use crate::{
             reset_engine::{Reset, ResetEngine},
             status_engine::{Status, StatusEngine},
         },
-        environment::{RlState, State},
-        instruction::InstructionGeneratorParameters,
-        program::{Program, ProgramGeneratorParameters},
+        environment::{EpisodeStatus, RlState, State},
+        instruction::{Instruction, InstructionGeneratorParameters},
+        program::{Program, ProgramGeneratorParameters, ProgramShape},
+        provenance::Provenance,
         registers::{ActionRegister, ArgmaxInput, Registers},
     },
-    utils::{float_ops, random::generator},
+    utils::{
+        error::{require, ConfigViolation, LgpError, LgpResult},
+        float_ops,
+        random::{derive_seed, generator, run_seed},
+    },
 };
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct QTable {
+    #[serde(with = "labelled_q_values")]
     table: Vec<Vec<f64>>,
     q_consts: QConsts,
     freeze: bool,
+    /// Eligibility traces for the current episode (Q(λ)).
+    #[serde(default)]
+    traces: EligibilityTraces,
+    /// Second estimator used when double Q-learning is enabled.
+    #[serde(default, with = "labelled_q_values")]
+    double_table: Vec<Vec<f64>>,
+    #[serde(default)]
+    n_updates: usize,
+    /// Seed of the exploration generator, derived from the run seed and the program id.
+    #[serde(default)]
+    exploration_seed: u64,
+    /// Used exclusively for epsilon decisions, random actions and tie breaking; restarted
+    /// from `exploration_seed` at every reset so that episodes replay exactly.
+    #[serde(skip)]
+    exploration: Option<Xoshiro256PlusPlus>,
+    /// Always act greedily, e.g. when evaluating on holdout trials.
+    #[serde(default)]
+    greedy: bool,
+    /// Q-values resets restore; zeros when empty.
+    #[serde(default)]
+    initial_table: Vec<Vec<f64>>,
+}
+
+/// Q-values of one register, labelled with the action they belong to.
+#[derive(Serialize, Deserialize)]
+struct LabelledRegister {
+    register: usize,
+    actions: Vec<LabelledAction>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LabelledAction {
+    action: usize,
+    value: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QValuesRepr {
+    Labelled(Vec<LabelledRegister>),
+    /// Bare nested arrays saved before the values were labelled.
+    Legacy(Vec<Vec<f64>>),
+}
+
+/// Saves Q-values as registers holding labelled actions in human-readable formats, and as bare
+/// nested arrays in binary ones.
+mod labelled_q_values {
+    use super::*;
+
+    pub fn serialize<S>(table: &[Vec<f64>], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if !serializer.is_human_readable() {
+            return table.serialize(serializer);
+        }
+
+        table
+            .iter()
+            .enumerate()
+            .map(|(register, values)| LabelledRegister {
+                register,
+                actions: values
+                    .iter()
+                    .enumerate()
+                    .map(|(action, &value)| LabelledAction { action, value })
+                    .collect(),
+            })
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Vec<f64>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Binary formats are not self-describing and only ever held the bare arrays.
+        if !deserializer.is_human_readable() {
+            return Vec::deserialize(deserializer);
+        }
+
+        let table = match QValuesRepr::deserialize(deserializer)? {
+            QValuesRepr::Legacy(table) => table,
+            QValuesRepr::Labelled(mut registers) => {
+                registers.sort_by_key(|register| register.register);
+                registers
+                    .into_iter()
+                    .map(|register| {
+                        let mut actions = register.actions;
+                        actions.sort_by_key(|action| action.action);
+                        actions.into_iter().map(|action| action.value).collect()
+                    })
+                    .collect()
+            }
+        };
+
+        Ok(table)
+    }
+}
+
+/// Best action and range of Q-values of a register.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegisterQSummary {
+    pub register: usize,
+    pub best_action: usize,
+    pub min_value: f64,
+    pub max_value: f64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EligibilityTraces(Vec<Vec<f64>>);
+
+impl Reset<EligibilityTraces> for ResetEngine {
+    fn reset(item: &mut EligibilityTraces) {
+        for value in item.0.iter_mut().flatten() {
+            *value = 0.
+        }
+    }
 }
 
 impl Freeze<QTable> for FreezeEngine {
@@ -45,6 +179,17 @@ impl Generate<(InstructionGeneratorParameters, QConsts), QTable> for GenerateEng
             table: vec![vec![0.; using.0.n_actions]; using.0.n_registers()],
             q_consts: using.1,
             freeze: false,
+            traces: EligibilityTraces(vec![vec![0.; using.0.n_actions]; using.0.n_registers()]),
+            double_table: if using.1.double_q {
+                vec![vec![0.; using.0.n_actions]; using.0.n_registers()]
+            } else {
+                vec![]
+            },
+            n_updates: 0,
+            exploration_seed: 0,
+            exploration: None,
+            greedy: false,
+            initial_table: vec![],
         };
 
         ResetEngine::reset(&mut table);
@@ -64,16 +209,60 @@ pub struct ActionRegisterPair {
     register: usize,
 }
 
+impl ActionRegisterPair {
+    pub fn action(&self) -> usize {
+        self.action
+    }
+}
+
 impl Reset<QTable> for ResetEngine {
     fn reset(item: &mut QTable) {
+        for table in [&mut item.table, &mut item.double_table] {
+            for (register, values) in table.iter_mut().enumerate() {
+                for (action, value) in values.iter_mut().enumerate() {
+                    *value = item
+                        .initial_table
+                        .get(register)
+                        .map_or(0., |initial| initial[action]);
+                }
+            }
+        }
+
         ResetEngine::reset(&mut item.q_consts);
+        ResetEngine::reset(&mut item.traces);
     }
 }
 
 impl QTable {
-    pub fn action_random(&self) -> usize {
+    fn exploration(&mut self) -> &mut Xoshiro256PlusPlus {
+        let seed = self.exploration_seed;
+        self.exploration
+            .get_or_insert_with(|| Xoshiro256PlusPlus::seed_from_u64(seed))
+    }
+
+    pub fn restart_exploration(&mut self) {
+        self.exploration = Some(Xoshiro256PlusPlus::seed_from_u64(self.exploration_seed));
+    }
+
+    /// Starts from `values`, one row of action values per register, instead of zeros, now and
+    /// whenever the table is reset.
+    pub fn warm_start(&mut self, values: &[Vec<f64>]) {
+        self.initial_table = values.to_vec();
+        ResetEngine::reset(self);
+    }
+
+    pub fn set_greedy(&mut self, greedy: bool) {
+        self.greedy = greedy;
+    }
+
+    /// Frozen tables neither learn nor explore, so replaying them is deterministic.
+    pub fn is_frozen(&self) -> bool {
+        self.freeze
+    }
+
+    pub fn action_random(&mut self) -> usize {
         let n_actions = self.table[0].len();
-        generator().gen_range(0..n_actions)
+        self.exploration().gen_range(0..n_actions)
     }
 
     pub fn action_argmax(&self, register_number: usize) -> usize {
@@ -82,23 +271,116 @@ impl QTable {
             .get(register_number)
             .expect("Register number to be less than length of QTable.");
 
-        let iter = available_actions.iter().copied();
-        let max = float_ops::argmax(iter);
+        let max = if self.q_consts.double_q {
+            let other_actions = &self.double_table[register_number];
+            let summed = available_actions
+                .iter()
+                .zip(other_actions.iter())
+                .map(|(a, b)| a + b);
+            float_ops::argmax(summed)
+        } else {
+            float_ops::argmax(available_actions.iter().copied())
+        };
 
         max.expect("Available action to yield an index.")
     }
 
-    pub fn get_action_register(&self, registers: &Registers) -> Option<ActionRegisterPair> {
-        let winning_register = match registers.argmax(ArgmaxInput::All).any() {
+    pub fn q_consts(&self) -> &QConsts {
+        &self.q_consts
+    }
+
+    /// Q-values actions are selected by, one row per register; with double Q-learning, the sum
+    /// of both estimators.
+    pub fn q_values(&self) -> Vec<Vec<f64>> {
+        if !self.q_consts.double_q {
+            return self.table.clone();
+        }
+
+        self.table
+            .iter()
+            .zip(self.double_table.iter())
+            .map(|(values, others)| values.iter().zip(others).map(|(a, b)| a + b).collect())
+            .collect()
+    }
+
+    /// Root mean squared difference between the Q-values of both tables, over the cells they
+    /// have in common.
+    pub fn distance(&self, other: &QTable) -> f64 {
+        let differences = self
+            .q_values()
+            .iter()
+            .zip(other.q_values().iter())
+            .flat_map(|(values, others)| {
+                values
+                    .iter()
+                    .zip(others.iter())
+                    .map(|(a, b)| (a - b).powi(2))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        if differences.is_empty() {
+            return 0.;
+        }
+
+        (differences.iter().sum::<f64>() / differences.len() as f64).sqrt()
+    }
+
+    pub fn summary(&self) -> Vec<RegisterQSummary> {
+        self.q_values()
+            .iter()
+            .enumerate()
+            .map(|(register, values)| RegisterQSummary {
+                register,
+                best_action: self.action_argmax(register),
+                min_value: values.iter().copied().fold(f64::INFINITY, f64::min),
+                max_value: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            })
+            .collect()
+    }
+
+    /// Q-values as CSV with a `register` column followed by one `action_<n>` column per action.
+    pub fn to_csv(&self) -> String {
+        let q_values = self.q_values();
+        let n_actions = q_values.first().map_or(0, Vec::len);
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        let header = std::iter::once("register".to_string())
+            .chain((0..n_actions).map(|action| format!("action_{}", action)));
+        writer
+            .write_record(header)
+            .expect("Writing to memory to succeed.");
+
+        for (register, values) in q_values.iter().enumerate() {
+            let record = std::iter::once(register.to_string())
+                .chain(values.iter().map(|value| value.to_string()));
+            writer
+                .write_record(record)
+                .expect("Writing to memory to succeed.");
+        }
+
+        String::from_utf8(writer.into_inner().expect("Writing to memory to succeed."))
+            .expect("CSV of numbers to be valid UTF-8.")
+    }
+
+    pub fn get_action_register(&mut self, registers: &Registers) -> Option<ActionRegisterPair> {
+        let range = if self.q_consts.output_registers_only {
+            ArgmaxInput::OutputRegisters
+        } else {
+            ArgmaxInput::All
+        };
+
+        let winning_register = match registers.argmax(range).any_with(self.exploration()) {
             ActionRegister::Value(register) => register,
             _ => {
                 return None;
             }
         };
 
-        let prob = generator().gen_range((0.)..(1.));
+        let prob = self.exploration().gen_range((0.)..(1.));
 
-        let winning_action = if prob <= self.q_consts.epsilon_active {
+        let explores = !self.greedy && !self.freeze;
+        let winning_action = if explores && prob <= self.q_consts.epsilon_active {
             self.action_random()
         } else {
             self.action_argmax(winning_register)
@@ -116,29 +398,194 @@ impl QTable {
         current_reward: f64,
         next_action_state: ActionRegisterPair,
     ) {
-        let current_q_value =
-            self.table[current_action_state.register][current_action_state.action];
-        let next_q_value = self.action_argmax(next_action_state.register) as f64;
+        self.learn(
+            current_action_state,
+            current_reward,
+            Some(next_action_state),
+        );
+    }
 
-        let new_q_value = self.q_consts.alpha_active
-            * (current_reward + (self.q_consts.gamma * next_q_value) - current_q_value);
+    /// Updates the last pair of a terminated episode towards its reward alone, since no reward
+    /// follows a terminal state.
+    pub fn update_terminal(
+        &mut self,
+        current_action_state: ActionRegisterPair,
+        current_reward: f64,
+    ) {
+        self.learn(current_action_state, current_reward, None);
+    }
 
-        self.table[current_action_state.register][current_action_state.action] += new_q_value;
+    /// Bootstraps from `next_action_state`, or from nothing after a termination.
+    fn learn(
+        &mut self,
+        current_action_state: ActionRegisterPair,
+        current_reward: f64,
+        next_action_state: Option<ActionRegisterPair>,
+    ) {
+        if self.freeze {
+            return;
+        }
+
+        // With double Q-learning, alternate which estimator is updated; the updated estimator
+        // selects the next action and the other one evaluates it.
+        let update_double_table = self.q_consts.double_q && self.n_updates % 2 == 1;
+        let (selector, evaluator) = if !self.q_consts.double_q {
+            (&self.table, &self.table)
+        } else if update_double_table {
+            (&self.double_table, &self.table)
+        } else {
+            (&self.table, &self.double_table)
+        };
+
+        // SARSA bootstraps from the action actually taken next, exploratory or not.
+        let next_q_value = next_action_state.map_or(0., |next_action_state| {
+            let next_action = match self.q_consts.mode {
+                QLearningMode::QLearning => {
+                    float_ops::argmax(selector[next_action_state.register].iter().copied())
+                        .expect("Available action to yield an index.")
+                }
+                QLearningMode::Sarsa => next_action_state.action,
+            };
+
+            evaluator[next_action_state.register][next_action]
+        });
+
+        let table = if update_double_table {
+            &mut self.double_table
+        } else {
+            &mut self.table
+        };
+
+        let current_q_value = table[current_action_state.register][current_action_state.action];
+
+        let td_error = current_reward + (self.q_consts.gamma * next_q_value) - current_q_value;
+
+        // Without traces (lambda = 0) only the current pair is updated, skipping the sweep over
+        // the whole table.
+        if self.q_consts.lambda == 0. || self.traces.0.is_empty() {
+            table[current_action_state.register][current_action_state.action] +=
+                self.q_consts.alpha_active * td_error;
+        } else {
+            // Accumulating traces.
+            self.traces.0[current_action_state.register][current_action_state.action] += 1.;
+
+            let decay = self.q_consts.gamma * self.q_consts.lambda;
+
+            for (q_values, traces) in table.iter_mut().zip(self.traces.0.iter_mut()) {
+                for (q_value, trace) in q_values.iter_mut().zip(traces.iter_mut()) {
+                    *q_value += self.q_consts.alpha_active * td_error * *trace;
+                    *trace *= decay;
+                }
+            }
+        }
 
-        if !self.freeze {
+        self.n_updates += 1;
+        if self.q_consts.decay_mode == DecayMode::PerUpdate {
             self.q_consts.decay();
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Derivative)]
-#[derivative(PartialEq, PartialOrd, Ord, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QProgram {
-    #[derivative(PartialEq = "ignore", PartialOrd = "ignore", Ord = "ignore")]
     pub q_table: QTable,
     pub program: Program,
 }
 
+/// Comparison policy: equality and ordering go through the program, i.e. its fitness then its
+/// id, so ranking is unaffected by what the Q-table has learned. Two individuals are only equal
+/// when they share an id (a clone and its parent), never because their instructions match.
+/// Use `structural_eq` to compare instructions and `behavioral_distance` to tell apart
+/// individuals whose learned tables differ.
+impl PartialEq for QProgram {
+    fn eq(&self, other: &Self) -> bool {
+        self.program == other.program
+    }
+}
+
+impl Eq for QProgram {}
+
+impl Ord for QProgram {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.program.cmp(&other.program)
+    }
+}
+
+impl PartialOrd for QProgram {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl QProgram {
+    /// Whether both individuals run the same instructions, whatever their Q-tables learned.
+    pub fn structural_eq(&self, other: &QProgram) -> bool {
+        self.program.instructions == other.program.instructions
+    }
+
+    /// Edit distance between the instruction sequences plus the root mean squared difference
+    /// between the Q-tables; zero only for structurally equal individuals with identical tables.
+    pub fn behavioral_distance(&self, other: &QProgram) -> f64 {
+        edit_distance(&self.program.instructions, &other.program.instructions) as f64
+            + self.q_table.distance(&other.q_table)
+    }
+
+    /// Derives the exploration seed from the run seed and the program id. Called whenever the
+    /// id changes.
+    pub fn reseed_exploration(&mut self) {
+        self.q_table.exploration_seed = derive_seed(run_seed(), self.program.id.as_u128());
+        self.q_table.restart_exploration();
+    }
+}
+
+impl Genotype for QProgram {
+    fn instructions(&self) -> &[Instruction] {
+        &self.program.instructions
+    }
+
+    fn registers(&self) -> &Registers {
+        &self.program.registers
+    }
+
+    fn n_actions(&self) -> Option<usize> {
+        self.q_table.table.first().map(Vec::len)
+    }
+
+    fn unfreeze(&mut self) {
+        self.q_table.freeze = false;
+    }
+
+    fn end_generation(&mut self) {
+        if self.q_table.q_consts.decay_mode == DecayMode::PerGeneration && !self.q_table.freeze {
+            self.q_table.q_consts.decay();
+        }
+    }
+
+    fn adaptive_rates(&self) -> Option<&AdaptiveRates> {
+        self.program.adaptive_rates()
+    }
+
+    fn set_adaptive_rates(&mut self, rates: AdaptiveRates) {
+        self.program.set_adaptive_rates(rates)
+    }
+
+    fn provenance(&self) -> Option<&Provenance> {
+        self.program.provenance()
+    }
+
+    fn set_provenance(&mut self, provenance: Provenance) {
+        self.program.set_provenance(provenance)
+    }
+
+    fn id(&self) -> Option<Uuid> {
+        Some(self.program.id)
+    }
+
+    fn distance(&self, other: &Self) -> f64 {
+        self.behavioral_distance(other)
+    }
+}
+
 impl Freeze<QProgram> for FreezeEngine {
     fn freeze(item: &mut QProgram) {
         FreezeEngine::freeze(&mut item.q_table);
@@ -148,6 +595,8 @@ impl Freeze<QProgram> for FreezeEngine {
 impl Reset<QProgram> for ResetEngine {
     fn reset(item: &mut QProgram) {
         ResetEngine::reset(&mut item.program);
+        ResetEngine::reset(&mut item.q_table.traces);
+        item.q_table.restart_exploration();
     }
 }
 
@@ -184,12 +633,21 @@ impl<T: RlState> Fitness<QProgram, T, ()> for FitnessEngine {
             let reward = state.execute_action(current_action_state.action);
             score += reward;
 
-            if state.is_terminal() {
+            let status = state.status();
+            if status == EpisodeStatus::Terminated {
+                if !program.q_table.is_frozen() {
+                    program
+                        .q_table
+                        .update_terminal(current_action_state, reward);
+                }
                 break;
             }
 
+            // A truncated episode would have gone on, so it still bootstraps from the state it
+            // was cut off in.
             let next_action_state = match get_action_state(state, program) {
                 Some(action_state) => action_state,
+                None if status == EpisodeStatus::Truncated => break,
                 None => {
                     return f64::NEG_INFINITY;
                 }
@@ -197,12 +655,18 @@ impl<T: RlState> Fitness<QProgram, T, ()> for FitnessEngine {
 
             // We only update when there is a transition.
             // NOTE: Why?
-            if current_action_state.register != next_action_state.register {
+            if !program.q_table.is_frozen()
+                && current_action_state.register != next_action_state.register
+            {
                 program
                     .q_table
                     .update(current_action_state, reward, next_action_state)
             }
 
+            if status == EpisodeStatus::Truncated {
+                break;
+            }
+
             current_action_state = next_action_state;
         }
 
@@ -219,14 +683,34 @@ impl<T: RlState> Fitness<QProgram, T, ()> for FitnessEngine {
 
 impl Breed<QProgram> for BreedEngine {
     fn two_point_crossover(mate_1: &QProgram, mate_2: &QProgram) -> (QProgram, QProgram) {
-        let (_child_1_program, _child_2_program) =
-            BreedEngine::two_point_crossover(&mate_1.program, &mate_2.program);
+        BreedEngine::crossover(mate_1, mate_2, CrossoverKind::TwoPoint)
+    }
+
+    fn one_point_crossover(mate_1: &QProgram, mate_2: &QProgram) -> (QProgram, QProgram) {
+        BreedEngine::crossover(mate_1, mate_2, CrossoverKind::OnePoint)
+    }
+
+    fn uniform_crossover(
+        mate_1: &QProgram,
+        mate_2: &QProgram,
+        swap_probability: f64,
+    ) -> (QProgram, QProgram) {
+        BreedEngine::crossover(mate_1, mate_2, CrossoverKind::Uniform(swap_probability))
+    }
+
+    fn crossover(
+        mate_1: &QProgram,
+        mate_2: &QProgram,
+        kind: CrossoverKind,
+    ) -> (QProgram, QProgram) {
+        let (child_1_program, child_2_program) =
+            BreedEngine::crossover(&mate_1.program, &mate_2.program, kind);
 
         let mut child_1 = mate_1.clone();
         let mut child_2 = mate_2.clone();
 
-        child_1.program = child_1.program;
-        child_2.program = child_2.program;
+        child_1.program = child_1_program;
+        child_2.program = child_2_program;
 
         ResetEngine::reset(&mut child_1.program.id);
         ResetEngine::reset(&mut child_2.program.id);
@@ -237,6 +721,9 @@ impl Breed<QProgram> for BreedEngine {
         ResetEngine::reset(&mut child_1.q_table);
         ResetEngine::reset(&mut child_2.q_table);
 
+        child_1.reseed_exploration();
+        child_2.reseed_exploration();
+
         (child_1, child_2)
     }
 }
@@ -247,16 +734,32 @@ impl Status<QProgram> for StatusEngine {
     }
 
     fn set_fitness(program: &mut QProgram, fitness: f64) {
-        program.program.fitness = fitness;
+        StatusEngine::set_fitness(&mut program.program, fitness);
     }
 
     fn get_fitness(program: &QProgram) -> f64 {
-        program.program.fitness
+        StatusEngine::get_fitness(&program.program)
     }
 
     fn evaluated(item: &QProgram) -> bool {
         StatusEngine::evaluated(&item.program)
     }
+
+    fn effective_length(item: &QProgram) -> usize {
+        StatusEngine::effective_length(&item.program)
+    }
+
+    fn take_saturations(item: &mut QProgram) -> usize {
+        StatusEngine::take_saturations(&mut item.program)
+    }
+
+    fn set_trial_scores(item: &mut QProgram, scores: Vec<f64>) {
+        StatusEngine::set_trial_scores(&mut item.program, scores);
+    }
+
+    fn trial_scores(item: &QProgram) -> Option<&[f64]> {
+        StatusEngine::trial_scores(&item.program)
+    }
 }
 
 impl Mutate<QProgramGeneratorParameters, QProgram> for MutateEngine {
@@ -265,18 +768,29 @@ impl Mutate<QProgramGeneratorParameters, QProgram> for MutateEngine {
         ResetEngine::reset(&mut item.program);
         ResetEngine::reset(&mut item.program.id);
         ResetEngine::reset(&mut item.q_table);
+        item.reseed_exploration();
     }
 }
 
 impl Generate<QProgramGeneratorParameters, QProgram> for GenerateEngine {
     fn generate(using: QProgramGeneratorParameters) -> QProgram {
+        let instruction_parameters = using.program_parameters.instruction_generator_parameters;
         let program = GenerateEngine::generate(using.program_parameters);
-        let q_table = GenerateEngine::generate((
-            using.program_parameters.instruction_generator_parameters,
-            using.consts,
-        ));
+        let mut q_table: QTable = GenerateEngine::generate((instruction_parameters, using.consts));
+
+        if let Some(initial_q_table) = using.initial_q_table {
+            let values = initial_q_table
+                .load(
+                    instruction_parameters.n_registers(),
+                    instruction_parameters.n_actions,
+                )
+                .expect("Failed to load the initial Q-table.");
+            q_table.warm_start(&values);
+        }
 
-        QProgram { q_table, program }
+        let mut q_program = QProgram { q_table, program };
+        q_program.reseed_exploration();
+        q_program
     }
 }
 
@@ -287,6 +801,110 @@ pub struct QProgramGeneratorParameters {
     #[builder(default)]
     #[command(flatten)]
     pub consts: QConsts,
+    /// Saved Q-table (or Q-program, e.g. `best.json`) every generated table starts from, and
+    /// returns to when reset, instead of zeros.
+    #[arg(long)]
+    #[builder(default)]
+    #[serde(default)]
+    pub initial_q_table: Option<InitialQTable>,
+}
+
+/// Path of a saved Q-table generated tables start from. Interned for the lifetime of the process
+/// so generator parameters stay `Copy`; each file is read once.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "PathBuf", into = "PathBuf")]
+pub struct InitialQTable(&'static Path);
+
+/// Q-values of the initial tables read so far, by path.
+static INITIAL_Q_TABLES: OnceLock<Mutex<HashMap<PathBuf, Arc<Vec<Vec<f64>>>>>> = OnceLock::new();
+
+impl InitialQTable {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        InitialQTable(Box::leak(path.into().into_boxed_path()))
+    }
+
+    pub fn path(&self) -> &'static Path {
+        self.0
+    }
+
+    /// Q-values of the saved table, which must have `n_registers` rows of `n_actions` values.
+    pub fn load(&self, n_registers: usize, n_actions: usize) -> LgpResult<Arc<Vec<Vec<f64>>>> {
+        let tables = INITIAL_Q_TABLES.get_or_init(Default::default);
+        let cached = tables.lock().unwrap().get(self.0).cloned();
+
+        let values = match cached {
+            Some(values) => values,
+            None => {
+                let format = Format::from_path(self.0);
+                let table = QTable::load_as(self.0, format)
+                    .or_else(|_| QProgram::load_as(self.0, format).map(|p| p.q_table))?;
+                let values = Arc::new(table.table);
+                tables
+                    .lock()
+                    .unwrap()
+                    .insert(self.0.to_path_buf(), values.clone());
+                values
+            }
+        };
+
+        let n_saved_actions = values.first().map_or(0, Vec::len);
+        if values.len() != n_registers || values.iter().any(|row| row.len() != n_actions) {
+            return Err(LgpError::Config(format!(
+                "Initial Q-table `{}` has {} registers of {} actions, expected {} of {}",
+                self.0.display(),
+                values.len(),
+                n_saved_actions,
+                n_registers,
+                n_actions
+            )));
+        }
+
+        Ok(values)
+    }
+}
+
+impl From<PathBuf> for InitialQTable {
+    fn from(path: PathBuf) -> Self {
+        InitialQTable::new(path)
+    }
+}
+
+impl From<InitialQTable> for PathBuf {
+    fn from(initial_q_table: InitialQTable) -> Self {
+        initial_q_table.0.to_path_buf()
+    }
+}
+
+impl FromStr for InitialQTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(InitialQTable::new(s))
+    }
+}
+
+/// Which action the update bootstraps from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum QLearningMode {
+    /// Off-policy: the greedy next action.
+    #[default]
+    QLearning,
+    /// On-policy: the next action actually taken, including exploratory ones.
+    Sarsa,
+}
+
+/// When `alpha` and `epsilon` decay.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum DecayMode {
+    /// After every Q-table update, so programs acting more decay faster.
+    #[default]
+    PerUpdate,
+    /// Once per generation for every individual, however much it acted.
+    PerGeneration,
 }
 
 #[derive(Debug, Clone, Copy, Args, Serialize, Deserialize, Builder)]
@@ -311,6 +929,31 @@ pub struct QConsts {
     #[arg(long, default_value = "0.001")]
     #[builder(default = "0.001")]
     epsilon_decay: f64,
+    /// Eligibility Trace Decay (Q(λ)), 0 disables traces
+    #[arg(long, default_value = "0.")]
+    #[builder(default = "0.")]
+    #[serde(default)]
+    lambda: f64,
+    /// Double Q-Learning
+    #[arg(long)]
+    #[builder(default = "false")]
+    #[serde(default)]
+    double_q: bool,
+    /// Pick the Q-table state among the output registers only, rather than all registers.
+    #[arg(long)]
+    #[builder(default = "false")]
+    #[serde(default)]
+    output_registers_only: bool,
+    /// Update rule: `q_learning` or `sarsa`.
+    #[arg(long = "q-mode", value_enum, default_value = "q_learning")]
+    #[builder(default)]
+    #[serde(default)]
+    mode: QLearningMode,
+    /// When the learning and exploration factors decay: `per_update` or `per_generation`.
+    #[arg(long, value_enum, default_value = "per_update")]
+    #[builder(default)]
+    #[serde(default)]
+    decay_mode: DecayMode,
 
     /// To allow new programs to start from the new state, we have active
     /// properties to mutuate.
@@ -325,6 +968,59 @@ pub struct QConsts {
     epsilon_active: f64,
 }
 
+impl ProgramShape for QProgramGeneratorParameters {
+    fn program_parameters(&mut self) -> &mut ProgramGeneratorParameters {
+        &mut self.program_parameters
+    }
+}
+
+impl QProgramGeneratorParameters {
+    /// Violations of the program and Q-learning ranges, with fields nested under `prefix`.
+    pub fn violations(&self, prefix: &str) -> Vec<ConfigViolation> {
+        let mut violations = self
+            .program_parameters
+            .violations(&format!("{}.program_parameters", prefix));
+        let consts = self.consts;
+
+        for (name, value) in [
+            ("alpha", consts.alpha),
+            ("gamma", consts.gamma),
+            ("epsilon", consts.epsilon),
+            ("alpha_decay", consts.alpha_decay),
+            ("epsilon_decay", consts.epsilon_decay),
+            ("lambda", consts.lambda),
+        ] {
+            require(
+                &mut violations,
+                (0. ..=1.).contains(&value),
+                &format!("{}.consts.{}", prefix, name),
+                value,
+                "in [0, 1]",
+            );
+        }
+
+        if let Some(initial_q_table) = self.initial_q_table {
+            let instruction_parameters = self.program_parameters.instruction_generator_parameters;
+            let (n_registers, n_actions) = (
+                instruction_parameters.n_registers(),
+                instruction_parameters.n_actions,
+            );
+            require(
+                &mut violations,
+                initial_q_table.load(n_registers, n_actions).is_ok(),
+                &format!("{}.initial_q_table", prefix),
+                initial_q_table.path().display(),
+                &format!(
+                    "a saved Q-table of {} registers and {} actions",
+                    n_registers, n_actions
+                ),
+            );
+        }
+
+        violations
+    }
+}
+
 impl Reset<QConsts> for ResetEngine {
     fn reset(item: &mut QConsts) {
         item.alpha_active = item.alpha;
@@ -342,30 +1038,778 @@ impl QConsts {
             epsilon,
             alpha_decay,
             epsilon_decay,
+            lambda: 0.,
+            double_q: false,
+            output_registers_only: false,
+            mode: QLearningMode::default(),
+            decay_mode: DecayMode::default(),
         }
     }
 
+    /// Constants drawn uniformly from [0, 1), with the other fields at their defaults.
+    pub fn random() -> Self {
+        let mut rng = generator();
+
+        QConsts::new(
+            rng.gen_range(0.0..1.),
+            rng.gen_range(0.0..1.),
+            rng.gen_range(0.0..1.),
+            rng.gen_range(0.0..1.),
+            rng.gen_range(0.0..1.),
+        )
+    }
+
+    /// Learning factor after decay.
+    pub fn alpha_active(&self) -> f64 {
+        self.alpha_active
+    }
+
+    /// Exploration factor after decay.
+    pub fn epsilon_active(&self) -> f64 {
+        self.epsilon_active
+    }
+
     pub fn decay(&mut self) {
         self.alpha_active *= 1. - self.alpha_decay;
         self.epsilon_active *= 1. - self.epsilon_decay
     }
 }
 
+/// The documented defaults: alpha 0.1, gamma 0.9, epsilon 0.05, alpha decay 0.01 and epsilon
+/// decay 0.001, decaying per update; see `QConsts::random` for random constants.
 impl Default for QConsts {
     fn default() -> Self {
-        let alpha = generator().gen_range(0.0..1.);
-        let gamma = generator().gen_range(0.0..1.);
-        let epsilon = generator().gen_range(0.0..1.);
-        let alpha_decay = generator().gen_range(0.0..1.);
-        let epsilon_decay = generator().gen_range(0.0..1.);
-        Self {
-            alpha,
-            gamma,
-            epsilon,
-            alpha_decay,
-            epsilon_decay,
-            alpha_active: alpha,
-            epsilon_active: epsilon_decay,
+        QConsts::new(0.1, 0.9, 0.05, 0.01, 0.001)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        characteristics::Save,
+        instruction::{InstructionGeneratorParametersBuilder, Mode, Op},
+        niching::genotypic_distance,
+    };
+    use crate::problems::test_env;
+    use crate::utils::random::with_seed;
+
+    fn q_table(lambda: f64, double_q: bool) -> QTable {
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_calculation_registers(0)
+            .n_actions(2)
+            .n_inputs(1)
+            .build()
+            .unwrap();
+        let consts = QConstsBuilder::default()
+            .alpha(0.5)
+            .gamma(0.9)
+            .alpha_decay(0.)
+            .epsilon_decay(0.)
+            .lambda(lambda)
+            .double_q(double_q)
+            .build()
+            .unwrap();
+
+        GenerateEngine::generate((instruction_parameters, consts))
+    }
+
+    fn two_step_episode(lambda: f64) -> QTable {
+        let mut q_table = q_table(lambda, false);
+
+        let first = ActionRegisterPair {
+            action: 0,
+            register: 0,
+        };
+        let second = ActionRegisterPair {
+            action: 1,
+            register: 1,
+        };
+
+        q_table.update(first, 0., second);
+        q_table.update(second, 1., first);
+
+        q_table
+    }
+
+    #[test]
+    fn given_two_step_episode_when_lambda_is_one_then_reward_propagates_to_first_pair() {
+        let q_table = two_step_episode(1.);
+
+        assert!(q_table.table[0][0] > 0.);
+        assert!(q_table.table[1][1] > 0.);
+    }
+
+    #[test]
+    fn given_two_step_episode_when_lambda_is_zero_then_first_pair_is_unchanged() {
+        let q_table = two_step_episode(0.);
+
+        assert_eq!(q_table.table[0][0], 0.);
+        assert!(q_table.table[1][1] > 0.);
+        assert!(q_table.traces.0.iter().flatten().all(|trace| *trace == 0.));
+    }
+
+    #[test]
+    fn given_double_q_when_updated_then_tables_alternate() {
+        let mut q_table = q_table(0., true);
+        let pair = ActionRegisterPair {
+            action: 0,
+            register: 0,
+        };
+
+        q_table.update(pair, 1., pair);
+        assert_eq!(q_table.n_updates, 1);
+        assert_eq!(q_table.table[0][0], 0.5);
+        assert_eq!(q_table.double_table[0][0], 0.);
+
+        q_table.update(pair, 1., pair);
+        assert_eq!(q_table.n_updates, 2);
+        assert_eq!(q_table.table[0][0], 0.5);
+        assert!(q_table.double_table[0][0] > 0.);
+    }
+
+    #[test]
+    fn given_double_q_disabled_when_updated_then_only_primary_table_is_used() {
+        let mut q_table = q_table(0., false);
+        let current = ActionRegisterPair {
+            action: 0,
+            register: 0,
+        };
+        let next = ActionRegisterPair {
+            action: 0,
+            register: 1,
+        };
+
+        q_table.update(current, 1., next);
+        q_table.update(current, 1., next);
+
+        assert!(q_table.double_table.is_empty());
+        assert_eq!(q_table.table[0][0], 0.5 + 0.5 * (1. - 0.5));
+    }
+
+    #[test]
+    fn given_hand_constructed_table_when_updated_then_bellman_target_uses_max_q_value() {
+        let mut q_table = q_table(0., false);
+        q_table.table = vec![vec![1., 2.], vec![3., 5.]];
+
+        let current = ActionRegisterPair {
+            action: 0,
+            register: 0,
+        };
+        let next = ActionRegisterPair {
+            action: 0,
+            register: 1,
+        };
+
+        q_table.update(current, 1., next);
+
+        // Q(s, a) + alpha * (r + gamma * max_a' Q(s', a') - Q(s, a))
+        let expected = 1. + 0.5 * (1. + 0.9 * 5. - 1.);
+        assert_eq!(q_table.table[0][0], expected);
+    }
+
+    fn always_exploring_episode(mode: QLearningMode) -> QTable {
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_calculation_registers(0)
+            .n_actions(2)
+            .n_inputs(1)
+            .build()
+            .unwrap();
+        let consts = QConstsBuilder::default()
+            .alpha(0.5)
+            .gamma(0.9)
+            .epsilon(1.)
+            .alpha_decay(0.)
+            .epsilon_decay(0.)
+            .mode(mode)
+            .build()
+            .unwrap();
+        let mut q_table: QTable = GenerateEngine::generate((instruction_parameters, consts));
+        q_table.table = vec![vec![1., 2.], vec![3., 5.]];
+
+        // Both next actions are exploratory: neither is the greedy action of its register.
+        let first = ActionRegisterPair {
+            action: 0,
+            register: 0,
+        };
+        let second = ActionRegisterPair {
+            action: 0,
+            register: 1,
+        };
+        let third = ActionRegisterPair {
+            action: 1,
+            register: 0,
+        };
+
+        q_table.update(first, 1., second);
+        q_table.update(second, 0., third);
+
+        q_table
+    }
+
+    #[test]
+    fn given_exploratory_next_actions_when_q_learning_then_greedy_actions_are_bootstrapped() {
+        let q_table = always_exploring_episode(QLearningMode::QLearning);
+
+        // Q(r0, a0) + alpha * (r + gamma * max_a Q(r1, a) - Q(r0, a0))
+        assert_eq!(q_table.table[0][0], 1. + 0.5 * (1. + 0.9 * 5. - 1.));
+        assert_eq!(q_table.table[1][0], 3. + 0.5 * (0. + 0.9 * 3.25 - 3.));
+    }
+
+    #[test]
+    fn given_exploratory_next_actions_when_sarsa_then_taken_actions_are_bootstrapped() {
+        let q_table = always_exploring_episode(QLearningMode::Sarsa);
+
+        // Q(r0, a0) + alpha * (r + gamma * Q(r1, a0) - Q(r0, a0)), with a0 the action taken.
+        assert_eq!(q_table.table[0][0], 1. + 0.5 * (1. + 0.9 * 3. - 1.));
+        assert_eq!(q_table.table[1][0], 3. + 0.5 * (0. + 0.9 * 2. - 3.));
+    }
+
+    #[test]
+    fn given_default_consts_then_active_values_match_configured_values() {
+        let consts = QConsts::default();
+
+        assert_eq!(consts.alpha_active, consts.alpha);
+        assert_eq!(consts.epsilon_active, consts.epsilon);
+        assert_eq!(
+            (consts.alpha, consts.gamma, consts.epsilon),
+            (0.1, 0.9, 0.05)
+        );
+        assert_eq!(consts.decay_mode, DecayMode::PerUpdate);
+        // Every constructor picks the Q-table state among all registers unless asked otherwise.
+        assert!(!consts.output_registers_only);
+        assert!(!QConsts::new(0.5, 0.5, 0.5, 0., 0.).output_registers_only);
+
+        let random = QConsts::random();
+        assert_eq!(random.alpha_active, random.alpha);
+        assert_eq!(random.epsilon_active, random.epsilon);
+    }
+
+    #[test]
+    fn given_each_decay_mode_when_updating_and_ending_generations_then_epsilon_decays_as_configured(
+    ) {
+        let decayed = |decay_mode: DecayMode, n_updates: usize, n_generations: usize| {
+            let mut q_program: QProgram =
+                GenerateEngine::generate(test_env::CorridorQEngine::program_parameters(1));
+            q_program.q_table.q_consts.epsilon_decay = 0.1;
+            q_program.q_table.q_consts.decay_mode = decay_mode;
+            let pair = ActionRegisterPair {
+                action: 0,
+                register: 0,
+            };
+
+            for _ in 0..n_updates {
+                q_program.q_table.update(pair, 1., pair);
+            }
+            for _ in 0..n_generations {
+                q_program.end_generation();
+            }
+
+            q_program.q_table.q_consts().epsilon_active()
+        };
+        let expected = |k: i32| 0.05 * 0.9f64.powi(k);
+
+        assert!((decayed(DecayMode::PerUpdate, 3, 0) - expected(3)).abs() < 1e-12);
+        assert!((decayed(DecayMode::PerUpdate, 3, 5) - expected(3)).abs() < 1e-12);
+        assert!((decayed(DecayMode::PerGeneration, 0, 4) - expected(4)).abs() < 1e-12);
+        assert!((decayed(DecayMode::PerGeneration, 10, 4) - expected(4)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn given_q_programs_when_two_point_crossover_then_children_use_crossed_programs() {
+        let instruction_generator_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_calculation_registers(4)
+            .n_actions(4)
+            .n_inputs(8)
+            .build()
+            .unwrap();
+        let program_parameters = ProgramGeneratorParameters {
+            max_instructions: 100,
+            min_instructions: 1,
+            instruction_generator_parameters,
+        };
+        let parameters = QProgramGeneratorParametersBuilder::default()
+            .program_parameters(program_parameters)
+            .consts(QConstsBuilder::default().build().unwrap())
+            .build()
+            .unwrap();
+
+        for seed in 0..10 {
+            let mut mate_1: QProgram = GenerateEngine::generate(parameters);
+            let mut mate_2: QProgram = GenerateEngine::generate(parameters);
+
+            mate_1.q_table.table[0][0] = 1.;
+            mate_2.q_table.table[0][0] = 1.;
+
+            // The same cut points cross the bare programs.
+            let (child_1, child_2) =
+                with_seed(seed, || BreedEngine::two_point_crossover(&mate_1, &mate_2));
+            let (program_1, program_2) = with_seed(seed, || {
+                BreedEngine::two_point_crossover(&mate_1.program, &mate_2.program)
+            });
+
+            assert_eq!(child_1.program.instructions, program_1.instructions);
+            assert_eq!(child_2.program.instructions, program_2.instructions);
+
+            for child in [&child_1, &child_2] {
+                assert_ne!(child.program.id, mate_1.program.id);
+                assert_ne!(child.program.id, mate_2.program.id);
+
+                assert!(child
+                    .q_table
+                    .table
+                    .iter()
+                    .flatten()
+                    .all(|value| *value == 0.));
+            }
+
+            assert_ne!(child_1.program.id, child_2.program.id);
+        }
+    }
+
+    #[derive(Default)]
+    struct Corridor {
+        position: usize,
+        actions: Vec<usize>,
+    }
+
+    impl State for Corridor {
+        fn get_value(&self, _at_idx: usize) -> f64 {
+            self.position as f64
+        }
+
+        fn execute_action(&mut self, action: usize) -> f64 {
+            self.actions.push(action);
+            self.position += 1;
+            1.
+        }
+
+        fn get(&mut self) -> Option<&mut Self> {
+            if self.position >= 20 {
+                return None;
+            }
+
+            Some(self)
+        }
+    }
+
+    impl RlState for Corridor {
+        fn is_terminal(&mut self) -> bool {
+            self.position >= 20
+        }
+
+        fn get_initial_state(&self) -> Vec<f64> {
+            vec![0.]
+        }
+    }
+
+    fn exploring_program() -> QProgram {
+        let instruction_generator_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_calculation_registers(1)
+            .n_actions(2)
+            .n_inputs(1)
+            .build()
+            .unwrap();
+        let program_parameters = ProgramGeneratorParameters {
+            max_instructions: 1,
+            min_instructions: 1,
+            instruction_generator_parameters,
+        };
+        let consts = QConstsBuilder::default()
+            .epsilon(1.)
+            .epsilon_decay(0.)
+            .build()
+            .unwrap();
+        let parameters = QProgramGeneratorParametersBuilder::default()
+            .program_parameters(program_parameters)
+            .consts(consts)
+            .build()
+            .unwrap();
+
+        let mut program: QProgram = GenerateEngine::generate(parameters);
+        program.program.instructions = vec![Instruction::new(0, 0, Mode::External, Op::Add, 1.)];
+        program
+    }
+
+    fn explore(program: &QProgram) -> Vec<usize> {
+        let mut program = program.clone();
+        let mut corridor = Corridor::default();
+
+        ResetEngine::reset(&mut program);
+        <FitnessEngine as Fitness<QProgram, Corridor, ()>>::eval_fitness(
+            &mut program,
+            &mut corridor,
+        );
+
+        corridor.actions
+    }
+
+    #[test]
+    fn given_same_individual_when_evaluated_at_different_positions_then_exploration_is_identical() {
+        let program = exploring_program();
+        let first = explore(&program);
+
+        // Consume the global generator as if other individuals had been evaluated first.
+        for _ in 0..100 {
+            generator().gen::<u64>();
         }
+
+        assert_eq!(first.len(), 20);
+        assert_eq!(first, explore(&program));
+    }
+
+    #[test]
+    fn given_individuals_with_different_ids_then_exploration_differs() {
+        let program = exploring_program();
+
+        let mut other = program.clone();
+        ResetEngine::reset(&mut other.program.id);
+        other.reseed_exploration();
+
+        assert_ne!(
+            program.q_table.exploration_seed,
+            other.q_table.exploration_seed
+        );
+        assert_ne!(explore(&program), explore(&other));
+    }
+
+    #[test]
+    fn given_q_programs_when_compared_then_q_tables_are_ignored() {
+        let mut unevaluated = exploring_program();
+        unevaluated.program.id = Uuid::from_u128(1);
+        ResetEngine::reset(&mut unevaluated);
+
+        let mut worse = exploring_program();
+        worse.program.id = Uuid::from_u128(2);
+        StatusEngine::set_fitness(&mut worse, f64::NEG_INFINITY);
+
+        let mut better = exploring_program();
+        better.program.id = Uuid::from_u128(3);
+        StatusEngine::set_fitness(&mut better, 1.);
+
+        let mut learned = better.clone();
+        learned.q_table.table[0][0] = 1.;
+        assert_eq!(learned, better);
+        assert_eq!(learned.cmp(&better), std::cmp::Ordering::Equal);
+
+        let mut tied = better.clone();
+        tied.program.id = Uuid::from_u128(4);
+        assert!(better > tied);
+
+        let mut population = vec![
+            tied.clone(),
+            unevaluated.clone(),
+            better.clone(),
+            worse.clone(),
+        ];
+        population.sort_by(|a, b| b.cmp(a));
+        let ids = population
+            .iter()
+            .map(|program| program.program.id.as_u128())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![3, 4, 2, 1]);
+    }
+
+    #[test]
+    fn given_q_programs_differing_only_in_q_tables_then_distance_separates_them_but_not_ranking() {
+        let mut program = exploring_program();
+        StatusEngine::set_fitness(&mut program, 1.);
+        let mut other = exploring_program();
+        StatusEngine::set_fitness(&mut other, 1.);
+
+        assert!(program.structural_eq(&other));
+        assert_eq!(program.behavioral_distance(&other), 0.);
+
+        let mut learned = other.clone();
+        learned.q_table.table[0][0] = 2.;
+        assert!(program.structural_eq(&learned));
+        assert!(program.behavioral_distance(&learned) > 0.);
+        assert_eq!(
+            program.behavioral_distance(&learned),
+            learned.behavioral_distance(&program)
+        );
+        assert!(genotypic_distance(&program, &learned) > 0.);
+
+        let ranked = |population: &[QProgram]| {
+            let mut population = population.to_vec();
+            population.sort_by(|a, b| b.cmp(a));
+            population
+                .iter()
+                .map(|program| program.program.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ranked(&[program.clone(), other]),
+            ranked(&[program.clone(), learned.clone()])
+        );
+
+        let mut rewritten = learned.clone();
+        rewritten.program.instructions = vec![Instruction::new(0, 0, Mode::External, Op::Sub, 1.)];
+        assert!(!rewritten.structural_eq(&learned));
+        assert_eq!(rewritten.behavioral_distance(&learned), 1.);
+    }
+
+    #[test]
+    fn given_greedy_table_when_evaluated_then_no_random_actions_are_taken() {
+        let mut program = exploring_program();
+        program.q_table.set_greedy(true);
+
+        // All q-values are zero, so the greedy action is always the first one.
+        assert!(explore(&program).iter().all(|action| *action == 0));
+    }
+
+    #[test]
+    fn given_frozen_table_when_updated_then_values_and_consts_are_unchanged() {
+        let mut q_table = q_table(1., false);
+        FreezeEngine::freeze(&mut q_table);
+        let before = q_table.clone();
+
+        let first = ActionRegisterPair {
+            action: 0,
+            register: 0,
+        };
+        let second = ActionRegisterPair {
+            action: 1,
+            register: 1,
+        };
+        q_table.update(first, 1., second);
+        q_table.update(second, 1., first);
+
+        assert_eq!(q_table.table, before.table);
+        assert_eq!(q_table.traces.0, before.traces.0);
+        assert_eq!(q_table.n_updates, 0);
+    }
+
+    #[test]
+    fn given_frozen_program_when_evaluated_twice_then_actions_are_greedy_and_table_is_unchanged() {
+        let mut program = exploring_program();
+        let n_registers = program.q_table.table.len();
+        program.q_table.warm_start(&vec![vec![0., 1.]; n_registers]);
+        FreezeEngine::freeze(&mut program);
+        let before = program.q_table.q_values();
+
+        let mut episodes = vec![];
+        for _ in 0..2 {
+            let mut corridor = Corridor::default();
+            ResetEngine::reset(&mut program);
+            <FitnessEngine as Fitness<QProgram, Corridor, ()>>::eval_fitness(
+                &mut program,
+                &mut corridor,
+            );
+            episodes.push(corridor.actions);
+        }
+
+        // Epsilon is one, so only a frozen table keeps to the greedy action.
+        assert_eq!(episodes[0], episodes[1]);
+        assert!(episodes[0].iter().all(|action| *action == 1));
+        assert_eq!(program.q_table.q_values(), before);
+    }
+
+    #[test]
+    fn given_one_step_episode_when_truncated_then_target_bootstraps_unlike_when_terminated() {
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(2)
+            .n_inputs(1)
+            .build()
+            .unwrap();
+        let consts = QConstsBuilder::default()
+            .alpha(0.5)
+            .gamma(0.9)
+            .alpha_decay(0.)
+            .epsilon_decay(0.)
+            .build()
+            .unwrap();
+        let parameters = QProgramGeneratorParametersBuilder::default()
+            .program_parameters(ProgramGeneratorParameters {
+                max_instructions: 2,
+                min_instructions: 1,
+                instruction_generator_parameters: instruction_parameters,
+            })
+            .consts(consts)
+            .build()
+            .unwrap();
+
+        let mut program: QProgram = GenerateEngine::generate(parameters);
+        // Register 0 wins at the start and register 1 once the corridor has been stepped into.
+        program.program.instructions = vec![
+            Instruction::with_constant(0, Op::Add, 0.25),
+            Instruction::new(1, 0, Mode::External, Op::Add, 1.),
+        ];
+        let n_registers = program.q_table.table.len();
+        program.q_table.warm_start(&vec![vec![0., 1.]; n_registers]);
+        program.q_table.set_greedy(true);
+
+        let value_after = |mut corridor: test_env::Corridor| {
+            let mut program = program.clone();
+            ResetEngine::reset(&mut program);
+            <FitnessEngine as Fitness<QProgram, _, ()>>::eval_fitness(&mut program, &mut corridor);
+            program.q_table.q_values()[0][test_env::RIGHT]
+        };
+
+        // Truncated: 1 + 0.5 * (1 + 0.9 * 1 - 1). Terminated: 1 + 0.5 * (1 - 1).
+        assert!((value_after(test_env::Corridor::new(1, None)) - 1.45).abs() < 1e-12);
+        assert_eq!(value_after(test_env::Corridor::new(10, Some(1))), 1.);
+    }
+
+    #[test]
+    fn given_out_of_range_consts_when_validated_then_each_is_reported() {
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(2)
+            .n_inputs(1)
+            .build()
+            .unwrap();
+        let program_parameters = ProgramGeneratorParameters {
+            max_instructions: 10,
+            min_instructions: 1,
+            instruction_generator_parameters: instruction_parameters,
+        };
+        let consts = QConstsBuilder::default()
+            .alpha(1.5)
+            .gamma(-0.1)
+            .build()
+            .unwrap();
+        let parameters = QProgramGeneratorParameters {
+            program_parameters,
+            consts,
+            initial_q_table: None,
+        };
+
+        let fields = parameters
+            .violations("program_parameters")
+            .into_iter()
+            .map(|violation| violation.field)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            fields,
+            vec![
+                "program_parameters.consts.alpha",
+                "program_parameters.consts.gamma"
+            ]
+        );
+    }
+
+    fn known_table(double_q: bool) -> QTable {
+        let mut q_table = q_table(0., double_q);
+        q_table.table = vec![vec![0.5, -1.], vec![2., 0.25]];
+        if double_q {
+            q_table.double_table = vec![vec![0., 2.], vec![0., 0.]];
+        }
+
+        q_table
+    }
+
+    #[test]
+    fn given_known_values_when_exported_then_csv_is_labelled_by_register_and_action() {
+        assert_eq!(
+            known_table(false).to_csv(),
+            "register,action_0,action_1\n0,0.5,-1\n1,2,0.25\n"
+        );
+        assert_eq!(
+            known_table(true).to_csv(),
+            "register,action_0,action_1\n0,0.5,1\n1,2,0.25\n"
+        );
+    }
+
+    #[test]
+    fn given_known_values_when_summarised_then_best_actions_and_ranges_are_reported() {
+        let summary = known_table(false).summary();
+
+        assert_eq!(
+            summary,
+            vec![
+                RegisterQSummary {
+                    register: 0,
+                    best_action: 0,
+                    min_value: -1.,
+                    max_value: 0.5
+                },
+                RegisterQSummary {
+                    register: 1,
+                    best_action: 0,
+                    min_value: 0.25,
+                    max_value: 2.
+                },
+            ]
+        );
+        assert_eq!(known_table(true).summary()[0].best_action, 1);
+    }
+
+    #[test]
+    fn given_table_when_serialized_then_values_are_labelled_and_read_back() {
+        let q_table = known_table(false);
+        let json = serde_json::to_value(&q_table).unwrap();
+
+        assert_eq!(
+            json["table"][1],
+            serde_json::json!({
+                "register": 1,
+                "actions": [{"action": 0, "value": 2.0}, {"action": 1, "value": 0.25}]
+            })
+        );
+
+        let loaded: QTable = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.table, q_table.table);
+
+        let binary = bincode::serialize(&q_table).unwrap();
+        let loaded: QTable = bincode::deserialize(&binary).unwrap();
+        assert_eq!(loaded.table, q_table.table);
+    }
+
+    #[test]
+    fn given_table_saved_as_bare_arrays_when_loaded_then_values_are_read() {
+        let mut json = serde_json::to_value(known_table(false)).unwrap();
+        json["table"] = serde_json::json!([[1.0, 2.0], [3.0, 4.0]]);
+
+        let loaded: QTable = serde_json::from_value(json).unwrap();
+
+        assert_eq!(loaded.table, vec![vec![1., 2.], vec![3., 4.]]);
+    }
+
+    #[cfg(feature = "plots")]
+    #[test]
+    fn given_known_values_when_plotted_then_heatmap_is_written() {
+        use crate::utils::plotting::{plot_q_table, PlotParameters};
+
+        let output = std::env::temp_dir().join("lgp_q_table").join("q_table.png");
+        plot_q_table(&known_table(false), &output, &PlotParameters::default()).unwrap();
+
+        assert!(std::fs::metadata(&output).unwrap().len() > 0);
+    }
+
+    #[test]
+    fn given_saved_table_when_warm_started_then_programs_carry_and_reset_to_its_values() {
+        let path = std::env::temp_dir()
+            .join(Uuid::new_v4().to_string())
+            .join("q_table.json");
+        known_table(false).save(path.to_str().unwrap()).unwrap();
+
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_calculation_registers(0)
+            .n_actions(2)
+            .n_inputs(1)
+            .build()
+            .unwrap();
+        let parameters = QProgramGeneratorParametersBuilder::default()
+            .program_parameters(ProgramGeneratorParameters {
+                max_instructions: 5,
+                min_instructions: 1,
+                instruction_generator_parameters: instruction_parameters,
+            })
+            .consts(QConstsBuilder::default().build().unwrap())
+            .initial_q_table(Some(InitialQTable::new(&path)))
+            .build()
+            .unwrap();
+        assert!(parameters.violations("program_parameters").is_empty());
+
+        let mut q_program: QProgram = GenerateEngine::generate(parameters);
+        assert_eq!(q_program.q_table.table, known_table(false).table);
+
+        q_program.q_table.table[1][0] = 7.;
+        ResetEngine::reset(&mut q_program.q_table);
+        assert_eq!(q_program.q_table.table, known_table(false).table);
+
+        let error = InitialQTable::new(&path).load(3, 2).unwrap_err();
+        assert!(matches!(error, LgpError::Config(_)));
     }
 }
@@ -5,6 +5,7 @@
 //!
 //! Provides a bootstrapped implementation to help you start exploring problems immediately.
 pub mod core;
+pub mod data;
 pub mod extensions;
 pub mod problems;
 pub mod utils;
@@ -1,7 +1,41 @@
 use clap::Parser;
 use lgp::core::config::Actuator;
+#[cfg(feature = "file-logging")]
+use lgp::utils::logging::init_file_tracing;
+use lgp::utils::logging::init_tracing;
+use lgp::utils::shutdown::install_signal_handler;
+#[cfg(feature = "file-logging")]
+use tracing_appender::non_blocking::WorkerGuard;
+
+/// Logs to daily rotated files under `LGP_LOG_DIR` when it is set, and to stderr otherwise.
+#[cfg(feature = "file-logging")]
+fn init_logging() -> Option<WorkerGuard> {
+    match std::env::var_os("LGP_LOG_DIR") {
+        Some(directory) => init_file_tracing(directory),
+        None => {
+            init_tracing();
+            None
+        }
+    }
+}
 
 fn main() {
+    // Buffered log lines are flushed when the guard is dropped.
+    #[cfg(feature = "file-logging")]
+    let log_guard = init_logging();
+    #[cfg(not(feature = "file-logging"))]
+    init_tracing();
+
+    if let Err(error) = install_signal_handler() {
+        eprintln!("Runs cannot be interrupted gracefully: {}", error);
+    }
+
     let mut cli = Actuator::parse();
-    cli.run();
+
+    if let Err(error) = cli.run() {
+        eprintln!("{}", error);
+        #[cfg(feature = "file-logging")]
+        drop(log_guard);
+        std::process::exit(error.exit_code());
+    }
 }
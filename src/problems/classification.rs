@@ -0,0 +1,460 @@
+use std::{fmt, str::FromStr};
+
+use clap::ValueEnum;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        environment::State,
+        program::Program,
+        registers::{ActionRegister, ArgmaxInput},
+    },
+    data::dataset::Dataset,
+    utils::error::{LgpError, LgpResult},
+};
+
+/// Score classification programs evolve against.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum FitnessMetric {
+    #[default]
+    Accuracy,
+    /// Unweighted mean of the per-class F1 scores, for imbalanced datasets.
+    MacroF1,
+}
+
+/// How much a correct prediction of each class counts towards accuracy, so evolution on
+/// imbalanced datasets does not collapse to the majority class.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassWeights {
+    #[default]
+    Uniform,
+    /// Inverse class frequency, `n_rows / (n_classes * n_class_rows)`, which turns accuracy into
+    /// balanced accuracy.
+    Balanced,
+    /// One weight per class, in label order.
+    Manual(Vec<f64>),
+}
+
+impl ClassWeights {
+    /// Weight of every class of `dataset`, in label order.
+    pub fn resolve(&self, dataset: &Dataset) -> LgpResult<Vec<f64>> {
+        let n_classes = dataset.n_classes();
+
+        match self {
+            ClassWeights::Uniform => Ok(vec![1.; n_classes]),
+            ClassWeights::Balanced => Ok(dataset
+                .class_indices()
+                .iter()
+                .map(|rows| match rows.len() {
+                    0 => 0.,
+                    n_rows => dataset.len() as f64 / (n_classes * n_rows) as f64,
+                })
+                .collect()),
+            ClassWeights::Manual(weights) if weights.len() != n_classes => {
+                Err(LgpError::Config(format!(
+                    "class_weights has {} weights but the dataset has {} classes.",
+                    weights.len(),
+                    n_classes
+                )))
+            }
+            ClassWeights::Manual(weights) => Ok(weights.clone()),
+        }
+    }
+
+    /// Manual weights must be finite and non-negative.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            ClassWeights::Manual(weights) => weights
+                .iter()
+                .all(|weight| weight.is_finite() && *weight >= 0.),
+            _ => true,
+        }
+    }
+}
+
+impl FromStr for ClassWeights {
+    type Err = String;
+
+    /// Parses `uniform`, `balanced` or comma-separated weights such as `1,9`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "uniform" => Ok(ClassWeights::Uniform),
+            "balanced" => Ok(ClassWeights::Balanced),
+            other => other
+                .split(',')
+                .map(|weight| weight.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map(ClassWeights::Manual)
+                .map_err(|_| format!("Unknown class weights `{}`", other)),
+        }
+    }
+}
+
+impl fmt::Display for ClassWeights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClassWeights::Uniform => write!(f, "uniform"),
+            ClassWeights::Balanced => write!(f, "balanced"),
+            ClassWeights::Manual(weights) => write!(f, "{}", weights.iter().join(",")),
+        }
+    }
+}
+
+/// A classification state whose current row knows its class.
+pub trait LabelledState: State {
+    /// Class of the current row.
+    fn label(&self) -> usize;
+
+    fn n_classes(&self) -> usize;
+
+    fn fitness_metric(&self) -> FitnessMetric {
+        FitnessMetric::Accuracy
+    }
+
+    /// What a correct prediction of `class` counts towards accuracy.
+    fn class_weight(&self, _class: usize) -> f64 {
+        1.
+    }
+}
+
+/// Runs `program` on every row of `states` and pairs each label with the predicted class.
+///
+/// Stops at the first row whose output registers overflow or tie, returning the rows classified
+/// so far and `true`.
+pub fn classify<T>(program: &mut Program, states: &mut T) -> (Vec<(usize, usize)>, bool)
+where
+    T: LabelledState,
+{
+    let mut predictions = vec![];
+
+    while let Some(state) = states.get() {
+        program.run(state);
+
+        match program.registers.argmax(ArgmaxInput::OutputRegisters).one() {
+            ActionRegister::Overflow => return (predictions, true),
+            ActionRegister::Value(predicted_class) => {
+                predictions.push((state.label(), predicted_class));
+                state.execute_action(predicted_class);
+            }
+        };
+    }
+
+    (predictions, false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClassMetrics {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    /// Number of rows of the class.
+    pub support: usize,
+}
+
+/// Per-class breakdown of a classification program's predictions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassificationReport {
+    /// `confusion_matrix[label][predicted]` counts the rows of class `label` predicted as
+    /// `predicted`.
+    pub confusion_matrix: Vec<Vec<usize>>,
+    pub classes: Vec<ClassMetrics>,
+    pub accuracy: f64,
+    /// Mean F1 over the classes that occur or are predicted.
+    pub macro_f1: f64,
+    /// The output registers overflowed, so rows after the counted ones were not classified.
+    pub overflowed: bool,
+}
+
+impl ClassificationReport {
+    /// Builds the report of `(label, predicted)` pairs over at least `n_classes` classes.
+    /// Precision, recall and F1 are 0 when undefined; accuracy is NaN without predictions.
+    pub fn new(n_classes: usize, predictions: &[(usize, usize)], overflowed: bool) -> Self {
+        let n_classes = predictions
+            .iter()
+            .map(|(label, predicted)| label.max(predicted) + 1)
+            .fold(n_classes, usize::max);
+
+        let mut confusion_matrix = vec![vec![0; n_classes]; n_classes];
+        for (label, predicted) in predictions {
+            confusion_matrix[*label][*predicted] += 1;
+        }
+
+        let ratio = |numerator: usize, denominator: usize| match denominator {
+            0 => 0.,
+            _ => numerator as f64 / denominator as f64,
+        };
+
+        let classes = (0..n_classes)
+            .map(|class| {
+                let true_positives = confusion_matrix[class][class];
+                let support = confusion_matrix[class].iter().sum();
+                let n_predicted = confusion_matrix.iter().map(|row| row[class]).sum();
+
+                let precision = ratio(true_positives, n_predicted);
+                let recall = ratio(true_positives, support);
+                let f1 = match precision + recall {
+                    sum if sum == 0. => 0.,
+                    sum => 2. * precision * recall / sum,
+                };
+
+                ClassMetrics {
+                    precision,
+                    recall,
+                    f1,
+                    support,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let n_correct = (0..n_classes).map(|class| confusion_matrix[class][class]);
+        let accuracy = n_correct.sum::<usize>() as f64 / predictions.len() as f64;
+
+        let observed = (0..n_classes)
+            .filter(|class| {
+                classes[*class].support > 0 || confusion_matrix.iter().any(|row| row[*class] > 0)
+            })
+            .map(|class| classes[class].f1)
+            .collect::<Vec<_>>();
+        let macro_f1 = observed.iter().sum::<f64>() / observed.len().max(1) as f64;
+
+        ClassificationReport {
+            confusion_matrix,
+            classes,
+            accuracy,
+            macro_f1,
+            overflowed,
+        }
+    }
+
+    /// Accuracy counting every row by the weight of its class; plain accuracy for unit weights.
+    pub fn weighted_accuracy(&self, weight: impl Fn(usize) -> f64) -> f64 {
+        let (correct, total) =
+            self.classes
+                .iter()
+                .enumerate()
+                .fold((0., 0.), |(correct, total), (class, metrics)| {
+                    (
+                        correct + weight(class) * self.confusion_matrix[class][class] as f64,
+                        total + weight(class) * metrics.support as f64,
+                    )
+                });
+
+        correct / total
+    }
+}
+
+/// Classifies every row of `states` like the classification fitness does, but reports the
+/// confusion matrix and per-class metrics instead of a single score.
+pub fn evaluate_detailed<T>(program: &mut Program, states: &mut T) -> ClassificationReport
+where
+    T: LabelledState,
+{
+    let (predictions, overflowed) = classify(program, states);
+
+    ClassificationReport::new(states.n_classes(), &predictions, overflowed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{
+            engines::{
+                fitness_engine::{Fitness, FitnessEngine},
+                generate_engine::{Generate, GenerateEngine},
+                reset_engine::{Reset, ResetEngine},
+            },
+            instruction::{Instruction, InstructionGeneratorParametersBuilder, Mode, Op},
+            program::ProgramGeneratorParametersBuilder,
+        },
+        data::dataset::Dataset,
+        problems::iris::IrisState,
+    };
+
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    fn program(n_inputs: usize, n_actions: usize, instructions: Vec<Instruction>) -> Program {
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(1)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_actions(n_actions)
+                    .n_inputs(n_inputs)
+                    .n_calculation_registers(0)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        let mut program: Program = GenerateEngine::generate(program_parameters);
+        program.instructions = instructions;
+        ResetEngine::reset(&mut program);
+
+        program
+    }
+
+    /// Accumulates the first three inputs into the three output registers, so each row is
+    /// predicted as the class with the largest running sum.
+    fn running_sum_program() -> Program {
+        program(
+            4,
+            3,
+            (0..3)
+                .map(|class| Instruction::new(class, class, Mode::External, Op::Add, 1.))
+                .collect(),
+        )
+    }
+
+    /// Predicted as 0, 1, 2, 2, 0, 1 by the running sum program.
+    fn state(fitness_metric: FitnessMetric) -> IrisState {
+        let rows = [
+            ([1., 0., 0., 0.], 0),
+            ([0., 2., 0., 0.], 1),
+            ([0., 0., 3., 0.], 2),
+            ([0., 0., 1., 0.], 1),
+            ([4., 0., 0., 0.], 0),
+            ([0., 4., 0., 0.], 1),
+        ];
+        let dataset = Dataset::new(
+            rows.iter().map(|(features, _)| features.to_vec()).collect(),
+            rows.iter().map(|(_, label)| *label).collect(),
+            vec![],
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        );
+
+        IrisState::new(dataset).with_fitness_metric(fitness_metric)
+    }
+
+    #[test]
+    fn given_known_predictions_when_evaluated_in_detail_then_every_cell_matches() {
+        let report = evaluate_detailed(
+            &mut running_sum_program(),
+            &mut state(FitnessMetric::Accuracy),
+        );
+
+        assert_eq!(
+            report.confusion_matrix,
+            vec![vec![2, 0, 0], vec![0, 2, 1], vec![0, 0, 1]]
+        );
+        let expected = [
+            (1., 1., 1., 2),
+            (1., 2. / 3., 0.8, 3),
+            (0.5, 1., 2. / 3., 1),
+        ];
+        for (class, (precision, recall, f1, support)) in expected.into_iter().enumerate() {
+            let metrics = report.classes[class];
+            assert!(close(metrics.precision, precision), "class {}", class);
+            assert!(close(metrics.recall, recall), "class {}", class);
+            assert!(close(metrics.f1, f1), "class {}", class);
+            assert_eq!(metrics.support, support, "class {}", class);
+        }
+        assert_eq!(report.accuracy, 5. / 6.);
+        assert!(close(report.macro_f1, (1. + 0.8 + 2. / 3.) / 3.));
+        assert!(!report.overflowed);
+    }
+
+    #[test]
+    fn given_fitness_metric_when_evaluated_then_fitness_is_the_matching_score() {
+        let fitness = |metric| {
+            <FitnessEngine as Fitness<Program, IrisState, ()>>::eval_fitness(
+                &mut running_sum_program(),
+                &mut state(metric),
+            )
+        };
+
+        assert_eq!(fitness(FitnessMetric::Accuracy), 5. / 6.);
+        assert!(close(
+            fitness(FitnessMetric::MacroF1),
+            (1. + 0.8 + 2. / 3.) / 3.
+        ));
+    }
+
+    #[test]
+    fn given_never_predicted_class_when_reported_then_its_scores_are_zero() {
+        let report = ClassificationReport::new(3, &[(0, 0), (1, 1), (2, 1)], false);
+
+        assert_eq!(report.classes[2].precision, 0.);
+        assert_eq!(report.classes[2].recall, 0.);
+        assert_eq!(report.classes[2].f1, 0.);
+        assert!(close(report.macro_f1, (1. + 2. / 3.) / 3.));
+    }
+
+    /// Nine rows of class 0 and one of class 1, where two rows of class 0 look like class 1.
+    fn imbalanced_state(class_weights: ClassWeights) -> IrisState {
+        let rows = [
+            vec![([1., 0.], 0); 7],
+            vec![([0., 1.], 0); 2],
+            vec![([0., 1.], 1)],
+        ]
+        .concat();
+        let dataset = Dataset::new(
+            rows.iter().map(|(features, _)| features.to_vec()).collect(),
+            rows.iter().map(|(_, label)| *label).collect(),
+            vec![],
+            vec!["majority".to_string(), "minority".to_string()],
+        );
+        let class_weights = class_weights.resolve(&dataset).unwrap();
+
+        IrisState::new(dataset).with_class_weights(class_weights)
+    }
+
+    #[test]
+    fn given_imbalanced_dataset_when_weighted_then_balanced_program_outranks_majority_program() {
+        // Always predicts class 0.
+        let majority = || program(2, 2, vec![Instruction::with_constant(0, Op::Add, 1.)]);
+        // Predicts the larger input of every row, so it gets the minority row right at the cost
+        // of two majority rows.
+        let balanced = || {
+            let instructions = (0..2)
+                .flat_map(|class| {
+                    [
+                        Instruction::with_constant(class, Op::Mult, 0.),
+                        Instruction::new(class, class, Mode::External, Op::Add, 1.),
+                    ]
+                })
+                .collect();
+            program(2, 2, instructions)
+        };
+        let fitness = |mut program: Program, class_weights| {
+            <FitnessEngine as Fitness<Program, IrisState, ()>>::eval_fitness(
+                &mut program,
+                &mut imbalanced_state(class_weights),
+            )
+        };
+
+        assert_eq!(fitness(majority(), ClassWeights::Uniform), 0.9);
+        assert_eq!(fitness(balanced(), ClassWeights::Uniform), 0.8);
+
+        assert!(close(fitness(majority(), ClassWeights::Balanced), 0.5));
+        assert!(close(
+            fitness(balanced(), ClassWeights::Balanced),
+            (7. / 9. + 1.) / 2.
+        ));
+
+        let manual = "1, 9".parse::<ClassWeights>().unwrap();
+        assert_eq!(manual, ClassWeights::Manual(vec![1., 9.]));
+        assert!(fitness(balanced(), manual.clone()) > fitness(majority(), manual));
+    }
+
+    #[test]
+    fn given_manual_weights_for_other_classes_when_resolved_then_config_error_is_returned() {
+        let dataset = Dataset::new(vec![vec![0.]], vec![0], vec![], vec!["a".to_string()]);
+
+        assert!(matches!(
+            ClassWeights::Manual(vec![1., 2.]).resolve(&dataset),
+            Err(LgpError::Config(_))
+        ));
+        assert!(!ClassWeights::Manual(vec![-1.]).is_valid());
+        assert_eq!(
+            ClassWeights::Balanced.to_string().parse(),
+            Ok(ClassWeights::Balanced)
+        );
+    }
+}
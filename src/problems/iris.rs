@@ -1,13 +1,19 @@
-use rand::seq::SliceRandom;
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use strum::EnumCount;
-use tokio::runtime::Runtime;
 
 use crate::{
     core::{
         engines::{
             breed_engine::BreedEngine,
-            core_engine::Core,
+            core_engine::{Core, HyperParameters},
             fitness_engine::FitnessEngine,
             freeze_engine::FreezeEngine,
             generate_engine::{Generate, GenerateEngine},
@@ -15,12 +21,22 @@ use crate::{
             reset_engine::{Reset, ResetEngine},
             status_engine::StatusEngine,
         },
-        environment::State,
+        environment::{BehaviorDescriptor, State},
         program::{Program, ProgramGeneratorParameters},
     },
-    utils::{loader::download_and_load_csv, random::generator},
+    data::dataset::{Dataset, DatasetParameters},
+    problems::classification::{evaluate_detailed, FitnessMetric, LabelledState},
+    utils::{
+        error::{require, ConfigViolation, LgpResult},
+        loader::DataLoader,
+        predict::Features,
+        random::generator,
+    },
 };
 
+#[cfg(feature = "download")]
+use crate::utils::{benchmark_tools::create_path, loader::download};
+
 pub const IRIS_DATASET_LINK: &'static str =
     "https://archive.ics.uci.edu/ml/machine-learning-databases/iris/bezdekIris.data";
 
@@ -58,34 +74,92 @@ pub struct IrisInput {
     class: IrisClass,
 }
 
+impl IrisInput {
+    pub const FEATURE_NAMES: [&'static str; 4] =
+        ["sepal_length", "sepal_width", "petal_length", "petal_width"];
+
+    fn features(&self) -> Vec<f64> {
+        vec![
+            self.sepal_length,
+            self.sepal_width,
+            self.petal_length,
+            self.petal_width,
+        ]
+    }
+}
+
+impl From<Vec<IrisInput>> for Dataset {
+    fn from(rows: Vec<IrisInput>) -> Self {
+        let features = rows.iter().map(IrisInput::features).collect();
+        let labels = rows.iter().map(|row| row.class as usize).collect();
+        let feature_names = IrisInput::FEATURE_NAMES.map(String::from).to_vec();
+        let label_names = [
+            IrisClass::Setosa,
+            IrisClass::Versicolour,
+            IrisClass::Virginica,
+        ]
+        .iter()
+        .map(|class| {
+            serde_json::to_value(class)
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+
+        Dataset::new(features, labels, feature_names, label_names)
+    }
+}
+
 pub struct IrisState {
-    data: Vec<IrisInput>,
+    dataset: Dataset,
     idx: usize,
+    fitness_metric: FitnessMetric,
+    /// Weight of every class; uniform when empty.
+    class_weights: Vec<f64>,
+}
+
+impl IrisState {
+    pub fn new(dataset: Dataset) -> Self {
+        IrisState {
+            dataset,
+            idx: 0,
+            fitness_metric: FitnessMetric::default(),
+            class_weights: vec![],
+        }
+    }
+
+    pub fn with_fitness_metric(mut self, fitness_metric: FitnessMetric) -> Self {
+        self.fitness_metric = fitness_metric;
+        self
+    }
+
+    pub fn with_class_weights(mut self, class_weights: Vec<f64>) -> Self {
+        self.class_weights = class_weights;
+        self
+    }
+
+    pub fn dataset(&self) -> &Dataset {
+        &self.dataset
+    }
 }
 
 impl State for IrisState {
     fn get_value(&self, idx: usize) -> f64 {
-        let item = &self.data[self.idx];
-
-        match idx {
-            0 => item.sepal_length,
-            1 => item.sepal_width,
-            2 => item.petal_length,
-            3 => item.petal_width,
-            _ => unreachable!(),
-        }
+        self.dataset.features[self.idx][idx]
     }
 
+    /// Rewards a correct prediction with the weight of its class.
     fn execute_action(&mut self, action: usize) -> f64 {
-        let item = &self.data[self.idx];
+        let correct_class = self.dataset.labels[self.idx];
         self.idx += 1;
-        let correct_class = item.class as usize;
         let is_correct = correct_class == action;
-        is_correct as usize as f64
+        is_correct as usize as f64 * self.class_weight(correct_class)
     }
 
     fn get(&mut self) -> Option<&mut Self> {
-        if self.idx >= self.data.len() {
+        if self.idx >= self.dataset.len() {
             return None;
         }
 
@@ -93,22 +167,160 @@ impl State for IrisState {
     }
 }
 
+impl BehaviorDescriptor for IrisState {}
+
+impl LabelledState for IrisState {
+    fn label(&self) -> usize {
+        self.dataset.labels[self.idx]
+    }
+
+    fn n_classes(&self) -> usize {
+        self.dataset.n_classes()
+    }
+
+    fn fitness_metric(&self) -> FitnessMetric {
+        self.fitness_metric
+    }
+
+    fn class_weight(&self, class: usize) -> f64 {
+        self.class_weights.get(class).copied().unwrap_or(1.)
+    }
+}
+
 impl Reset<IrisState> for ResetEngine {
     fn reset(item: &mut IrisState) {
         item.idx = 0;
     }
 }
 
-impl Generate<(), IrisState> for GenerateEngine {
-    fn generate(_using: ()) -> IrisState {
-        let runtime = Runtime::new().unwrap();
-        let mut data = runtime
-            .block_on(download_and_load_csv(IRIS_DATASET_LINK))
-            .expect("Failed to download and load the dataset");
+/// The dataset shared by every Iris trial.
+#[derive(Debug, Clone)]
+pub struct IrisStateParameters {
+    pub dataset: Arc<Dataset>,
+    pub fitness_metric: FitnessMetric,
+    /// Weight of every class; uniform when empty.
+    pub class_weights: Vec<f64>,
+    /// Each trial draws a stratified sample of about this many rows instead of the whole dataset.
+    pub max_samples_per_trial: Option<usize>,
+    /// Each trial draws a bootstrap resample of this fraction of the rows instead.
+    pub trial_fraction: Option<f64>,
+}
+
+impl IrisStateParameters {
+    pub fn new(dataset: Dataset) -> Self {
+        IrisStateParameters::shared(Arc::new(dataset))
+    }
+
+    fn shared(dataset: Arc<Dataset>) -> Self {
+        IrisStateParameters {
+            dataset,
+            fitness_metric: FitnessMetric::default(),
+            class_weights: vec![],
+            max_samples_per_trial: None,
+            trial_fraction: None,
+        }
+    }
+
+    /// The UCI Iris dataset, from `iris.csv` in the data directory. A missing file is downloaded
+    /// there first.
+    pub fn load() -> LgpResult<Self> {
+        IrisStateParameters::load_from(&data_dir().join(IRIS_FILE_NAME))
+    }
+
+    /// Loads the dataset from `path`, downloading a missing file there first. Every file is only
+    /// parsed once per process.
+    pub fn load_from(path: &Path) -> LgpResult<Self> {
+        let path = path.to_owned();
+
+        let mut datasets = DATASETS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
 
-        data.shuffle(&mut generator());
+        if let Some(dataset) = datasets.get(&path) {
+            return Ok(IrisStateParameters::shared(dataset.clone()));
+        }
+
+        #[cfg(feature = "download")]
+        if !path.exists() {
+            let content = download(IRIS_DATASET_LINK)?;
+            create_path(path.to_str().unwrap(), true)?;
+            std::fs::write(&path, content)?;
+        }
 
-        IrisState { data, idx: 0 }
+        let rows: Vec<IrisInput> = DataLoader::default().from_path(&path)?;
+        let dataset = Arc::new(Dataset::from(rows));
+        datasets.insert(path, dataset.clone());
+
+        Ok(IrisStateParameters::shared(dataset))
+    }
+}
+
+impl DatasetParameters for IrisStateParameters {
+    fn dataset(&self) -> &Dataset {
+        &self.dataset
+    }
+
+    fn with_dataset(&self, dataset: Dataset) -> Self {
+        IrisStateParameters {
+            dataset: Arc::new(dataset),
+            ..self.clone()
+        }
+    }
+
+    fn without_sampling(&self) -> Self {
+        IrisStateParameters {
+            max_samples_per_trial: None,
+            trial_fraction: None,
+            ..self.clone()
+        }
+    }
+}
+
+pub const IRIS_FILE_NAME: &str = "iris.csv";
+/// Share of the rows held out by `IrisEngine::holdout_split`.
+pub const HOLDOUT_RATIO: f64 = 0.2;
+
+/// Datasets parsed so far, by path.
+static DATASETS: OnceLock<Mutex<HashMap<PathBuf, Arc<Dataset>>>> = OnceLock::new();
+
+/// Directory datasets are cached in: `LGP_DATA_DIR`, or `assets/data` when unset.
+pub fn data_dir() -> PathBuf {
+    env::var_os("LGP_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("assets/data"))
+}
+
+impl From<Dataset> for IrisStateParameters {
+    fn from(dataset: Dataset) -> Self {
+        IrisStateParameters::new(dataset)
+    }
+}
+
+impl Default for IrisStateParameters {
+    /// An empty dataset; see `load` for the UCI Iris dataset.
+    fn default() -> Self {
+        IrisStateParameters::new(Dataset::default())
+    }
+}
+
+impl Generate<IrisStateParameters, IrisState> for GenerateEngine {
+    fn generate(using: IrisStateParameters) -> IrisState {
+        let mut dataset = match (using.max_samples_per_trial, using.trial_fraction) {
+            (Some(n), _) => using
+                .dataset
+                .stratified_subsample(n, generator().next_u64()),
+            (None, Some(fraction)) => {
+                let n = (using.dataset.len() as f64 * fraction).round().max(1.) as usize;
+                using.dataset.bootstrap(n, generator().next_u64())
+            }
+            (None, None) => using.dataset.as_ref().clone(),
+        };
+        dataset.shuffle(&mut generator());
+
+        IrisState::new(dataset)
+            .with_fitness_metric(using.fitness_metric)
+            .with_class_weights(using.class_weights)
     }
 }
 
@@ -117,6 +329,7 @@ pub struct IrisEngine;
 
 impl Core for IrisEngine {
     type State = IrisState;
+    type StateParameters = IrisStateParameters;
     type Individual = Program;
     type ProgramParameters = ProgramGeneratorParameters;
     type FitnessMarker = ();
@@ -127,22 +340,921 @@ impl Core for IrisEngine {
     type Mutate = MutateEngine;
     type Status = StatusEngine;
     type Freeze = FreezeEngine;
+
+    fn default_state_parameters() -> LgpResult<IrisStateParameters> {
+        IrisStateParameters::load()
+    }
+
+    fn state_parameters(hp: &HyperParameters<Self>) -> LgpResult<IrisStateParameters> {
+        let parameters = match hp.dataset_path.as_deref() {
+            Some(path) => IrisStateParameters::load_from(path)?,
+            None => IrisStateParameters::load()?,
+        };
+
+        Ok(IrisStateParameters {
+            fitness_metric: hp.fitness_metric,
+            class_weights: hp.class_weights.resolve(&parameters.dataset)?,
+            max_samples_per_trial: hp.max_samples_per_trial,
+            // Whole-dataset trials only differ in row order, so more than one would repeat the
+            // same evaluation.
+            trial_fraction: hp
+                .trial_fraction
+                .or((hp.n_trials > 1 && hp.max_samples_per_trial.is_none()).then_some(1.)),
+            ..parameters
+        })
+    }
+
+    /// Holds out a stratified `HOLDOUT_RATIO` of the rows, evaluated once each.
+    fn holdout_split(
+        state_parameters: &IrisStateParameters,
+        seed: u64,
+    ) -> (IrisStateParameters, IrisStateParameters) {
+        let (train, holdout) = state_parameters
+            .dataset
+            .stratified_split(1. - HOLDOUT_RATIO, seed);
+
+        (
+            state_parameters.with_dataset(train),
+            state_parameters.with_dataset(holdout).without_sampling(),
+        )
+    }
+
+    fn report(
+        best: &Program,
+        state_parameters: &IrisStateParameters,
+    ) -> LgpResult<Option<serde_json::Value>> {
+        let mut state = IrisState::new(state_parameters.dataset.as_ref().clone());
+        let mut best = best.clone();
+        ResetEngine::reset(&mut best);
+
+        let report = evaluate_detailed(&mut best, &mut state);
+
+        Ok(Some(serde_json::to_value(report)?))
+    }
+
+    fn simplify(best: &Program, state_parameters: &IrisStateParameters) -> Option<Program> {
+        let probes = state_parameters
+            .dataset
+            .features
+            .iter()
+            .map(|row| Features(row))
+            .collect::<Vec<_>>();
+
+        Some(best.simplify(&probes))
+    }
+
+    fn validate(hp: &HyperParameters<Self>) -> Vec<ConfigViolation> {
+        let mut violations = hp.program_parameters.violations("program_parameters");
+
+        for (field, is_set) in [
+            ("max_episode_steps", hp.max_episode_steps.is_some()),
+            ("record_trajectory", hp.record_trajectory),
+            ("render", hp.render),
+            ("reward_shaping", !hp.reward_shaping.is_none()),
+            ("normalization", !hp.normalization.is_none()),
+            ("novelty", hp.novelty.is_some()),
+        ] {
+            require(
+                &mut violations,
+                !is_set,
+                field,
+                "set",
+                "unset for classification environments",
+            );
+        }
+        require(
+            &mut violations,
+            hp.class_weights.is_valid(),
+            "class_weights",
+            &hp.class_weights,
+            "finite and non-negative",
+        );
+        require(
+            &mut violations,
+            hp.max_samples_per_trial != Some(0),
+            "max_samples_per_trial",
+            0,
+            "positive",
+        );
+        if let Some(trial_fraction) = hp.trial_fraction {
+            require(
+                &mut violations,
+                trial_fraction > 0. && trial_fraction <= 1.,
+                "trial_fraction",
+                trial_fraction,
+                "in (0, 1]",
+            );
+            require(
+                &mut violations,
+                hp.max_samples_per_trial.is_none(),
+                "trial_fraction",
+                trial_fraction,
+                "unset alongside max_samples_per_trial",
+            );
+        }
+
+        violations
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+    use std::time::Duration;
 
     use itertools::Itertools;
 
-    use crate::core::engines::core_engine::HyperParametersBuilder;
+    use crate::core::characteristics::{Load, Save};
+    use crate::core::engines::core_engine::{
+        CoreIter, GenerationObserver, HyperParametersBuilder, StopReason,
+    };
+    use crate::core::engines::fitness_engine::Objective;
     use crate::core::engines::status_engine::Status;
-    use crate::core::instruction::InstructionGeneratorParametersBuilder;
+    use crate::core::instruction::{InstructionGeneratorParametersBuilder, Op};
+    use crate::core::instructions::OpSet;
     use crate::core::program::ProgramGeneratorParametersBuilder;
-    use crate::utils::benchmark_tools::save_experiment;
+    use crate::core::registers::{RegisterInit, RegisterSaturation};
+    use crate::problems::classification::ClassificationReport;
+    use crate::utils::benchmark_tools::{
+        cross_validate, default_output_root, run_repeats, save_cross_validation, save_experiment,
+        CrossValidation, FitnessCurves, GenerationSummary,
+    };
+    use crate::utils::manifest::{sha256_file, Manifest, RunStatus};
     use crate::utils::misc::VoidResultAnyError;
 
     use super::*;
 
+    fn rows() -> Vec<IrisInput> {
+        [
+            (5.1, 3.5, 1.4, 0.2, IrisClass::Setosa),
+            (7.0, 3.2, 4.7, 1.4, IrisClass::Versicolour),
+            (6.3, 3.3, 6.0, 2.5, IrisClass::Virginica),
+            (4.9, 3.0, 1.4, 0.2, IrisClass::Setosa),
+            (6.4, 3.2, 4.5, 1.5, IrisClass::Versicolour),
+        ]
+        .into_iter()
+        .map(
+            |(sepal_length, sepal_width, petal_length, petal_width, class)| IrisInput {
+                sepal_length,
+                sepal_width,
+                petal_length,
+                petal_width,
+                class,
+            },
+        )
+        .collect()
+    }
+
+    #[test]
+    fn given_same_seed_when_dataset_state_is_shuffled_then_rows_match_shuffled_inputs() {
+        use rand::{seq::SliceRandom, SeedableRng};
+        use rand_xoshiro::Xoshiro256PlusPlus;
+
+        let mut expected = rows();
+        expected.shuffle(&mut Xoshiro256PlusPlus::seed_from_u64(42));
+
+        let mut dataset = Dataset::from(rows());
+        dataset.shuffle(&mut Xoshiro256PlusPlus::seed_from_u64(42));
+        let mut state = IrisState::new(dataset);
+
+        assert_eq!(state.dataset().label_names[2], "Iris-virginica");
+
+        let mut fitness = 0.;
+        let mut expected_fitness = 0.;
+
+        for (i, row) in expected.iter().enumerate() {
+            let state = state.get().unwrap();
+            assert_eq!(
+                (0..4).map(|idx| state.get_value(idx)).collect_vec(),
+                row.features()
+            );
+
+            // Always predicts setosa.
+            fitness += state.execute_action(0);
+            expected_fitness += (row.class == IrisClass::Setosa) as usize as f64;
+            assert_eq!(fitness, expected_fitness, "row {}", i);
+        }
+
+        assert!(state.get().is_none());
+        assert_eq!(fitness, 2.);
+    }
+
+    #[test]
+    fn given_bundled_fixture_when_loaded_twice_then_file_is_parsed_once() -> VoidResultAnyError {
+        let path = Path::new("assets/fixtures/iris.csv");
+
+        let first = IrisStateParameters::load_from(path)?;
+        let second = IrisStateParameters::load_from(path)?;
+
+        assert_eq!(first.dataset.len(), 15);
+        assert!(Arc::ptr_eq(&first.dataset, &second.dataset));
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_dataset_when_split_for_holdout_then_parts_are_disjoint_and_cover_it(
+    ) -> VoidResultAnyError {
+        let mut parameters = IrisStateParameters::load_from(Path::new("assets/fixtures/iris.csv"))?;
+        parameters.trial_fraction = Some(1.);
+
+        let (train, holdout) = IrisEngine::holdout_split(&parameters, 0);
+
+        assert_eq!((train.dataset.len(), holdout.dataset.len()), (12, 3));
+        assert!(holdout
+            .dataset
+            .features
+            .iter()
+            .all(|row| !train.dataset.features.contains(row)));
+        assert_eq!(
+            holdout.dataset.labels.iter().sorted().collect_vec(),
+            [&0, &1, &2]
+        );
+        assert_eq!(train.trial_fraction, Some(1.));
+        assert_eq!(holdout.trial_fraction, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_dataset_path_when_engine_is_built_then_fixture_is_used() -> VoidResultAnyError {
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_generations(2)
+            .dataset_path(Some(PathBuf::from("assets/fixtures/iris.csv")))
+            .build()?;
+
+        let engine = parameters.try_build_engine()?;
+        assert!(engine
+            .trials()
+            .iter()
+            .all(|trial| trial.dataset().len() == 15));
+        assert_eq!(engine.take(parameters.n_generations).count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_best_program_when_written_to_buffer_then_it_is_read_back_unchanged(
+    ) -> VoidResultAnyError {
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_generations(1)
+            .seed(Some(3))
+            .dataset_path(Some(PathBuf::from("assets/fixtures/iris.csv")))
+            .build()?;
+
+        let population = parameters.try_build_engine()?.next().unwrap();
+        let best = population.first().unwrap();
+
+        let mut buffer = Vec::new();
+        best.write_to(&mut buffer)?;
+        let restored = Program::read_from(buffer.as_slice())?;
+
+        assert_eq!(&restored, best);
+        assert_eq!(restored.instructions, best.instructions);
+
+        Ok(())
+    }
+
+    fn first_generation(
+        saturation: RegisterSaturation,
+    ) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .ops(OpSet::new(&[Op::Add, Op::Mult]))
+            .register_init(RegisterInit::Ones)
+            .register_saturation(saturation)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(100)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(100)
+            .n_generations(1)
+            .default_fitness(f64::NEG_INFINITY)
+            .seed(Some(11))
+            .dataset_path(Some(PathBuf::from("assets/fixtures/iris.csv")))
+            .build()?;
+
+        let mut engine = parameters.try_build_engine()?;
+        let population = engine.next().unwrap();
+        let n_invalid = population
+            .iter()
+            .filter(|individual| !StatusEngine::valid(*individual))
+            .count();
+
+        Ok((n_invalid, engine.n_saturated()))
+    }
+
+    #[test]
+    fn given_overflowing_programs_when_registers_wrap_then_fewer_individuals_are_invalid(
+    ) -> VoidResultAnyError {
+        let (unsaturated_invalid, unsaturated) = first_generation(RegisterSaturation::None)?;
+        let (wrapped_invalid, wrapped) = first_generation(RegisterSaturation::Wrap {
+            min: -1e6,
+            max: 1e6,
+        })?;
+
+        assert_eq!(unsaturated, 0);
+        assert!(wrapped > 0);
+        assert!(wrapped_invalid < unsaturated_invalid);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_in_memory_dataset_when_generated_then_state_is_a_shuffle_of_it() {
+        let parameters = IrisStateParameters::new(Dataset::from(rows()));
+        let state: IrisState = GenerateEngine::generate(parameters.clone());
+
+        let sorted = |dataset: &Dataset| {
+            dataset
+                .features
+                .iter()
+                .cloned()
+                .sorted_by(|a, b| a.partial_cmp(b).unwrap())
+                .collect_vec()
+        };
+
+        assert_eq!(state.dataset().len(), parameters.dataset.len());
+        assert_eq!(sorted(state.dataset()), sorted(&parameters.dataset));
+    }
+
+    #[test]
+    fn given_five_folds_when_cross_validated_then_each_row_is_validated_exactly_once(
+    ) -> VoidResultAnyError {
+        let rows = rows().into_iter().cycle().take(15).collect_vec();
+        let mut dataset = Dataset::from(rows);
+        // Make every row distinguishable.
+        for (idx, features) in dataset.features.iter_mut().enumerate() {
+            features[0] += idx as f64;
+        }
+
+        let cross_validation = CrossValidation {
+            folds: 5,
+            stratified: true,
+        };
+
+        let mut validated = dataset
+            .stratified_k_folds(cross_validation.folds, 0)
+            .into_iter()
+            .flat_map(|(_, validation)| validation.features)
+            .collect_vec();
+        let mut expected = dataset.features.clone();
+        validated.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(validated, expected);
+
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_generations(2)
+            .n_trials(1)
+            .seed(Some(0))
+            .build()?;
+
+        let results = cross_validate(
+            &parameters,
+            &IrisStateParameters::new(dataset.clone()),
+            cross_validation,
+        );
+        save_cross_validation(&results, &default_output_root(), "iris_cross_validation")?;
+
+        assert_eq!(results.folds.len(), 5);
+        assert!(results.folds.iter().all(|fold| fold.n_validation == 3));
+        assert!(results
+            .folds
+            .iter()
+            .all(|fold| fold.n_train + fold.n_validation == dataset.len()));
+        assert!((0. ..=1.).contains(&results.mean_accuracy));
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_three_repeats_then_each_run_is_saved_and_aggregated() -> VoidResultAnyError {
+        let name = "iris_repeats";
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_generations(2)
+            .n_trials(1)
+            .seed(Some(10))
+            .build()?;
+
+        let aggregate = run_repeats(
+            &parameters,
+            IrisStateParameters::new(Dataset::from(rows())),
+            3,
+            &default_output_root(),
+            name,
+        )?;
+
+        assert_eq!(aggregate.seeds, vec![10, 11, 12]);
+        assert!(aggregate.failures.is_empty());
+        assert_eq!(aggregate.best_fitness.len(), 2);
+
+        let directory = default_output_root().join(name);
+        for repeat in 0..3 {
+            assert!(directory
+                .join(format!("run_{}", repeat))
+                .join("best.json")
+                .exists());
+        }
+        assert!(directory.join("aggregate.json").exists());
+        assert!(directory.join("aggregate.csv").exists());
+
+        let report: ClassificationReport = serde_json::from_str(&std::fs::read_to_string(
+            directory.join("run_0").join("best_report.json"),
+        )?)?;
+        assert_eq!(report.confusion_matrix.len(), 3);
+        assert_eq!(report.classes.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_repeat_when_finished_then_manifest_checksums_match_saved_files() -> VoidResultAnyError
+    {
+        let name = "iris_manifest";
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_generations(2)
+            .n_trials(1)
+            .seed(Some(4))
+            .build()?;
+
+        run_repeats(
+            &parameters,
+            IrisStateParameters::new(Dataset::from(rows())),
+            1,
+            &default_output_root(),
+            name,
+        )?;
+
+        let run_dir = default_output_root().join(name).join("run_0");
+        let manifest = Manifest::read(&run_dir)?;
+
+        assert_eq!(manifest.status, RunStatus::Completed);
+        assert_eq!(manifest.seed, Some(4));
+        assert_eq!(manifest.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest.config["population_size"], 10);
+        assert!(manifest.finished_at >= Some(manifest.started_at));
+        assert!(manifest
+            .artifacts
+            .iter()
+            .any(|artifact| artifact.path == std::path::Path::new("best.json")));
+
+        for artifact in &manifest.artifacts {
+            assert_eq!(
+                sha256_file(&run_dir.join(&artifact.path))?,
+                artifact.sha256,
+                "{}",
+                artifact.path.display()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_tiny_wall_time_when_run_then_it_stops_early_with_outputs_saved() -> VoidResultAnyError
+    {
+        let name = "iris_wall_time";
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_generations(1_000_000)
+            .n_trials(1)
+            .seed(Some(5))
+            .max_wall_time(Some(Duration::from_millis(5)))
+            .build()?;
+
+        let aggregate = run_repeats(
+            &parameters,
+            IrisStateParameters::new(Dataset::from(rows())),
+            1,
+            &default_output_root(),
+            name,
+        )?;
+
+        assert!(aggregate.failures.is_empty());
+        assert!(!aggregate.best_fitness.is_empty());
+        assert!(aggregate.best_fitness.len() < 1_000_000);
+
+        let run_dir = default_output_root().join(name).join("run_0");
+        let manifest = Manifest::read(&run_dir)?;
+
+        assert_eq!(manifest.status, RunStatus::Completed);
+        assert_eq!(manifest.stopped_reason, Some(StopReason::WallTime));
+        assert!(run_dir.join("best.json").exists());
+        assert_eq!(manifest.config["max_wall_time"], "5ms");
+
+        Ok(())
+    }
+
+    /// Requests a stop once `generation` has been evaluated, like a signal arriving mid-run.
+    struct StopAt {
+        generation: usize,
+        flag: Arc<AtomicBool>,
+    }
+
+    impl GenerationObserver<IrisEngine> for StopAt {
+        fn on_generation(&mut self, generation: usize, _ranked: &[Program]) {
+            if generation == self.generation {
+                self.flag.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[test]
+    fn given_stop_flag_set_mid_run_then_completed_generations_are_saved() -> VoidResultAnyError {
+        let name = "iris_interrupted";
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_generations(50)
+            .n_trials(1)
+            .build()?;
+        let flag = Arc::new(AtomicBool::new(false));
+
+        let mut engine = parameters
+            .build_engine_with(IrisStateParameters::new(Dataset::from(rows())))
+            .with_stop_flag(flag.clone())
+            .with_observer(Box::new(StopAt {
+                generation: 2,
+                flag,
+            }));
+        let populations = engine.by_ref().take(parameters.n_generations).collect_vec();
+
+        assert_eq!(populations.len(), 3);
+        assert_eq!(engine.stop_reason(), Some(StopReason::Interrupted));
+        assert!(engine.next().is_none());
+
+        save_experiment(&populations, &parameters, &default_output_root(), name)?;
+
+        let run_dir = default_output_root().join(name);
+        assert!(run_dir.join("best.json").exists());
+        assert_eq!(FitnessCurves::load_run(&run_dir)?.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_snapshot_interval_when_saved_then_every_fifth_generation_has_a_snapshot(
+    ) -> VoidResultAnyError {
+        let name = "iris_snapshots";
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_generations(10)
+            .n_trials(1)
+            .snapshot_interval(Some(5))
+            .build()?;
+
+        let directory = default_output_root().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let populations = parameters
+            .build_engine_with(IrisStateParameters::new(Dataset::from(rows())))
+            .take(parameters.n_generations)
+            .collect_vec();
+        save_experiment(&populations, &parameters, &default_output_root(), name)?;
+
+        for generation in [5, 10] {
+            let snapshot = directory.join(format!("gen_{}", generation));
+            let best = Program::load(snapshot.join("best.json"))?;
+            let summary: GenerationSummary =
+                serde_json::from_str(&std::fs::read_to_string(snapshot.join("summary.json"))?)?;
+
+            assert_eq!(
+                StatusEngine::get_fitness(&best),
+                StatusEngine::get_fitness(&populations[generation - 1][0])
+            );
+            assert_eq!(summary.generation, generation);
+        }
+        assert!(!directory.join("gen_15").exists());
+        assert!(!directory.join("population.json").exists());
+        assert_eq!(FitnessCurves::load_run(&directory)?.len(), 10);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_trial_fraction_then_trials_are_reproducible_bootstrap_samples_each_evaluated_once(
+    ) -> VoidResultAnyError {
+        let parameters = |trial_fraction: Option<f64>| {
+            HyperParametersBuilder::<IrisEngine>::default()
+                .program_parameters(
+                    ProgramGeneratorParametersBuilder::default()
+                        .max_instructions(10)
+                        .instruction_generator_parameters(
+                            InstructionGeneratorParametersBuilder::default()
+                                .n_actions(3)
+                                .n_inputs(4)
+                                .build()
+                                .unwrap(),
+                        )
+                        .build()
+                        .unwrap(),
+                )
+                .population_size(10)
+                .n_trials(3)
+                .trial_fraction(trial_fraction)
+                .seed(Some(8))
+                .dataset_path(Some(PathBuf::from("assets/fixtures/iris.csv")))
+                .build()
+                .unwrap()
+        };
+        let trials = |engine: &CoreIter<IrisEngine>| {
+            engine
+                .trials()
+                .iter()
+                .map(|trial| trial.dataset().clone())
+                .collect_vec()
+        };
+
+        let mut engine = parameters(Some(0.5)).try_build_engine()?;
+        let samples = trials(&engine);
+        let n_rows = engine.state_parameters().dataset.len();
+        assert!(samples
+            .iter()
+            .all(|sample| sample.len() == (n_rows as f64 * 0.5).round() as usize));
+        assert_ne!(samples[0], samples[1]);
+        assert_eq!(trials(&parameters(Some(0.5)).try_build_engine()?), samples);
+
+        engine.next().unwrap();
+        assert_eq!(engine.n_evaluations(), 10 * 3);
+
+        // Several trials default to full-size bootstrap samples, drawn with replacement so rows
+        // repeat; a single trial keeps every row.
+        let defaulted = parameters(None).try_build_engine()?;
+        assert_eq!(defaulted.state_parameters().trial_fraction, Some(1.));
+        assert!(trials(&defaulted)
+            .iter()
+            .all(|sample| sample.len() == n_rows));
+        assert!(trials(&defaulted).iter().any(|sample| {
+            sample
+                .features
+                .iter()
+                .map(|row| format!("{:?}", row))
+                .unique()
+                .count()
+                < n_rows
+        }));
+        let mut single = parameters(None);
+        single.n_trials = 1;
+        assert_eq!(IrisEngine::state_parameters(&single)?.trial_fraction, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_minimized_accuracy_when_saved_then_best_json_holds_the_lowest_fitness(
+    ) -> VoidResultAnyError {
+        let name = "iris_minimize";
+        let root = std::env::temp_dir().join("lgp_objective");
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(20)
+            .n_generations(3)
+            .n_trials(1)
+            .objective(Objective::Minimize)
+            .seed(Some(4))
+            .build()?;
+
+        let populations = parameters
+            .build_engine_with(IrisStateParameters::new(Dataset::from(rows())))
+            .take(parameters.n_generations)
+            .collect_vec();
+        save_experiment(&populations, &parameters, &root, name)?;
+
+        let last_population = populations.last().unwrap();
+        let fitness = last_population
+            .iter()
+            .map(StatusEngine::get_fitness)
+            .collect_vec();
+        assert!(fitness.windows(2).all(|w| w[0] <= w[1]));
+
+        let best = Program::load(root.join(name).join("best.json"))?;
+        let worst = Program::load(root.join(name).join("worst.json"))?;
+        assert_eq!(StatusEngine::get_fitness(&best), fitness[0]);
+        assert_eq!(StatusEngine::get_fitness(&worst), *fitness.last().unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_recorded_trial_scores_when_saved_then_best_json_holds_one_score_per_trial(
+    ) -> VoidResultAnyError {
+        let root = std::env::temp_dir().join("lgp_trial_scores");
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+
+        for (name, record_trial_scores) in [("iris_recorded", true), ("iris_unrecorded", false)] {
+            let parameters = HyperParametersBuilder::<IrisEngine>::default()
+                .program_parameters(program_parameters)
+                .population_size(10)
+                .n_generations(3)
+                .n_trials(3)
+                .record_trial_scores(record_trial_scores)
+                .seed(Some(5))
+                .build()?;
+
+            let populations = parameters
+                .build_engine_with(IrisStateParameters::new(Dataset::from(rows())))
+                .take(parameters.n_generations)
+                .collect_vec();
+            save_experiment(&populations, &parameters, &root, name)?;
+
+            let best = Program::load(root.join(name).join("best.json"))?;
+            let Some(scores) = StatusEngine::trial_scores(&best) else {
+                assert!(!record_trial_scores);
+                continue;
+            };
+
+            assert!(record_trial_scores);
+            assert_eq!(scores.len(), 3);
+            assert_eq!(
+                scores.iter().sum::<f64>() / 3.,
+                StatusEngine::get_fitness(&best)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_two_roots_when_saved_concurrently_then_each_run_lands_under_its_own_root(
+    ) -> VoidResultAnyError {
+        let name = "iris_output_root";
+        let roots = ["a", "b"].map(|root| std::env::temp_dir().join("lgp_output_roots").join(root));
+
+        std::thread::scope(|scope| {
+            for (root, seed) in roots.iter().zip([1, 2]) {
+                scope.spawn(move || {
+                    let _ = std::fs::remove_dir_all(root);
+                    let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+                        .n_actions(3)
+                        .n_inputs(4)
+                        .build()
+                        .unwrap();
+                    let program_parameters = ProgramGeneratorParametersBuilder::default()
+                        .max_instructions(10)
+                        .instruction_generator_parameters(instruction_parameters)
+                        .build()
+                        .unwrap();
+                    let parameters = HyperParametersBuilder::<IrisEngine>::default()
+                        .program_parameters(program_parameters)
+                        .population_size(10)
+                        .n_generations(2)
+                        .n_trials(1)
+                        .seed(Some(seed))
+                        .build()
+                        .unwrap();
+
+                    let populations = parameters
+                        .build_engine_with(IrisStateParameters::new(Dataset::from(rows())))
+                        .take(parameters.n_generations)
+                        .collect_vec();
+                    save_experiment(&populations, &parameters, root, name).unwrap();
+                });
+            }
+        });
+
+        for (root, seed) in roots.iter().zip([1, 2]) {
+            let directory = root.join(name);
+            let saved: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(directory.join("params.json"))?)?;
+
+            assert!(directory.join("best.json").is_file());
+            assert_eq!(saved["seed"], seed);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_legacy_run_with_only_population_json_then_curves_are_loaded() -> VoidResultAnyError {
+        let directory = std::env::temp_dir().join("lgp_legacy_run");
+        std::fs::create_dir_all(&directory)?;
+        let _ = std::fs::remove_file(directory.join("metrics.csv"));
+
+        let mut population = vec![];
+        for fitness in [0.9, 0.5, 0.1] {
+            let mut program: Program = GenerateEngine::generate(
+                ProgramGeneratorParametersBuilder::default()
+                    .max_instructions(5)
+                    .instruction_generator_parameters(
+                        InstructionGeneratorParametersBuilder::default()
+                            .n_actions(3)
+                            .n_inputs(4)
+                            .build()?,
+                    )
+                    .build()?,
+            );
+            StatusEngine::set_fitness(&mut program, fitness);
+            population.push(program);
+        }
+        vec![population.clone(), population]
+            .save(directory.join("population.json").to_str().unwrap())?;
+
+        let curves = FitnessCurves::load_run(&directory)?;
+        assert_eq!(curves.best, vec![0.9, 0.9]);
+        assert_eq!(curves.median, vec![0.5, 0.5]);
+        assert_eq!(curves.worst, vec![0.1, 0.1]);
+
+        Ok(())
+    }
+
     #[test]
     fn baseline() -> VoidResultAnyError {
         let name = "iris_baseline";
@@ -167,7 +1279,7 @@ mod test {
             .take(parameters.n_generations)
             .collect_vec();
 
-        save_experiment(&populations, &parameters, name)?;
+        save_experiment(&populations, &parameters, &default_output_root(), name)?;
 
         let last_population = populations.last().unwrap();
         assert!(last_population
@@ -201,7 +1313,7 @@ mod test {
             .take(parameters.n_generations)
             .collect_vec();
 
-        save_experiment(&populations, &parameters, name)?;
+        save_experiment(&populations, &parameters, &default_output_root(), name)?;
 
         Ok(())
     }
@@ -229,7 +1341,7 @@ mod test {
             .take(parameters.n_generations)
             .collect_vec();
 
-        save_experiment(&populations, &parameters, name)?;
+        save_experiment(&populations, &parameters, &default_output_root(), name)?;
 
         Ok(())
     }
@@ -258,7 +1370,7 @@ mod test {
             .take(parameters.n_generations)
             .collect_vec();
 
-        save_experiment(&populations, &parameters, name)?;
+        save_experiment(&populations, &parameters, &default_output_root(), name)?;
 
         Ok(())
     }
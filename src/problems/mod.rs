@@ -1,2 +1,9 @@
+pub mod classification;
+#[cfg(feature = "gym")]
 pub mod gym;
 pub mod iris;
+pub mod multi_task;
+pub mod normalization;
+pub mod reward_shaping;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_env;
@@ -0,0 +1,444 @@
+//! Evolving one program to perform on several variants of a problem at once, e.g. CartPole with
+//! different episode lengths for a robustness study.
+//!
+//! Every task generates trials of the same state type from its own state parameters, so tasks
+//! share observation and action dimensions by construction. Trial `slot` belongs to task
+//! `slot % n_tasks`; the fitness on each task aggregates its trials with `fitness_aggregation`
+//! and the fitness of the individual combines those with `TaskAggregation`.
+
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        config_override::apply_overrides,
+        engines::{
+            core_engine::{Core, HyperParameters, TrialSeed},
+            fitness_engine::Objective,
+            generate_engine::{Generate, GenerateEngine},
+        },
+    },
+    utils::error::{require, ConfigViolation, LgpError, LgpResult},
+};
+
+/// How the fitness on every task is combined into the fitness of an individual.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskAggregation {
+    /// Mean weighted by the weight of every task.
+    #[default]
+    Weighted,
+    /// Fitness on the worst task, for controllers that must cope with all of them.
+    WorstCase,
+}
+
+impl TaskAggregation {
+    pub fn aggregate(&self, task_fitness: &[f64], weights: &[f64], objective: Objective) -> f64 {
+        match self {
+            TaskAggregation::Weighted => {
+                let total = weights.iter().sum::<f64>();
+
+                task_fitness
+                    .iter()
+                    .zip(weights)
+                    .map(|(fitness, weight)| fitness * weight)
+                    .sum::<f64>()
+                    / total
+            }
+            TaskAggregation::WorstCase => task_fitness
+                .iter()
+                .copied()
+                .max_by(|a, b| objective.compare(*a, *b))
+                .unwrap_or(f64::NAN),
+        }
+    }
+}
+
+/// A task of a multi-task run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Task<P> {
+    pub name: String,
+    pub weight: f64,
+    pub parameters: P,
+}
+
+/// State parameters of a `MultiTaskEngine`: the tasks trials are drawn from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiTaskParameters<P> {
+    pub tasks: Vec<Task<P>>,
+    pub aggregation: TaskAggregation,
+}
+
+impl<P> Default for MultiTaskParameters<P>
+where
+    P: Default,
+{
+    /// The standard configuration of the wrapped problem as the only task.
+    fn default() -> Self {
+        MultiTaskParameters {
+            tasks: vec![Task {
+                name: "default".to_string(),
+                weight: 1.,
+                parameters: P::default(),
+            }],
+            aggregation: TaskAggregation::default(),
+        }
+    }
+}
+
+/// Generates a trial of the first task; `MultiTaskEngine::task_parameters` narrows the tasks to
+/// the one a trial is drawn from.
+impl<P, S> Generate<MultiTaskParameters<P>, S> for GenerateEngine
+where
+    GenerateEngine: Generate<P, S>,
+{
+    fn generate(using: MultiTaskParameters<P>) -> S {
+        let task = using
+            .tasks
+            .into_iter()
+            .next()
+            .expect("Multi-task runs have at least one task.");
+
+        GenerateEngine::generate(task.parameters)
+    }
+}
+
+/// A task as configured in the `[multi_task]` section of a config file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TaskConfig {
+    pub name: String,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    /// `key=value` overrides of the hyperparameters the task's state parameters are built from,
+    /// e.g. `max_episode_steps=200`.
+    #[serde(default)]
+    pub overrides: Vec<String>,
+}
+
+fn default_weight() -> f64 {
+    1.
+}
+
+/// Tasks of a `MultiTaskEngine` run and how their fitness is combined.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MultiTaskConfig {
+    #[serde(default)]
+    pub aggregation: TaskAggregation,
+    pub tasks: Vec<TaskConfig>,
+}
+
+/// Evolves the individuals of `C` on every task of `MultiTaskParameters`, all generating states
+/// of `C`.
+#[derive(Clone)]
+pub struct MultiTaskEngine<C>(PhantomData<C>);
+
+impl<C> MultiTaskEngine<C>
+where
+    C: Core,
+    GenerateEngine:
+        Generate<C::ProgramParameters, C::Individual> + Generate<C::StateParameters, C::State>,
+{
+    /// The hyperparameters of the wrapped problem with the overrides of `task` applied.
+    fn task_hyperparameters(
+        hp: &HyperParameters<Self>,
+        task: &TaskConfig,
+    ) -> LgpResult<HyperParameters<C>> {
+        let mut hyperparameters = hp.retarget::<C>()?;
+        hyperparameters.multi_task = None;
+
+        apply_overrides(&hyperparameters, &task.overrides)
+    }
+}
+
+impl<C> Core for MultiTaskEngine<C>
+where
+    C: Core,
+    GenerateEngine:
+        Generate<C::ProgramParameters, C::Individual> + Generate<C::StateParameters, C::State>,
+{
+    type Individual = C::Individual;
+    type ProgramParameters = C::ProgramParameters;
+    type State = C::State;
+    type StateParameters = MultiTaskParameters<C::StateParameters>;
+    type FitnessMarker = C::FitnessMarker;
+    type Generate = GenerateEngine;
+    type Fitness = C::Fitness;
+    type Reset = C::Reset;
+    type Breed = C::Breed;
+    type Mutate = C::Mutate;
+    type Status = C::Status;
+    type Freeze = C::Freeze;
+
+    /// One task per entry of `multi_task`, with the state parameters the wrapped problem builds
+    /// from the task's overrides.
+    fn state_parameters(hp: &HyperParameters<Self>) -> LgpResult<Self::StateParameters> {
+        let config = hp
+            .multi_task
+            .as_ref()
+            .ok_or_else(|| LgpError::Config("Multi-task runs require `multi_task`.".to_string()))?;
+
+        let tasks = config
+            .tasks
+            .iter()
+            .map(|task| {
+                let hyperparameters = Self::task_hyperparameters(hp, task)?;
+
+                Ok(Task {
+                    name: task.name.clone(),
+                    weight: task.weight,
+                    parameters: C::state_parameters(&hyperparameters)?,
+                })
+            })
+            .collect::<LgpResult<Vec<_>>>()?;
+
+        Ok(MultiTaskParameters {
+            tasks,
+            aggregation: config.aggregation,
+        })
+    }
+
+    fn validate(hp: &HyperParameters<Self>) -> Vec<ConfigViolation> {
+        let Some(config) = &hp.multi_task else {
+            return vec![ConfigViolation {
+                field: "multi_task".to_string(),
+                value: "unset".to_string(),
+                allowed: "at least one task".to_string(),
+            }];
+        };
+
+        let mut violations = vec![];
+        require(
+            &mut violations,
+            !config.tasks.is_empty(),
+            "multi_task.tasks",
+            config.tasks.len(),
+            "at least one task",
+        );
+        require(
+            &mut violations,
+            hp.n_trials >= config.tasks.len(),
+            "n_trials",
+            hp.n_trials,
+            "at least one trial per task",
+        );
+
+        for (index, task) in config.tasks.iter().enumerate() {
+            require(
+                &mut violations,
+                task.weight.is_finite() && task.weight > 0.,
+                &format!("multi_task.tasks.{}.weight", index),
+                task.weight,
+                "finite and positive",
+            );
+
+            match Self::task_hyperparameters(hp, task) {
+                Ok(hyperparameters) => violations.extend(C::validate(&hyperparameters)),
+                Err(error) => violations.push(ConfigViolation {
+                    field: format!("multi_task.tasks.{}.overrides", index),
+                    value: task.overrides.join(" "),
+                    allowed: format!("valid overrides ({})", error),
+                }),
+            }
+        }
+
+        violations
+    }
+
+    fn seed_trial(parameters: &Self::StateParameters, seed: TrialSeed) -> Self::StateParameters {
+        MultiTaskParameters {
+            tasks: parameters
+                .tasks
+                .iter()
+                .map(|task| Task {
+                    parameters: C::seed_trial(&task.parameters, seed),
+                    ..task.clone()
+                })
+                .collect(),
+            aggregation: parameters.aggregation,
+        }
+    }
+
+    fn n_tasks(parameters: &Self::StateParameters) -> usize {
+        parameters.tasks.len()
+    }
+
+    fn task_parameters(parameters: &Self::StateParameters, task: usize) -> Self::StateParameters {
+        MultiTaskParameters {
+            tasks: vec![parameters.tasks[task].clone()],
+            aggregation: parameters.aggregation,
+        }
+    }
+
+    fn aggregate_tasks(
+        parameters: &Self::StateParameters,
+        task_fitness: &[f64],
+        objective: Objective,
+    ) -> f64 {
+        let weights = parameters
+            .tasks
+            .iter()
+            .map(|task| task.weight)
+            .collect::<Vec<_>>();
+
+        parameters
+            .aggregation
+            .aggregate(task_fitness, &weights, objective)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, File, FileFormat};
+    use itertools::Itertools;
+
+    use crate::{
+        core::{
+            engines::{
+                core_engine::{CoreIter, HyperParametersBuilder, TaskMetrics},
+                status_engine::{Status, StatusEngine},
+            },
+            instruction::{Instruction, Op},
+            program::Program,
+        },
+        problems::test_env::{
+            CorridorEngine, CorridorParameters, ScaledCorridorEngine, CORRIDOR_LENGTH, RIGHT,
+        },
+    };
+
+    use super::*;
+
+    type Engine = MultiTaskEngine<ScaledCorridorEngine>;
+
+    fn task(name: &str, weight: f64, reward: f64) -> Task<CorridorParameters> {
+        Task {
+            name: name.to_string(),
+            weight,
+            parameters: CorridorParameters { reward },
+        }
+    }
+
+    /// Runs one generation of programs always stepping right on corridors rewarding 1 (weight 1)
+    /// and 10 (weight 3) per step.
+    fn run(aggregation: TaskAggregation) -> (Vec<Program>, Vec<usize>, Vec<TaskMetrics>) {
+        let parameters = HyperParametersBuilder::<Engine>::default()
+            .program_parameters(CorridorEngine::program_parameters(1))
+            .population_size(4)
+            .n_trials(4)
+            .n_generations(1)
+            .seed(Some(5))
+            .build()
+            .unwrap();
+        let state_parameters = MultiTaskParameters {
+            tasks: vec![task("unit", 1., 1.), task("scaled", 3., 10.)],
+            aggregation,
+        };
+
+        let mut program: Program = GenerateEngine::generate(parameters.program_parameters);
+        program.instructions = vec![Instruction::with_constant(RIGHT, Op::Add, 1.)];
+        let population = vec![program; parameters.population_size];
+
+        let mut engine = CoreIter::with_population(parameters, state_parameters, population);
+        let evaluated = engine.next().unwrap();
+
+        (
+            evaluated,
+            engine.trial_tasks().to_vec(),
+            engine.task_metrics().to_vec(),
+        )
+    }
+
+    #[test]
+    fn given_tasks_with_different_reward_scales_when_evaluated_then_fitness_is_weighted_per_task() {
+        let unit = CORRIDOR_LENGTH as f64;
+        let scaled = 10. * CORRIDOR_LENGTH as f64;
+
+        let (population, trial_tasks, task_metrics) = run(TaskAggregation::Weighted);
+        assert_eq!(trial_tasks, vec![0, 1, 0, 1]);
+        assert!(population
+            .iter()
+            .all(|program| StatusEngine::get_fitness(program) == (unit + 3. * scaled) / 4.));
+        assert_eq!(
+            task_metrics,
+            vec![
+                TaskMetrics {
+                    task: 0,
+                    mean: unit,
+                    best: unit
+                },
+                TaskMetrics {
+                    task: 1,
+                    mean: scaled,
+                    best: scaled
+                },
+            ]
+        );
+
+        let (population, _, _) = run(TaskAggregation::WorstCase);
+        assert!(population
+            .iter()
+            .all(|program| StatusEngine::get_fitness(program) == unit));
+    }
+
+    #[test]
+    fn given_objective_when_worst_case_is_aggregated_then_the_worst_task_is_chosen() {
+        let task_fitness = [3., 1., 2.];
+        let weights = [1., 1., 2.];
+
+        let worst =
+            |objective| TaskAggregation::WorstCase.aggregate(&task_fitness, &weights, objective);
+        assert_eq!(worst(Objective::Maximize), 1.);
+        assert_eq!(worst(Objective::Minimize), 3.);
+        assert_eq!(
+            TaskAggregation::Weighted.aggregate(&task_fitness, &weights, Objective::Maximize),
+            2.
+        );
+    }
+
+    #[test]
+    fn given_multi_task_config_when_validated_then_tasks_need_trials_and_positive_weights() {
+        let toml = r#"
+            aggregation = "worst_case"
+
+            [[tasks]]
+            name = "unit"
+
+            [[tasks]]
+            name = "heavy"
+            weight = 0.0
+        "#;
+        let config: MultiTaskConfig = Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(config.aggregation, TaskAggregation::WorstCase);
+        assert_eq!(config.tasks[0].weight, 1.);
+
+        let parameters = HyperParametersBuilder::<Engine>::default()
+            .program_parameters(CorridorEngine::program_parameters(1))
+            .n_trials(1)
+            .multi_task(Some(config))
+            .build()
+            .unwrap();
+
+        let fields = parameters
+            .validate()
+            .unwrap_err()
+            .into_iter()
+            .map(|violation| violation.field)
+            .collect_vec();
+        assert_eq!(fields, vec!["n_trials", "multi_task.tasks.1.weight"]);
+
+        let state_parameters = Engine::state_parameters(&parameters).unwrap();
+        assert_eq!(
+            state_parameters
+                .tasks
+                .iter()
+                .map(|task| task.name.as_str())
+                .collect_vec(),
+            vec!["unit", "heavy"]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Smallest standard deviation running normalization divides by.
+const MIN_STD: f64 = 1e-8;
+
+/// Mean and variance of every observation dimension, updated one observation at a time with
+/// Welford's algorithm.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunningStats {
+    #[serde(default)]
+    count: usize,
+    #[serde(default)]
+    mean: Vec<f64>,
+    /// Sum of squared deviations from the mean.
+    #[serde(default)]
+    m2: Vec<f64>,
+}
+
+impl RunningStats {
+    pub fn update(&mut self, observation: &[f64]) {
+        if self.mean.len() != observation.len() {
+            *self = RunningStats {
+                count: 0,
+                mean: vec![0.; observation.len()],
+                m2: vec![0.; observation.len()],
+            };
+        }
+
+        self.count += 1;
+
+        for (idx, &value) in observation.iter().enumerate() {
+            let delta = value - self.mean[idx];
+            self.mean[idx] += delta / self.count as f64;
+            self.m2[idx] += delta * (value - self.mean[idx]);
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn mean(&self) -> &[f64] {
+        &self.mean
+    }
+
+    /// Population standard deviation of dimension `idx`.
+    pub fn std(&self, idx: usize) -> f64 {
+        if self.count == 0 {
+            return 0.;
+        }
+
+        (self.m2[idx] / self.count as f64).sqrt()
+    }
+
+    /// Standardises `value`; left as is until a first observation is seen.
+    pub fn normalize(&self, idx: usize, value: f64) -> f64 {
+        match self.mean.get(idx) {
+            Some(mean) => (value - mean) / self.std(idx).max(MIN_STD),
+            None => value,
+        }
+    }
+}
+
+/// Running statistics shared by every trial generated from the same parameters, so all trials
+/// of a run learn, and are normalized with, the same statistics. Saved as their current values.
+#[derive(Clone, Debug, Default)]
+pub struct SharedStats(Arc<Mutex<RunningStats>>);
+
+impl SharedStats {
+    pub fn new(stats: RunningStats) -> Self {
+        SharedStats(Arc::new(Mutex::new(stats)))
+    }
+
+    /// Copy of the current statistics.
+    pub fn get(&self) -> RunningStats {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Serialize for SharedStats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedStats {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        RunningStats::deserialize(deserializer).map(SharedStats::new)
+    }
+}
+
+/// Maps gym observations onto comparable scales before programs read them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationNormalization {
+    #[default]
+    None,
+    /// Maps `[lows[i], highs[i]]` linearly onto `[-1, 1]`; values outside are not clipped.
+    Fixed { lows: Vec<f64>, highs: Vec<f64> },
+    /// Standardises every dimension with the mean and standard deviation of the observations
+    /// seen so far, starting from the given statistics (`{}` for none).
+    Running(SharedStats),
+}
+
+impl ObservationNormalization {
+    pub fn is_none(&self) -> bool {
+        matches!(self, ObservationNormalization::None)
+    }
+
+    pub fn normalize(&self, observation: &[f64]) -> Vec<f64> {
+        match self {
+            ObservationNormalization::None => observation.to_vec(),
+            ObservationNormalization::Fixed { lows, highs } => observation
+                .iter()
+                .zip(lows.iter().zip(highs))
+                .map(|(value, (low, high))| 2. * (value - low) / (high - low) - 1.)
+                .collect(),
+            ObservationNormalization::Running(stats) => {
+                let stats = stats.0.lock().unwrap();
+                observation
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, &value)| stats.normalize(idx, value))
+                    .collect()
+            }
+        }
+    }
+
+    /// Adds `observation` to the running statistics, if any.
+    pub fn observe(&self, observation: &[f64]) {
+        if let ObservationNormalization::Running(stats) = self {
+            stats.0.lock().unwrap().update(observation);
+        }
+    }
+
+    /// A copy whose running statistics are no longer shared with `self`.
+    pub fn detached(&self) -> Self {
+        match self {
+            ObservationNormalization::Running(stats) => {
+                ObservationNormalization::Running(SharedStats::new(stats.get()))
+            }
+            other => other.clone(),
+        }
+    }
+
+    /// Fixed bounds must match `n_inputs` and satisfy `low < high`.
+    pub fn is_valid(&self, n_inputs: usize) -> bool {
+        match self {
+            ObservationNormalization::Fixed { lows, highs } => {
+                lows.len() == n_inputs
+                    && highs.len() == n_inputs
+                    && lows.iter().zip(highs).all(|(low, high)| low < high)
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_fixed_bounds_when_normalized_then_boundaries_map_to_unit_range() {
+        let normalization = ObservationNormalization::Fixed {
+            lows: vec![-4.8, 0.],
+            highs: vec![4.8, 10.],
+        };
+
+        assert_eq!(normalization.normalize(&[-4.8, 0.]), vec![-1., -1.]);
+        assert_eq!(normalization.normalize(&[4.8, 10.]), vec![1., 1.]);
+        assert_eq!(normalization.normalize(&[0., 5.]), vec![0., 0.]);
+        assert_eq!(normalization.normalize(&[0., 15.]), vec![0., 2.]);
+    }
+
+    #[test]
+    fn given_fixed_bounds_when_validated_then_lengths_and_order_are_checked() {
+        let fixed =
+            |lows: Vec<f64>, highs: Vec<f64>| ObservationNormalization::Fixed { lows, highs };
+
+        assert!(fixed(vec![-1., 0.], vec![1., 2.]).is_valid(2));
+        assert!(!fixed(vec![-1., 0.], vec![1., 2.]).is_valid(4));
+        assert!(!fixed(vec![1., 0.], vec![1., 2.]).is_valid(2));
+    }
+
+    #[test]
+    fn given_observations_when_running_then_values_are_standardised() {
+        let normalization = ObservationNormalization::Running(SharedStats::default());
+        assert_eq!(normalization.normalize(&[3.]), vec![3.]);
+
+        for value in [1., 3., 5.] {
+            normalization.observe(&[value]);
+        }
+
+        // Mean 3 and population standard deviation sqrt(8 / 3).
+        let normalized = normalization.normalize(&[5.]);
+        assert!((normalized[0] - 2. / (8f64 / 3.).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn given_running_stats_when_serialized_then_they_round_trip() {
+        let normalization = ObservationNormalization::Running(SharedStats::default());
+        normalization.observe(&[1., -2.]);
+        normalization.observe(&[2., 4.]);
+
+        let json = serde_json::to_string(&normalization).unwrap();
+        let loaded: ObservationNormalization = serde_json::from_str(&json).unwrap();
+        let ObservationNormalization::Running(stats) = &loaded else {
+            panic!("Expected running normalization, got {:?}", loaded);
+        };
+
+        assert_eq!(stats.get().count(), 2);
+        assert_eq!(stats.get().mean(), &[1.5, 1.]);
+        assert_eq!(
+            loaded.normalize(&[3., 0.]),
+            normalization.normalize(&[3., 0.])
+        );
+
+        let empty: ObservationNormalization = serde_json::from_str(r#"{"running": {}}"#).unwrap();
+        assert!(matches!(empty, ObservationNormalization::Running(_)));
+    }
+
+    #[test]
+    fn given_detached_copy_when_observing_then_original_is_unchanged() {
+        let normalization = ObservationNormalization::Running(SharedStats::default());
+        let detached = normalization.detached();
+
+        detached.observe(&[1.]);
+        normalization.clone().observe(&[2.]);
+
+        let ObservationNormalization::Running(stats) = normalization else {
+            unreachable!()
+        };
+        assert_eq!(stats.get().mean(), &[2.]);
+    }
+}
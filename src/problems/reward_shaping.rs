@@ -0,0 +1,120 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Gravity of the MountainCar dynamics, per step.
+const MOUNTAIN_CAR_GRAVITY: f64 = 0.0025;
+
+/// Rewrites the reward of every step before the fitness loop sees it, e.g. to give evolution a
+/// gradient on MountainCar's constant -1 per step. States still track the unshaped return.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardShaper {
+    #[default]
+    None,
+    /// Adds `scale * (Φ(s') - Φ(s))`, where Φ is the mechanical energy of the MountainCar car
+    /// computed from its position and velocity. The shaped return of an episode then differs
+    /// from the unshaped one by `scale * (Φ(s_T) - Φ(s_0))`, so the best policies are unchanged.
+    /// Energies are in the order of `1e-3`; a scale of about `1000` puts the bonus on par with
+    /// the step reward.
+    PotentialBased { scale: f64 },
+    /// `f(observation, next_observation, reward)`. Only available through the API, as
+    /// functions cannot be saved with the hyperparameters.
+    #[serde(skip)]
+    Custom(fn(&[f64], &[f64], f64) -> f64),
+}
+
+impl RewardShaper {
+    pub fn is_none(&self) -> bool {
+        matches!(self, RewardShaper::None)
+    }
+
+    /// The reward of the transition from `observation` to `next_observation`.
+    pub fn shape(&self, observation: &[f64], next_observation: &[f64], reward: f64) -> f64 {
+        match self {
+            RewardShaper::None => reward,
+            RewardShaper::PotentialBased { scale } => {
+                reward
+                    + scale
+                        * (mountain_car_potential(next_observation)
+                            - mountain_car_potential(observation))
+            }
+            RewardShaper::Custom(shape) => shape(observation, next_observation, reward),
+        }
+    }
+
+    /// The scale must be finite.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            RewardShaper::PotentialBased { scale } => scale.is_finite(),
+            _ => true,
+        }
+    }
+}
+
+/// Potential plus kinetic energy of a MountainCar observation `[position, velocity]`; the track
+/// is `sin(3x) / 3` high at position `x`.
+pub fn mountain_car_potential(observation: &[f64]) -> f64 {
+    let (position, velocity) = (observation[0], observation[1]);
+
+    MOUNTAIN_CAR_GRAVITY * (3. * position).sin() / 3. + velocity.powi(2) / 2.
+}
+
+impl FromStr for RewardShaper {
+    type Err = String;
+
+    /// Parses `none` or `potential:<scale>`, e.g. `potential:1000`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once(':') {
+            None if s.trim() == "none" => Ok(RewardShaper::None),
+            Some(("potential", scale)) => scale
+                .trim()
+                .parse()
+                .map(|scale| RewardShaper::PotentialBased { scale })
+                .map_err(|_| format!("Invalid potential scale `{}`", scale)),
+            _ => Err(format!("Unknown reward shaping `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for RewardShaper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewardShaper::None => write!(f, "none"),
+            RewardShaper::PotentialBased { scale } => write!(f, "potential:{}", scale),
+            RewardShaper::Custom(_) => write!(f, "custom"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_specs_when_parsed_then_round_trip_through_display() {
+        for spec in ["none", "potential:1000"] {
+            assert_eq!(spec.parse::<RewardShaper>().unwrap().to_string(), spec);
+        }
+
+        assert!("potential:fast".parse::<RewardShaper>().is_err());
+        assert!("energy".parse::<RewardShaper>().is_err());
+    }
+
+    #[test]
+    fn given_potential_shaping_then_bonus_is_the_scaled_change_in_energy() {
+        let shaper = RewardShaper::PotentialBased { scale: 1000. };
+        let (observation, next_observation) = ([-0.5, 0.], [-0.45, 0.01]);
+
+        let bonus = shaper.shape(&observation, &next_observation, -1.) + 1.;
+        let expected = 1000.
+            * (mountain_car_potential(&next_observation) - mountain_car_potential(&observation));
+
+        assert!(bonus > 0.);
+        assert!((bonus - expected).abs() < 1e-12);
+        assert_eq!(
+            RewardShaper::None.shape(&observation, &next_observation, -1.),
+            -1.
+        );
+    }
+}
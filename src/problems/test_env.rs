@@ -0,0 +1,353 @@
+use crate::{
+    core::{
+        engines::{
+            breed_engine::BreedEngine,
+            core_engine::{Core, HyperParameters},
+            fitness_engine::FitnessEngine,
+            freeze_engine::FreezeEngine,
+            generate_engine::{Generate, GenerateEngine},
+            mutate_engine::MutateEngine,
+            reset_engine::{Reset, ResetEngine},
+            status_engine::StatusEngine,
+        },
+        environment::{BehaviorDescriptor, EpisodeStatus, RlState, State},
+        instruction::InstructionGeneratorParametersBuilder,
+        program::{Program, ProgramGeneratorParameters, ProgramGeneratorParametersBuilder},
+    },
+    extensions::{
+        interactive::UseRlFitness,
+        q_learning::{QProgram, QProgramGeneratorParameters, QProgramGeneratorParametersBuilder},
+    },
+    utils::error::{require, ConfigViolation},
+};
+
+/// Steps of every corridor episode.
+pub const CORRIDOR_LENGTH: usize = 10;
+/// Return of the optimal policy, always stepping right.
+pub const OPTIMAL_RETURN: f64 = CORRIDOR_LENGTH as f64;
+
+/// Action moving towards the end of the corridor.
+pub const RIGHT: usize = 1;
+/// Action moving back towards the start, never past it.
+pub const LEFT: usize = 0;
+
+/// A deterministic one-dimensional corridor for exercising RL engines in milliseconds, without
+/// gym or datasets. Episodes are truncated after `CORRIDOR_LENGTH` steps, stepping right earns 1
+/// and stepping left earns nothing, so the optimal return is `OPTIMAL_RETURN`. Programs observe
+/// their position as their single input.
+#[derive(Debug, Clone)]
+pub struct Corridor {
+    position: usize,
+    n_steps: usize,
+    actions: Vec<usize>,
+    /// Steps after which episodes are truncated.
+    length: usize,
+    /// Position terminating episodes once reached.
+    goal: Option<usize>,
+    /// Reward of a step right.
+    reward: f64,
+}
+
+impl Default for Corridor {
+    fn default() -> Self {
+        Corridor::new(CORRIDOR_LENGTH, None)
+    }
+}
+
+impl Corridor {
+    /// A corridor whose episodes are truncated after `length` steps, or terminated as soon as
+    /// `goal` is reached.
+    pub fn new(length: usize, goal: Option<usize>) -> Self {
+        Corridor {
+            position: 0,
+            n_steps: 0,
+            actions: vec![],
+            length,
+            goal,
+            reward: 1.,
+        }
+    }
+
+    /// The same corridor with steps right earning `reward` instead of 1.
+    pub fn with_reward(self, reward: f64) -> Self {
+        Corridor { reward, ..self }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Actions taken in the current episode.
+    pub fn actions(&self) -> &[usize] {
+        &self.actions
+    }
+}
+
+impl State for Corridor {
+    fn get_value(&self, at_idx: usize) -> f64 {
+        [self.position as f64][at_idx]
+    }
+
+    fn execute_action(&mut self, action: usize) -> f64 {
+        self.actions.push(action);
+        self.n_steps += 1;
+
+        if action == RIGHT {
+            self.position += 1;
+            self.reward
+        } else {
+            self.position = self.position.saturating_sub(1);
+            0.
+        }
+    }
+
+    fn get(&mut self) -> Option<&mut Self> {
+        if self.status() != EpisodeStatus::Running {
+            return None;
+        }
+
+        Some(self)
+    }
+}
+
+impl RlState for Corridor {
+    fn is_terminal(&mut self) -> bool {
+        self.status() != EpisodeStatus::Running
+    }
+
+    fn get_initial_state(&self) -> Vec<f64> {
+        vec![0.]
+    }
+
+    fn steps(&self) -> usize {
+        self.n_steps
+    }
+
+    fn status(&mut self) -> EpisodeStatus {
+        if self.goal == Some(self.position) {
+            EpisodeStatus::Terminated
+        } else if self.n_steps >= self.length {
+            EpisodeStatus::Truncated
+        } else {
+            EpisodeStatus::Running
+        }
+    }
+}
+
+impl BehaviorDescriptor for Corridor {
+    /// Final position.
+    fn behavior_descriptor(&self) -> Option<Vec<f64>> {
+        Some(vec![self.position as f64])
+    }
+}
+
+impl Reset<Corridor> for ResetEngine {
+    fn reset(item: &mut Corridor) {
+        *item = Corridor::new(item.length, item.goal).with_reward(item.reward);
+    }
+}
+
+impl Generate<(), Corridor> for GenerateEngine {
+    fn generate(_using: ()) -> Corridor {
+        Corridor::default()
+    }
+}
+
+/// Corridors whose steps right earn `reward`, e.g. to tell the tasks of a multi-task run apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorridorParameters {
+    pub reward: f64,
+}
+
+impl Default for CorridorParameters {
+    fn default() -> Self {
+        CorridorParameters { reward: 1. }
+    }
+}
+
+impl Generate<CorridorParameters, Corridor> for GenerateEngine {
+    fn generate(using: CorridorParameters) -> Corridor {
+        Corridor::default().with_reward(using.reward)
+    }
+}
+
+/// Corridors are observed through one input and acted on with two actions.
+fn require_corridor_shape(
+    violations: &mut Vec<ConfigViolation>,
+    program_parameters: &ProgramGeneratorParameters,
+    prefix: &str,
+) {
+    let instruction_parameters = &program_parameters.instruction_generator_parameters;
+    require(
+        violations,
+        instruction_parameters.n_inputs == 1,
+        &format!("{}.instruction_generator_parameters.n_inputs", prefix),
+        instruction_parameters.n_inputs,
+        "1 for the corridor",
+    );
+    require(
+        violations,
+        instruction_parameters.n_actions == 2,
+        &format!("{}.instruction_generator_parameters.n_actions", prefix),
+        instruction_parameters.n_actions,
+        "2 for the corridor",
+    );
+}
+
+/// Programs acting on the corridor through their output registers.
+#[derive(Clone)]
+pub struct CorridorEngine;
+
+/// Q-programs acting on the corridor through their Q-tables.
+#[derive(Clone)]
+pub struct CorridorQEngine;
+
+/// Like `CorridorEngine`, on corridors generated from `CorridorParameters`.
+#[derive(Clone)]
+pub struct ScaledCorridorEngine;
+
+impl CorridorEngine {
+    /// Program parameters for the corridor, generating up to `max_instructions` instructions.
+    pub fn program_parameters(max_instructions: usize) -> ProgramGeneratorParameters {
+        ProgramGeneratorParametersBuilder::default()
+            .max_instructions(max_instructions)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_actions(2)
+                    .n_inputs(1)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+}
+
+impl CorridorQEngine {
+    /// Like `CorridorEngine::program_parameters`, with default Q-learning constants.
+    pub fn program_parameters(max_instructions: usize) -> QProgramGeneratorParameters {
+        QProgramGeneratorParametersBuilder::default()
+            .program_parameters(CorridorEngine::program_parameters(max_instructions))
+            .build()
+            .unwrap()
+    }
+}
+
+impl Core for CorridorEngine {
+    type Individual = Program;
+    type ProgramParameters = ProgramGeneratorParameters;
+    type State = Corridor;
+    type StateParameters = ();
+    type FitnessMarker = UseRlFitness;
+    type Generate = GenerateEngine;
+    type Fitness = FitnessEngine;
+    type Reset = ResetEngine;
+    type Breed = BreedEngine;
+    type Mutate = MutateEngine;
+    type Status = StatusEngine;
+    type Freeze = FreezeEngine;
+
+    fn validate(hp: &HyperParameters<Self>) -> Vec<ConfigViolation> {
+        let mut violations = hp.program_parameters.violations("program_parameters");
+        require_corridor_shape(
+            &mut violations,
+            &hp.program_parameters,
+            "program_parameters",
+        );
+
+        violations
+    }
+}
+
+impl Core for ScaledCorridorEngine {
+    type Individual = Program;
+    type ProgramParameters = ProgramGeneratorParameters;
+    type State = Corridor;
+    type StateParameters = CorridorParameters;
+    type FitnessMarker = UseRlFitness;
+    type Generate = GenerateEngine;
+    type Fitness = FitnessEngine;
+    type Reset = ResetEngine;
+    type Breed = BreedEngine;
+    type Mutate = MutateEngine;
+    type Status = StatusEngine;
+    type Freeze = FreezeEngine;
+
+    fn validate(hp: &HyperParameters<Self>) -> Vec<ConfigViolation> {
+        let mut violations = hp.program_parameters.violations("program_parameters");
+        require_corridor_shape(
+            &mut violations,
+            &hp.program_parameters,
+            "program_parameters",
+        );
+
+        violations
+    }
+}
+
+impl Core for CorridorQEngine {
+    type Individual = QProgram;
+    type ProgramParameters = QProgramGeneratorParameters;
+    type State = Corridor;
+    type StateParameters = ();
+    type FitnessMarker = ();
+    type Generate = GenerateEngine;
+    type Fitness = FitnessEngine;
+    type Reset = ResetEngine;
+    type Breed = BreedEngine;
+    type Mutate = MutateEngine;
+    type Status = StatusEngine;
+    type Freeze = FreezeEngine;
+
+    fn validate(hp: &HyperParameters<Self>) -> Vec<ConfigViolation> {
+        let mut violations = hp.program_parameters.violations("program_parameters");
+        require_corridor_shape(
+            &mut violations,
+            &hp.program_parameters.program_parameters,
+            "program_parameters.program_parameters",
+        );
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        engines::fitness_engine::Fitness,
+        instruction::{Instruction, Op},
+    };
+
+    use super::*;
+
+    #[test]
+    fn given_program_always_stepping_right_when_evaluated_then_return_is_optimal() {
+        let mut program: Program = GenerateEngine::generate(CorridorEngine::program_parameters(1));
+        // Output register 1 ends up above register 0, so the right action always wins.
+        program.instructions = vec![Instruction::with_constant(RIGHT, Op::Add, 1.)];
+
+        let mut corridor: Corridor = GenerateEngine::generate(());
+        ResetEngine::reset(&mut program);
+        let score = <FitnessEngine as Fitness<_, _, UseRlFitness>>::eval_fitness(
+            &mut program,
+            &mut corridor,
+        );
+
+        assert_eq!(score, OPTIMAL_RETURN);
+        assert_eq!(corridor.position(), CORRIDOR_LENGTH);
+        assert_eq!(corridor.actions(), &[RIGHT; CORRIDOR_LENGTH]);
+        assert_eq!(corridor.steps(), CORRIDOR_LENGTH);
+        assert_eq!(corridor.status(), EpisodeStatus::Truncated);
+
+        let mut corridor = Corridor::new(CORRIDOR_LENGTH, Some(3));
+        ResetEngine::reset(&mut program);
+        let score = <FitnessEngine as Fitness<_, _, UseRlFitness>>::eval_fitness(
+            &mut program,
+            &mut corridor,
+        );
+
+        assert_eq!(score, 3.);
+        assert_eq!(corridor.steps(), 3);
+        assert_eq!(corridor.status(), EpisodeStatus::Terminated);
+    }
+}
@@ -0,0 +1,247 @@
+use std::{
+    collections::HashSet,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+    time::Instant,
+};
+
+use config::{Config, File};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::core::registry::find_problem;
+
+use super::{
+    error::{LgpError, LgpResult},
+    shutdown::stop_requested,
+};
+
+/// A registered problem run as part of a batch, as `lgp run` would run it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchExperiment {
+    /// Name of a registered problem, e.g. `cart-pole-lgp` or `iris`.
+    pub environment: String,
+    /// Hyperparameters to start from; the command line defaults when unset.
+    #[serde(default)]
+    pub config: Option<String>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Directory the outputs are saved under, relative to the output root of the batch; the
+    /// problem's name in snake case by default. Must differ between experiments.
+    #[serde(default)]
+    pub output: Option<String>,
+    #[serde(default)]
+    pub overrides: Vec<String>,
+}
+
+impl BatchExperiment {
+    /// Output directory the experiment saves under.
+    pub fn output(&self) -> LgpResult<String> {
+        match &self.output {
+            Some(output) => Ok(output.clone()),
+            None => Ok(find_problem(&self.environment)?.name().replace('-', "_")),
+        }
+    }
+}
+
+/// Experiments run by `lgp batch`, read from the `[[experiments]]` tables of a file, e.g.
+///
+/// ```toml
+/// [[experiments]]
+/// environment = "iris"
+/// output = "iris_small"
+/// overrides = ["population_size=50"]
+///
+/// [[experiments]]
+/// environment = "cart-pole-lgp"
+/// config = "configs/cart_pole.toml"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchConfig {
+    pub experiments: Vec<BatchExperiment>,
+}
+
+/// How an experiment of a batch ended.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchOutcome {
+    pub output: String,
+    pub environment: String,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+impl BatchConfig {
+    pub fn load(filename: &str) -> LgpResult<Self> {
+        let settings = Config::builder()
+            .add_source(File::with_name(filename))
+            .build()?;
+        let batch: BatchConfig = settings.try_deserialize()?;
+        batch.validate()?;
+
+        Ok(batch)
+    }
+
+    /// Every experiment must name a registered problem and save under its own directory.
+    pub fn validate(&self) -> LgpResult<()> {
+        let mut outputs = HashSet::new();
+
+        for experiment in &self.experiments {
+            let output = experiment.output()?;
+
+            if !outputs.insert(output.clone()) {
+                return Err(LgpError::Config(format!(
+                    "Several experiments save under `{}`; give each its own `output`",
+                    output
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs the experiments of `batch` under `output_root`, up to `parallel` at a time, returning
+/// their outcomes in the order of `batch.experiments`. Failed experiments are reported without
+/// stopping the others; experiments not started yet when a stop is requested are reported as
+/// interrupted.
+pub fn run_batch(
+    batch: &BatchConfig,
+    parallel: usize,
+    output_root: &Path,
+) -> LgpResult<Vec<BatchOutcome>> {
+    batch.validate()?;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(parallel.max(1))
+        .build()
+        .map_err(|error| LgpError::Other(error.to_string()))?;
+
+    let outcomes = pool.install(|| {
+        batch
+            .experiments
+            .par_iter()
+            .map(|experiment| {
+                let output = experiment.output().unwrap();
+                let started = Instant::now();
+
+                let result = if stop_requested() {
+                    Err("Interrupted before the experiment started.".to_string())
+                } else {
+                    info!(
+                        experiment = output.as_str(),
+                        environment = experiment.environment.as_str(),
+                        "Starting experiment."
+                    );
+
+                    catch_unwind(AssertUnwindSafe(|| {
+                        find_problem(&experiment.environment)?.run(
+                            experiment.config.as_deref(),
+                            &experiment.overrides,
+                            experiment.seed,
+                            output_root,
+                            Some(&output),
+                        )
+                    }))
+                    .unwrap_or_else(|_| Err(LgpError::Other("Experiment panicked.".to_string())))
+                    .map_err(|error| error.to_string())
+                };
+
+                let outcome = BatchOutcome {
+                    output,
+                    environment: experiment.environment.clone(),
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    error: result.err(),
+                };
+
+                match &outcome.error {
+                    None => info!(
+                        experiment = outcome.output.as_str(),
+                        duration_ms = outcome.duration_ms,
+                        "Finished experiment."
+                    ),
+                    Some(message) => error!(
+                        experiment = outcome.output.as_str(),
+                        duration_ms = outcome.duration_ms,
+                        "Experiment failed: {}",
+                        message
+                    ),
+                }
+
+                outcome
+            })
+            .collect()
+    });
+
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::utils::manifest::Manifest;
+
+    use super::*;
+
+    fn experiment(output: &str, seed: u64) -> BatchExperiment {
+        BatchExperiment {
+            environment: "iris".to_string(),
+            config: None,
+            seed: Some(seed),
+            output: Some(output.to_string()),
+            overrides: [
+                "population_size=8",
+                "n_generations=2",
+                "n_trials=1",
+                "repeats=2",
+                "dataset_path=assets/fixtures/iris.csv",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+
+    #[test]
+    fn given_two_experiments_when_run_in_parallel_then_each_saves_under_its_own_output(
+    ) -> LgpResult<()> {
+        let root = env::temp_dir().join("lgp_batch");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root)?;
+
+        let batch = BatchConfig {
+            experiments: vec![experiment("batch_a", 1), experiment("batch_b", 100)],
+        };
+        let outcomes = run_batch(&batch, 2, &root)?;
+
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|outcome| outcome.error.is_none()));
+
+        for (output, seed) in [("batch_a", 1), ("batch_b", 100)] {
+            let directory = root.join(output);
+            let run_dirs = fs::read_dir(&directory)?
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().join("best.json").is_file())
+                .count();
+            assert_eq!(run_dirs, 2);
+
+            // Repeats are seeded from their experiment's seed, so a run saved under the wrong
+            // output would carry the other experiment's seeds.
+            for repeat in 0..2 {
+                let manifest = Manifest::read(&directory.join(format!("run_{}", repeat)))?;
+                assert_eq!(manifest.seed, Some(seed + repeat));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_experiments_sharing_an_output_then_the_batch_is_rejected() {
+        let batch = BatchConfig {
+            experiments: vec![experiment("shared", 1), experiment("shared", 2)],
+        };
+
+        assert!(matches!(batch.validate(), Err(LgpError::Config(_))));
+    }
+}
@@ -3,23 +3,63 @@ use std::{
     error::Error,
     fs,
     iter::repeat_with,
+    panic::{catch_unwind, AssertUnwindSafe},
     path::{Path, PathBuf},
+    sync::Once,
 };
 
+use rand::RngCore;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
 use crate::core::{
-    characteristics::{Load, Save},
+    characteristics::{Format, Genotype, Load, Save},
+    diversity::DiversityMetrics,
     engines::generate_engine::Generate,
     engines::{
-        core_engine::{Core, HyperParameters},
+        core_engine::{Core, HyperParameters, StopReason},
+        fitness_engine::FitnessAggregation,
         freeze_engine::Freeze,
-        status_engine::Status,
+        status_engine::{FitnessState, Status},
     },
+    instruction_stats::RunInstructionStats,
+};
+
+use crate::{data::dataset::DatasetParameters, utils::random::generator};
+
+#[cfg(feature = "metrics-export")]
+use super::metrics::MetricsRecorder;
+use super::{
+    error::LgpResult,
+    manifest::{Manifest, RunStatus},
+    misc::VoidResultAnyError,
+    population_stream::{read_populations, PopulationRecorder},
+    shutdown::{stop_flag, stop_requested},
 };
 
-use super::misc::VoidResultAnyError;
+/// Directory outputs are saved under when none is given: `outputs`, or `BENCHMARK_PREFIX` for
+/// setups that still set it.
+pub fn default_output_root() -> PathBuf {
+    static DEPRECATION: Once = Once::new();
+
+    match env::var_os("BENCHMARK_PREFIX") {
+        Some(prefix) => {
+            DEPRECATION.call_once(|| {
+                warn!(
+                    "BENCHMARK_PREFIX is deprecated; pass the output root explicitly, e.g. with \
+                     `--output-root`."
+                )
+            });
+            PathBuf::from(prefix)
+        }
+        None => PathBuf::from("outputs"),
+    }
+}
 
+#[deprecated(note = "pass an explicit output root, `default_output_root()` when none is given")]
 pub fn benchmark_prefix() -> String {
-    env::var("BENCHMARK_PREFIX").expect("BENCHMARK_PREFIX must be set")
+    default_output_root().to_string_lossy().into_owned()
 }
 
 pub fn log_prefix() -> String {
@@ -28,7 +68,7 @@ pub fn log_prefix() -> String {
 
 use itertools::Itertools;
 
-pub fn create_path(path: &str, file: bool) -> Result<PathBuf, Box<dyn Error>> {
+pub fn create_path(path: &str, file: bool) -> LgpResult<PathBuf> {
     let path = Path::new(path);
 
     if let Some(parent) = path.parent() {
@@ -44,58 +84,42 @@ pub fn create_path(path: &str, file: bool) -> Result<PathBuf, Box<dyn Error>> {
     Ok(path.to_owned())
 }
 
+/// Serializes `params` along with the seeds of the initial trials of seeded runs.
+fn params_with_trial_seeds<C>(
+    params: &HyperParameters<C>,
+) -> Result<serde_json::Value, Box<dyn Error>>
+where
+    C: Core,
+{
+    let mut value = serde_json::to_value(params)?;
+    let trial_seeds = (0..params.n_trials)
+        .map(|slot| params.trial_seed(0, slot))
+        .collect::<Option<Vec<_>>>();
+
+    if let (Some(trial_seeds), Some(object)) = (trial_seeds, value.as_object_mut()) {
+        object.insert(
+            "trial_seeds".to_string(),
+            serde_json::to_value(trial_seeds)?,
+        );
+    }
+
+    Ok(value)
+}
+
 pub fn save_experiment<C>(
     populations: &Vec<Vec<C::Individual>>,
     params: &HyperParameters<C>,
+    output_root: &Path,
     test_name: &str,
 ) -> VoidResultAnyError
 where
     C: Core,
 {
-    let best_path = create_path(
-        Path::new(&benchmark_prefix())
-            .join(test_name)
-            .join("best.json")
-            .to_str()
-            .unwrap(),
-        true,
-    )?;
-
-    let median_path = create_path(
-        Path::new(&benchmark_prefix())
-            .join(test_name)
-            .join("median.json")
-            .to_str()
-            .unwrap(),
-        true,
-    )?;
-
-    let worst_path = create_path(
-        Path::new(&benchmark_prefix())
-            .join(test_name)
-            .join("worst.json")
-            .to_str()
-            .unwrap(),
-        true,
-    )?;
-
-    let params_path = create_path(
-        Path::new(&benchmark_prefix())
-            .join(test_name)
-            .join("params.json")
-            .to_str()
-            .unwrap(),
-        true,
-    )?;
+    let run_dir = output_root.join(test_name);
+    let format = params.output_format;
 
-    let plot_path = create_path(
-        Path::new(&benchmark_prefix())
-            .join(test_name)
-            .join("population.json")
-            .to_str()
-            .unwrap(),
-        true,
-    )?;
+    let params_path = create_path(run_dir.join("params.json").to_str().unwrap(), true)?;
+    let metrics_path = create_path(run_dir.join("metrics.csv").to_str().unwrap(), true)?;
 
     let last_population = populations.last().unwrap();
 
@@ -114,15 +138,304 @@ where
     C::Freeze::freeze(&mut median);
     C::Freeze::freeze(&mut best);
 
-    worst.save(worst_path.to_str().unwrap())?;
-    median.save(median_path.to_str().unwrap())?;
-    best.save(best_path.to_str().unwrap())?;
-    params.save(params_path.to_str().unwrap())?;
-    populations.save(plot_path.to_str().unwrap())?;
+    worst.save_as(&format.file_name(&run_dir, "worst"), format)?;
+    median.save_as(&format.file_name(&run_dir, "median"), format)?;
+    best.save_as(&format.file_name(&run_dir, "best"), format)?;
+    params_with_trial_seeds(params)?.save(params_path.to_str().unwrap())?;
+    FitnessCurves::from_populations::<C>(populations).save_csv(&metrics_path)?;
+
+    if let Some(interval) = params.snapshot_interval {
+        save_snapshots::<C>(populations, interval, output_root, test_name, format)?;
+    }
+
+    let champions = populations
+        .iter()
+        .filter_map(|population| population.first().cloned())
+        .map(|mut champion| {
+            C::Freeze::freeze(&mut champion);
+            champion
+        })
+        .collect_vec();
+    champions.save_as(&format.file_name(&run_dir, "champions"), format)?;
+
+    if params.objectives.is_multi_objective() {
+        let front = C::pareto_front(last_population, params.objective)
+            .into_iter()
+            .map(|mut individual| {
+                C::Freeze::freeze(&mut individual);
+                individual
+            })
+            .collect_vec();
+
+        front.save_as(&format.file_name(&run_dir, "pareto"), format)?;
+    }
+
+    if params.track_diversity {
+        let diversity_path = create_path(
+            output_root
+                .join(test_name)
+                .join("diversity.json")
+                .to_str()
+                .unwrap(),
+            true,
+        )?;
+
+        let diversity = populations
+            .iter()
+            .map(|population| DiversityMetrics::compute::<C>(population))
+            .collect_vec();
+
+        diversity.save(diversity_path.to_str().unwrap())?;
+    }
+
+    if params.track_instruction_stats {
+        let stats_path = output_root.join(test_name).join("instruction_stats.json");
+        RunInstructionStats::from_populations::<C>(populations)
+            .save(stats_path.to_str().unwrap())?;
+    }
 
     Ok(())
 }
 
+/// Saves the problem's breakdown of the final champion on `state_parameters`, if it has one, as
+/// `best_report.json`.
+pub fn save_best_report<C>(
+    populations: &[Vec<C::Individual>],
+    state_parameters: &C::StateParameters,
+    output_root: &Path,
+    test_name: &str,
+) -> VoidResultAnyError
+where
+    C: Core,
+{
+    let Some(mut best) = populations
+        .last()
+        .and_then(|population| population.first().cloned())
+    else {
+        return Ok(());
+    };
+    C::Freeze::freeze(&mut best);
+
+    if let Some(report) = C::report(&best, state_parameters)? {
+        let path = output_root.join(test_name).join("best_report.json");
+        report.save(path.to_str().unwrap())?;
+    }
+
+    Ok(())
+}
+
+/// Saves the state the problem's individuals depend on at inference, if any, as
+/// `inference_state.json`.
+pub fn save_inference_state<C>(
+    state_parameters: &C::StateParameters,
+    output_root: &Path,
+    test_name: &str,
+) -> VoidResultAnyError
+where
+    C: Core,
+{
+    if let Some(state) = C::inference_state(state_parameters)? {
+        let path = output_root.join(test_name).join("inference_state.json");
+        state.save(path.to_str().unwrap())?;
+    }
+
+    Ok(())
+}
+
+/// Saves a simplified copy of the final champion, if the problem supports it, as
+/// `best_simplified`.
+pub fn save_simplified_best<C>(
+    populations: &[Vec<C::Individual>],
+    state_parameters: &C::StateParameters,
+    output_root: &Path,
+    test_name: &str,
+    format: Format,
+) -> VoidResultAnyError
+where
+    C: Core,
+{
+    let simplified = populations
+        .last()
+        .and_then(|population| population.first())
+        .and_then(|best| C::simplify(best, state_parameters));
+
+    if let Some(mut simplified) = simplified {
+        C::Freeze::freeze(&mut simplified);
+        let run_dir = output_root.join(test_name);
+        simplified.save_as(&format.file_name(&run_dir, "best_simplified"), format)?;
+    }
+
+    Ok(())
+}
+
+/// Fitness of a generation saved alongside its snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GenerationSummary {
+    /// Number of generations evaluated, counting the initial population as the first.
+    pub generation: usize,
+    pub best: f64,
+    pub median: f64,
+    pub worst: f64,
+    pub population_size: usize,
+}
+
+/// Saves the frozen champion and a summary of every `interval`-th generation under
+/// `<test_name>/gen_<n>`.
+fn save_snapshots<C>(
+    populations: &[Vec<C::Individual>],
+    interval: usize,
+    output_root: &Path,
+    test_name: &str,
+    format: Format,
+) -> VoidResultAnyError
+where
+    C: Core,
+{
+    for (index, population) in populations.iter().enumerate() {
+        let generation = index + 1;
+
+        if generation % interval != 0 || population.is_empty() {
+            continue;
+        }
+
+        let directory = output_root
+            .join(test_name)
+            .join(format!("gen_{}", generation));
+
+        let summary_path = create_path(directory.join("summary.json").to_str().unwrap(), true)?;
+
+        let mut best = population[0].clone();
+        C::Freeze::freeze(&mut best);
+        best.save_as(&format.file_name(&directory, "best"), format)?;
+
+        GenerationSummary {
+            generation,
+            best: C::Status::get_fitness(&population[0]),
+            median: C::Status::get_fitness(&population[population.len() / 2]),
+            worst: C::Status::get_fitness(&population[population.len() - 1]),
+            population_size: population.len(),
+        }
+        .save(summary_path.to_str().unwrap())?;
+    }
+
+    Ok(())
+}
+
+/// Best, median and worst fitness of every generation of a run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FitnessCurves {
+    pub best: Vec<f64>,
+    pub median: Vec<f64>,
+    pub worst: Vec<f64>,
+}
+
+impl FitnessCurves {
+    /// Expects every population to be ranked, best first.
+    pub fn from_populations<C>(populations: &[Vec<C::Individual>]) -> Self
+    where
+        C: Core,
+    {
+        let mut curves = FitnessCurves::default();
+
+        for population in populations.iter().filter(|p| !p.is_empty()) {
+            curves.best.push(C::Status::get_fitness(&population[0]));
+            curves
+                .median
+                .push(C::Status::get_fitness(&population[population.len() / 2]));
+            curves
+                .worst
+                .push(C::Status::get_fitness(&population[population.len() - 1]));
+        }
+
+        curves
+    }
+
+    pub fn len(&self) -> usize {
+        self.best.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.best.is_empty()
+    }
+
+    /// Writes `generation,best,median,worst` rows.
+    pub fn save_csv(&self, path: &Path) -> VoidResultAnyError {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(["generation", "best", "median", "worst"])?;
+
+        for generation in 0..self.len() {
+            writer.write_record([
+                generation.to_string(),
+                self.best[generation].to_string(),
+                self.median[generation].to_string(),
+                self.worst[generation].to_string(),
+            ])?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Loads the curves of a run directory from `metrics.csv`, falling back to the recorded
+    /// `population.jsonl.gz` or the `population.json` written by older runs.
+    pub fn load_run(run_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let metrics_path = run_dir.join("metrics.csv");
+        let stream_path = run_dir.join("population.jsonl.gz");
+
+        if metrics_path.exists() {
+            return Self::load_csv(&metrics_path);
+        }
+
+        let populations: Vec<Vec<serde_json::Value>> = if stream_path.exists() {
+            read_populations(&stream_path)?
+        } else {
+            serde_json::from_str(&fs::read_to_string(run_dir.join("population.json"))?)?
+        };
+
+        let mut curves = FitnessCurves::default();
+        for population in populations.iter().filter(|p| !p.is_empty()) {
+            let fitness = |individual: &serde_json::Value| -> Result<f64, Box<dyn Error>> {
+                // Q-learning individuals nest their program.
+                let program = individual.get("program").unwrap_or(individual);
+                let fitness: FitnessState = serde_json::from_value(
+                    program.get("fitness").cloned().ok_or("Missing fitness.")?,
+                )?;
+
+                Ok(fitness.fitness())
+            };
+
+            curves.best.push(fitness(&population[0])?);
+            curves
+                .median
+                .push(fitness(&population[population.len() / 2])?);
+            curves
+                .worst
+                .push(fitness(&population[population.len() - 1])?);
+        }
+
+        Ok(curves)
+    }
+
+    pub fn load_csv(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut reader = csv::Reader::from_path(path)?;
+        let mut curves = FitnessCurves::default();
+
+        for record in reader.records() {
+            let record = record?;
+            let value = |idx: usize| -> Result<f64, Box<dyn Error>> {
+                Ok(record.get(idx).ok_or("Missing column.")?.parse::<f64>()?)
+            };
+
+            curves.best.push(value(1)?);
+            curves.median.push(value(2)?);
+            curves.worst.push(value(3)?);
+        }
+
+        Ok(curves)
+    }
+}
+
 pub fn load_and_run_program<C>(
     program_path: impl Into<PathBuf> + Clone,
     n_trials: usize,
@@ -131,17 +444,439 @@ pub fn load_and_run_program<C>(
 where
     C: Core,
 {
-    let program = C::Individual::load(program_path);
+    let program = C::Individual::load(program_path)?;
     let original_fitness = C::Status::get_fitness(&program);
 
-    let mut trials: Vec<C::State> = repeat_with(|| C::Generate::generate(()))
+    let state_parameters = C::default_state_parameters()?;
+    let mut trials: Vec<C::State> = repeat_with(|| C::Generate::generate(state_parameters.clone()))
         .take(n_trials)
         .collect_vec();
 
     let mut population = vec![program];
-    C::eval_fitness(&mut population, &mut trials, default_fitness);
+    C::eval_fitness(
+        &mut population,
+        &mut trials,
+        default_fitness,
+        FitnessAggregation::default(),
+    );
 
     let new_fitness = C::Status::get_fitness(population.first().unwrap());
 
     Ok((original_fitness, new_fitness))
 }
+
+/// k-fold cross-validation of a classification experiment.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CrossValidation {
+    pub folds: usize,
+    /// Preserve the class proportions in every fold.
+    #[serde(default)]
+    pub stratified: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FoldResult {
+    pub fold: usize,
+    pub n_train: usize,
+    pub n_validation: usize,
+    /// Fitness of the champion on the training folds.
+    pub train_accuracy: f64,
+    /// Fitness of the champion on the held-out fold.
+    pub validation_accuracy: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrossValidationResults {
+    pub folds: Vec<FoldResult>,
+    pub mean_accuracy: f64,
+    /// Population standard deviation of the validation accuracies.
+    pub std_accuracy: f64,
+}
+
+/// Runs one evolution per fold, using the training folds as fitness cases, and evaluates the
+/// champion of the last generation on the held-out fold.
+///
+/// Folds are drawn from `params.seed` (0 when unset), so runs with the same seed validate on
+/// the same partitions. Every fold keeps the other settings of `state_parameters`, but the
+/// held-out fold is evaluated on every row once, without trial sampling.
+pub fn cross_validate<C>(
+    params: &HyperParameters<C>,
+    state_parameters: &C::StateParameters,
+    cross_validation: CrossValidation,
+) -> CrossValidationResults
+where
+    C: Core,
+    C::StateParameters: DatasetParameters,
+{
+    let dataset = state_parameters.dataset();
+    let seed = params.seed.unwrap_or_default();
+    let partitions = if cross_validation.stratified {
+        dataset.stratified_k_folds(cross_validation.folds, seed)
+    } else {
+        dataset.k_folds(cross_validation.folds, seed)
+    };
+
+    let folds = partitions
+        .into_iter()
+        .enumerate()
+        .map(|(fold, (train, validation))| {
+            let (n_train, n_validation) = (train.len(), validation.len());
+
+            let champion = params
+                .build_engine_with(state_parameters.with_dataset(train))
+                .take(params.n_generations)
+                .last()
+                .and_then(|population| population.first().cloned())
+                .expect("Evolution to yield at least one individual.");
+            let train_accuracy = C::Status::get_fitness(&champion);
+
+            let mut trials = vec![C::Generate::generate(
+                state_parameters.with_dataset(validation).without_sampling(),
+            )];
+            let mut population = vec![champion];
+            C::eval_fitness(
+                &mut population,
+                &mut trials,
+                params.fallback_fitness(),
+                params.fitness_aggregation,
+            );
+            let validation_accuracy = C::Status::get_fitness(population.first().unwrap());
+
+            FoldResult {
+                fold,
+                n_train,
+                n_validation,
+                train_accuracy,
+                validation_accuracy,
+            }
+        })
+        .collect_vec();
+
+    let accuracies = folds
+        .iter()
+        .map(|fold| fold.validation_accuracy)
+        .collect_vec();
+    let n_folds = accuracies.len().max(1) as f64;
+    let mean_accuracy = accuracies.iter().sum::<f64>() / n_folds;
+    let std_accuracy = (accuracies
+        .iter()
+        .map(|accuracy| (accuracy - mean_accuracy).powi(2))
+        .sum::<f64>()
+        / n_folds)
+        .sqrt();
+
+    CrossValidationResults {
+        folds,
+        mean_accuracy,
+        std_accuracy,
+    }
+}
+
+pub fn save_cross_validation(
+    results: &CrossValidationResults,
+    output_root: &Path,
+    test_name: &str,
+) -> VoidResultAnyError {
+    let results_path = create_path(
+        output_root
+            .join(test_name)
+            .join("cv_results.json")
+            .to_str()
+            .unwrap(),
+        true,
+    )?;
+
+    results.save(results_path.to_str().unwrap())?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SummaryStatistics {
+    pub mean: f64,
+    pub median: f64,
+    /// Population standard deviation.
+    pub std: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl SummaryStatistics {
+    pub fn from_values(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return SummaryStatistics::default();
+        }
+
+        let n = values.len() as f64;
+        let sorted = values
+            .iter()
+            .copied()
+            .sorted_by(f64::total_cmp)
+            .collect_vec();
+
+        let mean = sorted.iter().sum::<f64>() / n;
+        let middle = sorted.len() / 2;
+        let median = if sorted.len() % 2 == 0 {
+            (sorted[middle - 1] + sorted[middle]) / 2.
+        } else {
+            sorted[middle]
+        };
+        let std = (sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+
+        SummaryStatistics {
+            mean,
+            median,
+            std,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepeatFailure {
+    pub repeat: usize,
+    pub seed: u64,
+    pub error: String,
+}
+
+/// Best fitness across independent repeats of the same configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepeatAggregate {
+    pub n_repeats: usize,
+    pub seeds: Vec<u64>,
+    /// Repeats that failed and were left out of the statistics.
+    pub failures: Vec<RepeatFailure>,
+    /// Statistics of the best fitness of every generation.
+    pub best_fitness: Vec<SummaryStatistics>,
+    /// Statistics of the best fitness of the last generation.
+    pub final_best_fitness: SummaryStatistics,
+}
+
+/// Runs one seeded evolution saved under `output_root/run_name`, returning the best fitness per
+/// generation.
+///
+/// A `manifest.json` is written when the run starts and completed with the checksums of every
+/// saved file, or marked as failed with the error.
+pub(crate) fn run_repeat<C>(
+    params: &HyperParameters<C>,
+    state_parameters: &C::StateParameters,
+    output_root: &Path,
+    run_name: &str,
+) -> Result<Vec<f64>, Box<dyn Error>>
+where
+    C: Core,
+{
+    let run_dir = output_root.join(run_name);
+    let mut manifest = Manifest::start(params)?;
+    manifest.write(&run_dir)?;
+
+    let result = evolve_and_save(params, state_parameters, output_root, run_name);
+
+    match &result {
+        Ok((_, stopped_reason)) => {
+            manifest.stopped_reason = *stopped_reason;
+            manifest.complete(&run_dir)?;
+            if *stopped_reason == Some(StopReason::Interrupted) {
+                manifest.status = RunStatus::Interrupted;
+            }
+        }
+        Err(error) => manifest.fail(error),
+    }
+    manifest.write(&run_dir)?;
+
+    result.map(|(best_fitness, _)| best_fitness)
+}
+
+/// Evolves and saves a run, returning its best fitness per generation and why it stopped early,
+/// if it did.
+fn evolve_and_save<C>(
+    params: &HyperParameters<C>,
+    state_parameters: &C::StateParameters,
+    output_root: &Path,
+    run_name: &str,
+) -> Result<(Vec<f64>, Option<StopReason>), Box<dyn Error>>
+where
+    C: Core,
+{
+    let mut engine = params
+        .try_build_engine_with(state_parameters.clone())?
+        .with_stop_flag(stop_flag());
+    #[cfg(feature = "metrics-export")]
+    {
+        let experiment = match run_name.rsplit_once('/') {
+            Some((experiment, repeat)) if repeat.starts_with("run_") => experiment,
+            _ => run_name,
+        };
+        engine = engine.with_metrics(MetricsRecorder::new(experiment, run_name));
+    }
+    if params.save_full_population {
+        let path = output_root.join(run_name).join("population.jsonl.gz");
+        engine = engine.with_observer(Box::new(PopulationRecorder::<C>::create(&path)?));
+    }
+
+    let populations = catch_unwind(AssertUnwindSafe(|| {
+        engine.by_ref().take(params.n_generations).collect_vec()
+    }))
+    .map_err(|panic| {
+        panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Repeat panicked.".to_string())
+    })?;
+    let novelty_archive = params
+        .novelty
+        .is_some()
+        .then(|| engine.novelty_archive().clone());
+    let hall_of_fame = engine.hall_of_fame().cloned();
+    let lineage = engine.lineage().and_then(|lineage| {
+        let best = populations.last()?.first()?;
+        best.id().map(|id| lineage.ancestry(id))
+    });
+    let stopped_reason = engine.stop_reason();
+    // Completes the population stream, if any.
+    drop(engine);
+
+    if populations.is_empty() {
+        return Err("Repeat produced no generations.".into());
+    }
+
+    save_experiment(&populations, params, output_root, run_name)?;
+    if let Some(novelty_archive) = novelty_archive {
+        let path = output_root.join(run_name).join("novelty_archive.json");
+        novelty_archive.save(path.to_str().unwrap())?;
+    }
+    if let Some(hall_of_fame) = hall_of_fame {
+        let path = output_root.join(run_name).join("hall_of_fame.json");
+        hall_of_fame.save(path.to_str().unwrap())?;
+    }
+    if let Some(lineage) = lineage {
+        let run_dir = output_root.join(run_name);
+        lineage.save(run_dir.join("lineage.json").to_str().unwrap())?;
+        if params.lineage_dot {
+            fs::write(run_dir.join("lineage.dot"), lineage.to_dot())?;
+        }
+    }
+    save_best_report::<C>(&populations, state_parameters, output_root, run_name)?;
+    save_inference_state::<C>(state_parameters, output_root, run_name)?;
+    if params.export_simplified {
+        save_simplified_best::<C>(
+            &populations,
+            state_parameters,
+            output_root,
+            run_name,
+            params.output_format,
+        )?;
+    }
+
+    let best_fitness = populations
+        .iter()
+        .filter_map(|population| population.first().map(C::Status::get_fitness))
+        .collect();
+
+    Ok((best_fitness, stopped_reason))
+}
+
+/// Runs `n_repeats` independent evolutions, seeding repeat `i` with `seed + i` (a random base
+/// seed when unset) and saving each under `<output_root>/<test_name>/run_<i>`. Failed repeats are
+/// recorded and skipped; the aggregate is written to `aggregate.json` and `aggregate.csv`.
+pub fn run_repeats<C>(
+    params: &HyperParameters<C>,
+    state_parameters: C::StateParameters,
+    n_repeats: usize,
+    output_root: &Path,
+    test_name: &str,
+) -> Result<RepeatAggregate, Box<dyn Error>>
+where
+    C: Core,
+{
+    let base_seed = params.seed.unwrap_or_else(|| generator().next_u64());
+
+    let mut seeds = vec![];
+    let mut failures = vec![];
+    let mut curves = vec![];
+
+    for repeat in 0..n_repeats {
+        if stop_requested() {
+            warn!(
+                "Interrupted, skipping the remaining {} repeats.",
+                n_repeats - repeat
+            );
+            break;
+        }
+
+        let seed = base_seed.wrapping_add(repeat as u64);
+        let mut repeat_params = params.clone();
+        repeat_params.seed = Some(seed);
+        seeds.push(seed);
+
+        let run_name = format!("{}/run_{}", test_name, repeat);
+
+        match run_repeat(&repeat_params, &state_parameters, output_root, &run_name) {
+            Ok(curve) => curves.push(curve),
+            Err(error) => failures.push(RepeatFailure {
+                repeat,
+                seed,
+                error: error.to_string(),
+            }),
+        }
+    }
+
+    let n_generations = curves.iter().map(Vec::len).max().unwrap_or(0);
+    let best_fitness = (0..n_generations)
+        .map(|generation| {
+            let values = curves
+                .iter()
+                .filter_map(|curve| curve.get(generation).copied())
+                .collect_vec();
+            SummaryStatistics::from_values(&values)
+        })
+        .collect_vec();
+    let final_values = curves
+        .iter()
+        .filter_map(|curve| curve.last().copied())
+        .collect_vec();
+
+    let aggregate = RepeatAggregate {
+        n_repeats,
+        seeds,
+        failures,
+        best_fitness,
+        final_best_fitness: SummaryStatistics::from_values(&final_values),
+    };
+
+    save_aggregate(&aggregate, output_root, test_name)?;
+
+    Ok(aggregate)
+}
+
+pub fn save_aggregate(
+    aggregate: &RepeatAggregate,
+    output_root: &Path,
+    test_name: &str,
+) -> VoidResultAnyError {
+    let directory = output_root.join(test_name);
+
+    let json_path = create_path(directory.join("aggregate.json").to_str().unwrap(), true)?;
+    aggregate.save(json_path.to_str().unwrap())?;
+
+    let csv_path = create_path(directory.join("aggregate.csv").to_str().unwrap(), true)?;
+    let mut writer = csv::Writer::from_path(csv_path)?;
+    writer.write_record(["generation", "mean", "median", "std", "min", "max"])?;
+
+    for (generation, statistics) in aggregate.best_fitness.iter().enumerate() {
+        writer.write_record([
+            generation.to_string(),
+            statistics.mean.to_string(),
+            statistics.median.to_string(),
+            statistics.std.to_string(),
+            statistics.min.to_string(),
+            statistics.max.to_string(),
+        ])?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
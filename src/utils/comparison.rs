@@ -0,0 +1,355 @@
+use std::{error::Error, f64::consts::SQRT_2, fs, path::Path};
+
+use itertools::Itertools;
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+
+use crate::core::engines::fitness_engine::FitnessAggregation;
+
+use super::benchmark_tools::{FitnessCurves, SummaryStatistics};
+
+const N_BOOTSTRAP: usize = 1000;
+const CONFIDENCE: f64 = 0.95;
+
+/// Difference of the mean best fitness of run A and run B at one generation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GenerationDifference {
+    pub generation: usize,
+    pub mean_difference: f64,
+    /// Bootstrap confidence interval of the difference.
+    pub lower: f64,
+    pub upper: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComparisonReport {
+    pub final_best_a: SummaryStatistics,
+    pub final_best_b: SummaryStatistics,
+    /// Mann-Whitney U of run A on the final best fitness of every repeat.
+    pub u_statistic: f64,
+    /// Two-sided p-value of the rank-sum test (normal approximation with tie correction).
+    pub p_value: f64,
+    /// Rank-biserial correlation, from `-1` (B always better) to `1` (A always better).
+    pub effect_size: f64,
+    pub generations: Vec<GenerationDifference>,
+}
+
+/// Best fitness curves of a run: one per `run_*/metrics.csv` repeat, or the run's own
+/// `metrics.csv`.
+fn load_best_curves(run_dir: &Path) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    let mut directories = fs::read_dir(run_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.join("metrics.csv").is_file())
+        .sorted()
+        .collect_vec();
+
+    if directories.is_empty() {
+        directories.push(run_dir.to_owned());
+    }
+
+    directories
+        .iter()
+        .map(|directory| Ok(FitnessCurves::load_csv(&directory.join("metrics.csv"))?.best))
+        .collect()
+}
+
+/// Compares the saved metrics of two runs (single or repeated).
+pub fn compare_runs(run_a: &Path, run_b: &Path) -> Result<ComparisonReport, Box<dyn Error>> {
+    let curves_a = load_best_curves(run_a)?;
+    let curves_b = load_best_curves(run_b)?;
+
+    Ok(compare_curves(&curves_a, &curves_b))
+}
+
+/// Compares two sets of best fitness curves, one curve per repeat.
+pub fn compare_curves(curves_a: &[Vec<f64>], curves_b: &[Vec<f64>]) -> ComparisonReport {
+    let final_best = |curves: &[Vec<f64>]| {
+        curves
+            .iter()
+            .filter_map(|curve| curve.last().copied())
+            .collect_vec()
+    };
+    let final_a = final_best(curves_a);
+    let final_b = final_best(curves_b);
+
+    let (u_statistic, p_value) = mann_whitney_u(&final_a, &final_b);
+    let n_pairs = (final_a.len() * final_b.len()) as f64;
+    let effect_size = if n_pairs > 0. {
+        2. * u_statistic / n_pairs - 1.
+    } else {
+        0.
+    };
+
+    let n_generations = curves_a
+        .iter()
+        .chain(curves_b.iter())
+        .map(Vec::len)
+        .min()
+        .unwrap_or(0);
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+
+    let generations = (0..n_generations)
+        .map(|generation| {
+            let values_a = curves_a.iter().map(|curve| curve[generation]).collect_vec();
+            let values_b = curves_b.iter().map(|curve| curve[generation]).collect_vec();
+            let (lower, upper) = bootstrap_interval(&values_a, &values_b, &mut rng);
+
+            GenerationDifference {
+                generation,
+                mean_difference: mean(&values_a) - mean(&values_b),
+                lower,
+                upper,
+            }
+        })
+        .collect();
+
+    ComparisonReport {
+        final_best_a: SummaryStatistics::from_values(&final_a),
+        final_best_b: SummaryStatistics::from_values(&final_b),
+        u_statistic,
+        p_value,
+        effect_size,
+        generations,
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Percentile interval of the difference of means, resampling both runs with replacement.
+fn bootstrap_interval(a: &[f64], b: &[f64], rng: &mut impl Rng) -> (f64, f64) {
+    let mut resample = |values: &[f64]| {
+        mean(
+            &(0..values.len())
+                .map(|_| values[rng.gen_range(0..values.len())])
+                .collect_vec(),
+        )
+    };
+
+    let differences = (0..N_BOOTSTRAP)
+        .map(|_| resample(a) - resample(b))
+        .collect_vec();
+    let tail = (1. - CONFIDENCE) / 2.;
+
+    (
+        FitnessAggregation::Quantile(tail).aggregate(&differences, f64::NAN),
+        FitnessAggregation::Quantile(1. - tail).aggregate(&differences, f64::NAN),
+    )
+}
+
+/// Returns the U statistic of `a` and the two-sided p-value of the Mann-Whitney U test.
+pub fn mann_whitney_u(a: &[f64], b: &[f64]) -> (f64, f64) {
+    let (n_a, n_b) = (a.len() as f64, b.len() as f64);
+    if a.is_empty() || b.is_empty() {
+        return (0., 1.);
+    }
+
+    let values = a
+        .iter()
+        .map(|value| (*value, true))
+        .chain(b.iter().map(|value| (*value, false)))
+        .sorted_by(|x, y| x.0.total_cmp(&y.0))
+        .collect_vec();
+
+    // Tied values share the average of their ranks.
+    let mut rank_sum_a = 0.;
+    let mut tie_term = 0.;
+    let mut start = 0;
+
+    while start < values.len() {
+        let end = (start..values.len())
+            .find(|idx| values[*idx].0.total_cmp(&values[start].0).is_ne())
+            .unwrap_or(values.len());
+        let n_tied = (end - start) as f64;
+        let rank = (start + end + 1) as f64 / 2.;
+
+        rank_sum_a += rank * values[start..end].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        tie_term += n_tied.powi(3) - n_tied;
+        start = end;
+    }
+
+    let u_a = rank_sum_a - n_a * (n_a + 1.) / 2.;
+    let n = n_a + n_b;
+    let mu = n_a * n_b / 2.;
+    let sigma = (n_a * n_b / 12. * ((n + 1.) - tie_term / (n * (n - 1.)))).sqrt();
+
+    if !(sigma > 0.) {
+        return (u_a, 1.);
+    }
+
+    let z = ((u_a - mu).abs() - 0.5).max(0.) / sigma;
+    let p_value = (2. * normal_survival(z)).min(1.);
+
+    (u_a, p_value)
+}
+
+/// Returns the signed-rank sum of the positive differences `a - b` and the two-sided p-value of
+/// the Wilcoxon signed-rank test of paired samples (normal approximation with tie correction).
+/// Pairs with equal values are dropped.
+pub fn wilcoxon_signed_rank(a: &[f64], b: &[f64]) -> (f64, f64) {
+    let differences = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| a - b)
+        .filter(|difference| *difference != 0.)
+        .sorted_by(|x, y| x.abs().total_cmp(&y.abs()))
+        .collect_vec();
+    let n = differences.len() as f64;
+
+    if differences.is_empty() {
+        return (0., 1.);
+    }
+
+    // Tied magnitudes share the average of their ranks.
+    let mut w_plus = 0.;
+    let mut tie_term = 0.;
+    let mut start = 0;
+
+    while start < differences.len() {
+        let magnitude = differences[start].abs();
+        let end = (start..differences.len())
+            .find(|idx| differences[*idx].abs().total_cmp(&magnitude).is_ne())
+            .unwrap_or(differences.len());
+        let n_tied = (end - start) as f64;
+        let rank = (start + end + 1) as f64 / 2.;
+
+        w_plus += rank
+            * differences[start..end]
+                .iter()
+                .filter(|difference| **difference > 0.)
+                .count() as f64;
+        tie_term += n_tied.powi(3) - n_tied;
+        start = end;
+    }
+
+    let mu = n * (n + 1.) / 4.;
+    let sigma = (n * (n + 1.) * (2. * n + 1.) / 24. - tie_term / 48.).sqrt();
+
+    if !(sigma > 0.) {
+        return (w_plus, 1.);
+    }
+
+    let z = ((w_plus - mu).abs() - 0.5).max(0.) / sigma;
+    let p_value = (2. * normal_survival(z)).min(1.);
+
+    (w_plus, p_value)
+}
+
+fn normal_survival(z: f64) -> f64 {
+    0.5 * erfc(z / SQRT_2)
+}
+
+/// Complementary error function (Chebyshev fit with a fractional error below 1.2e-7).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1. / (1. + 0.5 * z);
+    let r = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398
+                                    + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+
+    if x >= 0. {
+        r
+    } else {
+        2. - r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::core::characteristics::{Load, Save};
+
+    use super::*;
+
+    /// Ten repeats whose best fitness climbs towards `offset`.
+    fn curves(offset: f64) -> Vec<Vec<f64>> {
+        (0..10)
+            .map(|repeat| {
+                (0..5)
+                    .map(|generation| offset + generation as f64 + 0.1 * repeat as f64)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn given_known_difference_when_compared_then_p_value_and_effect_follow_it() {
+        let better = compare_curves(&curves(10.), &curves(5.));
+        assert!(better.p_value < 0.01);
+        assert_eq!(better.effect_size, 1.);
+        assert!(better.generations.iter().all(|generation| {
+            (generation.mean_difference - 5.).abs() < 1e-9 && generation.lower > 0.
+        }));
+
+        let worse = compare_curves(&curves(5.), &curves(10.));
+        assert!(worse.p_value < 0.01);
+        assert_eq!(worse.effect_size, -1.);
+
+        let same = compare_curves(&curves(5.), &curves(5.));
+        assert!(same.p_value > 0.5);
+        assert_eq!(same.effect_size, 0.);
+    }
+
+    #[test]
+    fn given_paired_samples_when_signed_rank_tested_then_consistent_shifts_are_significant() {
+        let b = (0..12).map(|repeat| repeat as f64).collect_vec();
+        let shifted = b.iter().map(|value| value + 0.5).collect_vec();
+
+        let (w_plus, p_value) = wilcoxon_signed_rank(&shifted, &b);
+        assert_eq!(w_plus, 78.);
+        assert!(p_value < 0.01);
+
+        let (w_plus, p_value) = wilcoxon_signed_rank(&b, &shifted);
+        assert_eq!(w_plus, 0.);
+        assert!(p_value < 0.01);
+
+        // Identical pairs carry no evidence.
+        assert_eq!(wilcoxon_signed_rank(&b, &b), (0., 1.));
+
+        let mixed = b
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| value + if idx % 2 == 0 { 1. } else { -1. })
+            .collect_vec();
+        assert!(wilcoxon_signed_rank(&mixed, &b).1 > 0.5);
+    }
+
+    #[test]
+    fn given_saved_repeats_when_compared_then_report_round_trips() -> Result<(), Box<dyn Error>> {
+        let root = env::temp_dir().join("lgp_comparison");
+
+        for (name, offset) in [("a", 10.), ("b", 5.)] {
+            for (repeat, best) in curves(offset).into_iter().enumerate() {
+                let directory = root.join(name).join(format!("run_{}", repeat));
+                fs::create_dir_all(&directory)?;
+
+                FitnessCurves {
+                    median: best.clone(),
+                    worst: best.clone(),
+                    best,
+                }
+                .save_csv(&directory.join("metrics.csv"))?;
+            }
+        }
+
+        let report = compare_runs(&root.join("a"), &root.join("b"))?;
+        assert!((report.final_best_a.mean - 14.45).abs() < 1e-9);
+        assert_eq!(report.generations.len(), 5);
+
+        let path = root.join("report.json");
+        report.save(path.to_str().unwrap())?;
+        assert_eq!(ComparisonReport::load(&path)?, report);
+
+        Ok(())
+    }
+}
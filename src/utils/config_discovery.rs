@@ -0,0 +1,363 @@
+use std::{fs, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::registry::{ProblemDescriptor, PROBLEMS};
+
+use super::error::{LgpError, LgpResult};
+
+/// Extensions of the files `config` can read hyperparameters from.
+const CONFIG_EXTENSIONS: &[&str] = &["json", "toml", "yaml", "yml", "ini", "ron", "json5"];
+
+/// Summary of a hyperparameter file, parsed with the problem its name starts with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigInfo {
+    /// File name without its extension, e.g. `cart-pole-lgp`.
+    pub name: String,
+    /// Path of the file relative to the discovered directory.
+    pub path: String,
+    /// Registered problem the file is parsed with.
+    pub environment: Option<String>,
+    pub population_size: Option<usize>,
+    pub n_generations: Option<usize>,
+    pub mutation_percent: Option<f64>,
+    pub crossover_percent: Option<f64>,
+    /// Whether programs are trained with Q-learning, i.e. the config has Q-learning constants.
+    pub q_learning: Option<bool>,
+    pub seed_pinned: Option<bool>,
+    /// Why the file could not be parsed.
+    pub error: Option<String>,
+}
+
+/// A `key=value` predicate on the configs listed by `lgp list --configs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfigFilter {
+    /// The environment contains the value, ignoring case, `-` and `_`; `CartPole` matches
+    /// `cart-pole-lgp`.
+    Environment(String),
+    QLearning(bool),
+    SeedPinned(bool),
+}
+
+impl FromStr for ConfigFilter {
+    type Err = LgpError;
+
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        let (key, value) = filter.split_once('=').ok_or_else(|| {
+            LgpError::Config(format!("Filter `{}` must have the form key=value", filter))
+        })?;
+        let flag = || {
+            value
+                .trim()
+                .parse::<bool>()
+                .map_err(|_| LgpError::Config(format!("Filter `{}` expects true or false", filter)))
+        };
+
+        match key.trim() {
+            "env" | "environment" => Ok(ConfigFilter::Environment(value.trim().to_string())),
+            "q_learning" => Ok(ConfigFilter::QLearning(flag()?)),
+            "seed_pinned" => Ok(ConfigFilter::SeedPinned(flag()?)),
+            key => Err(LgpError::Config(format!(
+                "Unknown filter `{}`; expected env, q_learning or seed_pinned",
+                key
+            ))),
+        }
+    }
+}
+
+impl ConfigFilter {
+    /// Whether `config` satisfies the predicate; fields that could not be parsed satisfy none.
+    pub fn matches(&self, config: &ConfigInfo) -> bool {
+        let normalize = |name: &str| name.replace(['-', '_'], "").to_lowercase();
+
+        match self {
+            ConfigFilter::Environment(environment) => {
+                config.environment.as_deref().map_or(false, |name| {
+                    normalize(name).contains(&normalize(environment))
+                })
+            }
+            ConfigFilter::QLearning(q_learning) => config.q_learning == Some(*q_learning),
+            ConfigFilter::SeedPinned(seed_pinned) => config.seed_pinned == Some(*seed_pinned),
+        }
+    }
+}
+
+/// Every hyperparameter file under `directory`, sorted by path. Files that cannot be parsed are
+/// listed with an `error`.
+pub fn discover_configs(directory: &Path) -> LgpResult<Vec<ConfigInfo>> {
+    if !directory.is_dir() {
+        return Err(LgpError::Config(format!(
+            "`{}` is not a directory",
+            directory.display()
+        )));
+    }
+
+    let mut configs = vec![];
+    let mut pending = vec![directory.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        for path in fs::read_dir(&current)?.filter_map(|entry| entry.ok().map(|e| e.path())) {
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_config(&path) {
+                configs.push(config_info(directory, &path));
+            }
+        }
+    }
+
+    configs.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(configs)
+}
+
+/// The configs as the table printed by `lgp list --configs`, one row per config followed by its
+/// parse error, if any.
+pub fn config_table(configs: &[ConfigInfo]) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let percent = |value: Option<f64>| optional(value.map(|value| format!("{:.0}%", value * 100.)));
+    let flag = |value: Option<bool>, shown: &str| {
+        optional(value.map(|value| match value {
+            true => shown.to_string(),
+            false => "no".to_string(),
+        }))
+    };
+
+    let mut table = format!(
+        "{:<32} {:<18} {:>10} {:>11} {:>8} {:>9} {:>10} {:>6}\n",
+        "config",
+        "environment",
+        "population",
+        "generations",
+        "mutation",
+        "crossover",
+        "q-learning",
+        "seed"
+    );
+
+    for config in configs {
+        table += &format!(
+            "{:<32} {:<18} {:>10} {:>11} {:>8} {:>9} {:>10} {:>6}\n",
+            config.path,
+            optional(config.environment.clone()),
+            optional(config.population_size.map(|size| size.to_string())),
+            optional(config.n_generations.map(|n| n.to_string())),
+            percent(config.mutation_percent),
+            percent(config.crossover_percent),
+            flag(config.q_learning, "yes"),
+            flag(config.seed_pinned, "pinned")
+        );
+
+        if let Some(error) = &config.error {
+            table += &format!("  {}\n", error.replace('\n', " "));
+        }
+    }
+
+    table
+}
+
+fn is_config(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| CONFIG_EXTENSIONS.contains(&extension))
+}
+
+/// The problem whose name or alias is the longest prefix of `name`, e.g. `iris-lgp` for
+/// `iris_lgp_small`.
+fn problem_for(name: &str) -> Option<&'static dyn ProblemDescriptor> {
+    let name = name.to_lowercase();
+
+    PROBLEMS
+        .iter()
+        .copied()
+        .flat_map(|descriptor| {
+            std::iter::once(descriptor.name())
+                .chain(descriptor.aliases().iter().copied())
+                .map(move |candidate| (candidate, descriptor))
+        })
+        .filter(|(candidate, _)| name.starts_with(candidate))
+        .max_by_key(|(candidate, _)| candidate.len())
+        .map(|(_, descriptor)| descriptor)
+}
+
+fn config_info(directory: &Path, path: &Path) -> ConfigInfo {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let problem = problem_for(&name);
+
+    let mut info = ConfigInfo {
+        path: path
+            .strip_prefix(directory)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/"),
+        environment: problem.map(|problem| problem.name().to_string()),
+        name,
+        population_size: None,
+        n_generations: None,
+        mutation_percent: None,
+        crossover_percent: None,
+        q_learning: None,
+        seed_pinned: None,
+        error: None,
+    };
+
+    let Some(problem) = problem else {
+        info.error = Some("No registered problem matches the file name".to_string());
+        return info;
+    };
+
+    match problem.load_config(&path.to_string_lossy()) {
+        Ok(config) => {
+            let number = |key: &str| config[key].as_u64().map(|value| value as usize);
+
+            info.population_size = number("population_size");
+            info.n_generations = number("n_generations");
+            info.mutation_percent = config["mutation_percent"].as_f64();
+            info.crossover_percent = config["crossover_percent"].as_f64();
+            info.q_learning = Some(config["program_parameters"].get("consts").is_some());
+            info.seed_pinned = Some(!config["seed"].is_null());
+        }
+        Err(error) => info.error = Some(error.to_string()),
+    }
+
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf};
+
+    use clap::Parser;
+
+    use crate::{core::engines::core_engine::HyperParameters, problems::iris::IrisEngine};
+
+    use super::*;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join("lgp_config_discovery").join(name);
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("rl")).unwrap();
+
+        fs::copy(
+            "assets/parameters/cart-pole-q.json",
+            directory.join("rl").join("cart-pole-q.json"),
+        )
+        .unwrap();
+        let mut iris = HyperParameters::<IrisEngine>::try_parse_from(["lgp"]).unwrap();
+        iris.population_size = 20;
+        iris.n_generations = 5;
+        iris.seed = Some(7);
+        let instructions = &mut iris.program_parameters.instruction_generator_parameters;
+        instructions.n_inputs = 4;
+        instructions.n_actions = 3;
+        fs::write(
+            directory.join("iris_small.json"),
+            serde_json::to_string(&iris).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            directory.join("mountain-car-lgp.toml"),
+            "population_size = [1,",
+        )
+        .unwrap();
+        fs::write(directory.join("notes.txt"), "not a config").unwrap();
+
+        directory
+    }
+
+    #[test]
+    fn given_valid_and_broken_configs_when_discovered_then_every_config_is_listed() {
+        let configs = discover_configs(&directory("listed")).unwrap();
+
+        assert_eq!(
+            configs
+                .iter()
+                .map(|config| config.path.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "iris_small.json",
+                "mountain-car-lgp.toml",
+                "rl/cart-pole-q.json"
+            ]
+        );
+
+        let iris = &configs[0];
+        assert_eq!(iris.environment.as_deref(), Some("iris-lgp"));
+        assert_eq!(iris.population_size, Some(20));
+        assert_eq!(iris.n_generations, Some(5));
+        assert_eq!(iris.q_learning, Some(false));
+        assert_eq!(iris.seed_pinned, Some(true));
+        assert_eq!(iris.error, None);
+
+        let broken = &configs[1];
+        assert_eq!(broken.environment.as_deref(), Some("mountain-car-lgp"));
+        assert_eq!(broken.population_size, None);
+        assert!(broken.error.is_some());
+
+        let cart_pole = &configs[2];
+        assert_eq!(cart_pole.population_size, Some(100));
+        assert_eq!(cart_pole.mutation_percent, Some(0.5));
+        assert_eq!(cart_pole.q_learning, Some(true));
+        assert_eq!(cart_pole.seed_pinned, Some(false));
+
+        let table = config_table(&configs);
+        let rows = table.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 5);
+        assert!(rows[0].starts_with("config"));
+        assert_eq!(
+            rows[1].split_whitespace().collect::<Vec<_>>(),
+            vec![
+                "iris_small.json",
+                "iris-lgp",
+                "20",
+                "5",
+                "50%",
+                "50%",
+                "no",
+                "pinned"
+            ]
+        );
+        assert_eq!(
+            rows[2].split_whitespace().take(3).collect::<Vec<_>>(),
+            vec!["mountain-car-lgp.toml", "mountain-car-lgp", "-"]
+        );
+        assert!(rows[3].starts_with("  "));
+        assert_eq!(
+            rows[4].split_whitespace().collect::<Vec<_>>(),
+            vec![
+                "rl/cart-pole-q.json",
+                "cart-pole-q",
+                "100",
+                "100",
+                "50%",
+                "50%",
+                "yes",
+                "no"
+            ]
+        );
+
+        let json = serde_json::to_string(&configs).unwrap();
+        let parsed: Vec<ConfigInfo> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, configs);
+    }
+
+    #[test]
+    fn given_filters_when_applied_then_only_matching_configs_remain() {
+        let configs = discover_configs(&directory("filtered")).unwrap();
+        let matching = |filter: &str| {
+            let filter: ConfigFilter = filter.parse().unwrap();
+            configs
+                .iter()
+                .filter(|config| filter.matches(config))
+                .map(|config| config.name.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matching("env=CartPole"), vec!["cart-pole-q"]);
+        assert_eq!(matching("q_learning=true"), vec!["cart-pole-q"]);
+        assert_eq!(matching("q_learning=false"), vec!["iris_small"]);
+        assert!("colour=red".parse::<ConfigFilter>().is_err());
+        assert!("q_learning=maybe".parse::<ConfigFilter>().is_err());
+    }
+}
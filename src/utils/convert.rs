@@ -0,0 +1,214 @@
+//! Translates saved individuals between JSON and the binary format.
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use clap::ValueEnum;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    core::{
+        characteristics::{Format, Load, Save},
+        program::Program,
+    },
+    extensions::q_learning::QProgram,
+};
+
+use super::error::{LgpError, LgpResult};
+
+/// What a saved artifact holds, e.g. `best` is a program and `champions` are programs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    Program,
+    QProgram,
+    Programs,
+    QPrograms,
+}
+
+/// Converts `input` to `output`, each in the format given by its extension (`.bin` for binary).
+///
+/// The kind is inferred from JSON inputs when omitted; binary inputs do not record it.
+pub fn convert(input: &Path, output: &Path, kind: Option<ArtifactKind>) -> LgpResult<ArtifactKind> {
+    let (from, to) = (Format::from_path(input), Format::from_path(output));
+
+    let kind = match kind {
+        Some(kind) => kind,
+        None if from == Format::Json => infer_kind(input)?,
+        None => {
+            return Err(LgpError::Config(
+                "Binary artifacts do not record what they hold; pass `--kind`.".to_string(),
+            ))
+        }
+    };
+
+    match kind {
+        ArtifactKind::Program => convert_as::<Program>(input, from, output, to),
+        ArtifactKind::QProgram => convert_as::<QProgram>(input, from, output, to),
+        ArtifactKind::Programs => convert_as::<Vec<Program>>(input, from, output, to),
+        ArtifactKind::QPrograms => convert_as::<Vec<QProgram>>(input, from, output, to),
+    }?;
+
+    Ok(kind)
+}
+
+fn convert_as<T>(input: &Path, from: Format, output: &Path, to: Format) -> LgpResult<()>
+where
+    T: Load + Save,
+{
+    T::load_as(input, from)?.save_as(output, to)
+}
+
+fn infer_kind(input: &Path) -> LgpResult<ArtifactKind> {
+    let value: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(input)?))?;
+
+    fn holds<T: DeserializeOwned>(value: &serde_json::Value) -> bool {
+        serde_json::from_value::<T>(value.clone()).is_ok()
+    }
+
+    // Q-programs first, since they wrap programs.
+    [
+        (ArtifactKind::QPrograms, holds::<Vec<QProgram>>(&value)),
+        (ArtifactKind::Programs, holds::<Vec<Program>>(&value)),
+        (ArtifactKind::QProgram, holds::<QProgram>(&value)),
+        (ArtifactKind::Program, holds::<Program>(&value)),
+    ]
+    .into_iter()
+    .find_map(|(kind, holds)| holds.then_some(kind))
+    .ok_or_else(|| {
+        LgpError::Config(format!(
+            "`{}` holds neither programs nor Q-programs.",
+            input.display()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use crate::{
+        core::{
+            engines::{
+                core_engine::{Core, HyperParameters, HyperParametersBuilder},
+                generate_engine::{Generate, GenerateEngine},
+            },
+            instruction::InstructionGeneratorParametersBuilder,
+            program::{ProgramGeneratorParameters, ProgramGeneratorParametersBuilder},
+        },
+        extensions::q_learning::{QConsts, QProgramGeneratorParameters},
+        problems::iris::IrisEngine,
+        utils::random::update_seed,
+    };
+
+    use super::*;
+
+    fn program_parameters() -> ProgramGeneratorParameters {
+        ProgramGeneratorParametersBuilder::default()
+            .max_instructions(50)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_actions(3)
+                    .n_inputs(4)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join("lgp_convert").join(name);
+        let _ = fs::remove_dir_all(&directory);
+
+        directory
+    }
+
+    /// Saves `item` in both formats and asserts both read back to the same value.
+    fn assert_round_trips<T>(item: &T, name: &str)
+    where
+        T: Load + Save,
+    {
+        let directory = directory(name);
+        let expected = serde_json::to_value(item).unwrap();
+
+        for format in [Format::Json, Format::Binary] {
+            let path = format.file_name(&directory, "item");
+            item.save_as(&path, format).unwrap();
+
+            let loaded = T::load_as(&path, format).unwrap();
+            assert_eq!(
+                serde_json::to_value(&loaded).unwrap(),
+                expected,
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn given_random_individuals_when_saved_in_each_format_then_they_round_trip() {
+        update_seed(Some(11));
+
+        let programs = IrisEngine::init_population(program_parameters(), 10);
+        let q_program: QProgram = GenerateEngine::generate(QProgramGeneratorParameters {
+            program_parameters: program_parameters(),
+            consts: QConsts::random(),
+            initial_q_table: None,
+        });
+        let hyper_parameters: HyperParameters<IrisEngine> = HyperParametersBuilder::default()
+            .program_parameters(program_parameters())
+            .seed(Some(11))
+            .build()
+            .unwrap();
+
+        assert_round_trips(&programs[0], "program");
+        assert_round_trips(&programs, "programs");
+        assert_round_trips(&q_program, "q_program");
+        assert_round_trips(&hyper_parameters, "hyper_parameters");
+    }
+
+    #[test]
+    fn given_population_when_saved_as_binary_then_file_is_at_least_three_times_smaller() {
+        update_seed(Some(12));
+        let population = IrisEngine::init_population(program_parameters(), 100);
+        let directory = directory("size");
+
+        let json = Format::Json.file_name(&directory, "population");
+        let binary = Format::Binary.file_name(&directory, "population");
+        population.save_as(&json, Format::Json).unwrap();
+        population.save_as(&binary, Format::Binary).unwrap();
+
+        let json_size = fs::metadata(&json).unwrap().len();
+        let binary_size = fs::metadata(&binary).unwrap().len();
+        assert!(
+            binary_size * 3 <= json_size,
+            "{} bytes of binary against {} bytes of JSON",
+            binary_size,
+            json_size
+        );
+    }
+
+    #[test]
+    fn given_saved_champions_when_converted_there_and_back_then_kind_is_inferred() {
+        update_seed(Some(13));
+        let champions = IrisEngine::init_population(program_parameters(), 5);
+        let directory = directory("champions");
+
+        let json = Format::Json.file_name(&directory, "champions");
+        let binary = Format::Binary.file_name(&directory, "champions");
+        let restored = directory.join("restored.json");
+        champions.save_as(&json, Format::Json).unwrap();
+
+        assert_eq!(
+            convert(&json, &binary, None).unwrap(),
+            ArtifactKind::Programs
+        );
+        assert!(convert(&binary, &restored, None).is_err());
+        convert(&binary, &restored, Some(ArtifactKind::Programs)).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(Vec::<Program>::load(&restored).unwrap()).unwrap(),
+            serde_json::to_value(&champions).unwrap()
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::{error::Error, fmt, io};
+
+use itertools::Itertools;
+
+use thiserror::Error;
+
+/// Failures surfaced by the configuration, loading, running and saving layers.
+#[derive(Debug, Error)]
+pub enum LgpError {
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Unknown environment `{name}`; available problems: {}", available.join(", "))]
+    UnknownEnvironment {
+        name: String,
+        available: Vec<String>,
+    },
+    #[cfg(feature = "download")]
+    #[error("Failed to download dataset from {url}")]
+    DatasetDownload {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+    #[error("Malformed binary artifact: {0}")]
+    Binary(#[from] bincode::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error("Malformed row {row}: {source}")]
+    MalformedRow {
+        row: usize,
+        #[source]
+        source: csv::Error,
+    },
+    /// Any other failure, kept as its message.
+    #[error("{0}")]
+    Other(String),
+}
+
+pub type LgpResult<T> = Result<T, LgpError>;
+
+impl LgpError {
+    /// Process exit code used by the CLI, distinct per variant.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LgpError::Other(_) => 1,
+            LgpError::Config(_) => 2,
+            LgpError::Io(_) => 3,
+            LgpError::UnknownEnvironment { .. } => 4,
+            #[cfg(feature = "download")]
+            LgpError::DatasetDownload { .. } => 5,
+            LgpError::Serialization(_) => 6,
+            LgpError::Csv(_) => 7,
+            LgpError::MalformedRow { .. } => 8,
+            LgpError::Binary(_) => 9,
+        }
+    }
+}
+
+/// A configuration value outside its allowed range, or inconsistent with another field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigViolation {
+    /// Dotted path of the field, e.g. `program_parameters.max_instructions`.
+    pub field: String,
+    pub value: String,
+    pub allowed: String,
+}
+
+impl fmt::Display for ConfigViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is {}, expected {}",
+            self.field, self.value, self.allowed
+        )
+    }
+}
+
+/// Records a violation of `field` unless `holds`.
+pub fn require(
+    violations: &mut Vec<ConfigViolation>,
+    holds: bool,
+    field: &str,
+    value: impl fmt::Display,
+    allowed: &str,
+) {
+    if !holds {
+        violations.push(ConfigViolation {
+            field: field.to_string(),
+            value: value.to_string(),
+            allowed: allowed.to_string(),
+        });
+    }
+}
+
+/// Lists every violation in a single configuration error.
+impl From<Vec<ConfigViolation>> for LgpError {
+    fn from(violations: Vec<ConfigViolation>) -> Self {
+        LgpError::Config(violations.iter().join("; "))
+    }
+}
+
+impl From<config::ConfigError> for LgpError {
+    fn from(error: config::ConfigError) -> Self {
+        LgpError::Config(error.to_string())
+    }
+}
+
+/// Recovers the typed failure behind a boxed error where possible.
+impl From<Box<dyn Error>> for LgpError {
+    fn from(error: Box<dyn Error>) -> Self {
+        let error = match error.downcast::<LgpError>() {
+            Ok(error) => return *error,
+            Err(error) => error,
+        };
+        let error = match error.downcast::<io::Error>() {
+            Ok(error) => return LgpError::Io(*error),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<serde_json::Error>() {
+            Ok(error) => return LgpError::Serialization(*error),
+            Err(error) => error,
+        };
+
+        match error.downcast::<csv::Error>() {
+            Ok(error) => LgpError::Csv(*error),
+            Err(error) => LgpError::Other(error.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_boxed_errors_when_converted_then_variant_is_recovered() {
+        let io: Box<dyn Error> = Box::new(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert!(matches!(LgpError::from(io), LgpError::Io(_)));
+
+        let config: Box<dyn Error> = Box::new(LgpError::Config("bad".to_string()));
+        assert!(matches!(LgpError::from(config), LgpError::Config(_)));
+
+        let other: Box<dyn Error> = "something else".into();
+        assert_eq!(LgpError::from(other).exit_code(), 1);
+    }
+}
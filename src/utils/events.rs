@@ -0,0 +1,228 @@
+use std::{
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    engines::core_engine::{EffectiveRates, TaskMetrics, VariationCounts},
+    instruction_stats::InstructionStats,
+};
+
+use super::misc::VoidResultAnyError;
+
+/// A line of the JSONL event stream written by `CoreIter` when `events_file` is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    RunStarted {
+        seed: u64,
+        config: serde_json::Value,
+    },
+    Generation {
+        generation: usize,
+        best: f64,
+        median: f64,
+        worst: f64,
+        mean: f64,
+        std: f64,
+        duration_ms: u64,
+        n_evaluations: usize,
+        /// Individuals whose registers were saturated during evaluation.
+        #[serde(default)]
+        n_saturated: usize,
+        /// Evaluations that panicked or timed out so far.
+        #[serde(default)]
+        n_eval_failures: usize,
+        /// Set when `track_instruction_stats` is.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        instruction_stats: Option<InstructionStats>,
+        /// Mean within-individual variance of the trial scores; set when
+        /// `record_trial_scores` is.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        trial_variance: Option<f64>,
+        /// Fitness on every task; set in multi-task runs.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        task_metrics: Option<Vec<TaskMetrics>>,
+        /// Offspring bred for the next generation, per operator, produced and requested.
+        #[serde(default)]
+        offspring: VariationCounts,
+        /// Scheduled hyperparameters in effect during the generation.
+        #[serde(default)]
+        rates: EffectiveRates,
+    },
+    RunFinished {
+        n_generations: usize,
+        n_evaluations: usize,
+        duration_ms: u64,
+    },
+}
+
+/// Appends events to a file, flushing after every line so the stream can be tailed live.
+#[derive(Debug)]
+pub struct EventWriter {
+    file: File,
+}
+
+impl EventWriter {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(EventWriter { file })
+    }
+
+    pub fn write(&mut self, event: &Event) -> VoidResultAnyError {
+        let line = serde_json::to_string(event)?;
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Parses every complete event of a stream. Malformed lines, such as a final line cut short by
+/// a crash, are skipped.
+pub fn read_events(path: &Path) -> Result<Vec<Event>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use itertools::Itertools;
+
+    use crate::{
+        core::{
+            engines::core_engine::HyperParametersBuilder,
+            instruction::InstructionGeneratorParametersBuilder,
+            program::ProgramGeneratorParametersBuilder,
+        },
+        data::dataset::Dataset,
+        problems::iris::{IrisEngine, IrisStateParameters},
+    };
+
+    use super::*;
+
+    fn run(path: &Path, track_instruction_stats: bool) -> VoidResultAnyError {
+        let _ = fs::remove_file(path);
+
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_trials(1)
+            .seed(Some(5))
+            .events_file(Some(path.to_owned()))
+            .track_instruction_stats(track_instruction_stats)
+            .build()?;
+
+        let dataset = Dataset::new(
+            vec![vec![5.1, 3.5, 1.4, 0.2], vec![7.0, 3.2, 4.7, 1.4]],
+            vec![0, 1],
+            vec![],
+            vec![],
+        );
+
+        let populations = parameters
+            .build_engine_with(IrisStateParameters::new(dataset))
+            .take(5)
+            .collect_vec();
+        assert_eq!(populations.len(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_five_generations_then_events_are_framed_by_start_and_finish() -> VoidResultAnyError {
+        let path = env::temp_dir().join("lgp_events").join("complete.jsonl");
+        run(&path, false)?;
+
+        let events = read_events(&path)?;
+        assert_eq!(events.len(), 7);
+        assert!(matches!(events[0], Event::RunStarted { seed: 5, .. }));
+        assert!(matches!(
+            events[6],
+            Event::RunFinished {
+                n_generations: 5,
+                ..
+            }
+        ));
+
+        let generations = events[1..6]
+            .iter()
+            .map(|event| match event {
+                Event::Generation {
+                    generation,
+                    best,
+                    worst,
+                    ..
+                } => {
+                    assert!(best >= worst);
+                    *generation
+                }
+                _ => panic!("Expected a generation event."),
+            })
+            .collect_vec();
+        assert_eq!(generations, vec![0, 1, 2, 3, 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_truncated_final_line_then_complete_events_are_read() -> VoidResultAnyError {
+        let path = env::temp_dir().join("lgp_events").join("truncated.jsonl");
+        run(&path, false)?;
+
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        write!(file, "{{\"event\":\"generation\",\"generation\":5,\"be")?;
+
+        let events = read_events(&path)?;
+        assert_eq!(events.len(), 7);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_tracked_instruction_stats_then_every_generation_event_counts_the_population(
+    ) -> VoidResultAnyError {
+        let path = env::temp_dir()
+            .join("lgp_events")
+            .join("instruction_stats.jsonl");
+        run(&path, true)?;
+
+        for event in &read_events(&path)?[1..6] {
+            let Event::Generation {
+                instruction_stats: Some(stats),
+                ..
+            } = event
+            else {
+                panic!("Expected a generation event with instruction stats.");
+            };
+
+            assert_eq!(stats.n_programs, 10);
+            assert_eq!(stats.ops.values().sum::<usize>(), stats.n_instructions);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        characteristics::{Format, Load},
+        program::Program,
+    },
+    problems::classification::ClassificationReport,
+};
+
+use super::{
+    error::{LgpError, LgpResult},
+    loader::DataLoader,
+    predict::Predictor,
+};
+
+/// What to do with feature values that are empty, `?` or NaN.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingValues {
+    /// Fail, naming the row and column.
+    #[default]
+    Error,
+    /// Read them as 0.
+    Zero,
+}
+
+/// How `score_csv` reads a CSV, besides which column holds the labels.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CsvOptions {
+    pub loader: DataLoader,
+    /// Class names in class order (e.g. `Dataset::label_names`) for labels given by name;
+    /// labels are read as class indices when empty.
+    pub label_names: Vec<String>,
+    pub missing: MissingValues,
+}
+
+/// Predictions for every row of a CSV, and their report against its labels if it has any.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CsvScores {
+    pub predictions: Vec<usize>,
+    pub report: Option<ClassificationReport>,
+}
+
+fn parse_feature(value: &str, row: usize, column: usize, missing: MissingValues) -> LgpResult<f64> {
+    let value = value.trim();
+    let parsed = match value {
+        "" | "?" => f64::NAN,
+        value => value.parse::<f64>().map_err(|error| {
+            LgpError::Other(format!("Row {}, column {}: {}.", row, column, error))
+        })?,
+    };
+
+    match (parsed.is_nan(), missing) {
+        (false, _) => Ok(parsed),
+        (true, MissingValues::Zero) => Ok(0.),
+        (true, MissingValues::Error) => Err(LgpError::Other(format!(
+            "Row {}, column {}: missing value; impute it with `MissingValues::Zero`.",
+            row, column
+        ))),
+    }
+}
+
+fn parse_label(value: &str, row: usize, label_names: &[String]) -> LgpResult<usize> {
+    let value = value.trim();
+    let label = if label_names.is_empty() {
+        value.parse::<usize>().ok()
+    } else {
+        label_names.iter().position(|name| name == value)
+    };
+
+    label.ok_or_else(|| LgpError::Other(format!("Row {}: unknown label `{}`.", row, value)))
+}
+
+/// Reads the feature values of every row of `csv_path`, which are all its columns but
+/// `label_column`, and the labels held in that column.
+pub fn read_rows(
+    csv_path: &Path,
+    label_column: Option<usize>,
+    options: &CsvOptions,
+) -> LgpResult<(Vec<Vec<f64>>, Option<Vec<usize>>)> {
+    let records: Vec<Vec<String>> = options.loader.from_path(csv_path)?;
+    let mut features = Vec::with_capacity(records.len());
+    let mut labels = Vec::with_capacity(records.len());
+
+    for (row, record) in records.iter().enumerate() {
+        if let Some(column) = label_column {
+            let value = record.get(column).ok_or_else(|| {
+                LgpError::Other(format!("Row {} has no label column {}.", row, column))
+            })?;
+            labels.push(parse_label(value, row, &options.label_names)?);
+        }
+
+        let values = record
+            .iter()
+            .enumerate()
+            .filter(|(column, _)| Some(*column) != label_column)
+            .map(|(column, value)| parse_feature(value, row, column, options.missing))
+            .collect::<LgpResult<Vec<_>>>()?;
+        features.push(values);
+    }
+
+    Ok((features, label_column.map(|_| labels)))
+}
+
+/// Predicts every row of `csv_path` with the classification model saved at `model_path`,
+/// registers cleared between rows, and reports accuracy and confusion against the labels in
+/// `label_column`, if given.
+pub fn score_csv(
+    model_path: &Path,
+    csv_path: &Path,
+    label_column: Option<usize>,
+    options: &CsvOptions,
+) -> LgpResult<CsvScores> {
+    let mut model = Program::load_as(model_path, Format::from_path(model_path))?;
+    let (features, labels) = read_rows(csv_path, label_column, options)?;
+    let predictions = model.predict_batch(&features);
+
+    let report = labels.map(|labels| {
+        let pairs = labels
+            .into_iter()
+            .zip(predictions.iter().copied())
+            .collect_vec();
+        ClassificationReport::new(options.label_names.len(), &pairs, false)
+    });
+
+    Ok(CsvScores {
+        predictions,
+        report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::{
+        core::{
+            characteristics::Save,
+            engines::{
+                core_engine::HyperParametersBuilder,
+                fitness_engine::{Fitness, FitnessEngine},
+                generate_engine::{Generate, GenerateEngine},
+                reset_engine::{Reset, ResetEngine},
+                status_engine::{Status, StatusEngine},
+            },
+            instruction::{Instruction, InstructionGeneratorParametersBuilder, Mode, Op},
+            program::{ProgramGeneratorParameters, ProgramGeneratorParametersBuilder},
+        },
+        data::dataset::Dataset,
+        problems::iris::{IrisEngine, IrisState, IrisStateParameters},
+        utils::{misc::VoidResultAnyError, predict::Features},
+    };
+
+    use super::*;
+
+    fn program_parameters() -> ProgramGeneratorParameters {
+        ProgramGeneratorParametersBuilder::default()
+            .max_instructions(20)
+            .instruction_generator_parameters(
+                InstructionGeneratorParametersBuilder::default()
+                    .n_actions(3)
+                    .n_inputs(4)
+                    .n_calculation_registers(0)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    /// Iris rows; the last one is a versicolor with unusually wide petals.
+    fn dataset() -> Dataset {
+        Dataset::new(
+            vec![
+                vec![5.1, 3.5, 1.4, 0.2],
+                vec![7.0, 3.2, 4.7, 1.4],
+                vec![6.3, 3.3, 6.0, 2.5],
+                vec![4.9, 3.0, 1.4, 0.2],
+                vec![6.4, 3.2, 4.5, 1.5],
+                vec![5.8, 2.7, 5.1, 1.9],
+                vec![5.9, 3.2, 4.8, 1.8],
+            ],
+            vec![0, 1, 2, 0, 1, 2, 1],
+            vec![],
+            ["Iris-setosa", "Iris-versicolor", "Iris-virginica"]
+                .map(String::from)
+                .to_vec(),
+        )
+    }
+
+    /// Writes `dataset` as a headerless CSV with the class name last, like the Iris dataset.
+    fn write_csv(dataset: &Dataset, name: &str) -> std::path::PathBuf {
+        let path = env::temp_dir().join("lgp_inference").join(name);
+        let csv = dataset
+            .features
+            .iter()
+            .zip(dataset.labels.iter())
+            .map(|(row, label)| format!("{},{}", row.iter().join(","), dataset.label_names[*label]))
+            .join("\n");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, csv).unwrap();
+
+        path
+    }
+
+    fn options() -> CsvOptions {
+        CsvOptions {
+            label_names: dataset().label_names,
+            ..Default::default()
+        }
+    }
+
+    /// Overwrites every output register from the current row alone: setosa scores 2.5, versicolor
+    /// the petal length and virginica 2.8 times the petal width.
+    fn row_independent_program() -> Program {
+        let mut program: Program = GenerateEngine::generate(program_parameters());
+        program.instructions = vec![
+            Instruction::with_constant(0, Op::Mult, 0.),
+            Instruction::with_constant(1, Op::Mult, 0.),
+            Instruction::with_constant(2, Op::Mult, 0.),
+            Instruction::with_constant(0, Op::Add, 2.5),
+            Instruction::new(1, 2, Mode::External, Op::Add, 1.),
+            Instruction::new(2, 3, Mode::External, Op::Add, 2.8),
+        ];
+        ResetEngine::reset(&mut program);
+
+        program
+    }
+
+    #[test]
+    fn given_row_independent_model_when_training_csv_is_scored_then_accuracy_is_training_fitness(
+    ) -> VoidResultAnyError {
+        let dataset = dataset();
+        let mut model = row_independent_program();
+        let fitness = <FitnessEngine as Fitness<_, _, ()>>::eval_fitness(
+            &mut model,
+            &mut IrisState::new(dataset.clone()),
+        );
+        StatusEngine::set_fitness(&mut model, fitness);
+
+        let model_path = env::temp_dir()
+            .join("lgp_inference")
+            .join("independent.json");
+        model.save(model_path.to_str().unwrap())?;
+        let csv_path = write_csv(&dataset, "independent.csv");
+
+        let scores = score_csv(&model_path, &csv_path, Some(4), &options())?;
+        let report = scores.report.unwrap();
+
+        assert!((report.accuracy - fitness).abs() < 1e-12);
+        assert!((report.accuracy - 6. / 7.).abs() < 1e-12);
+        assert_eq!(report.confusion_matrix[1][2], 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_evolved_model_when_csv_is_scored_then_predictions_match_row_by_row_prediction(
+    ) -> VoidResultAnyError {
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters())
+            .population_size(20)
+            .n_generations(3)
+            .n_trials(1)
+            .seed(Some(5))
+            .build()?;
+
+        let dataset = dataset();
+        let mut model = parameters
+            .build_engine_with(IrisStateParameters::new(dataset.clone()))
+            .last()
+            .and_then(|population| population.first().cloned())
+            .unwrap();
+
+        let model_path = env::temp_dir().join("lgp_inference").join("evolved.json");
+        model.save(model_path.to_str().unwrap())?;
+        let csv_path = write_csv(&dataset, "evolved.csv");
+
+        let expected = dataset
+            .features
+            .iter()
+            .map(|row| model.predict(&mut Features(row)))
+            .collect_vec();
+        assert_eq!(model.predict_batch(&dataset.features), expected);
+
+        let scores = score_csv(&model_path, &csv_path, Some(4), &options())?;
+        assert_eq!(scores.predictions, expected);
+
+        let n_correct = expected
+            .iter()
+            .zip(dataset.labels.iter())
+            .filter(|(predicted, label)| predicted == label)
+            .count();
+        let report = scores.report.unwrap();
+        assert!((report.accuracy - n_correct as f64 / dataset.len() as f64).abs() < 1e-12);
+
+        let unlabelled = score_csv(&model_path, &csv_path, None, &options());
+        assert!(unlabelled.is_err(), "Class names are not features.");
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_missing_values_when_read_then_they_fail_or_are_imputed_as_configured() {
+        let path = env::temp_dir().join("lgp_inference").join("missing.csv");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "1,,NaN,0\n2,?,3,1\n").unwrap();
+
+        let result = read_rows(&path, Some(3), &CsvOptions::default());
+        assert!(matches!(result, Err(LgpError::Other(message)) if message.contains("Row 0")));
+
+        let options = CsvOptions {
+            missing: MissingValues::Zero,
+            ..Default::default()
+        };
+        let (features, labels) = read_rows(&path, Some(3), &options).unwrap();
+        assert_eq!(features, vec![vec![1., 0., 0.], vec![2., 0., 3.]]);
+        assert_eq!(labels, Some(vec![0, 1]));
+    }
+}
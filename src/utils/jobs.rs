@@ -0,0 +1,302 @@
+//! Evolution jobs behind an HTTP API: `JobServer` starts runs on background threads, reports
+//! their progress and champions, and cancels them cooperatively.
+
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, ToSocketAddrs},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    core::{
+        characteristics::{Format, Save},
+        engines::{
+            core_engine::{Core, GenerationObserver, HyperParameters},
+            status_engine::Status,
+        },
+    },
+    utils::error::{LgpError, LgpResult},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Progress of a job, as answered by `GET /jobs/<id>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub state: JobState,
+    /// Index of the last evaluated generation.
+    pub generation: usize,
+    pub best_fitness: Option<f64>,
+    /// Why the job failed, if it did.
+    pub error: Option<String>,
+}
+
+/// Answer to `POST /jobs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobCreated {
+    pub id: Uuid,
+}
+
+struct Progress<I> {
+    status: JobStatus,
+    champion: Option<I>,
+}
+
+struct Job<I> {
+    progress: Arc<Mutex<Progress<I>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Feeds the best individual of every generation into a job's progress.
+struct ProgressObserver<I> {
+    progress: Arc<Mutex<Progress<I>>>,
+}
+
+impl<C> GenerationObserver<C> for ProgressObserver<C::Individual>
+where
+    C: Core,
+{
+    fn on_generation(&mut self, generation: usize, ranked: &[C::Individual]) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.status.generation = generation;
+
+        if let Some(best) = ranked.first() {
+            progress.status.best_fitness = Some(C::Status::get_fitness(best));
+            progress.champion = Some(best.clone());
+        }
+    }
+}
+
+/// Serves evolution jobs on the state parameters it was bound with:
+///
+/// - `POST /jobs` starts a run of the `HyperParameters` in the body and answers its id.
+/// - `GET /jobs/<id>` answers its `JobStatus`.
+/// - `GET /jobs/<id>/champion` answers the best individual once the job has stopped.
+/// - `DELETE /jobs/<id>` cancels it before its next generation.
+pub struct JobServer<C>
+where
+    C: Core,
+{
+    server: Server,
+    state_parameters: C::StateParameters,
+    jobs: Mutex<HashMap<Uuid, Job<C::Individual>>>,
+    artifacts: Option<PathBuf>,
+}
+
+impl<C> JobServer<C>
+where
+    C: Core,
+{
+    /// Binds to `address`; port `0` picks a free port, see `local_addr`.
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        state_parameters: C::StateParameters,
+    ) -> LgpResult<Self> {
+        let server = Server::http(address).map_err(|error| LgpError::Other(error.to_string()))?;
+
+        Ok(JobServer {
+            server,
+            state_parameters,
+            jobs: Mutex::new(HashMap::new()),
+            artifacts: None,
+        })
+    }
+
+    /// Saves the champion of every job that stops as `<dir>/<id>.json`.
+    pub fn with_artifacts(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.artifacts = Some(dir.into());
+        self
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server
+            .server_addr()
+            .to_ip()
+            .expect("Job servers listen on TCP.")
+    }
+
+    /// Handles requests one at a time until the process exits.
+    pub fn run(self) {
+        for request in self.server.incoming_requests() {
+            self.handle(request);
+        }
+    }
+
+    /// Serves on a background thread, returning the address it listens on.
+    pub fn spawn(self) -> SocketAddr {
+        let address = self.local_addr();
+        thread::spawn(move || self.run());
+
+        address
+    }
+
+    /// Starts a run of `parameters` on a background thread.
+    pub fn start(&self, parameters: HyperParameters<C>) -> LgpResult<Uuid> {
+        parameters.validate().map_err(LgpError::from)?;
+
+        let id = Uuid::new_v4();
+        let progress = Arc::new(Mutex::new(Progress {
+            status: JobStatus {
+                state: JobState::Running,
+                generation: 0,
+                best_fitness: None,
+                error: None,
+            },
+            champion: None,
+        }));
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        {
+            let progress = progress.clone();
+            let cancel = cancel.clone();
+            let state_parameters = self.state_parameters.clone();
+            let artifact = self
+                .artifacts
+                .as_ref()
+                .map(|dir| dir.join(format!("{}.json", id)));
+
+            thread::spawn(move || {
+                let (state, error) = match parameters.try_build_engine_with(state_parameters) {
+                    Ok(engine) => {
+                        let mut engine = engine.with_cancellation(cancel).with_observer(Box::new(
+                            ProgressObserver {
+                                progress: progress.clone(),
+                            },
+                        ));
+                        engine.by_ref().for_each(drop);
+
+                        match engine.failure() {
+                            Some(failure) => (JobState::Failed, Some(failure.to_string())),
+                            None if engine.is_cancelled() => (JobState::Cancelled, None),
+                            None => (JobState::Completed, None),
+                        }
+                    }
+                    Err(error) => (JobState::Failed, Some(error.to_string())),
+                };
+
+                let mut progress = progress.lock().unwrap();
+                progress.status.state = state;
+                progress.status.error = error;
+
+                if let (Some(path), Some(champion)) = (artifact, progress.champion.as_ref()) {
+                    if let Err(error) = champion.save_as(&path, Format::Json) {
+                        warn!(error = %error, path = ?path, "Failed to save a job's champion.");
+                    }
+                }
+            });
+        }
+
+        self.jobs
+            .lock()
+            .unwrap()
+            .insert(id, Job { progress, cancel });
+
+        Ok(id)
+    }
+
+    pub fn status(&self, id: &Uuid) -> Option<JobStatus> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|job| job.progress.lock().unwrap().status.clone())
+    }
+
+    /// Best individual of a job, once it has stopped.
+    pub fn champion(&self, id: &Uuid) -> Option<C::Individual> {
+        let jobs = self.jobs.lock().unwrap();
+        let progress = jobs.get(id)?.progress.lock().unwrap();
+
+        match progress.status.state {
+            JobState::Running => None,
+            _ => progress.champion.clone(),
+        }
+    }
+
+    /// Requests a job to stop before its next generation; returns whether the job exists.
+    pub fn cancel(&self, id: &Uuid) -> bool {
+        match self.jobs.lock().unwrap().get(id) {
+            Some(job) => {
+                job.cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn handle(&self, mut request: Request) {
+        let url = request.url().to_string();
+        let segments = url.trim_matches('/').split('/').collect_vec();
+        let job_id = segments.get(1).and_then(|id| Uuid::parse_str(id).ok());
+
+        let response = match (request.method(), &segments[..], job_id) {
+            (Method::Post, ["jobs"], _) => {
+                let mut body = String::new();
+                let started = request
+                    .as_reader()
+                    .read_to_string(&mut body)
+                    .map_err(LgpError::from)
+                    .and_then(|_| Ok(serde_json::from_str(&body)?))
+                    .and_then(|parameters| self.start(parameters));
+
+                match started {
+                    Ok(id) => json(&JobCreated { id }).with_status_code(201),
+                    Err(error) => Response::from_string(error.to_string()).with_status_code(400),
+                }
+            }
+            (Method::Get, ["jobs", _], Some(id)) => match self.status(&id) {
+                Some(status) => json(&status),
+                None => not_found(),
+            },
+            (Method::Get, ["jobs", _, "champion"], Some(id)) => {
+                match (self.champion(&id), self.status(&id)) {
+                    (Some(champion), _) => json(&champion),
+                    (None, Some(_)) => {
+                        Response::from_string("Job has no champion yet").with_status_code(409)
+                    }
+                    (None, None) => not_found(),
+                }
+            }
+            (Method::Delete, ["jobs", _], Some(id)) if self.cancel(&id) => {
+                Response::from_string("Cancelling").with_status_code(202)
+            }
+            _ => not_found(),
+        };
+
+        if let Err(error) = request.respond(response) {
+            warn!(error = %error, "Failed to answer a job request.");
+        }
+    }
+}
+
+fn json<T>(value: &T) -> Response<std::io::Cursor<Vec<u8>>>
+where
+    T: Serialize,
+{
+    let header =
+        Header::from_bytes("Content-Type", "application/json").expect("Static header is valid.");
+
+    Response::from_string(serde_json::to_string(value).unwrap()).with_header(header)
+}
+
+fn not_found() -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string("Not found").with_status_code(404)
+}
@@ -0,0 +1,349 @@
+use std::{iter::repeat_with, path::PathBuf};
+
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        characteristics::Save,
+        engines::{
+            core_engine::{Core, HyperParameters},
+            generate_engine::Generate,
+            reset_engine::{Reset, ResetEngine},
+            status_engine::Status,
+        },
+        instruction::Perturbation,
+        program::{Program, ProgramGeneratorParameters},
+    },
+    extensions::q_learning::{QProgram, QProgramGeneratorParameters},
+    utils::{
+        benchmark_tools::create_path, error::LgpResult, misc::VoidResultAnyError, random::generator,
+    },
+};
+
+/// Enumerates the single-step neighbourhood of an individual.
+pub trait Neighbourhood<P>: Sized {
+    fn neighbours(&self, using: P) -> Vec<(Perturbation, Self)>;
+}
+
+impl Neighbourhood<ProgramGeneratorParameters> for Program {
+    fn neighbours(&self, using: ProgramGeneratorParameters) -> Vec<(Perturbation, Self)> {
+        let mut neighbours = vec![];
+        let n_instructions = self.instructions.len();
+
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            for (perturbation, neighbour) in
+                instruction.neighbours(index, using.instruction_generator_parameters)
+            {
+                let mut program = self.clone();
+                program.instructions[index] = neighbour;
+                neighbours.push((perturbation, program));
+            }
+
+            if n_instructions > 1 {
+                let mut program = self.clone();
+                program.instructions.remove(index);
+                neighbours.push((Perturbation::Delete { index }, program));
+            }
+
+            // Insertions duplicate the instruction at the current position.
+            if n_instructions < using.max_instructions {
+                let mut program = self.clone();
+                program.instructions.insert(index, *instruction);
+                neighbours.push((Perturbation::Insert { index }, program));
+            }
+        }
+
+        for (_, program) in neighbours.iter_mut() {
+            ResetEngine::reset(&mut program.id);
+            ResetEngine::reset(program);
+        }
+
+        neighbours
+    }
+}
+
+impl Neighbourhood<QProgramGeneratorParameters> for QProgram {
+    fn neighbours(&self, using: QProgramGeneratorParameters) -> Vec<(Perturbation, Self)> {
+        self.program
+            .neighbours(using.program_parameters)
+            .into_iter()
+            .map(|(perturbation, program)| {
+                let mut q_table = self.q_table.clone();
+                ResetEngine::reset(&mut q_table);
+
+                let mut neighbour = QProgram { q_table, program };
+                neighbour.reseed_exploration();
+                (perturbation, neighbour)
+            })
+            .collect()
+    }
+}
+
+/// Caps the neighbourhood size by sampling each perturbation kind proportionally to its share.
+///
+/// Every kind keeps at least one neighbour, so rare kinds are never sampled away; the cap is only
+/// exceeded when it is smaller than the number of kinds.
+pub fn sample_stratified<T>(
+    neighbours: Vec<(Perturbation, T)>,
+    max_neighbours: usize,
+) -> Vec<(Perturbation, T)> {
+    let n_neighbours = neighbours.len();
+
+    if n_neighbours <= max_neighbours {
+        return neighbours;
+    }
+
+    let groups = neighbours
+        .into_iter()
+        .sorted_by_key(|(perturbation, _)| perturbation.kind())
+        .group_by(|(perturbation, _)| perturbation.kind())
+        .into_iter()
+        .map(|(_, group)| group.collect_vec())
+        .collect_vec();
+
+    // The neighbours left after one per kind share what is left of the cap.
+    let n_rest = n_neighbours - groups.len();
+    let rest_quota = max_neighbours.saturating_sub(groups.len());
+    let mut sampled = vec![];
+
+    for mut group in groups {
+        let quota = match n_rest {
+            0 => 1,
+            n_rest => 1 + (group.len() - 1) * rest_quota / n_rest,
+        };
+
+        group.shuffle(&mut generator());
+        sampled.extend(group.into_iter().take(quota));
+    }
+
+    sampled
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LandscapeEntry {
+    pub perturbation: String,
+    pub kind: String,
+    pub fitness: f64,
+    pub delta: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LandscapeSummary {
+    pub champion_fitness: f64,
+    pub n_neighbours: usize,
+    pub neutral: f64,
+    pub deleterious: f64,
+    pub beneficial: f64,
+}
+
+impl LandscapeSummary {
+    pub fn new(champion_fitness: f64, entries: &[LandscapeEntry]) -> Self {
+        let n_neighbours = entries.len();
+        let fraction = |predicate: &dyn Fn(f64) -> bool| {
+            if n_neighbours == 0 {
+                return 0.;
+            }
+
+            entries
+                .iter()
+                .filter(|entry| predicate(entry.delta))
+                .count() as f64
+                / n_neighbours as f64
+        };
+
+        LandscapeSummary {
+            champion_fitness,
+            n_neighbours,
+            neutral: fraction(&|delta| delta.abs() <= f64::EPSILON),
+            deleterious: fraction(&|delta| delta < -f64::EPSILON),
+            beneficial: fraction(&|delta| delta > f64::EPSILON),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Landscape {
+    pub entries: Vec<LandscapeEntry>,
+    pub summary: LandscapeSummary,
+}
+
+/// Evaluates the champion and its (capped) neighbourhood on holdout trials, see
+/// `Core::holdout_split`.
+pub fn explore_landscape<C>(
+    champion: &C::Individual,
+    params: &HyperParameters<C>,
+    max_neighbours: usize,
+) -> LgpResult<Landscape>
+where
+    C: Core,
+    C::Individual: Neighbourhood<C::ProgramParameters>,
+{
+    let state_parameters = params.state_parameters()?;
+    let (_, holdout) = C::holdout_split(&state_parameters, params.seed.unwrap_or_default());
+    let mut trials: Vec<C::State> = repeat_with(|| C::Generate::generate(holdout.clone()))
+        .take(params.n_trials)
+        .collect_vec();
+
+    let mut baseline = vec![champion.clone()];
+    C::eval_fitness(
+        &mut baseline,
+        &mut trials,
+        params.fallback_fitness(),
+        params.fitness_aggregation,
+    );
+    let champion_fitness = C::Status::get_fitness(&baseline[0]);
+
+    let neighbours = sample_stratified(
+        champion.neighbours(params.program_parameters),
+        max_neighbours,
+    );
+    let (perturbations, mut population): (Vec<_>, Vec<_>) = neighbours.into_iter().unzip();
+
+    C::eval_fitness(
+        &mut population,
+        &mut trials,
+        params.fallback_fitness(),
+        params.fitness_aggregation,
+    );
+
+    let entries = perturbations
+        .into_iter()
+        .zip(population.iter())
+        .map(|(perturbation, neighbour)| {
+            let fitness = C::Status::get_fitness(neighbour);
+            LandscapeEntry {
+                perturbation: perturbation.to_string(),
+                kind: perturbation.kind().to_string(),
+                fitness,
+                delta: fitness - champion_fitness,
+            }
+        })
+        .collect_vec();
+
+    let summary = LandscapeSummary::new(champion_fitness, &entries);
+
+    Ok(Landscape { entries, summary })
+}
+
+/// Writes the landscape table as CSV and its summary as JSON next to it.
+pub fn save_landscape(landscape: &Landscape, path: impl Into<PathBuf>) -> VoidResultAnyError {
+    let path: PathBuf = path.into();
+    let csv_path = create_path(path.to_str().unwrap(), true)?;
+
+    let mut writer = csv::Writer::from_path(&csv_path)?;
+
+    for entry in &landscape.entries {
+        writer.serialize(entry)?;
+    }
+
+    writer.flush()?;
+
+    let summary_path = csv_path.with_extension("json");
+    landscape.summary.save(summary_path.to_str().unwrap())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::instructions::OpSet;
+    use crate::core::{
+        engines::generate_engine::{Generate, GenerateEngine},
+        instruction::InstructionGeneratorParameters,
+    };
+
+    use super::*;
+    use crate::core::registers::{RegisterInit, RegisterSaturation};
+
+    fn parameters(max_instructions: usize) -> ProgramGeneratorParameters {
+        ProgramGeneratorParameters {
+            max_instructions,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 1,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 10.,
+                input_scales: None,
+                n_actions: 2,
+                n_inputs: 2,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        }
+    }
+
+    #[test]
+    fn given_single_instruction_program_when_neighbours_then_every_micro_mutation_is_enumerated() {
+        let params = parameters(2);
+        let mut program: Program = GenerateEngine::generate(params);
+        program.instructions.truncate(1);
+
+        let neighbours = program.neighbours(params);
+        let count = |kind: &str| {
+            neighbours
+                .iter()
+                .filter(|(perturbation, _)| perturbation.kind() == kind)
+                .count()
+        };
+
+        // 3 other ops, 2 other sources, 3 + 2 - 1 other targets, no deletion, 1 insertion.
+        assert_eq!(neighbours.len(), 10);
+        assert_eq!(count("op"), 3);
+        assert_eq!(count("source"), 2);
+        assert_eq!(count("target"), 4);
+        assert_eq!(count("delete"), 0);
+        assert_eq!(count("insert"), 1);
+
+        assert!(neighbours
+            .iter()
+            .all(|(_, neighbour)| neighbour.instructions != program.instructions));
+        assert_eq!(neighbours[0].0.to_string().split(':').next(), Some("op[0]"));
+    }
+
+    #[test]
+    fn given_large_neighbourhood_when_sampled_then_cap_is_respected() {
+        let params = parameters(20);
+        let program: Program = GenerateEngine::generate(params);
+
+        let neighbours = program.neighbours(params);
+        let sampled = sample_stratified(neighbours, 5);
+
+        assert!(sampled.len() <= 5);
+    }
+
+    #[test]
+    fn given_capped_neighbourhood_when_sampled_then_every_kind_is_present() {
+        let params = parameters(20);
+        let mut program: Program = GenerateEngine::generate(params);
+        program.instructions = vec![program.instructions[0]; 3];
+
+        let neighbours = program.neighbours(params);
+        let kinds = |neighbours: &[(Perturbation, Program)]| {
+            neighbours
+                .iter()
+                .map(|(perturbation, _)| perturbation.kind())
+                .unique()
+                .sorted()
+                .collect_vec()
+        };
+        let all_kinds = kinds(&neighbours);
+        assert_eq!(
+            all_kinds,
+            vec!["delete", "insert", "op", "source", "target"]
+        );
+
+        for max_neighbours in [5, 8] {
+            let sampled = sample_stratified(neighbours.clone(), max_neighbours);
+
+            assert!(sampled.len() <= max_neighbours);
+            assert_eq!(kinds(&sampled), all_kinds);
+        }
+    }
+}
@@ -1,25 +1,118 @@
-use std::error::Error;
+use std::{fs::File, io::Read, path::Path};
 
 use csv::ReaderBuilder;
-use reqwest::get;
 use serde::de::DeserializeOwned;
 
-pub async fn download_and_load_csv<T>(url: &str) -> Result<Vec<T>, Box<dyn Error>>
-where
-    T: DeserializeOwned + Send,
-{
-    let response = get(url).await?;
-    let content = response.text().await?;
+use super::error::{LgpError, LgpResult};
 
-    let mut csv_reader = ReaderBuilder::new()
-        .has_headers(false)
-        .from_reader(content.as_bytes());
+/// Reads CSV rows into any deserializable row type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataLoader {
+    pub delimiter: u8,
+    /// Whether the first line holds column names rather than a row.
+    pub has_headers: bool,
+}
+
+impl Default for DataLoader {
+    fn default() -> Self {
+        DataLoader {
+            delimiter: b',',
+            has_headers: false,
+        }
+    }
+}
+
+impl DataLoader {
+    /// Parses every row, failing with the index of the first malformed one.
+    pub fn from_reader<T, R>(&self, reader: R) -> LgpResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .from_reader(reader)
+            .deserialize()
+            .enumerate()
+            .map(|(row, result)| result.map_err(|source| LgpError::MalformedRow { row, source }))
+            .collect()
+    }
+
+    pub fn from_path<T>(&self, path: &Path) -> LgpResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        self.from_reader(File::open(path)?)
+    }
+
+    #[cfg(feature = "download")]
+    pub fn from_url<T>(&self, url: &str) -> LgpResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        self.from_reader(download(url)?.as_bytes())
+    }
+}
+
+/// Fetches the body of `url`, blocking the current thread.
+#[cfg(feature = "download")]
+pub fn download(url: &str) -> LgpResult<String> {
+    let download = |source| LgpError::DatasetDownload {
+        url: url.to_string(),
+        source,
+    };
+
+    reqwest::blocking::get(url)
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(download)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        x: f64,
+        label: String,
+    }
+
+    fn row(x: f64, label: &str) -> Row {
+        Row {
+            x,
+            label: label.to_string(),
+        }
+    }
+
+    #[test]
+    fn given_headerless_csv_when_loaded_then_every_line_is_a_row() {
+        let rows: Vec<Row> = DataLoader::default()
+            .from_reader("1.5,a\n2,b\n".as_bytes())
+            .unwrap();
+
+        assert_eq!(rows, vec![row(1.5, "a"), row(2., "b")]);
+    }
+
+    #[test]
+    fn given_custom_delimiter_and_headers_when_loaded_then_rows_are_parsed() {
+        let loader = DataLoader {
+            delimiter: b';',
+            has_headers: true,
+        };
+        let rows: Vec<Row> = loader.from_reader("x;label\n3;c\n".as_bytes()).unwrap();
+
+        assert_eq!(rows, vec![row(3., "c")]);
+    }
 
-    let inputs: Result<Vec<T>, _> = csv_reader
-        .deserialize()
-        .into_iter()
-        .map(|input| input)
-        .collect();
+    #[test]
+    fn given_malformed_row_when_loaded_then_error_names_the_row() {
+        let result =
+            DataLoader::default().from_reader::<Row, _>("1,a\n2,b\nnot a number,c\n".as_bytes());
 
-    Ok(inputs?)
+        assert!(matches!(result, Err(LgpError::MalformedRow { row: 2, .. })));
+    }
 }
@@ -0,0 +1,78 @@
+#[cfg(feature = "file-logging")]
+use std::path::Path;
+
+use tracing::subscriber::DefaultGuard;
+#[cfg(feature = "file-logging")]
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::EnvFilter;
+
+/// Installs the global JSON subscriber (written to stderr) unless one is already set.
+///
+/// Returns `true` if this call installed the subscriber, `false` if an existing one was kept.
+pub fn init_tracing() -> bool {
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .try_init()
+        .is_ok()
+}
+
+/// Installs the global JSON subscriber, writing to daily rotated files under `directory`.
+///
+/// Lines are written on a background thread and flushed until the returned guard is dropped;
+/// `None` means an existing subscriber was kept.
+#[cfg(feature = "file-logging")]
+pub fn init_file_tracing(directory: impl AsRef<Path>) -> Option<WorkerGuard> {
+    let appender = tracing_appender::rolling::daily(directory, "lgp.log");
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(writer)
+        .try_init()
+        .ok()
+        .map(|_| guard)
+}
+
+/// Installs a subscriber for the current thread only, until the returned guard is dropped.
+///
+/// Intended for tests, where several test functions may want to capture logs concurrently.
+pub fn init_tracing_scoped() -> DefaultGuard {
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .finish();
+
+    tracing::subscriber::set_default(subscriber)
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::info;
+
+    use super::*;
+
+    #[test]
+    fn given_global_subscriber_when_initialized_again_then_existing_subscriber_is_kept() {
+        init_tracing();
+
+        assert!(!init_tracing());
+    }
+
+    #[test]
+    fn given_scoped_subscriber_when_initialized_again_then_no_panic_occurs() {
+        let _outer = init_tracing_scoped();
+        info!(message = "outer");
+
+        {
+            let _inner = init_tracing_scoped();
+            info!(message = "inner");
+        }
+
+        init_tracing();
+        info!(message = "outer again");
+    }
+}
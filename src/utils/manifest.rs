@@ -0,0 +1,153 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::core::{
+    characteristics::{Load, Save},
+    engines::core_engine::{Core, HyperParameters, StopReason},
+};
+
+use super::{benchmark_tools::create_path, error::LgpResult};
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Completed,
+    Failed,
+    /// Stopped on request before `n_generations`; the outputs cover the completed generations.
+    Interrupted,
+}
+
+/// A file produced by a run, relative to the run directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+/// Provenance of a run directory: which code and configuration produced it, and what it holds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub crate_version: String,
+    pub git_commit: String,
+    pub rustc_version: String,
+    pub hostname: String,
+    /// Seconds since the Unix epoch.
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub seed: Option<u64>,
+    pub config: serde_json::Value,
+    pub status: RunStatus,
+    pub error: Option<String>,
+    /// Why a completed run stopped before `n_generations`, e.g. `wall_time`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped_reason: Option<StopReason>,
+    /// Checksums are computed when the run finishes, so the list is empty while running.
+    pub artifacts: Vec<Artifact>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn hostname() -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    return gethostname::gethostname().to_string_lossy().into_owned();
+    #[cfg(target_arch = "wasm32")]
+    return "unknown".to_string();
+}
+
+impl Manifest {
+    pub fn start<C>(params: &HyperParameters<C>) -> LgpResult<Self>
+    where
+        C: Core,
+    {
+        Ok(Manifest {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: env!("LGP_GIT_COMMIT").to_string(),
+            rustc_version: env!("LGP_RUSTC_VERSION").to_string(),
+            hostname: hostname(),
+            started_at: now(),
+            finished_at: None,
+            seed: params.seed,
+            config: serde_json::to_value(params)?,
+            status: RunStatus::Running,
+            error: None,
+            stopped_reason: None,
+            artifacts: vec![],
+        })
+    }
+
+    /// Records every file under `run_dir` with its checksum.
+    pub fn complete(&mut self, run_dir: &Path) -> LgpResult<()> {
+        self.artifacts = artifacts(run_dir)?;
+        self.status = RunStatus::Completed;
+        self.finished_at = Some(now());
+
+        Ok(())
+    }
+
+    pub fn fail(&mut self, error: impl ToString) {
+        self.status = RunStatus::Failed;
+        self.error = Some(error.to_string());
+        self.finished_at = Some(now());
+    }
+
+    /// Writes `manifest.json` into `run_dir`, replacing any previous version.
+    pub fn write(&self, run_dir: &Path) -> LgpResult<()> {
+        let path = create_path(run_dir.join(MANIFEST_FILE_NAME).to_str().unwrap(), true)?;
+
+        self.write_to(File::create(path)?)
+    }
+
+    pub fn read(run_dir: &Path) -> LgpResult<Self> {
+        Self::read_from(File::open(run_dir.join(MANIFEST_FILE_NAME))?)
+    }
+}
+
+pub fn sha256_file(path: &Path) -> LgpResult<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Every file under `run_dir` except the manifest, sorted by path.
+fn artifacts(run_dir: &Path) -> LgpResult<Vec<Artifact>> {
+    let mut pending = vec![run_dir.to_path_buf()];
+    let mut artifacts = vec![];
+
+    while let Some(directory) = pending.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                pending.push(path);
+            } else if path != run_dir.join(MANIFEST_FILE_NAME) {
+                artifacts.push(Artifact {
+                    sha256: sha256_file(&path)?,
+                    path: path.strip_prefix(run_dir).unwrap().to_path_buf(),
+                });
+            }
+        }
+    }
+
+    artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(artifacts)
+}
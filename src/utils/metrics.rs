@@ -0,0 +1,363 @@
+//! Metrics of running evolutions for Prometheus: `CoreIter`s given a `MetricsRecorder` record
+//! them every generation, and `MetricsExporter` serves the latest values of every run in the
+//! text exposition format on `GET /metrics`.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    net::{SocketAddr, ToSocketAddrs},
+    sync::{Mutex, OnceLock},
+    thread,
+    time::Instant,
+};
+
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::warn;
+
+use crate::core::{
+    characteristics::Genotype,
+    diversity::fingerprint,
+    engines::{
+        core_engine::{mean_trial_variance, Core},
+        status_engine::Status,
+    },
+};
+
+use super::error::{LgpError, LgpResult};
+
+/// Identifies a run among those exported by the process.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RunLabels {
+    pub experiment: String,
+    pub run_id: String,
+}
+
+/// Latest metrics of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunMetrics {
+    /// Generations completed.
+    pub generation: usize,
+    pub best: f64,
+    pub median: f64,
+    pub worst: f64,
+    pub n_evaluations: usize,
+    /// Evaluations per second over the last generation.
+    pub evaluations_per_second: f64,
+    /// Share of structurally distinct individuals in the population.
+    pub diversity: f64,
+    /// Individuals of the population that are not valid, e.g. with a non-finite fitness.
+    pub n_invalid: usize,
+    /// Mean within-individual variance of the trial scores; NaN unless `record_trial_scores`.
+    pub trial_variance: f64,
+}
+
+static RUNS: OnceLock<Mutex<BTreeMap<RunLabels, RunMetrics>>> = OnceLock::new();
+
+fn runs() -> &'static Mutex<BTreeMap<RunLabels, RunMetrics>> {
+    RUNS.get_or_init(Default::default)
+}
+
+/// Replaces the metrics of the run labelled `labels`.
+pub fn record(labels: &RunLabels, metrics: RunMetrics) {
+    runs()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(labels.clone(), metrics);
+}
+
+/// Latest metrics of the run labelled `labels`, if it recorded any.
+pub fn run_metrics(labels: &RunLabels) -> Option<RunMetrics> {
+    runs()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(labels)
+        .copied()
+}
+
+/// Records the metrics of a run at the end of every generation.
+#[derive(Debug, Clone)]
+pub struct MetricsRecorder {
+    labels: RunLabels,
+    /// When the previous generation ended, and the evaluations counted by then.
+    last: (Instant, usize),
+}
+
+impl MetricsRecorder {
+    pub fn new(experiment: &str, run_id: &str) -> Self {
+        MetricsRecorder {
+            labels: RunLabels {
+                experiment: experiment.to_string(),
+                run_id: run_id.to_string(),
+            },
+            last: (Instant::now(), 0),
+        }
+    }
+
+    pub fn labels(&self) -> &RunLabels {
+        &self.labels
+    }
+
+    /// Records the ranked `population` of the generation at index `generation`, with the
+    /// evaluations counted since the run started.
+    pub fn record<C>(
+        &mut self,
+        generation: usize,
+        population: &[C::Individual],
+        n_evaluations: usize,
+    ) where
+        C: Core,
+    {
+        let (last_recorded, last_n_evaluations) = self.last;
+        let seconds = last_recorded.elapsed().as_secs_f64();
+        let fitness = |individual: Option<&C::Individual>| {
+            individual.map_or(f64::NAN, C::Status::get_fitness)
+        };
+        let n_unique = population
+            .iter()
+            .map(|individual| fingerprint(individual.instructions()))
+            .collect::<HashSet<_>>()
+            .len();
+
+        record(
+            &self.labels,
+            RunMetrics {
+                generation: generation + 1,
+                best: fitness(population.first()),
+                median: fitness(population.get(population.len() / 2)),
+                worst: fitness(population.last()),
+                n_evaluations,
+                evaluations_per_second: match seconds > 0. {
+                    true => (n_evaluations - last_n_evaluations) as f64 / seconds,
+                    false => 0.,
+                },
+                diversity: n_unique as f64 / population.len().max(1) as f64,
+                n_invalid: population
+                    .iter()
+                    .filter(|individual| !C::Status::valid(individual))
+                    .count(),
+                trial_variance: mean_trial_variance::<C>(population).unwrap_or(f64::NAN),
+            },
+        );
+        self.last = (Instant::now(), n_evaluations);
+    }
+}
+
+/// The metrics of every run in the Prometheus text exposition format.
+pub fn render_metrics() -> String {
+    let runs = runs()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    let families: [(&str, &str, &str, fn(&RunMetrics) -> f64); 9] = [
+        ("lgp_generation", "gauge", "Generations completed.", |m| {
+            m.generation as f64
+        }),
+        (
+            "lgp_best_fitness",
+            "gauge",
+            "Fitness of the best individual.",
+            |m| m.best,
+        ),
+        (
+            "lgp_median_fitness",
+            "gauge",
+            "Fitness of the median individual.",
+            |m| m.median,
+        ),
+        (
+            "lgp_worst_fitness",
+            "gauge",
+            "Fitness of the worst individual.",
+            |m| m.worst,
+        ),
+        (
+            "lgp_evaluations_total",
+            "counter",
+            "Fitness evaluations.",
+            |m| m.n_evaluations as f64,
+        ),
+        (
+            "lgp_evaluations_per_second",
+            "gauge",
+            "Fitness evaluations per second over the last generation.",
+            |m| m.evaluations_per_second,
+        ),
+        (
+            "lgp_diversity",
+            "gauge",
+            "Share of structurally distinct individuals.",
+            |m| m.diversity,
+        ),
+        (
+            "lgp_invalid_individuals",
+            "gauge",
+            "Invalid individuals.",
+            |m| m.n_invalid as f64,
+        ),
+        (
+            "lgp_trial_variance",
+            "gauge",
+            "Mean within-individual variance of the trial scores.",
+            |m| m.trial_variance,
+        ),
+    ];
+
+    let mut text = String::new();
+    for (name, kind, help, value) in families {
+        text += &format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind);
+
+        for (labels, metrics) in &runs {
+            text += &format!(
+                "{}{{experiment=\"{}\",run_id=\"{}\"}} {}\n",
+                name,
+                escape_label(&labels.experiment),
+                escape_label(&labels.run_id),
+                format_value(value(metrics))
+            );
+        }
+    }
+
+    text
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    match value {
+        value if value.is_nan() => "NaN".to_string(),
+        value if value == f64::INFINITY => "+Inf".to_string(),
+        value if value == f64::NEG_INFINITY => "-Inf".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Serves `GET /metrics` with `render_metrics`.
+pub struct MetricsExporter {
+    server: Server,
+}
+
+impl MetricsExporter {
+    /// Binds to `address`; port `0` picks a free port, see `local_addr`.
+    pub fn bind(address: impl ToSocketAddrs) -> LgpResult<Self> {
+        let server = Server::http(address).map_err(|error| LgpError::Other(error.to_string()))?;
+
+        Ok(MetricsExporter { server })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server
+            .server_addr()
+            .to_ip()
+            .expect("Metrics exporters listen on TCP.")
+    }
+
+    /// Serves on a background thread, returning the address it listens on.
+    pub fn spawn(self) -> SocketAddr {
+        let address = self.local_addr();
+        thread::spawn(move || {
+            for request in self.server.incoming_requests() {
+                handle(request);
+            }
+        });
+
+        address
+    }
+}
+
+fn handle(request: Request) {
+    let response = match (request.method(), request.url()) {
+        (Method::Get, "/metrics") => {
+            let header = Header::from_bytes("Content-Type", "text/plain; version=0.0.4")
+                .expect("Static header is valid.");
+
+            Response::from_string(render_metrics()).with_header(header)
+        }
+        _ => Response::from_string("Not found").with_status_code(404),
+    };
+
+    if let Err(error) = request.respond(response) {
+        warn!(error = %error, "Failed to answer a metrics request.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    use itertools::Itertools;
+
+    use crate::{
+        core::{
+            engines::{core_engine::HyperParametersBuilder, status_engine::StatusEngine},
+            instruction::InstructionGeneratorParametersBuilder,
+            program::ProgramGeneratorParametersBuilder,
+        },
+        problems::iris::IrisEngine,
+    };
+
+    use super::*;
+
+    fn scrape(address: SocketAddr) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET /metrics HTTP/1.0\r\nHost: localhost\r\n\r\n").unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn given_run_with_recorder_when_two_generations_pass_then_scrape_reports_them() {
+        let address = MetricsExporter::bind("127.0.0.1:0").unwrap().spawn();
+
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()
+            .unwrap();
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()
+            .unwrap();
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_generations(2)
+            .n_trials(1)
+            .seed(Some(3))
+            .dataset_path(Some("assets/fixtures/iris.csv".into()))
+            .build()
+            .unwrap();
+
+        let recorder = MetricsRecorder::new("iris_metrics", "iris_metrics/run_0");
+        let labels = recorder.labels().clone();
+        let populations = parameters
+            .try_build_engine()
+            .unwrap()
+            .with_metrics(recorder)
+            .take(parameters.n_generations)
+            .collect_vec();
+
+        let metrics = run_metrics(&labels).unwrap();
+        assert_eq!(metrics.generation, 2);
+        assert_eq!(metrics.best, StatusEngine::get_fitness(&populations[1][0]));
+        assert!(metrics.n_evaluations > 0);
+        assert!(metrics.diversity > 0. && metrics.diversity <= 1.);
+
+        let response = scrape(address);
+        assert!(response.starts_with("HTTP/1.1 200") || response.starts_with("HTTP/1.0 200"));
+        assert!(response.contains("# TYPE lgp_generation gauge"));
+        assert!(response.contains(
+            "lgp_generation{experiment=\"iris_metrics\",run_id=\"iris_metrics/run_0\"} 2\n"
+        ));
+        assert!(response.contains("lgp_evaluations_total{experiment=\"iris_metrics\""));
+    }
+}
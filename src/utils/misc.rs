@@ -1,11 +1,14 @@
 use std::error::Error;
 
-use crate::core::engines::reset_engine::{Reset, ResetEngine};
+use crate::{
+    core::engines::reset_engine::{Reset, ResetEngine},
+    utils::random::new_id,
+};
 
 pub type VoidResultAnyError = Result<(), Box<dyn Error>>;
 
 impl Reset<uuid::Uuid> for ResetEngine {
     fn reset(item: &mut uuid::Uuid) {
-        *item = uuid::Uuid::new_v4();
+        *item = new_id();
     }
 }
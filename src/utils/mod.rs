@@ -1,6 +1,35 @@
+#[cfg(feature = "cli")]
+pub mod batch;
 pub mod benchmark_tools;
+pub mod comparison;
+#[cfg(feature = "cli")]
+pub mod config_discovery;
+pub mod convert;
+pub mod error;
+pub mod events;
 pub mod float_ops;
+pub mod inference;
+#[cfg(feature = "serve")]
+pub mod jobs;
+pub mod landscape;
 pub mod loader;
+pub mod logging;
+pub mod manifest;
+#[cfg(feature = "metrics-export")]
+pub mod metrics;
 pub mod misc;
+pub mod paired;
+#[cfg(feature = "plots")]
+pub mod plotting;
+pub mod population_stream;
+pub mod predict;
 pub mod random;
+pub mod refine;
+#[cfg(feature = "serve")]
+pub mod remote;
+pub mod run_store;
+pub mod shutdown;
+pub mod sweep;
 pub mod test;
+pub mod trajectory;
+pub mod tune;
@@ -0,0 +1,217 @@
+//! Paired comparison of two engines on the same problem: every repeat runs both arms with the
+//! same seed, so they are evaluated on identical trials and differ only in the engine.
+
+use std::path::Path;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::core::{
+    characteristics::Save,
+    engines::{
+        core_engine::{Core, HyperParameters, TrialSeed},
+        status_engine::Status,
+    },
+};
+
+use super::{
+    benchmark_tools::SummaryStatistics,
+    comparison::wilcoxon_signed_rank,
+    error::{LgpError, LgpResult},
+};
+
+/// Which arm reached the better final best fitness in a repeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PairedOutcome {
+    Win,
+    Loss,
+    Tie,
+}
+
+/// A repeat of both arms, from the point of view of arm A.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairedRepeat {
+    pub seed: u64,
+    pub final_best_a: f64,
+    pub final_best_b: f64,
+    pub outcome: PairedOutcome,
+    /// Whether both arms were evaluated on the same trial seeds in every generation.
+    pub identical_trials: bool,
+}
+
+/// Mean over the repeats of the best fitness of arm A minus that of arm B.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PairedGeneration {
+    pub generation: usize,
+    pub mean_difference: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairedReport {
+    pub arm_a: String,
+    pub arm_b: String,
+    pub repeats: Vec<PairedRepeat>,
+    pub final_best_a: SummaryStatistics,
+    pub final_best_b: SummaryStatistics,
+    pub wins: usize,
+    pub losses: usize,
+    pub ties: usize,
+    /// Signed-rank sum of the repeats arm A won.
+    pub wilcoxon_statistic: f64,
+    /// Two-sided p-value of the Wilcoxon signed-rank test on the final best fitness.
+    pub p_value: f64,
+    pub generations: Vec<PairedGeneration>,
+}
+
+/// Best fitness of every generation of a run, with the trial seeds each generation was
+/// evaluated on.
+fn run_arm<C>(params: &HyperParameters<C>) -> LgpResult<(Vec<f64>, Vec<Vec<Option<TrialSeed>>>)>
+where
+    C: Core,
+{
+    let mut engine = params.try_build_engine()?;
+    let mut best = vec![];
+    let mut trial_seeds = vec![];
+
+    for _ in 0..params.n_generations {
+        let seeds = engine.trial_seeds().to_vec();
+        let Some(population) = engine.next() else {
+            break;
+        };
+
+        best.push(C::Status::get_fitness(&population[0]));
+        trial_seeds.push(seeds);
+    }
+
+    Ok((best, trial_seeds))
+}
+
+fn reseeded<C>(params: &HyperParameters<C>, seed: u64) -> HyperParameters<C>
+where
+    C: Core,
+{
+    let mut params = params.clone();
+    params.seed = Some(seed);
+    params
+}
+
+/// Runs `n_repeats` repeats of both arms, seeding repeat `i` of each with `seed + i`.
+///
+/// Arms must use the same trials, i.e. the same number of trials and trial policy, so that their
+/// trials only depend on the seed.
+pub fn run_paired<A, B>(
+    arm_a: (&str, &HyperParameters<A>),
+    arm_b: (&str, &HyperParameters<B>),
+    n_repeats: usize,
+    seed: u64,
+) -> LgpResult<PairedReport>
+where
+    A: Core,
+    B: Core,
+{
+    let (name_a, params_a) = arm_a;
+    let (name_b, params_b) = arm_b;
+
+    if params_a.n_trials != params_b.n_trials || params_a.trial_policy != params_b.trial_policy {
+        return Err(LgpError::Config(format!(
+            "`{}` and `{}` must use the same n_trials and trial_policy to be paired",
+            name_a, name_b
+        )));
+    }
+
+    let mut repeats = vec![];
+    let mut curves_a = vec![];
+    let mut curves_b = vec![];
+
+    for repeat in 0..n_repeats {
+        let repeat_seed = seed + repeat as u64;
+        let (best_a, trials_a) = run_arm(&reseeded(params_a, repeat_seed))?;
+        let (best_b, trials_b) = run_arm(&reseeded(params_b, repeat_seed))?;
+
+        let final_best_a = best_a.last().copied().unwrap_or(f64::NAN);
+        let final_best_b = best_b.last().copied().unwrap_or(f64::NAN);
+        let outcome = if final_best_a > final_best_b {
+            PairedOutcome::Win
+        } else if final_best_a < final_best_b {
+            PairedOutcome::Loss
+        } else {
+            PairedOutcome::Tie
+        };
+
+        info!(
+            repeat = repeat,
+            seed = repeat_seed,
+            final_best_a = final_best_a,
+            final_best_b = final_best_b,
+            "Finished paired repeat."
+        );
+
+        repeats.push(PairedRepeat {
+            seed: repeat_seed,
+            final_best_a,
+            final_best_b,
+            outcome,
+            identical_trials: trials_a == trials_b,
+        });
+        curves_a.push(best_a);
+        curves_b.push(best_b);
+    }
+
+    let final_a = repeats
+        .iter()
+        .map(|repeat| repeat.final_best_a)
+        .collect_vec();
+    let final_b = repeats
+        .iter()
+        .map(|repeat| repeat.final_best_b)
+        .collect_vec();
+    let (wilcoxon_statistic, p_value) = wilcoxon_signed_rank(&final_a, &final_b);
+    let count = |outcome| {
+        repeats
+            .iter()
+            .filter(|repeat| repeat.outcome == outcome)
+            .count()
+    };
+
+    let n_generations = curves_a
+        .iter()
+        .chain(curves_b.iter())
+        .map(Vec::len)
+        .min()
+        .unwrap_or(0);
+    let generations = (0..n_generations)
+        .map(|generation| PairedGeneration {
+            generation,
+            mean_difference: curves_a
+                .iter()
+                .zip(&curves_b)
+                .map(|(a, b)| a[generation] - b[generation])
+                .sum::<f64>()
+                / n_repeats as f64,
+        })
+        .collect();
+
+    Ok(PairedReport {
+        arm_a: name_a.to_string(),
+        arm_b: name_b.to_string(),
+        final_best_a: SummaryStatistics::from_values(&final_a),
+        final_best_b: SummaryStatistics::from_values(&final_b),
+        wins: count(PairedOutcome::Win),
+        losses: count(PairedOutcome::Loss),
+        ties: count(PairedOutcome::Tie),
+        wilcoxon_statistic,
+        p_value,
+        generations,
+        repeats,
+    })
+}
+
+/// Saves the report as `paired.json` under `output_root/name`.
+pub fn save_paired_report(report: &PairedReport, output_root: &Path, name: &str) -> LgpResult<()> {
+    let path = output_root.join(name).join("paired.json");
+    report.save(path.to_str().unwrap())?;
+
+    Ok(())
+}
@@ -0,0 +1,308 @@
+use std::{error::Error, path::Path};
+
+use plotters::{coord::Shift, prelude::*};
+
+use crate::{core::engines::core_engine::Core, extensions::q_learning::QTable};
+
+use super::{benchmark_tools::FitnessCurves, misc::VoidResultAnyError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotParameters {
+    pub title: String,
+    /// Fixed y-axis range; fitted to the curves when unset.
+    pub y_range: Option<(f64, f64)>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for PlotParameters {
+    fn default() -> Self {
+        PlotParameters {
+            title: "Fitness".to_string(),
+            y_range: None,
+            width: 800,
+            height: 600,
+        }
+    }
+}
+
+fn fitted_range(curves: &FitnessCurves) -> (f64, f64) {
+    let values = curves
+        .best
+        .iter()
+        .chain(curves.median.iter())
+        .chain(curves.worst.iter())
+        .copied()
+        .filter(|value| value.is_finite());
+
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+        (min.min(value), max.max(value))
+    });
+
+    if min > max {
+        (0., 1.)
+    } else if min == max {
+        (min - 1., max + 1.)
+    } else {
+        (min, max)
+    }
+}
+
+fn draw<DB>(
+    root: DrawingArea<DB, Shift>,
+    curves: &FitnessCurves,
+    parameters: &PlotParameters,
+) -> VoidResultAnyError
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let (y_min, y_max) = parameters.y_range.unwrap_or_else(|| fitted_range(curves));
+    let n_generations = curves.len().max(2);
+
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(&parameters.title, ("sans-serif", 24))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0..n_generations - 1, y_min..y_max)?;
+
+    chart
+        .configure_mesh()
+        .x_desc("Generation")
+        .y_desc("Fitness")
+        .draw()?;
+
+    let series = [
+        ("best", &curves.best, BLUE),
+        ("median", &curves.median, GREEN),
+        ("worst", &curves.worst, RED),
+    ];
+
+    for (label, values, colour) in series {
+        chart
+            .draw_series(LineSeries::new(
+                values
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .filter(|(_, value)| value.is_finite()),
+                &colour,
+            ))?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &colour));
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(&WHITE.mix(0.8))
+        .border_style(&BLACK)
+        .draw()?;
+
+    root.present()?;
+
+    Ok(())
+}
+
+/// Renders best, median and worst fitness per generation. The format follows the extension
+/// of `output`: `.svg` renders a vector image, anything else a bitmap (e.g. `.png`).
+pub fn plot_fitness(
+    curves: &FitnessCurves,
+    output: &Path,
+    parameters: &PlotParameters,
+) -> VoidResultAnyError {
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let size = (parameters.width, parameters.height);
+    let is_svg = output
+        .extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("svg"));
+
+    if is_svg {
+        draw(
+            SVGBackend::new(output, size).into_drawing_area(),
+            curves,
+            parameters,
+        )
+    } else {
+        draw(
+            BitMapBackend::new(output, size).into_drawing_area(),
+            curves,
+            parameters,
+        )
+    }
+}
+
+fn draw_q_table<DB>(
+    root: DrawingArea<DB, Shift>,
+    q_values: &[Vec<f64>],
+    parameters: &PlotParameters,
+) -> VoidResultAnyError
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let n_registers = q_values.len().max(1);
+    let n_actions = q_values.first().map_or(0, Vec::len).max(1);
+    let (min, max) = parameters.y_range.unwrap_or_else(|| {
+        let finite = q_values.iter().flatten().copied().filter(|v| v.is_finite());
+        finite.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        })
+    });
+
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(&parameters.title, ("sans-serif", 24))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0..n_actions, 0..n_registers)?;
+
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .x_desc("Action")
+        .y_desc("Register")
+        .x_labels(n_actions)
+        .y_labels(n_registers)
+        .draw()?;
+
+    // Low values are blue and high values red; non-finite values are left white.
+    let colour = |value: f64| {
+        let scaled = if max > min {
+            ((value - min) / (max - min)).clamp(0., 1.)
+        } else {
+            0.5
+        };
+        HSLColor(0.66 * (1. - scaled), 0.8, 0.5)
+    };
+
+    chart.draw_series(q_values.iter().enumerate().flat_map(|(register, values)| {
+        values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| value.is_finite())
+            .map(move |(action, &value)| {
+                Rectangle::new(
+                    [(action, register), (action + 1, register + 1)],
+                    colour(value).filled(),
+                )
+            })
+    }))?;
+
+    root.present()?;
+
+    Ok(())
+}
+
+/// Renders the Q-values of a table as a heatmap of registers by actions. `y_range` fixes the
+/// values mapped to the ends of the colour scale, which are fitted to the table when unset.
+pub fn plot_q_table(
+    q_table: &QTable,
+    output: &Path,
+    parameters: &PlotParameters,
+) -> VoidResultAnyError {
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let q_values = q_table.q_values();
+    let size = (parameters.width, parameters.height);
+    let is_svg = output
+        .extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("svg"));
+
+    if is_svg {
+        draw_q_table(
+            SVGBackend::new(output, size).into_drawing_area(),
+            &q_values,
+            parameters,
+        )
+    } else {
+        draw_q_table(
+            BitMapBackend::new(output, size).into_drawing_area(),
+            &q_values,
+            parameters,
+        )
+    }
+}
+
+pub fn plot_populations<C>(
+    populations: &[Vec<C::Individual>],
+    output: &Path,
+    parameters: &PlotParameters,
+) -> VoidResultAnyError
+where
+    C: Core,
+{
+    plot_fitness(
+        &FitnessCurves::from_populations::<C>(populations),
+        output,
+        parameters,
+    )
+}
+
+/// Plots the fitness curves saved in a run directory.
+pub fn plot_run(
+    run_dir: &Path,
+    output: &Path,
+    parameters: &PlotParameters,
+) -> Result<FitnessCurves, Box<dyn Error>> {
+    let curves = FitnessCurves::load_run(run_dir)?;
+    plot_fitness(&curves, output, parameters)?;
+
+    Ok(curves)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn curves() -> FitnessCurves {
+        FitnessCurves {
+            best: vec![0.4, 0.6, 0.8, 0.9],
+            median: vec![0.2, 0.4, 0.5, 0.7],
+            worst: vec![0., 0.1, f64::NEG_INFINITY, 0.3],
+        }
+    }
+
+    #[test]
+    fn given_saved_metrics_when_plotted_then_image_is_written() -> VoidResultAnyError {
+        let run_dir = env::temp_dir().join("lgp_plotting");
+        fs::create_dir_all(&run_dir)?;
+        curves().save_csv(&run_dir.join("metrics.csv"))?;
+
+        for output in ["fitness.png", "fitness.svg"] {
+            let output = run_dir.join(output);
+            let loaded = plot_run(&run_dir, &output, &PlotParameters::default())?;
+
+            assert_eq!(loaded.best, curves().best);
+            assert!(fs::metadata(&output)?.len() > 1024);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_fixed_y_range_then_non_finite_values_are_skipped() -> VoidResultAnyError {
+        let output = env::temp_dir().join("lgp_plotting").join("fixed.png");
+        let parameters = PlotParameters {
+            title: "Iris".to_string(),
+            y_range: Some((0., 1.)),
+            ..Default::default()
+        };
+
+        plot_fitness(&curves(), &output, &parameters)?;
+        assert!(fs::metadata(&output)?.len() > 1024);
+
+        Ok(())
+    }
+}
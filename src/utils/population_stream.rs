@@ -0,0 +1,231 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::error;
+
+use crate::core::engines::core_engine::{Core, GenerationObserver};
+
+use super::misc::VoidResultAnyError;
+
+/// Writes populations to a gzip-compressed JSONL file, one generation per line, so that no
+/// more than one generation is ever serialized in memory.
+pub struct PopulationWriter {
+    encoder: GzEncoder<BufWriter<File>>,
+    n_generations: usize,
+}
+
+impl PopulationWriter {
+    pub fn create(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = BufWriter::new(File::create(path)?);
+
+        Ok(PopulationWriter {
+            encoder: GzEncoder::new(file, Compression::default()),
+            n_generations: 0,
+        })
+    }
+
+    /// Appends a generation, flushing it so that the file can be read while the run continues.
+    pub fn write_generation<T>(&mut self, population: &[T]) -> VoidResultAnyError
+    where
+        T: Serialize,
+    {
+        serde_json::to_writer(&mut self.encoder, population)?;
+        self.encoder.write_all(b"\n")?;
+        self.encoder.flush()?;
+        self.n_generations += 1;
+
+        Ok(())
+    }
+
+    pub fn n_generations(&self) -> usize {
+        self.n_generations
+    }
+
+    /// Writes the gzip trailer. Dropping the writer does the same but ignores errors.
+    pub fn finish(self) -> VoidResultAnyError {
+        self.encoder.finish()?.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Reads the generations written by a `PopulationWriter` one at a time.
+pub struct PopulationReader<T> {
+    lines: std::io::Lines<BufReader<MultiGzDecoder<File>>>,
+    individual: PhantomData<T>,
+}
+
+impl<T> PopulationReader<T> {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let decoder = MultiGzDecoder::new(File::open(path)?);
+
+        Ok(PopulationReader {
+            lines: BufReader::new(decoder).lines(),
+            individual: PhantomData,
+        })
+    }
+}
+
+impl<T> Iterator for PopulationReader<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<Vec<T>, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.lines.next()?;
+
+        Some(
+            line.map_err(Into::into)
+                .and_then(|line| serde_json::from_str(&line).map_err(Into::into)),
+        )
+    }
+}
+
+/// Reads every generation of a population stream.
+pub fn read_populations<T>(path: &Path) -> Result<Vec<Vec<T>>, Box<dyn Error>>
+where
+    T: DeserializeOwned,
+{
+    PopulationReader::open(path)?.collect()
+}
+
+/// Streams every ranked generation of a run to a `PopulationWriter`. Write failures are logged
+/// and stop the recording without ending the run.
+pub struct PopulationRecorder<C> {
+    writer: Option<PopulationWriter>,
+    core: PhantomData<fn() -> C>,
+}
+
+impl<C> PopulationRecorder<C> {
+    pub fn create(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(PopulationRecorder {
+            writer: Some(PopulationWriter::create(path)?),
+            core: PhantomData,
+        })
+    }
+}
+
+impl<C> GenerationObserver<C> for PopulationRecorder<C>
+where
+    C: Core,
+{
+    fn on_generation(&mut self, generation: usize, ranked: &[C::Individual]) {
+        if let Some(writer) = self.writer.as_mut() {
+            if let Err(failure) = writer.write_generation(ranked) {
+                error!(generation, "Failed to record population: {}", failure);
+                self.writer = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use itertools::Itertools;
+
+    use crate::{
+        core::{
+            engines::{
+                core_engine::HyperParametersBuilder,
+                status_engine::{Status, StatusEngine},
+            },
+            instruction::InstructionGeneratorParametersBuilder,
+            program::{Program, ProgramGeneratorParametersBuilder},
+        },
+        data::dataset::Dataset,
+        problems::iris::{IrisEngine, IrisStateParameters},
+    };
+
+    use super::*;
+
+    #[test]
+    fn given_three_generations_when_written_then_they_are_read_back_in_order() -> VoidResultAnyError
+    {
+        let path = env::temp_dir()
+            .join("lgp_population_stream")
+            .join("round_trip.jsonl.gz");
+        let generations = vec![vec![1., 2.], vec![3.], vec![4., 5., 6.]];
+
+        let mut writer = PopulationWriter::create(&path)?;
+        for generation in &generations {
+            writer.write_generation(generation)?;
+        }
+        assert_eq!(writer.n_generations(), 3);
+        writer.finish()?;
+
+        assert_eq!(read_populations::<f64>(&path)?, generations);
+
+        Ok(())
+    }
+
+    #[test]
+    fn given_recorder_when_iterating_then_each_generation_is_written_as_it_is_produced(
+    ) -> VoidResultAnyError {
+        let path = env::temp_dir()
+            .join("lgp_population_stream")
+            .join("recorded.jsonl.gz");
+
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_trials(1)
+            .build()?;
+        let dataset = Dataset::new(
+            vec![vec![5.1, 3.5, 1.4, 0.2], vec![7.0, 3.2, 4.7, 1.4]],
+            vec![0, 1],
+            vec![],
+            vec![],
+        );
+
+        let mut engine = parameters
+            .build_engine_with(IrisStateParameters::new(dataset))
+            .with_observer(Box::new(PopulationRecorder::<IrisEngine>::create(&path)?));
+
+        // The file grows with every generation rather than being written once at the end.
+        let mut sizes = vec![fs::metadata(&path)?.len()];
+        let mut populations = vec![];
+        for _ in 0..3 {
+            populations.push(engine.next().unwrap());
+            sizes.push(fs::metadata(&path)?.len());
+        }
+        drop(engine);
+
+        assert!(sizes.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let recorded = read_populations::<Program>(&path)?;
+        assert_eq!(recorded.len(), 3);
+        for (recorded, population) in recorded.iter().zip(populations.iter()) {
+            assert_eq!(
+                recorded.iter().map(StatusEngine::get_fitness).collect_vec(),
+                population
+                    .iter()
+                    .map(StatusEngine::get_fitness)
+                    .collect_vec()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use std::{error::Error, path::Path};
+
+use csv::{ReaderBuilder, Writer};
+
+use crate::{
+    core::{
+        engines::{
+            freeze_engine::{Freeze, FreezeEngine},
+            reset_engine::{Reset, ResetEngine},
+        },
+        environment::State,
+        program::Program,
+        registers::{ArgmaxInput, ArgmaxResult},
+    },
+    extensions::q_learning::QProgram,
+};
+
+use super::benchmark_tools::create_path;
+
+/// Runs a trained individual on a single state and returns the predicted class or action.
+pub trait Predictor {
+    fn predict(&mut self, state: &mut impl State) -> usize;
+
+    /// Predicts every row of feature values independently of the others.
+    fn predict_batch(&mut self, rows: &[Vec<f64>]) -> Vec<usize> {
+        rows.iter()
+            .map(|row| self.predict(&mut Features(row)))
+            .collect()
+    }
+}
+
+impl Predictor for Program {
+    /// Registers are cleared first, so every state is predicted independently. Ties go to the
+    /// lowest action and overflowing registers predict action 0.
+    fn predict(&mut self, state: &mut impl State) -> usize {
+        ResetEngine::reset(&mut self.registers);
+        self.run(state);
+
+        match self.registers.argmax(ArgmaxInput::OutputRegisters) {
+            ArgmaxResult::MaxValues(indices) => indices[0],
+            ArgmaxResult::Overflow => 0,
+        }
+    }
+}
+
+impl Predictor for QProgram {
+    /// Acts greedily on a frozen Q-table, so predicting never explores or learns.
+    fn predict(&mut self, state: &mut impl State) -> usize {
+        FreezeEngine::freeze(self);
+        self.q_table.set_greedy(true);
+        self.q_table.restart_exploration();
+
+        ResetEngine::reset(&mut self.program.registers);
+        self.program.run(state);
+
+        self.q_table
+            .get_action_register(&self.program.registers)
+            .map(|pair| pair.action())
+            .unwrap_or(0)
+    }
+}
+
+/// A single row of feature values.
+pub struct Features<'a>(pub &'a [f64]);
+
+impl State for Features<'_> {
+    fn get_value(&self, at_idx: usize) -> f64 {
+        self.0[at_idx]
+    }
+
+    fn execute_action(&mut self, _action: usize) -> f64 {
+        0.
+    }
+
+    fn get(&mut self) -> Option<&mut Self> {
+        Some(self)
+    }
+}
+
+/// Reads the first `n_inputs` columns of every row of a headerless CSV.
+pub fn load_features(input: &Path, n_inputs: usize) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    let mut reader = ReaderBuilder::new().has_headers(false).from_path(input)?;
+    let mut rows = vec![];
+
+    for record in reader.records() {
+        let record = record?;
+
+        if record.len() < n_inputs {
+            return Err(format!(
+                "Expected at least {} columns but found {}.",
+                n_inputs,
+                record.len()
+            )
+            .into());
+        }
+
+        let row = record
+            .iter()
+            .take(n_inputs)
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// Predicts every row of `input` (extra columns, e.g. labels, are ignored) and writes one
+/// prediction per row to `output`. Returns the number of predictions.
+pub fn predict_csv(
+    model: &mut impl Predictor,
+    input: &Path,
+    output: &Path,
+    n_inputs: usize,
+) -> Result<usize, Box<dyn Error>> {
+    let rows = load_features(input, n_inputs)?;
+
+    create_path(output.to_str().unwrap(), true)?;
+    let mut writer = Writer::from_path(output)?;
+    writer.write_record(["prediction"])?;
+
+    for row in &rows {
+        let prediction = model.predict(&mut Features(row));
+        writer.write_record([prediction.to_string()])?;
+    }
+
+    writer.flush()?;
+
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use itertools::Itertools;
+
+    use crate::{
+        core::{
+            characteristics::{Load, Save},
+            engines::core_engine::HyperParametersBuilder,
+            instruction::InstructionGeneratorParametersBuilder,
+            program::ProgramGeneratorParametersBuilder,
+        },
+        data::dataset::Dataset,
+        problems::iris::{IrisEngine, IrisStateParameters},
+        utils::misc::VoidResultAnyError,
+    };
+
+    use super::*;
+
+    fn dataset() -> Dataset {
+        Dataset::new(
+            vec![
+                vec![5.1, 3.5, 1.4, 0.2],
+                vec![7.0, 3.2, 4.7, 1.4],
+                vec![6.3, 3.3, 6.0, 2.5],
+                vec![4.9, 3.0, 1.4, 0.2],
+                vec![6.4, 3.2, 4.5, 1.5],
+                vec![5.8, 2.7, 5.1, 1.9],
+            ],
+            vec![0, 1, 2, 0, 1, 2],
+            vec![],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn given_saved_iris_model_when_reloaded_then_predictions_are_identical() -> VoidResultAnyError {
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()?;
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(20)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()?;
+        let parameters = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(20)
+            .n_generations(5)
+            .n_trials(1)
+            .seed(Some(3))
+            .build()?;
+
+        let dataset = dataset();
+        let mut trained = parameters
+            .build_engine_with(IrisStateParameters::new(dataset.clone()))
+            .take(parameters.n_generations)
+            .last()
+            .and_then(|population| population.first().cloned())
+            .unwrap();
+
+        let directory = env::temp_dir().join("lgp_predict");
+        let model_path = directory.join("best.json");
+        trained.save(model_path.to_str().unwrap())?;
+        let mut reloaded = Program::load(&model_path)?;
+
+        let expected = dataset
+            .features
+            .iter()
+            .map(|row| trained.predict(&mut Features(row)))
+            .collect_vec();
+        let actual = dataset
+            .features
+            .iter()
+            .map(|row| reloaded.predict(&mut Features(row)))
+            .collect_vec();
+        assert_eq!(actual, expected);
+
+        let input_path = directory.join("data.csv");
+        let output_path = directory.join("predictions.csv");
+        let csv = dataset
+            .features
+            .iter()
+            .zip(dataset.labels.iter())
+            .map(|(row, label)| format!("{},class-{}", row.iter().join(","), label))
+            .join("\n");
+        fs::write(&input_path, csv)?;
+
+        let n_predictions = predict_csv(&mut reloaded, &input_path, &output_path, 4)?;
+        assert_eq!(n_predictions, dataset.len());
+
+        let written = fs::read_to_string(&output_path)?
+            .lines()
+            .skip(1)
+            .map(|line| line.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(written, expected);
+
+        Ok(())
+    }
+}
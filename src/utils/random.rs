@@ -1,7 +1,14 @@
-use std::{cell::UnsafeCell, sync::Arc};
+use std::{
+    cell::{Cell, UnsafeCell},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
-use rand::{RngCore, SeedableRng};
+use rand::{Rng, RngCore, SeedableRng};
 use rand_xoshiro::Xoshiro256PlusPlus;
+use uuid::{Builder, Uuid};
 
 type InternalGenerator = Arc<UnsafeCell<Xoshiro256PlusPlus>>;
 
@@ -15,27 +22,183 @@ thread_local! {
         let prng = Xoshiro256PlusPlus::from_entropy();
 
         Arc::new(UnsafeCell::new(prng))
+    };
+
+    /// Master seed of a thread that seeded its own stream (`reseed`, `with_seed`,
+    /// `RngManager::enter`); other threads follow the shared master seed.
+    static PINNED_SEED: Cell<Option<u64>> = const { Cell::new(None) };
+
+    /// Last `MASTER_EPOCH` an unpinned thread derived its stream for.
+    static SYNCED_EPOCH: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Master seed of the last `reseed`, shared by every thread.
+static MASTER_SEED: AtomicU64 = AtomicU64::new(0);
+
+/// Number of `reseed` calls so far; `0` until the first one.
+static MASTER_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// Derives reproducible random streams from one master seed: one per rayon worker thread, and
+/// independent ones (`fork`) for anything that must not depend on what was drawn before, e.g.
+/// observers or trials.
+///
+/// A thread that calls `reseed` draws from the master seed itself. Rayon worker threads draw
+/// from the stream of their pool index instead, rederived whenever the master seed changes,
+/// so the same seed gives every worker the same values however many runs came before. Work
+/// handed to other threads should still be `enter`ed with a forked stream, as which worker
+/// picks it up is not deterministic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RngManager {
+    seed: u64,
+}
+
+impl RngManager {
+    pub fn new(seed: u64) -> Self {
+        RngManager { seed }
+    }
+
+    /// The master seed the calling thread draws from.
+    pub fn current() -> Self {
+        let seed = PINNED_SEED
+            .with(Cell::get)
+            .unwrap_or_else(|| MASTER_SEED.load(Ordering::Acquire));
+
+        RngManager::new(seed)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Seed of the stream of the worker with index `index` in a rayon pool; threads outside of
+    /// one use `usize::MAX`.
+    pub fn thread_seed(&self, index: usize) -> u64 {
+        derive_seed(splitmix64(self.seed), index as u128)
+    }
+
+    /// An independent stream for `key` that depends only on the master seed and `key`.
+    pub fn fork(&self, key: u128) -> Random {
+        let prng = Xoshiro256PlusPlus::seed_from_u64(derive_seed(self.seed, key));
+
+        Random {
+            rng: Arc::new(UnsafeCell::new(prng)),
+        }
     }
+
+    /// Runs `f` on the calling thread, whichever it is, drawing from the stream `fork(key)`
+    /// and with `run_seed` returning this master seed. The previous state of the thread is
+    /// restored afterwards.
+    pub fn enter<T>(&self, key: u128, f: impl FnOnce() -> T) -> T {
+        scoped(self.seed, derive_seed(self.seed, key), f)
+    }
+}
+
+/// Makes `seed` the master seed of the calling thread and of the rayon workers that do not
+/// seed their own streams. The calling thread then draws from `seed` directly.
+pub fn reseed(seed: u64) {
+    MASTER_SEED.store(seed, Ordering::Release);
+    MASTER_EPOCH.fetch_add(1, Ordering::AcqRel);
+    PINNED_SEED.with(|pinned| pinned.set(Some(seed)));
+
+    swap_generator(Xoshiro256PlusPlus::seed_from_u64(seed));
+}
+
+/// A seed for unseeded runs.
+pub fn entropy_seed() -> u64 {
+    Xoshiro256PlusPlus::from_entropy().next_u64()
 }
 
-/// This function should only be called once and at the top level of a program.
+/// `reseed` with `seed`, or with `entropy_seed` when there is none. Should be called once at
+/// the top level of a program, e.g. when an engine is built.
 pub fn update_seed(seed: Option<u64>) {
-    let prng = match seed {
-        Some(internal_seed) => Xoshiro256PlusPlus::seed_from_u64(internal_seed),
-        None => Xoshiro256PlusPlus::from_entropy(),
-    };
+    reseed(seed.unwrap_or_else(entropy_seed));
+}
+
+/// Seed of the current run on this thread.
+pub fn run_seed() -> u64 {
+    RngManager::current().seed()
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Deterministically combines a seed with a key (e.g. an individual's id) into a new seed.
+pub fn derive_seed(seed: u64, key: u128) -> u64 {
+    let high = (key >> 64) as u64;
+    let low = key as u64;
+
+    splitmix64(splitmix64(seed ^ splitmix64(high)) ^ low)
+}
+
+/// Samples from the standard normal distribution using the Box-Muller transform.
+pub fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    // `gen` samples from [0, 1), so shift to (0, 1] to keep the logarithm finite.
+    let u1 = 1. - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
 
+    (-2. * u1.ln()).sqrt() * (2. * std::f64::consts::PI * u2).cos()
+}
+
+/// Runs `f` with the generator of this thread temporarily seeded with `seed`, so that whatever
+/// `f` draws is reproducible regardless of what was drawn before. The previous state of the
+/// generator is restored afterwards.
+pub fn with_seed<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    scoped(run_seed(), seed, f)
+}
+
+fn swap_generator(prng: Xoshiro256PlusPlus) -> Xoshiro256PlusPlus {
     GENERATOR.with(|t| {
         let generator = unsafe { &mut *t.get() };
-        *generator = prng;
-    });
+        std::mem::replace(generator, prng)
+    })
+}
+
+/// Runs `f` with the thread pinned to `master` and drawing from `seed`, so a `reseed` on
+/// another thread cannot redirect it midway.
+fn scoped<T>(master: u64, seed: u64, f: impl FnOnce() -> T) -> T {
+    let previous_master = PINNED_SEED.with(|pinned| pinned.replace(Some(master)));
+    let previous = swap_generator(Xoshiro256PlusPlus::seed_from_u64(seed));
+    let result = f();
+    swap_generator(previous);
+    PINNED_SEED.with(|pinned| pinned.set(previous_master));
+
+    result
+}
+
+/// Rederives the stream of an unpinned thread after the master seed changed.
+fn follow_master() {
+    if PINNED_SEED.with(Cell::get).is_some() {
+        return;
+    }
+
+    let epoch = MASTER_EPOCH.load(Ordering::Acquire);
+    if epoch == 0 || SYNCED_EPOCH.with(Cell::get) == epoch {
+        return;
+    }
+    SYNCED_EPOCH.with(|synced| synced.set(epoch));
+
+    let index = rayon::current_thread_index().unwrap_or(usize::MAX);
+    let seed = RngManager::current().thread_seed(index);
+    swap_generator(Xoshiro256PlusPlus::seed_from_u64(seed));
 }
 
 pub fn generator() -> Random {
+    follow_master();
+
     let rng = GENERATOR.with(|t| t.clone());
     Random { rng }
 }
 
+/// A random (version 4) id drawn from the generator of this thread, so seeded runs assign the
+/// same ids and rankings breaking ties by id are reproducible.
+pub fn new_id() -> Uuid {
+    Builder::from_random_bytes(generator().gen()).into_uuid()
+}
+
 impl Default for Random {
     fn default() -> Self {
         generator()
@@ -63,3 +226,68 @@ impl RngCore for Random {
         rng.try_fill_bytes(dest)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    /// Draws `n` values on every thread of a fresh pool of `n_threads`, each in the stream of
+    /// its index under `seed`.
+    fn draw_on_every_thread(seed: u64, n_threads: usize, n: usize) -> Vec<Vec<u64>> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(n_threads)
+            .build()
+            .unwrap();
+        let rng = RngManager::new(seed);
+
+        pool.broadcast(|context| {
+            rng.enter(context.index() as u128, || {
+                (0..n).map(|_| generator().next_u64()).collect()
+            })
+        })
+    }
+
+    #[test]
+    fn given_fixed_seed_when_drawn_on_every_thread_twice_then_sequences_are_identical() {
+        let first = draw_on_every_thread(42, 4, 8);
+        let second = draw_on_every_thread(42, 4, 8);
+
+        assert_eq!(first, second);
+        assert!(
+            first.iter().all_unique(),
+            "Every thread has its own stream."
+        );
+        assert_ne!(first, draw_on_every_thread(43, 4, 8));
+    }
+
+    #[test]
+    fn given_fork_when_entered_then_draws_match_and_outer_stream_is_restored() {
+        reseed(7);
+        let expected_outer = Xoshiro256PlusPlus::seed_from_u64(7).next_u64();
+        let rng = RngManager::current();
+        assert_eq!(rng.seed(), 7);
+
+        let forked = (0..4).map(|_| rng.fork(3).next_u64()).collect::<Vec<_>>();
+        let mut fork = rng.fork(3);
+        let drawn = (0..4).map(|_| fork.next_u64()).collect::<Vec<_>>();
+        let entered = rng.enter(3, || {
+            (0..4).map(|_| generator().next_u64()).collect::<Vec<_>>()
+        });
+
+        assert!(forked.iter().all_equal());
+        assert_eq!(drawn, entered);
+        assert_eq!(generator().next_u64(), expected_outer);
+        assert_eq!(run_seed(), 7);
+    }
+
+    #[test]
+    fn given_entered_manager_when_run_seed_is_read_then_it_is_the_entered_master_seed() {
+        reseed(1);
+        let seen = RngManager::new(2).enter(0, run_seed);
+
+        assert_eq!(seen, 2);
+        assert_eq!(run_seed(), 1);
+    }
+}
@@ -0,0 +1,315 @@
+use std::{iter::repeat_with, path::Path};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        characteristics::Save,
+        engines::{
+            core_engine::{Core, HyperParameters},
+            freeze_engine::Freeze,
+            generate_engine::Generate,
+            reset_engine::Reset,
+            status_engine::Status,
+        },
+        program::Program,
+    },
+    extensions::q_learning::QProgram,
+    utils::{error::LgpResult, misc::VoidResultAnyError},
+};
+
+/// Exposes the numeric constants of an individual while keeping its structure fixed.
+pub trait Constants {
+    fn constants(&self) -> Vec<f64>;
+    fn set_constants(&mut self, values: &[f64]);
+}
+
+impl Constants for Program {
+    fn constants(&self) -> Vec<f64> {
+        self.instructions
+            .iter()
+            .filter_map(|instruction| instruction.constant())
+            .collect()
+    }
+
+    fn set_constants(&mut self, values: &[f64]) {
+        let instructions = self
+            .instructions
+            .iter_mut()
+            .filter(|instruction| instruction.constant().is_some());
+
+        for (instruction, value) in instructions.zip(values.iter().copied()) {
+            instruction.set_constant(value);
+        }
+    }
+}
+
+impl Constants for QProgram {
+    fn constants(&self) -> Vec<f64> {
+        self.program.constants()
+    }
+
+    fn set_constants(&mut self, values: &[f64]) {
+        self.program.set_constants(values)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RefineParameters {
+    /// Maximum number of objective evaluations.
+    pub budget: usize,
+    pub initial_step: f64,
+    pub min_step: f64,
+}
+
+impl Default for RefineParameters {
+    fn default() -> Self {
+        RefineParameters {
+            budget: 100,
+            initial_step: 1.,
+            min_step: 1e-6,
+        }
+    }
+}
+
+/// Maximizes `objective` by perturbing one coordinate at a time, halving the step size whenever
+/// a full sweep yields no improvement.
+///
+/// Returns the best values, their score and the number of evaluations used.
+pub fn coordinate_descent(
+    initial: Vec<f64>,
+    parameters: RefineParameters,
+    mut objective: impl FnMut(&[f64]) -> f64,
+) -> (Vec<f64>, f64, usize) {
+    let mut best = initial;
+    let mut best_score = objective(&best);
+    let mut n_evaluations = 1;
+    let mut step = parameters.initial_step;
+
+    while !best.is_empty() && step >= parameters.min_step && n_evaluations < parameters.budget {
+        let mut improved = false;
+
+        for idx in 0..best.len() {
+            for direction in [1., -1.] {
+                if n_evaluations >= parameters.budget {
+                    break;
+                }
+
+                let mut candidate = best.clone();
+                candidate[idx] += direction * step;
+
+                let score = objective(&candidate);
+                n_evaluations += 1;
+
+                if score > best_score {
+                    best = candidate;
+                    best_score = score;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+
+        if !improved {
+            step /= 2.;
+        }
+    }
+
+    (best, best_score, n_evaluations)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefinementReport {
+    pub train_before: f64,
+    pub train_after: f64,
+    pub holdout_before: f64,
+    pub holdout_after: f64,
+    pub n_evaluations: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Refinement<T> {
+    pub refined: T,
+    pub report: RefinementReport,
+}
+
+fn evaluate<C>(
+    individual: &C::Individual,
+    trials: &mut Vec<C::State>,
+    params: &HyperParameters<C>,
+) -> f64
+where
+    C: Core,
+{
+    let mut population = vec![individual.clone()];
+    C::eval_fitness(
+        &mut population,
+        trials,
+        params.fallback_fitness(),
+        params.fitness_aggregation,
+    );
+    C::Status::get_fitness(&population[0])
+}
+
+/// Polishes the constants of the champion on a training trial set and reports the fitness
+/// before and after refinement on both the training set and a disjoint holdout set, split once
+/// by `Core::holdout_split`.
+pub fn refine<C>(
+    champion: &C::Individual,
+    params: &HyperParameters<C>,
+    parameters: RefineParameters,
+) -> LgpResult<Refinement<C::Individual>>
+where
+    C: Core,
+    C::Individual: Constants,
+{
+    let state_parameters = params.state_parameters()?;
+    let (train_parameters, holdout_parameters) =
+        C::holdout_split(&state_parameters, params.seed.unwrap_or_default());
+    let mut train: Vec<C::State> = repeat_with(|| C::Generate::generate(train_parameters.clone()))
+        .take(params.n_trials)
+        .collect_vec();
+    let mut holdout: Vec<C::State> =
+        repeat_with(|| C::Generate::generate(holdout_parameters.clone()))
+            .take(params.n_trials)
+            .collect_vec();
+
+    let (constants, train_after, n_evaluations) =
+        coordinate_descent(champion.constants(), parameters, |values| {
+            let mut candidate = champion.clone();
+            candidate.set_constants(values);
+            C::Reset::reset(&mut candidate);
+            params
+                .objective
+                .maximized(evaluate::<C>(&candidate, &mut train, params))
+        });
+    let train_after = params.objective.maximized(train_after);
+
+    let mut refined = champion.clone();
+    refined.set_constants(&constants);
+    C::Reset::reset(&mut refined);
+
+    let report = RefinementReport {
+        train_before: evaluate::<C>(champion, &mut train, params),
+        train_after,
+        holdout_before: evaluate::<C>(champion, &mut holdout, params),
+        holdout_after: evaluate::<C>(&refined, &mut holdout, params),
+        n_evaluations,
+    };
+
+    let mut population = vec![refined];
+    C::eval_fitness(
+        &mut population,
+        &mut train,
+        params.fallback_fitness(),
+        params.fitness_aggregation,
+    );
+    let refined = population.pop().unwrap();
+
+    Ok(Refinement { refined, report })
+}
+
+/// Saves the refined individual and its report next to the original model without
+/// overwriting it.
+pub fn save_refinement<C>(
+    refinement: &Refinement<C::Individual>,
+    model_path: &Path,
+) -> VoidResultAnyError
+where
+    C: Core,
+{
+    let mut refined = refinement.refined.clone();
+    C::Freeze::freeze(&mut refined);
+
+    let refined_path = model_path.with_extension("refined.json");
+    let report_path = model_path.with_extension("refinement.json");
+
+    refined.save(refined_path.to_str().unwrap())?;
+    refinement.report.save(report_path.to_str().unwrap())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::instructions::OpSet;
+    use crate::core::{
+        engines::{
+            generate_engine::{Generate, GenerateEngine},
+            reset_engine::ResetEngine,
+        },
+        environment::State,
+        instruction::{Instruction, InstructionGeneratorParameters, Mode, Op},
+        program::ProgramGeneratorParameters,
+    };
+
+    use super::*;
+    use crate::core::registers::{RegisterInit, RegisterSaturation};
+
+    struct Sample(f64);
+
+    impl State for Sample {
+        fn get_value(&self, _at_idx: usize) -> f64 {
+            self.0
+        }
+
+        fn execute_action(&mut self, _action: usize) -> f64 {
+            0.
+        }
+
+        fn get(&mut self) -> Option<&mut Self> {
+            Some(self)
+        }
+    }
+
+    #[test]
+    fn given_wrong_constant_when_refined_then_correct_value_is_recovered() {
+        let params = ProgramGeneratorParameters {
+            max_instructions: 1,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 0,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 1.,
+                input_scales: None,
+                n_actions: 1,
+                n_inputs: 1,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        };
+        let mut program: Program = GenerateEngine::generate(params);
+        // r0 = r0 + c * x, with c = 1.0 instead of 3.7.
+        program.instructions = vec![Instruction::new(0, 0, Mode::External, Op::Add, 1.)];
+
+        let samples = [0.5, 1., 2., 3.];
+        let parameters = RefineParameters {
+            budget: 200,
+            ..Default::default()
+        };
+
+        let (constants, _, n_evaluations) =
+            coordinate_descent(program.constants(), parameters, |values| {
+                let mut candidate = program.clone();
+                candidate.set_constants(values);
+
+                -samples
+                    .iter()
+                    .map(|x| {
+                        ResetEngine::reset(&mut candidate.registers);
+                        candidate.run(&Sample(*x));
+                        (candidate.registers[0] - 3.7 * x).powi(2)
+                    })
+                    .sum::<f64>()
+            });
+
+        assert!(n_evaluations <= 200);
+        assert!((constants[0] - 3.7).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,387 @@
+//! Distributed fitness evaluation: `EvalServer` evaluates individuals on trials it regenerates
+//! from their seeds, and `RemoteFitness` fans a population out to such servers.
+
+use std::{net::SocketAddr, net::ToSocketAddrs, thread, time::Duration};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::warn;
+
+use crate::{
+    core::{
+        engines::{
+            core_engine::{seeded_trial, Core, TrialSeed},
+            fitness_engine::{Fitness, FitnessAggregation},
+            reset_engine::Reset,
+            status_engine::Status,
+        },
+        registers::deserialize_vec_with_null,
+    },
+    utils::error::{LgpError, LgpResult},
+};
+
+/// An individual to evaluate on the trials generated from `seeds`, in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalRequest<I> {
+    pub individual: I,
+    pub seeds: Vec<TrialSeed>,
+}
+
+/// Raw score of every trial, and the individual as left by evaluation (e.g. a trained Q-table).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalResponse<I> {
+    pub individual: I,
+    /// Non-finite scores are sent as `null` and read back as NaN.
+    #[serde(deserialize_with = "deserialize_vec_with_null")]
+    pub scores: Vec<f64>,
+}
+
+/// Serves `POST /eval` requests, answering each `EvalRequest` with an `EvalResponse`.
+pub struct EvalServer<C>
+where
+    C: Core,
+{
+    server: Server,
+    state_parameters: C::StateParameters,
+}
+
+impl<C> EvalServer<C>
+where
+    C: Core,
+{
+    /// Binds to `address`; port `0` picks a free port, see `local_addr`.
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        state_parameters: C::StateParameters,
+    ) -> LgpResult<Self> {
+        let server = Server::http(address).map_err(|error| LgpError::Other(error.to_string()))?;
+
+        Ok(EvalServer {
+            server,
+            state_parameters,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server
+            .server_addr()
+            .to_ip()
+            .expect("Evaluation servers listen on TCP.")
+    }
+
+    /// Handles requests one at a time until the process exits.
+    pub fn run(self) {
+        for request in self.server.incoming_requests() {
+            self.handle(request);
+        }
+    }
+
+    /// Serves on a background thread, returning the address it listens on.
+    pub fn spawn(self) -> SocketAddr
+    where
+        C: 'static,
+        C::StateParameters: 'static,
+    {
+        let address = self.local_addr();
+        thread::spawn(move || self.run());
+
+        address
+    }
+
+    pub fn evaluate(&self, request: EvalRequest<C::Individual>) -> EvalResponse<C::Individual> {
+        let mut individual = request.individual;
+
+        let scores = request
+            .seeds
+            .into_iter()
+            .map(|seed| {
+                let mut trial = seeded_trial::<C>(&self.state_parameters, seed);
+                C::Reset::reset(&mut individual);
+                C::Reset::reset(&mut trial);
+                C::Fitness::eval_fitness(&mut individual, &mut trial)
+            })
+            .collect();
+
+        EvalResponse { individual, scores }
+    }
+
+    fn handle(&self, mut request: Request) {
+        let response = match (request.method(), request.url()) {
+            (Method::Post, "/eval") => {
+                let mut body = String::new();
+                let parsed: LgpResult<EvalRequest<C::Individual>> = request
+                    .as_reader()
+                    .read_to_string(&mut body)
+                    .map_err(LgpError::from)
+                    .and_then(|_| Ok(serde_json::from_str(&body)?));
+
+                match parsed {
+                    Ok(eval) => {
+                        let json = serde_json::to_string(&self.evaluate(eval)).unwrap();
+                        let header = Header::from_bytes("Content-Type", "application/json")
+                            .expect("Static header is valid.");
+
+                        Response::from_string(json).with_header(header)
+                    }
+                    Err(error) => Response::from_string(error.to_string()).with_status_code(400),
+                }
+            }
+            _ => Response::from_string("Not found").with_status_code(404),
+        };
+
+        if let Err(error) = request.respond(response) {
+            warn!(error = %error, "Failed to answer an evaluation request.");
+        }
+    }
+}
+
+/// Evaluates populations on `lgp serve-eval` workers, spreading individuals across them.
+///
+/// Works at the level of whole populations rather than implementing `Fitness`, whose
+/// evaluations have no access to worker addresses or trial seeds. A failed request is retried on
+/// the next worker, and an individual no worker could evaluate is evaluated locally.
+#[derive(Debug, Clone)]
+pub struct RemoteFitness {
+    workers: Vec<String>,
+    client: reqwest::blocking::Client,
+    retries: usize,
+}
+
+impl RemoteFitness {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const DEFAULT_RETRIES: usize = 2;
+
+    /// Workers are base URLs, e.g. `http://10.0.0.2:8080`.
+    pub fn new(workers: Vec<String>) -> LgpResult<Self> {
+        Self::with_limits(workers, Self::DEFAULT_TIMEOUT, Self::DEFAULT_RETRIES)
+    }
+
+    pub fn with_limits(workers: Vec<String>, timeout: Duration, retries: usize) -> LgpResult<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|error| LgpError::Other(error.to_string()))?;
+
+        Ok(RemoteFitness {
+            workers,
+            client,
+            retries,
+        })
+    }
+
+    /// Evaluates like `Core::eval_trial_scores`, where `seeds[i]` generated `trials[i]`. Trials
+    /// are only used for local fallbacks.
+    pub fn eval_trial_scores<C>(
+        &self,
+        population: &mut [C::Individual],
+        trials: &mut [C::State],
+        seeds: &[TrialSeed],
+        default_fitness: f64,
+        aggregation: FitnessAggregation,
+    ) -> Vec<Vec<f64>>
+    where
+        C: Core,
+    {
+        let evaluated = population
+            .par_iter()
+            .enumerate()
+            .map(|(index, individual)| self.eval_remote::<C>(index, individual, seeds))
+            .collect::<Vec<_>>();
+
+        population
+            .iter_mut()
+            .zip(evaluated)
+            .map(|(individual, evaluated)| match evaluated {
+                Some(EvalResponse {
+                    individual: evaluated,
+                    scores,
+                }) => {
+                    *individual = evaluated;
+
+                    let scores = scores
+                        .into_iter()
+                        .map(|s| if !s.is_finite() { default_fitness } else { s })
+                        .collect::<Vec<_>>();
+                    C::Status::set_fitness(
+                        individual,
+                        aggregation.aggregate(&scores, default_fitness),
+                    );
+
+                    scores
+                }
+                None => C::eval_trial_scores(
+                    std::slice::from_mut(individual),
+                    trials,
+                    default_fitness,
+                    aggregation,
+                )
+                .remove(0),
+            })
+            .collect()
+    }
+
+    fn eval_remote<C>(
+        &self,
+        index: usize,
+        individual: &C::Individual,
+        seeds: &[TrialSeed],
+    ) -> Option<EvalResponse<C::Individual>>
+    where
+        C: Core,
+    {
+        let body = serde_json::to_vec(&EvalRequest {
+            individual,
+            seeds: seeds.to_vec(),
+        })
+        .ok()?;
+
+        for attempt in 0..=self.retries {
+            let worker = &self.workers[(index + attempt) % self.workers.len()];
+
+            let response = self
+                .client
+                .post(format!("{}/eval", worker.trim_end_matches('/')))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .and_then(|response| response.error_for_status())
+                .and_then(|response| response.bytes());
+
+            match response
+                .map(|bytes| serde_json::from_slice::<EvalResponse<C::Individual>>(&bytes))
+            {
+                Ok(Ok(evaluated)) if evaluated.scores.len() == seeds.len() => {
+                    return Some(evaluated)
+                }
+                Ok(Ok(_)) => warn!(worker = %worker, "Worker returned the wrong number of scores."),
+                Ok(Err(error)) => warn!(worker = %worker, error = %error, "Malformed evaluation."),
+                Err(error) => warn!(worker = %worker, error = %error, "Remote evaluation failed."),
+            }
+        }
+
+        warn!(index, "Evaluating locally after every worker failed.");
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use itertools::Itertools;
+
+    use crate::{
+        core::{
+            engines::{
+                core_engine::{HyperParameters, HyperParametersBuilder},
+                status_engine::StatusEngine,
+            },
+            instruction::InstructionGeneratorParametersBuilder,
+            program::ProgramGeneratorParametersBuilder,
+        },
+        problems::iris::IrisEngine,
+        utils::random::update_seed,
+    };
+
+    use super::*;
+
+    fn hyper_parameters(eval_workers: Vec<String>) -> HyperParameters<IrisEngine> {
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()
+            .unwrap();
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(20)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()
+            .unwrap();
+
+        HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(10)
+            .n_generations(2)
+            .n_trials(3)
+            .seed(Some(17))
+            .dataset_path(Some(PathBuf::from("assets/fixtures/iris.csv")))
+            .eval_workers(eval_workers)
+            .build()
+            .unwrap()
+    }
+
+    fn fitnesses(parameters: &HyperParameters<IrisEngine>) -> Vec<Vec<f64>> {
+        parameters
+            .try_build_engine()
+            .unwrap()
+            .take(parameters.n_generations)
+            .map(|population| {
+                population
+                    .iter()
+                    .map(StatusEngine::get_fitness)
+                    .collect_vec()
+            })
+            .collect_vec()
+    }
+
+    #[test]
+    fn given_in_process_workers_when_population_is_evaluated_then_scores_match_local() {
+        let state_parameters = hyper_parameters(vec![]).state_parameters().unwrap();
+        let workers = (0..2)
+            .map(|_| {
+                let server =
+                    EvalServer::<IrisEngine>::bind("127.0.0.1:0", state_parameters.clone())
+                        .unwrap();
+                format!("http://{}", server.spawn())
+            })
+            .collect_vec();
+
+        let local = fitnesses(&hyper_parameters(vec![]));
+        let remote = fitnesses(&hyper_parameters(workers));
+
+        assert_eq!(local.len(), 2);
+        assert!(local.iter().all(|population| population.len() == 10));
+        assert_eq!(local, remote);
+    }
+
+    #[test]
+    fn given_unreachable_worker_when_evaluated_then_local_fallback_is_used() {
+        let parameters = hyper_parameters(vec![]);
+        let state_parameters = parameters.state_parameters().unwrap();
+        let seeds = (0..parameters.n_trials)
+            .map(|slot| parameters.trial_seed(0, slot).unwrap())
+            .collect_vec();
+        let mut trials = seeds
+            .iter()
+            .map(|seed| seeded_trial::<IrisEngine>(&state_parameters, *seed))
+            .collect_vec();
+
+        update_seed(parameters.seed);
+        let mut local = IrisEngine::init_population(parameters.program_parameters, 10);
+        let mut remote = local.clone();
+
+        let expected = IrisEngine::eval_trial_scores(
+            &mut local,
+            &mut trials,
+            parameters.default_fitness,
+            parameters.fitness_aggregation,
+        );
+
+        // Nothing listens on the discard port.
+        let fitness = RemoteFitness::with_limits(
+            vec!["http://127.0.0.1:9".to_string()],
+            Duration::from_millis(200),
+            1,
+        )
+        .unwrap();
+        let scores = fitness.eval_trial_scores::<IrisEngine>(
+            &mut remote,
+            &mut trials,
+            &seeds,
+            parameters.default_fitness,
+            parameters.fitness_aggregation,
+        );
+
+        assert_eq!(scores, expected);
+    }
+}
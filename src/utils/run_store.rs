@@ -0,0 +1,267 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::engines::core_engine::StopReason;
+
+use super::{
+    benchmark_tools::{default_output_root, FitnessCurves},
+    manifest::{Manifest, RunStatus, MANIFEST_FILE_NAME},
+};
+
+/// Summary of a saved run, parsed from its manifest and metrics.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunInfo {
+    /// Experiment the run belongs to: its directory, without the `run_<i>` of repeats.
+    pub name: String,
+    /// Directory of the run relative to the store, e.g. `iris_repeats/run_0`.
+    pub run_id: String,
+    /// Unknown for runs saved without a manifest.
+    pub status: Option<RunStatus>,
+    pub seed: Option<u64>,
+    pub n_generations: usize,
+    pub final_best_fitness: Option<f64>,
+    /// Seconds since the Unix epoch: when the run started, or when its metrics were last
+    /// written for runs without a manifest.
+    pub timestamp: u64,
+    /// Why the run could not be read completely.
+    pub error: Option<String>,
+    /// Why the run stopped before `n_generations`, if it did.
+    pub stopped_reason: Option<StopReason>,
+}
+
+/// Discovers the runs saved under a directory, `default_output_root()` by default.
+///
+/// A run is any directory holding a `manifest.json` or a `metrics.csv`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunStore {
+    base_dir: PathBuf,
+}
+
+impl RunStore {
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        RunStore {
+            base_dir: base_dir.into(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        RunStore::new(default_output_root())
+    }
+
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+
+    /// Every run, oldest first. Runs that cannot be read are listed with an `error`.
+    pub fn list_runs(&self) -> Vec<RunInfo> {
+        let mut runs = vec![];
+        let mut pending = vec![self.base_dir.clone()];
+
+        while let Some(directory) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&directory) else {
+                continue;
+            };
+
+            for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+                if !path.is_dir() {
+                    continue;
+                }
+
+                if is_run(&path) {
+                    runs.push(self.run_info(&path));
+                } else {
+                    pending.push(path);
+                }
+            }
+        }
+
+        runs.sort_by(|a, b| (a.timestamp, &a.run_id).cmp(&(b.timestamp, &b.run_id)));
+        runs
+    }
+
+    /// The most recent run of the experiment `name`.
+    pub fn latest(&self, name: &str) -> Option<RunInfo> {
+        self.list_runs()
+            .into_iter()
+            .rev()
+            .find(|run| run.name == name)
+    }
+
+    pub fn load_metrics(&self, run_id: &str) -> Result<FitnessCurves, Box<dyn Error>> {
+        FitnessCurves::load_run(&self.base_dir.join(run_id))
+    }
+
+    fn run_info(&self, run_dir: &Path) -> RunInfo {
+        let run_id = run_dir
+            .strip_prefix(&self.base_dir)
+            .unwrap_or(run_dir)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let name = match run_id.rsplit_once('/') {
+            Some((name, repeat)) if repeat.starts_with("run_") => name.to_string(),
+            _ => run_id.clone(),
+        };
+
+        let mut info = RunInfo {
+            name,
+            run_id,
+            status: None,
+            seed: None,
+            n_generations: 0,
+            final_best_fitness: None,
+            timestamp: modified(&run_dir.join("metrics.csv")).unwrap_or(0),
+            error: None,
+            stopped_reason: None,
+        };
+
+        if run_dir.join(MANIFEST_FILE_NAME).exists() {
+            match Manifest::read(run_dir) {
+                Ok(manifest) => {
+                    info.status = Some(manifest.status);
+                    info.seed = manifest.seed;
+                    info.timestamp = manifest.started_at;
+                    info.error = manifest.error;
+                    info.stopped_reason = manifest.stopped_reason;
+                }
+                Err(error) => info.error = Some(format!("Unreadable manifest: {}", error)),
+            }
+        }
+
+        match self.load_metrics(&info.run_id) {
+            Ok(curves) => {
+                info.n_generations = curves.len();
+                info.final_best_fitness = curves.best.last().copied();
+            }
+            // A run that is still going, or failed, may not have saved metrics yet.
+            Err(_) if info.status.is_some() => {}
+            Err(error) => {
+                info.error
+                    .get_or_insert_with(|| format!("Unreadable metrics: {}", error));
+            }
+        }
+
+        info
+    }
+}
+
+fn is_run(directory: &Path) -> bool {
+    directory.join(MANIFEST_FILE_NAME).exists() || directory.join("metrics.csv").exists()
+}
+
+fn modified(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn manifest(started_at: u64, seed: u64, status: RunStatus) -> Manifest {
+        Manifest {
+            crate_version: "1.0.0".to_string(),
+            git_commit: "unknown".to_string(),
+            rustc_version: "unknown".to_string(),
+            hostname: "localhost".to_string(),
+            started_at,
+            finished_at: Some(started_at + 1),
+            seed: Some(seed),
+            config: serde_json::Value::Null,
+            status,
+            error: None,
+            stopped_reason: None,
+            artifacts: vec![],
+        }
+    }
+
+    fn curves(best: &[f64]) -> FitnessCurves {
+        FitnessCurves {
+            best: best.to_vec(),
+            median: best.to_vec(),
+            worst: best.to_vec(),
+        }
+    }
+
+    fn store(name: &str) -> RunStore {
+        let base_dir = env::temp_dir().join("lgp_run_store").join(name);
+        let _ = fs::remove_dir_all(&base_dir);
+
+        for (run_id, started_at, best) in [
+            ("iris/run_0", 200, [0.5, 0.75]),
+            ("iris/run_1", 100, [0.25, 0.5]),
+            ("cart_pole/run_0", 300, [10., 20.]),
+        ] {
+            let run_dir = base_dir.join(run_id);
+            fs::create_dir_all(&run_dir).unwrap();
+            manifest(started_at, started_at, RunStatus::Completed)
+                .write(&run_dir)
+                .unwrap();
+            curves(&best)
+                .save_csv(&run_dir.join("metrics.csv"))
+                .unwrap();
+            // Snapshots inside a run are not runs themselves.
+            fs::create_dir_all(run_dir.join("gen_1")).unwrap();
+            manifest(0, 0, RunStatus::Completed)
+                .write(&run_dir.join("gen_1"))
+                .unwrap();
+        }
+
+        let corrupt = base_dir.join("broken").join("run_0");
+        fs::create_dir_all(&corrupt).unwrap();
+        fs::write(corrupt.join(MANIFEST_FILE_NAME), "{ not json").unwrap();
+
+        RunStore::new(base_dir)
+    }
+
+    #[test]
+    fn given_saved_runs_when_listed_then_they_are_ordered_by_start_time() {
+        let store = store("ordered");
+        let runs = store.list_runs();
+
+        assert_eq!(
+            runs.iter()
+                .map(|run| run.run_id.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "broken/run_0",
+                "iris/run_1",
+                "iris/run_0",
+                "cart_pole/run_0"
+            ]
+        );
+
+        let run = &runs[2];
+        assert_eq!(run.name, "iris");
+        assert_eq!(run.status, Some(RunStatus::Completed));
+        assert_eq!(run.seed, Some(200));
+        assert_eq!(run.n_generations, 2);
+        assert_eq!(run.final_best_fitness, Some(0.75));
+        assert_eq!(run.error, None);
+
+        assert_eq!(store.latest("iris").unwrap().run_id, "iris/run_0");
+        assert!(store.latest("mountain_car").is_none());
+        assert_eq!(
+            store.load_metrics("cart_pole/run_0").unwrap().best,
+            vec![10., 20.]
+        );
+    }
+
+    #[test]
+    fn given_corrupt_run_when_listed_then_it_is_reported_without_failing() {
+        let runs = store("corrupt").list_runs();
+        let broken = runs.iter().find(|run| run.name == "broken").unwrap();
+
+        assert_eq!(broken.status, None);
+        assert_eq!(broken.final_best_fitness, None);
+        assert!(broken.error.as_ref().unwrap().contains("manifest"));
+    }
+}
@@ -0,0 +1,39 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, OnceLock,
+};
+
+#[cfg(feature = "cli")]
+use super::error::{LgpError, LgpResult};
+
+static STOP_FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Process-wide flag set when a stop is requested; runners hand it to every engine they build
+/// so runs stop after their current generation.
+pub fn stop_flag() -> Arc<AtomicBool> {
+    STOP_FLAG.get_or_init(Default::default).clone()
+}
+
+pub fn stop_requested() -> bool {
+    stop_flag().load(Ordering::Relaxed)
+}
+
+/// Handles SIGINT and SIGTERM: the first signal sets the stop flag, so runs finish their
+/// current generation and save their outputs; the second exits immediately.
+#[cfg(feature = "cli")]
+pub fn install_signal_handler() -> LgpResult<()> {
+    let flag = stop_flag();
+
+    ctrlc::set_handler(move || {
+        if flag.swap(true, Ordering::SeqCst) {
+            eprintln!("Interrupted again, exiting without saving.");
+            std::process::exit(130);
+        }
+
+        eprintln!(
+            "Interrupted, stopping after the current generation. Interrupt again to exit \
+             immediately."
+        );
+    })
+    .map_err(|error| LgpError::Other(error.to_string()))
+}
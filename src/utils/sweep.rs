@@ -0,0 +1,312 @@
+use std::{collections::BTreeMap, path::Path};
+
+use config::{Config, File};
+use itertools::Itertools;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::core::{
+    config_override::apply_overrides,
+    engines::core_engine::{Core, HyperParameters},
+};
+
+use super::{
+    benchmark_tools::{create_path, run_repeat},
+    error::{LgpError, LgpResult},
+    shutdown::stop_requested,
+};
+
+/// Parameter grids of a sweep, keyed by dotted field path.
+///
+/// Read from the `[sweep]` table of a TOML file, e.g.
+///
+/// ```toml
+/// [sweep]
+/// mutation_percent = [0.1, 0.3, 0.5]
+/// program_parameters.max_instructions = [50, 100]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SweepConfig {
+    pub grid: BTreeMap<String, Vec<Value>>,
+}
+
+/// One configuration of the grid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepCell {
+    /// Directory name, built from the varied values.
+    pub name: String,
+    pub overrides: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepResult {
+    pub rank: usize,
+    pub cell: String,
+    pub overrides: Vec<String>,
+    /// Best fitness of the last generation; NaN when the cell failed.
+    pub final_best_fitness: f64,
+    pub error: Option<String>,
+}
+
+/// Collects the arrays of a (possibly nested) table under their dotted paths.
+fn flatten(prefix: &str, value: Value, grid: &mut BTreeMap<String, Vec<Value>>) -> LgpResult<()> {
+    match value {
+        Value::Object(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, grid)?;
+            }
+        }
+        Value::Array(values) if !values.is_empty() => {
+            grid.insert(prefix.to_string(), values);
+        }
+        _ => {
+            return Err(LgpError::Config(format!(
+                "Expected a non-empty list of values for sweep key `{}`",
+                prefix
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+fn literal(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl SweepConfig {
+    /// Reads the `[sweep]` table of `filename`, which may also hold the base hyperparameters.
+    pub fn load(filename: &str) -> LgpResult<Self> {
+        let settings = Config::builder()
+            .add_source(File::with_name(filename))
+            .build()?;
+        let table: Value = settings.get("sweep")?;
+
+        let mut grid = BTreeMap::new();
+        flatten("", table, &mut grid)?;
+
+        Ok(SweepConfig { grid })
+    }
+
+    /// Cartesian product of the grid, varying the last key fastest.
+    pub fn cells(&self) -> Vec<SweepCell> {
+        self.grid
+            .iter()
+            .map(|(key, values)| values.iter().map(move |value| (key, literal(value))))
+            .multi_cartesian_product()
+            .map(|assignments| SweepCell {
+                name: assignments
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value).replace('/', "_"))
+                    .join("__"),
+                overrides: assignments
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// Runs every cell of the grid from `base`, `jobs` at a time, saving each run under
+/// `<output_root>/<test_name>/<cell>` and the ranking under `sweep_summary.csv` next to them.
+/// Failed cells are ranked last.
+pub fn run_sweep<C>(
+    base: &HyperParameters<C>,
+    state_parameters: C::StateParameters,
+    sweep: &SweepConfig,
+    jobs: usize,
+    output_root: &Path,
+    test_name: &str,
+) -> LgpResult<Vec<SweepResult>>
+where
+    C: Core,
+{
+    let cells = sweep.cells();
+    let mut parameters = Vec::with_capacity(cells.len());
+
+    // Reject the whole sweep before running anything if a cell is misconfigured.
+    for cell in &cells {
+        let cell_parameters = apply_overrides(base, &cell.overrides)?;
+        cell_parameters.validate()?;
+        parameters.push(cell_parameters);
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.max(1))
+        .build()
+        .map_err(|error| LgpError::Other(error.to_string()))?;
+
+    let outcomes: Vec<Result<f64, String>> = pool.install(|| {
+        cells
+            .par_iter()
+            .zip(parameters.par_iter())
+            .map(|(cell, cell_parameters)| {
+                if stop_requested() {
+                    return Err("Interrupted before the cell started.".to_string());
+                }
+
+                let run_name = format!("{}/{}", test_name, cell.name);
+
+                run_repeat(cell_parameters, &state_parameters, output_root, &run_name)
+                    .map_err(|error| error.to_string())
+                    .and_then(|curve| curve.last().copied().ok_or_else(String::new))
+            })
+            .collect()
+    });
+
+    let results = cells
+        .into_iter()
+        .zip(outcomes)
+        .sorted_by(|(_, a), (_, b)| match (a, b) {
+            (Ok(a), Ok(b)) => b.total_cmp(a),
+            (a, b) => b.is_ok().cmp(&a.is_ok()),
+        })
+        .enumerate()
+        .map(|(idx, (cell, outcome))| SweepResult {
+            rank: idx + 1,
+            cell: cell.name,
+            overrides: cell.overrides,
+            final_best_fitness: *outcome.as_ref().unwrap_or(&f64::NAN),
+            error: outcome.err(),
+        })
+        .collect_vec();
+
+    save_summary(&results, sweep, output_root, test_name)?;
+
+    Ok(results)
+}
+
+fn save_summary(
+    results: &[SweepResult],
+    sweep: &SweepConfig,
+    output_root: &Path,
+    test_name: &str,
+) -> LgpResult<()> {
+    let path = output_root.join(test_name).join("sweep_summary.csv");
+    let path = create_path(path.to_str().unwrap(), true)?;
+
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(
+        ["rank", "cell"]
+            .into_iter()
+            .map(String::from)
+            .chain(sweep.grid.keys().cloned())
+            .chain(["final_best_fitness", "error"].map(String::from)),
+    )?;
+
+    for result in results {
+        let values = result.overrides.iter().filter_map(|assignment| {
+            assignment
+                .split_once('=')
+                .map(|(_, value)| value.to_string())
+        });
+
+        writer.write_record(
+            [result.rank.to_string(), result.cell.clone()]
+                .into_iter()
+                .chain(values)
+                .chain([
+                    result.final_best_fitness.to_string(),
+                    result.error.clone().unwrap_or_default(),
+                ]),
+        )?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use crate::{
+        core::{
+            engines::core_engine::HyperParametersBuilder,
+            instruction::InstructionGeneratorParametersBuilder,
+            program::ProgramGeneratorParametersBuilder,
+        },
+        problems::iris::IrisEngine,
+    };
+
+    use super::*;
+
+    #[test]
+    fn given_two_by_two_grid_when_swept_then_four_runs_are_ranked() -> LgpResult<()> {
+        let root = env::temp_dir().join("lgp_sweep");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root)?;
+
+        let sweep_path = root.join("sweep.toml");
+        fs::write(
+            &sweep_path,
+            "[sweep]\nmutation_percent = [0.2, 0.4]\nprogram_parameters.max_instructions = [5, 10]\n",
+        )?;
+        let sweep = SweepConfig::load(sweep_path.to_str().unwrap())?;
+        assert_eq!(sweep.cells().len(), 4);
+
+        let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+            .n_actions(3)
+            .n_inputs(4)
+            .build()
+            .unwrap();
+        let program_parameters = ProgramGeneratorParametersBuilder::default()
+            .max_instructions(10)
+            .instruction_generator_parameters(instruction_parameters)
+            .build()
+            .unwrap();
+        let base = HyperParametersBuilder::<IrisEngine>::default()
+            .program_parameters(program_parameters)
+            .population_size(8)
+            .n_generations(3)
+            .n_trials(1)
+            .seed(Some(3))
+            .dataset_path(Some(PathBuf::from("assets/fixtures/iris.csv")))
+            .build()
+            .unwrap();
+
+        let results = run_sweep(
+            &base,
+            base.state_parameters()?,
+            &sweep,
+            2,
+            &root,
+            "iris_sweep",
+        )?;
+
+        assert_eq!(
+            results.iter().map(|result| result.rank).collect_vec(),
+            vec![1, 2, 3, 4]
+        );
+        assert!(results
+            .windows(2)
+            .all(|pair| pair[0].final_best_fitness >= pair[1].final_best_fitness));
+
+        let run_dirs = fs::read_dir(root.join("iris_sweep"))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join("best.json").is_file())
+            .count();
+        assert_eq!(run_dirs, 4);
+
+        let summary = fs::read_to_string(root.join("iris_sweep").join("sweep_summary.csv"))?;
+        assert_eq!(summary.lines().count(), 5);
+        assert!(summary
+            .lines()
+            .next()
+            .unwrap()
+            .contains("mutation_percent,program_parameters.max_instructions"));
+
+        Ok(())
+    }
+}
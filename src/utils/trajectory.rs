@@ -0,0 +1,323 @@
+use std::{iter::repeat_with, path::Path};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::core::{
+    characteristics::{Format, Load, Save},
+    engines::{
+        core_engine::{Core, HyperParameters},
+        fitness_engine::Fitness,
+        freeze_engine::Freeze,
+        generate_engine::Generate,
+        reset_engine::Reset,
+    },
+    environment::{EpisodeStatus, RlState, State},
+};
+
+use super::{
+    benchmark_tools::create_path,
+    misc::VoidResultAnyError,
+    random::{derive_seed, run_seed, with_seed},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Step {
+    pub observation: Vec<f64>,
+    pub action: usize,
+    pub reward: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Trajectory {
+    pub n_inputs: usize,
+    pub steps: Vec<Step>,
+}
+
+impl Trajectory {
+    pub fn new(n_inputs: usize) -> Self {
+        Trajectory {
+            n_inputs,
+            steps: vec![],
+        }
+    }
+
+    pub fn total_reward(&self) -> f64 {
+        self.steps.iter().map(|step| step.reward).sum()
+    }
+
+    pub fn save_csv(&self, path: &str) -> VoidResultAnyError {
+        let path = create_path(path, true)?;
+        let mut writer = csv::Writer::from_path(path)?;
+
+        let header = ["step", "action", "reward"]
+            .into_iter()
+            .map(String::from)
+            .chain((0..self.n_inputs).map(|idx| format!("observation_{}", idx)))
+            .collect_vec();
+        writer.write_record(&header)?;
+
+        for (idx, step) in self.steps.iter().enumerate() {
+            let record = [
+                idx.to_string(),
+                step.action.to_string(),
+                step.reward.to_string(),
+            ]
+            .into_iter()
+            .chain(step.observation.iter().map(|value| value.to_string()))
+            .collect_vec();
+            writer.write_record(&record)?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Wraps a state and records every observation, action and reward passing through it.
+pub struct Recorder<S> {
+    pub state: S,
+    n_inputs: usize,
+    trajectory: Trajectory,
+}
+
+impl<S> Recorder<S>
+where
+    S: State,
+{
+    pub fn new(state: S, n_inputs: usize) -> Self {
+        Recorder {
+            state,
+            n_inputs,
+            trajectory: Trajectory::new(n_inputs),
+        }
+    }
+
+    pub fn take_trajectory(&mut self) -> Trajectory {
+        std::mem::replace(&mut self.trajectory, Trajectory::new(self.n_inputs))
+    }
+}
+
+impl<S> State for Recorder<S>
+where
+    S: State,
+{
+    fn get_value(&self, at_idx: usize) -> f64 {
+        self.state.get_value(at_idx)
+    }
+
+    fn execute_action(&mut self, action: usize) -> f64 {
+        let observation = (0..self.n_inputs)
+            .map(|idx| self.state.get_value(idx))
+            .collect_vec();
+        let reward = self.state.execute_action(action);
+
+        self.trajectory.steps.push(Step {
+            observation,
+            action,
+            reward,
+        });
+
+        reward
+    }
+
+    fn get(&mut self) -> Option<&mut Self> {
+        self.state.get()?;
+        Some(self)
+    }
+}
+
+impl<S> RlState for Recorder<S>
+where
+    S: RlState,
+{
+    fn is_terminal(&mut self) -> bool {
+        self.state.is_terminal()
+    }
+
+    fn get_initial_state(&self) -> Vec<f64> {
+        self.state.get_initial_state()
+    }
+
+    fn steps(&self) -> usize {
+        self.state.steps()
+    }
+
+    fn status(&mut self) -> EpisodeStatus {
+        self.state.status()
+    }
+}
+
+/// Replays a frozen copy of an individual from the recorder's initial state and returns the
+/// captured trajectory. The individual itself is left untouched.
+pub fn record_trajectory<C>(
+    individual: &C::Individual,
+    recorder: &mut Recorder<C::State>,
+) -> Trajectory
+where
+    C: Core,
+    C::Fitness: Fitness<C::Individual, Recorder<C::State>, C::FitnessMarker>,
+{
+    let mut individual = individual.clone();
+
+    C::Freeze::freeze(&mut individual);
+    C::Reset::reset(&mut individual);
+    C::Reset::reset(&mut recorder.state);
+    recorder.take_trajectory();
+
+    C::Fitness::eval_fitness(&mut individual, recorder);
+
+    recorder.take_trajectory()
+}
+
+/// Replays the champion once on an evaluation state fixed by the run seed and saves the
+/// trajectory to `trajectory.json` next to the other outputs of `test_name`.
+pub fn save_trajectory<C>(
+    champion: &C::Individual,
+    params: &HyperParameters<C>,
+    output_root: &Path,
+    test_name: &str,
+) -> Result<Trajectory, Box<dyn std::error::Error>>
+where
+    C: Core,
+    C::State: RlState,
+    C::Fitness: Fitness<C::Individual, Recorder<C::State>, C::FitnessMarker>,
+{
+    let state_parameters = params.state_parameters()?;
+    let state: C::State = with_seed(derive_seed(run_seed(), u128::MAX), || {
+        C::Generate::generate(state_parameters)
+    });
+    let n_inputs = state.get_initial_state().len();
+
+    let trajectory = record_trajectory::<C>(champion, &mut Recorder::new(state, n_inputs));
+
+    let path = output_root.join(test_name).join("trajectory.json");
+    trajectory.save(path.to_str().unwrap())?;
+
+    Ok(trajectory)
+}
+
+/// Replays the champion of every `every`-th generation saved in `run_dir/champions.json` on the
+/// same fixed initial states, writing one trajectory CSV per generation and episode to `out`.
+///
+/// Returns the generations that were replayed.
+pub fn animate<C>(
+    run_dir: &Path,
+    every: usize,
+    episodes: usize,
+    out: &Path,
+    n_inputs: usize,
+) -> Result<Vec<usize>, Box<dyn std::error::Error>>
+where
+    C: Core,
+    C::Fitness: Fitness<C::Individual, Recorder<C::State>, C::FitnessMarker>,
+{
+    let champions: Vec<C::Individual> = Vec::load(Format::find(run_dir, "champions"))?;
+
+    let state_parameters = C::default_state_parameters()?;
+    let mut recorders = repeat_with(|| {
+        let state: C::State = C::Generate::generate(state_parameters.clone());
+        Recorder::new(state, n_inputs)
+    })
+    .take(episodes)
+    .collect_vec();
+
+    let generations = (0..champions.len()).step_by(every.max(1)).collect_vec();
+
+    for generation in generations.iter().copied() {
+        for (episode, recorder) in recorders.iter_mut().enumerate() {
+            let trajectory = record_trajectory::<C>(&champions[generation], recorder);
+            let path = out.join(format!("generation_{}_episode_{}.csv", generation, episode));
+
+            trajectory.save_csv(path.to_str().unwrap())?;
+        }
+
+        info!(
+            animate_generation = serde_json::to_string(&generation).unwrap(),
+            n_generations = serde_json::to_string(&champions.len()).unwrap()
+        );
+    }
+
+    Ok(generations)
+}
+
+#[cfg(all(test, feature = "gym"))]
+mod tests {
+    use crate::core::instructions::OpSet;
+    use gym_rs::envs::classical_control::cartpole::CartPoleEnv;
+
+    use crate::core::{
+        characteristics::Save,
+        engines::generate_engine::GenerateEngine,
+        instruction::InstructionGeneratorParameters,
+        program::{Program, ProgramGeneratorParameters},
+    };
+    use crate::problems::gym::{GymRsEngine, GymRsParameters};
+
+    use super::*;
+    use crate::core::registers::{RegisterInit, RegisterSaturation};
+
+    fn parameters() -> ProgramGeneratorParameters {
+        ProgramGeneratorParameters {
+            max_instructions: 10,
+            min_instructions: 1,
+            instruction_generator_parameters: InstructionGeneratorParameters {
+                n_calculation_registers: 1,
+                n_output_registers: None,
+                register_init: RegisterInit::Zeros,
+                register_saturation: RegisterSaturation::None,
+                external_factor: 10.,
+                input_scales: None,
+                n_actions: 2,
+                n_inputs: 4,
+                ops: OpSet::default(),
+                conditional_probability: 0.,
+                constant_probability: 0.,
+                constant_range: (-5., 5.),
+                constant_sigma: 1.,
+            },
+        }
+    }
+
+    #[test]
+    fn given_cart_pole_program_when_recorded_then_trajectory_matches_reward() {
+        let program: Program = GenerateEngine::generate(parameters());
+        let mut recorder = Recorder::new(GenerateEngine::generate(GymRsParameters::default()), 4);
+
+        let trajectory = record_trajectory::<GymRsEngine<CartPoleEnv>>(&program, &mut recorder);
+
+        assert!(trajectory
+            .steps
+            .iter()
+            .all(|step| step.observation.len() == 4));
+        assert_eq!(trajectory.steps.len() as f64, trajectory.total_reward());
+    }
+
+    #[test]
+    fn given_saved_champions_when_animated_then_one_trajectory_per_selected_generation() {
+        let run_dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let out = run_dir.join("frames");
+
+        let champions: Vec<Program> = repeat_with(|| GenerateEngine::generate(parameters()))
+            .take(5)
+            .collect();
+        champions
+            .save(run_dir.join("champions.json").to_str().unwrap())
+            .unwrap();
+
+        let generations = animate::<GymRsEngine<CartPoleEnv>>(&run_dir, 2, 1, &out, 4).unwrap();
+
+        assert_eq!(generations, vec![0, 2, 4]);
+
+        for generation in generations {
+            let path = out.join(format!("generation_{}_episode_0.csv", generation));
+            let header = std::fs::read_to_string(path).unwrap();
+
+            assert!(header.starts_with(
+                "step,action,reward,observation_0,observation_1,observation_2,observation_3"
+            ));
+        }
+    }
+}
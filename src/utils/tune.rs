@@ -0,0 +1,444 @@
+use std::{collections::BTreeMap, path::Path};
+
+use config::{Config, File};
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::core::{
+    characteristics::Save,
+    config_override::apply_overrides,
+    engines::{
+        core_engine::{Core, HyperParameters},
+        status_engine::Status,
+    },
+};
+
+use super::{
+    benchmark_tools::create_path,
+    error::{LgpError, LgpResult},
+};
+
+/// Values a single hyperparameter is sampled from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Distribution {
+    Uniform {
+        low: f64,
+        high: f64,
+    },
+    /// Uniform in log space; both bounds must be positive.
+    LogUniform {
+        low: f64,
+        high: f64,
+    },
+    /// Integers in `[low, high]`.
+    Int {
+        low: i64,
+        high: i64,
+    },
+    Categorical {
+        choices: Vec<Value>,
+    },
+}
+
+impl Distribution {
+    pub fn sample(&self, rng: &mut impl Rng) -> Value {
+        match self {
+            Distribution::Uniform { low, high } => Value::from(rng.gen_range(*low..=*high)),
+            Distribution::LogUniform { low, high } => {
+                Value::from(rng.gen_range(low.ln()..=high.ln()).exp())
+            }
+            Distribution::Int { low, high } => Value::from(rng.gen_range(*low..=*high)),
+            Distribution::Categorical { choices } => {
+                choices.choose(rng).cloned().unwrap_or(Value::Null)
+            }
+        }
+    }
+
+    fn violation(&self) -> Option<&'static str> {
+        match self {
+            Distribution::Uniform { low, high } if !(low <= high) => Some("low <= high"),
+            Distribution::LogUniform { low, high } if !(0. < *low && low <= high) => {
+                Some("0 < low <= high")
+            }
+            Distribution::Int { low, high } if low > high => Some("low <= high"),
+            Distribution::Categorical { choices } if choices.is_empty() => {
+                Some("at least one choice")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Distributions of the tuned hyperparameters, keyed by dotted field path.
+///
+/// Read from the `[space]` table of a TOML file, e.g.
+///
+/// ```toml
+/// [space.mutation_percent]
+/// type = "uniform"
+/// low = 0.1
+/// high = 0.9
+///
+/// [space.program_parameters.max_instructions]
+/// type = "int"
+/// low = 10
+/// high = 200
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchSpace {
+    pub parameters: BTreeMap<String, Distribution>,
+}
+
+/// A sampled configuration, keyed like its `SearchSpace`.
+pub type Configuration = BTreeMap<String, Value>;
+
+fn collect(prefix: &str, value: Value, space: &mut SearchSpace) -> LgpResult<()> {
+    match value {
+        Value::Object(table) if table.contains_key("type") => {
+            let distribution: Distribution =
+                serde_json::from_value(Value::Object(table)).map_err(|error| {
+                    LgpError::Config(format!("Invalid distribution for `{}`: {}", prefix, error))
+                })?;
+
+            if let Some(allowed) = distribution.violation() {
+                return Err(LgpError::Config(format!(
+                    "Invalid distribution for `{}`: expected {}",
+                    prefix, allowed
+                )));
+            }
+
+            space.parameters.insert(prefix.to_string(), distribution);
+        }
+        Value::Object(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                collect(&key, value, space)?;
+            }
+        }
+        _ => {
+            return Err(LgpError::Config(format!(
+                "Expected a distribution table for `{}`",
+                prefix
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+impl SearchSpace {
+    /// Reads the `[space]` table of `filename`.
+    pub fn load(filename: &str) -> LgpResult<Self> {
+        let settings = Config::builder()
+            .add_source(File::with_name(filename))
+            .build()?;
+
+        let mut space = SearchSpace::default();
+        collect("", settings.get("space")?, &mut space)?;
+
+        Ok(space)
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> Configuration {
+        self.parameters
+            .iter()
+            .map(|(key, distribution)| (key.clone(), distribution.sample(rng)))
+            .collect()
+    }
+}
+
+/// `key=value` overrides applying a configuration.
+pub fn overrides(configuration: &Configuration) -> Vec<String> {
+    configuration
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => format!("{}={}", key, value),
+            other => format!("{}={}", key, other),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TuneParameters {
+    /// Number of sampled configurations.
+    pub budget: usize,
+    /// Resource (e.g. generations) a configuration is evaluated with when it is not pruned.
+    pub max_resource: usize,
+    /// Successive halving: keep the best `1 / eta` of the configurations at each rung, starting
+    /// from `max_resource / eta^k`. Plain random search when unset.
+    pub eta: Option<usize>,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TuningRecord {
+    pub params: Configuration,
+    pub resource: usize,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TuningResults {
+    pub records: Vec<TuningRecord>,
+    /// Best configuration among those evaluated with the largest resource.
+    pub best: Option<TuningRecord>,
+}
+
+/// Samples `budget` configurations and maximizes `objective(configuration, resource)` over
+/// them, pruning with successive halving when `eta` is set. NaN scores rank last.
+pub fn tune(
+    space: &SearchSpace,
+    parameters: TuneParameters,
+    mut objective: impl FnMut(&Configuration, usize) -> f64,
+) -> TuningResults {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(parameters.seed);
+    let mut candidates = (0..parameters.budget)
+        .map(|_| space.sample(&mut rng))
+        .collect_vec();
+
+    let eta = parameters.eta.unwrap_or(0).max(1);
+    let max_resource = parameters.max_resource.max(1);
+
+    // Number of rungs needed to shrink the candidates to one.
+    let mut n_rungs = 0;
+    let mut remaining = candidates.len();
+    while eta > 1 && remaining > 1 {
+        remaining = (remaining / eta).max(1);
+        n_rungs += 1;
+    }
+
+    let mut resource = (0..n_rungs).fold(max_resource, |resource, _| (resource / eta).max(1));
+    let mut records = vec![];
+
+    loop {
+        let scored = candidates
+            .into_iter()
+            .map(|params| {
+                let score = objective(&params, resource);
+                records.push(TuningRecord {
+                    params: params.clone(),
+                    resource,
+                    score,
+                });
+                (params, score)
+            })
+            .sorted_by(|(_, a), (_, b)| match (a.is_nan(), b.is_nan()) {
+                (false, false) => b.total_cmp(a),
+                (a_nan, b_nan) => a_nan.cmp(&b_nan),
+            })
+            .collect_vec();
+
+        if eta == 1 || scored.len() <= 1 || resource >= max_resource {
+            break;
+        }
+
+        let n_kept = (scored.len() / eta).max(1);
+        candidates = scored
+            .into_iter()
+            .take(n_kept)
+            .map(|(params, _)| params)
+            .collect();
+        resource = (resource * eta).min(max_resource);
+    }
+
+    let best = records
+        .iter()
+        .filter(|record| record.resource == resource && !record.score.is_nan())
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+        .cloned();
+
+    TuningResults { records, best }
+}
+
+/// Final best fitness of an evolution of `configuration` run for `n_generations`; NaN when the
+/// configuration is invalid.
+pub fn evolution_score<C>(
+    base: &HyperParameters<C>,
+    state_parameters: &C::StateParameters,
+    configuration: &Configuration,
+    n_generations: usize,
+) -> f64
+where
+    C: Core,
+{
+    let parameters = match apply_overrides(base, &overrides(configuration)) {
+        Ok(mut parameters) => {
+            parameters.n_generations = n_generations;
+            parameters
+        }
+        Err(_) => return f64::NAN,
+    };
+
+    if parameters.validate().is_err() {
+        return f64::NAN;
+    }
+
+    parameters
+        .build_engine_with(state_parameters.clone())
+        .take(n_generations)
+        .last()
+        .and_then(|population| population.first().map(C::Status::get_fitness))
+        .unwrap_or(f64::NAN)
+}
+
+/// Writes `<output_root>/<test_name>/tuning_results.json`.
+pub fn save_tuning_results(
+    results: &TuningResults,
+    output_root: &Path,
+    test_name: &str,
+) -> LgpResult<()> {
+    let path = output_root.join(test_name).join("tuning_results.json");
+    let path = create_path(path.to_str().unwrap(), true)?;
+
+    results.save(path.to_str().unwrap()).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn space() -> SearchSpace {
+        SearchSpace {
+            parameters: BTreeMap::from([
+                (
+                    "gap".to_string(),
+                    Distribution::Uniform { low: 0., high: 1. },
+                ),
+                (
+                    "learning_rate".to_string(),
+                    Distribution::LogUniform {
+                        low: 1e-4,
+                        high: 1.,
+                    },
+                ),
+                (
+                    "population_size".to_string(),
+                    Distribution::Int { low: 1, high: 20 },
+                ),
+                (
+                    "fitness_aggregation".to_string(),
+                    Distribution::Categorical {
+                        choices: vec![Value::from("mean"), Value::from("median")],
+                    },
+                ),
+            ]),
+        }
+    }
+
+    /// Optimum at gap = 0.3, learning_rate = 0.01, population_size = 12 and `median`.
+    fn objective(params: &Configuration) -> f64 {
+        let gap = params["gap"].as_f64().unwrap();
+        let learning_rate = params["learning_rate"].as_f64().unwrap();
+        let population_size = params["population_size"].as_i64().unwrap() as f64;
+        let bonus = if params["fitness_aggregation"] == "median" {
+            0.
+        } else {
+            -1.
+        };
+
+        -(gap - 0.3).powi(2)
+            - (learning_rate.log10() + 2.).powi(2)
+            - ((population_size - 12.) / 10.).powi(2)
+            + bonus
+    }
+
+    fn assert_near_optimum(best: &Configuration) {
+        assert!(objective(best) > -0.2);
+        assert!((best["gap"].as_f64().unwrap() - 0.3).abs() < 0.45);
+        assert!((best["learning_rate"].as_f64().unwrap().log10() + 2.).abs() < 0.45);
+        assert!((best["population_size"].as_i64().unwrap() - 12).abs() <= 4);
+        assert_eq!(best["fitness_aggregation"], "median");
+    }
+
+    #[test]
+    fn given_random_search_when_tuned_then_best_approaches_the_optimum() {
+        let parameters = TuneParameters {
+            budget: 500,
+            max_resource: 10,
+            eta: None,
+            seed: 1,
+        };
+
+        let results = tune(&space(), parameters, |params, resource| {
+            assert_eq!(resource, 10);
+            objective(params)
+        });
+
+        assert_eq!(results.records.len(), 500);
+        assert_near_optimum(&results.best.unwrap().params);
+    }
+
+    #[test]
+    fn given_successive_halving_when_tuned_then_only_survivors_get_the_full_resource() {
+        let parameters = TuneParameters {
+            budget: 729,
+            max_resource: 729,
+            eta: Some(3),
+            seed: 2,
+        };
+
+        // Short evaluations are noisy estimates of the full one.
+        let results = tune(&space(), parameters, |params, resource| {
+            objective(params) - 1. / resource as f64
+        });
+
+        let resources = results
+            .records
+            .iter()
+            .map(|record| record.resource)
+            .counts();
+        assert_eq!(resources[&1], 729);
+        assert_eq!(resources[&243], 3);
+        assert_eq!(resources[&729], 1);
+        assert!(results.records.len() < 729 * 2);
+
+        let best = results.best.unwrap();
+        assert_eq!(best.resource, 729);
+        assert_near_optimum(&best.params);
+    }
+
+    #[test]
+    fn given_space_file_when_loaded_then_nested_keys_are_flattened() -> LgpResult<()> {
+        let path = env::temp_dir().join("lgp_tune").join("space.toml");
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(
+            &path,
+            "[space.gap]\ntype = \"uniform\"\nlow = 0.1\nhigh = 0.9\n\n\
+             [space.program_parameters.max_instructions]\ntype = \"int\"\nlow = 10\nhigh = 200\n",
+        )?;
+
+        let space = SearchSpace::load(path.to_str().unwrap())?;
+        assert_eq!(
+            space.parameters["program_parameters.max_instructions"],
+            Distribution::Int { low: 10, high: 200 }
+        );
+        assert_eq!(
+            space.parameters["gap"],
+            Distribution::Uniform {
+                low: 0.1,
+                high: 0.9
+            }
+        );
+
+        fs::write(
+            &path,
+            "[space.gap]\ntype = \"log_uniform\"\nlow = 0.\nhigh = 1.\n",
+        )?;
+        assert!(matches!(
+            SearchSpace::load(path.to_str().unwrap()),
+            Err(LgpError::Config(_))
+        ));
+
+        Ok(())
+    }
+}
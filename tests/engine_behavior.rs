@@ -0,0 +1,270 @@
+//! Engine behaviour on `problems::test_env`'s corridor, which needs neither gym nor datasets.
+//! Run with `cargo test --features test-utils`.
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use itertools::Itertools;
+use lgp::{
+    core::{
+        adaptation::Variation,
+        characteristics::{Format, Genotype, Load, Save},
+        engines::{
+            core_engine::{CoreIter, HyperParameters, HyperParametersBuilder, StopReason},
+            fitness_engine::{Fitness, FitnessEngine},
+            freeze_engine::{Freeze, FreezeEngine},
+            generate_engine::{Generate, GenerateEngine},
+            reset_engine::{Reset, ResetEngine},
+            status_engine::{Status, StatusEngine},
+        },
+        hall_of_fame::HallOfFameConfig,
+        instruction::{Instruction, Mode, Op},
+        program::Program,
+        testing::{check_evaluated, check_ranked},
+    },
+    extensions::q_learning::{DecayMode, QConstsBuilder, QProgram},
+    problems::test_env::{
+        Corridor, CorridorEngine, CorridorQEngine, CORRIDOR_LENGTH, OPTIMAL_RETURN, RIGHT,
+    },
+};
+
+/// The run seed is shared by every thread, so all tests use the same one. Offspring are bred on
+/// worker threads, so only the first generation of a seeded run is reproducible.
+const SEED: u64 = 42;
+
+fn parameters() -> HyperParameters<CorridorEngine> {
+    HyperParametersBuilder::<CorridorEngine>::default()
+        .program_parameters(CorridorEngine::program_parameters(8))
+        .population_size(20)
+        .n_trials(1)
+        .n_generations(10)
+        .seed(Some(SEED))
+        .build()
+        .unwrap()
+}
+
+fn q_parameters() -> HyperParameters<CorridorQEngine> {
+    HyperParametersBuilder::<CorridorQEngine>::default()
+        .program_parameters(CorridorQEngine::program_parameters(8))
+        .population_size(20)
+        .n_trials(1)
+        .n_generations(5)
+        .seed(Some(SEED))
+        .build()
+        .unwrap()
+}
+
+fn fitness<T>(population: &[T]) -> Vec<f64>
+where
+    StatusEngine: Status<T>,
+{
+    population.iter().map(StatusEngine::get_fitness).collect()
+}
+
+#[test]
+fn given_corridor_when_evolving_then_generations_are_ranked_and_best_never_regresses() {
+    let hp = parameters();
+    assert!(hp.validate().is_ok());
+
+    let populations = hp.build_engine_with(()).collect_vec();
+    assert_eq!(populations.len(), hp.n_generations + 1);
+
+    for population in &populations {
+        assert_eq!(population.len(), hp.population_size);
+        check_evaluated::<CorridorEngine>(population).unwrap();
+        check_ranked::<CorridorEngine>(population, hp.objective).unwrap();
+        assert!(fitness(population)
+            .iter()
+            .all(|fitness| (0. ..=OPTIMAL_RETURN).contains(fitness)));
+    }
+
+    // Survivors are the best of their generation and the corridor is deterministic.
+    let best = populations
+        .iter()
+        .map(|population| StatusEngine::get_fitness(&population[0]))
+        .collect_vec();
+    assert!(best.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", best);
+}
+
+#[test]
+fn given_only_mutation_when_evolving_then_offspring_are_mutants_of_the_previous_generation() {
+    let mut hp = parameters();
+    hp.mutation_percent = 1.0.into();
+    hp.crossover_percent = 0.0.into();
+    hp.n_generations = 3;
+
+    let populations = hp.build_engine_with(()).collect_vec();
+
+    for (generation, pair) in populations.windows(2).enumerate() {
+        let parents = pair[0]
+            .iter()
+            .map(|program| program.id)
+            .collect::<HashSet<_>>();
+        let offspring = pair[1]
+            .iter()
+            .filter(|program| !parents.contains(&program.id))
+            .collect_vec();
+        assert!(!offspring.is_empty());
+
+        for child in offspring {
+            let provenance = child.provenance().unwrap();
+            assert_eq!(provenance.operator, Variation::Mutation);
+            assert_eq!(provenance.generation, generation);
+            assert_eq!(provenance.parents.len(), 1);
+            assert!(parents.contains(&provenance.parents[0]));
+        }
+    }
+}
+
+#[test]
+fn given_q_engine_when_evolving_then_generations_are_evaluated_and_ranked() {
+    let hp = q_parameters();
+    assert!(hp.validate().is_ok());
+
+    let populations = hp.build_engine_with(()).collect_vec();
+    assert_eq!(populations.len(), hp.n_generations + 1);
+
+    for population in &populations {
+        check_evaluated::<CorridorQEngine>(population).unwrap();
+        check_ranked::<CorridorQEngine>(population, hp.objective).unwrap();
+        assert!(fitness(population)
+            .iter()
+            .all(|fitness| (0. ..=OPTIMAL_RETURN).contains(fitness)));
+    }
+}
+
+#[test]
+fn given_per_generation_decay_when_evolving_then_every_survivor_decays_once_per_generation() {
+    let mut hp = q_parameters();
+    // Nobody is replaced, so every individual lives through every generation.
+    hp.gap = 0.0.into();
+    hp.program_parameters.consts = QConstsBuilder::default()
+        .epsilon_decay(0.1)
+        .decay_mode(DecayMode::PerGeneration)
+        .build()
+        .unwrap();
+
+    let populations = hp.build_engine_with(()).collect_vec();
+
+    for (generation, population) in populations.iter().enumerate() {
+        let expected = 0.05 * 0.9f64.powi(generation as i32);
+
+        for individual in population {
+            let epsilon = individual.q_table.q_consts().epsilon_active();
+            assert!((epsilon - expected).abs() < 1e-12, "{}", generation);
+        }
+    }
+}
+
+#[test]
+fn given_frozen_q_program_preferring_right_when_replayed_then_episodes_are_optimal_and_identical() {
+    let mut program: QProgram = GenerateEngine::generate(CorridorQEngine::program_parameters(1));
+    program.program.instructions = vec![Instruction::new(0, 0, Mode::External, Op::Add, 1.)];
+    let n_registers = program.q_table.q_values().len();
+    program.q_table.warm_start(&vec![vec![0., 1.]; n_registers]);
+    FreezeEngine::freeze(&mut program);
+    let table = program.q_table.q_values();
+
+    let episodes = (0..2)
+        .map(|_| {
+            let mut corridor: Corridor = GenerateEngine::generate(());
+            ResetEngine::reset(&mut program);
+            let score =
+                <FitnessEngine as Fitness<_, _, ()>>::eval_fitness(&mut program, &mut corridor);
+            (score, corridor.actions().to_vec())
+        })
+        .collect_vec();
+
+    assert_eq!(episodes[0], episodes[1]);
+    assert_eq!(episodes[0].0, OPTIMAL_RETURN);
+    assert_eq!(episodes[0].1, vec![RIGHT; CORRIDOR_LENGTH]);
+    assert_eq!(program.q_table.q_values(), table);
+}
+
+#[test]
+fn given_stop_flag_when_set_then_run_stops_before_the_next_generation() {
+    let flag = Arc::new(AtomicBool::new(false));
+    let mut engine = parameters()
+        .build_engine_with(())
+        .with_stop_flag(flag.clone());
+
+    assert!(engine.next().is_some());
+    assert!(engine.next().is_some());
+    flag.store(true, Ordering::Relaxed);
+
+    assert!(engine.next().is_none());
+    assert_eq!(engine.generation(), 2);
+    assert_eq!(engine.stop_reason(), Some(StopReason::Interrupted));
+}
+
+#[test]
+fn given_elapsed_wall_time_when_evolving_then_only_the_first_generation_runs() {
+    let mut hp = parameters();
+    hp.max_wall_time = Some(Duration::ZERO);
+
+    let mut engine = hp.build_engine_with(());
+    let populations = engine.by_ref().collect_vec();
+
+    assert_eq!(populations.len(), 1);
+    assert_eq!(engine.stop_reason(), Some(StopReason::WallTime));
+}
+
+#[test]
+fn given_checkpointed_population_when_resumed_then_evaluation_picks_up_where_it_left_off() {
+    let hp = parameters();
+    let mut engine = hp.build_engine_with(());
+    let checkpoint = engine.by_ref().take(3).last().unwrap();
+
+    let mut saved = vec![];
+    checkpoint.write_as(&mut saved, Format::Binary).unwrap();
+    let restored = Vec::<Program>::read_as(saved.as_slice(), Format::Binary).unwrap();
+
+    let mut resumed = CoreIter::with_population(hp.clone(), (), restored);
+    let population = resumed.next().unwrap();
+
+    // Programs whose outputs tie act at random, so the re-evaluation may rank them elsewhere.
+    assert_eq!(
+        population
+            .iter()
+            .map(|program| program.id)
+            .collect::<HashSet<_>>(),
+        checkpoint
+            .iter()
+            .map(|program| program.id)
+            .collect::<HashSet<_>>()
+    );
+    check_evaluated::<CorridorEngine>(&population).unwrap();
+    check_ranked::<CorridorEngine>(&population, hp.objective).unwrap();
+    assert_eq!(resumed.generation(), 1);
+}
+
+#[test]
+fn given_hall_of_fame_when_evolving_then_it_archives_the_best_validated_individual() {
+    let mut hp = parameters();
+    hp.hall_of_fame = Some(HallOfFameConfig {
+        capacity: 3,
+        n_candidates: 1,
+        n_validation_trials: 2,
+        validation_seed: SEED,
+    });
+
+    let mut engine = hp.build_engine_with(());
+    let best = engine
+        .by_ref()
+        .map(|population| StatusEngine::get_fitness(&population[0]))
+        .fold(f64::NEG_INFINITY, f64::max);
+    let hall_of_fame = engine.hall_of_fame().unwrap();
+
+    // Validation corridors are the same as training ones, so fitness carries over.
+    assert!(!hall_of_fame.is_empty() && hall_of_fame.len() <= 3);
+    assert_eq!(hall_of_fame.best().unwrap().fitness, best);
+    assert!(hall_of_fame
+        .entries()
+        .windows(2)
+        .all(|pair| pair[0].fitness >= pair[1].fitness));
+}
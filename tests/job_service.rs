@@ -0,0 +1,166 @@
+//! Drives Iris jobs through `JobServer`'s HTTP API end to end.
+//! Run with `cargo test --features serve`.
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use lgp::{
+    core::{
+        engines::{
+            core_engine::{HyperParameters, HyperParametersBuilder},
+            status_engine::{Status, StatusEngine},
+        },
+        instruction::InstructionGeneratorParametersBuilder,
+        program::{Program, ProgramGeneratorParametersBuilder},
+    },
+    data::dataset::Dataset,
+    problems::iris::{IrisEngine, IrisStateParameters},
+    utils::jobs::{JobCreated, JobServer, JobState, JobStatus},
+};
+use reqwest::{blocking::Client, StatusCode};
+use uuid::Uuid;
+
+fn dataset() -> Dataset {
+    Dataset::new(
+        vec![
+            vec![5.1, 3.5, 1.4, 0.2],
+            vec![4.9, 3.0, 1.4, 0.2],
+            vec![7.0, 3.2, 4.7, 1.4],
+            vec![6.4, 3.2, 4.5, 1.5],
+            vec![6.3, 3.3, 6.0, 2.5],
+            vec![5.8, 2.7, 5.1, 1.9],
+        ],
+        vec![0, 0, 1, 1, 2, 2],
+        vec![],
+        vec![],
+    )
+}
+
+fn parameters(n_generations: usize) -> HyperParameters<IrisEngine> {
+    let instruction_parameters = InstructionGeneratorParametersBuilder::default()
+        .n_actions(3)
+        .n_inputs(4)
+        .build()
+        .unwrap();
+    let program_parameters = ProgramGeneratorParametersBuilder::default()
+        .max_instructions(10)
+        .instruction_generator_parameters(instruction_parameters)
+        .build()
+        .unwrap();
+
+    HyperParametersBuilder::<IrisEngine>::default()
+        .program_parameters(program_parameters)
+        .population_size(10)
+        .n_trials(1)
+        .n_generations(n_generations)
+        .build()
+        .unwrap()
+}
+
+fn serve() -> String {
+    let server =
+        JobServer::<IrisEngine>::bind("127.0.0.1:0", IrisStateParameters::new(dataset())).unwrap();
+
+    format!("http://{}", server.spawn())
+}
+
+fn start(client: &Client, base: &str, parameters: &HyperParameters<IrisEngine>) -> Uuid {
+    let response = client
+        .post(format!("{}/jobs", base))
+        .body(serde_json::to_string(parameters).unwrap())
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    serde_json::from_str::<JobCreated>(&response.text().unwrap())
+        .unwrap()
+        .id
+}
+
+fn status(client: &Client, base: &str, id: Uuid) -> JobStatus {
+    let response = client.get(format!("{}/jobs/{}", base, id)).send().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    serde_json::from_str(&response.text().unwrap()).unwrap()
+}
+
+/// Polls the job until it stops.
+fn wait(client: &Client, base: &str, id: Uuid) -> JobStatus {
+    let deadline = Instant::now() + Duration::from_secs(60);
+
+    loop {
+        let status = status(client, base, id);
+        if status.state != JobState::Running {
+            return status;
+        }
+
+        assert!(Instant::now() < deadline, "Job never stopped: {:?}", status);
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn given_tiny_iris_job_when_posted_then_it_completes_and_serves_its_champion() {
+    let client = Client::new();
+    let base = serve();
+
+    let id = start(&client, &base, &parameters(5));
+    let status = wait(&client, &base, id);
+    assert_eq!(status.state, JobState::Completed);
+    assert_eq!(status.generation, 5);
+
+    let response = client
+        .get(format!("{}/jobs/{}/champion", base, id))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let champion: Program = serde_json::from_str(&response.text().unwrap()).unwrap();
+    assert_eq!(
+        Some(StatusEngine::get_fitness(&champion)),
+        status.best_fitness
+    );
+}
+
+#[test]
+fn given_long_job_when_deleted_then_it_stops_as_cancelled() {
+    let client = Client::new();
+    let base = serve();
+
+    let id = start(&client, &base, &parameters(1_000_000));
+    let response = client
+        .delete(format!("{}/jobs/{}", base, id))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let status = wait(&client, &base, id);
+    assert_eq!(status.state, JobState::Cancelled);
+    assert!(status.generation < 1_000_000);
+}
+
+#[test]
+fn given_invalid_or_unknown_jobs_when_requested_then_errors_are_answered() {
+    let client = Client::new();
+    let base = serve();
+
+    let mut invalid = parameters(5);
+    invalid.population_size = 0;
+    let response = client
+        .post(format!("{}/jobs", base))
+        .body(serde_json::to_string(&invalid).unwrap())
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let unknown = Uuid::new_v4();
+    for response in [
+        client.get(format!("{}/jobs/{}", base, unknown)).send(),
+        client
+            .get(format!("{}/jobs/{}/champion", base, unknown))
+            .send(),
+        client.delete(format!("{}/jobs/{}", base, unknown)).send(),
+    ] {
+        assert_eq!(response.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+}